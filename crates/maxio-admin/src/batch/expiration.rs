@@ -3,7 +3,7 @@ use maxio_common::{
     error::{MaxioError, Result},
     types::ObjectInfo,
 };
-use maxio_storage::traits::ObjectLayer;
+use maxio_storage::traits::{ListOrder, ObjectLayer};
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -41,7 +41,14 @@ pub async fn collect_expired_keys(
 
     loop {
         let page = object_layer
-            .list_objects(&config.bucket, &config.prefix, &marker, "", 1000)
+            .list_objects(
+                &config.bucket,
+                &config.prefix,
+                &marker,
+                "",
+                1000,
+                ListOrder::KeyAscending,
+            )
             .await?;
 
         keys.extend(
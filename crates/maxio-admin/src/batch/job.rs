@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use maxio_storage::traits::ChecksumReport;
 use serde::Serialize;
 
 use crate::batch::types::{JobStatus, JobType};
@@ -11,4 +12,7 @@ pub struct BatchJob {
     pub progress: u8,
     pub created_at: DateTime<Utc>,
     pub error: Option<String>,
+    /// Findings of a finished verify job.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<ChecksumReport>,
 }
@@ -2,8 +2,10 @@ pub mod expiration;
 pub mod job;
 pub mod scheduler;
 pub mod types;
+pub mod verify;
 
 pub use expiration::ExpirationJobConfig;
 pub use job::BatchJob;
 pub use scheduler::JobScheduler;
 pub use types::{JobStatus, JobType};
+pub use verify::VerifyJobConfig;
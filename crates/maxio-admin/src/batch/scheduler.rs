@@ -10,6 +10,7 @@ use crate::batch::{
     expiration::{ExpirationJobConfig, collect_expired_keys},
     job::BatchJob,
     types::{JobStatus, JobType},
+    verify::VerifyJobConfig,
 };
 
 #[derive(Clone)]
@@ -32,6 +33,7 @@ impl JobScheduler {
         &self,
         job_type: JobType,
         expiration: Option<ExpirationJobConfig>,
+        verify: Option<VerifyJobConfig>,
     ) -> Result<BatchJob> {
         if job_type == JobType::Expiration {
             expiration
@@ -43,6 +45,16 @@ impl JobScheduler {
                 })?
                 .validate()?;
         }
+        if job_type == JobType::Verify {
+            verify
+                .as_ref()
+                .ok_or_else(|| {
+                    MaxioError::InvalidArgument(
+                        "verify payload is required for verify jobs".to_string(),
+                    )
+                })?
+                .validate()?;
+        }
 
         let id = Uuid::new_v4().to_string();
         let job = BatchJob {
@@ -52,13 +64,14 @@ impl JobScheduler {
             progress: 0,
             created_at: Utc::now(),
             error: None,
+            report: None,
         };
 
         self.jobs.write().await.insert(id.clone(), job.clone());
 
         let scheduler = self.clone();
         let handle = tokio::spawn(async move {
-            scheduler.run_job(id, job_type, expiration).await;
+            scheduler.run_job(id, job_type, expiration, verify).await;
         });
         self.tasks.write().await.insert(job.id.clone(), handle);
 
@@ -71,7 +84,7 @@ impl JobScheduler {
 
     pub async fn list_jobs(&self) -> Vec<BatchJob> {
         let mut jobs: Vec<BatchJob> = self.jobs.read().await.values().cloned().collect();
-        jobs.sort_by(|left, right| right.created_at.cmp(&left.created_at));
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
         jobs
     }
 
@@ -94,13 +107,20 @@ impl JobScheduler {
         Ok(job.clone())
     }
 
-    async fn run_job(&self, id: String, job_type: JobType, expiration: Option<ExpirationJobConfig>) {
+    async fn run_job(
+        &self,
+        id: String,
+        job_type: JobType,
+        expiration: Option<ExpirationJobConfig>,
+        verify: Option<VerifyJobConfig>,
+    ) {
         self.update_status(&id, JobStatus::Running).await;
 
         let result = match job_type {
             JobType::Expiration => {
                 self.run_expiration_job(&id, expiration).await
             }
+            JobType::Verify => self.run_verify_job(&id, verify).await,
             JobType::Replication | JobType::KeyRotation => Err(MaxioError::NotImplemented(
                 "batch job type is not implemented yet".to_string(),
             )),
@@ -146,6 +166,19 @@ impl JobScheduler {
         Ok(())
     }
 
+    async fn run_verify_job(&self, id: &str, verify: Option<VerifyJobConfig>) -> Result<()> {
+        let config = verify
+            .ok_or_else(|| MaxioError::InvalidArgument("verify payload is required".to_string()))?;
+        let report = self
+            .object_layer
+            .verify_objects(&config.bucket, &config.prefix)
+            .await?;
+        if let Some(job) = self.jobs.write().await.get_mut(id) {
+            job.report = Some(report);
+        }
+        Ok(())
+    }
+
     async fn update_status(&self, id: &str, status: JobStatus) {
         if let Some(job) = self.jobs.write().await.get_mut(id) {
             job.status = status;
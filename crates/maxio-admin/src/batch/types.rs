@@ -5,6 +5,9 @@ pub enum JobType {
     Expiration,
     Replication,
     KeyRotation,
+    /// Re-reads stored objects and reports those whose data no longer
+    /// matches its checksums.
+    Verify,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
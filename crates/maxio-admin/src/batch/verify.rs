@@ -0,0 +1,106 @@
+use maxio_common::error::{MaxioError, Result};
+use serde::Deserialize;
+
+/// Scope of a checksum verification job.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyJobConfig {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+impl VerifyJobConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.bucket.is_empty() {
+            return Err(MaxioError::InvalidArgument(
+                "verify job bucket is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use maxio_storage::{single::SingleDiskObjectLayer, traits::ObjectLayer};
+
+    use super::*;
+    use crate::batch::{JobScheduler, JobStatus, JobType};
+
+    fn data_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(data_files(&path));
+            } else if path.file_name().is_some_and(|name| name == "part.1") {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    #[tokio::test]
+    async fn verify_job_flags_an_object_with_a_corrupted_data_file() {
+        let root = std::env::temp_dir().join(format!("maxio-verify-{}", uuid::Uuid::new_v4()));
+        let layer = SingleDiskObjectLayer::new(root.clone()).await.unwrap();
+        layer.make_bucket("bucket").await.unwrap();
+        for key in ["logs/intact", "logs/corrupt", "other/corrupt"] {
+            layer
+                .put_object(
+                    "bucket",
+                    key,
+                    b"object data".to_vec().into(),
+                    None,
+                    Default::default(),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        for key in ["logs/corrupt", "other/corrupt"] {
+            for path in data_files(&root.join("bucket").join(key)) {
+                std::fs::write(path, b"object dat4").unwrap();
+            }
+        }
+
+        let scheduler = JobScheduler::new(Arc::new(layer) as Arc<dyn ObjectLayer>);
+        let config = VerifyJobConfig {
+            bucket: "bucket".to_string(),
+            prefix: "logs/".to_string(),
+        };
+        let job = scheduler
+            .submit_job(JobType::Verify, None, Some(config))
+            .await
+            .unwrap();
+        let job = loop {
+            let job = scheduler.get_job(&job.id).await.unwrap();
+            if job.status != JobStatus::Pending && job.status != JobStatus::Running {
+                break job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        assert_eq!(job.status, JobStatus::Completed, "{:?}", job.error);
+        let report = job.report.unwrap();
+        assert_eq!(report.verified, 2);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].key, "logs/corrupt");
+        assert!(report.mismatches[0].reason.contains("ETag"));
+
+        assert!(
+            scheduler
+                .submit_job(JobType::Verify, None, None)
+                .await
+                .is_err()
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
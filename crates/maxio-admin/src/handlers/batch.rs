@@ -19,7 +19,7 @@ pub async fn submit_batch_job(
 ) -> Result<Json<BatchJob>, AdminApiError> {
     let job = admin
         .job_scheduler()
-        .submit_job(payload.job_type, payload.expiration)
+        .submit_job(payload.job_type, payload.expiration, payload.verify)
         .await
         .map_err(AdminApiError::from)?;
     Ok(Json(job))
@@ -15,6 +15,10 @@ pub async fn health_live() -> impl IntoResponse {
 }
 
 pub async fn health_ready(State(state): State<Arc<AdminState>>) -> impl IntoResponse {
+    let storage = state.object_layer.storage_info().await;
+    if storage.online_disks() == 0 || !state.object_layer.has_write_quorum() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
     match state.object_layer.list_buckets().await {
         Ok(_) => StatusCode::OK,
         Err(_) => StatusCode::SERVICE_UNAVAILABLE,
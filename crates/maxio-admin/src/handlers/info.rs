@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use axum::{Json, extract::State};
+use maxio_storage::traits::ListOrder;
 
 use crate::{
     AdminSys,
@@ -43,7 +44,7 @@ async fn collect_storage_info(
         let mut marker = String::new();
         loop {
             let page = object_layer
-                .list_objects(&bucket.name, "", &marker, "", 1000)
+                .list_objects(&bucket.name, "", &marker, "", 1000, ListOrder::KeyAscending)
                 .await
                 .map_err(AdminApiError::from)?;
 
@@ -64,8 +65,12 @@ async fn collect_storage_info(
         }
     }
 
+    let disks = object_layer.storage_info().await;
     Ok(StorageInfo {
         used_bytes,
-        available_bytes: 0,
+        available_bytes: disks.free_bytes(),
+        online_disks: disks.online_disks(),
+        offline_disks: disks.offline_disks(),
+        disks: disks.disks,
     })
 }
@@ -1,31 +1,79 @@
 use std::sync::Arc;
 
 use axum::{
+    Json,
     body::Body,
     extract::Request,
     extract::State,
-    http::{HeaderValue, StatusCode, header},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use serde::Serialize;
 use std::time::Instant;
 
-use crate::router::AdminState;
+use crate::{
+    metrics::{CollectedMetric, ExpositionFormat},
+    router::AdminState,
+};
 
-pub async fn prometheus_metrics(State(state): State<Arc<AdminState>>) -> impl IntoResponse {
+/// Brings the gauges that are sampled rather than updated in place up to
+/// date before a scrape or snapshot.
+fn refresh_snapshots(state: &AdminState) {
     state.system_metrics.refresh();
-    let payload = state.registry.render_prometheus();
+    if let Some(bandwidth) = &state.replication_bandwidth {
+        state
+            .replication_metrics
+            .update_snapshot(&bandwidth.report());
+    }
+    if let Some(connections) = &state.replication_connections {
+        state
+            .replication_metrics
+            .update_connections(&connections.metrics());
+    }
+    if let Some(sweeper) = &state.multipart_sweeper {
+        state
+            .multipart_metrics
+            .update_snapshot(sweeper.reclaimed_total());
+    }
+}
+
+pub async fn prometheus_metrics(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    refresh_snapshots(&state);
+    let format = ExpositionFormat::from_accept(
+        headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let payload = state.registry.render(format);
 
     let mut response = Response::new(Body::from(payload));
     *response.status_mut() = StatusCode::OK;
     response.headers_mut().insert(
         header::CONTENT_TYPE,
-        HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"),
+        HeaderValue::from_static(format.content_type()),
     );
 
     response
 }
 
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub metrics: Vec<CollectedMetric>,
+}
+
+/// The same metrics as the Prometheus endpoint, as JSON for tools that do
+/// not speak the text exposition format.
+pub async fn json_metrics(State(state): State<Arc<AdminState>>) -> Json<MetricsSnapshot> {
+    refresh_snapshots(&state);
+    Json(MetricsSnapshot {
+        metrics: state.registry.collect_all(),
+    })
+}
+
 pub async fn track_api_metrics(
     State(state): State<Arc<AdminState>>,
     request: Request,
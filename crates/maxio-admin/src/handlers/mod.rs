@@ -20,8 +20,10 @@ impl From<MaxioError> for AdminApiError {
 impl IntoResponse for AdminApiError {
     fn into_response(self) -> axum::response::Response {
         let status = match self.0 {
-            MaxioError::AccessDenied(_) | MaxioError::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
-            MaxioError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            MaxioError::AccessDenied(_)
+            | MaxioError::SignatureDoesNotMatch
+            | MaxioError::InvalidAccessKeyId(_) => StatusCode::FORBIDDEN,
+            MaxioError::InvalidArgument(_) | MaxioError::KeyTooLong(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -188,6 +188,7 @@ fn builtin_readwrite_policy() -> Policy {
             effect: Effect::Allow,
             actions: vec!["s3:*".to_string(), "admin:*".to_string()],
             resources: vec!["*".to_string()],
+            conditions: HashMap::new(),
         }],
     }
 }
@@ -202,6 +203,7 @@ fn builtin_readonly_policy() -> Policy {
             effect: Effect::Allow,
             actions: vec!["s3:Get*".to_string(), "s3:List*".to_string()],
             resources: vec!["*".to_string()],
+            conditions: HashMap::new(),
         }],
     }
 }
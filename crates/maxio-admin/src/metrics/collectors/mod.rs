@@ -1,3 +1,5 @@
 pub mod api;
+pub mod multipart;
+pub mod replication;
 pub mod storage;
 pub mod system;
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use maxio_common::error::Result;
+
+use crate::metrics::registry::{GaugeMetric, MetricsRegistry};
+
+pub struct MultipartMetrics {
+    stale_uploads_reclaimed: Arc<GaugeMetric>,
+}
+
+impl MultipartMetrics {
+    pub fn register(registry: &MetricsRegistry) -> Result<Self> {
+        Ok(Self {
+            stale_uploads_reclaimed: registry.register_gauge(
+                "multipart_stale_uploads_reclaimed",
+                "Abandoned multipart uploads removed since startup",
+                &[],
+            )?,
+        })
+    }
+
+    pub fn update_snapshot(&self, reclaimed: u64) {
+        self.stale_uploads_reclaimed
+            .set(&[], i64::try_from(reclaimed).unwrap_or(i64::MAX));
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use maxio_common::error::Result;
+use maxio_distributed::replication::{BandwidthReport, ConnectionMetrics};
+
+use crate::metrics::registry::{GaugeMetric, MetricsRegistry};
+
+/// Label value used for the node-wide figures next to the per-target ones.
+const ALL_TARGETS: &str = "all";
+
+pub struct ReplicationMetrics {
+    bandwidth_limit_bytes: Arc<GaugeMetric>,
+    throughput_bytes: Arc<GaugeMetric>,
+    connections_opened: Arc<GaugeMetric>,
+    connections_reused: Arc<GaugeMetric>,
+}
+
+impl ReplicationMetrics {
+    pub fn register(registry: &MetricsRegistry) -> Result<Self> {
+        Ok(Self {
+            bandwidth_limit_bytes: registry.register_gauge(
+                "replication_bandwidth_limit_bytes",
+                "Replication bandwidth cap in bytes per second, 0 when unthrottled",
+                &["target"],
+            )?,
+            throughput_bytes: registry.register_gauge(
+                "replication_throughput_bytes",
+                "Effective replication throughput in bytes per second",
+                &["target"],
+            )?,
+            connections_opened: registry.register_gauge(
+                "replication_connections_opened",
+                "Connections opened to replication targets since startup",
+                &[],
+            )?,
+            connections_reused: registry.register_gauge(
+                "replication_connections_reused",
+                "Replication requests sent over an already open connection since startup",
+                &[],
+            )?,
+        })
+    }
+
+    pub fn update_snapshot(&self, report: &BandwidthReport) {
+        self.bandwidth_limit_bytes.set(
+            &[ALL_TARGETS],
+            saturating_i64_from_u64(report.global_limit.unwrap_or(0)),
+        );
+        self.throughput_bytes
+            .set(&[ALL_TARGETS], report.bytes_per_second as i64);
+
+        for target in &report.targets {
+            self.bandwidth_limit_bytes.set(
+                &[&target.arn],
+                saturating_i64_from_u64(target.limit.unwrap_or(0)),
+            );
+            self.throughput_bytes
+                .set(&[&target.arn], target.bytes_per_second as i64);
+        }
+    }
+
+    pub fn update_connections(&self, connections: &ConnectionMetrics) {
+        self.connections_opened
+            .set(&[], saturating_i64_from_u64(connections.connections_opened));
+        self.connections_reused
+            .set(&[], saturating_i64_from_u64(connections.connections_reused));
+    }
+}
+
+fn saturating_i64_from_u64(value: u64) -> i64 {
+    if value > i64::MAX as u64 {
+        i64::MAX
+    } else {
+        value as i64
+    }
+}
@@ -2,6 +2,11 @@ pub mod collectors;
 pub mod registry;
 pub mod types;
 
-pub use collectors::{api::ApiMetrics, storage::StorageMetrics, system::SystemMetrics};
+pub use collectors::{
+    api::ApiMetrics, multipart::MultipartMetrics, replication::ReplicationMetrics,
+    storage::StorageMetrics, system::SystemMetrics,
+};
 pub use registry::{CounterMetric, GaugeMetric, HistogramMetric, MetricsRegistry};
-pub use types::{MetricDescriptor, MetricType, MetricValue};
+pub use types::{
+    CollectedMetric, ExpositionFormat, MetricDescriptor, MetricSample, MetricType, MetricValue,
+};
@@ -9,7 +9,9 @@ use std::{
 
 use maxio_common::error::{MaxioError, Result};
 
-use crate::metrics::types::{CollectedMetric, MetricDescriptor, MetricSample, MetricType, MetricValue};
+use crate::metrics::types::{
+    CollectedMetric, ExpositionFormat, MetricDescriptor, MetricSample, MetricType, MetricValue,
+};
 
 type LabelValues = Vec<String>;
 
@@ -20,15 +22,25 @@ trait RegisteredMetric: Send + Sync {
 
 pub struct MetricsRegistry {
     metrics: RwLock<HashMap<String, Arc<dyn RegisteredMetric>>>,
+    timestamps: bool,
 }
 
 impl MetricsRegistry {
     pub fn new() -> Self {
         Self {
             metrics: RwLock::new(HashMap::new()),
+            timestamps: false,
         }
     }
 
+    /// Appends the render time to every sample. Off by default: Prometheus
+    /// assigns scrape-time timestamps itself, and explicit ones defeat its
+    /// staleness handling.
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
     pub fn register_counter(
         &self,
         name: &str,
@@ -63,6 +75,15 @@ impl MetricsRegistry {
         Ok(metric)
     }
 
+    /// Removes a metric so the name can be registered again. Returns whether
+    /// a metric with that name existed.
+    pub fn unregister(&self, name: &str) -> bool {
+        match self.metrics.write() {
+            Ok(mut metrics) => metrics.remove(name).is_some(),
+            Err(_) => false,
+        }
+    }
+
     pub fn collect_all(&self) -> Vec<CollectedMetric> {
         let metrics = match self.metrics.read() {
             Ok(guard) => guard,
@@ -82,32 +103,78 @@ impl MetricsRegistry {
     }
 
     pub fn render_prometheus(&self) -> String {
-        let timestamp = SystemTime::now()
+        self.render(ExpositionFormat::Prometheus)
+    }
+
+    pub fn render_openmetrics(&self) -> String {
+        self.render(ExpositionFormat::OpenMetrics)
+    }
+
+    pub fn render(&self, format: ExpositionFormat) -> String {
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .ok()
-            .map(|duration| duration.as_millis());
+            .filter(|_| self.timestamps);
+        // Prometheus text uses milliseconds, OpenMetrics uses seconds.
+        let timestamp = now.map(|duration| match format {
+            ExpositionFormat::Prometheus => duration.as_millis().to_string(),
+            ExpositionFormat::OpenMetrics => format!("{:.3}", duration.as_secs_f64()),
+        });
+        let timestamp = timestamp.as_deref();
 
         let metrics = self.collect_all();
         let mut output = String::new();
 
         for metric in metrics {
+            let family = match (format, metric.descriptor.metric_type) {
+                (ExpositionFormat::OpenMetrics, MetricType::Counter) => metric
+                    .descriptor
+                    .name
+                    .strip_suffix("_total")
+                    .unwrap_or(&metric.descriptor.name)
+                    .to_string(),
+                _ => metric.descriptor.name.clone(),
+            };
+
             output.push_str("# HELP ");
-            output.push_str(&metric.descriptor.name);
+            output.push_str(&family);
             output.push(' ');
             output.push_str(&escape_help(&metric.descriptor.help));
             output.push('\n');
 
             output.push_str("# TYPE ");
-            output.push_str(&metric.descriptor.name);
+            output.push_str(&family);
             output.push(' ');
             output.push_str(metric.descriptor.metric_type.as_prometheus_type());
             output.push('\n');
 
+            if format == ExpositionFormat::OpenMetrics
+                && let Some(unit) = metric_unit(&family)
+            {
+                output.push_str("# UNIT ");
+                output.push_str(&family);
+                output.push(' ');
+                output.push_str(unit);
+                output.push('\n');
+            }
+
             for sample in metric.samples {
                 match sample.value {
-                    MetricValue::Counter(value) | MetricValue::Gauge(value) => {
+                    MetricValue::Counter(value) => {
+                        let name = match format {
+                            ExpositionFormat::Prometheus => family.clone(),
+                            ExpositionFormat::OpenMetrics => format!("{family}_total"),
+                        };
+                        output.push_str(&render_sample_line(
+                            &name,
+                            &sample.labels,
+                            value,
+                            timestamp,
+                        ));
+                    }
+                    MetricValue::Gauge(value) => {
                         output.push_str(&render_sample_line(
-                            &metric.descriptor.name,
+                            &family,
                             &sample.labels,
                             value,
                             timestamp,
@@ -124,7 +191,7 @@ impl MetricsRegistry {
                             let mut labels = sample.labels.clone();
                             labels.push(("le".to_string(), format_bucket_bound(bound)));
                             output.push_str(&render_sample_line(
-                                &format!("{}_bucket", metric.descriptor.name),
+                                &format!("{family}_bucket"),
                                 &labels,
                                 cumulative as f64,
                                 timestamp,
@@ -132,13 +199,13 @@ impl MetricsRegistry {
                         }
 
                         output.push_str(&render_sample_line(
-                            &format!("{}_sum", metric.descriptor.name),
+                            &format!("{family}_sum"),
                             &sample.labels,
                             sum,
                             timestamp,
                         ));
                         output.push_str(&render_sample_line(
-                            &format!("{}_count", metric.descriptor.name),
+                            &format!("{family}_count"),
                             &sample.labels,
                             count as f64,
                             timestamp,
@@ -148,6 +215,10 @@ impl MetricsRegistry {
             }
         }
 
+        if format == ExpositionFormat::OpenMetrics {
+            output.push_str("# EOF\n");
+        }
+
         output
     }
 
@@ -202,6 +273,16 @@ impl CounterMetric {
         self.inc(labels, 1);
     }
 
+    /// Drops every label series.
+    pub fn reset(&self) {
+        clear_series(&self.series);
+    }
+
+    /// Drops a single label series, e.g. for a deleted bucket.
+    pub fn remove_series(&self, labels: &[&str]) -> bool {
+        remove_series(&self.descriptor, &self.series, labels)
+    }
+
     fn get_or_create_series(&self, labels: &[&str]) -> Arc<AtomicU64> {
         let label_values = normalize_labels(&self.descriptor, labels);
         if let Ok(guard) = self.series.read()
@@ -273,6 +354,16 @@ impl GaugeMetric {
         self.inc(labels, -value);
     }
 
+    /// Drops every label series.
+    pub fn reset(&self) {
+        clear_series(&self.series);
+    }
+
+    /// Drops a single label series, e.g. for a deleted bucket.
+    pub fn remove_series(&self, labels: &[&str]) -> bool {
+        remove_series(&self.descriptor, &self.series, labels)
+    }
+
     fn get_or_create_series(&self, labels: &[&str]) -> Arc<AtomicI64> {
         let label_values = normalize_labels(&self.descriptor, labels);
         if let Ok(guard) = self.series.read()
@@ -343,12 +434,7 @@ impl HistogramMetric {
 
     pub fn observe(&self, labels: &[&str], value: f64) {
         let series = self.get_or_create_series(labels);
-
-        let bucket_index = self
-            .buckets
-            .iter()
-            .position(|bucket| value <= *bucket)
-            .unwrap_or(self.buckets.len());
+        let bucket_index = self.bucket_index(value);
 
         if let Some(bucket) = series.bucket_counts.get(bucket_index) {
             bucket.fetch_add(1, Ordering::Relaxed);
@@ -360,6 +446,26 @@ impl HistogramMetric {
         }
     }
 
+    /// Drops every label series.
+    pub fn reset(&self) {
+        clear_series(&self.series);
+    }
+
+    /// Drops a single label series, e.g. for a deleted bucket.
+    pub fn remove_series(&self, labels: &[&str]) -> bool {
+        remove_series(&self.descriptor, &self.series, labels)
+    }
+
+    /// Index of the first bucket whose upper bound is `>= value`, or the
+    /// `+Inf` slot. `buckets` is sorted at construction, so this is a binary
+    /// search; NaN never satisfies a bound and lands in `+Inf`.
+    fn bucket_index(&self, value: f64) -> usize {
+        if value.is_nan() {
+            return self.buckets.len();
+        }
+        self.buckets.partition_point(|bucket| *bucket < value)
+    }
+
     fn get_or_create_series(&self, labels: &[&str]) -> Arc<HistogramSeries> {
         let label_values = normalize_labels(&self.descriptor, labels);
         if let Ok(guard) = self.series.read()
@@ -443,6 +549,24 @@ fn normalize_labels(descriptor: &MetricDescriptor, labels: &[&str]) -> LabelValu
         .collect()
 }
 
+fn clear_series<T>(series: &RwLock<HashMap<LabelValues, T>>) {
+    if let Ok(mut guard) = series.write() {
+        guard.clear();
+    }
+}
+
+fn remove_series<T>(
+    descriptor: &MetricDescriptor,
+    series: &RwLock<HashMap<LabelValues, T>>,
+    labels: &[&str],
+) -> bool {
+    let label_values = normalize_labels(descriptor, labels);
+    match series.write() {
+        Ok(mut guard) => guard.remove(&label_values).is_some(),
+        Err(_) => false,
+    }
+}
+
 fn materialize_labels(descriptor: &MetricDescriptor, values: &[String]) -> Vec<(String, String)> {
     descriptor
         .variable_labels
@@ -456,7 +580,7 @@ fn render_sample_line(
     name: &str,
     labels: &[(String, String)],
     value: f64,
-    timestamp: Option<u128>,
+    timestamp: Option<&str>,
 ) -> String {
     let mut rendered = String::new();
     rendered.push_str(name);
@@ -480,13 +604,20 @@ fn render_sample_line(
 
     if let Some(ts) = timestamp {
         rendered.push(' ');
-        rendered.push_str(&ts.to_string());
+        rendered.push_str(ts);
     }
 
     rendered.push('\n');
     rendered
 }
 
+/// OpenMetrics unit for a metric family, inferred from its name suffix.
+fn metric_unit(family: &str) -> Option<&'static str> {
+    ["seconds", "bytes", "ratio"]
+        .into_iter()
+        .find(|unit| family.ends_with(&format!("_{unit}")))
+}
+
 fn format_metric_value(value: f64) -> String {
     if value.fract() == 0.0 {
         format!("{value:.0}")
@@ -516,3 +647,188 @@ fn escape_label_value(value: &str) -> String {
         .replace('\n', "\\n")
         .replace('"', "\\\"")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_bucket_index_matches_linear_scan() {
+        let histogram = HistogramMetric::new(
+            "test_latency_seconds",
+            "test",
+            &[],
+            &[
+                0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+            ],
+        );
+        let linear = |value: f64| {
+            histogram
+                .buckets
+                .iter()
+                .position(|bucket| value <= *bucket)
+                .unwrap_or(histogram.buckets.len())
+        };
+
+        let mut values = vec![f64::NEG_INFINITY, -1.0, 0.0, 20.0, f64::INFINITY, f64::NAN];
+        for bound in &histogram.buckets {
+            values.extend([*bound, bound - 1e-9, bound + 1e-9]);
+        }
+        values.extend((0..1200).map(|step| f64::from(step) * 0.01));
+
+        for value in values {
+            assert_eq!(
+                histogram.bucket_index(value),
+                linear(value),
+                "bucket mismatch for {value}"
+            );
+        }
+    }
+
+    fn series_count(registry: &MetricsRegistry, name: &str) -> usize {
+        registry
+            .collect_all()
+            .into_iter()
+            .find(|metric| metric.descriptor.name == name)
+            .map(|metric| metric.samples.len())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn reset_remove_and_unregister_clear_series() {
+        let registry = MetricsRegistry::new();
+        let counter = registry
+            .register_counter("test_requests_total", "test", &["bucket"])
+            .expect("register counter");
+        let gauge = registry
+            .register_gauge("test_objects", "test", &["bucket"])
+            .expect("register gauge");
+        let histogram = registry
+            .register_histogram("test_latency_seconds", "test", &["bucket"], &[0.1, 1.0])
+            .expect("register histogram");
+
+        for bucket in ["alpha", "beta"] {
+            counter.inc_one(&[bucket]);
+            gauge.set(&[bucket], 3);
+            histogram.observe(&[bucket], 0.5);
+        }
+        assert_eq!(series_count(&registry, "test_requests_total"), 2);
+        assert_eq!(series_count(&registry, "test_objects"), 2);
+        assert_eq!(series_count(&registry, "test_latency_seconds"), 2);
+
+        assert!(counter.remove_series(&["alpha"]));
+        assert!(!counter.remove_series(&["alpha"]));
+        assert!(gauge.remove_series(&["beta"]));
+        assert_eq!(series_count(&registry, "test_requests_total"), 1);
+        assert_eq!(series_count(&registry, "test_objects"), 1);
+
+        counter.reset();
+        gauge.reset();
+        histogram.reset();
+        assert_eq!(series_count(&registry, "test_requests_total"), 0);
+        assert_eq!(series_count(&registry, "test_objects"), 0);
+        assert_eq!(series_count(&registry, "test_latency_seconds"), 0);
+
+        assert!(registry.unregister("test_requests_total"));
+        assert!(!registry.unregister("test_requests_total"));
+        assert!(
+            registry
+                .collect_all()
+                .iter()
+                .all(|metric| metric.descriptor.name != "test_requests_total")
+        );
+        registry
+            .register_counter("test_requests_total", "test", &["bucket"])
+            .expect("re-register after unregister");
+    }
+
+    #[test]
+    fn openmetrics_output_suffixes_counters_and_ends_with_eof() {
+        let registry = MetricsRegistry::new();
+        registry
+            .register_counter("test_requests", "test", &["method"])
+            .expect("register counter")
+            .inc_one(&["GET"]);
+        registry
+            .register_counter("test_errors_total", "test", &[])
+            .expect("register counter")
+            .inc_one(&[]);
+        registry
+            .register_histogram("test_duration_seconds", "test", &[], &[1.0])
+            .expect("register histogram")
+            .observe(&[], 0.5);
+
+        let output = registry.render_openmetrics();
+        assert!(output.ends_with("# EOF\n"));
+        assert!(output.contains("# TYPE test_requests counter\n"));
+        assert!(output.contains("test_requests_total{method=\"GET\"} 1\n"));
+        assert!(output.contains("# TYPE test_errors counter\n"));
+        assert!(output.contains("\ntest_errors_total 1\n"));
+        assert!(!output.contains("test_errors_total_total"));
+        assert!(output.contains("# UNIT test_duration_seconds seconds\n"));
+
+        let legacy = registry.render_prometheus();
+        assert!(!legacy.contains("# EOF"));
+        assert!(legacy.contains("\ntest_requests{method=\"GET\"} 1\n"));
+    }
+
+    #[test]
+    fn timestamps_are_opt_in() {
+        let registry = MetricsRegistry::new();
+        registry
+            .register_gauge("test_objects", "test", &[])
+            .expect("register gauge")
+            .set(&[], 7);
+        assert!(registry.render_prometheus().contains("\ntest_objects 7\n"));
+
+        let registry = MetricsRegistry::new().with_timestamps(true);
+        registry
+            .register_gauge("test_objects", "test", &[])
+            .expect("register gauge")
+            .set(&[], 7);
+        let line = registry
+            .render_prometheus()
+            .lines()
+            .find(|line| line.starts_with("test_objects "))
+            .expect("sample line")
+            .to_string();
+        let fields = line.split(' ').collect::<Vec<_>>();
+        assert_eq!(fields.len(), 3);
+        assert!(fields[2].parse::<u128>().is_ok());
+    }
+
+    #[test]
+    fn collected_metrics_serialize_to_json() {
+        let registry = MetricsRegistry::new();
+        let api = crate::metrics::ApiMetrics::register(&registry).expect("register api metrics");
+        for _ in 0..3 {
+            api.record_request("GET", 200, std::time::Duration::from_millis(20));
+        }
+
+        let snapshot = serde_json::to_value(registry.collect_all()).expect("serialize metrics");
+        let requests = snapshot
+            .as_array()
+            .expect("metric list")
+            .iter()
+            .find(|metric| metric["name"] == "requests_total")
+            .expect("requests_total metric");
+        assert_eq!(requests["type"], "counter");
+        assert_eq!(
+            requests["variable_labels"],
+            serde_json::json!(["method", "status"])
+        );
+        assert_eq!(
+            requests["samples"],
+            serde_json::json!([{"labels": {"method": "GET", "status": "200"}, "value": 3.0}])
+        );
+
+        let latency = snapshot
+            .as_array()
+            .expect("metric list")
+            .iter()
+            .find(|metric| metric["name"] == "request_duration_seconds")
+            .expect("latency metric");
+        assert_eq!(latency["type"], "histogram");
+        assert_eq!(latency["samples"][0]["value"]["count"], 3);
+    }
+}
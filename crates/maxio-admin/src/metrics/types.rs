@@ -1,4 +1,7 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MetricType {
     Counter,
     Gauge,
@@ -15,15 +18,19 @@ impl MetricType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MetricDescriptor {
     pub name: String,
     pub help: String,
+    #[serde(rename = "type")]
     pub metric_type: MetricType,
     pub variable_labels: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+/// Serialized as a bare number for counters and gauges, and as an object
+/// with `[upper_bound, cumulative_count]` buckets for histograms.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum MetricValue {
     Counter(f64),
     Gauge(f64),
@@ -34,14 +41,52 @@ pub enum MetricValue {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MetricSample {
+    #[serde(serialize_with = "serialize_labels")]
     pub labels: Vec<(String, String)>,
     pub value: MetricValue,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CollectedMetric {
+    #[serde(flatten)]
     pub descriptor: MetricDescriptor,
     pub samples: Vec<MetricSample>,
 }
+
+fn serialize_labels<S: Serializer>(
+    labels: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(labels.len()))?;
+    for (name, value) in labels {
+        map.serialize_entry(name, value)?;
+    }
+    map.end()
+}
+
+/// Text exposition format served by the metrics endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpositionFormat {
+    #[default]
+    Prometheus,
+    OpenMetrics,
+}
+
+impl ExpositionFormat {
+    /// Picks OpenMetrics only when the scraper asks for it explicitly.
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        match accept {
+            Some(value) if value.contains("application/openmetrics-text") => Self::OpenMetrics,
+            _ => Self::Prometheus,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            Self::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+}
@@ -50,7 +50,7 @@ pub async fn admin_auth(admin: axum::extract::State<std::sync::Arc<AdminSys>>, r
 
     let provider = admin.credentials();
     let Some(credentials) = provider.lookup(&parsed.access_key) else {
-        return json_error(MaxioError::AccessDenied("access key not found".to_string()));
+        return json_error(MaxioError::InvalidAccessKeyId(parsed.access_key));
     };
 
     let date_time = req
@@ -122,8 +122,10 @@ fn derive_admin_action(method: &str, path: &str) -> String {
 
 fn json_error(error: MaxioError) -> Response {
     let status = match error {
-        MaxioError::AccessDenied(_) | MaxioError::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
-        MaxioError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        MaxioError::AccessDenied(_)
+        | MaxioError::SignatureDoesNotMatch
+        | MaxioError::InvalidAccessKeyId(_) => StatusCode::FORBIDDEN,
+        MaxioError::InvalidArgument(_) | MaxioError::KeyTooLong(_) => StatusCode::BAD_REQUEST,
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
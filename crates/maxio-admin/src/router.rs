@@ -2,12 +2,18 @@ use std::sync::Arc;
 
 use axum::{middleware, routing::get, Router};
 use maxio_common::error::Result;
-use maxio_distributed::DistributedSys;
-use maxio_storage::traits::ObjectLayer;
+use maxio_distributed::{
+    DistributedSys,
+    replication::{BandwidthLimiter, ConnectionStats},
+};
+use maxio_storage::{multipart_sweep::MultipartSweeper, traits::ObjectLayer};
 
 use crate::{
     handlers,
-    metrics::{ApiMetrics, MetricsRegistry, StorageMetrics, SystemMetrics},
+    metrics::{
+        ApiMetrics, MetricsRegistry, MultipartMetrics, ReplicationMetrics, StorageMetrics,
+        SystemMetrics,
+    },
     middleware::admin_auth,
     AdminSys,
 };
@@ -19,6 +25,14 @@ pub struct AdminState {
     pub api_metrics: Arc<ApiMetrics>,
     pub storage_metrics: Arc<StorageMetrics>,
     pub system_metrics: Arc<SystemMetrics>,
+    pub replication_metrics: Arc<ReplicationMetrics>,
+    pub multipart_metrics: Arc<MultipartMetrics>,
+    /// Limiter of the node's replication pool, reported on every scrape.
+    pub replication_bandwidth: Option<Arc<BandwidthLimiter>>,
+    /// Connection counts of the node's replication client.
+    pub replication_connections: Option<Arc<ConnectionStats>>,
+    /// Stale multipart upload sweeper, reported on every scrape.
+    pub multipart_sweeper: Option<Arc<MultipartSweeper>>,
 }
 
 impl AdminState {
@@ -26,10 +40,15 @@ impl AdminState {
         object_layer: Arc<dyn ObjectLayer>,
         distributed: Arc<DistributedSys>,
     ) -> Result<Self> {
-        let registry = Arc::new(MetricsRegistry::new());
+        let timestamps = std::env::var("MAXIO_METRICS_TIMESTAMPS")
+            .map(|value| matches!(value.trim(), "1" | "true" | "on"))
+            .unwrap_or(false);
+        let registry = Arc::new(MetricsRegistry::new().with_timestamps(timestamps));
         let api_metrics = Arc::new(ApiMetrics::register(registry.as_ref())?);
         let storage_metrics = Arc::new(StorageMetrics::register(registry.as_ref())?);
         let system_metrics = Arc::new(SystemMetrics::register(registry.as_ref())?);
+        let replication_metrics = Arc::new(ReplicationMetrics::register(registry.as_ref())?);
+        let multipart_metrics = Arc::new(MultipartMetrics::register(registry.as_ref())?);
 
         Ok(Self {
             object_layer,
@@ -38,8 +57,28 @@ impl AdminState {
             api_metrics,
             storage_metrics,
             system_metrics,
+            replication_metrics,
+            multipart_metrics,
+            replication_bandwidth: None,
+            replication_connections: None,
+            multipart_sweeper: None,
         })
     }
+
+    pub fn with_replication_bandwidth(mut self, bandwidth: Arc<BandwidthLimiter>) -> Self {
+        self.replication_bandwidth = Some(bandwidth);
+        self
+    }
+
+    pub fn with_replication_connections(mut self, connections: Arc<ConnectionStats>) -> Self {
+        self.replication_connections = Some(connections);
+        self
+    }
+
+    pub fn with_multipart_sweeper(mut self, sweeper: Arc<MultipartSweeper>) -> Self {
+        self.multipart_sweeper = Some(sweeper);
+        self
+    }
 }
 
 pub fn admin_router(state: Arc<AdminState>) -> Router {
@@ -54,6 +93,10 @@ pub fn admin_router(state: Arc<AdminState>) -> Router {
             "/minio/prometheus/metrics",
             get(handlers::metrics::prometheus_metrics),
         )
+        .route(
+            "/minio/admin/v3/metrics",
+            get(handlers::metrics::json_metrics),
+        )
         .route_layer(middleware::from_fn_with_state(
             Arc::clone(&state),
             handlers::metrics::track_api_metrics,
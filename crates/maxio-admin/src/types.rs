@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
+use maxio_storage::storage_info::DiskInfo;
 use serde::{Deserialize, Serialize};
 
-use crate::batch::{ExpirationJobConfig, JobType};
+use crate::batch::{ExpirationJobConfig, JobType, VerifyJobConfig};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct AdminInfo {
@@ -25,6 +26,9 @@ pub struct ServerProperties {
 pub struct StorageInfo {
     pub used_bytes: u64,
     pub available_bytes: u64,
+    pub online_disks: usize,
+    pub offline_disks: usize,
+    pub disks: Vec<DiskInfo>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -96,4 +100,5 @@ pub struct PolicyPutRequest {
 pub struct BatchJobSubmitRequest {
     pub job_type: JobType,
     pub expiration: Option<ExpirationJobConfig>,
+    pub verify: Option<VerifyJobConfig>,
 }
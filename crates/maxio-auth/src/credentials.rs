@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use maxio_iam::IAMSys;
 
@@ -22,6 +22,19 @@ pub trait CredentialProvider: Send + Sync {
                 true
             })
     }
+
+    /// Authorizes with request condition keys (`s3:prefix`, ...). Providers
+    /// without policy conditions fall back to `is_allowed`.
+    fn is_allowed_with_context(
+        &self,
+        access_key: &str,
+        action: &str,
+        resource: &str,
+        context: &HashMap<String, String>,
+    ) -> bool {
+        let _ = context;
+        self.is_allowed(access_key, action, resource)
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -91,13 +104,23 @@ impl CredentialProvider for StaticCredentialProvider {
     }
 
     fn is_allowed(&self, access_key: &str, action: &str, resource: &str) -> bool {
+        self.is_allowed_with_context(access_key, action, resource, &HashMap::new())
+    }
+
+    fn is_allowed_with_context(
+        &self,
+        access_key: &str,
+        action: &str,
+        resource: &str,
+        context: &HashMap<String, String>,
+    ) -> bool {
         if self.is_root_access_key(access_key) {
             return true;
         }
 
-        self.iam
-            .as_ref()
-            .is_some_and(|iam| iam.check_permission(access_key, action, resource))
+        self.iam.as_ref().is_some_and(|iam| {
+            iam.check_permission_with_context(access_key, action, resource, context)
+        })
     }
 }
 
@@ -113,4 +136,15 @@ impl CredentialProvider for Arc<dyn CredentialProvider> {
     fn is_allowed(&self, access_key: &str, action: &str, resource: &str) -> bool {
         self.as_ref().is_allowed(access_key, action, resource)
     }
+
+    fn is_allowed_with_context(
+        &self,
+        access_key: &str,
+        action: &str,
+        resource: &str,
+        context: &HashMap<String, String>,
+    ) -> bool {
+        self.as_ref()
+            .is_allowed_with_context(access_key, action, resource, context)
+    }
 }
@@ -1,4 +1,4 @@
-use std::{future::Future, pin::Pin, sync::Arc, task::Poll};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, task::Poll};
 
 use axum::{
     body::Body,
@@ -9,6 +9,7 @@ use http::{
     header::{AUTHORIZATION, HeaderName},
 };
 use maxio_common::error::MaxioError;
+use percent_encoding::percent_decode_str;
 use tower::{Layer, Service};
 use tracing::debug;
 
@@ -16,14 +17,86 @@ use crate::{
     credentials::CredentialProvider, parser::parse_auth_header, signature_v4::verify_signature,
 };
 
+/// The signed-in caller of a request. [`AuthMiddleware`] adds it to the
+/// request extensions once the request is authorized, so handlers can make
+/// further checks, such as which buckets to list.
+#[derive(Clone)]
+pub struct Caller {
+    access_key: String,
+    provider: Arc<dyn CredentialProvider>,
+}
+
+impl Caller {
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+
+    pub fn is_allowed(&self, action: &str, resource: &str) -> bool {
+        self.provider.is_allowed(&self.access_key, action, resource)
+    }
+}
+
+/// Regions a request may be signed for. S3 rejects a credential scope
+/// naming any region but the bucket's; like MinIO, the server accepts any by
+/// default, since clients that do not know the region tend to sign for
+/// `us-east-1`. The signature itself is checked either way.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SigningRegions {
+    #[default]
+    Any,
+    Only(Vec<String>),
+}
+
+impl SigningRegions {
+    /// Reads `MAXIO_API_SIGNING_REGIONS`, a comma-separated list of regions
+    /// accepted besides `server_region`. Unset, blank or `*` accepts any
+    /// region.
+    pub fn from_env(server_region: &str) -> Self {
+        let value = std::env::var("MAXIO_API_SIGNING_REGIONS").unwrap_or_default();
+        Self::parse(&value, server_region)
+    }
+
+    pub fn parse(value: &str, server_region: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() || value == "*" {
+            return Self::Any;
+        }
+        let mut regions = vec![server_region.to_string()];
+        for region in value.split(',').map(str::trim) {
+            if !region.is_empty() && !regions.iter().any(|known| known == region) {
+                regions.push(region.to_string());
+            }
+        }
+        Self::Only(regions)
+    }
+
+    pub fn allows(&self, region: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Only(regions) => regions.iter().any(|allowed| allowed == region),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthLayer {
     provider: Arc<dyn CredentialProvider>,
+    regions: Arc<SigningRegions>,
 }
 
 impl AuthLayer {
     pub fn new(provider: Arc<dyn CredentialProvider>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            regions: Arc::default(),
+        }
+    }
+
+    /// Limits the regions requests may be signed for; any is accepted
+    /// otherwise.
+    pub fn with_signing_regions(mut self, regions: SigningRegions) -> Self {
+        self.regions = Arc::new(regions);
+        self
     }
 }
 
@@ -34,6 +107,7 @@ impl<S> Layer<S> for AuthLayer {
         AuthMiddleware {
             inner,
             provider: Arc::clone(&self.provider),
+            regions: Arc::clone(&self.regions),
         }
     }
 }
@@ -42,6 +116,7 @@ impl<S> Layer<S> for AuthLayer {
 pub struct AuthMiddleware<S> {
     inner: S,
     provider: Arc<dyn CredentialProvider>,
+    regions: Arc<SigningRegions>,
 }
 
 impl<S, ReqBody> Service<Request<ReqBody>> for AuthMiddleware<S>
@@ -59,9 +134,10 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
         let mut inner = self.inner.clone();
         let provider = Arc::clone(&self.provider);
+        let regions = Arc::clone(&self.regions);
 
         Box::pin(async move {
             let auth_header = req
@@ -95,6 +171,12 @@ where
                 )));
             }
 
+            if !regions.allows(&parsed.region) {
+                return Ok(s3_error_response(MaxioError::AuthorizationHeaderMalformed(
+                    format!("region {} is not accepted by this server", parsed.region),
+                )));
+            }
+
             if !parsed.signed_headers.iter().any(|h| h == "host") {
                 return Ok(s3_error_response(MaxioError::AccessDenied(
                     "host must be part of signed headers".to_string(),
@@ -102,8 +184,8 @@ where
             }
 
             let Some(credentials) = provider.lookup(&parsed.access_key) else {
-                return Ok(s3_error_response(MaxioError::AccessDenied(
-                    "access key not found".to_string(),
+                return Ok(s3_error_response(MaxioError::InvalidAccessKeyId(
+                    parsed.access_key,
                 )));
             };
 
@@ -158,12 +240,17 @@ where
 
             let (action, resource) =
                 derive_action_resource(req.method().as_str(), req.uri().path());
-            if !provider.is_allowed(&parsed.access_key, &action, &resource) {
+            let context = condition_context(&action, req.uri().query());
+            if !provider.is_allowed_with_context(&parsed.access_key, &action, &resource, &context) {
                 return Ok(s3_error_response(MaxioError::AccessDenied(
                     "iam policy denied this operation".to_string(),
                 )));
             }
 
+            req.extensions_mut().insert(Caller {
+                access_key: parsed.access_key,
+                provider,
+            });
             inner.call(req).await
         })
     }
@@ -210,10 +297,38 @@ fn derive_action_resource(method: &str, path: &str) -> (String, String) {
     (action.to_string(), resource)
 }
 
+/// Request values exposed to policy conditions. Only ListBucket carries
+/// condition keys today: `s3:prefix` and `s3:delimiter`, present only when the
+/// request sets them.
+fn condition_context(action: &str, query: Option<&str>) -> HashMap<String, String> {
+    let mut context = HashMap::new();
+    if action != "s3:ListBucket" {
+        return context;
+    }
+
+    for pair in query.unwrap_or_default().split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = match name {
+            "prefix" => "s3:prefix",
+            "delimiter" => "s3:delimiter",
+            _ => continue,
+        };
+        let value = value.replace('+', " ");
+        let value = percent_decode_str(&value).decode_utf8_lossy().into_owned();
+        context.insert(key.to_string(), value);
+    }
+
+    context
+}
+
 fn s3_error_response(error: MaxioError) -> Response {
     let status = match error {
-        MaxioError::AccessDenied(_) | MaxioError::SignatureDoesNotMatch => StatusCode::FORBIDDEN,
-        MaxioError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        MaxioError::AccessDenied(_)
+        | MaxioError::SignatureDoesNotMatch
+        | MaxioError::InvalidAccessKeyId(_) => StatusCode::FORBIDDEN,
+        MaxioError::InvalidArgument(_)
+        | MaxioError::KeyTooLong(_)
+        | MaxioError::AuthorizationHeaderMalformed(_) => StatusCode::BAD_REQUEST,
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     };
 
@@ -236,3 +351,157 @@ fn s3_error_response(error: MaxioError) -> Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+    use crate::credentials::StaticCredentialProvider;
+
+    async fn call_with_access_key(access_key: &str) -> (StatusCode, String) {
+        let provider: Arc<dyn CredentialProvider> =
+            Arc::new(StaticCredentialProvider::new("known-key", "secret"));
+        let service = AuthLayer::new(provider).layer(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(StatusCode::OK.into_response())
+        }));
+        let request = Request::builder()
+            .uri("/bucket/key")
+            .header("host", "localhost")
+            .header("x-amz-date", "20260101T000000Z")
+            .header(
+                AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/20260101/us-east-1/s3/aws4_request, \
+                     SignedHeaders=host;x-amz-date, Signature={}",
+                    "0".repeat(64)
+                ),
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        let response = service.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn unknown_access_key_is_invalid_access_key_id() {
+        let (status, body) = call_with_access_key("unknown-key").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("<Code>InvalidAccessKeyId</Code>"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn known_access_key_with_bad_signature_is_signature_mismatch() {
+        let (status, body) = call_with_access_key("known-key").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(
+            body.contains("<Code>SignatureDoesNotMatch</Code>"),
+            "{body}"
+        );
+    }
+
+    /// Calls a service behind `layer` with a GET correctly signed by
+    /// `known-key` for `region`.
+    async fn call_signed_for_region(layer: AuthLayer, region: &str) -> (StatusCode, String) {
+        use crate::signature_v4::{
+            get_canonical_request, get_signature, get_signing_key, get_string_to_sign,
+        };
+
+        let date_time = "20260101T000000Z";
+        let canonical_request = get_canonical_request(
+            "GET",
+            "/bucket/key",
+            "",
+            &format!("host:localhost\nx-amz-date:{date_time}\n"),
+            "host;x-amz-date",
+            "UNSIGNED-PAYLOAD",
+        );
+        let scope = format!("20260101/{region}/s3/aws4_request");
+        let signature = get_signature(
+            &get_signing_key("secret", "20260101", region),
+            &get_string_to_sign(&canonical_request, date_time, &scope),
+        );
+        let service = layer.layer(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(StatusCode::OK.into_response())
+        }));
+        let request = Request::builder()
+            .uri("/bucket/key")
+            .header("host", "localhost")
+            .header("x-amz-date", date_time)
+            .header(
+                AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential=known-key/{scope}, \
+                     SignedHeaders=host;x-amz-date, Signature={signature}"
+                ),
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        let response = service.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn known_key_layer() -> AuthLayer {
+        AuthLayer::new(Arc::new(StaticCredentialProvider::new(
+            "known-key",
+            "secret",
+        )))
+    }
+
+    #[tokio::test]
+    async fn request_signed_for_the_server_region_is_accepted() {
+        let layer =
+            known_key_layer().with_signing_regions(SigningRegions::parse("eu-west-1", "eu-west-1"));
+        let (status, body) = call_signed_for_region(layer, "eu-west-1").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    #[tokio::test]
+    async fn request_signed_for_another_region_is_accepted_by_default() {
+        let (status, body) = call_signed_for_region(known_key_layer(), "us-east-1").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    #[tokio::test]
+    async fn request_signed_for_another_region_is_rejected_when_regions_are_limited() {
+        let layer = known_key_layer()
+            .with_signing_regions(SigningRegions::parse("eu-central-1", "eu-west-1"));
+        let (status, body) = call_signed_for_region(layer.clone(), "eu-central-1").await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+
+        let (status, body) = call_signed_for_region(layer, "us-east-1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            body.contains("<Code>AuthorizationHeaderMalformed</Code>"),
+            "{body}"
+        );
+    }
+
+    #[test]
+    fn list_bucket_exposes_prefix_and_delimiter_conditions() {
+        let context = condition_context(
+            "s3:ListBucket",
+            Some("list-type=2&prefix=home%2Falice%2F&delimiter=%2F"),
+        );
+        assert_eq!(
+            context.get("s3:prefix").map(String::as_str),
+            Some("home/alice/")
+        );
+        assert_eq!(context.get("s3:delimiter").map(String::as_str), Some("/"));
+
+        assert!(condition_context("s3:ListBucket", Some("list-type=2")).is_empty());
+        assert!(condition_context("s3:GetObject", Some("prefix=home")).is_empty());
+    }
+}
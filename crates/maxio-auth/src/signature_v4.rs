@@ -57,6 +57,7 @@ pub fn get_signature(signing_key: &[u8], string_to_sign: &str) -> String {
     hex::encode(hmac_sha256(signing_key, string_to_sign.as_bytes()))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn verify_signature(
     secret_key: &str,
     method: &str,
@@ -12,6 +12,8 @@ pub enum MaxioError {
     InvalidBucketName(String),
     #[error("invalid object name: {0}")]
     InvalidObjectName(String),
+    #[error("object key is too long: {0}")]
+    KeyTooLong(String),
     #[error("internal error: {0}")]
     InternalError(String),
     #[error("not implemented: {0}")]
@@ -20,14 +22,54 @@ pub enum MaxioError {
     AccessDenied(String),
     #[error("signature does not match")]
     SignatureDoesNotMatch,
+    /// The credential scope names something the server does not accept,
+    /// such as a region it does not serve.
+    #[error("authorization header is malformed: {0}")]
+    AuthorizationHeaderMalformed(String),
+    #[error("access key id does not exist: {0}")]
+    InvalidAccessKeyId(String),
     #[error("invalid argument: {0}")]
     InvalidArgument(String),
+    /// A request S3 refuses as a whole, such as a read of an SSE-C object
+    /// without its key. The message is shown to clients as is.
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("precondition failed: {0}")]
+    PreconditionFailed(String),
+    #[error("user metadata too large: {0}")]
+    MetadataTooLarge(String),
     #[error("entity too large: size={size}, max_size={max_size}")]
     EntityTooLarge { size: u64, max_size: u64 },
+    /// A bucket was requested in a region other than the server's.
+    #[error("invalid location constraint: {0}")]
+    InvalidLocationConstraint(String),
+    /// A `Range` that starts past the end of an object of `size` bytes.
+    #[error("requested range not satisfiable for object of {size} bytes")]
+    InvalidRange { size: u64 },
+    /// Too few disks answered for an erasure read or write to be trusted.
+    /// Transient: the request can succeed once the disks are back.
+    #[error("quorum unavailable: have {have}, need {needed}")]
+    QuorumUnavailable { needed: usize, have: usize },
+    /// The server is already serving as many requests as it allows.
+    #[error("server is busy: {0} requests in flight")]
+    ServerBusy(usize),
+    /// The client stopped sending the request body.
+    #[error("request body not received within {0:?}")]
+    RequestTimeout(std::time::Duration),
+    /// The request was received but not answered before its deadline.
+    #[error("request not completed within {0:?}")]
+    OperationTimedOut(std::time::Duration),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
 
+/// Longest object key S3 accepts, in UTF-8 bytes.
+pub const MAX_OBJECT_KEY_LEN: usize = 1024;
+
+/// Combined size S3 allows for the user metadata of an object: the bytes of
+/// every `x-amz-meta-*` name (without the prefix) and value.
+pub const MAX_USER_METADATA_SIZE: usize = 2 * 1024;
+
 impl MaxioError {
     pub fn s3_error_code(&self) -> &'static str {
         match self {
@@ -36,12 +78,23 @@ impl MaxioError {
             Self::ObjectNotFound { .. } => "NoSuchKey",
             Self::InvalidBucketName(_) => "InvalidBucketName",
             Self::InvalidObjectName(_) => "InvalidObjectName",
+            Self::KeyTooLong(_) => "KeyTooLongError",
             Self::InternalError(_) => "InternalError",
             Self::NotImplemented(_) => "NotImplemented",
             Self::AccessDenied(_) => "AccessDenied",
             Self::SignatureDoesNotMatch => "SignatureDoesNotMatch",
+            Self::AuthorizationHeaderMalformed(_) => "AuthorizationHeaderMalformed",
+            Self::InvalidAccessKeyId(_) => "InvalidAccessKeyId",
             Self::InvalidArgument(_) => "InvalidArgument",
+            Self::InvalidRequest(_) => "InvalidRequest",
+            Self::PreconditionFailed(_) => "PreconditionFailed",
+            Self::MetadataTooLarge(_) => "MetadataTooLarge",
             Self::EntityTooLarge { .. } => "EntityTooLarge",
+            Self::InvalidLocationConstraint(_) => "InvalidLocationConstraint",
+            Self::InvalidRange { .. } => "InvalidRange",
+            Self::QuorumUnavailable { .. } | Self::ServerBusy(_) => "SlowDown",
+            Self::RequestTimeout(_) => "RequestTimeout",
+            Self::OperationTimedOut(_) => "OperationTimedOut",
             Self::Io(_) => "InternalError",
         }
     }
@@ -1 +1,158 @@
-pub struct HashPlaceholder;
+use md5::{Digest, Md5};
+
+use crate::error::{MaxioError, Result};
+
+/// MD5 of `data`.
+pub fn md5_digest(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+/// Lowercase hex MD5 of `data`, the ETag of a single-part object.
+pub fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", Md5::digest(data))
+}
+
+/// Incremental MD5 for data that arrives in chunks.
+#[derive(Debug, Clone, Default)]
+pub struct Md5Hasher {
+    inner: Md5,
+}
+
+impl Md5Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finish(self) -> [u8; 16] {
+        self.inner.finalize().into()
+    }
+
+    pub fn finish_hex(self) -> String {
+        format!("{:x}", self.inner.finalize())
+    }
+}
+
+/// Builds the ETag of a multipart object the way S3 does: the MD5 of the
+/// concatenated binary part MD5s, followed by `-<part count>`.
+#[derive(Debug, Clone, Default)]
+pub struct CompositeEtag {
+    hasher: Md5Hasher,
+    parts: usize,
+}
+
+impl CompositeEtag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the next part by its ETag, quoted or not.
+    pub fn add_part(&mut self, part_etag: &str) -> Result<()> {
+        self.add_part_md5(&decode_md5_hex(&normalize_etag(part_etag))?);
+        Ok(())
+    }
+
+    pub fn add_part_md5(&mut self, part_md5: &[u8; 16]) {
+        self.hasher.update(part_md5);
+        self.parts += 1;
+    }
+
+    pub fn finish(self) -> String {
+        format!("{}-{}", self.hasher.finish_hex(), self.parts)
+    }
+}
+
+/// Decodes a 32-character hex MD5 ETag into its binary digest.
+pub fn decode_md5_hex(etag: &str) -> Result<[u8; 16]> {
+    let invalid = || MaxioError::InvalidArgument(format!("invalid part etag format: {etag}"));
+    if etag.len() != 32 || !etag.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut out = [0_u8; 16];
+    for (idx, byte) in out.iter_mut().enumerate() {
+        let start = idx * 2;
+        *byte = u8::from_str_radix(&etag[start..start + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(out)
+}
+
+/// Strips surrounding whitespace and one pair of double quotes.
+pub fn normalize_etag(etag: &str) -> String {
+    let trimmed = etag.trim();
+    if trimmed.starts_with('"') && trimmed.ends_with('"') && trimmed.len() >= 2 {
+        trimmed[1..trimmed.len() - 1].to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Wraps an ETag in double quotes for the `ETag` header and XML bodies,
+/// leaving already quoted values alone.
+pub fn quoted_etag(etag: &str) -> String {
+    if etag.starts_with('"') && etag.ends_with('"') {
+        etag.to_string()
+    } else {
+        format!("\"{etag}\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composite_etag_hashes_the_binary_part_digests() {
+        let first = md5_hex(b"first part");
+        let second = md5_hex(b"second part");
+
+        let mut composite = CompositeEtag::new();
+        composite.add_part(&first).unwrap();
+        composite.add_part(&format!("\"{second}\"")).unwrap();
+
+        let mut material = md5_digest(b"first part").to_vec();
+        material.extend_from_slice(&md5_digest(b"second part"));
+        assert_eq!(composite.finish(), format!("{}-2", md5_hex(&material)));
+    }
+
+    #[test]
+    fn composite_etag_matches_a_known_s3_etag() {
+        // A 5 MiB part of 'a' followed by a one byte part "b".
+        let mut composite = CompositeEtag::new();
+        composite
+            .add_part("\"79b281060d337b9b2b84ccf390adcf74\"")
+            .unwrap();
+        composite
+            .add_part("92eb5ffee6ae2fec3ad71c777531578f")
+            .unwrap();
+        assert_eq!(composite.finish(), "e5a8c5272b26fc10581a21089559b006-2");
+    }
+
+    #[test]
+    fn incremental_hash_matches_one_shot() {
+        let mut hasher = Md5Hasher::new();
+        hasher.update(b"hello ");
+        hasher.update(b"world");
+        assert_eq!(hasher.finish_hex(), md5_hex(b"hello world"));
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+    }
+
+    #[test]
+    fn etag_quoting_round_trips() {
+        assert_eq!(quoted_etag("abc"), "\"abc\"");
+        assert_eq!(quoted_etag("\"abc\""), "\"abc\"");
+        assert_eq!(normalize_etag(" \"abc-2\" "), "abc-2");
+        assert_eq!(normalize_etag("abc"), "abc");
+        assert_eq!(normalize_etag("\""), "\"");
+    }
+
+    #[test]
+    fn malformed_part_etags_are_rejected() {
+        assert!(decode_md5_hex("abc").is_err());
+        assert!(decode_md5_hex(&"z".repeat(32)).is_err());
+        assert!(CompositeEtag::new().add_part("abc-2").is_err());
+    }
+}
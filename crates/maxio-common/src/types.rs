@@ -3,6 +3,12 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Storage class of objects stored without an explicit one.
+pub const STANDARD_STORAGE_CLASS: &str = "STANDARD";
+/// Storage class trading durability for throughput: writes skip fsync and
+/// erasure sets use less parity.
+pub const REDUCED_REDUNDANCY_STORAGE_CLASS: &str = "REDUCED_REDUNDANCY";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectEncryption {
     pub algorithm: String,
@@ -21,10 +27,15 @@ pub struct ObjectInfo {
     pub bucket: String,
     pub key: String,
     pub size: i64,
+    /// MD5 of the plaintext (or the composite of its parts' plaintext MD5s),
+    /// even when the object is stored encrypted.
     pub etag: String,
     pub content_type: String,
     pub last_modified: DateTime<Utc>,
     pub metadata: HashMap<String, String>,
     pub version_id: Option<String>,
     pub encryption: Option<ObjectEncryption>,
+    /// `None` for [`STANDARD_STORAGE_CLASS`].
+    #[serde(default)]
+    pub storage_class: Option<String>,
 }
@@ -1 +1,88 @@
-pub struct XmlPlaceholder;
+use quick_xml::se::to_string as xml_to_string;
+use serde::Serialize;
+
+use crate::error::{MaxioError, Result};
+
+/// Namespace AWS declares on the root element of every S3 response.
+pub const S3_XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+pub const XML_PROLOG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// Serializes an S3 response document: the XML prolog followed by `payload`
+/// with the S3 namespace declared on its root element.
+pub fn to_s3_xml<T: Serialize>(payload: &T) -> Result<String> {
+    let xml = xml_to_string(payload).map_err(|err| {
+        MaxioError::InternalError(format!("failed to serialize xml response: {err}"))
+    })?;
+    Ok(format!("{XML_PROLOG}\n{}", with_s3_namespace(&xml)))
+}
+
+/// Declares the S3 namespace on the root element of `xml`, unless the root
+/// already declares a default namespace.
+pub fn with_s3_namespace(xml: &str) -> String {
+    let Some(rest) = xml.strip_prefix('<') else {
+        return xml.to_string();
+    };
+    let name_len = rest
+        .find(|ch: char| ch.is_whitespace() || ch == '>' || ch == '/')
+        .unwrap_or(rest.len());
+    let tag_end = rest.find('>').unwrap_or(rest.len());
+    if rest[..tag_end].contains("xmlns=") {
+        return xml.to_string();
+    }
+
+    let (name, attributes) = rest.split_at(name_len);
+    format!("<{name} xmlns=\"{S3_XMLNS}\"{attributes}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    #[serde(rename = "ListBucketResult")]
+    struct ListBucketResult {
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Contents")]
+        contents: Vec<Contents>,
+    }
+
+    #[derive(Serialize)]
+    struct Contents {
+        #[serde(rename = "Key")]
+        key: String,
+    }
+
+    #[test]
+    fn list_bucket_result_carries_s3_namespace() {
+        let xml = to_s3_xml(&ListBucketResult {
+            name: "bucket".to_string(),
+            contents: vec![Contents {
+                key: "a".to_string(),
+            }],
+        })
+        .unwrap();
+
+        assert_eq!(
+            xml,
+            format!(
+                "{XML_PROLOG}\n<ListBucketResult xmlns=\"{S3_XMLNS}\"><Name>bucket</Name>\
+                 <Contents><Key>a</Key></Contents></ListBucketResult>"
+            )
+        );
+    }
+
+    #[test]
+    fn namespace_is_added_once_to_the_root_only() {
+        assert_eq!(
+            with_s3_namespace("<Empty/>"),
+            format!("<Empty xmlns=\"{S3_XMLNS}\"/>")
+        );
+        assert_eq!(
+            with_s3_namespace("<Root id=\"1\"><Child/></Root>"),
+            format!("<Root xmlns=\"{S3_XMLNS}\" id=\"1\"><Child/></Root>")
+        );
+        let declared = "<Root xmlns=\"urn:other\"><Child/></Root>";
+        assert_eq!(with_s3_namespace(declared), declared);
+    }
+}
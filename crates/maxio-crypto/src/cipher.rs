@@ -1,21 +1,43 @@
 use aes_gcm::{
     Aes256Gcm, Nonce,
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
 };
 use rand::{RngCore, rngs::OsRng};
 
 use crate::{CryptoError, Result};
 
 const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
 
-pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+/// Bytes [`encrypt`] adds to the plaintext it seals: the nonce and the tag.
+pub const SEALED_OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+/// Additional authenticated data naming an object version. Ciphertext
+/// sealed with it only opens for the same bucket, key and version, so it
+/// cannot be moved to another object. A missing version reads as `null`.
+pub fn object_aad(bucket: &str, key: &str, version_id: Option<&str>) -> Vec<u8> {
+    let mut aad = Vec::new();
+    for field in [bucket, key, version_id.unwrap_or("null")] {
+        aad.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        aad.extend_from_slice(field.as_bytes());
+    }
+    aad
+}
+
+pub fn encrypt(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength(32))?;
     let mut nonce_bytes = [0_u8; NONCE_SIZE];
     OsRng.fill_bytes(&mut nonce_bytes);
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     let ciphertext = cipher
-        .encrypt(nonce, plaintext)
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
         .map_err(|_| CryptoError::Encrypt)?;
 
     let mut output = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
@@ -24,7 +46,9 @@ pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
     Ok(output)
 }
 
-pub fn decrypt(key: &[u8; 32], ciphertext: &[u8]) -> Result<Vec<u8>> {
+/// Opens `ciphertext` sealed by [`encrypt`]; fails unless `aad` matches the
+/// data it was sealed with.
+pub fn decrypt(key: &[u8; 32], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     if ciphertext.len() < NONCE_SIZE {
         return Err(CryptoError::InvalidCiphertext("missing nonce"));
     }
@@ -34,6 +58,55 @@ pub fn decrypt(key: &[u8; 32], ciphertext: &[u8]) -> Result<Vec<u8>> {
     let nonce = Nonce::from_slice(nonce_bytes);
 
     cipher
-        .decrypt(nonce, encrypted)
+        .decrypt(
+            nonce,
+            Payload {
+                msg: encrypted,
+                aad,
+            },
+        )
         .map_err(|_| CryptoError::Decrypt)
 }
+
+/// Seals one chunk of data that is sealed in fixed-size chunks. The chunk's
+/// index and whether it is the last one are authenticated along with
+/// `aad`, so chunks cannot be reordered, dropped or cut off at the end.
+pub fn encrypt_chunk(
+    key: &[u8; 32],
+    plaintext: &[u8],
+    aad: &[u8],
+    index: u64,
+    last: bool,
+) -> Result<Vec<u8>> {
+    encrypt(key, plaintext, &chunk_aad(aad, index, last))
+}
+
+/// Opens data sealed by [`encrypt_chunk`] in chunks of `chunk_size`
+/// plaintext bytes.
+pub fn decrypt_chunks(
+    key: &[u8; 32],
+    ciphertext: &[u8],
+    aad: &[u8],
+    chunk_size: usize,
+) -> Result<Vec<u8>> {
+    if ciphertext.is_empty() {
+        return Err(CryptoError::InvalidCiphertext("missing chunks"));
+    }
+
+    let sealed = ciphertext.chunks(chunk_size + SEALED_OVERHEAD);
+    let count = sealed.len();
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for (index, chunk) in sealed.enumerate() {
+        let aad = chunk_aad(aad, index as u64, index + 1 == count);
+        plaintext.extend_from_slice(&decrypt(key, chunk, &aad)?);
+    }
+    Ok(plaintext)
+}
+
+fn chunk_aad(aad: &[u8], index: u64, last: bool) -> Vec<u8> {
+    let mut chunk_aad = Vec::with_capacity(aad.len() + 9);
+    chunk_aad.extend_from_slice(aad);
+    chunk_aad.extend_from_slice(&index.to_be_bytes());
+    chunk_aad.push(u8::from(last));
+    chunk_aad
+}
@@ -4,7 +4,7 @@ use futures::{StreamExt, stream::FuturesUnordered};
 use tokio::time::{sleep, timeout};
 
 use super::{
-    lock_args::LockArgs,
+    lock_args::{DEFAULT_LOCK_TTL, LockArgs},
     locker::{LockResult, NetLocker},
 };
 
@@ -33,11 +33,30 @@ pub struct RefreshOutcome {
 #[derive(Clone)]
 pub struct DsyncClient {
     lockers: Vec<Arc<dyn NetLocker>>,
+    lock_ttl: Duration,
 }
 
 impl DsyncClient {
     pub fn new(lockers: Vec<Arc<dyn NetLocker>>) -> Self {
-        Self { lockers }
+        Self {
+            lockers,
+            lock_ttl: DEFAULT_LOCK_TTL,
+        }
+    }
+
+    pub fn with_lock_ttl(mut self, ttl: Duration) -> Self {
+        self.lock_ttl = ttl;
+        self
+    }
+
+    pub fn lock_ttl(&self) -> Duration {
+        self.lock_ttl
+    }
+
+    /// Held locks are refreshed three times per TTL so a single slow or
+    /// dropped refresh does not let the grant expire on the lockers.
+    pub fn refresh_interval(&self) -> Duration {
+        (self.lock_ttl / 3).max(Duration::from_millis(1))
     }
 
     pub fn total_nodes(&self) -> usize {
@@ -136,6 +155,10 @@ impl DsyncClient {
         }
 
         let succeeded = locks_acquired >= quorum && failures <= tolerance;
+        if !succeeded && locks_acquired > 0 {
+            self.release_partial(args, &granted, read_lock).await;
+            granted.fill(false);
+        }
 
         AcquireOutcome {
             granted,
@@ -147,6 +170,31 @@ impl DsyncClient {
         }
     }
 
+    /// Drops grants from an acquisition that missed quorum so they do not
+    /// block other acquirers until the TTL runs out.
+    async fn release_partial(&self, args: &LockArgs, granted: &[bool], read_lock: bool) {
+        let mut pending = FuturesUnordered::new();
+
+        for (index, locker) in self.lockers.iter().enumerate() {
+            if !granted.get(index).copied().unwrap_or(false) {
+                continue;
+            }
+
+            let locker = Arc::clone(locker);
+            let call_args = args.clone();
+            pending.push(async move {
+                let call = if read_lock {
+                    locker.runlock(&call_args)
+                } else {
+                    locker.unlock(&call_args)
+                };
+                let _ = timeout(ACQUIRE_TIMEOUT, call).await;
+            });
+        }
+
+        while pending.next().await.is_some() {}
+    }
+
     pub async fn refresh(&self, args: &LockArgs, granted: &[bool]) -> RefreshOutcome {
         let total = self.lockers.len();
         let quorum = args.quorum.clamp(1, total.max(1));
@@ -160,13 +208,11 @@ impl DsyncClient {
             let locker = Arc::clone(locker);
             let call_args = args.clone();
             pending.push(async move {
-                let outcome = match timeout(REFRESH_CALL_TIMEOUT, locker.refresh(&call_args)).await
-                {
+                match timeout(REFRESH_CALL_TIMEOUT, locker.refresh(&call_args)).await {
                     Ok(Ok(result)) => result,
                     Ok(Err(_)) => LockResult::Failed,
                     Err(_) => LockResult::Failed,
-                };
-                outcome
+                }
             });
         }
 
@@ -248,3 +294,90 @@ impl DsyncClient {
         while pending.next().await.is_some() {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use maxio_common::error::Result;
+
+    use super::*;
+
+    struct StubLocker {
+        grant: bool,
+        unlocks: AtomicUsize,
+    }
+
+    impl StubLocker {
+        fn new(grant: bool) -> Arc<Self> {
+            Arc::new(Self {
+                grant,
+                unlocks: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl NetLocker for StubLocker {
+        async fn lock(&self, _args: &LockArgs) -> Result<LockResult> {
+            if self.grant {
+                return Ok(LockResult::Success);
+            }
+            sleep(Duration::from_millis(50)).await;
+            Ok(LockResult::NotAcquired)
+        }
+
+        async fn rlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.lock(args).await
+        }
+
+        async fn unlock(&self, _args: &LockArgs) -> Result<LockResult> {
+            self.unlocks.fetch_add(1, Ordering::SeqCst);
+            Ok(LockResult::Success)
+        }
+
+        async fn runlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.unlock(args).await
+        }
+
+        async fn refresh(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::Success)
+        }
+
+        async fn force_unlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.unlock(args).await
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_acquire_releases_partial_grants() {
+        let lockers = [
+            StubLocker::new(true),
+            StubLocker::new(false),
+            StubLocker::new(false),
+        ];
+        let client = DsyncClient::new(
+            lockers
+                .iter()
+                .map(|locker| Arc::clone(locker) as Arc<dyn NetLocker>)
+                .collect(),
+        );
+        let args = LockArgs::new(
+            "uid".to_string(),
+            vec!["bucket/key".to_string()],
+            "owner".to_string(),
+            "test".to_string(),
+            client.quorum(true),
+        );
+
+        let outcome = client.lock(&args).await;
+
+        assert!(!outcome.succeeded);
+        assert_eq!(outcome.locks_acquired, 1);
+        assert!(outcome.granted.iter().all(|granted| !granted));
+        assert_eq!(lockers[0].unlocks.load(Ordering::SeqCst), 1);
+        assert_eq!(lockers[1].unlocks.load(Ordering::SeqCst), 0);
+        assert_eq!(lockers[2].unlocks.load(Ordering::SeqCst), 0);
+    }
+}
@@ -3,13 +3,16 @@ use std::{
         Arc, RwLock,
         atomic::{AtomicU64, Ordering},
     },
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use maxio_common::error::{MaxioError, Result};
 use tracing::warn;
 
-use super::{client::DsyncClient, lock_args::LockArgs};
+use super::{
+    client::{AcquireOutcome, DsyncClient},
+    lock_args::LockArgs,
+};
 
 static UID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
@@ -28,12 +31,18 @@ pub struct DRWMutex {
 }
 
 impl DRWMutex {
+    /// Resources are sorted and deduplicated so every client requests them in
+    /// the same order. Lockers that grant multi-resource locks piecewise can
+    /// then never deadlock on overlapping sets; lock, refresh and unlock all
+    /// reuse this ordering through the stored `LockArgs`.
     pub fn new(
         client: Arc<DsyncClient>,
-        resources: Vec<String>,
+        mut resources: Vec<String>,
         owner: impl Into<String>,
         source: impl Into<String>,
     ) -> Self {
+        resources.sort_unstable();
+        resources.dedup();
         let nodes = client.total_nodes();
         Self {
             client,
@@ -50,11 +59,31 @@ impl DRWMutex {
     }
 
     pub async fn lock(&self) -> Result<bool> {
-        self.acquire(false).await
+        Ok(self.acquire(false).await?.succeeded)
     }
 
     pub async fn rlock(&self) -> Result<bool> {
-        self.acquire(true).await
+        Ok(self.acquire(true).await?.succeeded)
+    }
+
+    /// Like [`lock`](Self::lock), but a lock that misses quorum fails with
+    /// `QuorumUnavailable`, which clients see as a retriable `SlowDown`.
+    pub async fn lock_or_unavailable(&self) -> Result<()> {
+        quorum_reached(self.acquire(false).await?)
+    }
+
+    /// Like [`rlock`](Self::rlock), but a lock that misses quorum fails with
+    /// `QuorumUnavailable`, which clients see as a retriable `SlowDown`.
+    pub async fn rlock_or_unavailable(&self) -> Result<()> {
+        quorum_reached(self.acquire(true).await?)
+    }
+
+    /// Like [`lock_or_unavailable`](Self::lock_or_unavailable), but the lock
+    /// is held by the returned guard and released when it is dropped, so a
+    /// caller cancelled mid-way does not leave the resources locked.
+    pub async fn lock_guard_or_unavailable(self) -> Result<DRWMutexGuard> {
+        self.lock_or_unavailable().await?;
+        Ok(DRWMutexGuard { mutex: Some(self) })
     }
 
     pub async fn unlock(&self) -> Result<()> {
@@ -85,7 +114,7 @@ impl DRWMutex {
         self.runlock().await
     }
 
-    async fn acquire(&self, read_lock: bool) -> Result<bool> {
+    async fn acquire(&self, read_lock: bool) -> Result<AcquireOutcome> {
         let quorum = self.client.quorum(!read_lock);
         if quorum == 0 {
             return Err(MaxioError::InvalidArgument(
@@ -99,7 +128,8 @@ impl DRWMutex {
             self.owner.clone(),
             self.source.clone(),
             quorum,
-        );
+        )
+        .with_ttl(self.client.lock_ttl());
 
         let outcome = if read_lock {
             self.client.rlock(&args).await
@@ -108,7 +138,7 @@ impl DRWMutex {
         };
 
         if !outcome.succeeded {
-            return Ok(false);
+            return Ok(outcome);
         }
 
         if read_lock {
@@ -122,7 +152,7 @@ impl DRWMutex {
                 Arc::clone(&self.read_args),
             );
             self.store_refresh_task(&self.read_refresh_task, Some(task))?;
-            return Ok(true);
+            return Ok(outcome);
         }
 
         self.abort_refresh_task(&self.write_refresh_task)?;
@@ -136,7 +166,7 @@ impl DRWMutex {
         );
         self.store_refresh_task(&self.write_refresh_task, Some(task))?;
 
-        Ok(true)
+        Ok(outcome)
     }
 
     async fn release(&self, read_lock: bool) -> Result<()> {
@@ -172,9 +202,10 @@ impl DRWMutex {
         args_store: Arc<RwLock<Option<LockArgs>>>,
     ) -> tokio::task::JoinHandle<()> {
         let client = Arc::clone(&self.client);
+        let period = client.refresh_interval();
 
         tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(Duration::from_secs(10));
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
             loop {
                 ticker.tick().await;
 
@@ -282,6 +313,54 @@ impl DRWMutex {
     }
 }
 
+/// A write lock held through [`DRWMutex::lock_guard_or_unavailable`].
+pub struct DRWMutexGuard {
+    mutex: Option<DRWMutex>,
+}
+
+impl DRWMutexGuard {
+    /// Releases the lock and waits for the lockers to confirm. A release
+    /// that fails is only logged; the grants expire once they stop being
+    /// refreshed.
+    pub async fn unlock(mut self) {
+        if let Some(mutex) = self.mutex.take()
+            && let Err(err) = mutex.unlock().await
+        {
+            warn!(error = %err, "failed to release dsync lock");
+        }
+    }
+}
+
+impl Drop for DRWMutexGuard {
+    /// Stops refreshing right away and releases in the background. Without a
+    /// runtime to release on, the grants still expire with their TTL.
+    fn drop(&mut self) {
+        let Some(mutex) = self.mutex.take() else {
+            return;
+        };
+        if let Err(err) = mutex.abort_refresh_task(&mutex.write_refresh_task) {
+            warn!(error = %err, "failed to stop dsync lock refresh");
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(err) = mutex.unlock().await {
+                    warn!(error = %err, "failed to release dropped dsync lock");
+                }
+            });
+        }
+    }
+}
+
+fn quorum_reached(outcome: AcquireOutcome) -> Result<()> {
+    if outcome.succeeded {
+        return Ok(());
+    }
+    Err(MaxioError::QuorumUnavailable {
+        needed: outcome.quorum,
+        have: outcome.locks_acquired,
+    })
+}
+
 fn next_uid() -> String {
     let nanos = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -290,3 +369,244 @@ fn next_uid() -> String {
     let counter = UID_COUNTER.fetch_add(1, Ordering::Relaxed);
     format!("{nanos}-{counter}")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::dsync::locker::{LockResult, NetLocker};
+
+    /// In-memory locker that expires grants which are not refreshed within
+    /// the TTL carried by the request.
+    #[derive(Default)]
+    struct ExpiringLocker {
+        grants: Mutex<HashMap<String, Instant>>,
+        refreshes: AtomicU64,
+    }
+
+    impl ExpiringLocker {
+        fn holds(&self, uid: &str) -> bool {
+            let grants = self.grants.lock().unwrap();
+            grants
+                .get(uid)
+                .is_some_and(|deadline| *deadline > Instant::now())
+        }
+    }
+
+    #[async_trait]
+    impl NetLocker for ExpiringLocker {
+        async fn lock(&self, args: &LockArgs) -> Result<LockResult> {
+            let mut grants = self.grants.lock().unwrap();
+            grants.retain(|_, deadline| *deadline > Instant::now());
+            if !grants.is_empty() {
+                return Ok(LockResult::NotAcquired);
+            }
+            grants.insert(args.uid.clone(), Instant::now() + args.ttl);
+            Ok(LockResult::Success)
+        }
+
+        async fn rlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.lock(args).await
+        }
+
+        async fn unlock(&self, args: &LockArgs) -> Result<LockResult> {
+            let removed = self.grants.lock().unwrap().remove(&args.uid);
+            Ok(match removed {
+                Some(_) => LockResult::Success,
+                None => LockResult::LockNotFound,
+            })
+        }
+
+        async fn runlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.unlock(args).await
+        }
+
+        async fn refresh(&self, args: &LockArgs) -> Result<LockResult> {
+            self.refreshes.fetch_add(1, Ordering::Relaxed);
+            let mut grants = self.grants.lock().unwrap();
+            match grants.get_mut(&args.uid) {
+                Some(deadline) if *deadline > Instant::now() => {
+                    *deadline = Instant::now() + args.ttl;
+                    Ok(LockResult::Success)
+                }
+                _ => Ok(LockResult::LockNotFound),
+            }
+        }
+
+        async fn force_unlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.unlock(args).await
+        }
+    }
+
+    /// Locker that takes resources one at a time in request order, waiting
+    /// on each while holding the ones it already has.
+    #[derive(Default)]
+    struct PiecewiseLocker {
+        held: Mutex<HashMap<String, String>>,
+    }
+
+    impl PiecewiseLocker {
+        fn release(&self, args: &LockArgs) -> LockResult {
+            let mut held = self.held.lock().unwrap();
+            let before = held.len();
+            held.retain(|_, uid| *uid != args.uid);
+            if held.len() < before {
+                LockResult::Success
+            } else {
+                LockResult::LockNotFound
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NetLocker for PiecewiseLocker {
+        async fn lock(&self, args: &LockArgs) -> Result<LockResult> {
+            for resource in &args.resources {
+                loop {
+                    {
+                        let mut held = self.held.lock().unwrap();
+                        if !held.contains_key(resource) {
+                            held.insert(resource.clone(), args.uid.clone());
+                            break;
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            Ok(LockResult::Success)
+        }
+
+        async fn rlock(&self, args: &LockArgs) -> Result<LockResult> {
+            self.lock(args).await
+        }
+
+        async fn unlock(&self, args: &LockArgs) -> Result<LockResult> {
+            Ok(self.release(args))
+        }
+
+        async fn runlock(&self, args: &LockArgs) -> Result<LockResult> {
+            Ok(self.release(args))
+        }
+
+        async fn refresh(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::Success)
+        }
+
+        async fn force_unlock(&self, args: &LockArgs) -> Result<LockResult> {
+            Ok(self.release(args))
+        }
+    }
+
+    fn current_uid(mutex: &DRWMutex) -> String {
+        mutex
+            .write_args
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|args| args.uid.clone())
+            .expect("lock args")
+    }
+
+    #[tokio::test]
+    async fn refresh_keeps_short_ttl_lock_alive() {
+        let lockers = (0..3)
+            .map(|_| Arc::new(ExpiringLocker::default()))
+            .collect::<Vec<_>>();
+        let client = DsyncClient::new(
+            lockers
+                .iter()
+                .map(|locker| Arc::clone(locker) as Arc<dyn NetLocker>)
+                .collect(),
+        )
+        .with_lock_ttl(Duration::from_millis(150));
+        assert_eq!(client.refresh_interval(), Duration::from_millis(50));
+
+        let mutex = DRWMutex::new(
+            Arc::new(client),
+            vec!["bucket/key".to_string()],
+            "owner",
+            "test",
+        );
+        assert!(mutex.lock().await.unwrap());
+        let uid = current_uid(&mutex);
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+
+        let alive = lockers
+            .iter()
+            .filter(|locker| locker.holds(&uid))
+            .inspect(|locker| assert!(locker.refreshes.load(Ordering::Relaxed) >= 4))
+            .count();
+        assert!(alive >= 2, "grants expired before refresh");
+
+        mutex.unlock().await.unwrap();
+        assert!(lockers.iter().all(|locker| !locker.holds(&uid)));
+    }
+
+    #[tokio::test]
+    async fn overlapping_resource_sets_lock_in_canonical_order() {
+        let locker: Arc<dyn NetLocker> = Arc::new(PiecewiseLocker::default());
+        let client = Arc::new(DsyncClient::new(vec![locker]));
+        let forward = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["a".to_string(), "b".to_string()],
+            "owner-1",
+            "test",
+        );
+        let backward = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["b".to_string(), "a".to_string(), "b".to_string()],
+            "owner-2",
+            "test",
+        );
+        assert_eq!(backward.resources, vec!["a".to_string(), "b".to_string()]);
+
+        let run = |mutex: DRWMutex| async move {
+            while !mutex.lock().await.unwrap() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            mutex.unlock().await.unwrap();
+        };
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::future::join(run(forward), run(backward)),
+        )
+        .await
+        .expect("both lockers make progress");
+    }
+
+    #[tokio::test]
+    async fn contended_lock_reports_quorum_unavailable() {
+        let lockers = (0..3)
+            .map(|_| Arc::new(ExpiringLocker::default()) as Arc<dyn NetLocker>)
+            .collect::<Vec<_>>();
+        let client = Arc::new(DsyncClient::new(lockers));
+        let holder = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["bucket/key".to_string()],
+            "owner-1",
+            "test",
+        );
+        holder.lock_or_unavailable().await.expect("first lock");
+
+        let contender = DRWMutex::new(client, vec!["bucket/key".to_string()], "owner-2", "test");
+        assert!(matches!(
+            contender.lock_or_unavailable().await,
+            Err(MaxioError::QuorumUnavailable { needed: 2, have: 0 })
+        ));
+
+        holder.unlock().await.unwrap();
+        contender
+            .lock_or_unavailable()
+            .await
+            .expect("lock after release");
+        contender.unlock().await.unwrap();
+    }
+}
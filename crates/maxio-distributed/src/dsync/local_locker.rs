@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use maxio_common::error::Result;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use super::{
+    lock_args::LockArgs,
+    locker::{LockResult, NetLocker},
+};
+
+/// One resource lock granted by a [`LocalLocker`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    pub resource: String,
+    pub uid: String,
+    pub owner: String,
+    pub source: String,
+    /// Write locks are exclusive; read locks share the resource.
+    pub writer: bool,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct Grant {
+    entry: LockEntry,
+    expires: Instant,
+}
+
+/// In-process locker holding the grants of this node. Grants that are not
+/// refreshed within their TTL expire, so a crashed holder frees its
+/// resources eventually; [`force_unlock`](NetLocker::force_unlock) frees
+/// them at once.
+#[derive(Debug, Default)]
+pub struct LocalLocker {
+    grants: Mutex<HashMap<String, Vec<Grant>>>,
+}
+
+impl LocalLocker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every live grant, oldest first.
+    pub fn held_locks(&self) -> Vec<LockEntry> {
+        let mut entries = self
+            .live_grants()
+            .values()
+            .flatten()
+            .map(|grant| grant.entry.clone())
+            .collect::<Vec<_>>();
+        entries.sort_by(|left, right| {
+            (left.since, &left.resource, &left.uid).cmp(&(right.since, &right.resource, &right.uid))
+        });
+        entries
+    }
+
+    fn live_grants(&self) -> MutexGuard<'_, HashMap<String, Vec<Grant>>> {
+        let mut grants = self
+            .grants
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        grants.retain(|_, held| {
+            held.retain(|grant| grant.expires > now);
+            !held.is_empty()
+        });
+        grants
+    }
+
+    fn grant(&self, args: &LockArgs, writer: bool) -> LockResult {
+        let mut grants = self.live_grants();
+        let conflicts = args.resources.iter().any(|resource| {
+            grants
+                .get(resource)
+                .is_some_and(|held| writer || held.iter().any(|grant| grant.entry.writer))
+        });
+        if conflicts {
+            return LockResult::NotAcquired;
+        }
+
+        let since = Utc::now();
+        let expires = Instant::now() + args.ttl;
+        for resource in &args.resources {
+            grants.entry(resource.clone()).or_default().push(Grant {
+                entry: LockEntry {
+                    resource: resource.clone(),
+                    uid: args.uid.clone(),
+                    owner: args.owner.clone(),
+                    source: args.source.clone(),
+                    writer,
+                    since,
+                },
+                expires,
+            });
+        }
+        LockResult::Success
+    }
+
+    /// Drops the grants matching `release` on the resources of `args`.
+    fn release(&self, args: &LockArgs, release: impl Fn(&LockEntry) -> bool) -> LockResult {
+        let mut grants = self.live_grants();
+        let mut removed = false;
+        for resource in &args.resources {
+            if let Some(held) = grants.get_mut(resource) {
+                let before = held.len();
+                held.retain(|grant| !release(&grant.entry));
+                removed |= held.len() < before;
+                if held.is_empty() {
+                    grants.remove(resource);
+                }
+            }
+        }
+        if removed {
+            LockResult::Success
+        } else {
+            LockResult::LockNotFound
+        }
+    }
+}
+
+#[async_trait]
+impl NetLocker for LocalLocker {
+    async fn lock(&self, args: &LockArgs) -> Result<LockResult> {
+        Ok(self.grant(args, true))
+    }
+
+    async fn rlock(&self, args: &LockArgs) -> Result<LockResult> {
+        Ok(self.grant(args, false))
+    }
+
+    async fn unlock(&self, args: &LockArgs) -> Result<LockResult> {
+        Ok(self.release(args, |entry| entry.writer && entry.uid == args.uid))
+    }
+
+    async fn runlock(&self, args: &LockArgs) -> Result<LockResult> {
+        Ok(self.release(args, |entry| !entry.writer && entry.uid == args.uid))
+    }
+
+    async fn refresh(&self, args: &LockArgs) -> Result<LockResult> {
+        let mut grants = self.live_grants();
+        let expires = Instant::now() + args.ttl;
+        let mut refreshed = false;
+        for grant in grants
+            .values_mut()
+            .flatten()
+            .filter(|grant| grant.entry.uid == args.uid)
+        {
+            grant.expires = expires;
+            refreshed = true;
+        }
+        Ok(if refreshed {
+            LockResult::Success
+        } else {
+            LockResult::LockNotFound
+        })
+    }
+
+    /// Releases the grants of `args.uid`, or every grant on the resources
+    /// of `args` when the uid is empty.
+    async fn force_unlock(&self, args: &LockArgs) -> Result<LockResult> {
+        Ok(self.release(args, |entry| args.uid.is_empty() || entry.uid == args.uid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::dsync::{DRWMutex, DsyncClient};
+
+    #[tokio::test]
+    async fn force_unlock_frees_a_held_resource() {
+        let locker = Arc::new(LocalLocker::new());
+        let client = Arc::new(DsyncClient::new(vec![
+            Arc::clone(&locker) as Arc<dyn NetLocker>
+        ]));
+        let holder = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["bucket/key".to_string()],
+            "crashed-node",
+            "test",
+        );
+        assert!(holder.lock().await.unwrap());
+
+        let held = locker.held_locks();
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].resource, "bucket/key");
+        assert_eq!(held[0].owner, "crashed-node");
+        assert!(held[0].writer);
+
+        let contender = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["bucket/key".to_string()],
+            "other-node",
+            "test",
+        );
+        assert!(!contender.lock().await.unwrap());
+
+        let args = LockArgs::new(
+            String::new(),
+            vec!["bucket/key".to_string()],
+            "admin".to_string(),
+            "test".to_string(),
+            1,
+        );
+        client.force_unlock(&args).await;
+        assert!(locker.held_locks().is_empty());
+        assert!(contender.lock().await.unwrap());
+        contender.unlock().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn readers_share_and_grants_expire() {
+        let locker = LocalLocker::new();
+        let args = |uid: &str| {
+            LockArgs::new(
+                uid.to_string(),
+                vec!["bucket/key".to_string()],
+                "owner".to_string(),
+                "test".to_string(),
+                1,
+            )
+            .with_ttl(Duration::from_millis(50))
+        };
+
+        assert_eq!(
+            locker.rlock(&args("r1")).await.unwrap(),
+            LockResult::Success
+        );
+        assert_eq!(
+            locker.rlock(&args("r2")).await.unwrap(),
+            LockResult::Success
+        );
+        assert_eq!(
+            locker.lock(&args("w1")).await.unwrap(),
+            LockResult::NotAcquired
+        );
+        assert_eq!(locker.held_locks().len(), 2);
+
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(locker.held_locks().is_empty());
+        assert_eq!(locker.lock(&args("w1")).await.unwrap(), LockResult::Success);
+    }
+}
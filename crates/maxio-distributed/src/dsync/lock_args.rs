@@ -1,5 +1,10 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+/// How long a locker keeps a grant alive without a refresh.
+pub const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockArgs {
     pub uid: String,
@@ -7,6 +12,8 @@ pub struct LockArgs {
     pub owner: String,
     pub source: String,
     pub quorum: usize,
+    #[serde(default = "default_lock_ttl")]
+    pub ttl: Duration,
 }
 
 impl LockArgs {
@@ -23,6 +30,16 @@ impl LockArgs {
             owner,
             source,
             quorum,
+            ttl: DEFAULT_LOCK_TTL,
         }
     }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+fn default_lock_ttl() -> Duration {
+    DEFAULT_LOCK_TTL
 }
@@ -1,9 +1,10 @@
 use async_trait::async_trait;
 use maxio_common::error::Result;
+use serde::{Deserialize, Serialize};
 
 use super::lock_args::LockArgs;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LockResult {
     Success,
     NotAcquired,
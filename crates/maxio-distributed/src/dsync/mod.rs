@@ -1,9 +1,11 @@
 pub mod client;
 pub mod drwmutex;
+pub mod local_locker;
 pub mod lock_args;
 pub mod locker;
 
 pub use client::{AcquireOutcome, DsyncClient, RefreshOutcome};
-pub use drwmutex::DRWMutex;
+pub use drwmutex::{DRWMutex, DRWMutexGuard};
+pub use local_locker::{LocalLocker, LockEntry};
 pub use lock_args::LockArgs;
 pub use locker::{LockResult, NetLocker};
@@ -9,7 +9,7 @@ pub enum GridError {
     #[error("message decode error: {0}")]
     Decode(#[source] rmp_serde::decode::Error),
     #[error("websocket error: {0}")]
-    WebSocket(#[source] tokio_tungstenite::tungstenite::Error),
+    WebSocket(#[source] Box<tokio_tungstenite::tungstenite::Error>),
     #[error("connection already started")]
     ConnectionAlreadyStarted,
     #[error("connection closed")]
@@ -29,12 +29,26 @@ pub enum GridError {
     StreamClosed(u32),
     #[error("unknown mux id {mux_id}")]
     UnknownMux { mux_id: u32 },
+    #[error("grid frame of {len} bytes exceeds the {max} byte limit")]
+    FrameTooLarge { len: usize, max: usize },
     #[error("invalid subroute payload")]
     InvalidSubroutePayload,
     #[error("subroute too long: {len}")]
     SubrouteTooLong { len: usize },
     #[error("invalid utf8 in subroute: {0}")]
     Utf8(#[source] std::str::Utf8Error),
+    #[error("too many in-flight requests: limit {limit}")]
+    TooManyRequests { limit: usize },
+    #[error("object transfer failed: {0}")]
+    Transfer(String),
+    #[error("object transfer checksum mismatch for {bucket}/{key}")]
+    ChecksumMismatch { bucket: String, key: String },
+    #[error("object transfer of {bucket}/{key} received nothing for {timeout:?}")]
+    TransferStalled {
+        bucket: String,
+        key: String,
+        timeout: std::time::Duration,
+    },
     #[error("node not connected: {0}")]
     NodeNotConnected(String),
 }
@@ -1,15 +1,25 @@
 use std::{
+    hash::{BuildHasher, RandomState},
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use futures::{SinkExt, StreamExt};
+use sha2::{Digest, Sha256};
 use tokio::{
-    net::TcpStream,
+    io::{AsyncRead, AsyncWrite},
     sync::{RwLock, mpsc},
     time,
 };
-use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{
+    Connector, WebSocketStream,
+    tungstenite::{
+        Message as WsMessage,
+        client::IntoClientRequest,
+        http::{HeaderValue, header::AUTHORIZATION},
+        protocol::Role,
+    },
+};
 
 use crate::errors::{GridError, Result};
 
@@ -21,7 +31,103 @@ use super::{
 
 const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
 const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+const RECONNECT_MIN_BACKOFF: Duration = Duration::from_secs(1);
 const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const RECONNECT_STABLE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Path every node serves grid connections from its peers on.
+pub const GRID_PATH: &str = "/minio/grid/v1";
+
+/// Bearer token peers present when opening a grid connection. Every node of
+/// a cluster shares the root credentials, so each can derive it without
+/// sending the secret itself.
+pub fn grid_auth_token(access_key: &str, secret_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"maxio-grid\0");
+    hasher.update(access_key.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(secret_key.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The grid URL of a node given its S3 endpoint.
+pub fn grid_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    let endpoint = if let Some(rest) = endpoint.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        format!("ws://{endpoint}")
+    };
+    format!("{endpoint}{GRID_PATH}")
+}
+
+/// `Sec-WebSocket-Accept` for a peer's `Sec-WebSocket-Key`, for servers that
+/// complete the upgrade handshake themselves.
+pub fn grid_accept_key(key: &[u8]) -> String {
+    tokio_tungstenite::tungstenite::handshake::derive_accept_key(key)
+}
+
+/// Exponential reconnect delay. It only resets once a session has stayed up
+/// for the stable window, so a peer that accepts and immediately drops the
+/// connection still backs off. Each delay is jittered by up to ±25% so many
+/// nodes losing the same peer do not reconnect in lockstep.
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    min: Duration,
+    max: Duration,
+    stable_window: Duration,
+    current: Duration,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self::new(
+            RECONNECT_MIN_BACKOFF,
+            RECONNECT_MAX_BACKOFF,
+            RECONNECT_STABLE_WINDOW,
+        )
+    }
+}
+
+impl ReconnectBackoff {
+    pub fn new(min: Duration, max: Duration, stable_window: Duration) -> Self {
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            stable_window,
+            current: min,
+        }
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Records how long the last session stayed connected.
+    pub fn session_ended(&mut self, uptime: Duration) {
+        if uptime >= self.stable_window {
+            self.current = self.min;
+        }
+    }
+
+    /// Returns the jittered delay before the next attempt and grows the base
+    /// delay for the one after.
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.current;
+        self.current = std::cmp::min(base.saturating_mul(2), self.max);
+
+        let spread = base / 2;
+        let spread_nanos = u64::try_from(spread.as_nanos()).unwrap_or(u64::MAX);
+        let offset = match spread_nanos {
+            0 => 0,
+            nanos => RandomState::new().hash_one(Instant::now()) % nanos,
+        };
+        (base - base / 4 + Duration::from_nanos(offset)).min(self.max)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum ConnectionState {
@@ -40,6 +146,9 @@ pub struct Connection {
     inbound_tx: mpsc::Sender<Message>,
     mux_client: MuxClient,
     mux_server: MuxServer,
+    backoff: ReconnectBackoff,
+    auth_token: Option<String>,
+    tls: Option<Arc<rustls::ClientConfig>>,
 }
 
 impl Connection {
@@ -58,16 +167,40 @@ impl Connection {
             inbound_tx,
             mux_client,
             mux_server,
+            backoff: ReconnectBackoff::default(),
+            auth_token: None,
+            tls: None,
         };
 
         connection.spawn_dispatcher(inbound_rx);
         connection
     }
 
+    pub fn with_backoff(mut self, backoff: ReconnectBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sent as a bearer token when dialing the peer.
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Trust used when dialing a `wss://` peer.
+    pub fn with_tls(mut self, config: Arc<rustls::ClientConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
     pub fn remote_addr(&self) -> &str {
         &self.remote_addr
     }
 
+    pub fn in_flight(&self) -> usize {
+        self.mux_client.in_flight()
+    }
+
     pub async fn state(&self) -> ConnectionState {
         self.state.read().await.clone()
     }
@@ -87,6 +220,33 @@ impl Connection {
         Ok(())
     }
 
+    /// Runs a single session on a connection a peer opened to this node,
+    /// answering its requests until either side closes it. Unlike
+    /// [`start`](Self::start), a dropped session is not redialed; the peer
+    /// reconnects.
+    pub async fn serve<S>(&self, io: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut outgoing = self
+            .outgoing_rx
+            .write()
+            .await
+            .take()
+            .ok_or(GridError::ConnectionAlreadyStarted)?;
+        let stream = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+        self.set_state(ConnectionState::Connected).await;
+        let result = self.session(stream, &mut outgoing).await;
+        if let Err(err) = &result {
+            self.mux_client.fail_all(err).await;
+        }
+        self.set_state(ConnectionState::Unconnected).await;
+        match result {
+            Err(GridError::ConnectionClosed) => Ok(()),
+            result => result,
+        }
+    }
+
     pub async fn request(
         &self,
         mux_id: MuxId,
@@ -99,6 +259,18 @@ impl Connection {
             .await
     }
 
+    pub async fn open_stream(
+        &self,
+        mux_id: MuxId,
+        handler: u8,
+        subroute: Option<&str>,
+        payload: Vec<u8>,
+    ) -> Result<mpsc::Receiver<Vec<u8>>> {
+        self.mux_client
+            .open_stream(mux_id, handler, subroute, payload)
+            .await
+    }
+
     pub async fn send(&self, message: Message) -> Result<()> {
         self.outgoing_tx
             .send(message)
@@ -106,6 +278,30 @@ impl Connection {
             .map_err(|_| GridError::ConnectionClosed)
     }
 
+    /// Wires two connections back to back without a websocket so grid
+    /// handlers can be exercised between in-process nodes.
+    #[cfg(test)]
+    pub(crate) async fn connect_in_process(&self, peer: &Connection) -> Result<()> {
+        for (from, to) in [(self, peer), (peer, self)] {
+            let mut outgoing = from
+                .outgoing_rx
+                .write()
+                .await
+                .take()
+                .ok_or(GridError::ConnectionAlreadyStarted)?;
+            let inbound = to.inbound_tx.clone();
+            tokio::spawn(async move {
+                while let Some(message) = outgoing.recv().await {
+                    if inbound.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            from.set_state(ConnectionState::Connected).await;
+        }
+        Ok(())
+    }
+
     async fn set_state(&self, state: ConnectionState) {
         *self.state.write().await = state;
     }
@@ -156,16 +352,29 @@ impl Connection {
     }
 
     async fn run(&self, outgoing: &mut mpsc::Receiver<Message>) {
-        let mut backoff = Duration::from_secs(1);
+        let mut backoff = self.backoff.clone();
 
         loop {
             self.set_state(ConnectionState::Connecting).await;
 
-            match tokio_tungstenite::connect_async(&self.remote_addr).await {
+            let request = match self.client_request() {
+                Ok(request) => request,
+                Err(err) => {
+                    self.set_state(ConnectionState::Error(err.to_string()))
+                        .await;
+                    time::sleep(backoff.next_delay()).await;
+                    continue;
+                }
+            };
+            let connector = self.tls.clone().map(Connector::Rustls);
+            match tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector)
+                .await
+            {
                 Ok((stream, _)) => {
                     self.set_state(ConnectionState::Connected).await;
-                    backoff = Duration::from_secs(1);
+                    let connected_at = Instant::now();
                     let result = self.session(stream, outgoing).await;
+                    backoff.session_ended(connected_at.elapsed());
                     if let Err(err) = result {
                         self.set_state(ConnectionState::Error(err.to_string()))
                             .await;
@@ -176,29 +385,52 @@ impl Connection {
                 Err(err) => {
                     self.set_state(ConnectionState::Error(err.to_string()))
                         .await;
-                    self.mux_client.fail_all(&GridError::WebSocket(err)).await;
+                    self.mux_client
+                        .fail_all(&GridError::WebSocket(Box::new(err)))
+                        .await;
                 }
             }
 
-            time::sleep(backoff).await;
-            backoff = std::cmp::min(backoff.saturating_mul(2), RECONNECT_MAX_BACKOFF);
+            time::sleep(backoff.next_delay()).await;
         }
     }
 
-    async fn session(
+    fn client_request(&self) -> Result<tokio_tungstenite::tungstenite::handshake::client::Request> {
+        let websocket_error =
+            |err: tokio_tungstenite::tungstenite::Error| GridError::WebSocket(Box::new(err));
+        let mut request = self
+            .remote_addr
+            .as_str()
+            .into_client_request()
+            .map_err(websocket_error)?;
+        if let Some(token) = &self.auth_token {
+            let value = HeaderValue::from_str(&format!("Bearer {token}")).map_err(|err| {
+                websocket_error(tokio_tungstenite::tungstenite::Error::HttpFormat(
+                    err.into(),
+                ))
+            })?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+        Ok(request)
+    }
+
+    async fn session<S>(
         &self,
-        stream: tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>,
+        stream: WebSocketStream<S>,
         outgoing: &mut mpsc::Receiver<Message>,
-    ) -> Result<()> {
+    ) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
         let (mut ws_tx, mut ws_rx) = stream.split();
         let mut keepalive = time::interval(KEEPALIVE_INTERVAL);
         let mut last_pong = Instant::now();
 
         let connect_msg = Message::new(0, 0, 0, Op::Connect, Flags::STATELESS, Vec::new());
         ws_tx
-            .send(WsMessage::Binary(connect_msg.encode()?.into()))
+            .send(WsMessage::Binary(connect_msg.encode()?))
             .await
-            .map_err(GridError::WebSocket)?;
+            .map_err(|err| GridError::WebSocket(Box::new(err)))?;
 
         loop {
             tokio::select! {
@@ -207,9 +439,9 @@ impl Connection {
                         return Err(GridError::ConnectionClosed);
                     };
                     ws_tx
-                        .send(WsMessage::Binary(msg.encode()?.into()))
+                        .send(WsMessage::Binary(msg.encode()?))
                         .await
-                        .map_err(GridError::WebSocket)?;
+                        .map_err(|err| GridError::WebSocket(Box::new(err)))?;
                 }
                 incoming = ws_rx.next() => {
                     match incoming {
@@ -227,7 +459,7 @@ impl Connection {
                             ws_tx
                                 .send(WsMessage::Pong(payload))
                                 .await
-                                .map_err(GridError::WebSocket)?;
+                                .map_err(|err| GridError::WebSocket(Box::new(err)))?;
                         }
                         Some(Ok(WsMessage::Pong(_))) => {
                             last_pong = Instant::now();
@@ -236,7 +468,7 @@ impl Connection {
                             return Err(GridError::ConnectionClosed);
                         }
                         Some(Ok(_)) => {}
-                        Some(Err(err)) => return Err(GridError::WebSocket(err)),
+                        Some(Err(err)) => return Err(GridError::WebSocket(Box::new(err))),
                         None => return Err(GridError::ConnectionClosed),
                     }
                 }
@@ -247,11 +479,74 @@ impl Connection {
 
                     let ping = Message::new(0, 0, 0, Op::Ping, Flags::STATELESS, Vec::new());
                     ws_tx
-                        .send(WsMessage::Binary(ping.encode()?.into()))
+                        .send(WsMessage::Binary(ping.encode()?))
                         .await
-                        .map_err(GridError::WebSocket)?;
+                        .map_err(|err| GridError::WebSocket(Box::new(err)))?;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn backoff_resets_only_after_stable_session() {
+        let mut backoff = ReconnectBackoff::new(
+            Duration::from_secs(1),
+            Duration::from_secs(30),
+            Duration::from_secs(30),
+        );
+
+        for expected in [1, 2, 4, 8] {
+            let delay = backoff.next_delay();
+            let base = Duration::from_secs(expected);
+            assert!(delay >= base - base / 4 && delay <= base + base / 4);
+            backoff.session_ended(Duration::from_millis(50));
+        }
+        assert_eq!(backoff.current(), Duration::from_secs(16));
+
+        backoff.next_delay();
+        backoff.next_delay();
+        assert_eq!(backoff.current(), Duration::from_secs(30));
+
+        backoff.session_ended(Duration::from_secs(31));
+        assert_eq!(backoff.current(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn flapping_peer_is_not_redialed_at_the_minimum_delay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if let Ok(mut ws) = tokio_tungstenite::accept_async(tcp).await {
+                    let _ = ws.close(None).await;
+                }
+            }
+        });
+
+        let min = Duration::from_millis(20);
+        let connection =
+            Connection::new(format!("ws://{addr}"), HandlerRegistry::new()).with_backoff(
+                ReconnectBackoff::new(min, Duration::from_secs(5), Duration::from_secs(30)),
+            );
+        connection.start().await.unwrap();
+
+        time::sleep(Duration::from_millis(600)).await;
+
+        // A reset-on-connect backoff redials every ~20ms (about 30 times);
+        // a growing one only gets through 20+40+80+160+320ms.
+        let attempts = accepted.load(Ordering::SeqCst);
+        assert!((2..=7).contains(&attempts), "attempts = {attempts}");
+    }
+}
@@ -13,6 +13,7 @@ pub enum HandlerID {
     Healing,
     Replication,
     Admin,
+    Lock,
     Custom(u8),
 }
 
@@ -23,6 +24,7 @@ impl HandlerID {
             Self::Healing => 2,
             Self::Replication => 3,
             Self::Admin => 4,
+            Self::Lock => 5,
             Self::Custom(value) => value,
         }
     }
@@ -33,6 +35,7 @@ impl HandlerID {
             2 => Self::Healing,
             3 => Self::Replication,
             4 => Self::Admin,
+            5 => Self::Lock,
             _ => Self::Custom(value),
         }
     }
@@ -54,9 +57,11 @@ pub enum HandlerKind {
     Stream(Arc<dyn StreamHandler>),
 }
 
+type HandlerKey = (u8, Option<String>);
+
 #[derive(Clone, Default)]
 pub struct HandlerRegistry {
-    inner: Arc<RwLock<HashMap<(u8, Option<String>), HandlerKind>>>,
+    inner: Arc<RwLock<HashMap<HandlerKey, HandlerKind>>>,
 }
 
 impl HandlerRegistry {
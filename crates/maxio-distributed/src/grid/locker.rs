@@ -0,0 +1,253 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use async_trait::async_trait;
+use maxio_common::error::{MaxioError, Result as MaxioResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dsync::{LockArgs, LockResult, NetLocker},
+    errors::{GridError, Result},
+};
+
+use super::{
+    connection::Connection,
+    handler::{HandlerID, HandlerRegistry, SingleHandler},
+    message::Flags,
+};
+
+static LOCK_MUX_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum LockOp {
+    Lock,
+    RLock,
+    Unlock,
+    RUnlock,
+    Refresh,
+    ForceUnlock,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockRequest {
+    op: LockOp,
+    args: LockArgs,
+}
+
+type LockReply = std::result::Result<LockResult, String>;
+
+/// Serves a node's own locker to its peers, so their dsync clients can
+/// count it towards quorum.
+pub struct LockHandler {
+    locker: Arc<dyn NetLocker>,
+}
+
+impl LockHandler {
+    pub fn new(locker: Arc<dyn NetLocker>) -> Self {
+        Self { locker }
+    }
+
+    pub async fn register(registry: &HandlerRegistry, locker: Arc<dyn NetLocker>) {
+        registry
+            .register_single(HandlerID::Lock, None, Arc::new(Self::new(locker)))
+            .await;
+    }
+}
+
+#[async_trait]
+impl SingleHandler for LockHandler {
+    /// Locker errors travel back in the reply; the grid only answers
+    /// requests whose handler succeeded.
+    async fn handle(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let request: LockRequest = rmp_serde::from_slice(&payload).map_err(GridError::Decode)?;
+        let args = &request.args;
+        let result = match request.op {
+            LockOp::Lock => self.locker.lock(args).await,
+            LockOp::RLock => self.locker.rlock(args).await,
+            LockOp::Unlock => self.locker.unlock(args).await,
+            LockOp::RUnlock => self.locker.runlock(args).await,
+            LockOp::Refresh => self.locker.refresh(args).await,
+            LockOp::ForceUnlock => self.locker.force_unlock(args).await,
+        };
+        let reply: LockReply = result.map_err(|err| err.to_string());
+        rmp_serde::to_vec(&reply).map_err(GridError::Encode)
+    }
+}
+
+/// A peer's locker, reached over the grid connection to that node.
+pub struct GridLocker {
+    connection: Arc<Connection>,
+}
+
+impl GridLocker {
+    pub fn new(connection: Arc<Connection>) -> Self {
+        Self { connection }
+    }
+
+    async fn call(&self, op: LockOp, args: &LockArgs) -> MaxioResult<LockResult> {
+        let request = LockRequest {
+            op,
+            args: args.clone(),
+        };
+        let payload = rmp_serde::to_vec(&request).map_err(|err| {
+            MaxioError::InternalError(format!("failed to encode lock request: {err}"))
+        })?;
+        let response = self
+            .connection
+            .request(
+                LOCK_MUX_ID.fetch_add(1, Ordering::Relaxed),
+                HandlerID::Lock.as_u8(),
+                payload,
+                Flags::NONE,
+            )
+            .await
+            .map_err(|err| {
+                MaxioError::InternalError(format!(
+                    "lock request to {} failed: {err}",
+                    self.connection.remote_addr()
+                ))
+            })?;
+        let reply: LockReply = rmp_serde::from_slice(&response.payload).map_err(|err| {
+            MaxioError::InternalError(format!("failed to decode lock reply: {err}"))
+        })?;
+        reply.map_err(|err| {
+            MaxioError::InternalError(format!(
+                "locker on {} failed: {err}",
+                self.connection.remote_addr()
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl NetLocker for GridLocker {
+    async fn lock(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::Lock, args).await
+    }
+
+    async fn rlock(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::RLock, args).await
+    }
+
+    async fn unlock(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::Unlock, args).await
+    }
+
+    async fn runlock(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::RUnlock, args).await
+    }
+
+    async fn refresh(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::Refresh, args).await
+    }
+
+    async fn force_unlock(&self, args: &LockArgs) -> MaxioResult<LockResult> {
+        self.call(LockOp::ForceUnlock, args).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::dsync::{DRWMutex, DsyncClient, LocalLocker};
+    use crate::grid::connection::{GRID_PATH, grid_accept_key, grid_auth_token};
+
+    /// Completes the websocket upgrade by hand, as the server's grid route
+    /// does, and returns the bearer token the peer presented.
+    async fn accept_upgrade(tcp: &mut TcpStream) -> Option<String> {
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            tcp.read_exact(&mut byte).await.ok()?;
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).ok()?;
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let accept = grid_accept_key(header("sec-websocket-key")?.as_bytes());
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+        );
+        tcp.write_all(response.as_bytes()).await.ok()?;
+        header("authorization")?
+            .strip_prefix("Bearer ")
+            .map(ToOwned::to_owned)
+    }
+
+    #[tokio::test]
+    async fn peer_lockers_exclude_holders_across_nodes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = grid_auth_token("access", "secret");
+        let remote = Arc::new(LocalLocker::new());
+        let serving = HandlerRegistry::new();
+        LockHandler::register(&serving, Arc::clone(&remote) as Arc<dyn NetLocker>).await;
+        let (tokens_tx, mut tokens) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut tcp, peer)) = listener.accept().await {
+                let connection = Connection::new(peer.to_string(), serving.clone());
+                let tokens_tx = tokens_tx.clone();
+                tokio::spawn(async move {
+                    let _ = tokens_tx.send(accept_upgrade(&mut tcp).await);
+                    let _ = connection.serve(tcp).await;
+                });
+            }
+        });
+
+        let dialer = Arc::new(
+            Connection::new(format!("ws://{addr}{GRID_PATH}"), HandlerRegistry::new())
+                .with_auth_token(token.clone()),
+        );
+        dialer.start().await.unwrap();
+        let local = Arc::new(LocalLocker::new());
+        let client = Arc::new(DsyncClient::new(vec![
+            Arc::clone(&local) as Arc<dyn NetLocker>,
+            Arc::new(GridLocker::new(dialer)),
+        ]));
+        let other = Arc::new(DsyncClient::new(vec![
+            Arc::new(LocalLocker::new()) as Arc<dyn NetLocker>,
+            Arc::clone(&remote) as Arc<dyn NetLocker>,
+        ]));
+
+        let held = DRWMutex::new(
+            Arc::clone(&client),
+            vec!["bucket/key".to_string()],
+            "a",
+            "test",
+        );
+        let mut locked = false;
+        for _ in 0..100 {
+            if held.lock().await.unwrap() {
+                locked = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(locked, "lock through the peer never succeeded");
+        assert_eq!(tokens.recv().await.unwrap(), Some(token));
+        assert_eq!(remote.held_locks().len(), 1);
+
+        let contender = DRWMutex::new(other, vec!["bucket/key".to_string()], "b", "test");
+        assert!(!contender.lock().await.unwrap());
+
+        held.unlock().await.unwrap();
+        assert!(remote.held_locks().is_empty());
+        assert!(contender.lock().await.unwrap());
+        contender.unlock().await.unwrap();
+    }
+}
@@ -61,6 +61,16 @@ impl Manager {
         states
     }
 
+    /// Outstanding requests per node, for backpressure metrics.
+    pub async fn list_in_flight(&self) -> HashMap<String, usize> {
+        self.connections
+            .read()
+            .await
+            .iter()
+            .map(|(addr, connection)| (addr.clone(), connection.in_flight()))
+            .collect()
+    }
+
     pub async fn request(
         &self,
         node_addr: &str,
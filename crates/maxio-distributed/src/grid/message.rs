@@ -5,6 +5,13 @@ use crate::errors::{GridError, Result};
 pub type MuxId = u32;
 pub type Seq = u32;
 
+/// Largest encoded message a peer may send. Matches the WebSocket layer's
+/// default message limit, well above the object transfer chunk size.
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// A message nests no deeper than its payload array; anything deeper is
+/// malformed and is rejected before it can exhaust the stack.
+const MAX_DECODE_DEPTH: usize = 8;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Op {
@@ -74,8 +81,24 @@ impl Message {
         rmp_serde::to_vec(self).map_err(GridError::Encode)
     }
 
+    /// Decodes a frame received from a peer. Oversized frames are refused
+    /// up front, and length fields are checked against the bytes actually
+    /// present, so a malformed frame fails instead of allocating what it
+    /// claims to carry.
     pub fn decode(raw: &[u8]) -> Result<Self> {
-        rmp_serde::from_slice(raw).map_err(GridError::Decode)
+        if raw.len() > MAX_MESSAGE_SIZE {
+            return Err(GridError::FrameTooLarge {
+                len: raw.len(),
+                max: MAX_MESSAGE_SIZE,
+            });
+        }
+        let mut deserializer = rmp_serde::Deserializer::from_read_ref(raw);
+        deserializer.set_max_depth(MAX_DECODE_DEPTH);
+        let message = Self::deserialize(&mut deserializer).map_err(GridError::Decode)?;
+        if message.flags.contains(Flags::SUBROUTE) {
+            message.extract_subroute()?;
+        }
+        Ok(message)
     }
 
     pub fn with_subroute(mut self, subroute: &str) -> Result<Self> {
@@ -98,19 +121,92 @@ impl Message {
             return Ok((None, &self.payload));
         }
 
-        if self.payload.len() < 2 {
-            return Err(GridError::InvalidSubroutePayload);
-        }
-
-        let route_len = u16::from_be_bytes([self.payload[0], self.payload[1]]) as usize;
-        if self.payload.len() < 2 + route_len {
-            return Err(GridError::InvalidSubroutePayload);
-        }
+        let (route_len, rest) = self
+            .payload
+            .split_first_chunk::<2>()
+            .ok_or(GridError::InvalidSubroutePayload)?;
+        let (route, body) = rest
+            .split_at_checked(u16::from_be_bytes(*route_len) as usize)
+            .ok_or(GridError::InvalidSubroutePayload)?;
 
-        let route = std::str::from_utf8(&self.payload[2..2 + route_len])
+        let route = std::str::from_utf8(route)
             .map_err(GridError::Utf8)?
             .to_string();
-        let body = &self.payload[2 + route_len..];
         Ok((Some(route), body))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Message {
+        Message::new(7, 42, 3, Op::Request, Flags::NONE, b"hello".to_vec())
+    }
+
+    #[test]
+    fn decode_round_trips_encoded_messages() {
+        let message = sample().with_subroute("object-transfer").unwrap();
+        let decoded = Message::decode(&message.encode().unwrap()).unwrap();
+        assert_eq!(decoded.mux_id, 7);
+        assert_eq!(decoded.seq, 42);
+        let (route, body) = decoded.extract_subroute().unwrap();
+        assert_eq!(route.as_deref(), Some("object-transfer"));
+        assert_eq!(body, b"hello");
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let encoded = sample().encode().unwrap();
+        for len in 0..encoded.len() {
+            assert!(
+                matches!(Message::decode(&encoded[..len]), Err(GridError::Decode(_))),
+                "frame truncated to {len} bytes decoded"
+            );
+        }
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected_before_decoding() {
+        let raw = vec![0u8; MAX_MESSAGE_SIZE + 1];
+        assert!(matches!(
+            Message::decode(&raw),
+            Err(GridError::FrameTooLarge { len, max: MAX_MESSAGE_SIZE }) if len == MAX_MESSAGE_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn declared_lengths_beyond_the_frame_are_rejected() {
+        let mut encoded = Message::new(1, 1, 0, Op::Request, Flags::NONE, Vec::new())
+            .encode()
+            .unwrap();
+        // The empty payload is the trailing fixarray marker.
+        assert_eq!(encoded.pop(), Some(0x90));
+        for header in [
+            [0xdd, 0xff, 0xff, 0xff, 0xff], // array 32
+            [0xc6, 0xff, 0xff, 0xff, 0xff], // bin 32
+        ] {
+            let mut raw = encoded.clone();
+            raw.extend_from_slice(&header);
+            assert!(matches!(Message::decode(&raw), Err(GridError::Decode(_))));
+        }
+    }
+
+    #[test]
+    fn subroute_lengths_beyond_the_payload_are_rejected() {
+        let mut message = sample();
+        message.flags.insert(Flags::SUBROUTE);
+        message.payload = vec![0xff, 0xff, b'a'];
+        let raw = message.encode().unwrap();
+        assert!(matches!(
+            Message::decode(&raw),
+            Err(GridError::InvalidSubroutePayload)
+        ));
+
+        message.payload = vec![0x00];
+        assert!(matches!(
+            message.extract_subroute(),
+            Err(GridError::InvalidSubroutePayload)
+        ));
+    }
+}
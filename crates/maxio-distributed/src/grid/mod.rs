@@ -1,13 +1,25 @@
 pub mod connection;
 pub mod handler;
+pub mod locker;
 pub mod manager;
 pub mod message;
+pub mod mover;
 pub mod mux;
 pub mod stream;
+pub mod transfer;
 
-pub use connection::{Connection, ConnectionState};
+pub use connection::{
+    Connection, ConnectionState, GRID_PATH, ReconnectBackoff, grid_accept_key, grid_auth_token,
+    grid_url,
+};
 pub use handler::{HandlerID, HandlerKind, HandlerRegistry, SingleHandler, StreamHandler};
+pub use locker::{GridLocker, LockHandler};
 pub use manager::Manager;
 pub use message::{Flags, Message, MuxId, Op, Seq};
+pub use mover::GridPoolMover;
 pub use mux::{MuxClient, MuxServer};
 pub use stream::Stream;
+pub use transfer::{
+    CatalogEntry, IncomingObject, ObjectTransferHandler, TRANSFER_FRAME_TIMEOUT, open_object,
+    transfer_object,
+};
@@ -0,0 +1,283 @@
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use maxio_common::error::{MaxioError, Result as MaxioResult};
+use maxio_storage::{
+    pool::{MovedObjects, PoolInfo, PoolMover},
+    traits::ObjectLayer,
+};
+
+use crate::errors::GridError;
+
+use super::{
+    connection::Connection,
+    transfer::{
+        TRANSFER_FRAME_TIMEOUT, list_remote_buckets, list_remote_objects, remove_remote_object,
+        transfer_object,
+    },
+};
+
+const MOVE_PAGE_SIZE: i32 = 1000;
+
+/// Moves objects into the pools this node stores by pulling them over the
+/// grid from the nodes storing the source pools, which must serve
+/// `ObjectTransferHandler`. Each object is removed from the source only
+/// after it was written to the target, and only if it did not change in
+/// between.
+#[derive(Clone, Default)]
+pub struct GridPoolMover {
+    local: HashMap<String, Arc<dyn ObjectLayer>>,
+    peers: HashMap<String, Arc<Connection>>,
+    frame_timeout: Option<Duration>,
+}
+
+impl GridPoolMover {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A pool stored on this node, which objects can be moved into.
+    pub fn with_local_pool(
+        mut self,
+        pool_id: impl Into<String>,
+        layer: Arc<dyn ObjectLayer>,
+    ) -> Self {
+        self.local.insert(pool_id.into(), layer);
+        self
+    }
+
+    /// The grid connection to the node storing `pool_id`, which objects can
+    /// be moved out of.
+    pub fn with_peer_pool(
+        mut self,
+        pool_id: impl Into<String>,
+        connection: Arc<Connection>,
+    ) -> Self {
+        self.peers.insert(pool_id.into(), connection);
+        self
+    }
+
+    /// How long a transfer may go without a frame; `TRANSFER_FRAME_TIMEOUT`
+    /// by default.
+    pub fn with_frame_timeout(mut self, timeout: Duration) -> Self {
+        self.frame_timeout = Some(timeout);
+        self
+    }
+}
+
+impl fmt::Debug for GridPoolMover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GridPoolMover")
+            .field("local", &self.local.keys().collect::<Vec<_>>())
+            .field("peers", &self.peers.keys().collect::<Vec<_>>())
+            .field("frame_timeout", &self.frame_timeout)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl PoolMover for GridPoolMover {
+    async fn move_objects(
+        &self,
+        source: &PoolInfo,
+        target: &PoolInfo,
+        bytes: u64,
+    ) -> MaxioResult<MovedObjects> {
+        let layer = self.local.get(&target.id).ok_or_else(|| {
+            MaxioError::InvalidArgument(format!("pool {} is not stored on this node", target.id))
+        })?;
+        let connection = self.peers.get(&source.id).ok_or_else(|| {
+            MaxioError::InvalidArgument(format!("no grid connection to pool {}", source.id))
+        })?;
+        let frame_timeout = self.frame_timeout.unwrap_or(TRANSFER_FRAME_TIMEOUT);
+        let grid_error = |err: GridError| {
+            MaxioError::InternalError(format!(
+                "moving objects from pool {} failed: {err}",
+                source.id
+            ))
+        };
+
+        let mut moved = MovedObjects::default();
+        let mut kept = 0_usize;
+        for bucket in list_remote_buckets(connection).await.map_err(grid_error)? {
+            if matches!(
+                layer.get_bucket_info(&bucket).await,
+                Err(MaxioError::BucketNotFound(_))
+            ) {
+                layer.make_bucket(&bucket).await?;
+            }
+
+            let mut marker = String::new();
+            loop {
+                let (entries, next_marker) =
+                    list_remote_objects(connection, &bucket, &marker, MOVE_PAGE_SIZE)
+                        .await
+                        .map_err(grid_error)?;
+                for entry in entries {
+                    transfer_object(
+                        connection,
+                        &bucket,
+                        &entry.key,
+                        layer.as_ref(),
+                        frame_timeout,
+                    )
+                    .await
+                    .map_err(grid_error)?;
+                    // A source object that changed since it was listed stays
+                    // where it is, and the source is not reported empty.
+                    if let Err(err) =
+                        remove_remote_object(connection, &bucket, &entry.key, &entry.etag).await
+                    {
+                        tracing::warn!(
+                            %bucket,
+                            key = %entry.key,
+                            ?err,
+                            "moved object was kept on the source pool"
+                        );
+                        kept += 1;
+                        continue;
+                    }
+                    moved.bytes = moved.bytes.saturating_add(entry.size);
+                    moved.objects = moved.objects.saturating_add(1);
+                    if moved.bytes >= bytes {
+                        return Ok(moved);
+                    }
+                }
+                match next_marker {
+                    Some(next) => marker = next,
+                    None => break,
+                }
+            }
+        }
+        if kept > 0 {
+            return Err(MaxioError::InternalError(format!(
+                "{kept} objects changed on pool {} while they were moved",
+                source.id
+            )));
+        }
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use bytes::Bytes;
+    use maxio_storage::{
+        pool::{PoolManager, PoolStatus},
+        single::SingleDiskObjectLayer,
+        traits::ListOrder,
+    };
+
+    use super::*;
+    use crate::grid::{handler::HandlerRegistry, transfer::ObjectTransferHandler};
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!(
+            "maxio-mover-{label}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    #[tokio::test]
+    async fn decommission_moves_objects_to_the_target_pool() {
+        let source_dir = temp_dir("source");
+        let target_dir = temp_dir("target");
+        let source: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(source_dir.clone())
+                .await
+                .expect("source layer"),
+        );
+        let target: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(target_dir.clone())
+                .await
+                .expect("target layer"),
+        );
+
+        let objects = [
+            ("docs", "readme.txt", vec![b'r'; 1024]),
+            ("photos", "a.jpg", vec![b'a'; 4096]),
+            ("photos", "nested/b.jpg", vec![b'b'; 2048]),
+        ];
+        for (bucket, key, data) in &objects {
+            if source.get_bucket_info(bucket).await.is_err() {
+                source.make_bucket(bucket).await.expect("source bucket");
+            }
+            source
+                .put_object(
+                    bucket,
+                    key,
+                    Bytes::from(data.clone()),
+                    Some("image/jpeg"),
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("seed object");
+        }
+
+        let serving = HandlerRegistry::new();
+        ObjectTransferHandler::register(&serving, Arc::clone(&source)).await;
+        let source_node = Connection::new("source".to_string(), serving);
+        let target_node = Arc::new(Connection::new(
+            "target".to_string(),
+            HandlerRegistry::new(),
+        ));
+        target_node
+            .connect_in_process(&source_node)
+            .await
+            .expect("connect nodes");
+
+        let mover = GridPoolMover::new()
+            .with_local_pool("target", Arc::clone(&target))
+            .with_peer_pool("source", target_node)
+            .with_frame_timeout(Duration::from_secs(5));
+        let manager = PoolManager::new().with_mover(Arc::new(mover));
+        manager
+            .add_pool("source", vec!["http://source/disk".to_string()], 1 << 30)
+            .await
+            .expect("add source pool");
+        manager
+            .add_pool("target", vec!["http://target/disk".to_string()], 1 << 30)
+            .await
+            .expect("add target pool");
+
+        let status = manager
+            .start_decommission("source")
+            .await
+            .expect("decommission");
+        assert_eq!(status.progress, 100);
+        assert_eq!(status.objects_moved, 3);
+        assert_eq!(status.bytes_moved, 1024 + 4096 + 2048);
+        assert_eq!(
+            manager.get_pool_info("source").await.unwrap().status,
+            PoolStatus::Decommissioned
+        );
+
+        for (bucket, key, data) in &objects {
+            let (info, stored) = target
+                .get_object(bucket, key, None)
+                .await
+                .expect("moved object");
+            assert_eq!(stored.as_ref(), data.as_slice());
+            assert_eq!(info.content_type, "image/jpeg");
+            let left = source
+                .list_objects(bucket, "", "", "", 1000, ListOrder::KeyAscending)
+                .await
+                .expect("list source");
+            assert!(left.objects.is_empty(), "{bucket} kept {:?}", left.objects);
+        }
+
+        let _ = std::fs::remove_dir_all(source_dir);
+        let _ = std::fs::remove_dir_all(target_dir);
+    }
+}
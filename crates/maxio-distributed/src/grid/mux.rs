@@ -1,7 +1,7 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use tokio::{
-    sync::{RwLock, mpsc, oneshot},
+    sync::{OwnedSemaphorePermit, RwLock, Semaphore, mpsc, oneshot},
     time,
 };
 
@@ -13,12 +13,20 @@ use super::{
     stream::Stream,
 };
 
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 512;
+const STREAM_RESPONSE_BUFFER: usize = 64;
+
+type StreamWaiter = (mpsc::Sender<Vec<u8>>, OwnedSemaphorePermit);
+
 #[derive(Clone)]
 pub struct MuxClient {
     tx: mpsc::Sender<Message>,
     next_seq: Arc<std::sync::atomic::AtomicU32>,
     pending: Arc<RwLock<HashMap<Seq, oneshot::Sender<Message>>>>,
+    streams: Arc<RwLock<HashMap<Seq, StreamWaiter>>>,
     timeout: Duration,
+    max_in_flight: usize,
+    in_flight: Arc<Semaphore>,
 }
 
 impl MuxClient {
@@ -27,10 +35,31 @@ impl MuxClient {
             tx,
             next_seq: Arc::new(std::sync::atomic::AtomicU32::new(1)),
             pending: Arc::new(RwLock::new(HashMap::new())),
+            streams: Arc::new(RwLock::new(HashMap::new())),
             timeout,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            in_flight: Arc::new(Semaphore::new(DEFAULT_MAX_IN_FLIGHT)),
         }
     }
 
+    /// Requests beyond `limit` fail fast with `TooManyRequests` instead of
+    /// queueing behind a slow peer.
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        let limit = limit.max(1);
+        self.max_in_flight = limit;
+        self.in_flight = Arc::new(Semaphore::new(limit));
+        self
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.max_in_flight
+            .saturating_sub(self.in_flight.available_permits())
+    }
+
     pub async fn request(
         &self,
         mux_id: MuxId,
@@ -38,6 +67,12 @@ impl MuxClient {
         payload: Vec<u8>,
         flags: Flags,
     ) -> Result<Message> {
+        let _permit = self
+            .in_flight
+            .try_acquire()
+            .map_err(|_| GridError::TooManyRequests {
+                limit: self.max_in_flight,
+            })?;
         let seq = self
             .next_seq
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -63,7 +98,58 @@ impl MuxClient {
         }
     }
 
+    /// Opens a streaming request. Response payloads arrive on the returned
+    /// channel in order; it closes after the EOF frame or when the connection
+    /// fails. The open stream holds an in-flight slot until then.
+    pub async fn open_stream(
+        &self,
+        mux_id: MuxId,
+        handler: u8,
+        subroute: Option<&str>,
+        payload: Vec<u8>,
+    ) -> Result<mpsc::Receiver<Vec<u8>>> {
+        let permit = Arc::clone(&self.in_flight)
+            .try_acquire_owned()
+            .map_err(|_| GridError::TooManyRequests {
+                limit: self.max_in_flight,
+            })?;
+        let seq = self
+            .next_seq
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let mut message = Message::new(mux_id, seq, handler, Op::Request, Flags::NONE, payload);
+        if let Some(subroute) = subroute {
+            message = message.with_subroute(subroute)?;
+        }
+
+        let (tx, rx) = mpsc::channel(STREAM_RESPONSE_BUFFER);
+        self.streams.write().await.insert(seq, (tx, permit));
+
+        if let Err(_send_err) = self.tx.send(message).await {
+            self.streams.write().await.remove(&seq);
+            return Err(GridError::ConnectionClosed);
+        }
+
+        Ok(rx)
+    }
+
     pub async fn handle_response(&self, message: Message) -> Result<()> {
+        let stream = self
+            .streams
+            .read()
+            .await
+            .get(&message.seq)
+            .map(|(tx, _)| tx.clone());
+        if let Some(stream) = stream {
+            let eof = message.flags.contains(Flags::EOF);
+            if eof {
+                self.streams.write().await.remove(&message.seq);
+            }
+            if !message.payload.is_empty() && stream.send(message.payload).await.is_err() {
+                self.streams.write().await.remove(&message.seq);
+            }
+            return Ok(());
+        }
+
         let tx = self.pending.write().await.remove(&message.seq);
         match tx {
             Some(waiter) => waiter
@@ -76,6 +162,7 @@ impl MuxClient {
     pub async fn fail_all(&self, err: &GridError) {
         let mut pending = self.pending.write().await;
         pending.clear();
+        self.streams.write().await.clear();
         tracing::debug!(?err, "mux pending requests cleared");
     }
 }
@@ -185,3 +272,36 @@ impl MuxServer {
         self.stream_incoming.write().await.remove(&mux_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_beyond_in_flight_limit_are_rejected() {
+        let (tx, _rx) = mpsc::channel(16);
+        let client = MuxClient::new(tx, Duration::from_secs(30)).with_max_in_flight(2);
+
+        let waiting = (0..2)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.request(1, 1, Vec::new(), Flags::NONE).await })
+            })
+            .collect::<Vec<_>>();
+        while client.in_flight() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let err = client
+            .request(1, 1, Vec::new(), Flags::NONE)
+            .await
+            .expect_err("limit exceeded");
+        assert!(matches!(err, GridError::TooManyRequests { limit: 2 }));
+
+        for task in waiting {
+            task.abort();
+            let _ = task.await;
+        }
+        assert_eq!(client.in_flight(), 0);
+    }
+}
@@ -0,0 +1,738 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use maxio_common::types::ObjectInfo;
+use maxio_storage::traits::{CompletePart, DeleteCondition, ListOrder, ObjectLayer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{sync::mpsc, time};
+
+use crate::errors::{GridError, Result};
+
+use super::{
+    connection::Connection,
+    handler::{HandlerID, HandlerRegistry, SingleHandler, StreamHandler},
+    message::Flags,
+    stream::Stream,
+};
+
+pub const OBJECT_TRANSFER_SUBROUTE: &str = "object-transfer";
+pub const TRANSFER_CHUNK_SIZE: usize = 1024 * 1024;
+/// How long a receiver waits for the next frame before giving up on the
+/// sender.
+pub const TRANSFER_FRAME_TIMEOUT: Duration = Duration::from_secs(30);
+/// Objects larger than this reach the target as a multipart upload of parts
+/// this size, so a receiver holds at most one part in memory.
+pub const TRANSFER_PART_SIZE: usize = 8 * 1024 * 1024;
+
+const FRAME_HEADER: u8 = 0;
+const FRAME_DATA: u8 = 1;
+const FRAME_TRAILER: u8 = 2;
+const FRAME_ERROR: u8 = 3;
+
+static TRANSFER_MUX_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TransferRequest {
+    bucket: String,
+    key: String,
+}
+
+/// Serves objects from the local layer to peers over a grid stream. The
+/// stream carries a header with the object info, data frames of at most
+/// `TRANSFER_CHUNK_SIZE` bytes and a trailer with the SHA-256 of the data;
+/// the bounded stream channels provide flow control.
+pub struct ObjectTransferHandler {
+    layer: Arc<dyn ObjectLayer>,
+}
+
+impl ObjectTransferHandler {
+    pub fn new(layer: Arc<dyn ObjectLayer>) -> Self {
+        Self { layer }
+    }
+
+    /// Registers the transfer stream along with the catalog requests pool
+    /// movers use to list and remove the objects they move.
+    pub async fn register(registry: &HandlerRegistry, layer: Arc<dyn ObjectLayer>) {
+        registry
+            .register_stream(
+                HandlerID::Storage,
+                Some(OBJECT_TRANSFER_SUBROUTE.to_string()),
+                Arc::new(Self::new(Arc::clone(&layer))),
+            )
+            .await;
+        registry
+            .register_single(
+                HandlerID::Storage,
+                None,
+                Arc::new(ObjectCatalogHandler { layer }),
+            )
+            .await;
+    }
+}
+
+#[async_trait]
+impl StreamHandler for ObjectTransferHandler {
+    async fn open(&self, stream: Stream, initial_payload: Vec<u8>) -> Result<()> {
+        let request: TransferRequest =
+            rmp_serde::from_slice(&initial_payload).map_err(GridError::Decode)?;
+        let layer = Arc::clone(&self.layer);
+
+        // Run the transfer off the connection dispatcher so other requests on
+        // this connection keep flowing while the object is sent.
+        tokio::spawn(async move {
+            if let Err(err) = send_object(layer.as_ref(), &stream, &request).await {
+                tracing::warn!(
+                    bucket = %request.bucket,
+                    key = %request.key,
+                    ?err,
+                    "object transfer failed"
+                );
+                let _ = stream
+                    .send(frame(FRAME_ERROR, err.to_string().as_bytes()))
+                    .await;
+            }
+            stream.close();
+        });
+
+        Ok(())
+    }
+}
+
+async fn send_object(
+    layer: &dyn ObjectLayer,
+    stream: &Stream,
+    request: &TransferRequest,
+) -> Result<()> {
+    let (info, data) = layer
+        .get_object(&request.bucket, &request.key, None)
+        .await
+        .map_err(|err| GridError::Transfer(err.to_string()))?;
+
+    let header = rmp_serde::to_vec(&info).map_err(GridError::Encode)?;
+    stream.send(frame(FRAME_HEADER, &header)).await?;
+
+    let mut hasher = Sha256::new();
+    for chunk in data.chunks(TRANSFER_CHUNK_SIZE) {
+        hasher.update(chunk);
+        stream.send(frame(FRAME_DATA, chunk)).await?;
+    }
+
+    stream
+        .send(frame(FRAME_TRAILER, hasher.finalize().as_slice()))
+        .await
+}
+
+/// An object arriving from a peer, read one data frame at a time.
+pub struct IncomingObject {
+    bucket: String,
+    key: String,
+    info: ObjectInfo,
+    frames: mpsc::Receiver<Vec<u8>>,
+    frame_timeout: Duration,
+    hasher: Sha256,
+    received: u64,
+    finished: bool,
+}
+
+impl IncomingObject {
+    pub fn info(&self) -> &ObjectInfo {
+        &self.info
+    }
+
+    /// The next chunk of data, or `None` once the trailer has confirmed the
+    /// object's size and SHA-256. Fails when no frame arrives within the
+    /// frame timeout.
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        loop {
+            let raw = Bytes::from(self.next_frame().await?);
+            let Some(&kind) = raw.first() else {
+                continue;
+            };
+            let body = raw.slice(1..);
+
+            match kind {
+                FRAME_DATA => {
+                    self.hasher.update(&body);
+                    self.received = self.received.saturating_add(body.len() as u64);
+                    if self.received > self.expected_size() {
+                        return Err(GridError::Transfer(format!(
+                            "received more than the {} bytes of {}/{}",
+                            self.info.size, self.bucket, self.key
+                        )));
+                    }
+                    return Ok(Some(body));
+                }
+                FRAME_TRAILER => {
+                    if self.received != self.expected_size() {
+                        return Err(GridError::Transfer(format!(
+                            "received {} bytes, expected {}",
+                            self.received, self.info.size
+                        )));
+                    }
+                    if std::mem::take(&mut self.hasher).finalize().as_slice() != body {
+                        return Err(GridError::ChecksumMismatch {
+                            bucket: self.bucket.clone(),
+                            key: self.key.clone(),
+                        });
+                    }
+                    self.finished = true;
+                    return Ok(None);
+                }
+                other => return Err(unexpected_frame(other, &body)),
+            }
+        }
+    }
+
+    async fn next_frame(&mut self) -> Result<Vec<u8>> {
+        next_frame(
+            &mut self.frames,
+            self.frame_timeout,
+            &self.bucket,
+            &self.key,
+        )
+        .await
+    }
+
+    fn expected_size(&self) -> u64 {
+        u64::try_from(self.info.size).unwrap_or(0)
+    }
+}
+
+/// Opens a transfer of an object from a peer serving
+/// `ObjectTransferHandler` and waits for its header.
+pub async fn open_object(
+    connection: &Connection,
+    bucket: &str,
+    key: &str,
+    frame_timeout: Duration,
+) -> Result<IncomingObject> {
+    let request = TransferRequest {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+    };
+    let payload = rmp_serde::to_vec(&request).map_err(GridError::Encode)?;
+    let mut frames = connection
+        .open_stream(
+            TRANSFER_MUX_ID.fetch_add(1, Ordering::Relaxed),
+            HandlerID::Storage.as_u8(),
+            Some(OBJECT_TRANSFER_SUBROUTE),
+            payload,
+        )
+        .await?;
+
+    loop {
+        let raw = next_frame(&mut frames, frame_timeout, bucket, key).await?;
+        let Some((&kind, body)) = raw.split_first() else {
+            continue;
+        };
+        if kind != FRAME_HEADER {
+            return Err(unexpected_frame(kind, body));
+        }
+        return Ok(IncomingObject {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            info: rmp_serde::from_slice(body).map_err(GridError::Decode)?,
+            frames,
+            frame_timeout,
+            hasher: Sha256::new(),
+            received: 0,
+            finished: false,
+        });
+    }
+}
+
+/// Copies an object from a peer into `target`, keeping its content type and
+/// user metadata. This is the data path for moving objects between pools on
+/// different nodes. Objects above `TRANSFER_PART_SIZE` are written part by
+/// part and the upload is aborted if the transfer fails.
+pub async fn transfer_object(
+    connection: &Connection,
+    bucket: &str,
+    key: &str,
+    target: &dyn ObjectLayer,
+    frame_timeout: Duration,
+) -> Result<ObjectInfo> {
+    let mut incoming = open_object(connection, bucket, key, frame_timeout).await?;
+    let info = incoming.info().clone();
+    let content_type = Some(info.content_type.as_str());
+
+    if incoming.expected_size() <= TRANSFER_PART_SIZE as u64 {
+        let mut data = BytesMut::new();
+        while let Some(chunk) = incoming.next_chunk().await? {
+            data.extend_from_slice(&chunk);
+        }
+        return target
+            .put_object(
+                bucket,
+                key,
+                data.freeze(),
+                content_type,
+                info.metadata,
+                None,
+            )
+            .await
+            .map_err(|err| GridError::Transfer(err.to_string()));
+    }
+
+    let upload_id = target
+        .create_multipart_upload(bucket, key, content_type, info.metadata.clone())
+        .await
+        .map_err(|err| GridError::Transfer(err.to_string()))?;
+    let written = match write_parts(&mut incoming, target, &upload_id).await {
+        Ok(parts) => target
+            .complete_multipart_upload(bucket, key, &upload_id, parts)
+            .await
+            .map_err(|err| GridError::Transfer(err.to_string())),
+        Err(err) => Err(err),
+    };
+    if written.is_err() {
+        let _ = target
+            .abort_multipart_upload(bucket, key, &upload_id)
+            .await
+            .map_err(|err| tracing::debug!(?err, "aborting failed transfer upload failed"));
+    }
+    written
+}
+
+async fn write_parts(
+    incoming: &mut IncomingObject,
+    target: &dyn ObjectLayer,
+    upload_id: &str,
+) -> Result<Vec<CompletePart>> {
+    let bucket = incoming.bucket.clone();
+    let key = incoming.key.clone();
+    let mut parts = Vec::new();
+    let mut pending = BytesMut::with_capacity(TRANSFER_PART_SIZE);
+
+    loop {
+        let chunk = incoming.next_chunk().await?;
+        let last = chunk.is_none();
+        if let Some(chunk) = chunk {
+            pending.extend_from_slice(&chunk);
+        }
+
+        while pending.len() >= TRANSFER_PART_SIZE || (last && !pending.is_empty()) {
+            let take = pending.len().min(TRANSFER_PART_SIZE);
+            let part_number = i32::try_from(parts.len() + 1)
+                .map_err(|_| GridError::Transfer(format!("too many parts for {bucket}/{key}")))?;
+            let etag = target
+                .upload_part(
+                    &bucket,
+                    &key,
+                    upload_id,
+                    part_number,
+                    pending.split_to(take).freeze(),
+                )
+                .await
+                .map_err(|err| GridError::Transfer(err.to_string()))?;
+            parts.push(CompletePart { part_number, etag });
+        }
+
+        if last {
+            return Ok(parts);
+        }
+    }
+}
+
+async fn next_frame(
+    frames: &mut mpsc::Receiver<Vec<u8>>,
+    frame_timeout: Duration,
+    bucket: &str,
+    key: &str,
+) -> Result<Vec<u8>> {
+    match time::timeout(frame_timeout, frames.recv()).await {
+        Ok(Some(raw)) => Ok(raw),
+        Ok(None) => Err(GridError::Transfer(format!(
+            "stream for {bucket}/{key} ended before trailer"
+        ))),
+        Err(_) => Err(GridError::TransferStalled {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            timeout: frame_timeout,
+        }),
+    }
+}
+
+fn unexpected_frame(kind: u8, body: &[u8]) -> GridError {
+    match kind {
+        FRAME_ERROR => GridError::Transfer(String::from_utf8_lossy(body).into_owned()),
+        FRAME_HEADER => GridError::Transfer("duplicate header frame".to_string()),
+        FRAME_DATA | FRAME_TRAILER => {
+            GridError::Transfer("frame received before header".to_string())
+        }
+        other => GridError::Transfer(format!("unknown transfer frame type {other}")),
+    }
+}
+
+fn frame(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + body.len());
+    out.push(kind);
+    out.extend_from_slice(body);
+    out
+}
+
+/// One object as listed by [`ObjectCatalogHandler`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub key: String,
+    pub size: u64,
+    pub etag: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum CatalogRequest {
+    ListBuckets,
+    ListObjects {
+        bucket: String,
+        marker: String,
+        max_keys: i32,
+    },
+    Remove {
+        bucket: String,
+        key: String,
+        etag: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum CatalogReply {
+    Buckets(Vec<String>),
+    Objects {
+        entries: Vec<CatalogEntry>,
+        next_marker: Option<String>,
+    },
+    Removed,
+}
+
+type CatalogResult = std::result::Result<CatalogReply, String>;
+
+static CATALOG_MUX_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Lists and removes objects of the local layer for peers moving them into
+/// their own pools.
+struct ObjectCatalogHandler {
+    layer: Arc<dyn ObjectLayer>,
+}
+
+#[async_trait]
+impl SingleHandler for ObjectCatalogHandler {
+    /// Layer errors travel back in the reply, as for the lock handler.
+    async fn handle(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let request: CatalogRequest = rmp_serde::from_slice(&payload).map_err(GridError::Decode)?;
+        let reply: CatalogResult = match request {
+            CatalogRequest::ListBuckets => self.layer.list_buckets().await.map(|buckets| {
+                CatalogReply::Buckets(buckets.into_iter().map(|bucket| bucket.name).collect())
+            }),
+            CatalogRequest::ListObjects {
+                bucket,
+                marker,
+                max_keys,
+            } => self
+                .layer
+                .list_objects(&bucket, "", &marker, "", max_keys, ListOrder::KeyAscending)
+                .await
+                .map(|listed| {
+                    let entries = listed
+                        .objects
+                        .into_iter()
+                        .map(|info| CatalogEntry {
+                            key: info.key,
+                            size: u64::try_from(info.size).unwrap_or(0),
+                            etag: info.etag,
+                        })
+                        .collect::<Vec<_>>();
+                    let next_marker = listed
+                        .is_truncated
+                        .then(|| entries.last().map(|entry| entry.key.clone()))
+                        .flatten();
+                    CatalogReply::Objects {
+                        entries,
+                        next_marker,
+                    }
+                }),
+            CatalogRequest::Remove { bucket, key, etag } => self
+                .layer
+                .delete_object_if(&bucket, &key, &DeleteCondition::ETag(etag))
+                .await
+                .map(|_| CatalogReply::Removed),
+        }
+        .map_err(|err| err.to_string());
+        rmp_serde::to_vec(&reply).map_err(GridError::Encode)
+    }
+}
+
+async fn catalog_call(connection: &Connection, request: CatalogRequest) -> Result<CatalogReply> {
+    let payload = rmp_serde::to_vec(&request).map_err(GridError::Encode)?;
+    let response = connection
+        .request(
+            CATALOG_MUX_ID.fetch_add(1, Ordering::Relaxed),
+            HandlerID::Storage.as_u8(),
+            payload,
+            Flags::NONE,
+        )
+        .await?;
+    let reply: CatalogResult =
+        rmp_serde::from_slice(&response.payload).map_err(GridError::Decode)?;
+    reply.map_err(GridError::Transfer)
+}
+
+/// Names the buckets of the peer's layer.
+pub async fn list_remote_buckets(connection: &Connection) -> Result<Vec<String>> {
+    match catalog_call(connection, CatalogRequest::ListBuckets).await? {
+        CatalogReply::Buckets(buckets) => Ok(buckets),
+        other => Err(unexpected_reply(&other)),
+    }
+}
+
+/// Lists up to `max_keys` objects of a peer's bucket after `marker`,
+/// returning the marker of the next page when there is one.
+pub async fn list_remote_objects(
+    connection: &Connection,
+    bucket: &str,
+    marker: &str,
+    max_keys: i32,
+) -> Result<(Vec<CatalogEntry>, Option<String>)> {
+    let request = CatalogRequest::ListObjects {
+        bucket: bucket.to_string(),
+        marker: marker.to_string(),
+        max_keys,
+    };
+    match catalog_call(connection, request).await? {
+        CatalogReply::Objects {
+            entries,
+            next_marker,
+        } => Ok((entries, next_marker)),
+        other => Err(unexpected_reply(&other)),
+    }
+}
+
+/// Removes an object from the peer's layer unless it changed since it was
+/// listed with `etag`.
+pub async fn remove_remote_object(
+    connection: &Connection,
+    bucket: &str,
+    key: &str,
+    etag: &str,
+) -> Result<()> {
+    let request = CatalogRequest::Remove {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        etag: etag.to_string(),
+    };
+    match catalog_call(connection, request).await? {
+        CatalogReply::Removed => Ok(()),
+        other => Err(unexpected_reply(&other)),
+    }
+}
+
+fn unexpected_reply(reply: &CatalogReply) -> GridError {
+    GridError::Transfer(format!("unexpected catalog reply {reply:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use maxio_storage::single::SingleDiskObjectLayer;
+
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!(
+            "maxio-transfer-{label}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    /// Sends the header of an object larger than one part and a single data
+    /// frame, then goes quiet without closing the stream.
+    struct StalledSender;
+
+    #[async_trait]
+    impl StreamHandler for StalledSender {
+        async fn open(&self, stream: Stream, _initial_payload: Vec<u8>) -> Result<()> {
+            let info = ObjectInfo {
+                bucket: "bucket".to_string(),
+                key: "stalled.bin".to_string(),
+                size: (TRANSFER_PART_SIZE * 2) as i64,
+                etag: String::new(),
+                content_type: "application/octet-stream".to_string(),
+                last_modified: chrono::Utc::now(),
+                metadata: HashMap::new(),
+                version_id: None,
+                encryption: None,
+                storage_class: None,
+            };
+            tokio::spawn(async move {
+                let header = rmp_serde::to_vec(&info).expect("encode header");
+                let _ = stream.send(frame(FRAME_HEADER, &header)).await;
+                let _ = stream
+                    .send(frame(FRAME_DATA, &vec![7u8; TRANSFER_PART_SIZE]))
+                    .await;
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                drop(stream);
+            });
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn transfers_multi_part_object_between_nodes() {
+        let source_dir = temp_dir("source");
+        let target_dir = temp_dir("target");
+        let source: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(source_dir.clone())
+                .await
+                .expect("source layer"),
+        );
+        let target = SingleDiskObjectLayer::new(target_dir.clone())
+            .await
+            .expect("target layer");
+
+        let payload = (0..2 * TRANSFER_PART_SIZE + 123)
+            .map(|index: usize| (index.wrapping_mul(31) % 251) as u8)
+            .collect::<Vec<_>>();
+        source.make_bucket("bucket").await.expect("source bucket");
+        target.make_bucket("bucket").await.expect("target bucket");
+        source
+            .put_object(
+                "bucket",
+                "large.bin",
+                Bytes::from(payload.clone()),
+                Some("application/octet-stream"),
+                HashMap::from([("x-amz-meta-origin".to_string(), "pool-a".to_string())]),
+                None,
+            )
+            .await
+            .expect("seed object");
+
+        let serving = HandlerRegistry::new();
+        ObjectTransferHandler::register(&serving, Arc::clone(&source)).await;
+        let source_node = Connection::new("source".to_string(), serving);
+        let target_node = Connection::new("target".to_string(), HandlerRegistry::new());
+        target_node
+            .connect_in_process(&source_node)
+            .await
+            .expect("connect nodes");
+
+        let mut incoming = open_object(&target_node, "bucket", "large.bin", TRANSFER_FRAME_TIMEOUT)
+            .await
+            .expect("open object");
+        assert_eq!(incoming.info().size, payload.len() as i64);
+        let mut hasher = Sha256::new();
+        let mut largest_chunk = 0;
+        while let Some(chunk) = incoming.next_chunk().await.expect("read chunk") {
+            largest_chunk = largest_chunk.max(chunk.len());
+            hasher.update(&chunk);
+        }
+        assert!(largest_chunk <= TRANSFER_CHUNK_SIZE);
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            hex::encode(Sha256::digest(&payload))
+        );
+
+        let info = transfer_object(
+            &target_node,
+            "bucket",
+            "large.bin",
+            &target,
+            TRANSFER_FRAME_TIMEOUT,
+        )
+        .await
+        .expect("transfer object");
+        assert_eq!(info.size, payload.len() as i64);
+        assert!(info.etag.ends_with("-3"), "written in parts: {}", info.etag);
+        let (stored, data) = target
+            .get_object("bucket", "large.bin", None)
+            .await
+            .expect("read transferred object");
+        assert_eq!(data.as_ref(), payload.as_slice());
+        assert_eq!(stored.content_type, "application/octet-stream");
+        assert_eq!(
+            stored.metadata.get("x-amz-meta-origin").map(String::as_str),
+            Some("pool-a")
+        );
+
+        let missing = open_object(
+            &target_node,
+            "bucket",
+            "missing.bin",
+            TRANSFER_FRAME_TIMEOUT,
+        )
+        .await;
+        assert!(matches!(missing, Err(GridError::Transfer(_))));
+
+        let _ = std::fs::remove_dir_all(source_dir);
+        let _ = std::fs::remove_dir_all(target_dir);
+    }
+
+    #[tokio::test]
+    async fn stalled_transfer_times_out_and_aborts_the_upload() {
+        let target_dir = temp_dir("stalled");
+        let target = SingleDiskObjectLayer::new(target_dir.clone())
+            .await
+            .expect("target layer");
+        target.make_bucket("bucket").await.expect("target bucket");
+
+        let serving = HandlerRegistry::new();
+        serving
+            .register_stream(
+                HandlerID::Storage,
+                Some(OBJECT_TRANSFER_SUBROUTE.to_string()),
+                Arc::new(StalledSender),
+            )
+            .await;
+        let source_node = Connection::new("source".to_string(), serving);
+        let target_node = Connection::new("target".to_string(), HandlerRegistry::new());
+        target_node
+            .connect_in_process(&source_node)
+            .await
+            .expect("connect nodes");
+
+        let result = transfer_object(
+            &target_node,
+            "bucket",
+            "stalled.bin",
+            &target,
+            Duration::from_millis(200),
+        )
+        .await;
+        assert!(
+            matches!(result, Err(GridError::TransferStalled { .. })),
+            "{result:?}"
+        );
+        let uploads = target
+            .list_multipart_uploads("bucket", "", "", "", "", 0)
+            .await
+            .expect("list uploads");
+        assert!(uploads.uploads.is_empty());
+        assert!(
+            target
+                .get_object_info("bucket", "stalled.bin", None)
+                .await
+                .is_err()
+        );
+
+        let _ = std::fs::remove_dir_all(target_dir);
+    }
+}
@@ -4,6 +4,7 @@ use std::path::{Path, PathBuf};
 use maxio_common::error::{MaxioError, Result};
 use maxio_storage::erasure::{ErasureConfig, decode_block, encode_block};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 const META_FILE_NAME: &str = "xl.meta";
 const DATA_PART_FILE_NAME: &str = "part.1";
@@ -38,6 +39,59 @@ pub struct HealResult {
     pub items: Vec<HealResultItem>,
 }
 
+/// Outcome of auditing one object without repairing it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VerifyStatus {
+    /// Every disk holds a current, intact shard.
+    Healthy,
+    /// Some shards are missing, stale or corrupted, but enough intact ones
+    /// remain to heal the object.
+    Degraded,
+    /// Too few intact shards remain to rebuild the object.
+    Unrecoverable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub bucket: String,
+    pub object: String,
+    pub status: VerifyStatus,
+    /// State of each disk's shard, indexed like the engine's disks.
+    pub shards: Vec<HealShardState>,
+    pub error: Option<String>,
+}
+
+/// Read-only audit of the objects under a prefix.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub healthy: usize,
+    pub degraded: usize,
+    pub unrecoverable: usize,
+    pub objects: Vec<VerifyResult>,
+}
+
+impl VerifyReport {
+    pub fn push(&mut self, result: VerifyResult) {
+        match result.status {
+            VerifyStatus::Healthy => self.healthy += 1,
+            VerifyStatus::Degraded => self.degraded += 1,
+            VerifyStatus::Unrecoverable => self.unrecoverable += 1,
+        }
+        self.objects.push(result);
+    }
+
+    /// Adds the objects of another report, such as one from another
+    /// erasure set, keeping them ordered by bucket and name.
+    pub fn merge(&mut self, other: VerifyReport) {
+        for result in other.objects {
+            self.push(result);
+        }
+        self.objects.sort_by(|left, right| {
+            (&left.bucket, &left.object).cmp(&(&right.bucket, &right.object))
+        });
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HealEngine {
     disk_paths: Vec<PathBuf>,
@@ -52,6 +106,8 @@ struct ErasureMeta {
     content_type: String,
     metadata: HashMap<String, String>,
     erasure: ErasureMetaInfo,
+    #[serde(default)]
+    generation: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,6 +180,7 @@ impl HealEngine {
             data_shards: canonical_meta.erasure.data_shards,
             parity_shards: canonical_meta.erasure.parity_shards,
             block_size: canonical_meta.erasure.block_size,
+            ..ErasureConfig::default()
         };
         let block_count = object_block_count(&canonical_meta);
 
@@ -141,6 +198,7 @@ impl HealEngine {
             let mut shards = Vec::with_capacity(block_config.total_shards());
             let mut available = 0_usize;
 
+            #[allow(clippy::needless_range_loop)]
             for disk_index in 0..self.disk_paths.len() {
                 let is_canonical = match observations
                     .get(disk_index)
@@ -178,10 +236,10 @@ impl HealEngine {
             }
 
             if available < block_config.data_shards {
-                return Err(MaxioError::InternalError(format!(
-                    "insufficient shard quorum for {bucket}/{object}, block {block_index}: have {}, need {}",
-                    available, block_config.data_shards
-                )));
+                return Err(MaxioError::QuorumUnavailable {
+                    needed: block_config.data_shards,
+                    have: available,
+                });
             }
 
             let decoded = decode_block(shards, &block_config)?;
@@ -294,6 +352,137 @@ impl HealEngine {
         self.heal_bucket(bucket).await
     }
 
+    /// Audits every object under `prefix`: checks that each disk holds the
+    /// current metadata and shards that match the block checksums, without
+    /// writing anything.
+    pub async fn verify_prefix(&self, bucket: &str, prefix: &str) -> Result<VerifyReport> {
+        let mut objects = self
+            .collect_bucket_objects(bucket)
+            .await?
+            .into_iter()
+            .filter(|object| object.starts_with(prefix))
+            .collect::<Vec<_>>();
+        objects.sort_unstable();
+
+        let mut report = VerifyReport::default();
+        for object in objects {
+            report.push(self.verify_object(bucket, &object).await);
+        }
+        Ok(report)
+    }
+
+    pub async fn verify_object(&self, bucket: &str, object: &str) -> VerifyResult {
+        let observations = self.read_meta_from_all_disks(bucket, object).await;
+        let mut shards = observations
+            .iter()
+            .map(|observation| observation.state)
+            .collect::<Vec<_>>();
+        let error = self
+            .verify_shards(bucket, object, &observations, &mut shards)
+            .await
+            .err()
+            .map(|err| err.to_string());
+        let status = if error.is_some() {
+            VerifyStatus::Unrecoverable
+        } else if shards.iter().all(|state| *state == HealShardState::Healthy) {
+            VerifyStatus::Healthy
+        } else {
+            VerifyStatus::Degraded
+        };
+
+        VerifyResult {
+            bucket: bucket.to_string(),
+            object: object.to_string(),
+            status,
+            shards,
+            error,
+        }
+    }
+
+    /// Downgrades the entries of `shards` whose disk lacks an intact copy,
+    /// failing once a block can no longer be rebuilt.
+    async fn verify_shards(
+        &self,
+        bucket: &str,
+        object: &str,
+        observations: &[MetaObservation],
+        shards: &mut [HealShardState],
+    ) -> Result<()> {
+        let (canonical_meta, canonical_signature, _) = self.select_canonical_meta(observations)?;
+        let current = observations
+            .iter()
+            .map(|observation| observation.meta_signature.as_ref() == Some(&canonical_signature))
+            .collect::<Vec<_>>();
+        for (state, current) in shards.iter_mut().zip(&current) {
+            if !current {
+                degrade(state, HealShardState::Outdated);
+            }
+        }
+
+        let block_config = ErasureConfig {
+            data_shards: canonical_meta.erasure.data_shards,
+            parity_shards: canonical_meta.erasure.parity_shards,
+            block_size: canonical_meta.erasure.block_size,
+            ..ErasureConfig::default()
+        };
+        if block_config.total_shards() != self.disk_paths.len() {
+            return Err(MaxioError::InternalError(format!(
+                "metadata shard configuration mismatch for {bucket}/{object}: meta shards {}, local disks {}",
+                block_config.total_shards(),
+                self.disk_paths.len()
+            )));
+        }
+        let shard_size = block_config.shard_size()?;
+
+        for block_index in 0..object_block_count(&canonical_meta) {
+            let mut read = vec![None; self.disk_paths.len()];
+            for disk_index in (0..self.disk_paths.len()).filter(|index| current[*index]) {
+                let part_path = self.block_part_path(disk_index, bucket, object, block_index);
+                match tokio::fs::read(&part_path).await {
+                    Ok(bytes) if bytes.len() == shard_size => read[disk_index] = Some(bytes),
+                    Ok(_) => degrade(&mut shards[disk_index], HealShardState::Corrupted),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                        degrade(&mut shards[disk_index], HealShardState::Missing);
+                    }
+                    Err(_) => degrade(&mut shards[disk_index], HealShardState::Corrupted),
+                }
+            }
+
+            let available = read.iter().filter(|shard| shard.is_some()).count();
+            if available < block_config.data_shards {
+                return Err(MaxioError::QuorumUnavailable {
+                    needed: block_config.data_shards,
+                    have: available,
+                });
+            }
+
+            let block_size = expected_block_size(
+                block_index,
+                canonical_meta.erasure.total_size,
+                block_config.block_size,
+            )?;
+            let checksum = canonical_meta.erasure.block_checksums.get(block_index);
+            let block = decode_intact_block(&read, &block_config, block_size, checksum)
+                .ok_or_else(|| {
+                    MaxioError::InternalError(format!(
+                        "block {block_index} of {bucket}/{object} cannot be rebuilt from its intact shards"
+                    ))
+                })?;
+
+            let encoded = encode_block(&block, &block_config)?;
+            for (disk_index, shard) in read.iter().enumerate() {
+                if shard
+                    .as_ref()
+                    .is_some_and(|shard| *shard != encoded[disk_index])
+                {
+                    degrade(&mut shards[disk_index], HealShardState::Corrupted);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn read_meta_from_all_disks(&self, bucket: &str, object: &str) -> Vec<MetaObservation> {
         let mut observations = Vec::with_capacity(self.disk_paths.len());
 
@@ -355,10 +544,16 @@ impl HealEngine {
             }
         }
 
+        // Disks that missed a metadata write keep an older generation; the
+        // newest generation wins once it has quorum, otherwise the most
+        // common copy is reported against the quorum below.
+        let rank = |count: usize, meta: &ErasureMeta| {
+            (count >= self.erasure.data_shards, meta.generation, count)
+        };
         let mut selected: Option<(String, usize, ErasureMeta)> = None;
         for (signature, (count, meta)) in by_signature {
-            match selected {
-                Some((_, best_count, _)) if count <= best_count => {}
+            match &selected {
+                Some((_, best_count, best)) if rank(count, &meta) <= rank(*best_count, best) => {}
                 _ => {
                     selected = Some((signature, count, meta));
                 }
@@ -370,10 +565,10 @@ impl HealEngine {
         })?;
 
         if count < self.erasure.data_shards {
-            return Err(MaxioError::InternalError(format!(
-                "metadata read quorum not met: have {}, need {}",
-                count, self.erasure.data_shards
-            )));
+            return Err(MaxioError::QuorumUnavailable {
+                needed: self.erasure.data_shards,
+                have: count,
+            });
         }
 
         Ok((meta, signature, count))
@@ -449,9 +644,76 @@ impl HealEngine {
     }
 }
 
+/// Records the first problem found on a disk; later ones do not override it.
+fn degrade(state: &mut HealShardState, problem: HealShardState) {
+    if *state == HealShardState::Healthy {
+        *state = problem;
+    }
+}
+
+/// Decodes the first `block_size` bytes of a block whose checksum matches.
+/// A corrupted shard spoils the decode, so when all shards together do not
+/// match, every subset of `data_shards` shards is tried in turn.
+fn decode_intact_block(
+    shards: &[Option<Vec<u8>>],
+    config: &ErasureConfig,
+    block_size: usize,
+    checksum: Option<&String>,
+) -> Option<Vec<u8>> {
+    let decode = |shards: Vec<Option<Vec<u8>>>| {
+        let mut decoded = decode_block(shards, config).ok()?;
+        if decoded.len() < block_size {
+            return None;
+        }
+        decoded.truncate(block_size);
+        checksum
+            .is_none_or(|checksum| format!("{:x}", Sha256::digest(&decoded)) == *checksum)
+            .then_some(decoded)
+    };
+    if let Some(block) = decode(shards.to_vec()) {
+        return Some(block);
+    }
+
+    let available = (0..shards.len())
+        .filter(|index| shards[*index].is_some())
+        .collect::<Vec<_>>();
+    combinations(&available, config.data_shards)
+        .into_iter()
+        .find_map(|subset| {
+            let subset_shards = (0..shards.len())
+                .map(|index| {
+                    subset
+                        .contains(&index)
+                        .then(|| shards[index].clone())
+                        .flatten()
+                })
+                .collect();
+            decode(subset_shards)
+        })
+}
+
+fn combinations(items: &[usize], size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let Some((first, rest)) = items.split_first() else {
+        return Vec::new();
+    };
+    let mut found = combinations(rest, size - 1)
+        .into_iter()
+        .map(|mut combination| {
+            combination.insert(0, *first);
+            combination
+        })
+        .collect::<Vec<_>>();
+    found.extend(combinations(rest, size));
+    found
+}
+
 fn meta_signature(meta: &ErasureMeta) -> Option<String> {
     Some(format!(
-        "{}:{}:{}:{}:{}:{}:{}",
+        "{}:{}:{}:{}:{}:{}:{}:{}",
+        meta.generation,
         meta.version,
         meta.size,
         meta.etag,
@@ -467,10 +729,7 @@ fn object_block_count(meta: &ErasureMeta) -> usize {
         return meta.erasure.block_checksums.len();
     }
 
-    let total_size = match usize::try_from(meta.erasure.total_size) {
-        Ok(size) => size,
-        Err(_) => 0,
-    };
+    let total_size = usize::try_from(meta.erasure.total_size).unwrap_or_default();
     if total_size == 0 {
         return 1;
     }
@@ -499,3 +758,112 @@ fn path_to_object_key(path: &Path) -> String {
     let key = path.to_string_lossy().to_string();
     key.replace(std::path::MAIN_SEPARATOR, "/")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use bytes::Bytes;
+    use maxio_storage::erasure::objects::ErasureSet;
+    use maxio_storage::traits::ObjectLayer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn verify_reports_degraded_and_unrecoverable_objects() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root =
+            std::env::temp_dir().join(format!("maxio-verify-{}-{nanos}", std::process::id()));
+        let config = ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            block_size: 64,
+            ..ErasureConfig::default()
+        };
+        let disks = (0..config.total_shards())
+            .map(|index| root.join(format!("disk{index}")))
+            .collect::<Vec<_>>();
+        let set = ErasureSet::new(disks.clone(), config.clone())
+            .await
+            .expect("create erasure set");
+        set.make_bucket("bucket").await.expect("make bucket");
+        for key in [
+            "logs/healthy",
+            "logs/degraded",
+            "logs/lost",
+            "other/skipped",
+        ] {
+            set.put_object(
+                "bucket",
+                key,
+                Bytes::from(vec![7_u8; 150]),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put object");
+        }
+
+        let part = |disk: usize, key: &str, block: usize| {
+            disks[disk]
+                .join("bucket")
+                .join(key)
+                .join(format!("block_{block}"))
+                .join(DATA_PART_FILE_NAME)
+        };
+        tokio::fs::remove_file(part(1, "logs/degraded", 0))
+            .await
+            .unwrap();
+        tokio::fs::write(
+            part(3, "logs/degraded", 2),
+            b"rotten!!rotten!!rotten!!rotten!!",
+        )
+        .await
+        .unwrap();
+        for disk in 0..3 {
+            tokio::fs::remove_file(part(disk, "logs/lost", 1))
+                .await
+                .unwrap();
+        }
+
+        let engine = HealEngine::new(disks.clone(), config).unwrap();
+        let report = engine.verify_prefix("bucket", "logs/").await.unwrap();
+        let statuses = report
+            .objects
+            .iter()
+            .map(|result| (result.object.as_str(), result.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                ("logs/degraded", VerifyStatus::Degraded),
+                ("logs/healthy", VerifyStatus::Healthy),
+                ("logs/lost", VerifyStatus::Unrecoverable),
+            ]
+        );
+        assert_eq!(
+            (report.healthy, report.degraded, report.unrecoverable),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            report.objects[0].shards,
+            [
+                HealShardState::Healthy,
+                HealShardState::Missing,
+                HealShardState::Healthy,
+                HealShardState::Corrupted,
+            ]
+        );
+        assert!(report.objects[2].error.is_some());
+
+        // Auditing wrote nothing back.
+        assert!(!part(1, "logs/degraded", 0).exists());
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
@@ -3,7 +3,10 @@ pub mod mrf;
 pub mod sequence;
 pub mod tracker;
 
-pub use heal::{HealEngine, HealResult, HealResultItem, HealShardState};
+pub use heal::{
+    HealEngine, HealResult, HealResultItem, HealShardState, VerifyReport, VerifyResult,
+    VerifyStatus,
+};
 pub use mrf::{MrfQueue, PartialOperation, PartialOperationKind};
 pub use sequence::{HealSequence, HealSequenceState, HealSequenceStatus};
 pub use tracker::{HealingTracker, HealingTrackerSnapshot};
@@ -8,10 +8,15 @@ pub mod system;
 pub mod types;
 
 pub use discovery::NodeDiscovery;
-pub use dsync::{DRWMutex, DsyncClient, LockArgs, LockResult, NetLocker};
+pub use dsync::{
+    DRWMutex, DRWMutexGuard, DsyncClient, LocalLocker, LockArgs, LockEntry, LockResult, NetLocker,
+};
 pub use errors::{GridError, Result as GridResult};
 pub use grid::*;
-pub use healing::{HealEngine, HealResult, HealResultItem, HealSequence, HealingTracker, MrfQueue};
+pub use healing::{
+    HealEngine, HealResult, HealResultItem, HealSequence, HealingTracker, MrfQueue, VerifyReport,
+    VerifyResult, VerifyStatus,
+};
 pub use replication::*;
 pub use system::DistributedSys;
 pub use types::{ClusterConfig, ClusterStatus, NodeInfo, NodeStatus};
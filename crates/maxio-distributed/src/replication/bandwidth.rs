@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Window over which the effective replication throughput is measured.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Replication bandwidth caps in bytes per second. `None` or a missing
+/// target entry means that level is unthrottled.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthConfig {
+    pub global_limit: Option<u64>,
+    /// Per-target caps keyed by target ARN.
+    pub target_limits: HashMap<String, u64>,
+}
+
+/// Current throttle and measured throughput of one replication target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetBandwidth {
+    pub arn: String,
+    pub limit: Option<u64>,
+    pub bytes_per_second: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthReport {
+    pub global_limit: Option<u64>,
+    pub bytes_per_second: f64,
+    pub targets: Vec<TargetBandwidth>,
+}
+
+/// Token bucket holding at most one second of traffic. Reservations may run
+/// the bucket into debt so that objects larger than the burst still go
+/// through; the caller waits until the debt is paid back.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            tokens: rate as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.rate as f64).min(self.rate as f64);
+        self.refilled_at = now;
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate as f64)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Throughput {
+    window_start: Instant,
+    window_bytes: u64,
+    bytes_per_second: f64,
+}
+
+impl Throughput {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            window_bytes: 0,
+            bytes_per_second: 0.0,
+        }
+    }
+
+    fn record(&mut self, bytes: u64, now: Instant) {
+        self.roll(now);
+        self.window_bytes += bytes;
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= THROUGHPUT_WINDOW {
+            self.bytes_per_second = self.window_bytes as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_bytes = 0;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TargetState {
+    bucket: Option<TokenBucket>,
+    throughput: Throughput,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    global: Option<TokenBucket>,
+    throughput: Throughput,
+    targets: HashMap<String, TargetState>,
+    target_limits: HashMap<String, u64>,
+}
+
+/// Bandwidth limiter shared by every replication worker, so the caps hold
+/// for the node as a whole rather than per worker.
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    state: Mutex<LimiterState>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(config: BandwidthConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            state: Mutex::new(LimiterState {
+                global: positive(config.global_limit).map(TokenBucket::new),
+                throughput: Throughput::new(now),
+                targets: HashMap::new(),
+                target_limits: config.target_limits,
+            }),
+        }
+    }
+
+    /// Waits until `bytes` may be sent to `target_arn` under both the global
+    /// and the target's cap, and accounts them towards the throughput.
+    pub async fn throttle(&self, target_arn: &str, bytes: u64) {
+        let delay = {
+            let mut state = self.lock();
+            let now = Instant::now();
+            let global_delay = state
+                .global
+                .as_mut()
+                .map(|bucket| bucket.reserve(bytes, now))
+                .unwrap_or_default();
+            state.throughput.record(bytes, now);
+
+            let limit = positive(state.target_limits.get(target_arn).copied());
+            let target = state
+                .targets
+                .entry(target_arn.to_string())
+                .or_insert_with(|| TargetState {
+                    bucket: limit.map(TokenBucket::new),
+                    throughput: Throughput::new(now),
+                });
+            let target_delay = target
+                .bucket
+                .as_mut()
+                .map(|bucket| bucket.reserve(bytes, now))
+                .unwrap_or_default();
+            target.throughput.record(bytes, now);
+
+            global_delay.max(target_delay)
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Replaces the caps. Buckets restart full at the new rate.
+    pub fn set_config(&self, config: BandwidthConfig) {
+        let mut state = self.lock();
+        state.global = positive(config.global_limit).map(TokenBucket::new);
+        for (arn, target) in &mut state.targets {
+            target.bucket = positive(config.target_limits.get(arn).copied()).map(TokenBucket::new);
+        }
+        state.target_limits = config.target_limits;
+    }
+
+    pub fn report(&self) -> BandwidthReport {
+        let mut state = self.lock();
+        let now = Instant::now();
+        state.throughput.roll(now);
+        let global_limit = state.global.as_ref().map(|bucket| bucket.rate);
+        let bytes_per_second = state.throughput.bytes_per_second;
+
+        let mut targets = state
+            .targets
+            .iter_mut()
+            .map(|(arn, target)| {
+                target.throughput.roll(now);
+                TargetBandwidth {
+                    arn: arn.clone(),
+                    limit: target.bucket.as_ref().map(|bucket| bucket.rate),
+                    bytes_per_second: target.throughput.bytes_per_second,
+                }
+            })
+            .collect::<Vec<_>>();
+        targets.sort_by(|left, right| left.arn.cmp(&right.arn));
+
+        BandwidthReport {
+            global_limit,
+            bytes_per_second,
+            targets,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn positive(limit: Option<u64>) -> Option<u64> {
+    limit.filter(|limit| *limit > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_one_second_burst_then_charges_debt() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1_000);
+        bucket.refilled_at = start;
+
+        assert_eq!(bucket.reserve(1_000, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        assert_eq!(
+            bucket.reserve(0, start + Duration::from_millis(500)),
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn target_limit_applies_only_to_its_target() {
+        let limiter = BandwidthLimiter::new(BandwidthConfig {
+            global_limit: None,
+            target_limits: HashMap::from([("slow".to_string(), 1_000)]),
+        });
+
+        let started = Instant::now();
+        limiter.throttle("fast", 10_000).await;
+        limiter.throttle("slow", 1_000).await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        let report = limiter.report();
+        assert_eq!(report.global_limit, None);
+        let limits = report
+            .targets
+            .iter()
+            .map(|target| (target.arn.as_str(), target.limit))
+            .collect::<Vec<_>>();
+        assert_eq!(limits, vec![("fast", None), ("slow", Some(1_000))]);
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use maxio_common::error::{MaxioError, Result};
+use serde::Serialize;
+use tower::util::MapResponseLayer;
+
+pub const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 64;
+
+/// How the replication client talks to destinations. One client is shared
+/// by every worker, so its idle connections are reused across objects.
+#[derive(Debug, Clone)]
+pub struct ReplicationClientConfig {
+    pub request_timeout: Duration,
+    /// Idle connections kept open to each destination.
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    /// TCP and HTTP/2 keep-alive ping interval.
+    pub keep_alive_interval: Duration,
+    /// Speaks HTTP/2 to plain `http://` destinations without negotiating.
+    /// TLS destinations agree on HTTP/2 through ALPN either way.
+    pub http2_prior_knowledge: bool,
+}
+
+impl Default for ReplicationClientConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            pool_max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            pool_idle_timeout: Duration::from_secs(90),
+            keep_alive_interval: Duration::from_secs(30),
+            http2_prior_knowledge: false,
+        }
+    }
+}
+
+impl ReplicationClientConfig {
+    /// Builds a client whose new connections are counted in `connections`.
+    pub fn build(&self, connections: Arc<ConnectionStats>) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.request_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.keep_alive_interval)
+            .http2_keep_alive_interval(self.keep_alive_interval)
+            .http2_keep_alive_while_idle(true)
+            .http2_adaptive_window(true)
+            .connector_layer(MapResponseLayer::new(move |conn| {
+                connections.opened.fetch_add(1, Ordering::Relaxed);
+                conn
+            }));
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder.build().map_err(|err| {
+            MaxioError::InternalError(format!("failed to create replication client: {err}"))
+        })
+    }
+}
+
+/// Counts replication requests against the connections opened to carry
+/// them; every request beyond the connections opened rode a pooled one.
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    requests: AtomicU64,
+    opened: AtomicU64,
+}
+
+/// Point-in-time view of a [`ConnectionStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionMetrics {
+    pub requests: u64,
+    pub connections_opened: u64,
+    pub connections_reused: u64,
+}
+
+impl ConnectionStats {
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn metrics(&self) -> ConnectionMetrics {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let connections_opened = self.opened.load(Ordering::Relaxed);
+        ConnectionMetrics {
+            requests,
+            connections_opened,
+            connections_reused: requests.saturating_sub(connections_opened),
+        }
+    }
+}
@@ -71,3 +71,23 @@ impl ReplicationConfig {
             .filter(|rule| rule.status == RuleStatus::Enabled)
     }
 }
+
+impl ReplicationRule {
+    /// Whether the rule's prefix filter covers `key`.
+    pub fn matches(&self, key: &str) -> bool {
+        self.filter
+            .as_ref()
+            .and_then(|filter| filter.prefix.as_deref())
+            .is_none_or(|prefix| key.starts_with(prefix))
+    }
+}
+
+impl ReplicationDestination {
+    /// The destination bucket's name; configurations usually give it as an
+    /// `arn:aws:s3:::` ARN.
+    pub fn bucket_name(&self) -> &str {
+        self.bucket
+            .strip_prefix("arn:aws:s3:::")
+            .unwrap_or(&self.bucket)
+    }
+}
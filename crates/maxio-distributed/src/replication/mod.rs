@@ -1,3 +1,5 @@
+pub mod bandwidth;
+pub mod client;
 pub mod config;
 pub mod mrf;
 pub mod pool;
@@ -5,15 +7,19 @@ pub mod state;
 pub mod types;
 pub mod worker;
 
+pub use bandwidth::{BandwidthConfig, BandwidthLimiter, BandwidthReport, TargetBandwidth};
+pub use client::{
+    ConnectionMetrics, ConnectionStats, DEFAULT_POOL_MAX_IDLE_PER_HOST, ReplicationClientConfig,
+};
 pub use config::{
     ReplicationConfig, ReplicationDestination, ReplicationFilter, ReplicationRule, RuleStatus,
 };
 pub use mrf::{DEFAULT_MRF_CAPACITY, DEFAULT_MRF_RETRY_LIMIT, MrfEntry, MrfQueue};
 pub use pool::{
     DEFAULT_LARGE_OBJECT_THRESHOLD, DEFAULT_LARGE_WORKERS, DEFAULT_MRF_WORKERS,
-    DEFAULT_NORMAL_WORKERS, ReplicationPool, ReplicationPoolConfig,
+    DEFAULT_NORMAL_WORKERS, ReplicationPool, ReplicationPoolConfig, ReplicationSource,
 };
-pub use state::{ReplicationState, StatusType};
+pub use state::{PendingReplication, ReplicationState, StatusType};
 pub use types::{
     DeletedObjectReplicationInfo, ReplicateObjectInfo, ReplicationStatus, ReplicationTarget,
 };
@@ -142,6 +142,10 @@ impl MrfQueue {
         self.pending.read().await.len()
     }
 
+    pub async fn is_empty(&self) -> bool {
+        self.pending.read().await.is_empty()
+    }
+
     pub fn should_retry(&self, entry: &MrfEntry) -> bool {
         entry.info.retry_count < self.retry_limit
     }
@@ -5,6 +5,7 @@ use std::{
     time::Duration,
 };
 
+use async_trait::async_trait;
 use maxio_common::error::{MaxioError, Result};
 use tokio::{
     sync::{RwLock, mpsc},
@@ -12,9 +13,12 @@ use tokio::{
 };
 
 use super::{
+    bandwidth::{BandwidthConfig, BandwidthLimiter},
+    client::{ConnectionStats, ReplicationClientConfig},
+    config::ReplicationConfig,
     mrf::{DEFAULT_MRF_CAPACITY, DEFAULT_MRF_RETRY_LIMIT, MrfEntry, MrfQueue},
-    state::{ReplicationState, StatusType},
-    types::ReplicateObjectInfo,
+    state::{PendingReplication, ReplicationState, StatusType},
+    types::{DeletedObjectReplicationInfo, ReplicateObjectInfo, ReplicationTarget},
     worker::ReplicationWorker,
 };
 
@@ -34,6 +38,13 @@ pub struct ReplicationPoolConfig {
     pub mrf_retry_limit: u32,
     pub mrf_persistence_interval: Duration,
     pub mrf_persistence_dir: PathBuf,
+    pub state_persistence_interval: Duration,
+    pub state_persistence_dir: PathBuf,
+    pub bandwidth: BandwidthConfig,
+    pub client: ReplicationClientConfig,
+    /// Remote targets bucket replication rules send to, matched to a rule by
+    /// its destination bucket.
+    pub targets: Vec<ReplicationTarget>,
 }
 
 impl Default for ReplicationPoolConfig {
@@ -48,10 +59,24 @@ impl Default for ReplicationPoolConfig {
             mrf_retry_limit: DEFAULT_MRF_RETRY_LIMIT,
             mrf_persistence_interval: Duration::from_secs(30),
             mrf_persistence_dir: PathBuf::from(".minio.sys/replication/mrf"),
+            state_persistence_interval: Duration::from_secs(30),
+            state_persistence_dir: PathBuf::from(".minio.sys/replication/state"),
+            bandwidth: BandwidthConfig::default(),
+            client: ReplicationClientConfig::default(),
+            targets: Vec::new(),
         }
     }
 }
 
+/// Rebuilds the requests of replications resumed after a restart. Their
+/// data and target credentials are not persisted, so both are read again.
+#[async_trait]
+pub trait ReplicationSource: Send + Sync {
+    /// The request for `pending` with the object's current data and the
+    /// bucket's targets, or `None` when the version no longer exists.
+    async fn resume(&self, pending: &PendingReplication) -> Result<Option<ReplicateObjectInfo>>;
+}
+
 #[derive(Debug)]
 struct StandardWorker {
     sender: mpsc::Sender<ReplicateObjectInfo>,
@@ -85,20 +110,26 @@ pub struct ReplicationPool {
     large_tier: Arc<StandardTier>,
     mrf_queue: Arc<MrfQueue>,
     mrf_workers: Arc<RwLock<Vec<JoinHandle<()>>>>,
+    bandwidth: Arc<BandwidthLimiter>,
+    /// Shared by every worker, so resizing keeps the pooled connections.
+    worker: Arc<ReplicationWorker>,
+    connections: Arc<ConnectionStats>,
     _mrf_persist_handle: Arc<JoinHandle<()>>,
+    _state_persist_handle: Arc<JoinHandle<()>>,
 }
 
 impl ReplicationPool {
     pub async fn new(config: ReplicationPoolConfig) -> Result<Self> {
-        let worker_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .map_err(|err| {
-                MaxioError::InternalError(format!("failed to create replication client: {err}"))
-            })?;
-
-        let worker = Arc::new(ReplicationWorker::new(worker_client));
-        let state = Arc::new(ReplicationState::new());
+        let connections = Arc::new(ConnectionStats::default());
+        let worker_client = config.client.build(connections.clone())?;
+
+        let bandwidth = Arc::new(BandwidthLimiter::new(config.bandwidth.clone()));
+        let worker = Arc::new(
+            ReplicationWorker::new(worker_client)
+                .with_bandwidth_limiter(bandwidth.clone())
+                .with_connection_stats(connections.clone()),
+        );
+        let state = Arc::new(ReplicationState::load_or_new(&config.state_persistence_dir).await?);
         let mrf_queue = Arc::new(
             MrfQueue::load_or_new(
                 &config.mrf_persistence_dir,
@@ -111,6 +142,9 @@ impl ReplicationPool {
         let mrf_persist_handle = mrf_queue
             .clone()
             .start_persistence_loop(config.mrf_persistence_interval);
+        let state_persist_handle = state
+            .clone()
+            .start_persistence_loop(config.state_persistence_interval);
 
         let pool = Self {
             config: Arc::new(RwLock::new(config.clone())),
@@ -119,7 +153,11 @@ impl ReplicationPool {
             large_tier: Arc::new(StandardTier::new("large", config.worker_queue_capacity)),
             mrf_queue,
             mrf_workers: Arc::new(RwLock::new(Vec::new())),
+            bandwidth,
+            worker: worker.clone(),
+            connections,
             _mrf_persist_handle: Arc::new(mrf_persist_handle),
+            _state_persist_handle: Arc::new(state_persist_handle),
         };
 
         pool.resize_standard_tier(&pool.normal_tier, config.normal_workers, worker.clone())
@@ -139,9 +177,106 @@ impl ReplicationPool {
         self.mrf_queue.clone()
     }
 
+    /// Limiter shared by all workers; exposes the current caps and throughput.
+    pub fn bandwidth(&self) -> Arc<BandwidthLimiter> {
+        self.bandwidth.clone()
+    }
+
+    /// Requests and connections of the client shared by all workers.
+    pub fn connections(&self) -> Arc<ConnectionStats> {
+        self.connections.clone()
+    }
+
+    pub async fn set_bandwidth(&self, bandwidth: BandwidthConfig) {
+        self.bandwidth.set_config(bandwidth.clone());
+        self.config.write().await.bandwidth = bandwidth;
+    }
+
     pub async fn submit(&self, info: ReplicateObjectInfo) -> Result<()> {
         self.state.mark_targets_pending(&info).await;
 
+        self.dispatch(info).await
+    }
+
+    /// The configured targets `config`'s enabled rules replicate `key` to.
+    pub async fn targets_for(
+        &self,
+        config: &ReplicationConfig,
+        key: &str,
+    ) -> Vec<ReplicationTarget> {
+        let rules = config
+            .enabled_rules()
+            .filter(|rule| rule.matches(key))
+            .collect::<Vec<_>>();
+        self.config
+            .read()
+            .await
+            .targets
+            .iter()
+            .filter(|target| {
+                rules
+                    .iter()
+                    .any(|rule| rule.destination.bucket_name() == target.bucket)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Replicates a delete marker to its targets, tracking its status under
+    /// the marker's own version. Deletes carry no data, so they skip the size
+    /// tiers and are not retried through the MRF queue.
+    pub async fn submit_delete(&self, info: DeletedObjectReplicationInfo) {
+        self.state.mark_delete_pending(&info).await;
+
+        let worker = self.worker.clone();
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            for target in &info.targets {
+                let status = match worker.replicate_delete(&info, target).await {
+                    Ok(()) => StatusType::Completed,
+                    Err(_) => StatusType::Failed,
+                };
+                state
+                    .set_target_status(
+                        &info.bucket,
+                        &info.object,
+                        info.version_id.as_deref(),
+                        &target.arn,
+                        status,
+                    )
+                    .await;
+            }
+        });
+    }
+
+    /// Re-dispatches the targets that were still pending when the state was
+    /// last persisted, reading each object and its targets from `source`.
+    /// Failed targets are left to the MRF queue, which keeps its own
+    /// persisted retries. Versions deleted in the meantime are dropped.
+    pub async fn resume_unfinished(&self, source: &dyn ReplicationSource) -> Result<()> {
+        for entry in self.state.unfinished().await {
+            let Some(info) = source.resume(&entry).await? else {
+                self.state
+                    .remove_object(&entry.bucket, &entry.object, entry.version_id.as_deref())
+                    .await;
+                continue;
+            };
+            let targets = info
+                .targets
+                .iter()
+                .filter(|target| entry.state.targets.get(&target.arn) == Some(&StatusType::Pending))
+                .cloned()
+                .collect::<Vec<_>>();
+            if targets.is_empty() {
+                continue;
+            }
+            self.dispatch(ReplicateObjectInfo { targets, ..info })
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn dispatch(&self, info: ReplicateObjectInfo) -> Result<()> {
         let threshold = self.config.read().await.large_object_threshold;
         let tier = if info.size >= threshold {
             &self.large_tier
@@ -153,17 +288,12 @@ impl ReplicationPool {
     }
 
     pub async fn resize(&self, normal_workers: usize, large_workers: usize, mrf_workers: usize) {
-        let worker_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
-        let worker = Arc::new(ReplicationWorker::new(worker_client));
-
-        self.resize_standard_tier(&self.normal_tier, normal_workers, worker.clone())
+        self.resize_standard_tier(&self.normal_tier, normal_workers, self.worker.clone())
             .await;
-        self.resize_standard_tier(&self.large_tier, large_workers, worker.clone())
+        self.resize_standard_tier(&self.large_tier, large_workers, self.worker.clone())
+            .await;
+        self.resize_mrf_workers(mrf_workers, self.worker.clone())
             .await;
-        self.resize_mrf_workers(mrf_workers, worker).await;
 
         let mut config = self.config.write().await;
         config.normal_workers = normal_workers;
@@ -340,3 +470,323 @@ async fn replicate_to_targets(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::SocketAddr, path::Path, sync::atomic::AtomicUsize};
+
+    use maxio_common::hash::md5_hex;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::replication::client::ConnectionMetrics;
+
+    /// Accepts PUTs and answers each with the ETag `etag_for` derives from
+    /// the received body, keeping connections open between requests. Counts
+    /// the connections it accepts.
+    async fn mock_destination(etag_for: fn(&[u8]) -> String) -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(serve_connection(stream, etag_for));
+            }
+        });
+        (addr, accepted)
+    }
+
+    async fn serve_connection(mut stream: tokio::net::TcpStream, etag_for: fn(&[u8]) -> String) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let header_end = loop {
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+                match stream.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            while request.len() < header_end + content_length {
+                match stream.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let etag = etag_for(&request[header_end..header_end + content_length]);
+            request.drain(..header_end + content_length);
+            let response =
+                format!("HTTP/1.1 200 OK\r\nETag: \"{etag}\"\r\nContent-Length: 0\r\n\r\n");
+            if stream.write_all(response.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "maxio-replication-pool-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ))
+    }
+
+    fn test_config(dir: &Path, bandwidth: BandwidthConfig) -> ReplicationPoolConfig {
+        ReplicationPoolConfig {
+            normal_workers: 1,
+            large_workers: 1,
+            mrf_workers: 0,
+            mrf_persistence_dir: dir.join("mrf"),
+            state_persistence_dir: dir.join("state"),
+            bandwidth,
+            ..ReplicationPoolConfig::default()
+        }
+    }
+
+    fn object_info(addr: SocketAddr, object: &str, body: Vec<u8>) -> ReplicateObjectInfo {
+        ReplicateObjectInfo {
+            bucket: "src".to_string(),
+            object: object.to_string(),
+            version_id: None,
+            size: body.len() as u64,
+            retry_count: 0,
+            targets: vec![ReplicationTarget {
+                arn: "arn:maxio:replication::dest".to_string(),
+                endpoint: format!("http://{addr}"),
+                bucket: "dest".to_string(),
+                region: String::new(),
+                access_key: "access".to_string(),
+                secret_key: "secret".to_string(),
+                session_token: None,
+            }],
+            etag: Some(md5_hex(&body)),
+            body,
+            content_type: None,
+        }
+    }
+
+    /// Waits for the object's single target to leave `Pending`.
+    async fn settled_status(pool: &ReplicationPool, object: &str) -> Option<StatusType> {
+        let state = pool.state();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let status = state
+                    .get_object_state("src", object, None)
+                    .await
+                    .and_then(|object| object.targets.values().next().copied());
+                if status != Some(StatusType::Pending) {
+                    return status;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    async fn replicate_once(addr: SocketAddr) -> (Option<StatusType>, usize) {
+        let dir = test_dir();
+        let config = test_config(&dir, BandwidthConfig::default());
+        let pool = ReplicationPool::new(config).await.unwrap();
+
+        pool.submit(object_info(addr, "object", b"replicated payload".to_vec()))
+            .await
+            .unwrap();
+        let status = settled_status(&pool, "object").await;
+
+        let queued = pool.mrf_queue().len().await;
+        let _ = tokio::fs::remove_dir_all(dir).await;
+        (status, queued)
+    }
+
+    #[tokio::test]
+    async fn matching_destination_etag_completes_replication() {
+        let (addr, _) = mock_destination(md5_hex).await;
+        let (status, queued) = replicate_once(addr).await;
+        assert_eq!(status, Some(StatusType::Completed));
+        assert_eq!(queued, 0);
+    }
+
+    #[tokio::test]
+    async fn mismatched_destination_etag_is_retried() {
+        let (addr, _) = mock_destination(|_| "0123456789abcdef0123456789abcdef".to_string()).await;
+        let (status, queued) = replicate_once(addr).await;
+        assert_eq!(status, Some(StatusType::Failed));
+        assert_eq!(
+            queued, 1,
+            "mismatched replication should be re-queued via MRF"
+        );
+    }
+
+    #[tokio::test]
+    async fn bandwidth_cap_slows_replication_down() {
+        const RATE: u64 = 100_000;
+        const OBJECT_SIZE: usize = 50_000;
+        const OBJECTS: usize = 4;
+
+        let (addr, _) = mock_destination(md5_hex).await;
+        let dir = test_dir();
+        let config = test_config(
+            &dir,
+            BandwidthConfig {
+                global_limit: Some(RATE),
+                target_limits: HashMap::new(),
+            },
+        );
+        let pool = ReplicationPool::new(config).await.unwrap();
+
+        let started = std::time::Instant::now();
+        for index in 0..OBJECTS {
+            pool.submit(object_info(
+                addr,
+                &format!("object-{index}"),
+                vec![index as u8; OBJECT_SIZE],
+            ))
+            .await
+            .unwrap();
+        }
+        for index in 0..OBJECTS {
+            assert_eq!(
+                settled_status(&pool, &format!("object-{index}")).await,
+                Some(StatusType::Completed)
+            );
+        }
+        let elapsed = started.elapsed();
+
+        // The bucket starts with one second of burst; everything past it has
+        // to be paid for at the capped rate.
+        let minimum =
+            Duration::from_secs_f64(((OBJECT_SIZE * OBJECTS) as u64 - RATE) as f64 / RATE as f64);
+        assert!(
+            elapsed >= minimum,
+            "replicated {} bytes in {elapsed:?}, expected at least {minimum:?}",
+            OBJECT_SIZE * OBJECTS
+        );
+
+        let report = pool.bandwidth().report();
+        assert_eq!(report.global_limit, Some(RATE));
+        assert_eq!(report.targets.len(), 1);
+        let _ = tokio::fs::remove_dir_all(dir).await;
+    }
+
+    #[tokio::test]
+    async fn replicated_objects_share_pooled_connections() {
+        const OBJECTS: usize = 5;
+
+        let (addr, accepted) = mock_destination(md5_hex).await;
+        let dir = test_dir();
+        let pool = ReplicationPool::new(test_config(&dir, BandwidthConfig::default()))
+            .await
+            .unwrap();
+
+        for index in 0..OBJECTS {
+            pool.submit(object_info(
+                addr,
+                &format!("object-{index}"),
+                format!("payload {index}").into_bytes(),
+            ))
+            .await
+            .unwrap();
+            assert_eq!(
+                settled_status(&pool, &format!("object-{index}")).await,
+                Some(StatusType::Completed)
+            );
+        }
+        // Resizing hands the same client to the new workers.
+        pool.resize(2, 1, 0).await;
+        pool.submit(object_info(addr, "after-resize", b"payload".to_vec()))
+            .await
+            .unwrap();
+        assert_eq!(
+            settled_status(&pool, "after-resize").await,
+            Some(StatusType::Completed)
+        );
+
+        assert_eq!(accepted.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            pool.connections().metrics(),
+            ConnectionMetrics {
+                requests: OBJECTS as u64 + 1,
+                connections_opened: 1,
+                connections_reused: OBJECTS as u64,
+            }
+        );
+        let _ = tokio::fs::remove_dir_all(dir).await;
+    }
+
+    /// Serves resumed replications from a fixed set of objects.
+    struct ObjectSource {
+        objects: Vec<ReplicateObjectInfo>,
+    }
+
+    #[async_trait]
+    impl ReplicationSource for ObjectSource {
+        async fn resume(
+            &self,
+            pending: &PendingReplication,
+        ) -> Result<Option<ReplicateObjectInfo>> {
+            Ok(self
+                .objects
+                .iter()
+                .find(|info| info.object_key() == pending.object_key())
+                .cloned())
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_replication_resumes_after_restart() {
+        let (addr, _) = mock_destination(md5_hex).await;
+        let dir = test_dir();
+
+        // A previous run that crashed with the object still pending.
+        let previous = ReplicationState::load_or_new(dir.join("state"))
+            .await
+            .unwrap();
+        let object = object_info(addr, "object", b"backfill".to_vec());
+        previous.mark_targets_pending(&object).await;
+        previous
+            .mark_targets_pending(&object_info(addr, "deleted", b"gone".to_vec()))
+            .await;
+        previous.persist().await.unwrap();
+        drop(previous);
+
+        let pool = ReplicationPool::new(test_config(&dir, BandwidthConfig::default()))
+            .await
+            .unwrap();
+        pool.resume_unfinished(&ObjectSource {
+            objects: vec![object],
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            settled_status(&pool, "object").await,
+            Some(StatusType::Completed)
+        );
+        assert!(
+            pool.state()
+                .get_object_state("src", "deleted", None)
+                .await
+                .is_none()
+        );
+
+        pool.state().persist().await.unwrap();
+        let reloaded = ReplicationState::load_or_new(dir.join("state"))
+            .await
+            .unwrap();
+        assert!(reloaded.unfinished().await.is_empty());
+        let _ = tokio::fs::remove_dir_all(dir).await;
+    }
+}
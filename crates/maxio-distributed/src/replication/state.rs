@@ -1,10 +1,19 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use chrono::{DateTime, Utc};
+use maxio_common::error::{MaxioError, Result};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use super::types::{ReplicateObjectInfo, ReplicationStatus};
+use super::types::{
+    DeletedObjectReplicationInfo, ReplicateObjectInfo, ReplicationStatus, ReplicationTarget,
+    replication_key,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusType {
@@ -14,33 +23,121 @@ pub enum StatusType {
     Replica,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ObjectReplicationState {
     pub targets: HashMap<String, StatusType>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// An object whose replication has not finished on every target, as written
+/// to disk so it can be resumed after a restart. Only the version and the
+/// status of each target ARN are kept; the object's data and the targets'
+/// credentials are read again when it resumes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingReplication {
+    pub bucket: String,
+    pub object: String,
+    pub version_id: Option<String>,
+    pub state: ObjectReplicationState,
+}
+
+impl PendingReplication {
+    pub fn object_key(&self) -> String {
+        replication_key(&self.bucket, &self.object, self.version_id.as_deref())
+    }
+}
+
+/// The version an unfinished replication is for.
+#[derive(Debug, Clone)]
+struct ReplicatedVersion {
+    bucket: String,
+    object: String,
+    version_id: Option<String>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ReplicationState {
     objects: Arc<RwLock<HashMap<String, ObjectReplicationState>>>,
+    /// Versions with targets still pending or failed, kept so they can be
+    /// persisted and resumed.
+    unfinished: Arc<RwLock<HashMap<String, ReplicatedVersion>>>,
+    persistence_path: Option<PathBuf>,
 }
 
 impl ReplicationState {
     pub fn new() -> Self {
         Self {
             objects: Arc::new(RwLock::new(HashMap::new())),
+            unfinished: Arc::new(RwLock::new(HashMap::new())),
+            persistence_path: None,
         }
     }
 
-    pub async fn mark_targets_pending(&self, info: &ReplicateObjectInfo) {
-        let key = object_key(&info.bucket, &info.object, info.version_id.as_deref());
-        let mut targets = HashMap::new();
-        for target in &info.targets {
-            targets.insert(target.arn.clone(), StatusType::Pending);
+    /// Restores the unfinished replications persisted under `persistence_dir`
+    /// and keeps persisting there.
+    pub async fn load_or_new(persistence_dir: impl AsRef<Path>) -> Result<Self> {
+        let persistence_path = persistence_dir.as_ref().join("replication-state.json");
+        let persisted = match tokio::fs::read(&persistence_path).await {
+            Ok(bytes) => {
+                serde_json::from_slice::<Vec<PendingReplication>>(&bytes).map_err(|err| {
+                    MaxioError::InternalError(format!(
+                        "failed to parse persisted replication state {}: {err}",
+                        persistence_path.display()
+                    ))
+                })?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(MaxioError::Io(err)),
+        };
+
+        let mut objects = HashMap::new();
+        let mut unfinished = HashMap::new();
+        for entry in persisted {
+            let key = entry.object_key();
+            unfinished.insert(
+                key.clone(),
+                ReplicatedVersion {
+                    bucket: entry.bucket,
+                    object: entry.object,
+                    version_id: entry.version_id,
+                },
+            );
+            objects.insert(key, entry.state);
         }
 
-        let mut state = self.objects.write().await;
-        state.insert(
+        Ok(Self {
+            objects: Arc::new(RwLock::new(objects)),
+            unfinished: Arc::new(RwLock::new(unfinished)),
+            persistence_path: Some(persistence_path),
+        })
+    }
+
+    pub async fn mark_targets_pending(&self, info: &ReplicateObjectInfo) {
+        let key = info.object_key();
+        self.insert_pending(key.clone(), &info.targets).await;
+        self.unfinished.write().await.insert(
+            key,
+            ReplicatedVersion {
+                bucket: info.bucket.clone(),
+                object: info.object.clone(),
+                version_id: info.version_id.clone(),
+            },
+        );
+    }
+
+    /// Starts tracking the replication of a delete marker, separately from
+    /// the versions it hides. Deletes carry no data to resume from, so they
+    /// are not persisted.
+    pub async fn mark_delete_pending(&self, info: &DeletedObjectReplicationInfo) {
+        self.insert_pending(info.object_key(), &info.targets).await;
+    }
+
+    async fn insert_pending(&self, key: String, targets: &[ReplicationTarget]) {
+        let targets = targets
+            .iter()
+            .map(|target| (target.arn.clone(), StatusType::Pending))
+            .collect();
+        self.objects.write().await.insert(
             key,
             ObjectReplicationState {
                 targets,
@@ -49,6 +146,62 @@ impl ReplicationState {
         );
     }
 
+    /// Replications that still have pending or failed targets, with the
+    /// status of each target.
+    pub async fn unfinished(&self) -> Vec<PendingReplication> {
+        let objects = self.objects.read().await;
+        let unfinished = self.unfinished.read().await;
+        let mut entries = unfinished
+            .iter()
+            .filter_map(|(key, version)| {
+                let state = objects.get(key)?;
+                Some(PendingReplication {
+                    bucket: version.bucket.clone(),
+                    object: version.object.clone(),
+                    version_id: version.version_id.clone(),
+                    state: state.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(PendingReplication::object_key);
+        entries
+    }
+
+    /// Writes the unfinished replications to disk. A no-op for states that
+    /// were not loaded from a persistence directory.
+    pub async fn persist(&self) -> Result<()> {
+        let Some(path) = self.persistence_path.as_ref() else {
+            return Ok(());
+        };
+        let payload = serde_json::to_vec(&self.unfinished().await).map_err(|err| {
+            MaxioError::InternalError(format!("failed to serialize replication state: {err}"))
+        })?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, payload).await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+
+    pub fn start_persistence_loop(
+        self: Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if self.persist().await.is_err() {
+                    continue;
+                }
+            }
+        })
+    }
+
     pub async fn set_target_status(
         &self,
         bucket: &str,
@@ -57,14 +210,24 @@ impl ReplicationState {
         target_arn: &str,
         status: StatusType,
     ) {
-        let key = object_key(bucket, object, version_id);
+        let key = replication_key(bucket, object, version_id);
         let mut state = self.objects.write().await;
-        let entry = state.entry(key).or_insert_with(|| ObjectReplicationState {
-            targets: HashMap::new(),
-            updated_at: Utc::now(),
-        });
+        let entry = state
+            .entry(key.clone())
+            .or_insert_with(|| ObjectReplicationState {
+                targets: HashMap::new(),
+                updated_at: Utc::now(),
+            });
         entry.targets.insert(target_arn.to_string(), status);
         entry.updated_at = Utc::now();
+
+        let finished = entry
+            .targets
+            .values()
+            .all(|status| matches!(status, StatusType::Completed | StatusType::Replica));
+        if finished {
+            self.unfinished.write().await.remove(&key);
+        }
     }
 
     pub async fn get_object_state(
@@ -73,11 +236,14 @@ impl ReplicationState {
         object: &str,
         version_id: Option<&str>,
     ) -> Option<ObjectReplicationState> {
-        let key = object_key(bucket, object, version_id);
+        let key = replication_key(bucket, object, version_id);
         let state = self.objects.read().await;
         state.get(&key).cloned()
     }
 
+    /// Replication status of one version across all of its targets, or
+    /// `None` when the version is not being replicated. A `version_id` of
+    /// `None` or `null` means the unversioned object.
     pub async fn get_overall_status(
         &self,
         bucket: &str,
@@ -85,6 +251,16 @@ impl ReplicationState {
         version_id: Option<&str>,
     ) -> Option<ReplicationStatus> {
         let object_state = self.get_object_state(bucket, object, version_id).await?;
+        if object_state.targets.is_empty() {
+            return None;
+        }
+        if object_state
+            .targets
+            .values()
+            .all(|status| matches!(status, StatusType::Replica))
+        {
+            return Some(ReplicationStatus::Replica);
+        }
         if object_state
             .targets
             .values()
@@ -105,15 +281,150 @@ impl ReplicationState {
     }
 
     pub async fn remove_object(&self, bucket: &str, object: &str, version_id: Option<&str>) {
-        let key = object_key(bucket, object, version_id);
+        let key = replication_key(bucket, object, version_id);
         let mut state = self.objects.write().await;
         state.remove(&key);
+        self.unfinished.write().await.remove(&key);
     }
 }
 
-fn object_key(bucket: &str, object: &str, version_id: Option<&str>) -> String {
-    match version_id {
-        Some(version_id) if !version_id.is_empty() => format!("{bucket}/{object}/{version_id}"),
-        _ => format!("{bucket}/{object}"),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(arn: &str) -> ReplicationTarget {
+        ReplicationTarget {
+            arn: arn.to_string(),
+            endpoint: "http://127.0.0.1:9000".to_string(),
+            bucket: "dest".to_string(),
+            region: String::new(),
+            access_key: "access".to_string(),
+            secret_key: "secret".to_string(),
+            session_token: None,
+        }
+    }
+
+    fn info(object: &str) -> ReplicateObjectInfo {
+        ReplicateObjectInfo {
+            bucket: "src".to_string(),
+            object: object.to_string(),
+            version_id: None,
+            size: 4,
+            retry_count: 0,
+            targets: vec![target("first"), target("second")],
+            body: b"data".to_vec(),
+            content_type: None,
+            etag: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn unfinished_replications_survive_a_reload() {
+        let dir = std::env::temp_dir().join(format!(
+            "maxio-replication-state-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let state = ReplicationState::load_or_new(&dir).await.unwrap();
+        state.mark_targets_pending(&info("done")).await;
+        state.mark_targets_pending(&info("partial")).await;
+        for arn in ["first", "second"] {
+            state
+                .set_target_status("src", "done", None, arn, StatusType::Completed)
+                .await;
+        }
+        state
+            .set_target_status("src", "partial", None, "first", StatusType::Failed)
+            .await;
+        state.persist().await.unwrap();
+
+        // Neither the data nor the target credentials reach the disk.
+        let persisted = tokio::fs::read(dir.join("replication-state.json"))
+            .await
+            .unwrap();
+        let persisted = String::from_utf8_lossy(&persisted);
+        assert!(!persisted.contains("secret") && !persisted.contains("access"));
+        assert!(!persisted.contains("body"));
+
+        let reloaded = ReplicationState::load_or_new(&dir).await.unwrap();
+        let unfinished = reloaded.unfinished().await;
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].object, "partial");
+        assert_eq!(
+            reloaded
+                .get_object_state("src", "partial", None)
+                .await
+                .map(|state| state.targets),
+            Some(HashMap::from([
+                ("first".to_string(), StatusType::Failed),
+                ("second".to_string(), StatusType::Pending),
+            ]))
+        );
+        assert!(
+            reloaded
+                .get_object_state("src", "done", None)
+                .await
+                .is_none()
+        );
+
+        let _ = tokio::fs::remove_dir_all(dir).await;
+    }
+
+    #[tokio::test]
+    async fn versions_and_delete_markers_report_independent_statuses() {
+        let state = ReplicationState::new();
+        let version = |version_id: &str| ReplicateObjectInfo {
+            version_id: Some(version_id.to_string()),
+            ..info("doc")
+        };
+        state.mark_targets_pending(&version("v1")).await;
+        state.mark_targets_pending(&version("v2")).await;
+        state
+            .mark_delete_pending(&DeletedObjectReplicationInfo {
+                bucket: "src".to_string(),
+                object: "doc".to_string(),
+                version_id: Some("marker".to_string()),
+                retry_count: 0,
+                targets: vec![target("first")],
+            })
+            .await;
+        for arn in ["first", "second"] {
+            state
+                .set_target_status("src", "doc", Some("v1"), arn, StatusType::Completed)
+                .await;
+        }
+        state
+            .set_target_status("src", "doc", Some("v2"), "second", StatusType::Failed)
+            .await;
+
+        let status = |version_id: Option<&'static str>| {
+            let state = state.clone();
+            async move { state.get_overall_status("src", "doc", version_id).await }
+        };
+        assert_eq!(status(Some("v1")).await, Some(ReplicationStatus::Completed));
+        assert_eq!(status(Some("v2")).await, Some(ReplicationStatus::Failed));
+        assert_eq!(
+            status(Some("marker")).await,
+            Some(ReplicationStatus::Pending)
+        );
+        assert_eq!(status(None).await, None);
+
+        state
+            .set_target_status("src", "doc", Some("marker"), "first", StatusType::Completed)
+            .await;
+        assert_eq!(
+            status(Some("marker")).await,
+            Some(ReplicationStatus::Completed)
+        );
+        assert_eq!(status(Some("v2")).await, Some(ReplicationStatus::Failed));
+
+        // The null version is the object written before versioning.
+        state
+            .set_target_status("src", "plain", None, "first", StatusType::Replica)
+            .await;
+        assert_eq!(
+            state.get_overall_status("src", "plain", Some("null")).await,
+            Some(ReplicationStatus::Replica)
+        );
     }
 }
@@ -16,6 +16,19 @@ pub enum ReplicationStatus {
     Pending,
     Completed,
     Failed,
+    Replica,
+}
+
+impl ReplicationStatus {
+    /// The value S3 sends in `x-amz-replication-status`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Completed => "COMPLETED",
+            Self::Failed => "FAILED",
+            Self::Replica => "REPLICA",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,16 +41,15 @@ pub struct ReplicateObjectInfo {
     pub targets: Vec<ReplicationTarget>,
     pub body: Vec<u8>,
     pub content_type: Option<String>,
+    /// ETag of the source object; the destination's ETag must match it for
+    /// the replication to count as complete.
+    #[serde(default)]
+    pub etag: Option<String>,
 }
 
 impl ReplicateObjectInfo {
     pub fn object_key(&self) -> String {
-        match self.version_id.as_ref() {
-            Some(version_id) if !version_id.is_empty() => {
-                format!("{}/{}/{}", self.bucket, self.object, version_id)
-            }
-            _ => format!("{}/{}", self.bucket, self.object),
-        }
+        replication_key(&self.bucket, &self.object, self.version_id.as_deref())
     }
 }
 
@@ -49,3 +61,20 @@ pub struct DeletedObjectReplicationInfo {
     pub retry_count: u32,
     pub targets: Vec<ReplicationTarget>,
 }
+
+impl DeletedObjectReplicationInfo {
+    pub fn object_key(&self) -> String {
+        replication_key(&self.bucket, &self.object, self.version_id.as_deref())
+    }
+}
+
+/// Key replication status is tracked under. Each version, delete markers
+/// included, has its own; the `null` version is the unversioned object.
+pub(crate) fn replication_key(bucket: &str, object: &str, version_id: Option<&str>) -> String {
+    match version_id {
+        Some(version_id) if !version_id.is_empty() && version_id != "null" => {
+            format!("{bucket}/{object}/{version_id}")
+        }
+        _ => format!("{bucket}/{object}"),
+    }
+}
@@ -1,22 +1,50 @@
+use std::sync::Arc;
+
 use chrono::Utc;
 use maxio_auth::signature_v4::{
     canonical_query_string, canonical_uri, get_canonical_request, get_signature, get_signing_key,
     get_string_to_sign,
 };
-use maxio_common::error::{MaxioError, Result};
+use maxio_common::{
+    error::{MaxioError, Result},
+    hash::{md5_hex, normalize_etag},
+};
 use sha2::{Digest, Sha256};
 use url::Url;
 
-use super::types::{ReplicateObjectInfo, ReplicationTarget};
+use super::{
+    bandwidth::{BandwidthConfig, BandwidthLimiter},
+    client::ConnectionStats,
+    types::{DeletedObjectReplicationInfo, ReplicateObjectInfo, ReplicationTarget},
+};
 
 #[derive(Debug, Clone)]
 pub struct ReplicationWorker {
     client: reqwest::Client,
+    bandwidth: Arc<BandwidthLimiter>,
+    connections: Arc<ConnectionStats>,
 }
 
 impl ReplicationWorker {
     pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            bandwidth: Arc::new(BandwidthLimiter::new(BandwidthConfig::default())),
+            connections: Arc::default(),
+        }
+    }
+
+    /// Shares `bandwidth` with other workers so its caps hold across them.
+    pub fn with_bandwidth_limiter(mut self, bandwidth: Arc<BandwidthLimiter>) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// Counts this worker's requests in `connections`, which should be the
+    /// stats its client was built with.
+    pub fn with_connection_stats(mut self, connections: Arc<ConnectionStats>) -> Self {
+        self.connections = connections;
+        self
     }
 
     pub async fn replicate_object(
@@ -33,6 +61,86 @@ impl ReplicationWorker {
                 .query_pairs_mut()
                 .append_pair("versionId", version_id);
         }
+        let mut request = self
+            .signed_request(reqwest::Method::PUT, object_url, target, &info.body)?
+            .body(info.body.clone());
+        if let Some(content_type) = info.content_type.as_deref()
+            && !content_type.is_empty()
+        {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+
+        self.bandwidth
+            .throttle(&target.arn, info.body.len() as u64)
+            .await;
+        self.connections.record_request();
+        let response = request.send().await.map_err(|err| {
+            MaxioError::InternalError(format!(
+                "replication request failed for target {}: {err}",
+                target.arn
+            ))
+        })?;
+
+        if !response.status().is_success() {
+            return Err(MaxioError::InternalError(format!(
+                "replication PUT failed for target {} with status {}",
+                target.arn,
+                response.status()
+            )));
+        }
+
+        let expected = expected_etag(info);
+        let returned = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(normalize_etag);
+        if returned.as_deref() != Some(expected.as_str()) {
+            return Err(MaxioError::InternalError(format!(
+                "replication to target {} returned ETag {} but expected {expected}",
+                target.arn,
+                returned.as_deref().unwrap_or("<none>")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Replicates a delete marker by deleting the object on `target`, which
+    /// hides it behind a marker of the target's own.
+    pub async fn replicate_delete(
+        &self,
+        info: &DeletedObjectReplicationInfo,
+        target: &ReplicationTarget,
+    ) -> Result<()> {
+        let object_url = build_target_object_url(&target.endpoint, &target.bucket, &info.object)?;
+        let request = self.signed_request(reqwest::Method::DELETE, object_url, target, &[])?;
+
+        self.connections.record_request();
+        let response = request.send().await.map_err(|err| {
+            MaxioError::InternalError(format!(
+                "replication request failed for target {}: {err}",
+                target.arn
+            ))
+        })?;
+        if !response.status().is_success() {
+            return Err(MaxioError::InternalError(format!(
+                "replication DELETE failed for target {} with status {}",
+                target.arn,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    /// A request to `object_url` signed with the target's credentials.
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        object_url: Url,
+        target: &ReplicationTarget,
+        payload: &[u8],
+    ) -> Result<reqwest::RequestBuilder> {
         let host = host_header_value(&object_url)?;
 
         let now = Utc::now();
@@ -44,7 +152,7 @@ impl ReplicationWorker {
             target.region.as_str()
         };
 
-        let payload_hash = sha256_hex(&info.body);
+        let payload_hash = sha256_hex(payload);
         let canonical_uri = canonical_uri(object_url.path());
         let canonical_query = object_url
             .query()
@@ -74,7 +182,7 @@ impl ReplicationWorker {
             .join(";");
 
         let canonical_request = get_canonical_request(
-            "PUT",
+            method.as_str(),
             &canonical_uri,
             &canonical_query,
             &canonical_headers,
@@ -94,40 +202,27 @@ impl ReplicationWorker {
 
         let mut request = self
             .client
-            .put(object_url.clone())
+            .request(method, object_url)
             .header("Host", host)
             .header("x-amz-date", amz_date)
             .header("x-amz-content-sha256", payload_hash)
-            .header("Authorization", authorization)
-            .body(info.body.clone());
-
-        if let Some(content_type) = info.content_type.as_deref()
-            && !content_type.is_empty()
-        {
-            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
-        }
+            .header("Authorization", authorization);
         if let Some(token) = target.session_token.as_deref()
             && !token.is_empty()
         {
             request = request.header("x-amz-security-token", token);
         }
+        Ok(request)
+    }
+}
 
-        let response = request.send().await.map_err(|err| {
-            MaxioError::InternalError(format!(
-                "replication request failed for target {}: {err}",
-                target.arn
-            ))
-        })?;
-
-        if !response.status().is_success() {
-            return Err(MaxioError::InternalError(format!(
-                "replication PUT failed for target {} with status {}",
-                target.arn,
-                response.status()
-            )));
-        }
-
-        Ok(())
+/// The ETag the destination should report for the replicated body. Multipart
+/// source ETags are not content hashes of the single PUT we send, so those fall
+/// back to the MD5 of the body.
+fn expected_etag(info: &ReplicateObjectInfo) -> String {
+    match info.etag.as_deref().map(normalize_etag) {
+        Some(etag) if !etag.is_empty() && !etag.contains('-') => etag,
+        _ => md5_hex(&info.body),
     }
 }
 
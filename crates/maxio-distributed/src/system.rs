@@ -1,10 +1,17 @@
 use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
+    sync::Arc,
 };
 
+use tokio::io::{AsyncRead, AsyncWrite};
+use tracing::{debug, warn};
+
 use crate::{
     discovery::NodeDiscovery,
+    dsync::{DRWMutex, DsyncClient, LocalLocker, LockArgs, LockEntry, NetLocker},
+    grid::{Connection, GridLocker, HandlerRegistry, LockHandler, grid_url},
+    healing::HealEngine,
     types::{ClusterConfig, ClusterStatus, derive_node_id, normalize_endpoint},
 };
 
@@ -12,18 +19,142 @@ use crate::{
 pub struct DistributedSys {
     discovery: NodeDiscovery,
     this_node: String,
+    locker: Arc<LocalLocker>,
+    dsync: Arc<DsyncClient>,
+    heal_engines: Vec<HealEngine>,
+    grid_handlers: HandlerRegistry,
 }
 
 impl DistributedSys {
     pub async fn new(config: ClusterConfig) -> Self {
         let discovery = NodeDiscovery::new(config.clone()).await;
         discovery.start_health_checks().await;
+        let locker = Arc::new(LocalLocker::new());
+        let dsync = Arc::new(DsyncClient::new(vec![
+            Arc::clone(&locker) as Arc<dyn NetLocker>
+        ]));
+        let grid_handlers = HandlerRegistry::new();
+        LockHandler::register(&grid_handlers, Arc::clone(&locker) as Arc<dyn NetLocker>).await;
         Self {
             discovery,
             this_node: config.this_node,
+            locker,
+            dsync,
+            heal_engines: Vec::new(),
+            grid_handlers,
+        }
+    }
+
+    /// Attaches one heal engine per local erasure set.
+    pub fn with_heal_engines(mut self, engines: Vec<HealEngine>) -> Self {
+        self.heal_engines = engines;
+        self
+    }
+
+    /// Also locks through `peers`, the lockers of the other nodes, so a
+    /// lock granted here excludes holders on every node that shares them.
+    pub fn with_peer_lockers(mut self, peers: Vec<Arc<dyn NetLocker>>) -> Self {
+        let mut lockers = vec![Arc::clone(&self.locker) as Arc<dyn NetLocker>];
+        lockers.extend(peers);
+        self.dsync = Arc::new(DsyncClient::new(lockers).with_lock_ttl(self.dsync.lock_ttl()));
+        self
+    }
+
+    /// Dials a grid connection to every other node of the cluster and locks
+    /// through their lockers, so object locks hold cluster-wide. Peers
+    /// accept the connection when it presents `auth_token`; see
+    /// [`grid_auth_token`](crate::grid::grid_auth_token). `tls` is the
+    /// trust for peers with `https://` endpoints. Connections redial in the
+    /// background, and a peer that cannot be reached counts against lock
+    /// quorum meanwhile.
+    pub async fn with_grid_peers(
+        self,
+        auth_token: &str,
+        tls: Option<Arc<rustls::ClientConfig>>,
+    ) -> Self {
+        let mut peers = Vec::new();
+        for node in self.discovery.get_nodes() {
+            if node.endpoint == self.this_node {
+                continue;
+            }
+            let mut connection =
+                Connection::new(grid_url(&node.endpoint), self.grid_handlers.clone())
+                    .with_auth_token(auth_token);
+            if let Some(tls) = &tls {
+                connection = connection.with_tls(Arc::clone(tls));
+            }
+            let connection = Arc::new(connection);
+            if let Err(err) = connection.start().await {
+                warn!(node = %node.endpoint, error = %err, "failed to start grid connection");
+            }
+            peers.push(Arc::new(GridLocker::new(connection)) as Arc<dyn NetLocker>);
+        }
+        if peers.is_empty() {
+            return self;
+        }
+        self.with_peer_lockers(peers)
+    }
+
+    /// Answers grid requests, such as lock calls, from the peer on `io`, a
+    /// connection already upgraded to a websocket. Returns once it closes.
+    pub async fn serve_grid_peer<S>(&self, remote_addr: String, io: S)
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let connection = Connection::new(remote_addr.clone(), self.grid_handlers.clone());
+        if let Err(err) = connection.serve(io).await {
+            debug!(peer = %remote_addr, error = %err, "grid connection closed with error");
         }
     }
 
+    /// This node's own locker, for peers to lock through.
+    pub fn locker(&self) -> Arc<LocalLocker> {
+        Arc::clone(&self.locker)
+    }
+
+    /// Heal engines of the local erasure sets; empty on a single disk.
+    pub fn heal_engines(&self) -> &[HealEngine] {
+        &self.heal_engines
+    }
+
+    /// Lock client backed by this node's locker and, once
+    /// [`with_grid_peers`](Self::with_grid_peers) ran, every peer's.
+    pub fn dsync(&self) -> &Arc<DsyncClient> {
+        &self.dsync
+    }
+
+    /// Cluster-wide lock on one object, for writes that check the object's
+    /// current state and must not have another node write in between.
+    pub fn object_lock(&self, bucket: &str, key: &str, source: &str) -> DRWMutex {
+        DRWMutex::new(
+            Arc::clone(&self.dsync),
+            vec![format!("{bucket}/{key}")],
+            self.this_node.clone(),
+            source,
+        )
+    }
+
+    /// Locks currently granted by this node's locker, oldest first. Peers
+    /// lock through it too, so this covers every cluster-wide lock that
+    /// reached this node.
+    pub fn held_locks(&self) -> Vec<LockEntry> {
+        self.locker.held_locks()
+    }
+
+    /// Releases every lock on `resources`, whoever holds it, on this node's
+    /// locker and every peer's. For operators recovering from a holder that
+    /// crashed before its grants expired.
+    pub async fn force_unlock(&self, resources: Vec<String>) {
+        let args = LockArgs::new(
+            String::new(),
+            resources,
+            self.this_node.clone(),
+            "admin".to_string(),
+            0,
+        );
+        self.dsync.force_unlock(&args).await;
+    }
+
     pub fn is_distributed(&self) -> bool {
         self.discovery.is_distributed()
     }
@@ -31,6 +31,13 @@ pub struct ClusterStatus {
     pub nodes: Vec<NodeInfo>,
 }
 
+impl ClusterStatus {
+    /// A strict majority of nodes must be reachable for the cluster to serve.
+    pub fn has_quorum(&self) -> bool {
+        self.online_nodes > self.total_nodes / 2
+    }
+}
+
 impl ClusterConfig {
     pub fn single(this_node: String) -> Self {
         let this_node = normalize_endpoint(&this_node);
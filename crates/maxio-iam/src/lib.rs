@@ -3,7 +3,7 @@ pub mod store;
 pub mod system;
 pub mod types;
 
-pub use policy::evaluate_policy;
+pub use policy::{evaluate_policy, evaluate_policy_with_context, policy_warnings, validate_policy};
 pub use store::IamStore;
 pub use system::IAMSys;
-pub use types::{Effect, Policy, PolicyStatement, User};
+pub use types::{Conditions, Effect, Policy, PolicyStatement, User};
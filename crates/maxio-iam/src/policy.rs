@@ -1,10 +1,35 @@
-use crate::types::{Effect, Policy};
+use std::collections::HashMap;
+
+use maxio_common::error::{MaxioError, Result};
+
+use crate::types::{Effect, Policy, PolicyStatement};
+
+const CONDITION_OPERATORS: [&str; 4] = [
+    "StringEquals",
+    "StringNotEquals",
+    "StringLike",
+    "StringNotLike",
+];
 
 pub fn evaluate_policy(policies: &[Policy], action: &str, resource: &str) -> bool {
+    evaluate_policy_with_context(policies, action, resource, &HashMap::new())
+}
+
+/// Like `evaluate_policy`, but statements carrying a `Condition` block only
+/// apply when every condition holds against `context`, a map of condition
+/// keys (`s3:prefix`, `s3:delimiter`, ...) to the values of this request.
+pub fn evaluate_policy_with_context(
+    policies: &[Policy],
+    action: &str,
+    resource: &str,
+    context: &HashMap<String, String>,
+) -> bool {
     let mut allow = false;
 
     for statement in policies.iter().flat_map(|p| p.statements.iter()) {
-        if !matches_any(&statement.actions, action) || !matches_any(&statement.resources, resource)
+        if !matches_any(&statement.actions, action)
+            || !matches_any(&statement.resources, resource)
+            || !conditions_hold(statement, context)
         {
             continue;
         }
@@ -19,6 +44,89 @@ pub fn evaluate_policy(policies: &[Policy], action: &str, resource: &str) -> boo
     allow
 }
 
+/// Rejects policies that could never be stored: no name, no statements, a
+/// statement without actions or resources, or a condition operator this
+/// evaluator does not understand.
+pub fn validate_policy(policy: &Policy) -> Result<()> {
+    if policy.name.is_empty() {
+        return Err(MaxioError::InvalidArgument(
+            "policy name is required".to_string(),
+        ));
+    }
+    if policy.statements.is_empty() {
+        return Err(MaxioError::InvalidArgument(
+            "policy must include at least one statement".to_string(),
+        ));
+    }
+    for (index, statement) in policy.statements.iter().enumerate() {
+        if statement.actions.is_empty() {
+            return Err(MaxioError::InvalidArgument(format!(
+                "policy statement {index} must include at least one action"
+            )));
+        }
+        if statement.resources.is_empty() {
+            return Err(MaxioError::InvalidArgument(format!(
+                "policy statement {index} must include at least one resource"
+            )));
+        }
+        if let Some(operator) = statement
+            .conditions
+            .keys()
+            .find(|operator| !CONDITION_OPERATORS.contains(&operator.as_str()))
+        {
+            return Err(MaxioError::InvalidArgument(format!(
+                "policy statement {index} uses unsupported condition operator {operator}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Parts of a valid policy that probably do not do what was meant.
+pub fn policy_warnings(policy: &Policy) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (index, statement) in policy.statements.iter().enumerate() {
+        if statement.effect == Effect::Allow
+            && statement.actions.iter().any(|action| action == "*")
+            && statement.resources.iter().any(|resource| resource == "*")
+        {
+            warnings.push(format!(
+                "policy statement {index} allows every action on every resource"
+            ));
+        }
+    }
+    warnings
+}
+
+/// `validate_policy` keeps unknown operators out of stored policies; one that
+/// still turns up fails closed: it holds for a Deny and never for an Allow,
+/// so neither effect ends up granting more than intended.
+fn conditions_hold(statement: &PolicyStatement, context: &HashMap<String, String>) -> bool {
+    statement.conditions.iter().all(|(operator, keys)| {
+        keys.iter().all(|(key, expected)| {
+            let actual = context
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.as_str());
+            condition_matches(operator, actual, expected)
+                .unwrap_or(statement.effect == Effect::Deny)
+        })
+    })
+}
+
+/// `None` for an unknown operator. Negated operators match when the key is
+/// absent from the request, as in AWS.
+fn condition_matches(operator: &str, actual: Option<&str>, expected: &[String]) -> Option<bool> {
+    let matches = match operator {
+        "StringEquals" => actual.is_some_and(|value| expected.iter().any(|e| e == value)),
+        "StringNotEquals" => actual.is_none_or(|value| expected.iter().all(|e| e != value)),
+        "StringLike" => actual.is_some_and(|value| matches_any(expected, value)),
+        "StringNotLike" => actual.is_none_or(|value| !matches_any(expected, value)),
+        _ => return None,
+    };
+    Some(matches)
+}
+
 fn matches_any(patterns: &[String], value: &str) -> bool {
     patterns
         .iter()
@@ -62,9 +170,13 @@ fn wildcard_match(pattern: &str, input: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use maxio_common::error::MaxioError;
+
     use crate::types::{Effect, Policy, PolicyStatement};
 
-    use super::evaluate_policy;
+    use super::{evaluate_policy, evaluate_policy_with_context, policy_warnings, validate_policy};
 
     #[test]
     fn deny_precedes_allow() {
@@ -76,11 +188,13 @@ mod tests {
                     effect: Effect::Allow,
                     actions: vec!["s3:*".to_string()],
                     resources: vec!["arn:aws:s3:::mybucket/*".to_string()],
+                    conditions: HashMap::new(),
                 },
                 PolicyStatement {
                     effect: Effect::Deny,
                     actions: vec!["s3:DeleteObject".to_string()],
                     resources: vec!["arn:aws:s3:::mybucket/private/*".to_string()],
+                    conditions: HashMap::new(),
                 },
             ],
         }];
@@ -92,6 +206,23 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn statements_need_actions_and_resources() {
+        let mut policy: Policy = serde_json::from_str(
+            r#"{"name": "team", "Statement": {"Effect": "Allow", "Action": "*",
+                "Resource": "*"}}"#,
+        )
+        .unwrap();
+        assert!(validate_policy(&policy).is_ok());
+        assert_eq!(policy_warnings(&policy).len(), 1);
+
+        policy.statements[0].resources.clear();
+        assert!(matches!(
+            validate_policy(&policy),
+            Err(MaxioError::InvalidArgument(_))
+        ));
+    }
+
     #[test]
     fn wildcard_action_resource_work() {
         let policies = vec![Policy {
@@ -104,6 +235,7 @@ mod tests {
                     "arn:aws:s3:::mybucket/*".to_string(),
                     "arn:aws:s3:::mybucket".to_string(),
                 ],
+                conditions: HashMap::new(),
             }],
         }];
 
@@ -118,4 +250,71 @@ mod tests {
             "arn:aws:s3:::mybucket/key"
         ));
     }
+
+    #[test]
+    fn prefix_condition_limits_listing() {
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "name": "alice-home",
+            "Statement": {
+                "Effect": "Allow",
+                "Action": "s3:ListBucket",
+                "Resource": "arn:aws:s3:::mybucket",
+                "Condition": { "StringLike": { "s3:prefix": "home/alice/*" } }
+            }
+        }))
+        .expect("parse policy");
+        let policies = vec![policy];
+        let list = |context: &[(&str, &str)]| {
+            let context = context
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>();
+            evaluate_policy_with_context(
+                &policies,
+                "s3:ListBucket",
+                "arn:aws:s3:::mybucket",
+                &context,
+            )
+        };
+
+        assert!(list(&[("s3:prefix", "home/alice/docs/")]));
+        assert!(!list(&[]));
+        assert!(!list(&[("s3:prefix", "")]));
+        assert!(!list(&[("s3:prefix", "home/bob/")]));
+        assert!(!evaluate_policy(
+            &policies,
+            "s3:ListBucket",
+            "arn:aws:s3:::mybucket"
+        ));
+    }
+
+    #[test]
+    fn deny_with_unsupported_operator_fails_closed() {
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "name": "no-deletes",
+            "Statement": [
+                {"Effect": "Allow", "Action": "s3:*", "Resource": "arn:aws:s3:::mybucket/*"},
+                {"Effect": "Deny", "Action": "s3:DeleteObject",
+                 "Resource": "arn:aws:s3:::mybucket/*",
+                 "Condition": {"DateGreaterThan": {"aws:CurrentTime": "2020-01-01T00:00:00Z"}}}
+            ]
+        }))
+        .expect("parse policy");
+        assert!(matches!(
+            validate_policy(&policy),
+            Err(MaxioError::InvalidArgument(_))
+        ));
+
+        let policies = vec![policy];
+        assert!(!evaluate_policy(
+            &policies,
+            "s3:DeleteObject",
+            "arn:aws:s3:::mybucket/key"
+        ));
+        assert!(evaluate_policy(
+            &policies,
+            "s3:GetObject",
+            "arn:aws:s3:::mybucket/key"
+        ));
+    }
 }
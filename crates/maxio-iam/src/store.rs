@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use maxio_common::error::{MaxioError, Result};
@@ -9,6 +10,7 @@ use crate::types::{Policy, User};
 pub struct IamStore {
     users_dir: PathBuf,
     policies_dir: PathBuf,
+    policy_revisions_path: PathBuf,
 }
 
 impl IamStore {
@@ -22,6 +24,7 @@ impl IamStore {
         Ok(Self {
             users_dir,
             policies_dir,
+            policy_revisions_path: base.join("policy-revisions.json"),
         })
     }
 
@@ -70,6 +73,23 @@ impl IamStore {
         self.read_all_json::<Policy>(&self.policies_dir).await
     }
 
+    /// Last revision written for each policy name. Entries outlive the
+    /// policies they count, so they are kept apart from the policy documents.
+    pub async fn load_policy_revisions(&self) -> Result<HashMap<String, u64>> {
+        Ok(self
+            .read_json_if_exists(self.policy_revisions_path.clone())
+            .await?
+            .unwrap_or_default())
+    }
+
+    pub async fn save_policy_revisions(&self, revisions: &HashMap<String, u64>) -> Result<()> {
+        let data = serde_json::to_vec_pretty(revisions).map_err(|err| {
+            MaxioError::InternalError(format!("failed to serialize policy revisions: {err}"))
+        })?;
+        fs::write(&self.policy_revisions_path, data).await?;
+        Ok(())
+    }
+
     fn user_path(&self, access_key: &str) -> PathBuf {
         self.users_dir.join(format!("{access_key}.json"))
     }
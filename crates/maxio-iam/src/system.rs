@@ -8,7 +8,7 @@ use chrono::Utc;
 use maxio_common::error::{MaxioError, Result};
 
 use crate::{
-    policy::evaluate_policy,
+    policy::{evaluate_policy_with_context, validate_policy},
     store::IamStore,
     types::{Effect, Policy, PolicyStatement, User},
 };
@@ -18,6 +18,11 @@ pub struct IAMSys {
     store: IamStore,
     users: Arc<RwLock<HashMap<String, User>>>,
     policies: Arc<RwLock<HashMap<String, Policy>>>,
+    /// Last revision written for each policy name, kept after the policy is
+    /// deleted so a recreated policy never reuses an earlier revision. Held
+    /// across policy writes so a revision check and the write it guards
+    /// cannot interleave with another update.
+    policy_revisions: Arc<tokio::sync::Mutex<HashMap<String, u64>>>,
 }
 
 impl IAMSys {
@@ -29,6 +34,8 @@ impl IAMSys {
             policies.insert(policy.name.clone(), policy);
         }
 
+        let policy_revisions = store.load_policy_revisions().await?;
+
         let mut users = HashMap::new();
         for user in store.list_users().await? {
             users.insert(user.access_key.clone(), user);
@@ -38,6 +45,7 @@ impl IAMSys {
             store,
             users: Arc::new(RwLock::new(users)),
             policies: Arc::new(RwLock::new(policies)),
+            policy_revisions: Arc::new(tokio::sync::Mutex::new(policy_revisions)),
         };
 
         sys.ensure_builtin_policies().await?;
@@ -90,23 +98,63 @@ impl IAMSys {
     }
 
     pub async fn create_policy(&self, policy: Policy) -> Result<()> {
-        if policy.name.is_empty() {
-            return Err(MaxioError::InvalidArgument(
-                "policy name is required".to_string(),
-            ));
-        }
-        if policy.statements.is_empty() {
-            return Err(MaxioError::InvalidArgument(
-                "policy must include at least one statement".to_string(),
-            ));
+        self.create_policy_if_revision(policy, None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        Ok(self.policies_read()?.get(name).cloned())
+    }
+
+    /// The policy together with its revision. Policies stored before
+    /// revisions were tracked read as revision 0.
+    pub async fn get_policy_with_revision(&self, name: &str) -> Result<Option<(Policy, u64)>> {
+        let revisions = self.policy_revisions.lock().await;
+        let revision = revisions.get(name).copied().unwrap_or(0);
+        Ok(self
+            .policies_read()?
+            .get(name)
+            .map(|policy| (policy.clone(), revision)))
+    }
+
+    /// Stores the policy and returns its new revision. With
+    /// `expected_revision` set the write only happens while the stored policy
+    /// still has that revision, and fails with `PreconditionFailed` otherwise.
+    pub async fn create_policy_if_revision(
+        &self,
+        policy: Policy,
+        expected_revision: Option<u64>,
+    ) -> Result<u64> {
+        validate_policy(&policy)?;
+
+        let mut revisions = self.policy_revisions.lock().await;
+        let last = revisions.get(&policy.name).copied().unwrap_or(0);
+        let current = self
+            .policies_read()?
+            .contains_key(&policy.name)
+            .then_some(last);
+        if let Some(expected) = expected_revision
+            && current != Some(expected)
+        {
+            return Err(MaxioError::PreconditionFailed(format!(
+                "policy {} is not at revision {expected}",
+                policy.name
+            )));
         }
 
+        // The revision is recorded first, so it is never handed out twice
+        // even if storing the policy fails.
+        let revision = last + 1;
+        revisions.insert(policy.name.clone(), revision);
+        self.store.save_policy_revisions(&revisions).await?;
         self.store.save_policy(&policy).await?;
         self.policies_write()?.insert(policy.name.clone(), policy);
-        Ok(())
+        Ok(revision)
     }
 
     pub async fn delete_policy(&self, name: &str) -> Result<()> {
+        let _revisions = self.policy_revisions.lock().await;
         self.store.delete_policy(name).await?;
         self.policies_write()?.remove(name);
 
@@ -184,6 +232,18 @@ impl IAMSys {
     }
 
     pub fn check_permission(&self, access_key: &str, action: &str, resource: &str) -> bool {
+        self.check_permission_with_context(access_key, action, resource, &HashMap::new())
+    }
+
+    /// Evaluates the user's policies with request condition keys such as
+    /// `s3:prefix`.
+    pub fn check_permission_with_context(
+        &self,
+        access_key: &str,
+        action: &str,
+        resource: &str,
+        context: &HashMap<String, String>,
+    ) -> bool {
         let users = match self.users_read() {
             Ok(users) => users,
             Err(_) => return false,
@@ -202,7 +262,7 @@ impl IAMSys {
             .filter_map(|name| policies_map.get(name).cloned())
             .collect::<Vec<_>>();
 
-        evaluate_policy(&policies, action, resource)
+        evaluate_policy_with_context(&policies, action, resource, context)
     }
 
     pub fn user_secret_key(&self, access_key: &str) -> Option<String> {
@@ -261,6 +321,7 @@ fn builtin_readwrite_policy() -> Policy {
             effect: Effect::Allow,
             actions: vec!["s3:*".to_string()],
             resources: vec!["arn:aws:s3:::*".to_string(), "arn:aws:s3:::*/*".to_string()],
+            conditions: HashMap::new(),
         }],
     }
 }
@@ -273,6 +334,77 @@ fn builtin_readonly_policy() -> Policy {
             effect: Effect::Allow,
             actions: vec!["s3:Get*".to_string(), "s3:List*".to_string()],
             resources: vec!["arn:aws:s3:::*".to_string(), "arn:aws:s3:::*/*".to_string()],
+            conditions: HashMap::new(),
         }],
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+    use maxio_common::error::MaxioError;
+
+    use super::IAMSys;
+    use crate::types::{Effect, Policy, PolicyStatement};
+
+    fn team_policy() -> Policy {
+        Policy {
+            name: "team".to_string(),
+            version: "2012-10-17".to_string(),
+            statements: vec![PolicyStatement {
+                effect: Effect::Allow,
+                actions: vec!["s3:GetObject".to_string()],
+                resources: vec!["arn:aws:s3:::bucket/*".to_string()],
+                conditions: HashMap::new(),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn recreated_policies_never_reuse_a_revision() {
+        let root = std::env::temp_dir().join(format!(
+            "maxio-iam-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let iam = IAMSys::new(&root).await.expect("create iam");
+
+        let first = iam
+            .create_policy_if_revision(team_policy(), None)
+            .await
+            .expect("create policy");
+        iam.delete_policy("team").await.expect("delete policy");
+        let recreated = iam
+            .create_policy_if_revision(team_policy(), None)
+            .await
+            .expect("recreate policy");
+        assert!(recreated > first);
+
+        // A writer still holding the first revision must not overwrite the
+        // recreated policy, also after a restart.
+        let iam = IAMSys::new(&root).await.expect("reload iam");
+        let stale = iam
+            .create_policy_if_revision(team_policy(), Some(first))
+            .await;
+        assert!(
+            matches!(stale, Err(MaxioError::PreconditionFailed(_))),
+            "{stale:?}"
+        );
+        let (_, revision) = iam
+            .get_policy_with_revision("team")
+            .await
+            .expect("read policy")
+            .expect("policy exists");
+        assert_eq!(revision, recreated);
+
+        // The revision is bookkeeping, not part of the policy document.
+        let stored = tokio::fs::read_to_string(root.join(".iam/policies/team.json"))
+            .await
+            .expect("read stored policy");
+        assert!(!stored.contains("revision"), "{stored}");
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 
@@ -23,6 +25,10 @@ pub struct Policy {
     pub statements: Vec<PolicyStatement>,
 }
 
+/// Condition values keyed by operator (`StringLike`, ...) and then by
+/// condition key (`s3:prefix`, ...).
+pub type Conditions = HashMap<String, HashMap<String, Vec<String>>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicyStatement {
     #[serde(alias = "Effect")]
@@ -31,6 +37,13 @@ pub struct PolicyStatement {
     pub actions: Vec<String>,
     #[serde(alias = "Resource", deserialize_with = "string_or_vec")]
     pub resources: Vec<String>,
+    #[serde(
+        default,
+        alias = "Condition",
+        skip_serializing_if = "HashMap::is_empty",
+        deserialize_with = "conditions_from_json"
+    )]
+    pub conditions: Conditions,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -62,6 +75,28 @@ where
     }
 }
 
+fn conditions_from_json<'de, D>(
+    deserializer: D,
+) -> Result<Conditions, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Values(#[serde(deserialize_with = "string_or_vec")] Vec<String>);
+
+    let raw = HashMap::<String, HashMap<String, Values>>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(operator, keys)| {
+            let keys = keys
+                .into_iter()
+                .map(|(key, Values(values))| (key, values))
+                .collect();
+            (operator, keys)
+        })
+        .collect())
+}
+
 fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: Deserializer<'de>,
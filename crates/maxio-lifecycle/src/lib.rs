@@ -4,8 +4,8 @@ pub mod system;
 pub mod types;
 
 pub use scanner::{FolderScanner, ScanMode, ScannerConfig, ScannerCycle, ScannerItem};
-pub use store::LifecycleStore;
-pub use system::LifecycleSys;
+pub use store::{LifecycleObjectEntry, LifecycleScanState, LifecycleStore};
+pub use system::{LifecycleSys, config_warnings, validate_config};
 pub use types::{
     Expiration, LifecycleConfiguration, LifecycleFilter, LifecycleRule, NoncurrentVersionExpiration,
     RuleStatus,
@@ -11,10 +11,11 @@ use maxio_common::{
     error::{MaxioError, Result},
     types::ObjectInfo,
 };
-use maxio_storage::traits::ObjectLayer;
+use maxio_storage::traits::{ListOrder, ObjectLayer};
 use serde::{Deserialize, Serialize};
 use tokio::fs::{self, OpenOptions};
 use tracing::{debug, warn};
+use uuid::Uuid;
 
 use crate::{
     system::is_expired,
@@ -25,6 +26,10 @@ use crate::{
 const SCANNER_STATE_FILE: &str = ".scanner-state.json";
 const SCANNER_LOCK_FILE: &str = ".scanner-leader.lock";
 const SMALL_BRANCH_OBJECT_THRESHOLD: usize = 500;
+/// How long a leader lock stays valid without a heartbeat. The leader
+/// heartbeats three times per TTL, so a lock older than this belongs to a
+/// leader that is gone and may be taken over.
+const DEFAULT_LEADER_LOCK_TTL: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScanMode {
@@ -85,6 +90,8 @@ pub struct ScannerObjectCache {
     pub etag: String,
     pub size: i64,
     pub last_modified_unix_nanos: i64,
+    #[serde(default)]
+    pub last_access_unix_nanos: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -104,6 +111,15 @@ pub struct FolderScanner {
     pub data_usage_cache: HashMap<String, u64>,
     state_path: PathBuf,
     lock_path: PathBuf,
+    leader_lock_ttl: Duration,
+}
+
+/// Contents of the leader lock file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LeaderLockRecord {
+    owner: String,
+    created: DateTime<Utc>,
+    heartbeat: DateTime<Utc>,
 }
 
 impl FolderScanner {
@@ -118,9 +134,17 @@ impl FolderScanner {
             mode,
             cycle: ScannerCycle::default(),
             data_usage_cache: HashMap::new(),
+            leader_lock_ttl: DEFAULT_LEADER_LOCK_TTL,
         }
     }
 
+    /// Sets how long the leader lock survives without a heartbeat before
+    /// another scanner may take it over.
+    pub fn with_leader_lock_ttl(mut self, ttl: Duration) -> Self {
+        self.leader_lock_ttl = ttl;
+        self
+    }
+
     pub fn set_scan_mode(&mut self, mode: ScanMode) {
         self.mode = mode;
     }
@@ -225,7 +249,7 @@ impl FolderScanner {
             return ScanMode::Normal;
         }
 
-        if self.cycle.current % deep_scan_cycle_interval == 0 {
+        if self.cycle.current.is_multiple_of(deep_scan_cycle_interval) {
             ScanMode::Deep
         } else {
             ScanMode::Normal
@@ -244,7 +268,9 @@ impl FolderScanner {
         let mut scanned_count = 0_u64;
 
         loop {
-            let page = object_layer.list_objects(bucket, "", &marker, "", 1000).await?;
+            let page = object_layer
+                .list_objects(bucket, "", &marker, "", 1000, ListOrder::KeyAscending)
+                .await?;
             for object in page.objects {
                 scanned_count = scanned_count.saturating_add(1);
                 self.process_object(
@@ -285,6 +311,7 @@ impl FolderScanner {
             etag: object.etag.clone(),
             size: object.size,
             last_modified_unix_nanos: object.last_modified.timestamp_nanos_opt().unwrap_or_default(),
+            last_access_unix_nanos: None,
         };
 
         let changed = self.old_cache.get(&cache_key) != Some(&cache_value);
@@ -354,7 +381,7 @@ impl FolderScanner {
         cycle.hash(&mut hasher);
         bucket.hash(&mut hasher);
         object_name.hash(&mut hasher);
-        hasher.finish() % sample_rate == 0
+        hasher.finish().is_multiple_of(sample_rate)
     }
 
     fn compact_updates(&mut self) {
@@ -442,7 +469,43 @@ impl FolderScanner {
         Ok(())
     }
 
+    /// Takes the leader lock unless another live scanner holds it. A lock
+    /// whose heartbeat is older than the TTL is left over from a crashed
+    /// leader and is taken over.
     async fn acquire_leader_lock(&self) -> Result<Option<LeaderLockGuard>> {
+        if let Some(guard) = self.create_leader_lock().await? {
+            return Ok(Some(guard));
+        }
+        match leader_lock_age(&self.lock_path).await? {
+            Some(age) if age < self.leader_lock_ttl => return Ok(None),
+            _ => {}
+        }
+
+        // Move the stale lock aside rather than deleting it, so a scanner
+        // that took it over first is not robbed of a fresh lock.
+        let stale_path = self
+            .lock_path
+            .with_extension(format!("lock.stale-{}", Uuid::new_v4()));
+        match fs::rename(&self.lock_path, &stale_path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return self.create_leader_lock().await;
+            }
+            Err(err) => return Err(MaxioError::Io(err)),
+        }
+        let moved_age = leader_lock_age(&stale_path).await?;
+        if moved_age.is_some_and(|age| age < self.leader_lock_ttl) {
+            // Another scanner renewed the lock in between; put it back.
+            let _ = fs::hard_link(&stale_path, &self.lock_path).await;
+            try_remove_file(&stale_path).await?;
+            return Ok(None);
+        }
+        try_remove_file(&stale_path).await?;
+        warn!(path = %self.lock_path.display(), "taking over stale scanner leader lock");
+        self.create_leader_lock().await
+    }
+
+    async fn create_leader_lock(&self) -> Result<Option<LeaderLockGuard>> {
         let lock_file = OpenOptions::new()
             .write(true)
             .create_new(true)
@@ -451,12 +514,18 @@ impl FolderScanner {
 
         match lock_file {
             Ok(_) => {
-                let lock_payload = format!(
-                    "{{\"created\":\"{}\"}}",
-                    Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
-                );
-                fs::write(&self.lock_path, lock_payload).await?;
-                Ok(Some(LeaderLockGuard::new(self.lock_path.clone())))
+                let now = Utc::now();
+                let record = LeaderLockRecord {
+                    owner: Uuid::new_v4().to_string(),
+                    created: now,
+                    heartbeat: now,
+                };
+                write_leader_lock(&self.lock_path, &record).await?;
+                Ok(Some(LeaderLockGuard::new(
+                    self.lock_path.clone(),
+                    record,
+                    self.leader_lock_ttl,
+                )))
             }
             Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
             Err(err) => Err(MaxioError::Io(err)),
@@ -464,23 +533,96 @@ impl FolderScanner {
     }
 }
 
+/// Holds the leader lock and keeps its heartbeat fresh until released or
+/// dropped. A guard dropped without `release` (a failed cycle) leaves the
+/// file to go stale, after which any scanner may take it over.
 #[derive(Debug)]
 struct LeaderLockGuard {
     path: PathBuf,
+    owner: String,
+    heartbeat: tokio::task::JoinHandle<()>,
 }
 
 impl LeaderLockGuard {
-    fn new(path: PathBuf) -> Self {
-        Self { path }
+    fn new(path: PathBuf, record: LeaderLockRecord, ttl: Duration) -> Self {
+        let owner = record.owner.clone();
+        let heartbeat = tokio::spawn(heartbeat_leader_lock(path.clone(), record, ttl));
+        Self {
+            path,
+            owner,
+            heartbeat,
+        }
     }
 
     async fn release(self) {
+        self.heartbeat.abort();
+        if read_leader_lock(&self.path)
+            .await
+            .is_some_and(|record| record.owner != self.owner)
+        {
+            warn!(path = %self.path.display(), "scanner leader lock was taken over before release");
+            return;
+        }
         if let Err(err) = try_remove_file(&self.path).await {
             warn!(path = %self.path.display(), error = %err, "failed to release scanner leader lock");
         }
     }
 }
 
+impl Drop for LeaderLockGuard {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+    }
+}
+
+async fn heartbeat_leader_lock(path: PathBuf, mut record: LeaderLockRecord, ttl: Duration) {
+    let period = (ttl / 3).max(Duration::from_millis(1));
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        ticker.tick().await;
+        if read_leader_lock(&path)
+            .await
+            .is_some_and(|current| current.owner != record.owner)
+        {
+            warn!(path = %path.display(), "scanner leader lock was taken over");
+            return;
+        }
+        record.heartbeat = Utc::now();
+        if let Err(err) = write_leader_lock(&path, &record).await {
+            warn!(path = %path.display(), error = %err, "failed to refresh scanner leader lock");
+        }
+    }
+}
+
+async fn read_leader_lock(path: &Path) -> Option<LeaderLockRecord> {
+    let bytes = fs::read(path).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+async fn write_leader_lock(path: &Path, record: &LeaderLockRecord) -> Result<()> {
+    let bytes = serde_json::to_vec(record).map_err(|err| {
+        MaxioError::InternalError(format!("failed to serialize scanner leader lock: {err}"))
+    })?;
+    fs::write(path, bytes).await?;
+    Ok(())
+}
+
+/// Time since the lock at `path` last heartbeated, or `None` when there is
+/// no lock. Files without a readable heartbeat (older formats, a write in
+/// progress) fall back to their modification time.
+async fn leader_lock_age(path: &Path) -> Result<Option<Duration>> {
+    let modified = match fs::metadata(path).await {
+        Ok(metadata) => metadata.modified()?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(MaxioError::Io(err)),
+    };
+    let heartbeat = match read_leader_lock(path).await {
+        Some(record) => record.heartbeat,
+        None => DateTime::<Utc>::from(modified),
+    };
+    Ok(Some((Utc::now() - heartbeat).to_std().unwrap_or_default()))
+}
+
 async fn try_remove_file(path: &Path) -> Result<()> {
     match fs::remove_file(path).await {
         Ok(()) => Ok(()),
@@ -488,3 +630,80 @@ async fn try_remove_file(path: &Path) -> Result<()> {
         Err(err) => Err(MaxioError::Io(err)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_scanner() -> FolderScanner {
+        let root = std::env::temp_dir().join(format!("maxio-scanner-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create scanner root");
+        FolderScanner::new(root, ScanMode::Normal)
+    }
+
+    async fn write_record(path: &Path, owner: &str, heartbeat: DateTime<Utc>) {
+        let record = LeaderLockRecord {
+            owner: owner.to_string(),
+            created: heartbeat,
+            heartbeat,
+        };
+        write_leader_lock(path, &record).await.expect("write lock");
+    }
+
+    #[tokio::test]
+    async fn stale_leader_lock_is_taken_over() {
+        let scanner = test_scanner().with_leader_lock_ttl(Duration::from_secs(60));
+        write_record(
+            &scanner.lock_path,
+            "crashed",
+            Utc::now() - chrono::Duration::minutes(5),
+        )
+        .await;
+
+        let guard = scanner
+            .acquire_leader_lock()
+            .await
+            .unwrap()
+            .expect("stale lock taken over");
+        let record = read_leader_lock(&scanner.lock_path).await.expect("lock");
+        assert_eq!(record.owner, guard.owner);
+        assert_ne!(record.owner, "crashed");
+
+        guard.release().await;
+        assert!(!scanner.lock_path.exists());
+        let _ = fs::remove_dir_all(&scanner.root).await;
+    }
+
+    #[tokio::test]
+    async fn live_leader_lock_is_honored() {
+        let scanner = test_scanner().with_leader_lock_ttl(Duration::from_secs(60));
+        write_record(&scanner.lock_path, "leader", Utc::now()).await;
+        assert!(scanner.acquire_leader_lock().await.unwrap().is_none());
+        let record = read_leader_lock(&scanner.lock_path).await.expect("lock");
+        assert_eq!(record.owner, "leader");
+        let _ = fs::remove_dir_all(&scanner.root).await;
+    }
+
+    #[tokio::test]
+    async fn heartbeat_keeps_the_lock_past_its_ttl() {
+        let ttl = Duration::from_millis(150);
+        let leader = test_scanner().with_leader_lock_ttl(ttl);
+        let follower =
+            FolderScanner::new(leader.root.clone(), ScanMode::Normal).with_leader_lock_ttl(ttl);
+
+        let guard = leader
+            .acquire_leader_lock()
+            .await
+            .unwrap()
+            .expect("first lock");
+        tokio::time::sleep(ttl * 3).await;
+        assert!(follower.acquire_leader_lock().await.unwrap().is_none());
+
+        // A leader that stops heartbeating loses the lock after the TTL.
+        drop(guard);
+        tokio::time::sleep(ttl * 2).await;
+        let taken_over = follower.acquire_leader_lock().await.unwrap();
+        assert!(taken_over.is_some());
+        let _ = fs::remove_dir_all(&leader.root).await;
+    }
+}
@@ -1,54 +1,183 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
 
+use chrono::{DateTime, Utc};
 use maxio_common::error::{MaxioError, Result};
-use tokio::fs;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
 
-use crate::types::LifecycleConfiguration;
+use crate::{scanner::ScannerObjectCache, types::LifecycleConfiguration};
 
 const LIFECYCLE_FILE_NAME: &str = ".lifecycle.json";
+const LIFECYCLE_SCAN_FILE_NAME: &str = ".lifecycle-scan.json";
+
+/// Progress of the lifecycle scan of one bucket, persisted between runs so
+/// a run resumes where the previous one stopped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleScanState {
+    /// Last key evaluated per rule prefix. A prefix without a marker is
+    /// scanned from the start.
+    pub markers: HashMap<String, String>,
+    /// Objects already evaluated, keyed by object key.
+    pub objects: BTreeMap<String, LifecycleObjectEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleObjectEntry {
+    pub object: ScannerObjectCache,
+    /// When the object expires under the current rules, if ever.
+    pub due: Option<DateTime<Utc>>,
+}
+
+/// On-disk form of a bucket's lifecycle configuration. `version` counts the
+/// writes of the configuration so updates can be made conditional on it;
+/// files written before it existed read as version 0.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredLifecycleConfig {
+    #[serde(default)]
+    version: u64,
+    #[serde(flatten)]
+    config: LifecycleConfiguration,
+}
 
 #[derive(Debug, Clone)]
 pub struct LifecycleStore {
     root: PathBuf,
+    /// Serializes configuration writes so a version check and the write it
+    /// guards cannot interleave with another update.
+    config_writes: Arc<Mutex<()>>,
 }
 
 impl LifecycleStore {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            config_writes: Arc::new(Mutex::new(())),
+        }
     }
 
     pub async fn get_config(&self, bucket: &str) -> Result<Option<LifecycleConfiguration>> {
+        Ok(self
+            .get_versioned_config(bucket)
+            .await?
+            .map(|(config, _)| config))
+    }
+
+    /// The bucket's configuration together with its version.
+    pub async fn get_versioned_config(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<(LifecycleConfiguration, u64)>> {
         self.ensure_bucket_dir(bucket).await?;
         let path = self.config_path(bucket);
         match fs::read(&path).await {
-            Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|err| {
-                MaxioError::InternalError(format!(
-                    "failed to parse bucket lifecycle config {}: {err}",
-                    path.display()
-                ))
-            }),
+            Ok(bytes) => serde_json::from_slice::<StoredLifecycleConfig>(&bytes)
+                .map(|stored| Some((stored.config, stored.version)))
+                .map_err(|err| {
+                    MaxioError::InternalError(format!(
+                        "failed to parse bucket lifecycle config {}: {err}",
+                        path.display()
+                    ))
+                }),
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
             Err(err) => Err(MaxioError::Io(err)),
         }
     }
 
     pub async fn set_config(&self, bucket: &str, config: &LifecycleConfiguration) -> Result<()> {
-        self.ensure_bucket_dir(bucket).await?;
+        self.set_config_if_version(bucket, config, None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Writes the configuration and returns its new version. With
+    /// `expected_version` set the write only happens while the stored
+    /// configuration still has that version, and fails with
+    /// `PreconditionFailed` otherwise.
+    pub async fn set_config_if_version(
+        &self,
+        bucket: &str,
+        config: &LifecycleConfiguration,
+        expected_version: Option<u64>,
+    ) -> Result<u64> {
+        let _guard = self.config_writes.lock().await;
+        let current = self
+            .get_versioned_config(bucket)
+            .await?
+            .map(|(_, version)| version);
+        if let Some(expected) = expected_version
+            && current != Some(expected)
+        {
+            return Err(MaxioError::PreconditionFailed(format!(
+                "lifecycle configuration of bucket {bucket} is not at version {expected}"
+            )));
+        }
+
         let path = self.config_path(bucket);
-        let bytes = serde_json::to_vec_pretty(config).map_err(|err| {
+        let version = current.map_or(1, |version| version + 1);
+        let bytes = serde_json::to_vec_pretty(&StoredLifecycleConfig {
+            version,
+            config: config.clone(),
+        })
+        .map_err(|err| {
             MaxioError::InternalError(format!(
                 "failed to serialize bucket lifecycle config {}: {err}",
                 path.display()
             ))
         })?;
         fs::write(path, bytes).await?;
-        Ok(())
+        // Cached due times were computed from the previous rules.
+        self.delete_scan_state(bucket).await?;
+        Ok(version)
     }
 
     pub async fn delete_config(&self, bucket: &str) -> Result<()> {
+        let _guard = self.config_writes.lock().await;
         self.ensure_bucket_dir(bucket).await?;
         let path = self.config_path(bucket);
         match fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(MaxioError::Io(err)),
+        }
+        self.delete_scan_state(bucket).await
+    }
+
+    pub async fn get_scan_state(&self, bucket: &str) -> Result<LifecycleScanState> {
+        self.ensure_bucket_dir(bucket).await?;
+        let path = self.scan_state_path(bucket);
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                MaxioError::InternalError(format!(
+                    "failed to parse lifecycle scan state {}: {err}",
+                    path.display()
+                ))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(LifecycleScanState::default())
+            }
+            Err(err) => Err(MaxioError::Io(err)),
+        }
+    }
+
+    pub async fn set_scan_state(&self, bucket: &str, state: &LifecycleScanState) -> Result<()> {
+        self.ensure_bucket_dir(bucket).await?;
+        let path = self.scan_state_path(bucket);
+        let bytes = serde_json::to_vec(state).map_err(|err| {
+            MaxioError::InternalError(format!(
+                "failed to serialize lifecycle scan state {}: {err}",
+                path.display()
+            ))
+        })?;
+        fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    async fn delete_scan_state(&self, bucket: &str) -> Result<()> {
+        match fs::remove_file(self.scan_state_path(bucket)).await {
             Ok(()) => Ok(()),
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
             Err(err) => Err(MaxioError::Io(err)),
@@ -78,6 +207,10 @@ impl LifecycleStore {
         self.bucket_dir(bucket).join(LIFECYCLE_FILE_NAME)
     }
 
+    fn scan_state_path(&self, bucket: &str) -> PathBuf {
+        self.bucket_dir(bucket).join(LIFECYCLE_SCAN_FILE_NAME)
+    }
+
     fn bucket_dir(&self, bucket: &str) -> PathBuf {
         self.root.join(bucket)
     }
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashSet, ops::Bound, path::PathBuf, sync::Arc};
 
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use maxio_common::{
     error::{MaxioError, Result},
     types::ObjectInfo,
@@ -9,29 +9,72 @@ use maxio_storage::traits::{ObjectLayer, ObjectVersion};
 use tracing::warn;
 
 use crate::{
-    store::LifecycleStore,
+    scanner::ScannerObjectCache,
+    store::{LifecycleObjectEntry, LifecycleScanState, LifecycleStore},
     types::{LifecycleConfiguration, LifecycleRule, RuleStatus},
 };
 
+pub const DEFAULT_SCAN_BATCH_SIZE: usize = 10_000;
+const SCAN_CHECKPOINT_KEYS: usize = 1000;
+/// Upper bound on the versions fetched by one listing call of a scan.
+const SCAN_PAGE_VERSIONS: usize = 1000;
+
 pub struct LifecycleSys {
     store: LifecycleStore,
     data_dir: PathBuf,
+    scan_batch_size: usize,
 }
 
 impl LifecycleSys {
     pub fn new(store: LifecycleStore, data_dir: PathBuf) -> Self {
-        Self { store, data_dir }
+        Self {
+            store,
+            data_dir,
+            scan_batch_size: DEFAULT_SCAN_BATCH_SIZE,
+        }
+    }
+
+    /// Caps how many current objects of a bucket one run evaluates; the rest
+    /// are picked up by the following runs from the persisted marker.
+    pub fn with_scan_batch_size(mut self, objects: usize) -> Self {
+        self.scan_batch_size = objects.max(1);
+        self
+    }
+
+    pub async fn scan_state(&self, bucket: &str) -> Result<LifecycleScanState> {
+        self.store.get_scan_state(bucket).await
     }
 
     pub async fn get_config(&self, bucket: &str) -> Result<Option<LifecycleConfiguration>> {
         self.store.get_config(bucket).await
     }
 
+    pub async fn get_versioned_config(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<(LifecycleConfiguration, u64)>> {
+        self.store.get_versioned_config(bucket).await
+    }
+
     pub async fn set_config(&self, bucket: &str, config: LifecycleConfiguration) -> Result<()> {
         validate_config(&config)?;
         self.store.set_config(bucket, &config).await
     }
 
+    /// Like [`set_config`](Self::set_config), but only while the stored
+    /// configuration is at `expected_version`. Returns the new version.
+    pub async fn set_config_if_version(
+        &self,
+        bucket: &str,
+        config: LifecycleConfiguration,
+        expected_version: Option<u64>,
+    ) -> Result<u64> {
+        validate_config(&config)?;
+        self.store
+            .set_config_if_version(bucket, &config, expected_version)
+            .await
+    }
+
     pub async fn delete_config(&self, bucket: &str) -> Result<()> {
         self.store.delete_config(bucket).await
     }
@@ -64,115 +107,291 @@ impl LifecycleSys {
         bucket: &str,
         config: &LifecycleConfiguration,
     ) -> Result<()> {
-        let mut by_prefix: HashMap<String, Vec<&LifecycleRule>> = HashMap::new();
-        for rule in &config.rules {
-            if rule.status != RuleStatus::Enabled {
-                continue;
-            }
-            let prefix = rule
-                .filter
-                .as_ref()
-                .and_then(|filter| filter.prefix.clone())
-                .unwrap_or_default();
-            by_prefix.entry(prefix).or_default().push(rule);
-        }
+        let rules = config
+            .rules
+            .iter()
+            .filter(|rule| rule.status == RuleStatus::Enabled)
+            .collect::<Vec<_>>();
+        let mut prefixes = rules
+            .iter()
+            .map(|rule| rule_prefix(rule).to_string())
+            .collect::<Vec<_>>();
+        prefixes.sort();
+        prefixes.dedup();
 
-        for (prefix, rules) in by_prefix {
-            if rules.is_empty() {
-                continue;
-            }
-            self.apply_current_version_rules(object_layer, bucket, &prefix, &rules)
-                .await;
-            self.apply_noncurrent_version_rules(object_layer, bucket, &prefix, &rules)
-                .await;
+        let mut state = self.store.get_scan_state(bucket).await?;
+        let mut budget = self.scan_batch_size;
+        for prefix in &prefixes {
+            self.apply_prefix_rules(
+                object_layer,
+                bucket,
+                prefix,
+                &prefixes,
+                &rules,
+                &mut state,
+                &mut budget,
+            )
+            .await?;
         }
 
         Ok(())
     }
 
-    async fn apply_current_version_rules(
+    /// Evaluates the keys under `prefix` from a single version listing:
+    /// current version expiration, noncurrent version expiration and expired
+    /// delete marker cleanup all see the same snapshot of each key. A key is
+    /// handled only by the pass of its longest matching rule prefix, with
+    /// every enabled rule that applies to it.
+    ///
+    /// Keys are listed page by page from the marker persisted by the previous
+    /// run and at most `budget` keys are evaluated. Current versions whose
+    /// etag, size and mtime match the cache reuse their cached due time.
+    #[allow(clippy::too_many_arguments)]
+    async fn apply_prefix_rules(
         &self,
         object_layer: &dyn ObjectLayer,
         bucket: &str,
         prefix: &str,
+        prefixes: &[String],
         rules: &[&LifecycleRule],
-    ) {
-        let mut marker = String::new();
-        loop {
-            let page = match object_layer
-                .list_objects(bucket, prefix, &marker, "", 1000)
+        state: &mut LifecycleScanState,
+        budget: &mut usize,
+    ) -> Result<()> {
+        if *budget == 0 {
+            return Ok(());
+        }
+
+        let range_start = state.markers.get(prefix).cloned().unwrap_or_default();
+        let mut marker = range_start.clone();
+        let mut evaluated = HashSet::new();
+        let mut complete = true;
+        let mut key_marker = range_start.clone();
+        let mut version_marker = String::new();
+        let mut versions: Vec<ObjectVersion> = Vec::new();
+
+        'pages: loop {
+            // Pages are sized to the keys left in the budget, so a run never
+            // lists much further than it evaluates. Versions held back for a
+            // key split across pages grow the next page, so long histories
+            // take few calls.
+            let page_size = budget
+                .saturating_add(versions.len())
+                .clamp(1, SCAN_PAGE_VERSIONS) as i32;
+            let listed = match object_layer
+                .list_object_versions(bucket, prefix, &key_marker, &version_marker, "", page_size)
                 .await
             {
-                Ok(page) => page,
+                Ok(listed) => listed,
                 Err(err) => {
-                    warn!(bucket = %bucket, prefix = %prefix, error = %err, "failed to list objects for lifecycle scan");
-                    return;
+                    warn!(bucket = %bucket, prefix = %prefix, error = %err, "failed to list object versions for lifecycle scan");
+                    if evaluated.is_empty() {
+                        return Ok(());
+                    }
+                    complete = false;
+                    break;
                 }
             };
+            versions.extend(listed.versions);
 
-            for object in page.objects {
-                if rules.iter().any(|rule| is_expired(&object, rule)) {
-                    if let Err(err) = object_layer.delete_object(bucket, &object.key).await {
-                        warn!(bucket = %bucket, key = %object.key, error = %err, "failed to delete expired object");
-                    }
+            // The last key of a truncated page may have more versions on the
+            // next one; it is evaluated once they are all listed.
+            let held = match versions.last().filter(|_| listed.is_truncated) {
+                Some(last) => {
+                    let last = last.key.clone();
+                    let split = versions
+                        .iter()
+                        .position(|version| version.key == last)
+                        .unwrap_or(versions.len());
+                    versions.split_off(split)
+                }
+                None => Vec::new(),
+            };
+
+            // Versions are sorted by key and then newest first.
+            for key_versions in versions.chunk_by(|left, right| left.key == right.key) {
+                let key = &key_versions[0].key;
+                if owning_prefix(key, prefixes) != prefix
+                    || (!range_start.is_empty() && key.as_str() <= range_start.as_str())
+                {
+                    continue;
+                }
+                if *budget == 0 {
+                    complete = false;
+                    break 'pages;
+                }
+
+                *budget -= 1;
+                marker = key.clone();
+                evaluated.insert(key.clone());
+                let key_rules = rules
+                    .iter()
+                    .copied()
+                    .filter(|rule| key.starts_with(rule_prefix(rule)))
+                    .collect::<Vec<_>>();
+                self.apply_key_rules(object_layer, bucket, key_versions, &key_rules, state)
+                    .await;
+
+                if evaluated.len().is_multiple_of(SCAN_CHECKPOINT_KEYS) {
+                    state.markers.insert(prefix.to_string(), marker.clone());
+                    self.store.set_scan_state(bucket, state).await?;
                 }
             }
 
-            if !page.is_truncated {
+            versions = held;
+            if !listed.is_truncated {
                 break;
             }
-            marker = match page.next_marker {
-                Some(next) => next,
-                None => break,
-            };
+            key_marker = listed.next_key_marker.unwrap_or_default();
+            version_marker = listed.next_version_id_marker.unwrap_or_default();
         }
+
+        // Cached keys inside the evaluated range that were not listed are gone.
+        let range_end = if complete {
+            Bound::Unbounded
+        } else {
+            Bound::Included(marker.clone())
+        };
+        let stale = state
+            .objects
+            .range::<String, _>((Bound::Excluded(range_start), range_end))
+            .map(|(key, _)| key)
+            .filter(|key| owning_prefix(key, prefixes) == prefix && !evaluated.contains(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in stale {
+            state.objects.remove(&key);
+        }
+
+        if complete {
+            // The prefix is done; the next run starts it over.
+            state.markers.remove(prefix);
+        } else {
+            state.markers.insert(prefix.to_string(), marker);
+        }
+        self.store.set_scan_state(bucket, state).await
     }
 
-    async fn apply_noncurrent_version_rules(
+    /// `key_versions` holds every version of one key, newest first.
+    async fn apply_key_rules(
         &self,
         object_layer: &dyn ObjectLayer,
         bucket: &str,
-        prefix: &str,
+        key_versions: &[ObjectVersion],
         rules: &[&LifecycleRule],
+        state: &mut LifecycleScanState,
     ) {
+        let latest = &key_versions[0];
+        if !latest.is_delete_marker {
+            self.apply_current_version_rules(object_layer, bucket, latest, rules, state)
+                .await;
+        }
+
         let version_rules: Vec<&LifecycleRule> = rules
             .iter()
             .copied()
-            .filter(|rule| rule.noncurrent_version_expiration.is_some())
+            .filter(|rule| {
+                rule.noncurrent_version_expiration.is_some() || expires_delete_markers(rule)
+            })
             .collect();
-
         if version_rules.is_empty() {
             return;
         }
 
-        let versions = match object_layer.list_object_versions(bucket, prefix, i32::MAX).await {
-            Ok(versions) => versions,
-            Err(err) => {
-                warn!(bucket = %bucket, prefix = %prefix, error = %err, "failed to list object versions for lifecycle scan");
-                return;
+        let mut remaining = key_versions.len();
+        for (noncurrent_index, version) in key_versions.iter().skip(1).enumerate() {
+            if should_expire_noncurrent_version(version, noncurrent_index, &version_rules)
+                && self.delete_version(object_layer, bucket, version).await
+            {
+                remaining -= 1;
             }
+        }
+
+        if latest.is_delete_marker
+            && remaining == 1
+            && version_rules
+                .iter()
+                .any(|rule| expires_delete_markers(rule))
+        {
+            self.delete_version(object_layer, bucket, latest).await;
+        }
+    }
+
+    async fn apply_current_version_rules(
+        &self,
+        object_layer: &dyn ObjectLayer,
+        bucket: &str,
+        latest: &ObjectVersion,
+        rules: &[&LifecycleRule],
+        state: &mut LifecycleScanState,
+    ) {
+        let fingerprint = ScannerObjectCache {
+            etag: latest.etag.clone().unwrap_or_default(),
+            size: latest.size,
+            last_modified_unix_nanos: latest
+                .last_modified
+                .timestamp_nanos_opt()
+                .unwrap_or_default(),
+            last_access_unix_nanos: latest
+                .last_access
+                .and_then(|accessed| accessed.timestamp_nanos_opt()),
+        };
+        let due = match state.objects.get(&latest.key) {
+            Some(entry) if entry.object == fingerprint => entry.due,
+            _ => rules
+                .iter()
+                .filter_map(|rule| expiration_due(latest.last_modified, latest.last_access, rule))
+                .min(),
         };
 
-        for version in versions {
-            if should_expire_noncurrent_version(&version, &version_rules) {
-                if let Err(err) = object_layer
-                    .delete_object_version(bucket, &version.key, &version.version_id)
-                    .await
-                {
-                    warn!(
-                        bucket = %bucket,
-                        key = %version.key,
-                        version_id = %version.version_id,
-                        error = %err,
-                        "failed to delete expired noncurrent object version"
-                    );
+        if due.is_some_and(|due| Utc::now() >= due) {
+            match object_layer.delete_object(bucket, &latest.key).await {
+                Ok(_) => {
+                    state.objects.remove(&latest.key);
+                    return;
+                }
+                Err(err) => {
+                    warn!(bucket = %bucket, key = %latest.key, error = %err, "failed to delete expired object");
                 }
             }
         }
+        state.objects.insert(
+            latest.key.clone(),
+            LifecycleObjectEntry {
+                object: fingerprint,
+                due,
+            },
+        );
+    }
+
+    async fn delete_version(
+        &self,
+        object_layer: &dyn ObjectLayer,
+        bucket: &str,
+        version: &ObjectVersion,
+    ) -> bool {
+        match object_layer
+            .delete_object_version(bucket, &version.key, &version.version_id)
+            .await
+        {
+            Ok(_) => true,
+            Err(err) => {
+                warn!(
+                    bucket = %bucket,
+                    key = %version.key,
+                    version_id = %version.version_id,
+                    is_delete_marker = version.is_delete_marker,
+                    error = %err,
+                    "failed to delete expired object version"
+                );
+                false
+            }
+        }
     }
 }
 
-fn validate_config(config: &LifecycleConfiguration) -> Result<()> {
+/// Rejects configurations the scanner cannot apply: no rules, rules without
+/// an expiration action, or contradictory or negative expirations.
+pub fn validate_config(config: &LifecycleConfiguration) -> Result<()> {
     if config.rules.is_empty() {
         return Err(MaxioError::InvalidArgument(
             "lifecycle configuration must include at least one rule".to_string(),
@@ -190,14 +409,26 @@ fn validate_config(config: &LifecycleConfiguration) -> Result<()> {
         }
 
         if let Some(exp) = &rule.expiration {
-            if exp.days.is_some() && exp.date.is_some() {
+            let conditions = [
+                exp.days.is_some(),
+                exp.date.is_some(),
+                exp.days_after_last_access.is_some(),
+            ];
+            if conditions.into_iter().filter(|set| *set).count() > 1 {
                 return Err(MaxioError::InvalidArgument(format!(
-                    "lifecycle rule {} expiration cannot include both days and date",
+                    "lifecycle rule {} expiration can only include one of days, date and days after last access",
+                    rule.id
+                )));
+            }
+            if exp.expired_object_delete_marker == Some(true) && conditions.contains(&true) {
+                return Err(MaxioError::InvalidArgument(format!(
+                    "lifecycle rule {} cannot combine ExpiredObjectDeleteMarker with days or date",
                     rule.id
                 )));
             }
             if exp
                 .days
+                .or(exp.days_after_last_access)
                 .is_some_and(|days| days < 0)
             {
                 return Err(MaxioError::InvalidArgument(format!(
@@ -207,20 +438,57 @@ fn validate_config(config: &LifecycleConfiguration) -> Result<()> {
             }
         }
 
-        if let Some(noncurrent) = &rule.noncurrent_version_expiration {
-            if noncurrent.noncurrent_days < 0 {
-                return Err(MaxioError::InvalidArgument(format!(
-                    "lifecycle rule {} noncurrent days must be non-negative",
-                    rule.id
-                )));
-            }
+        if let Some(noncurrent) = &rule.noncurrent_version_expiration
+            && noncurrent.noncurrent_days < 0
+        {
+            return Err(MaxioError::InvalidArgument(format!(
+                "lifecycle rule {} noncurrent days must be non-negative",
+                rule.id
+            )));
         }
     }
 
     Ok(())
 }
 
-fn should_expire_noncurrent_version(version: &ObjectVersion, rules: &[&LifecycleRule]) -> bool {
+/// Parts of a valid configuration that probably do not do what was meant.
+pub fn config_warnings(config: &LifecycleConfiguration) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut ids = HashSet::new();
+    for rule in &config.rules {
+        if !rule.id.is_empty() && !ids.insert(rule.id.as_str()) {
+            warnings.push(format!(
+                "lifecycle rule id {} is used more than once",
+                rule.id
+            ));
+        }
+        if rule.status == RuleStatus::Disabled {
+            warnings.push(format!(
+                "lifecycle rule {} is disabled and never runs",
+                rule.id
+            ));
+        }
+        if rule
+            .expiration
+            .as_ref()
+            .is_some_and(|expiration| expiration.days == Some(0))
+        {
+            warnings.push(format!(
+                "lifecycle rule {} expires matching objects as soon as they are written",
+                rule.id
+            ));
+        }
+    }
+    warnings
+}
+
+/// `noncurrent_index` counts the noncurrent versions of the key, newest
+/// first, so `NewerNoncurrentVersions` keeps the first N of them.
+fn should_expire_noncurrent_version(
+    version: &ObjectVersion,
+    noncurrent_index: usize,
+    rules: &[&LifecycleRule],
+) -> bool {
     if version.is_latest {
         return false;
     }
@@ -229,19 +497,838 @@ fn should_expire_noncurrent_version(version: &ObjectVersion, rules: &[&Lifecycle
     rules.iter().any(|rule| {
         rule.noncurrent_version_expiration
             .as_ref()
-            .is_some_and(|policy| age_days >= i64::from(policy.noncurrent_days))
+            .is_some_and(|policy| {
+                let kept = policy
+                    .newer_noncurrent_versions
+                    .map_or(0, |newer| usize::try_from(newer).unwrap_or(usize::MAX));
+                age_days >= i64::from(policy.noncurrent_days) && noncurrent_index >= kept
+            })
     })
 }
 
+fn rule_prefix(rule: &LifecycleRule) -> &str {
+    rule.filter
+        .as_ref()
+        .and_then(|filter| filter.prefix.as_deref())
+        .unwrap_or_default()
+}
+
+/// The longest rule prefix matching `key`; `prefixes` must contain every
+/// rule prefix.
+fn owning_prefix<'a>(key: &str, prefixes: &'a [String]) -> &'a str {
+    prefixes
+        .iter()
+        .filter(|prefix| key.starts_with(prefix.as_str()))
+        .max_by_key(|prefix| prefix.len())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+fn expires_delete_markers(rule: &LifecycleRule) -> bool {
+    rule.expiration
+        .as_ref()
+        .is_some_and(|exp| exp.expired_object_delete_marker == Some(true))
+}
+
+/// `ObjectInfo` carries no access time, so access-based rules count from
+/// the object's last write here.
 pub fn is_expired(object: &ObjectInfo, rule: &LifecycleRule) -> bool {
-    if let Some(exp) = &rule.expiration {
-        if let Some(days) = exp.days {
-            let age = Utc::now() - object.last_modified;
-            return age.num_days() >= i64::from(days);
+    expiration_due(object.last_modified, None, rule).is_some_and(|due| Utc::now() >= due)
+}
+
+/// When `rule` expires a current version last modified at `last_modified`
+/// and last read at `last_access`, if ever. Access-based rules count from
+/// whichever came later, so rewriting an object restarts its idle time.
+pub fn expiration_due(
+    last_modified: DateTime<Utc>,
+    last_access: Option<DateTime<Utc>>,
+    rule: &LifecycleRule,
+) -> Option<DateTime<Utc>> {
+    let exp = rule.expiration.as_ref()?;
+    if let Some(days) = exp.days {
+        return Some(last_modified + Duration::days(i64::from(days)));
+    }
+    if let Some(days) = exp.days_after_last_access {
+        let idle_since = last_access.map_or(last_modified, |accessed| accessed.max(last_modified));
+        return Some(idle_since + Duration::days(i64::from(days)));
+    }
+    exp.date
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use maxio_common::types::BucketInfo;
+    use maxio_storage::{
+        access_tracker::{AccessTracker, DEFAULT_ACCESS_RESOLUTION},
+        single::SingleDiskObjectLayer,
+        storage_info::StorageInfo,
+        traits::{
+            CompletePart, DeleteCondition, DeletedObject, GetEncryptionOptions,
+            ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult, ListOrder,
+            ObjectPartInfo, PartInfo, PutEncryptionOptions, VersioningState,
+        },
+    };
+
+    use super::*;
+    use crate::types::{Expiration, LifecycleFilter, NoncurrentVersionExpiration};
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!(
+            "maxio-lifecycle-{label}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    async fn versioned_layer(root: &std::path::Path) -> Arc<dyn ObjectLayer> {
+        let layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        layer.make_bucket("bucket").await.expect("make bucket");
+        layer
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .expect("enable versioning");
+        layer
+    }
+
+    async fn put(layer: &dyn ObjectLayer, key: &str, body: &'static [u8]) -> String {
+        // Keep modification times distinct so version order is stable.
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        layer
+            .put_object(
+                "bucket",
+                key,
+                Bytes::from_static(body),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put object")
+            .version_id
+            .expect("version id")
+    }
+
+    async fn versions_of(layer: &dyn ObjectLayer, key: &str) -> Vec<ObjectVersion> {
+        layer
+            .list_object_versions("bucket", key, "", "", "", i32::MAX)
+            .await
+            .expect("list versions")
+            .versions
+            .into_iter()
+            .filter(|version| version.key == key)
+            .collect()
+    }
+
+    fn rule(
+        expiration: Option<Expiration>,
+        noncurrent: Option<NoncurrentVersionExpiration>,
+    ) -> LifecycleConfiguration {
+        LifecycleConfiguration {
+            rules: vec![LifecycleRule {
+                id: "versions".to_string(),
+                status: RuleStatus::Enabled,
+                filter: None,
+                expiration,
+                noncurrent_version_expiration: noncurrent,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_delete_marker_is_removed_only_when_alone() {
+        let root = temp_dir("markers");
+        let layer = versioned_layer(&root).await;
+
+        let gone = put(layer.as_ref(), "gone.txt", b"gone").await;
+        layer.delete_object("bucket", "gone.txt").await.unwrap();
+        layer
+            .delete_object_version("bucket", "gone.txt", &gone)
+            .await
+            .unwrap();
+        put(layer.as_ref(), "kept.txt", b"kept").await;
+        layer.delete_object("bucket", "kept.txt").await.unwrap();
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"));
+        lifecycle
+            .set_config(
+                "bucket",
+                rule(
+                    Some(Expiration {
+                        days: None,
+                        date: None,
+                        days_after_last_access: None,
+                        expired_object_delete_marker: Some(true),
+                    }),
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+
+        assert!(versions_of(layer.as_ref(), "gone.txt").await.is_empty());
+        let kept = versions_of(layer.as_ref(), "kept.txt").await;
+        assert_eq!(kept.len(), 2);
+        assert!(kept[0].is_delete_marker);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn newer_noncurrent_versions_are_retained() {
+        let root = temp_dir("noncurrent");
+        let layer = versioned_layer(&root).await;
+
+        let mut written = Vec::new();
+        for body in [b"v1", b"v2", b"v3", b"v4"] {
+            written.push(put(layer.as_ref(), "doc.txt", body).await);
+        }
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"));
+        lifecycle
+            .set_config(
+                "bucket",
+                rule(
+                    None,
+                    Some(NoncurrentVersionExpiration {
+                        noncurrent_days: 0,
+                        newer_noncurrent_versions: Some(1),
+                    }),
+                ),
+            )
+            .await
+            .unwrap();
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+
+        let remaining = versions_of(layer.as_ref(), "doc.txt")
+            .await
+            .into_iter()
+            .map(|version| version.version_id)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, vec![written[3].clone(), written[2].clone()]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn scan_resumes_from_persisted_marker() {
+        let root = temp_dir("resume");
+        let layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        layer.make_bucket("bucket").await.expect("make bucket");
+        let put_unversioned = |key: &'static str| {
+            let layer = Arc::clone(&layer);
+            async move {
+                layer
+                    .put_object(
+                        "bucket",
+                        key,
+                        Bytes::from_static(b"data"),
+                        None,
+                        HashMap::new(),
+                        None,
+                    )
+                    .await
+                    .expect("put object");
+            }
+        };
+        for key in ["a", "b", "c", "d", "e"] {
+            put_unversioned(key).await;
+        }
+        let exists = |key: &'static str| {
+            let layer = Arc::clone(&layer);
+            async move { layer.get_object_info("bucket", key, None).await.is_ok() }
+        };
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"))
+                .with_scan_batch_size(2);
+        lifecycle
+            .set_config(
+                "bucket",
+                rule(
+                    Some(Expiration {
+                        days: Some(0),
+                        date: None,
+                        days_after_last_access: None,
+                        expired_object_delete_marker: None,
+                    }),
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+        assert!(!exists("a").await && !exists("b").await);
+        assert!(exists("c").await);
+        let state = lifecycle.scan_state("bucket").await.unwrap();
+        assert_eq!(state.markers.get("").map(String::as_str), Some("b"));
+
+        // A key sorting before the marker is left for the next full pass.
+        put_unversioned("a2").await;
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+        assert!(exists("a2").await);
+        assert!(!exists("c").await && !exists("d").await);
+        assert!(exists("e").await);
+
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+        assert!(!exists("e").await);
+        assert!(
+            lifecycle
+                .scan_state("bucket")
+                .await
+                .unwrap()
+                .markers
+                .is_empty()
+        );
+
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+        assert!(!exists("a2").await);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn access_time_rules_expire_only_idle_objects() {
+        let root = temp_dir("access");
+        let layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        layer.make_bucket("bucket").await.expect("make bucket");
+        for key in ["idle", "rewritten", "read", "unread"] {
+            layer
+                .put_object(
+                    "bucket",
+                    key,
+                    Bytes::from_static(b"data"),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+        }
+        backdate_write(&root.join("data/bucket/idle"), Duration::days(5));
+        for key in ["idle", "rewritten"] {
+            layer
+                .record_access("bucket", key, Utc::now() - Duration::days(3))
+                .await
+                .unwrap();
         }
-        if let Some(date) = exp.date {
-            return Utc::now() >= date;
+        let tracker = AccessTracker::new(Arc::clone(&layer), DEFAULT_ACCESS_RESOLUTION);
+        tracker.record("bucket", "read");
+        tracker.flush().await;
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"));
+        lifecycle
+            .set_config(
+                "bucket",
+                rule(
+                    Some(Expiration {
+                        days: None,
+                        date: None,
+                        days_after_last_access: Some(2),
+                        expired_object_delete_marker: None,
+                    }),
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&layer))
+            .await
+            .unwrap();
+
+        let exists = |key: &'static str| {
+            let layer = Arc::clone(&layer);
+            async move { layer.get_object_info("bucket", key, None).await.is_ok() }
+        };
+        assert!(!exists("idle").await);
+        assert!(exists("rewritten").await);
+        assert!(exists("read").await);
+        assert!(exists("unread").await);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Moves the recorded write time of the unversioned object at
+    /// `object_path` back by `age`.
+    fn backdate_write(object_path: &std::path::Path, age: Duration) {
+        let meta_path = object_path.join("xl.meta");
+        let mut meta: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&meta_path).unwrap()).unwrap();
+        let written: DateTime<Utc> = serde_json::from_value(meta["mod_time"].clone()).unwrap();
+        meta["mod_time"] = serde_json::to_value(written - age).unwrap();
+        std::fs::write(&meta_path, serde_json::to_vec(&meta).unwrap()).unwrap();
+    }
+
+    /// Delegates to a single-disk layer and counts listing calls.
+    struct CountingLayer {
+        inner: SingleDiskObjectLayer,
+        object_listings: AtomicUsize,
+        version_listings: AtomicUsize,
+        /// Key marker and max keys of each version listing.
+        version_pages: std::sync::Mutex<Vec<(String, i32)>>,
+    }
+
+    impl CountingLayer {
+        async fn new(root: &Path) -> Self {
+            Self {
+                inner: SingleDiskObjectLayer::new(root.join("data"))
+                    .await
+                    .expect("object layer"),
+                object_listings: AtomicUsize::new(0),
+                version_listings: AtomicUsize::new(0),
+                version_pages: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+
+        fn take_version_pages(&self) -> Vec<(String, i32)> {
+            std::mem::take(&mut *self.version_pages.lock().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl ObjectLayer for CountingLayer {
+        async fn make_bucket(&self, bucket: &str) -> Result<()> {
+            self.inner.make_bucket(bucket).await
         }
+
+        async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+            self.inner.get_bucket_info(bucket).await
+        }
+
+        async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+            self.inner.list_buckets().await
+        }
+
+        async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+            self.inner.delete_bucket(bucket).await
+        }
+
+        async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+            self.inner.get_bucket_versioning(bucket).await
+        }
+
+        async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+            self.inner.set_bucket_versioning(bucket, state).await
+        }
+
+        async fn put_object(
+            &self,
+            bucket: &str,
+            key: &str,
+            data: Bytes,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+            encryption: Option<PutEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .put_object(bucket, key, data, content_type, metadata, encryption)
+                .await
+        }
+
+        async fn get_object(
+            &self,
+            bucket: &str,
+            key: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<(ObjectInfo, Bytes)> {
+            self.inner.get_object(bucket, key, encryption).await
+        }
+
+        async fn get_object_version(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<(ObjectInfo, Bytes)> {
+            self.inner
+                .get_object_version(bucket, key, version_id, encryption)
+                .await
+        }
+
+        async fn get_object_info(
+            &self,
+            bucket: &str,
+            key: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            self.inner.get_object_info(bucket, key, encryption).await
+        }
+
+        async fn get_object_version_info(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .get_object_version_info(bucket, key, version_id, encryption)
+                .await
+        }
+
+        async fn update_object_metadata(
+            &self,
+            bucket: &str,
+            key: &str,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .update_object_metadata(bucket, key, content_type, metadata)
+                .await
+        }
+
+        async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+            self.inner.delete_object(bucket, key).await
+        }
+
+        async fn delete_object_if(
+            &self,
+            bucket: &str,
+            key: &str,
+            condition: &DeleteCondition,
+        ) -> Result<DeletedObject> {
+            self.inner.delete_object_if(bucket, key, condition).await
+        }
+
+        async fn delete_object_version(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+        ) -> Result<DeletedObject> {
+            self.inner
+                .delete_object_version(bucket, key, version_id)
+                .await
+        }
+
+        async fn list_objects(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            marker: &str,
+            delimiter: &str,
+            max_keys: i32,
+            order: ListOrder,
+        ) -> Result<ListObjectsResult> {
+            self.object_listings.fetch_add(1, Ordering::SeqCst);
+            self.inner
+                .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
+                .await
+        }
+
+        async fn list_object_versions(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            key_marker: &str,
+            version_id_marker: &str,
+            delimiter: &str,
+            max_keys: i32,
+        ) -> Result<ListObjectVersionsResult> {
+            self.version_listings.fetch_add(1, Ordering::SeqCst);
+            self.version_pages
+                .lock()
+                .unwrap()
+                .push((key_marker.to_string(), max_keys));
+            self.inner
+                .list_object_versions(
+                    bucket,
+                    prefix,
+                    key_marker,
+                    version_id_marker,
+                    delimiter,
+                    max_keys,
+                )
+                .await
+        }
+
+        async fn create_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+        ) -> Result<String> {
+            self.inner
+                .create_multipart_upload(bucket, key, content_type, metadata)
+                .await
+        }
+
+        async fn upload_part(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+            part_number: i32,
+            data: Bytes,
+        ) -> Result<String> {
+            self.inner
+                .upload_part(bucket, key, upload_id, part_number, data)
+                .await
+        }
+
+        async fn complete_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+            parts: Vec<CompletePart>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .complete_multipart_upload(bucket, key, upload_id, parts)
+                .await
+        }
+
+        async fn abort_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+        ) -> Result<()> {
+            self.inner
+                .abort_multipart_upload(bucket, key, upload_id)
+                .await
+        }
+
+        async fn list_parts(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+        ) -> Result<Vec<PartInfo>> {
+            self.inner.list_parts(bucket, key, upload_id).await
+        }
+
+        async fn list_multipart_uploads(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            key_marker: &str,
+            upload_id_marker: &str,
+            delimiter: &str,
+            max_uploads: i32,
+        ) -> Result<ListMultipartUploadsResult> {
+            self.inner
+                .list_multipart_uploads(
+                    bucket,
+                    prefix,
+                    key_marker,
+                    upload_id_marker,
+                    delimiter,
+                    max_uploads,
+                )
+                .await
+        }
+
+        async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+            self.inner.stat_object_parts(bucket, key).await
+        }
+
+        async fn rename_prefix(
+            &self,
+            bucket: &str,
+            from_prefix: &str,
+            to_prefix: &str,
+        ) -> Result<()> {
+            self.inner
+                .rename_prefix(bucket, from_prefix, to_prefix)
+                .await
+        }
+
+        async fn storage_info(&self) -> StorageInfo {
+            self.inner.storage_info().await
+        }
+    }
+
+    #[tokio::test]
+    async fn current_and_noncurrent_rules_share_one_version_listing() {
+        let root = temp_dir("combined");
+        let layer = Arc::new(CountingLayer::new(&root).await);
+        layer.make_bucket("bucket").await.expect("make bucket");
+        layer
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .expect("enable versioning");
+
+        let old = put(layer.as_ref(), "old.txt", b"old").await;
+        let first = put(layer.as_ref(), "doc.txt", b"v1").await;
+        let second = put(layer.as_ref(), "doc.txt", b"v2").await;
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"));
+        let mut config = rule(
+            Some(Expiration {
+                days: Some(0),
+                date: None,
+                days_after_last_access: None,
+                expired_object_delete_marker: None,
+            }),
+            None,
+        );
+        config.rules[0].filter = Some(LifecycleFilter {
+            prefix: Some("old".to_string()),
+        });
+        config.rules.push(LifecycleRule {
+            id: "noncurrent".to_string(),
+            status: RuleStatus::Enabled,
+            filter: None,
+            expiration: None,
+            noncurrent_version_expiration: Some(NoncurrentVersionExpiration {
+                noncurrent_days: 0,
+                newer_noncurrent_versions: None,
+            }),
+        });
+        config.rules.push(LifecycleRule {
+            id: "noncurrent-old".to_string(),
+            status: RuleStatus::Enabled,
+            filter: Some(LifecycleFilter {
+                prefix: Some("old".to_string()),
+            }),
+            expiration: None,
+            noncurrent_version_expiration: Some(NoncurrentVersionExpiration {
+                noncurrent_days: 0,
+                newer_noncurrent_versions: None,
+            }),
+        });
+        lifecycle.set_config("bucket", config).await.unwrap();
+
+        let objects: Arc<dyn ObjectLayer> = layer.clone();
+        lifecycle.run_lifecycle_scan(objects).await.unwrap();
+
+        // One version listing per rule prefix ("" and "old"), no listing of
+        // current objects.
+        assert_eq!(layer.version_listings.load(Ordering::SeqCst), 2);
+        assert_eq!(layer.object_listings.load(Ordering::SeqCst), 0);
+
+        let doc = versions_of(layer.as_ref(), "doc.txt").await;
+        assert_eq!(
+            doc.iter().map(|v| v.version_id.clone()).collect::<Vec<_>>(),
+            vec![second]
+        );
+        assert!(!doc.iter().any(|v| v.version_id == first));
+
+        // The expired current version became noncurrent behind a delete
+        // marker; it is not expired again from the same snapshot.
+        let old_versions = versions_of(layer.as_ref(), "old.txt").await;
+        assert_eq!(old_versions.len(), 2);
+        assert!(old_versions[0].is_delete_marker);
+        assert_eq!(old_versions[1].version_id, old);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn version_listings_page_from_the_marker_within_the_budget() {
+        let root = temp_dir("paging");
+        let layer = Arc::new(CountingLayer::new(&root).await);
+        layer.make_bucket("bucket").await.expect("make bucket");
+        layer
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .expect("enable versioning");
+        for body in [b"a1", b"a2", b"a3"] {
+            put(layer.as_ref(), "a", body).await;
+        }
+        for key in ["b", "c", "d"] {
+            put(layer.as_ref(), key, b"data").await;
+        }
+
+        let lifecycle =
+            LifecycleSys::new(LifecycleStore::new(root.join("data")), root.join("data"))
+                .with_scan_batch_size(2);
+        lifecycle
+            .set_config(
+                "bucket",
+                rule(
+                    Some(Expiration {
+                        days: Some(365),
+                        date: None,
+                        days_after_last_access: None,
+                        expired_object_delete_marker: None,
+                    }),
+                    None,
+                ),
+            )
+            .await
+            .unwrap();
+
+        let objects: Arc<dyn ObjectLayer> = layer.clone();
+        lifecycle
+            .run_lifecycle_scan(Arc::clone(&objects))
+            .await
+            .unwrap();
+        let pages = layer.take_version_pages();
+        assert_eq!(pages[0], (String::new(), 2));
+        // "a" spans the first page, so its versions are listed on from there.
+        assert!(pages.len() > 1);
+        assert!(
+            pages.iter().all(|(_, max_keys)| *max_keys <= 4),
+            "{pages:?}"
+        );
+        let state = lifecycle.scan_state("bucket").await.unwrap();
+        assert_eq!(state.markers.get("").map(String::as_str), Some("b"));
+
+        lifecycle.run_lifecycle_scan(objects).await.unwrap();
+        let pages = layer.take_version_pages();
+        assert_eq!(pages[0], ("b".to_string(), 2));
+        assert!(
+            lifecycle
+                .scan_state("bucket")
+                .await
+                .unwrap()
+                .markers
+                .is_empty()
+        );
+
+        let _ = std::fs::remove_dir_all(root);
     }
-    false
 }
@@ -50,6 +50,15 @@ pub struct Expiration {
     pub days: Option<i32>,
     #[serde(rename = "Date", default, skip_serializing_if = "Option::is_none")]
     pub date: Option<DateTime<Utc>>,
+    /// Expires objects not read for this many days, counting from their
+    /// last write if they were never read. An extension to S3 for
+    /// cache-like buckets.
+    #[serde(
+        rename = "DaysAfterLastAccess",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub days_after_last_access: Option<i32>,
     #[serde(
         rename = "ExpiredObjectDeleteMarker",
         default,
@@ -62,4 +71,11 @@ pub struct Expiration {
 pub struct NoncurrentVersionExpiration {
     #[serde(rename = "NoncurrentDays")]
     pub noncurrent_days: i32,
+    /// Number of most recent noncurrent versions kept regardless of age.
+    #[serde(
+        rename = "NewerNoncurrentVersions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub newer_noncurrent_versions: Option<u32>,
 }
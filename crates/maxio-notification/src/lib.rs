@@ -4,5 +4,6 @@ pub mod targets;
 pub mod types;
 
 pub use store::NotificationStore;
-pub use system::{NotificationSys, NotificationTarget};
-pub use targets::webhook::WebhookTarget;
+pub use system::{NotificationSys, NotificationTarget, target_arn};
+pub use targets::{batch::BatchingTarget, webhook::WebhookTarget};
+pub use types::TargetConfig;
@@ -1,11 +1,13 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use maxio_common::error::{MaxioError, Result};
 use tokio::fs;
 
-use crate::types::NotificationConfiguration;
+use crate::types::{NotificationConfiguration, TargetConfig};
 
 const NOTIFICATION_FILE_NAME: &str = ".notification.json";
+const SYS_DIR_NAME: &str = ".maxio.sys";
+const TARGETS_FILE_NAME: &str = "notification-targets.json";
 
 #[derive(Debug, Clone)]
 pub struct NotificationStore {
@@ -57,6 +59,38 @@ impl NotificationStore {
         }
     }
 
+    /// Target definitions keyed by ARN; empty when none were persisted.
+    pub async fn get_targets(&self) -> Result<BTreeMap<String, TargetConfig>> {
+        let path = self.targets_path();
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                MaxioError::InternalError(format!(
+                    "failed to parse notification targets {}: {err}",
+                    path.display()
+                ))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(MaxioError::Io(err)),
+        }
+    }
+
+    pub async fn set_targets(&self, targets: &BTreeMap<String, TargetConfig>) -> Result<()> {
+        let path = self.targets_path();
+        let bytes = serde_json::to_vec_pretty(targets).map_err(|err| {
+            MaxioError::InternalError(format!(
+                "failed to serialize notification targets {}: {err}",
+                path.display()
+            ))
+        })?;
+        fs::create_dir_all(self.root.join(SYS_DIR_NAME)).await?;
+        fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    fn targets_path(&self) -> PathBuf {
+        self.root.join(SYS_DIR_NAME).join(TARGETS_FILE_NAME)
+    }
+
     fn config_path(&self, bucket: &str) -> PathBuf {
         self.bucket_dir(bucket).join(NOTIFICATION_FILE_NAME)
     }
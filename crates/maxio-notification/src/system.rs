@@ -1,17 +1,36 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use maxio_common::error::Result;
+use maxio_common::error::{MaxioError, Result};
 use tracing::warn;
 
 use crate::{
     store::NotificationStore,
-    types::{FilterRules, NotificationConfiguration, S3Event},
+    targets::{batch::BatchingTarget, webhook::WebhookTarget},
+    types::{FilterRules, NotificationConfiguration, S3Event, TargetConfig},
 };
 
 #[async_trait]
 pub trait NotificationTarget: Send + Sync {
     async fn send(&self, event: &S3Event) -> Result<()>;
+
+    /// Delivers `events` in order. Targets that can carry several records
+    /// in one request override this; by default each is sent on its own.
+    async fn send_batch(&self, events: &[S3Event]) -> Result<()> {
+        for event in events {
+            self.send(event).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the ARN a bucket notification configuration uses to reference a
+/// target, in the `arn:minio:sqs:<region>:<id>:<type>` form.
+pub fn target_arn(region: &str, id: &str, kind: &str) -> String {
+    format!("arn:minio:sqs:{region}:{id}:{kind}")
 }
 
 pub struct NotificationSys {
@@ -27,63 +46,116 @@ impl NotificationSys {
         }
     }
 
-    pub fn register_target(&mut self, name: String, target: Box<dyn NotificationTarget>) {
-        self.targets.insert(name, target);
+    /// Builds the system and re-registers every target persisted in `store`,
+    /// so bucket configurations set before a restart keep delivering.
+    pub async fn load(store: NotificationStore) -> Result<Self> {
+        let targets = store.get_targets().await?;
+        let mut sys = Self::new(store);
+        for (arn, config) in targets {
+            let target = build_target(&arn, &config);
+            sys.register_target(arn, target);
+        }
+        Ok(sys)
     }
 
-    pub async fn notify(&self, bucket: &str, event: S3Event) -> Result<()> {
-        let config = self.get_config(bucket).await?;
+    /// Persists `config` under `arn` and registers the target it describes.
+    pub async fn add_target(&mut self, arn: String, config: TargetConfig) -> Result<()> {
+        let mut targets = self.store.get_targets().await?;
+        targets.insert(arn.clone(), config.clone());
+        self.store.set_targets(&targets).await?;
+        let target = build_target(&arn, &config);
+        self.register_target(arn, target);
+        Ok(())
+    }
 
-        for queue in &config.queue_configurations {
-            if !event_matches(&queue.events, &event.event_name)
-                || !filter_matches(queue.filter.as_ref(), &event.object.key)
-            {
-                continue;
-            }
+    /// Registers the target `config` describes without persisting it, for
+    /// targets defined outside the store, such as by environment variables,
+    /// that must disappear once their definition does.
+    pub fn register_config(&mut self, arn: String, config: &TargetConfig) {
+        let target = build_target(&arn, config);
+        self.register_target(arn, target);
+    }
 
-            let Some(target_name) = target_name_from_arn(&queue.queue_arn) else {
-                warn!(queue_arn = %queue.queue_arn, "invalid queue target arn");
-                continue;
-            };
-            dispatch_target(self.targets.get(target_name), target_name, &event).await;
+    /// Unregisters `arn` and drops it from the persisted targets.
+    pub async fn remove_target(&mut self, arn: &str) -> Result<()> {
+        self.targets.remove(arn);
+        let mut targets = self.store.get_targets().await?;
+        if targets.remove(arn).is_some() {
+            self.store.set_targets(&targets).await?;
         }
+        Ok(())
+    }
 
-        for topic in &config.topic_configurations {
-            if !event_matches(&topic.events, &event.event_name)
-                || !filter_matches(topic.filter.as_ref(), &event.object.key)
-            {
-                continue;
-            }
+    /// Registers `target` under `arn` for this process only; bucket
+    /// configurations route events to it by referencing the same ARN.
+    pub fn register_target(&mut self, arn: String, target: Box<dyn NotificationTarget>) {
+        self.targets.insert(arn, target);
+    }
 
-            let Some(target_name) = target_name_from_arn(&topic.topic_arn) else {
-                warn!(topic_arn = %topic.topic_arn, "invalid topic target arn");
-                continue;
-            };
-            dispatch_target(self.targets.get(target_name), target_name, &event).await;
-        }
+    /// ARNs of all registered targets, sorted.
+    pub fn target_arns(&self) -> Vec<String> {
+        let mut arns = self.targets.keys().cloned().collect::<Vec<_>>();
+        arns.sort();
+        arns
+    }
 
-        for lambda in &config.lambda_configurations {
-            if !event_matches(&lambda.events, &event.event_name)
-                || !filter_matches(lambda.filter.as_ref(), &event.object.key)
+    pub async fn notify(&self, bucket: &str, event: S3Event) -> Result<()> {
+        let config = self.get_config(bucket).await?;
+
+        let mut dispatched = HashSet::new();
+        for (arn, events, filter) in config_targets(&config) {
+            if !event_matches(events, &event.event_name)
+                || !filter_matches(filter, &event.object.key)
             {
                 continue;
             }
 
-            let Some(target_name) = target_name_from_arn(&lambda.lambda_arn) else {
-                warn!(lambda_arn = %lambda.lambda_arn, "invalid lambda target arn");
+            let arn = self.resolve_arn(arn);
+            // A target referenced by several matching configurations still
+            // receives the event once.
+            if !dispatched.insert(arn) {
                 continue;
-            };
-            dispatch_target(self.targets.get(target_name), target_name, &event).await;
+            }
+            dispatch_target(self.targets.get(arn).map(Box::as_ref), arn, &event).await;
         }
 
         Ok(())
     }
 
+    /// The registered ARN a stored configuration's `arn` refers to.
+    /// Configurations set through [`Self::set_config`] name registered ARNs
+    /// exactly; ones stored before targets were addressed by ARN matched a
+    /// target by the ARN's last segment, and still do when exactly one
+    /// registered target has that name.
+    fn resolve_arn<'a>(&'a self, arn: &'a str) -> &'a str {
+        if self.targets.contains_key(arn) {
+            return arn;
+        }
+        let Some(name) = arn_name(arn) else {
+            return arn;
+        };
+        let mut named = self
+            .targets
+            .keys()
+            .filter(|registered| arn_name(registered) == Some(name));
+        match (named.next(), named.next()) {
+            (Some(registered), None) => registered,
+            _ => arn,
+        }
+    }
+
     pub async fn get_config(&self, bucket: &str) -> Result<NotificationConfiguration> {
         self.store.get_config(bucket).await
     }
 
     pub async fn set_config(&self, bucket: &str, config: NotificationConfiguration) -> Result<()> {
+        for (arn, _, _) in config_targets(&config) {
+            if !self.targets.contains_key(arn) {
+                return Err(MaxioError::InvalidArgument(format!(
+                    "notification target arn {arn} does not exist"
+                )));
+            }
+        }
         self.store.set_config(bucket, &config).await
     }
 
@@ -92,28 +164,70 @@ impl NotificationSys {
     }
 }
 
-async fn dispatch_target(
-    target: Option<&Box<dyn NotificationTarget>>,
-    target_name: &str,
-    event: &S3Event,
-) {
+fn build_target(arn: &str, config: &TargetConfig) -> Box<dyn NotificationTarget> {
+    match config {
+        TargetConfig::Webhook {
+            endpoint,
+            batch_window_ms,
+        } => {
+            let target = Box::new(WebhookTarget::new(endpoint.clone()));
+            match batch_window_ms {
+                Some(window_ms) if *window_ms > 0 => Box::new(BatchingTarget::new(
+                    arn.to_string(),
+                    target,
+                    Duration::from_millis(*window_ms),
+                )),
+                _ => target,
+            }
+        }
+    }
+}
+
+async fn dispatch_target(target: Option<&dyn NotificationTarget>, arn: &str, event: &S3Event) {
     let Some(target) = target else {
-        warn!(
-            target = target_name,
-            "notification target is not registered"
-        );
+        warn!(target = arn, "notification target is not registered");
         return;
     };
 
     if let Err(err) = target.send(event).await {
-        warn!(target = target_name, error = %err, "failed to send notification event");
+        warn!(target = arn, error = %err, "failed to send notification event");
     }
 }
 
-fn target_name_from_arn(arn: &str) -> Option<&str> {
+/// Last segment of an ARN, the target type for `arn:minio:sqs:...` ARNs.
+fn arn_name(arn: &str) -> Option<&str> {
     arn.rsplit(':').next().filter(|name| !name.is_empty())
 }
 
+/// Flattens queue, topic and lambda configurations into
+/// `(arn, events, filter)` triples in configuration order.
+fn config_targets(
+    config: &NotificationConfiguration,
+) -> impl Iterator<Item = (&str, &[String], Option<&FilterRules>)> {
+    let queues = config.queue_configurations.iter().map(|queue| {
+        (
+            queue.queue_arn.as_str(),
+            queue.events.as_slice(),
+            queue.filter.as_ref(),
+        )
+    });
+    let topics = config.topic_configurations.iter().map(|topic| {
+        (
+            topic.topic_arn.as_str(),
+            topic.events.as_slice(),
+            topic.filter.as_ref(),
+        )
+    });
+    let lambdas = config.lambda_configurations.iter().map(|lambda| {
+        (
+            lambda.lambda_arn.as_str(),
+            lambda.events.as_slice(),
+            lambda.filter.as_ref(),
+        )
+    });
+    queues.chain(topics).chain(lambdas)
+}
+
 fn event_matches(patterns: &[String], event_name: &str) -> bool {
     patterns.iter().any(|pattern| {
         if pattern == event_name {
@@ -131,17 +245,405 @@ fn filter_matches(filter: Option<&FilterRules>, key: &str) -> bool {
         return true;
     };
 
-    if let Some(prefix) = filter.prefix.as_deref() {
-        if !key.starts_with(prefix) {
-            return false;
+    if let Some(prefix) = filter.prefix.as_deref()
+        && !key.starts_with(prefix)
+    {
+        return false;
+    }
+
+    if let Some(suffix) = filter.suffix.as_deref()
+        && !key.ends_with(suffix)
+    {
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::types::{BucketInfo, ObjectInfo, QueueConfiguration, TopicConfiguration};
+
+    struct RecordingTarget {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NotificationTarget for RecordingTarget {
+        async fn send(&self, event: &S3Event) -> Result<()> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", event.event_name, event.object.key));
+            Ok(())
         }
     }
 
-    if let Some(suffix) = filter.suffix.as_deref() {
-        if !key.ends_with(suffix) {
-            return false;
+    fn event(name: &str, key: &str) -> S3Event {
+        S3Event {
+            event_version: "2.1".to_string(),
+            event_source: "maxio:s3".to_string(),
+            aws_region: String::new(),
+            event_time: "2024-01-01T00:00:00.000Z".to_string(),
+            event_name: name.to_string(),
+            request_id: "REQUEST".to_string(),
+            bucket: BucketInfo {
+                name: "bucket".to_string(),
+                arn: "arn:aws:s3:::bucket".to_string(),
+            },
+            object: ObjectInfo {
+                key: key.to_string(),
+                size: 0,
+                etag: String::new(),
+            },
         }
     }
 
-    true
+    fn test_root() -> std::path::PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!(
+            "maxio-notification-test-{}-{nanos}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(root.join("bucket")).unwrap();
+        root
+    }
+
+    /// Accepts one webhook request, answers 200 and returns its body.
+    async fn receive_one_webhook(listener: tokio::net::TcpListener) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            assert!(
+                read > 0,
+                "webhook connection closed before the body arrived"
+            );
+            request.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                        .await
+                        .unwrap();
+                    return body.to_string();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn events_route_to_the_arns_configured_for_the_bucket() {
+        let root = test_root();
+
+        let created = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let created_arn = target_arn("", "1", "webhook");
+        let removed_arn = target_arn("", "2", "webhook");
+        let mut sys = NotificationSys::new(NotificationStore::new(root.clone()));
+        sys.register_target(
+            created_arn.clone(),
+            Box::new(RecordingTarget {
+                events: Arc::clone(&created),
+            }),
+        );
+        sys.register_target(
+            removed_arn.clone(),
+            Box::new(RecordingTarget {
+                events: Arc::clone(&removed),
+            }),
+        );
+        assert_eq!(
+            sys.target_arns(),
+            vec![created_arn.clone(), removed_arn.clone()]
+        );
+
+        let unknown = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "missing".to_string(),
+                queue_arn: target_arn("", "3", "webhook"),
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        let err = sys.set_config("bucket", unknown).await.unwrap_err();
+        assert!(matches!(err, MaxioError::InvalidArgument(_)));
+
+        let config = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "created".to_string(),
+                queue_arn: created_arn,
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            topic_configurations: vec![TopicConfiguration {
+                id: "removed".to_string(),
+                topic_arn: removed_arn,
+                events: vec!["s3:ObjectRemoved:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        sys.set_config("bucket", config).await.unwrap();
+
+        sys.notify("bucket", event("s3:ObjectCreated:Put", "a.txt"))
+            .await
+            .unwrap();
+        sys.notify("bucket", event("s3:ObjectRemoved:Delete", "b.txt"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            created.lock().unwrap().as_slice(),
+            ["s3:ObjectCreated:Put a.txt"]
+        );
+        assert_eq!(
+            removed.lock().unwrap().as_slice(),
+            ["s3:ObjectRemoved:Delete b.txt"]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn configs_stored_before_arn_routing_keep_delivering() {
+        let root = test_root();
+        let store = NotificationStore::new(root.clone());
+        // Older releases stored any ARN ending in the target's name.
+        let legacy = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "created".to_string(),
+                queue_arn: "arn:minio:sqs:us-east-1:1:webhook".to_string(),
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        store.set_config("bucket", &legacy).await.unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut sys = NotificationSys::new(store);
+        sys.register_target(
+            target_arn("", "1", "webhook"),
+            Box::new(RecordingTarget {
+                events: Arc::clone(&events),
+            }),
+        );
+        sys.notify("bucket", event("s3:ObjectCreated:Put", "a.txt"))
+            .await
+            .unwrap();
+        assert_eq!(
+            events.lock().unwrap().as_slice(),
+            ["s3:ObjectCreated:Put a.txt"]
+        );
+
+        // With two targets of that name the legacy ARN is ambiguous.
+        sys.register_target(
+            target_arn("", "2", "webhook"),
+            Box::new(RecordingTarget {
+                events: Arc::clone(&events),
+            }),
+        );
+        sys.notify("bucket", event("s3:ObjectCreated:Put", "b.txt"))
+            .await
+            .unwrap();
+        assert_eq!(events.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn bucket_config_keeps_delivering_after_reload() {
+        let root = test_root();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/events", listener.local_addr().unwrap());
+        let arn = target_arn("", "1", "webhook");
+
+        let mut sys = NotificationSys::new(NotificationStore::new(root.clone()));
+        sys.add_target(
+            arn.clone(),
+            TargetConfig::Webhook {
+                endpoint,
+                batch_window_ms: None,
+            },
+        )
+        .await
+        .unwrap();
+        let config = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "created".to_string(),
+                queue_arn: arn.clone(),
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        sys.set_config("bucket", config).await.unwrap();
+        drop(sys);
+
+        let reloaded = NotificationSys::load(NotificationStore::new(root.clone()))
+            .await
+            .unwrap();
+        assert_eq!(reloaded.target_arns(), vec![arn]);
+        let config = reloaded.get_config("bucket").await.unwrap();
+        assert_eq!(config.queue_configurations.len(), 1);
+
+        let received = tokio::spawn(receive_one_webhook(listener));
+        reloaded
+            .notify("bucket", event("s3:ObjectCreated:Put", "a.txt"))
+            .await
+            .unwrap();
+        let body = received.await.unwrap();
+        assert!(body.contains("ObjectCreated:Put"), "{body}");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn unpersisted_targets_do_not_survive_a_reload() {
+        let root = test_root();
+        let persisted = target_arn("", "1", "webhook");
+        let from_env = target_arn("", "2", "webhook");
+        let config = TargetConfig::Webhook {
+            endpoint: "http://127.0.0.1:1/events".to_string(),
+            batch_window_ms: None,
+        };
+
+        let mut sys = NotificationSys::new(NotificationStore::new(root.clone()));
+        sys.add_target(persisted.clone(), config.clone())
+            .await
+            .unwrap();
+        sys.register_config(from_env.clone(), &config);
+        assert_eq!(sys.target_arns(), vec![persisted.clone(), from_env]);
+        drop(sys);
+
+        let mut reloaded = NotificationSys::load(NotificationStore::new(root.clone()))
+            .await
+            .unwrap();
+        assert_eq!(reloaded.target_arns(), vec![persisted.clone()]);
+
+        reloaded.remove_target(&persisted).await.unwrap();
+        assert!(reloaded.target_arns().is_empty());
+        let reloaded = NotificationSys::load(NotificationStore::new(root.clone()))
+            .await
+            .unwrap();
+        assert!(reloaded.target_arns().is_empty());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn batched_webhooks_deliver_rapid_events_in_one_request() {
+        let root = test_root();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/events", listener.local_addr().unwrap());
+        let arn = target_arn("", "1", "webhook");
+        let mut sys = NotificationSys::new(NotificationStore::new(root.clone()));
+        sys.add_target(
+            arn.clone(),
+            TargetConfig::Webhook {
+                endpoint,
+                batch_window_ms: Some(200),
+            },
+        )
+        .await
+        .unwrap();
+        let config = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "created".to_string(),
+                queue_arn: arn,
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        sys.set_config("bucket", config).await.unwrap();
+
+        let received = tokio::spawn(receive_one_webhook(listener));
+        for key in ["a.txt", "b.txt", "c.txt"] {
+            sys.notify("bucket", event("s3:ObjectCreated:Put", key))
+                .await
+                .unwrap();
+        }
+        let body = received.await.unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let keys = payload["Records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["s3"]["object"]["key"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["a.txt", "b.txt", "c.txt"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn webhook_payload_follows_aws_event_schema() {
+        let root = test_root();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/events", listener.local_addr().unwrap());
+        let arn = target_arn("", "1", "webhook");
+        let mut sys = NotificationSys::new(NotificationStore::new(root.clone()));
+        sys.add_target(
+            arn.clone(),
+            TargetConfig::Webhook {
+                endpoint,
+                batch_window_ms: None,
+            },
+        )
+        .await
+        .unwrap();
+        let config = NotificationConfiguration {
+            queue_configurations: vec![QueueConfiguration {
+                id: "created".to_string(),
+                queue_arn: arn,
+                events: vec!["s3:ObjectCreated:*".to_string()],
+                filter: None,
+            }],
+            ..Default::default()
+        };
+        sys.set_config("bucket", config).await.unwrap();
+
+        let received = tokio::spawn(receive_one_webhook(listener));
+        sys.notify(
+            "bucket",
+            event("s3:ObjectCreated:Put", "photos/my file.jpg"),
+        )
+        .await
+        .unwrap();
+        let body = received.await.unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let record = &payload["Records"][0];
+        assert_eq!(record["eventName"], "ObjectCreated:Put");
+        assert_eq!(record["eventTime"], "2024-01-01T00:00:00.000Z");
+        assert_eq!(record["responseElements"]["x-amz-request-id"], "REQUEST");
+        assert_eq!(record["s3"]["bucket"]["name"], "bucket");
+        assert_eq!(record["s3"]["object"]["key"], "photos/my+file.jpg");
+        assert_eq!(record["s3"]["object"]["size"], 0);
+        assert_eq!(record["s3"]["object"]["eTag"], "");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
 }
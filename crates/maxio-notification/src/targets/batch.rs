@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use maxio_common::error::{MaxioError, Result};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, timeout_at},
+};
+use tracing::warn;
+
+use crate::{system::NotificationTarget, types::S3Event};
+
+/// Most events delivered in one batch, however many arrive in the window.
+pub const MAX_BATCH_EVENTS: usize = 100;
+const QUEUED_EVENTS: usize = 10_000;
+
+/// Wraps a target so events arriving within `window` of each other reach it
+/// as one [`NotificationTarget::send_batch`] call, in the order they were
+/// sent. Sending only queues the event; delivery failures are logged.
+pub struct BatchingTarget {
+    sender: mpsc::Sender<S3Event>,
+}
+
+impl BatchingTarget {
+    /// Spawns the task delivering batches to `inner`, so it must be called
+    /// inside a Tokio runtime.
+    pub fn new(arn: String, inner: Box<dyn NotificationTarget>, window: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUED_EVENTS);
+        tokio::spawn(deliver_batches(arn, inner, window, receiver));
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl NotificationTarget for BatchingTarget {
+    async fn send(&self, event: &S3Event) -> Result<()> {
+        self.sender.send(event.clone()).await.map_err(|_| {
+            MaxioError::InternalError("notification batch delivery has stopped".to_string())
+        })
+    }
+}
+
+async fn deliver_batches(
+    arn: String,
+    inner: Box<dyn NotificationTarget>,
+    window: Duration,
+    mut receiver: mpsc::Receiver<S3Event>,
+) {
+    while let Some(first) = receiver.recv().await {
+        let deadline = Instant::now() + window;
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH_EVENTS {
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        if let Err(err) = inner.send_batch(&batch).await {
+            warn!(
+                target = arn,
+                events = batch.len(),
+                error = %err,
+                "failed to send notification batch"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::types::{BucketInfo, ObjectInfo};
+
+    struct RecordingTarget {
+        batches: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[async_trait]
+    impl NotificationTarget for RecordingTarget {
+        async fn send(&self, event: &S3Event) -> Result<()> {
+            self.send_batch(std::slice::from_ref(event)).await
+        }
+
+        async fn send_batch(&self, events: &[S3Event]) -> Result<()> {
+            self.batches.lock().unwrap().push(
+                events
+                    .iter()
+                    .map(|event| event.object.key.clone())
+                    .collect(),
+            );
+            Ok(())
+        }
+    }
+
+    fn event(key: &str) -> S3Event {
+        S3Event {
+            event_version: "2.1".to_string(),
+            event_source: "maxio:s3".to_string(),
+            aws_region: String::new(),
+            event_time: "2024-01-01T00:00:00.000Z".to_string(),
+            event_name: "s3:ObjectCreated:Put".to_string(),
+            request_id: "REQUEST".to_string(),
+            bucket: BucketInfo {
+                name: "bucket".to_string(),
+                arn: "arn:aws:s3:::bucket".to_string(),
+            },
+            object: ObjectInfo {
+                key: key.to_string(),
+                size: 0,
+                etag: String::new(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn events_within_the_window_are_delivered_together() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let target = BatchingTarget::new(
+            "arn".to_string(),
+            Box::new(RecordingTarget {
+                batches: Arc::clone(&batches),
+            }),
+            Duration::from_millis(200),
+        );
+
+        for key in ["a", "b", "c"] {
+            target.send(&event(key)).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        target.send(&event("d")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        assert_eq!(
+            batches.lock().unwrap().as_slice(),
+            [vec!["a", "b", "c"], vec!["d"]]
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 use maxio_common::error::{MaxioError, Result};
 
-use crate::{system::NotificationTarget, types::S3Event};
+use crate::{
+    system::NotificationTarget,
+    types::{EventNotification, S3Event},
+};
 
 pub struct WebhookTarget {
     endpoint: String,
@@ -17,10 +20,19 @@ impl WebhookTarget {
     }
 
     pub async fn send(&self, event: &S3Event) -> Result<()> {
+        self.post(&event.to_notification()).await
+    }
+
+    /// Posts every event as a record of a single payload.
+    pub async fn send_batch(&self, events: &[S3Event]) -> Result<()> {
+        self.post(&EventNotification::from_events(events)).await
+    }
+
+    async fn post(&self, notification: &EventNotification) -> Result<()> {
         let response = self
             .client
             .post(&self.endpoint)
-            .json(event)
+            .json(notification)
             .send()
             .await
             .map_err(|err| {
@@ -47,4 +59,8 @@ impl NotificationTarget for WebhookTarget {
     async fn send(&self, event: &S3Event) -> Result<()> {
         Self::send(self, event).await
     }
+
+    async fn send_batch(&self, events: &[S3Event]) -> Result<()> {
+        Self::send_batch(self, events).await
+    }
 }
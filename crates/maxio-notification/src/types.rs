@@ -65,6 +65,20 @@ pub struct LambdaConfiguration {
     pub filter: Option<FilterRules>,
 }
 
+/// Persisted definition of a notification target, used to re-register it on
+/// startup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TargetConfig {
+    Webhook {
+        endpoint: String,
+        /// Batches events arriving within this many milliseconds of the
+        /// first into one delivery; unset delivers each event on its own.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        batch_window_ms: Option<u64>,
+    },
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct FilterRules {
     pub prefix: Option<String>,
@@ -83,10 +97,117 @@ pub struct S3Event {
     pub event_time: String,
     #[serde(rename = "eventName")]
     pub event_name: String,
+    #[serde(rename = "requestId", default)]
+    pub request_id: String,
     pub bucket: BucketInfo,
     pub object: ObjectInfo,
 }
 
+impl S3Event {
+    /// Renders the event in the AWS S3 event notification schema, with the
+    /// object key URL-encoded the way S3 does.
+    pub fn to_notification(&self) -> EventNotification {
+        EventNotification {
+            records: vec![self.to_record()],
+        }
+    }
+
+    pub fn to_record(&self) -> EventRecord {
+        EventRecord {
+            event_version: self.event_version.clone(),
+            event_source: self.event_source.clone(),
+            aws_region: self.aws_region.clone(),
+            event_time: self.event_time.clone(),
+            event_name: self.event_name.trim_start_matches("s3:").to_string(),
+            response_elements: ResponseElements {
+                request_id: self.request_id.clone(),
+            },
+            s3: S3Entity {
+                s3_schema_version: "1.0".to_string(),
+                bucket: S3BucketEntity {
+                    name: self.bucket.name.clone(),
+                    arn: self.bucket.arn.clone(),
+                },
+                object: S3ObjectEntity {
+                    key: encode_event_key(&self.object.key),
+                    size: self.object.size,
+                    etag: self.object.etag.clone(),
+                },
+            },
+        }
+    }
+}
+
+/// Form-URL-encodes each path segment of `key` (space becomes `+`) while
+/// keeping `/` separators, matching the keys S3 puts in event records.
+fn encode_event_key(key: &str) -> String {
+    key.split('/')
+        .map(|segment| url::form_urlencoded::byte_serialize(segment.as_bytes()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Payload delivered to notification targets: `{"Records": [...]}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventNotification {
+    #[serde(rename = "Records")]
+    pub records: Vec<EventRecord>,
+}
+
+impl EventNotification {
+    /// A single payload carrying `events` as records, in order.
+    pub fn from_events(events: &[S3Event]) -> Self {
+        Self {
+            records: events.iter().map(S3Event::to_record).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    #[serde(rename = "eventVersion")]
+    pub event_version: String,
+    #[serde(rename = "eventSource")]
+    pub event_source: String,
+    #[serde(rename = "awsRegion")]
+    pub aws_region: String,
+    #[serde(rename = "eventTime")]
+    pub event_time: String,
+    #[serde(rename = "eventName")]
+    pub event_name: String,
+    #[serde(rename = "responseElements")]
+    pub response_elements: ResponseElements,
+    pub s3: S3Entity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseElements {
+    #[serde(rename = "x-amz-request-id")]
+    pub request_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Entity {
+    #[serde(rename = "s3SchemaVersion")]
+    pub s3_schema_version: String,
+    pub bucket: S3BucketEntity,
+    pub object: S3ObjectEntity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3BucketEntity {
+    pub name: String,
+    pub arn: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3ObjectEntity {
+    pub key: String,
+    pub size: i64,
+    #[serde(rename = "eTag")]
+    pub etag: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BucketInfo {
     pub name: String,
@@ -0,0 +1,125 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps how many S3 requests are served at once. Requests over the cap are
+/// shed rather than queued, so an overloaded server answers quickly and
+/// clients back off instead of piling up on its disks.
+#[derive(Debug, Clone, Default)]
+pub struct RequestLimiter {
+    /// Zero serves every request.
+    max_requests: usize,
+    slots: Option<Arc<Semaphore>>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    in_flight: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// Point-in-time view of a [`RequestLimiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestMetrics {
+    /// Zero when requests are not limited.
+    pub max_requests: u64,
+    pub in_flight: u64,
+    pub rejected: u64,
+}
+
+/// Marks one admitted request; its slot is released when this is dropped.
+#[derive(Debug)]
+pub struct RequestPermit {
+    _slot: Option<OwnedSemaphorePermit>,
+    counters: Arc<Counters>,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl RequestLimiter {
+    /// Serves at most `max_requests` requests at once; zero means no limit.
+    pub fn new(max_requests: usize) -> Self {
+        Self {
+            max_requests,
+            slots: (max_requests > 0).then(|| Arc::new(Semaphore::new(max_requests))),
+            counters: Arc::default(),
+        }
+    }
+
+    /// Reads `MAXIO_API_REQUESTS_MAX`; unset, zero or unparsable values
+    /// leave requests unlimited.
+    pub fn from_env() -> Self {
+        let max_requests = std::env::var("MAXIO_API_REQUESTS_MAX")
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        Self::new(max_requests)
+    }
+
+    /// Admits a request if a slot is free. `None` means the request is to be
+    /// shed, and is counted as rejected.
+    pub fn try_admit(&self) -> Option<RequestPermit> {
+        let slot = match &self.slots {
+            Some(slots) => match Arc::clone(slots).try_acquire_owned() {
+                Ok(slot) => Some(slot),
+                Err(_) => {
+                    self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+            },
+            None => None,
+        };
+        self.counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        Some(RequestPermit {
+            _slot: slot,
+            counters: Arc::clone(&self.counters),
+        })
+    }
+
+    pub fn metrics(&self) -> RequestMetrics {
+        RequestMetrics {
+            max_requests: self.max_requests as u64,
+            in_flight: self.counters.in_flight.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_over_the_limit_are_rejected_and_counted() {
+        let limiter = RequestLimiter::new(1);
+        let first = limiter.try_admit().expect("first request is admitted");
+        assert!(limiter.try_admit().is_none());
+        assert_eq!(
+            limiter.metrics(),
+            RequestMetrics {
+                max_requests: 1,
+                in_flight: 1,
+                rejected: 1,
+            }
+        );
+
+        drop(first);
+        assert!(limiter.try_admit().is_some());
+        assert_eq!(limiter.metrics().in_flight, 0);
+
+        let unlimited = RequestLimiter::default();
+        let held = (0..8).map(|_| unlimited.try_admit()).collect::<Vec<_>>();
+        assert!(held.iter().all(Option::is_some));
+        assert_eq!(unlimited.metrics().max_requests, 0);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Admin config key toggling content-type sniffing.
+pub const CONTENT_TYPE_SNIFFING_KEY: &str = "api:content_type_sniffing";
+
+const EXTENSION_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("md", "text/markdown"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("wasm", "application/wasm"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+];
+
+const MAGIC_TYPES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// Runtime switch for guessing the content type of uploads that omit
+/// `Content-Type`. Clones share the same flag so the admin API can flip it
+/// while the server runs.
+#[derive(Debug, Clone, Default)]
+pub struct ContentTypeSniffing {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ContentTypeSniffing {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    /// Reads `MAXIO_API_CONTENT_TYPE_SNIFFING`; sniffing is off by default.
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("MAXIO_API_CONTENT_TYPE_SNIFFING")
+            .ok()
+            .as_deref()
+            .and_then(parse_switch)
+            .unwrap_or(false);
+        Self::new(enabled)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the sniffed type when sniffing is enabled, `None` otherwise or
+    /// when nothing matched.
+    pub fn detect(&self, key: &str, data: &[u8]) -> Option<&'static str> {
+        if !self.enabled() {
+            return None;
+        }
+        sniff_content_type(key, data)
+    }
+}
+
+/// Parses the on/off values accepted for boolean config keys.
+pub fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" | "enable" | "enabled" => Some(true),
+        "off" | "false" | "0" | "disable" | "disabled" => Some(false),
+        _ => None,
+    }
+}
+
+/// Guesses a content type from the key's extension, falling back to the
+/// leading magic bytes of the data.
+pub fn sniff_content_type(key: &str, data: &[u8]) -> Option<&'static str> {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    let by_extension = file_name.rsplit_once('.').and_then(|(_, extension)| {
+        EXTENSION_TYPES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            .map(|(_, content_type)| *content_type)
+    });
+
+    by_extension.or_else(|| {
+        MAGIC_TYPES
+            .iter()
+            .find(|(magic, _)| data.starts_with(magic))
+            .map(|(_, content_type)| *content_type)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_wins_over_magic_bytes() {
+        assert_eq!(
+            sniff_content_type("site/index.HTML", b"<p>"),
+            Some("text/html")
+        );
+        assert_eq!(
+            sniff_content_type("logo", b"\x89PNG\r\n\x1a\nrest"),
+            Some("image/png")
+        );
+        assert_eq!(sniff_content_type("blob.bin", b"\0\0\0"), None);
+        assert_eq!(sniff_content_type("dir.d/file", b"plain"), None);
+    }
+
+    #[test]
+    fn disabled_sniffing_detects_nothing() {
+        let sniffing = ContentTypeSniffing::new(false);
+        assert_eq!(sniffing.detect("index.html", b""), None);
+
+        sniffing.clone().set_enabled(true);
+        assert_eq!(sniffing.detect("index.html", b""), Some("text/html"));
+    }
+}
@@ -1,7 +1,14 @@
 use axum::response::{IntoResponse, Response};
-use http::StatusCode;
+use http::{
+    HeaderValue, StatusCode,
+    header::{CONTENT_RANGE, RETRY_AFTER},
+};
 use maxio_common::error::MaxioError;
 
+/// Seconds a client is asked to back off after a request failed for lack of
+/// disk or lock quorum, or was shed under load.
+const QUORUM_RETRY_AFTER_SECS: &str = "1";
+
 pub struct S3Error(pub MaxioError);
 
 impl IntoResponse for S3Error {
@@ -13,14 +20,26 @@ impl IntoResponse for S3Error {
                 StatusCode::NOT_FOUND
             }
             MaxioError::BucketAlreadyExists(_) => StatusCode::CONFLICT,
-            MaxioError::AccessDenied(_) | MaxioError::SignatureDoesNotMatch => {
-                StatusCode::FORBIDDEN
-            }
+            MaxioError::AccessDenied(_)
+            | MaxioError::SignatureDoesNotMatch
+            | MaxioError::InvalidAccessKeyId(_) => StatusCode::FORBIDDEN,
             MaxioError::InvalidBucketName(_)
             | MaxioError::InvalidObjectName(_)
-            | MaxioError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            | MaxioError::KeyTooLong(_)
+            | MaxioError::MetadataTooLarge(_)
+            | MaxioError::InvalidArgument(_)
+            | MaxioError::InvalidRequest(_)
+            | MaxioError::InvalidLocationConstraint(_)
+            | MaxioError::AuthorizationHeaderMalformed(_) => StatusCode::BAD_REQUEST,
+            MaxioError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
             MaxioError::EntityTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            MaxioError::InvalidRange { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
             MaxioError::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            MaxioError::QuorumUnavailable { .. } | MaxioError::ServerBusy(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            MaxioError::RequestTimeout(_) => StatusCode::REQUEST_TIMEOUT,
+            MaxioError::OperationTimedOut(_) => StatusCode::GATEWAY_TIMEOUT,
             MaxioError::InternalError(_) | MaxioError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -34,7 +53,19 @@ impl IntoResponse for S3Error {
 </Error>"#
         );
 
-        (status, [("Content-Type", "application/xml")], body).into_response()
+        let mut response = (status, [("Content-Type", "application/xml")], body).into_response();
+        if let MaxioError::InvalidRange { size } = self.0
+            && let Ok(value) = HeaderValue::from_str(&format!("bytes */{size}"))
+        {
+            response.headers_mut().insert(CONTENT_RANGE, value);
+        }
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response.headers_mut().insert(
+                RETRY_AFTER,
+                HeaderValue::from_static(QUORUM_RETRY_AFTER_SECS),
+            );
+        }
+        response
     }
 }
 
@@ -43,3 +74,72 @@ impl From<MaxioError> for S3Error {
         S3Error(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use maxio_common::error::Result;
+    use maxio_distributed::{DRWMutex, DsyncClient, LockArgs, LockResult, NetLocker};
+
+    use super::*;
+
+    /// Locker whose resources are always held by someone else.
+    struct BusyLocker;
+
+    #[async_trait]
+    impl NetLocker for BusyLocker {
+        async fn lock(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::NotAcquired)
+        }
+
+        async fn rlock(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::NotAcquired)
+        }
+
+        async fn unlock(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::LockNotFound)
+        }
+
+        async fn runlock(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::LockNotFound)
+        }
+
+        async fn refresh(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::LockNotFound)
+        }
+
+        async fn force_unlock(&self, _args: &LockArgs) -> Result<LockResult> {
+            Ok(LockResult::LockNotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_lock_asks_the_client_to_retry() {
+        let lockers = (0..4)
+            .map(|_| Arc::new(BusyLocker) as Arc<dyn NetLocker>)
+            .collect();
+        let mutex = DRWMutex::new(
+            Arc::new(DsyncClient::new(lockers)),
+            vec!["bucket/key".to_string()],
+            "owner",
+            "test",
+        );
+        let err = mutex
+            .lock_or_unavailable()
+            .await
+            .expect_err("every locker refuses");
+
+        let response = S3Error::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(RETRY_AFTER),
+            Some(&HeaderValue::from_static(QUORUM_RETRY_AFTER_SECS))
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>SlowDown</Code>"));
+    }
+}
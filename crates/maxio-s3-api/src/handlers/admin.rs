@@ -1,17 +1,24 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
     Json,
-    extract::{Extension, Query},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Extension, Query, State},
+    http::{HeaderMap, StatusCode, header::ETAG},
+    response::{IntoResponse, Response},
 };
-use maxio_common::error::MaxioError;
-use maxio_iam::{IAMSys, Policy, User};
+use maxio_common::{error::MaxioError, types::ObjectInfo};
+use maxio_distributed::{DistributedSys, VerifyReport};
+use maxio_iam::{IAMSys, Policy, User, policy_warnings, validate_policy};
+use maxio_storage::traits::{ListOrder, ObjectLayer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::error::S3Error;
+use crate::{
+    concurrency::RequestLimiter,
+    content_type::{CONTENT_TYPE_SNIFFING_KEY, ContentTypeSniffing, parse_switch},
+    error::S3Error,
+    handlers::{config_etag, config_if_match},
+};
 
 #[derive(Debug, Deserialize)]
 pub struct AddUserRequest {
@@ -33,6 +40,11 @@ pub struct AddCannedPolicyRequest {
     pub policy: Value,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PolicyNameQuery {
+    pub name: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SetUserPolicyQuery {
     #[serde(rename = "userOrGroup")]
@@ -51,11 +63,93 @@ pub struct AdminUserInfo {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ConfigKeyQuery {
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigKV {
+    pub key: String,
+    pub value: String,
+}
+
+/// A lock currently granted by this node, as reported by `top/locks`.
+#[derive(Debug, Serialize)]
+pub struct AdminLockInfo {
+    pub resource: String,
+    pub uid: String,
+    pub owner: String,
+    pub source: String,
+    pub writer: bool,
+    pub since: chrono::DateTime<chrono::Utc>,
+    #[serde(rename = "ageSeconds")]
+    pub age_seconds: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForceUnlockQuery {
+    /// Comma-separated resources to release.
+    pub paths: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyPrefixQuery {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListObjectsQuery {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    /// The previous page's `nextMarker`.
+    #[serde(default)]
+    pub marker: String,
+    #[serde(default)]
+    pub delimiter: String,
+    #[serde(rename = "max-keys", default = "default_max_keys")]
+    pub max_keys: i32,
+    #[serde(default)]
+    pub order: ListOrder,
+}
+
+fn default_max_keys() -> i32 {
+    1000
+}
+
+/// One page of a `list-objects` listing.
+#[derive(Debug, Serialize)]
+pub struct AdminListObjectsResponse {
+    pub objects: Vec<ObjectInfo>,
+    pub prefixes: Vec<String>,
+    #[serde(rename = "isTruncated")]
+    pub is_truncated: bool,
+    #[serde(rename = "nextMarker")]
+    pub next_marker: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenamePrefixRequest {
+    pub bucket: String,
+    pub from: String,
+    pub to: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct MessageResponse {
     pub message: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PolicyValidationResult {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
 pub async fn add_user(
     Extension(iam): Extension<Arc<IAMSys>>,
     Json(payload): Json<AddUserRequest>,
@@ -87,10 +181,15 @@ pub async fn list_users(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// With `?validate` the policy is only checked: the response lists what
+/// would make it rejected and anything suspicious about it, and nothing is
+/// stored.
 pub async fn add_canned_policy(
     Extension(iam): Extension<Arc<IAMSys>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
     Json(payload): Json<AddCannedPolicyRequest>,
-) -> Result<impl IntoResponse, S3Error> {
+) -> Result<Response, S3Error> {
     let mut policy: Policy = serde_json::from_value(payload.policy).map_err(|err| {
         S3Error::from(MaxioError::InvalidArgument(format!(
             "failed to parse policy document: {err}"
@@ -101,18 +200,56 @@ pub async fn add_canned_policy(
         policy.name = payload.name;
     }
 
-    if policy.name.is_empty() {
-        return Err(S3Error::from(MaxioError::InvalidArgument(
-            "policy name is required".to_string(),
-        )));
+    if query.contains_key("validate") {
+        let errors = validate_policy(&policy)
+            .err()
+            .map(|err| err.to_string())
+            .into_iter()
+            .collect::<Vec<_>>();
+        let status = if errors.is_empty() {
+            StatusCode::OK
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        let result = PolicyValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings: policy_warnings(&policy),
+        };
+        return Ok((status, Json(result)).into_response());
     }
 
-    iam.create_policy(policy).await?;
+    let expected_revision = config_if_match(&headers)?;
+    let revision = iam
+        .create_policy_if_revision(policy, expected_revision)
+        .await?;
     Ok((
         StatusCode::OK,
+        [(ETAG, config_etag(revision))],
         Json(MessageResponse {
             message: "policy stored".to_string(),
         }),
+    )
+        .into_response())
+}
+
+pub async fn info_canned_policy(
+    Extension(iam): Extension<Arc<IAMSys>>,
+    Query(query): Query<PolicyNameQuery>,
+) -> Result<impl IntoResponse, S3Error> {
+    let (policy, revision) = iam
+        .get_policy_with_revision(&query.name)
+        .await?
+        .ok_or_else(|| {
+            S3Error::from(MaxioError::InvalidArgument(format!(
+                "policy not found: {}",
+                query.name
+            )))
+        })?;
+    Ok((
+        StatusCode::OK,
+        [(ETAG, config_etag(revision))],
+        Json(policy),
     ))
 }
 
@@ -130,6 +267,172 @@ pub async fn set_user_or_group_policy(
     ))
 }
 
+pub async fn get_config_kv(
+    Extension(sniffing): Extension<ContentTypeSniffing>,
+    Query(query): Query<ConfigKeyQuery>,
+) -> Result<impl IntoResponse, S3Error> {
+    if query.key != CONTENT_TYPE_SNIFFING_KEY {
+        return Err(unknown_config_key(&query.key));
+    }
+
+    let value = if sniffing.enabled() { "on" } else { "off" };
+    Ok((
+        StatusCode::OK,
+        Json(ConfigKV {
+            key: query.key,
+            value: value.to_string(),
+        }),
+    ))
+}
+
+pub async fn set_config_kv(
+    Extension(sniffing): Extension<ContentTypeSniffing>,
+    Json(payload): Json<ConfigKV>,
+) -> Result<impl IntoResponse, S3Error> {
+    if payload.key != CONTENT_TYPE_SNIFFING_KEY {
+        return Err(unknown_config_key(&payload.key));
+    }
+
+    let enabled = parse_switch(&payload.value).ok_or_else(|| {
+        S3Error::from(MaxioError::InvalidArgument(format!(
+            "invalid value for {}: {}",
+            payload.key, payload.value
+        )))
+    })?;
+    sniffing.set_enabled(enabled);
+    Ok((
+        StatusCode::OK,
+        Json(MessageResponse {
+            message: "config value updated".to_string(),
+        }),
+    ))
+}
+
+/// Moves every object under `from` to the same key under `to`, versions and
+/// metadata included, without copying the data where the layer allows.
+pub async fn rename_prefix(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Json(payload): Json<RenamePrefixRequest>,
+) -> Result<impl IntoResponse, S3Error> {
+    store
+        .rename_prefix(&payload.bucket, &payload.from, &payload.to)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(MessageResponse {
+            message: "prefix renamed".to_string(),
+        }),
+    ))
+}
+
+/// Lists a bucket in any [`ListOrder`], e.g. newest first or from the end
+/// of the key space, which the S3 listing APIs cannot express.
+pub async fn list_objects(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Query(query): Query<ListObjectsQuery>,
+) -> Result<impl IntoResponse, S3Error> {
+    let result = store
+        .list_objects(
+            &query.bucket,
+            &query.prefix,
+            &query.marker,
+            &query.delimiter,
+            query.max_keys,
+            query.order,
+        )
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(AdminListObjectsResponse {
+            objects: result.objects,
+            prefixes: result.prefixes,
+            is_truncated: result.is_truncated,
+            next_marker: result.next_marker,
+        }),
+    ))
+}
+
+/// Lists the locks this node holds for any client, oldest first, so stuck
+/// ones can be spotted.
+pub async fn top_locks(
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+) -> Result<impl IntoResponse, S3Error> {
+    let now = chrono::Utc::now();
+    let locks = distributed
+        .held_locks()
+        .into_iter()
+        .map(|entry| AdminLockInfo {
+            age_seconds: (now - entry.since).num_seconds(),
+            resource: entry.resource,
+            uid: entry.uid,
+            owner: entry.owner,
+            source: entry.source,
+            writer: entry.writer,
+            since: entry.since,
+        })
+        .collect::<Vec<_>>();
+    Ok((StatusCode::OK, Json(locks)))
+}
+
+/// Reports how many S3 requests are being served and how many were shed
+/// since startup.
+pub async fn request_metrics(
+    Extension(limiter): Extension<RequestLimiter>,
+) -> Result<impl IntoResponse, S3Error> {
+    Ok((StatusCode::OK, Json(limiter.metrics())))
+}
+
+/// Audits the objects under a prefix on every local erasure set and reports
+/// which are healthy, degraded or unrecoverable. Nothing is repaired.
+pub async fn verify_prefix(
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    Query(query): Query<VerifyPrefixQuery>,
+) -> Result<impl IntoResponse, S3Error> {
+    let engines = distributed.heal_engines();
+    if engines.is_empty() {
+        return Err(S3Error::from(MaxioError::NotImplemented(
+            "verifying objects needs erasure-coded disks".to_string(),
+        )));
+    }
+    let mut report = VerifyReport::default();
+    for engine in engines {
+        report.merge(engine.verify_prefix(&query.bucket, &query.prefix).await?);
+    }
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// Releases every lock on the given resources, whoever holds them.
+pub async fn force_unlock(
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    Query(query): Query<ForceUnlockQuery>,
+) -> Result<impl IntoResponse, S3Error> {
+    let resources = query
+        .paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    if resources.is_empty() {
+        return Err(S3Error::from(MaxioError::InvalidArgument(
+            "no lock paths to release".to_string(),
+        )));
+    }
+    distributed.force_unlock(resources).await;
+    Ok((
+        StatusCode::OK,
+        Json(MessageResponse {
+            message: "locks released".to_string(),
+        }),
+    ))
+}
+
+fn unknown_config_key(key: &str) -> S3Error {
+    S3Error::from(MaxioError::InvalidArgument(format!(
+        "unknown config key: {key}"
+    )))
+}
+
 fn admin_user_info(user: &User) -> AdminUserInfo {
     AdminUserInfo {
         access_key: user.access_key.clone(),
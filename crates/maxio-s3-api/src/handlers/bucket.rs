@@ -7,16 +7,21 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use http::StatusCode;
+use maxio_auth::middleware::Caller;
 use maxio_common::{error::MaxioError, types::BucketInfo};
 use maxio_notification::{NotificationSys, types::NotificationConfiguration};
 use maxio_storage::traits::ObjectLayer;
 use quick_xml::{de::from_str as xml_from_str, se::to_string as xml_to_string};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::error::S3Error;
+use crate::{error::S3Error, region::ServerRegion};
 
 type S3Result = Result<Response, S3Error>;
 
+/// Owner reported for buckets, and for objects written anonymously or before
+/// owners were recorded.
+pub(crate) const DEFAULT_OWNER: &str = "maxio";
+
 #[derive(Debug, Serialize)]
 #[serde(rename = "ListAllMyBucketsResult")]
 struct ListAllMyBucketsResult {
@@ -27,13 +32,23 @@ struct ListAllMyBucketsResult {
 }
 
 #[derive(Debug, Serialize)]
-struct Owner {
+pub(crate) struct Owner {
     #[serde(rename = "ID")]
     id: String,
     #[serde(rename = "DisplayName")]
     display_name: String,
 }
 
+impl Owner {
+    /// Owners are identified by access key, which doubles as display name.
+    pub(crate) fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            display_name: id.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct Buckets {
     #[serde(rename = "Bucket", default)]
@@ -57,6 +72,30 @@ struct LocationConstraint {
     value: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename = "CreateBucketConfiguration")]
+struct CreateBucketConfiguration {
+    #[serde(rename = "LocationConstraint")]
+    location_constraint: Option<String>,
+}
+
+/// Transfer acceleration is never enabled, so the document has no `Status`.
+#[derive(Debug, Serialize)]
+#[serde(rename = "AccelerateConfiguration")]
+struct AccelerateConfiguration {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "RequestPaymentConfiguration")]
+struct RequestPaymentConfiguration {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    #[serde(rename = "Payer")]
+    payer: &'static str,
+}
+
 impl From<&BucketInfo> for BucketXml {
     fn from(info: &BucketInfo) -> Self {
         Self {
@@ -76,13 +115,20 @@ fn xml_response<T: Serialize>(status: StatusCode, payload: &T) -> S3Result {
     Ok((status, [("Content-Type", "application/xml")], body).into_response())
 }
 
-pub async fn list_buckets(State(store): State<Arc<dyn ObjectLayer>>) -> S3Result {
-    let buckets = store.list_buckets().await?;
+/// Signed callers only see the buckets they may list (`s3:ListBucket` on the
+/// bucket); the root user sees all of them.
+pub async fn list_buckets(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    caller: Option<Extension<Caller>>,
+) -> S3Result {
+    let mut buckets = store.list_buckets().await?;
+    if let Some(Extension(caller)) = caller {
+        buckets.retain(|bucket| {
+            caller.is_allowed("s3:ListBucket", &format!("arn:aws:s3:::{}", bucket.name))
+        });
+    }
     let payload = ListAllMyBucketsResult {
-        owner: Owner {
-            id: "maxio".to_string(),
-            display_name: "maxio".to_string(),
-        },
+        owner: Owner::new(DEFAULT_OWNER),
         buckets: Buckets {
             bucket: buckets.iter().map(BucketXml::from).collect(),
         },
@@ -90,10 +136,22 @@ pub async fn list_buckets(State(store): State<Arc<dyn ObjectLayer>>) -> S3Result
     xml_response(StatusCode::OK, &payload)
 }
 
+/// An optional `CreateBucketConfiguration` body names the region the bucket
+/// is meant for, which has to be the server's own.
 pub async fn make_bucket(
     State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(region): Extension<ServerRegion>,
     Path(bucket): Path<String>,
+    body: Bytes,
 ) -> S3Result {
+    let body_str = std::str::from_utf8(&body)
+        .map_err(|_| MaxioError::InvalidArgument("request body must be utf-8 xml".to_string()))?;
+    if !body_str.trim().is_empty() {
+        let config: CreateBucketConfiguration = xml_from_str(body_str).map_err(|err| {
+            MaxioError::InvalidArgument(format!("invalid bucket configuration xml body: {err}"))
+        })?;
+        region.check_location_constraint(config.location_constraint.as_deref().unwrap_or(""))?;
+    }
     store.make_bucket(&bucket).await?;
     Ok(StatusCode::OK.into_response())
 }
@@ -116,12 +174,36 @@ pub async fn delete_bucket(
 
 pub async fn get_bucket_location(
     State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(region): Extension<ServerRegion>,
     Path(bucket): Path<String>,
 ) -> S3Result {
     store.get_bucket_info(&bucket).await?;
     let payload = LocationConstraint {
         xmlns: "http://s3.amazonaws.com/doc/2006-03-01/",
-        value: String::new(),
+        value: region.location_constraint().to_string(),
+    };
+    xml_response(StatusCode::OK, &payload)
+}
+
+pub async fn get_bucket_accelerate_configuration(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Path(bucket): Path<String>,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    let payload = AccelerateConfiguration {
+        xmlns: "http://s3.amazonaws.com/doc/2006-03-01/",
+    };
+    xml_response(StatusCode::OK, &payload)
+}
+
+pub async fn get_bucket_request_payment(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Path(bucket): Path<String>,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    let payload = RequestPaymentConfiguration {
+        xmlns: "http://s3.amazonaws.com/doc/2006-03-01/",
+        payer: "BucketOwner",
     };
     xml_response(StatusCode::OK, &payload)
 }
@@ -2,18 +2,45 @@ use std::sync::Arc;
 
 use axum::{
     Json,
-    extract::Extension,
+    extract::{Extension, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use maxio_distributed::DistributedSys;
+use maxio_storage::traits::ObjectLayer;
 
+/// Liveness: the process is up and answering requests.
 pub async fn health_live() -> impl IntoResponse {
     StatusCode::OK
 }
 
+/// Readiness: this node can serve traffic, meaning its disks reach write
+/// quorum and it can see a majority of the cluster.
+pub async fn health_ready(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+) -> impl IntoResponse {
+    serving_status(store.as_ref(), &distributed)
+}
+
+/// Cluster view from this node, answered with `503` under the same conditions
+/// as readiness so load balancers can gate on it.
 pub async fn health_cluster(
+    State(store): State<Arc<dyn ObjectLayer>>,
     Extension(distributed): Extension<Arc<DistributedSys>>,
 ) -> impl IntoResponse {
-    Json(distributed.get_cluster_status())
+    (
+        serving_status(store.as_ref(), &distributed),
+        Json(distributed.get_cluster_status()),
+    )
+}
+
+/// Read on every request rather than cached, so a node whose offline disks
+/// pass the layer's recovery probe turns ready again on its own.
+fn serving_status(store: &dyn ObjectLayer, distributed: &DistributedSys) -> StatusCode {
+    if store.has_write_quorum() && distributed.get_cluster_status().has_quorum() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
 }
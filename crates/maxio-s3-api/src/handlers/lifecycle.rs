@@ -4,20 +4,35 @@ use axum::{
     Extension,
     body::Bytes,
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header::ETAG},
     response::{IntoResponse, Response},
 };
 use maxio_common::error::MaxioError;
 use maxio_lifecycle::types::LifecycleConfiguration;
-use maxio_lifecycle::LifecycleSys;
+use maxio_lifecycle::{LifecycleSys, config_warnings, validate_config};
 use maxio_storage::traits::ObjectLayer;
 use quick_xml::{de::from_str as xml_from_str, se::to_string as xml_to_string};
 use serde::Serialize;
 
-use crate::error::S3Error;
+use crate::{
+    error::S3Error,
+    handlers::{config_etag, config_if_match},
+};
 
 type S3Result = Result<Response, S3Error>;
 
+/// Outcome of validating a lifecycle configuration without storing it.
+#[derive(Debug, Serialize)]
+#[serde(rename = "LifecycleValidationResult")]
+struct LifecycleValidationResult {
+    #[serde(rename = "Valid")]
+    valid: bool,
+    #[serde(rename = "Error", skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+    #[serde(rename = "Warning", skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
 fn xml_response<T: Serialize>(status: StatusCode, payload: &T) -> S3Result {
     let xml = xml_to_string(payload).map_err(|err| {
         S3Error::from(MaxioError::InternalError(format!(
@@ -34,26 +49,57 @@ pub async fn get_bucket_lifecycle_configuration(
     Path(bucket): Path<String>,
 ) -> S3Result {
     store.get_bucket_info(&bucket).await?;
-    let config = lifecycle
-        .get_config(&bucket)
-        .await?
-        .unwrap_or_default();
-    xml_response(StatusCode::OK, &config)
+    let Some((config, version)) = lifecycle.get_versioned_config(&bucket).await? else {
+        return xml_response(StatusCode::OK, &LifecycleConfiguration::default());
+    };
+    let response = xml_response(StatusCode::OK, &config)?;
+    Ok(([(ETAG, config_etag(version))], response).into_response())
 }
 
 pub async fn put_bucket_lifecycle_configuration(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(lifecycle): Extension<Arc<LifecycleSys>>,
     Path(bucket): Path<String>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> S3Result {
     store.get_bucket_info(&bucket).await?;
-    let body_str = std::str::from_utf8(&body)
-        .map_err(|err| MaxioError::InvalidArgument(format!("invalid xml body encoding: {err}")))?;
-    let config: LifecycleConfiguration = xml_from_str(body_str)
-        .map_err(|err| MaxioError::InvalidArgument(format!("invalid lifecycle xml body: {err}")))?;
-    lifecycle.set_config(&bucket, config).await?;
-    Ok(StatusCode::OK.into_response())
+    let expected_version = config_if_match(&headers)?;
+    let config = parse_lifecycle_configuration(&body)?;
+    let version = lifecycle
+        .set_config_if_version(&bucket, config, expected_version)
+        .await?;
+    Ok((StatusCode::OK, [(ETAG, config_etag(version))]).into_response())
+}
+
+/// `PUT ?lifecycle&validate`: reports whether the configuration would be
+/// accepted, and anything suspicious about it, without storing it. Answers
+/// `400` when it would be rejected.
+pub async fn validate_bucket_lifecycle_configuration(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Path(bucket): Path<String>,
+    body: Bytes,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    let config = parse_lifecycle_configuration(&body)?;
+    let errors = validate_config(&config)
+        .err()
+        .map(|err| err.to_string())
+        .into_iter()
+        .collect::<Vec<_>>();
+    let status = if errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    xml_response(
+        status,
+        &LifecycleValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings: config_warnings(&config),
+        },
+    )
 }
 
 pub async fn delete_bucket_lifecycle_configuration(
@@ -65,3 +111,10 @@ pub async fn delete_bucket_lifecycle_configuration(
     lifecycle.delete_config(&bucket).await?;
     Ok(StatusCode::NO_CONTENT.into_response())
 }
+
+fn parse_lifecycle_configuration(body: &[u8]) -> Result<LifecycleConfiguration, MaxioError> {
+    let body_str = std::str::from_utf8(body)
+        .map_err(|err| MaxioError::InvalidArgument(format!("invalid xml body encoding: {err}")))?;
+    xml_from_str(body_str)
+        .map_err(|err| MaxioError::InvalidArgument(format!("invalid lifecycle xml body: {err}")))
+}
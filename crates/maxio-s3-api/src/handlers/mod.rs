@@ -7,3 +7,30 @@ pub mod object;
 pub mod replication;
 pub mod tagging;
 pub mod versioning;
+pub mod website;
+
+use axum::http::{HeaderMap, header::IF_MATCH};
+use maxio_common::error::MaxioError;
+
+/// ETag of a stored configuration document: its quoted version counter.
+pub(crate) fn config_etag(version: u64) -> String {
+    format!("\"{version}\"")
+}
+
+/// The configuration version an update is conditional on, from `If-Match`.
+/// An ETag that is not one of ours can never match.
+pub(crate) fn config_if_match(headers: &HeaderMap) -> Result<Option<u64>, MaxioError> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().trim_matches('"').parse::<u64>().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            MaxioError::PreconditionFailed(format!(
+                "If-Match {value:?} does not name a configuration version"
+            ))
+        })
+}
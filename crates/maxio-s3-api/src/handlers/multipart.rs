@@ -10,18 +10,24 @@ use axum::{
     },
     response::{IntoResponse, Response},
 };
-use chrono::Utc;
-use maxio_common::error::MaxioError;
+use chrono::{SecondsFormat, Utc};
+use maxio_auth::middleware::Caller;
+use maxio_common::{error::MaxioError, hash::quoted_etag, xml::to_s3_xml};
 use maxio_notification::{
     NotificationSys,
     types::{BucketInfo as NotificationBucketInfo, ObjectInfo as NotificationObjectInfo, S3Event},
 };
 use maxio_storage::traits::{CompletePart, MultipartUploadInfo, ObjectLayer, PartInfo};
-use quick_xml::{de::from_str as xml_from_str, se::to_string as xml_to_string};
-use serde::{Deserialize, Serialize};
+use quick_xml::{Reader, events::Event};
+use serde::Serialize;
 use tracing::warn;
 
-use crate::error::S3Error;
+use crate::{
+    error::S3Error,
+    handlers::object::{extract_put_metadata, insert_owner, insert_storage_class},
+    limits::ObjectSizeLimits,
+    request_id::RequestId,
+};
 
 type S3Result = Result<Response, S3Error>;
 
@@ -36,21 +42,6 @@ struct InitiateMultipartUploadResultXml {
     upload_id: String,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename = "CompleteMultipartUpload")]
-struct CompleteMultipartUploadXml {
-    #[serde(rename = "Part", default)]
-    parts: Vec<CompletePartXml>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CompletePartXml {
-    #[serde(rename = "PartNumber")]
-    part_number: i32,
-    #[serde(rename = "ETag")]
-    etag: String,
-}
-
 #[derive(Debug, Serialize)]
 #[serde(rename = "CompleteMultipartUploadResult")]
 struct CompleteMultipartUploadResultXml {
@@ -94,12 +85,32 @@ struct PartXml {
 struct ListMultipartUploadsResultXml {
     #[serde(rename = "Bucket")]
     bucket: String,
+    #[serde(rename = "KeyMarker")]
+    key_marker: String,
+    #[serde(rename = "UploadIdMarker")]
+    upload_id_marker: String,
+    #[serde(rename = "NextKeyMarker", skip_serializing_if = "Option::is_none")]
+    next_key_marker: Option<String>,
+    #[serde(rename = "NextUploadIdMarker", skip_serializing_if = "Option::is_none")]
+    next_upload_id_marker: Option<String>,
+    #[serde(rename = "Delimiter", skip_serializing_if = "String::is_empty")]
+    delimiter: String,
     #[serde(rename = "Prefix")]
     prefix: String,
+    #[serde(rename = "MaxUploads")]
+    max_uploads: i32,
     #[serde(rename = "IsTruncated")]
     is_truncated: bool,
     #[serde(rename = "Upload", default)]
     uploads: Vec<MultipartUploadXml>,
+    #[serde(rename = "CommonPrefixes", default)]
+    common_prefixes: Vec<CommonPrefixXml>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommonPrefixXml {
+    #[serde(rename = "Prefix")]
+    prefix: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -113,36 +124,10 @@ struct MultipartUploadXml {
 }
 
 fn xml_response<T: Serialize>(status: StatusCode, payload: &T) -> S3Result {
-    let xml = xml_to_string(payload).map_err(|err| {
-        S3Error::from(MaxioError::InternalError(format!(
-            "failed to serialize xml response: {err}"
-        )))
-    })?;
-    let body = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{xml}");
+    let body = to_s3_xml(payload)?;
     Ok((status, [("Content-Type", "application/xml")], body).into_response())
 }
 
-fn quoted_etag(etag: &str) -> String {
-    if etag.starts_with('"') && etag.ends_with('"') {
-        etag.to_string()
-    } else {
-        format!("\"{etag}\"")
-    }
-}
-
-fn extract_put_metadata(headers: &HeaderMap) -> HashMap<String, String> {
-    let mut metadata = HashMap::new();
-    for (name, value) in headers {
-        let name = name.as_str();
-        if let Some(meta_key) = name.strip_prefix("x-amz-meta-") {
-            if let Ok(meta_value) = value.to_str() {
-                metadata.insert(meta_key.to_string(), meta_value.to_string());
-            }
-        }
-    }
-    metadata
-}
-
 fn parse_upload_id(query: &HashMap<String, String>) -> Result<&str, MaxioError> {
     query
         .get("uploadId")
@@ -159,15 +144,80 @@ fn parse_part_number(query: &HashMap<String, String>) -> Result<i32, MaxioError>
         .map_err(|_| MaxioError::InvalidArgument("invalid partNumber".to_string()))
 }
 
-fn parse_complete_parts(payload: CompleteMultipartUploadXml) -> Vec<CompletePart> {
-    payload
-        .parts
-        .into_iter()
-        .map(|part| CompletePart {
-            part_number: part.part_number,
-            etag: part.etag,
-        })
-        .collect()
+/// Streams the parts out of a CompleteMultipartUpload body. Elements are
+/// matched by local name so namespaced and prefixed bodies from different
+/// SDKs parse alike, and unknown elements (checksums and the like) are
+/// skipped.
+fn parse_complete_parts(body: &[u8]) -> Result<Vec<CompletePart>, MaxioError> {
+    let invalid = |detail: String| {
+        MaxioError::InvalidArgument(format!("invalid complete multipart xml body: {detail}"))
+    };
+
+    let mut reader = Reader::from_reader(body);
+    reader.config_mut().trim_text(true);
+
+    let mut parts = Vec::new();
+    let mut seen_root = false;
+    let mut closed_root = false;
+    let mut in_part = false;
+    let mut field: Option<&'static str> = None;
+    let mut part_number: Option<i32> = None;
+    let mut etag: Option<String> = None;
+
+    loop {
+        match reader
+            .read_event()
+            .map_err(|err| invalid(err.to_string()))?
+        {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"CompleteMultipartUpload" if !seen_root => seen_root = true,
+                _ if !seen_root => {
+                    return Err(invalid(
+                        "root element must be CompleteMultipartUpload".into(),
+                    ));
+                }
+                b"Part" if !in_part => {
+                    in_part = true;
+                    part_number = None;
+                    etag = None;
+                }
+                b"PartNumber" if in_part => field = Some("PartNumber"),
+                b"ETag" if in_part => field = Some("ETag"),
+                _ => field = None,
+            },
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|err| invalid(err.to_string()))?;
+                match field {
+                    Some("PartNumber") => {
+                        part_number = Some(text.trim().parse().map_err(|_| {
+                            invalid(format!("invalid PartNumber {:?}", text.trim()))
+                        })?);
+                    }
+                    Some("ETag") => etag = Some(text.trim().to_string()),
+                    _ => {}
+                }
+            }
+            Event::End(element) => {
+                field = None;
+                if in_part && element.local_name().as_ref() == b"Part" {
+                    in_part = false;
+                    let (Some(part_number), Some(etag)) = (part_number.take(), etag.take()) else {
+                        return Err(invalid("Part requires PartNumber and ETag".into()));
+                    };
+                    parts.push(CompletePart { part_number, etag });
+                } else if !in_part && element.local_name().as_ref() == b"CompleteMultipartUpload" {
+                    closed_root = true;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !seen_root || !closed_root {
+        return Err(invalid("missing CompleteMultipartUpload element".into()));
+    }
+    Ok(parts)
 }
 
 fn map_parts(parts: Vec<PartInfo>) -> Vec<PartXml> {
@@ -195,13 +245,16 @@ fn map_uploads(uploads: Vec<MultipartUploadInfo>) -> Vec<MultipartUploadXml> {
 
 pub async fn create_multipart_upload(
     State(store): State<Arc<dyn ObjectLayer>>,
+    caller: Option<Extension<Caller>>,
     Path((bucket, key)): Path<(String, String)>,
     headers: HeaderMap,
 ) -> S3Result {
     let content_type = headers
         .get(CONTENT_TYPE)
         .and_then(|value| value.to_str().ok());
-    let metadata = extract_put_metadata(&headers);
+    let mut metadata = extract_put_metadata(&headers)?;
+    insert_storage_class(&headers, &mut metadata)?;
+    insert_owner(&mut metadata, caller.as_deref());
     let upload_id = store
         .create_multipart_upload(&bucket, &key, content_type, metadata)
         .await?;
@@ -236,24 +289,31 @@ pub async fn upload_part(
     Ok((StatusCode::OK, response_headers).into_response())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn complete_multipart_upload(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(limits): Extension<ObjectSizeLimits>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
     headers: HeaderMap,
     body: Bytes,
 ) -> S3Result {
     let upload_id = parse_upload_id(&query)?;
-    let body_str = std::str::from_utf8(&body).map_err(|err| {
-        MaxioError::InvalidArgument(format!(
-            "invalid complete multipart xml body encoding: {err}"
-        ))
-    })?;
-    let payload: CompleteMultipartUploadXml = xml_from_str(body_str).map_err(|err| {
-        MaxioError::InvalidArgument(format!("invalid complete multipart xml body: {err}"))
-    })?;
-    let parts = parse_complete_parts(payload);
+    let parts = parse_complete_parts(&body)?;
+
+    let uploaded = store.list_parts(&bucket, &key, upload_id).await?;
+    let total_size = parts
+        .iter()
+        .filter_map(|part| {
+            uploaded
+                .iter()
+                .find(|uploaded| uploaded.part_number == part.part_number)
+        })
+        .map(|uploaded| u64::try_from(uploaded.size).unwrap_or_default())
+        .sum::<u64>();
+    limits.check_object(total_size)?;
 
     let info = store
         .complete_multipart_upload(&bucket, &key, upload_id, parts)
@@ -277,8 +337,9 @@ pub async fn complete_multipart_upload(
             event_version: "2.1".to_string(),
             event_source: "aws:s3".to_string(),
             aws_region: "".to_string(),
-            event_time: Utc::now().to_rfc3339(),
+            event_time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
             event_name: "s3:ObjectCreated:CompleteMultipartUpload".to_string(),
+            request_id: request_id.to_string(),
             bucket: NotificationBucketInfo {
                 name: payload.bucket.clone(),
                 arn: format!("arn:aws:s3:::{}", payload.bucket),
@@ -336,12 +397,103 @@ pub async fn list_multipart_uploads(
     Query(query): Query<HashMap<String, String>>,
 ) -> S3Result {
     let prefix = query.get("prefix").cloned().unwrap_or_default();
-    let uploads = store.list_multipart_uploads(&bucket, &prefix).await?;
+    let key_marker = query.get("key-marker").cloned().unwrap_or_default();
+    let upload_id_marker = query.get("upload-id-marker").cloned().unwrap_or_default();
+    let delimiter = query.get("delimiter").cloned().unwrap_or_default();
+    let max_uploads = query
+        .get("max-uploads")
+        .and_then(|value| value.parse::<i32>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(1000);
+
+    let listed = store
+        .list_multipart_uploads(
+            &bucket,
+            &prefix,
+            &key_marker,
+            &upload_id_marker,
+            &delimiter,
+            max_uploads,
+        )
+        .await?;
     let payload = ListMultipartUploadsResultXml {
         bucket,
+        key_marker,
+        upload_id_marker,
+        next_key_marker: listed.next_key_marker,
+        next_upload_id_marker: listed.next_upload_id_marker,
+        delimiter,
         prefix,
-        is_truncated: false,
-        uploads: map_uploads(uploads),
+        max_uploads,
+        is_truncated: listed.is_truncated,
+        uploads: map_uploads(listed.uploads),
+        common_prefixes: listed
+            .prefixes
+            .into_iter()
+            .map(|prefix| CommonPrefixXml { prefix })
+            .collect(),
     };
     xml_response(StatusCode::OK, &payload)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_body_with_ten_thousand_parts() {
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for number in 1..=10_000 {
+            body.push_str(&format!(
+                "<Part><PartNumber>{number}</PartNumber><ETag>\"{number:032x}\"</ETag></Part>"
+            ));
+        }
+        body.push_str("</CompleteMultipartUpload>");
+
+        let parts = parse_complete_parts(body.as_bytes()).unwrap();
+        assert_eq!(parts.len(), 10_000);
+        assert_eq!(parts[0].part_number, 1);
+        assert_eq!(parts[9_999].part_number, 10_000);
+        assert_eq!(parts[9_999].etag, format!("\"{:032x}\"", 10_000));
+    }
+
+    #[test]
+    fn completion_body_variants_from_sdks() {
+        let bodies = [
+            // Namespaced, pretty-printed and with an XML prolog.
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <CompleteMultipartUpload xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\n\
+               <Part>\n    <PartNumber> 1 </PartNumber>\n    <ETag>\"aaa\"</ETag>\n  </Part>\n\
+               <Part>\n    <ETag>\"bbb\"</ETag>\n    <PartNumber>2</PartNumber>\n  </Part>\n\
+             </CompleteMultipartUpload>\n",
+            // Prefixed elements, escaped quotes and checksum fields.
+            "<s3:CompleteMultipartUpload xmlns:s3=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+             <s3:Part><s3:ETag>&quot;aaa&quot;</s3:ETag><s3:ChecksumCRC32>AAAAAA==</s3:ChecksumCRC32>\
+             <s3:PartNumber>1</s3:PartNumber></s3:Part>\
+             <s3:Part><s3:PartNumber>2</s3:PartNumber><s3:ETag>&quot;bbb&quot;</s3:ETag></s3:Part>\
+             </s3:CompleteMultipartUpload>",
+        ];
+
+        for body in bodies {
+            let parts = parse_complete_parts(body.as_bytes()).unwrap();
+            let parsed = parts
+                .iter()
+                .map(|part| (part.part_number, part.etag.as_str()))
+                .collect::<Vec<_>>();
+            assert_eq!(parsed, vec![(1, "\"aaa\""), (2, "\"bbb\"")], "{body}");
+        }
+    }
+
+    #[test]
+    fn malformed_completion_bodies_are_rejected() {
+        for body in [
+            "",
+            "<Other><Part><PartNumber>1</PartNumber><ETag>a</ETag></Part></Other>",
+            "<CompleteMultipartUpload><Part><PartNumber>x</PartNumber><ETag>a</ETag></Part></CompleteMultipartUpload>",
+            "<CompleteMultipartUpload><Part><ETag>a</ETag></Part></CompleteMultipartUpload>",
+            "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber>",
+        ] {
+            assert!(parse_complete_parts(body.as_bytes()).is_err(), "{body}");
+        }
+    }
+}
@@ -6,29 +6,49 @@ use axum::{
     extract::{Path, Query, State},
     http::{
         HeaderMap, HeaderName, HeaderValue, StatusCode,
-        header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, LAST_MODIFIED, RANGE},
+        header::{
+            ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MATCH,
+            IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+        },
     },
     response::{IntoResponse, Response},
 };
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64_STANDARD};
-use chrono::Utc;
+use chrono::{DateTime, SecondsFormat, Utc};
+use maxio_auth::middleware::Caller;
 use maxio_common::{
-    error::MaxioError,
-    types::{ObjectEncryption, ObjectInfo},
+    error::{MAX_USER_METADATA_SIZE, MaxioError},
+    hash::{md5_digest, normalize_etag, quoted_etag},
+    types::{
+        ObjectEncryption, ObjectInfo, REDUCED_REDUNDANCY_STORAGE_CLASS, STANDARD_STORAGE_CLASS,
+    },
+    xml::to_s3_xml,
+};
+use maxio_distributed::{
+    DeletedObjectReplicationInfo, DistributedSys, ReplicationPool, ReplicationState,
 };
 use maxio_notification::{
     NotificationSys,
     types::{BucketInfo as NotificationBucketInfo, ObjectInfo as NotificationObjectInfo, S3Event},
 };
 use maxio_storage::traits::{
-    GetEncryptionOptions, ListObjectsResult, ObjectLayer, PutEncryptionOptions, VersioningState,
+    CopySource, DeleteCondition, DeletedObject, GetEncryptionOptions, ListObjectsResult, ListOrder,
+    ObjectLayer, PutEncryptionOptions, STORAGE_CLASS_META_KEY, VersioningState,
 };
-use md5::{Digest, Md5};
-use quick_xml::se::to_string as xml_to_string;
+use percent_encoding::percent_decode_str;
 use serde::Serialize;
 use tracing::warn;
 
-use crate::error::S3Error;
+use crate::{
+    content_type::ContentTypeSniffing,
+    error::S3Error,
+    handlers::{
+        bucket::{DEFAULT_OWNER, Owner},
+        replication::load_replication_config,
+    },
+    idempotency::{PutFingerprint, RecentPuts},
+    request_id::RequestId,
+};
 
 type S3Result = std::result::Result<Response, S3Error>;
 
@@ -36,6 +56,39 @@ const SSE_HEADER: &str = "x-amz-server-side-encryption";
 const SSE_C_ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
 const SSE_C_KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
 const SSE_C_KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-md5";
+const SSE_C_HEADERS: [&str; 3] = [
+    SSE_C_ALGORITHM_HEADER,
+    SSE_C_KEY_HEADER,
+    SSE_C_KEY_MD5_HEADER,
+];
+/// The key a CopyObject source was encrypted with; the regular SSE-C
+/// headers carry the key for the copy.
+const COPY_SOURCE_SSE_C_HEADERS: [&str; 3] = [
+    "x-amz-copy-source-server-side-encryption-customer-algorithm",
+    "x-amz-copy-source-server-side-encryption-customer-key",
+    "x-amz-copy-source-server-side-encryption-customer-key-md5",
+];
+pub(crate) const COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+const METADATA_DIRECTIVE_HEADER: &str = "x-amz-metadata-directive";
+const STORAGE_CLASS_HEADER: &str = "x-amz-storage-class";
+const REPLICATION_STATUS_HEADER: &str = "x-amz-replication-status";
+const DELETE_IF_MATCH_HEADER: &str = "x-amz-if-match";
+const COPY_SOURCE_IF_MATCH_HEADER: &str = "x-amz-copy-source-if-match";
+const COPY_SOURCE_IF_NONE_MATCH_HEADER: &str = "x-amz-copy-source-if-none-match";
+const COPY_SOURCE_IF_MODIFIED_SINCE_HEADER: &str = "x-amz-copy-source-if-modified-since";
+const COPY_SOURCE_IF_UNMODIFIED_SINCE_HEADER: &str = "x-amz-copy-source-if-unmodified-since";
+/// Metadata entry recording the access key that wrote an object. It is kept
+/// out of the `x-amz-meta-*` response headers.
+pub(crate) const OWNER_META_KEY: &str = "x-maxio-owner";
+
+#[derive(Debug, Serialize)]
+#[serde(rename = "CopyObjectResult")]
+struct CopyObjectResultXml {
+    #[serde(rename = "ETag")]
+    etag: String,
+    #[serde(rename = "LastModified")]
+    last_modified: String,
+}
 
 #[derive(Debug, Serialize)]
 #[serde(rename = "ListBucketResult")]
@@ -71,6 +124,8 @@ struct ListBucketV2ResultXml {
     is_truncated: bool,
     #[serde(rename = "Contents", default)]
     contents: Vec<ObjectContentXml>,
+    #[serde(rename = "StartAfter", skip_serializing_if = "Option::is_none")]
+    start_after: Option<String>,
     #[serde(rename = "ContinuationToken", skip_serializing_if = "Option::is_none")]
     continuation_token: Option<String>,
     #[serde(
@@ -94,6 +149,8 @@ struct ObjectContentXml {
     size: i64,
     #[serde(rename = "StorageClass")]
     storage_class: String,
+    #[serde(rename = "Owner", skip_serializing_if = "Option::is_none")]
+    owner: Option<Owner>,
 }
 
 #[derive(Debug, Serialize)]
@@ -103,23 +160,10 @@ struct CommonPrefixXml {
 }
 
 fn xml_response<T: Serialize>(status: StatusCode, payload: &T) -> S3Result {
-    let xml = xml_to_string(payload).map_err(|err| {
-        S3Error::from(MaxioError::InternalError(format!(
-            "failed to serialize xml response: {err}"
-        )))
-    })?;
-    let body = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{xml}");
+    let body = to_s3_xml(payload)?;
     Ok((status, [("Content-Type", "application/xml")], body).into_response())
 }
 
-fn quoted_etag(etag: &str) -> String {
-    if etag.starts_with('"') && etag.ends_with('"') {
-        etag.to_string()
-    } else {
-        format!("\"{etag}\"")
-    }
-}
-
 fn header_value(value: &str) -> std::result::Result<HeaderValue, MaxioError> {
     HeaderValue::from_str(value)
         .map_err(|err| MaxioError::InvalidArgument(format!("invalid header value: {err}")))
@@ -137,8 +181,12 @@ fn write_object_headers(
         LAST_MODIFIED,
         header_value(&info.last_modified.to_rfc2822())?,
     );
+    headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
 
     for (key, value) in &info.metadata {
+        if key == OWNER_META_KEY {
+            continue;
+        }
         let header_name = HeaderName::from_bytes(format!("x-amz-meta-{key}").as_bytes())
             .map_err(|err| MaxioError::InvalidArgument(format!("invalid metadata key: {err}")))?;
         headers.insert(header_name, header_value(value)?);
@@ -147,6 +195,9 @@ fn write_object_headers(
     if let Some(encryption) = info.encryption.as_ref() {
         write_encryption_response_headers(headers, encryption)?;
     }
+    if let Some(storage_class) = info.storage_class.as_deref() {
+        headers.insert(STORAGE_CLASS_HEADER, header_value(storage_class)?);
+    }
 
     Ok(())
 }
@@ -165,15 +216,24 @@ fn write_encryption_response_headers(
     Ok(())
 }
 
-fn map_objects(objects: Vec<ObjectInfo>) -> Vec<ObjectContentXml> {
+fn map_objects(objects: Vec<ObjectInfo>, fetch_owner: bool) -> Vec<ObjectContentXml> {
     objects
         .into_iter()
         .map(|item| ObjectContentXml {
+            owner: fetch_owner.then(|| {
+                Owner::new(
+                    item.metadata
+                        .get(OWNER_META_KEY)
+                        .map_or(DEFAULT_OWNER, String::as_str),
+                )
+            }),
             key: item.key,
             last_modified: item.last_modified.to_rfc3339(),
             etag: quoted_etag(&item.etag),
             size: item.size,
-            storage_class: "STANDARD".to_string(),
+            storage_class: item
+                .storage_class
+                .unwrap_or_else(|| STANDARD_STORAGE_CLASS.to_string()),
         })
         .collect()
 }
@@ -193,35 +253,104 @@ fn parse_max_keys(query: &HashMap<String, String>) -> i32 {
         .unwrap_or(1000)
 }
 
-fn extract_put_metadata(headers: &HeaderMap) -> HashMap<String, String> {
+/// Collects the `x-amz-meta-*` headers. Values must be ASCII (clients
+/// RFC 2047-encode anything else) and names plus values must fit in
+/// [`MAX_USER_METADATA_SIZE`]. Names colliding with
+/// [`STORAGE_CLASS_META_KEY`] are rejected, so a client cannot set the
+/// storage class without it being validated.
+///
+/// Names are stored lowercase: the HTTP stack normalizes header names
+/// before they reach a handler, so the casing a client sent is not known
+/// here. S3 reports user metadata names in lowercase over REST as well, and
+/// header lookups are case-insensitive, so `X-Amz-Meta-MyKey` reads back
+/// under any casing.
+pub(crate) fn extract_put_metadata(
+    headers: &HeaderMap,
+) -> std::result::Result<HashMap<String, String>, MaxioError> {
     let mut metadata = HashMap::new();
+    let mut size = 0;
     for (name, value) in headers {
-        let name = name.as_str();
-        if let Some(meta_key) = name.strip_prefix("x-amz-meta-") {
-            if let Ok(meta_value) = value.to_str() {
-                metadata.insert(meta_key.to_string(), meta_value.to_string());
-            }
+        let Some(meta_key) = name.as_str().strip_prefix("x-amz-meta-") else {
+            continue;
+        };
+        if meta_key == STORAGE_CLASS_META_KEY {
+            return Err(MaxioError::InvalidArgument(format!(
+                "x-amz-meta-{meta_key} is reserved, set the storage class with {STORAGE_CLASS_HEADER}"
+            )));
+        }
+        let meta_value = value
+            .to_str()
+            .ok()
+            .filter(|value| value.is_ascii())
+            .ok_or_else(|| {
+                MaxioError::InvalidArgument(format!(
+                    "metadata value of x-amz-meta-{meta_key} must be ASCII"
+                ))
+            })?;
+        size += meta_key.len() + meta_value.len();
+        metadata.insert(meta_key.to_string(), meta_value.to_string());
+    }
+    if size > MAX_USER_METADATA_SIZE {
+        return Err(MaxioError::MetadataTooLarge(format!(
+            "{size} bytes, limit is {MAX_USER_METADATA_SIZE}"
+        )));
+    }
+    Ok(metadata)
+}
+
+/// Records the caller as the owner of the object written with `metadata`,
+/// replacing any owner carried over from a copy source.
+pub(crate) fn insert_owner(metadata: &mut HashMap<String, String>, caller: Option<&Caller>) {
+    let owner = caller.map_or(DEFAULT_OWNER, Caller::access_key);
+    metadata.insert(OWNER_META_KEY.to_string(), owner.to_string());
+}
+
+/// Validates `x-amz-storage-class` and passes a non-standard class on to the
+/// object layer through `metadata`.
+pub(crate) fn insert_storage_class(
+    headers: &HeaderMap,
+    metadata: &mut HashMap<String, String>,
+) -> std::result::Result<(), MaxioError> {
+    let Some(value) = headers.get(STORAGE_CLASS_HEADER) else {
+        return Ok(());
+    };
+    match value.to_str().map(str::trim) {
+        Ok(STANDARD_STORAGE_CLASS) => Ok(()),
+        Ok(REDUCED_REDUNDANCY_STORAGE_CLASS) => {
+            metadata.insert(
+                STORAGE_CLASS_META_KEY.to_string(),
+                REDUCED_REDUNDANCY_STORAGE_CLASS.to_string(),
+            );
+            Ok(())
         }
+        Ok(other) => Err(MaxioError::InvalidArgument(format!(
+            "unsupported storage class: {other}"
+        ))),
+        Err(_) => Err(MaxioError::InvalidArgument(
+            "invalid storage class header".to_string(),
+        )),
     }
-    metadata
 }
 
 fn parse_sse_c_headers(
     headers: &HeaderMap,
     require_complete_if_present: bool,
 ) -> std::result::Result<Option<GetEncryptionOptions>, MaxioError> {
-    let algorithm = headers
-        .get(SSE_C_ALGORITHM_HEADER)
-        .and_then(|value| value.to_str().ok())
-        .map(str::trim);
-    let key_b64 = headers
-        .get(SSE_C_KEY_HEADER)
-        .and_then(|value| value.to_str().ok())
-        .map(str::trim);
-    let key_md5 = headers
-        .get(SSE_C_KEY_MD5_HEADER)
-        .and_then(|value| value.to_str().ok())
-        .map(str::trim);
+    parse_customer_key(headers, SSE_C_HEADERS, require_complete_if_present)
+}
+
+/// Reads an SSE-C key from the `[algorithm, key, key MD5]` headers `names`.
+fn parse_customer_key(
+    headers: &HeaderMap,
+    names: [&str; 3],
+    require_complete_if_present: bool,
+) -> std::result::Result<Option<GetEncryptionOptions>, MaxioError> {
+    let [algorithm, key_b64, key_md5] = names.map(|name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    });
 
     let any_present = algorithm.is_some() || key_b64.is_some() || key_md5.is_some();
     if !any_present {
@@ -258,7 +387,7 @@ fn parse_sse_c_headers(
         ));
     }
 
-    let computed_md5 = BASE64_STANDARD.encode(Md5::digest(&key_bytes));
+    let computed_md5 = BASE64_STANDARD.encode(md5_digest(&key_bytes));
     if computed_md5 != key_md5 {
         return Err(MaxioError::InvalidArgument(
             "SSE-C customer key MD5 mismatch".to_string(),
@@ -319,27 +448,357 @@ fn parse_put_encryption(
     Ok(None)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn put_object(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(sniffing): Extension<ContentTypeSniffing>,
+    Extension(recent_puts): Extension<RecentPuts>,
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    caller: Option<Extension<Caller>>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     headers: HeaderMap,
     body: Bytes,
 ) -> S3Result {
+    // An explicit Content-Type is always kept; sniffing only fills the gap.
     let content_type = headers
         .get(CONTENT_TYPE)
-        .and_then(|value| value.to_str().ok());
-    let metadata = extract_put_metadata(&headers);
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| sniffing.detect(&key, &body));
+    let mut metadata = extract_put_metadata(&headers)?;
+    insert_storage_class(&headers, &mut metadata)?;
+    insert_owner(&mut metadata, caller.as_deref());
     let encryption = parse_put_encryption(&headers)?;
-    let info = store
-        .put_object(&bucket, &key, body, content_type, metadata, encryption)
-        .await?;
+    let condition = put_condition(&headers)?;
+
+    // Encrypted PUTs are never replayed; their keys may differ per request.
+    // Conditional ones are not either: a replay would skip the condition.
+    let fingerprint = (encryption.is_none() && condition.is_none())
+        .then(|| PutFingerprint::new(&bucket, &key, &body, content_type, &metadata));
+    if let Some(fingerprint) = fingerprint.as_ref()
+        && let Some(info) = recent_puts.replay(store.as_ref(), fingerprint).await?
+    {
+        return put_object_response(&info);
+    }
 
+    let info = match condition {
+        // The check and the write happen under the object's cluster-wide
+        // lock, so no other node can write the key in between.
+        Some(condition) => {
+            let lock = distributed
+                .object_lock(&bucket, &key, "conditional put")
+                .lock_guard_or_unavailable()
+                .await?;
+            let written = async {
+                let current = match store.get_object_info(&bucket, &key, None).await {
+                    Ok(current) => Some(current),
+                    Err(MaxioError::ObjectNotFound { .. }) => None,
+                    Err(err) => return Err(err),
+                };
+                condition.check(&bucket, &key, current.as_ref())?;
+                store
+                    .put_object(&bucket, &key, body, content_type, metadata, encryption)
+                    .await
+            }
+            .await;
+            lock.unlock().await;
+            written?
+        }
+        None => {
+            store
+                .put_object(&bucket, &key, body, content_type, metadata, encryption)
+                .await?
+        }
+    };
+    if let Some(fingerprint) = fingerprint {
+        recent_puts.record(fingerprint, &info);
+    }
+    let response = put_object_response(&info)?;
+
+    spawn_notification(
+        notifications,
+        bucket.clone(),
+        S3Event {
+            event_version: "2.1".to_string(),
+            event_source: "aws:s3".to_string(),
+            aws_region: "".to_string(),
+            event_time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            event_name: "s3:ObjectCreated:Put".to_string(),
+            request_id: request_id.to_string(),
+            bucket: NotificationBucketInfo {
+                name: bucket.clone(),
+                arn: format!("arn:aws:s3:::{bucket}"),
+            },
+            object: NotificationObjectInfo {
+                key,
+                size: info.size,
+                etag: info.etag.clone(),
+            },
+        },
+    );
+
+    Ok(response)
+}
+
+/// A PUT that only goes ahead while the object is in an expected state.
+enum PutCondition {
+    /// `If-None-Match: *`: the key must not exist yet.
+    Absent,
+    /// `If-Match`: the latest version's ETag, or `*` for any existing
+    /// object.
+    ETag(String),
+}
+
+impl PutCondition {
+    /// Fails with `PreconditionFailed` unless `current`, the latest version
+    /// if any, satisfies the condition. Like AWS, an `If-Match` on a missing
+    /// key answers `NoSuchKey`.
+    fn check(
+        &self,
+        bucket: &str,
+        key: &str,
+        current: Option<&ObjectInfo>,
+    ) -> std::result::Result<(), MaxioError> {
+        match (self, current) {
+            (Self::Absent, None) => Ok(()),
+            (Self::Absent, Some(_)) => Err(MaxioError::PreconditionFailed(format!(
+                "{bucket}/{key} already exists"
+            ))),
+            (Self::ETag(_), None) => Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            }),
+            (Self::ETag(etag), Some(current)) if etag == "*" || *etag == current.etag => Ok(()),
+            (Self::ETag(_), Some(_)) => Err(MaxioError::PreconditionFailed(format!(
+                "{bucket}/{key} no longer matches If-Match"
+            ))),
+        }
+    }
+}
+
+/// S3 only supports `*` for `If-None-Match` on writes.
+fn put_condition(headers: &HeaderMap) -> std::result::Result<Option<PutCondition>, MaxioError> {
+    if let Some(value) = headers.get(IF_NONE_MATCH) {
+        return match value.to_str().map(str::trim) {
+            Ok("*") => Ok(Some(PutCondition::Absent)),
+            _ => Err(MaxioError::NotImplemented(
+                "If-None-Match on PUT only supports *".to_string(),
+            )),
+        };
+    }
+    headers
+        .get(IF_MATCH)
+        .map(|value| {
+            value
+                .to_str()
+                .map(|etag| PutCondition::ETag(normalize_etag(etag)))
+                .map_err(|_| MaxioError::InvalidArgument("invalid If-Match header".to_string()))
+        })
+        .transpose()
+}
+
+fn put_object_response(info: &ObjectInfo) -> S3Result {
     let mut response_headers = HeaderMap::new();
     response_headers.insert(ETAG, header_value(&quoted_etag(&info.etag))?);
+    if let Some(version_id) = info.version_id.as_deref() {
+        response_headers.insert("x-amz-version-id", header_value(version_id)?);
+    }
     if let Some(encryption) = info.encryption.as_ref() {
         write_encryption_response_headers(&mut response_headers, encryption)?;
     }
+    Ok((StatusCode::OK, response_headers).into_response())
+}
+
+/// Parses `x-amz-copy-source` (`[/]bucket/key[?versionId=id]`, URL-encoded).
+fn parse_copy_source(
+    value: &str,
+) -> std::result::Result<(String, String, Option<String>), MaxioError> {
+    let (path, query) = match value.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (value, None),
+    };
+    let path = percent_decode_str(path.trim_start_matches('/'))
+        .decode_utf8()
+        .map_err(|_| MaxioError::InvalidArgument("invalid copy source encoding".to_string()))?;
+    let (bucket, key) = path
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| MaxioError::InvalidArgument("invalid copy source".to_string()))?;
+
+    let version_id = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("versionId="))
+        .filter(|version_id| !version_id.is_empty())
+        .map(ToOwned::to_owned);
+
+    Ok((bucket.to_string(), key.to_string(), version_id))
+}
+
+/// Evaluates the `x-amz-copy-source-if-*` headers against the copy source.
+/// As in S3, a matching `if-match` overrides a failing `if-unmodified-since`
+/// and a failing `if-none-match` wins over a passing `if-modified-since`.
+fn check_copy_source_conditions(
+    headers: &HeaderMap,
+    source: &ObjectInfo,
+) -> std::result::Result<(), MaxioError> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let etag_matches = |value: &str| {
+        value.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || normalize_etag(candidate) == normalize_etag(&source.etag)
+        })
+    };
+    let last_modified = source.last_modified.timestamp();
+    let since = |name: &str| header(name).and_then(http_date_seconds);
+
+    let failed = match header(COPY_SOURCE_IF_MATCH_HEADER) {
+        Some(value) => (!etag_matches(value)).then_some(COPY_SOURCE_IF_MATCH_HEADER),
+        None => since(COPY_SOURCE_IF_UNMODIFIED_SINCE_HEADER)
+            .filter(|date| last_modified > *date)
+            .map(|_| COPY_SOURCE_IF_UNMODIFIED_SINCE_HEADER),
+    }
+    .or_else(|| match header(COPY_SOURCE_IF_NONE_MATCH_HEADER) {
+        Some(value) => etag_matches(value).then_some(COPY_SOURCE_IF_NONE_MATCH_HEADER),
+        None => since(COPY_SOURCE_IF_MODIFIED_SINCE_HEADER)
+            .filter(|date| last_modified <= *date)
+            .map(|_| COPY_SOURCE_IF_MODIFIED_SINCE_HEADER),
+    });
+
+    match failed {
+        Some(name) => Err(MaxioError::PreconditionFailed(format!(
+            "copy source does not satisfy {name}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// An HTTP date as seconds since the epoch. Last-Modified is only sent with
+/// second precision, so dates are compared to it in whole seconds.
+fn http_date_seconds(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+fn has_copy_source_conditions(headers: &HeaderMap) -> bool {
+    [
+        COPY_SOURCE_IF_MATCH_HEADER,
+        COPY_SOURCE_IF_NONE_MATCH_HEADER,
+        COPY_SOURCE_IF_MODIFIED_SINCE_HEADER,
+        COPY_SOURCE_IF_UNMODIFIED_SINCE_HEADER,
+    ]
+    .iter()
+    .any(|name| headers.contains_key(*name))
+}
+
+pub async fn copy_object(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(notifications): Extension<Arc<NotificationSys>>,
+    caller: Option<Extension<Caller>>,
+    request_id: RequestId,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> S3Result {
+    let copy_source = headers
+        .get(COPY_SOURCE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| MaxioError::InvalidArgument("missing copy source".to_string()))?;
+    let (source_bucket, source_key, source_version_id) = parse_copy_source(copy_source)?;
+    // The auth middleware only authorized the write to the destination;
+    // reading the source needs its own grant.
+    if let Some(Extension(caller)) = &caller {
+        let action = if source_version_id.is_some() {
+            "s3:GetObjectVersion"
+        } else {
+            "s3:GetObject"
+        };
+        if !caller.is_allowed(
+            action,
+            &format!("arn:aws:s3:::{source_bucket}/{source_key}"),
+        ) {
+            return Err(S3Error::from(MaxioError::AccessDenied(
+                "iam policy denied reading the copy source".to_string(),
+            )));
+        }
+    }
+    let replace_metadata = match headers
+        .get(METADATA_DIRECTIVE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+    {
+        None | Some("COPY") => false,
+        Some("REPLACE") => true,
+        Some(other) => {
+            return Err(S3Error::from(MaxioError::InvalidArgument(format!(
+                "unsupported metadata directive: {other}"
+            ))));
+        }
+    };
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let encryption = parse_put_encryption(&headers)?;
+    let source_encryption = parse_customer_key(&headers, COPY_SOURCE_SSE_C_HEADERS, true)?;
+
+    let self_copy = source_bucket == bucket && source_key == key && source_version_id.is_none();
+    if self_copy && !replace_metadata {
+        return Err(S3Error::from(MaxioError::InvalidArgument(
+            "copying an object to itself requires the REPLACE metadata directive".to_string(),
+        )));
+    }
+
+    // Changing only metadata of the current object in an unversioned bucket
+    // rewrites xl.meta in place instead of the object data.
+    let metadata_only = self_copy
+        && encryption.is_none()
+        && source_encryption.is_none()
+        && store.get_bucket_versioning(&bucket).await? == VersioningState::Unversioned;
+
+    // The destination's versioning state decides the new version id in
+    // put_object; the source version copied from is reported separately.
+    let (info, copied_version_id) = if metadata_only {
+        if has_copy_source_conditions(&headers) {
+            let source_info = store.get_object_info(&bucket, &key, None).await?;
+            check_copy_source_conditions(&headers, &source_info)?;
+        }
+        let mut metadata = extract_put_metadata(&headers)?;
+        insert_owner(&mut metadata, caller.as_deref());
+        let info = store
+            .update_object_metadata(&bucket, &key, content_type, metadata)
+            .await?;
+        (info, None)
+    } else {
+        let source = CopySource {
+            bucket: source_bucket,
+            key: source_key,
+            version_id: source_version_id,
+            encryption: source_encryption,
+        };
+        let caller = caller.as_deref();
+        let destination_metadata = |source_info: &ObjectInfo| {
+            check_copy_source_conditions(&headers, source_info)?;
+            let (content_type, mut metadata) = if replace_metadata {
+                (
+                    content_type.map(ToOwned::to_owned),
+                    extract_put_metadata(&headers)?,
+                )
+            } else {
+                (
+                    Some(source_info.content_type.clone()),
+                    source_info.metadata.clone(),
+                )
+            };
+            insert_storage_class(&headers, &mut metadata)?;
+            insert_owner(&mut metadata, caller);
+            Ok((content_type, metadata))
+        };
+        let (source_info, info) = store
+            .copy_object(&source, &bucket, &key, &destination_metadata, encryption)
+            .await?;
+        let copied_version_id = source_info.version_id;
+        (info, copied_version_id)
+    };
 
     spawn_notification(
         notifications,
@@ -348,8 +807,9 @@ pub async fn put_object(
             event_version: "2.1".to_string(),
             event_source: "aws:s3".to_string(),
             aws_region: "".to_string(),
-            event_time: Utc::now().to_rfc3339(),
-            event_name: "s3:ObjectCreated:Put".to_string(),
+            event_time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            event_name: "s3:ObjectCreated:Copy".to_string(),
+            request_id: request_id.to_string(),
             bucket: NotificationBucketInfo {
                 name: bucket.clone(),
                 arn: format!("arn:aws:s3:::{bucket}"),
@@ -362,11 +822,33 @@ pub async fn put_object(
         },
     );
 
-    Ok((StatusCode::OK, response_headers).into_response())
+    let mut response = xml_response(
+        StatusCode::OK,
+        &CopyObjectResultXml {
+            etag: quoted_etag(&info.etag),
+            last_modified: info.last_modified.to_rfc3339(),
+        },
+    )?;
+    if let Some(version_id) = info.version_id.as_deref() {
+        response
+            .headers_mut()
+            .insert("x-amz-version-id", header_value(version_id)?);
+    }
+    if let Some(version_id) = copied_version_id.as_deref() {
+        response
+            .headers_mut()
+            .insert("x-amz-copy-source-version-id", header_value(version_id)?);
+    }
+    if let Some(encryption) = info.encryption.as_ref() {
+        write_encryption_response_headers(response.headers_mut(), encryption)?;
+    }
+
+    Ok(response)
 }
 
 pub async fn get_object(
     State(store): State<Arc<dyn ObjectLayer>>,
+    replication: Option<Extension<Arc<ReplicationState>>>,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
     headers: HeaderMap,
@@ -386,12 +868,8 @@ pub async fn get_object(
     };
     let total_len = data.len();
 
-    let range_header = headers
-        .get(RANGE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| parse_range_header(s, total_len));
-
-    let (status, response_data, content_range) = match range_header {
+    let (status, response_data, content_range) = match requested_range(&headers, &info, total_len)?
+    {
         Some((start, end)) => {
             let slice = data.slice(start..=end);
             let content_range = format!("bytes {}-{}/{}", start, end, total_len);
@@ -404,6 +882,7 @@ pub async fn get_object(
     let mut response = Response::new(Body::from(response_data));
     *response.status_mut() = status;
     write_object_headers(response.headers_mut(), &info, response_len)?;
+    write_replication_status(response.headers_mut(), replication, &bucket, &key, &info).await;
     if let Some(version_id) = info.version_id.as_deref() {
         response.headers_mut().insert(
             "x-amz-version-id",
@@ -423,65 +902,196 @@ pub async fn get_object(
     Ok(response)
 }
 
-fn parse_range_header(header: &str, total_len: usize) -> Option<(usize, usize)> {
-    let header = header.strip_prefix("bytes=")?;
-    let parts: Vec<&str> = header.split('-').collect();
-    if parts.len() != 2 {
-        return None;
+/// Whether a `Range` request may be served partially. `If-Range` carries
+/// either an ETag or a Last-Modified date; when it no longer matches the
+/// object the whole body is returned instead.
+fn if_range_matches(headers: &HeaderMap, info: &ObjectInfo) -> bool {
+    let Some(validator) = headers.get(IF_RANGE) else {
+        return true;
+    };
+    let Ok(validator) = validator.to_str().map(str::trim) else {
+        return false;
+    };
+    if validator.starts_with("W/") {
+        // Weak validators never satisfy If-Range.
+        return false;
+    }
+    if validator.starts_with('"') {
+        return normalize_etag(validator) == normalize_etag(&info.etag);
+    }
+    http_date_seconds(validator) == Some(info.last_modified.timestamp())
+}
+
+/// The inclusive byte range a GET or HEAD selects out of an object of
+/// `total_len` bytes, or `None` for the whole object.
+fn requested_range(
+    headers: &HeaderMap,
+    info: &ObjectInfo,
+    total_len: usize,
+) -> std::result::Result<Option<(usize, usize)>, MaxioError> {
+    match headers.get(RANGE).and_then(|value| value.to_str().ok()) {
+        Some(range) if if_range_matches(headers, info) => parse_range_header(range, total_len),
+        _ => Ok(None),
     }
+}
 
-    let start = parts[0].parse::<usize>().ok();
-    let end_str = parts[1];
+/// Parses a single `bytes=` range. A header that is not one well-formed
+/// range is ignored, as HTTP requires; a range selecting nothing of the
+/// object is rejected with `InvalidRange`.
+fn parse_range_header(
+    header: &str,
+    total_len: usize,
+) -> std::result::Result<Option<(usize, usize)>, MaxioError> {
+    let unsatisfiable = || MaxioError::InvalidRange {
+        size: total_len as u64,
+    };
+    let Some((start, end)) = header
+        .strip_prefix("bytes=")
+        .and_then(|range| range.split_once('-'))
+    else {
+        return Ok(None);
+    };
+    let last = total_len.checked_sub(1);
 
-    match (start, end_str.is_empty()) {
-        (Some(s), true) => Some((s, total_len.saturating_sub(1))),
-        (Some(s), false) => {
-            let e = end_str.parse::<usize>().ok()?;
-            Some((s, e.min(total_len.saturating_sub(1))))
+    match (start.parse::<usize>().ok(), end) {
+        (Some(start), "") => match last {
+            Some(last) if start <= last => Ok(Some((start, last))),
+            _ => Err(unsatisfiable()),
+        },
+        (Some(start), end) => {
+            let Some(end) = end.parse::<usize>().ok().filter(|end| *end >= start) else {
+                return Ok(None);
+            };
+            match last {
+                Some(last) if start <= last => Ok(Some((start, end.min(last)))),
+                _ => Err(unsatisfiable()),
+            }
         }
-        (None, false) => {
-            let suffix_len = end_str.parse::<usize>().ok()?;
-            let start = total_len.saturating_sub(suffix_len);
-            Some((start, total_len.saturating_sub(1)))
+        (None, suffix) if start.is_empty() => {
+            let Ok(suffix_len) = suffix.parse::<usize>() else {
+                return Ok(None);
+            };
+            match last {
+                Some(last) if suffix_len > 0 => {
+                    Ok(Some((total_len.saturating_sub(suffix_len), last)))
+                }
+                _ => Err(unsatisfiable()),
+            }
         }
-        _ => None,
+        _ => Ok(None),
     }
 }
 
 pub async fn head_object(
     State(store): State<Arc<dyn ObjectLayer>>,
+    replication: Option<Extension<Arc<ReplicationState>>>,
     Path((bucket, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
     headers: HeaderMap,
 ) -> S3Result {
     let encryption = parse_sse_c_headers(&headers, false)?;
-    let info = store.get_object_info(&bucket, &key, encryption).await?;
-    let mut response = Response::new(Body::empty());
-    *response.status_mut() = StatusCode::OK;
-    let content_len = if info.size >= 0 {
-        info.size as usize
-    } else {
-        0
+    let info = match query.get("versionId").filter(|item| !item.is_empty()) {
+        Some(version_id) => {
+            store
+                .get_object_version_info(&bucket, &key, version_id, encryption)
+                .await?
+        }
+        None => store.get_object_info(&bucket, &key, encryption).await?,
     };
-    write_object_headers(response.headers_mut(), &info, content_len)?;
+    let total_len = usize::try_from(info.size).unwrap_or_default();
+
+    // A ranged HEAD answers with the headers the ranged GET would send.
+    let mut response = Response::new(Body::empty());
+    match requested_range(&headers, &info, total_len)? {
+        Some((start, end)) => {
+            *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+            write_object_headers(response.headers_mut(), &info, end - start + 1)?;
+            response.headers_mut().insert(
+                CONTENT_RANGE,
+                header_value(&format!("bytes {start}-{end}/{total_len}"))?,
+            );
+        }
+        None => write_object_headers(response.headers_mut(), &info, total_len)?,
+    }
+    write_replication_status(response.headers_mut(), replication, &bucket, &key, &info).await;
     Ok(response)
 }
 
+/// Reports how far the served version has replicated, when this node
+/// replicates it.
+async fn write_replication_status(
+    headers: &mut HeaderMap,
+    replication: Option<Extension<Arc<ReplicationState>>>,
+    bucket: &str,
+    key: &str,
+    info: &ObjectInfo,
+) {
+    let Some(Extension(replication)) = replication else {
+        return;
+    };
+    if let Some(status) = replication
+        .get_overall_status(bucket, key, info.version_id.as_deref())
+        .await
+    {
+        headers.insert(
+            REPLICATION_STATUS_HEADER,
+            HeaderValue::from_static(status.as_str()),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn delete_object(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    replication: Option<Extension<Arc<ReplicationPool>>>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> S3Result {
     if let Some(version_id) = query.get("versionId").filter(|item| !item.is_empty()) {
-        store
+        let deleted = store
             .delete_object_version(&bucket, &key, version_id)
             .await?;
-        return Ok(StatusCode::NO_CONTENT.into_response());
+        return deleted_object_response(&deleted);
     }
 
-    let versioning = store.get_bucket_versioning(&bucket).await?;
+    // `If-Match` makes the delete conditional on the current ETag; AWS
+    // answers a stale one with `412` and leaves the object in place.
+    let if_match = [IF_MATCH.as_str(), DELETE_IF_MATCH_HEADER]
+        .into_iter()
+        .find_map(|name| headers.get(name))
+        .map(|value| {
+            value
+                .to_str()
+                .map(|etag| DeleteCondition::ETag(normalize_etag(etag)))
+                .map_err(|_| MaxioError::InvalidArgument("invalid If-Match header".to_string()))
+        })
+        .transpose()?;
+
     let object_info = store.get_object_info(&bucket, &key, None).await.ok();
-    store.delete_object(&bucket, &key).await?;
+    let deleted = match if_match {
+        // Locked cluster-wide so a write on another node cannot slip in
+        // between the check and the delete.
+        Some(condition) => {
+            let lock = distributed
+                .object_lock(&bucket, &key, "conditional delete")
+                .lock_guard_or_unavailable()
+                .await?;
+            let deleted = store.delete_object_if(&bucket, &key, &condition).await;
+            lock.unlock().await;
+            deleted?
+        }
+        None => store.delete_object(&bucket, &key).await?,
+    };
+
+    if deleted.delete_marker
+        && let Some(Extension(replication)) = replication
+    {
+        replicate_delete_marker(&store, &replication, &bucket, &key, &deleted).await;
+    }
 
     spawn_notification(
         notifications,
@@ -490,8 +1100,9 @@ pub async fn delete_object(
             event_version: "2.1".to_string(),
             event_source: "aws:s3".to_string(),
             aws_region: "".to_string(),
-            event_time: Utc::now().to_rfc3339(),
+            event_time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
             event_name: "s3:ObjectRemoved:Delete".to_string(),
+            request_id: request_id.to_string(),
             bucket: NotificationBucketInfo {
                 name: bucket.clone(),
                 arn: format!("arn:aws:s3:::{bucket}"),
@@ -504,11 +1115,57 @@ pub async fn delete_object(
         },
     );
 
-    if versioning == VersioningState::Enabled {
-        return Ok((StatusCode::NO_CONTENT, [("x-amz-delete-marker", "true")]).into_response());
+    deleted_object_response(&deleted)
+}
+
+/// Hands a new delete marker to the replication pool when the bucket's
+/// replication rules cover the key. The delete has already happened, so a
+/// configuration that cannot be read only skips its replication.
+async fn replicate_delete_marker(
+    store: &Arc<dyn ObjectLayer>,
+    replication: &ReplicationPool,
+    bucket: &str,
+    key: &str,
+    deleted: &DeletedObject,
+) {
+    let config = match load_replication_config(store, bucket).await {
+        Ok(Some(config)) => config,
+        Ok(None) => return,
+        Err(err) => {
+            warn!(bucket, key, error = %err, "skipping delete marker replication");
+            return;
+        }
+    };
+    let targets = replication.targets_for(&config, key).await;
+    if targets.is_empty() {
+        return;
     }
+    replication
+        .submit_delete(DeletedObjectReplicationInfo {
+            bucket: bucket.to_string(),
+            object: key.to_string(),
+            version_id: deleted.version_id.clone(),
+            retry_count: 0,
+            targets,
+        })
+        .await;
+}
 
-    Ok(StatusCode::NO_CONTENT.into_response())
+/// `204 No Content` naming the version a delete created or removed, and
+/// whether that version is a delete marker.
+fn deleted_object_response(deleted: &DeletedObject) -> S3Result {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    if deleted.delete_marker {
+        response
+            .headers_mut()
+            .insert("x-amz-delete-marker", HeaderValue::from_static("true"));
+    }
+    if let Some(version_id) = deleted.version_id.as_deref() {
+        response
+            .headers_mut()
+            .insert("x-amz-version-id", header_value(version_id)?);
+    }
+    Ok(response)
 }
 
 fn spawn_notification(notifications: Arc<NotificationSys>, bucket: String, event: S3Event) {
@@ -530,7 +1187,14 @@ pub async fn list_objects_v1(
     let max_keys = parse_max_keys(&query);
 
     let result = store
-        .list_objects(&bucket, &prefix, &marker, &delimiter, max_keys)
+        .list_objects(
+            &bucket,
+            &prefix,
+            &marker,
+            &delimiter,
+            max_keys,
+            ListOrder::KeyAscending,
+        )
         .await?;
     let payload = ListBucketResultXml {
         name: bucket,
@@ -538,7 +1202,7 @@ pub async fn list_objects_v1(
         marker,
         max_keys,
         is_truncated: result.is_truncated,
-        contents: map_objects(result.objects),
+        contents: map_objects(result.objects, true),
         common_prefixes: map_prefixes(result.prefixes),
     };
 
@@ -552,12 +1216,18 @@ pub async fn list_objects_v2(
 ) -> S3Result {
     let prefix = query.get("prefix").cloned().unwrap_or_default();
     let continuation_token = query.get("continuation-token").cloned();
+    let start_after = query.get("start-after").cloned();
+    // `start-after` only positions the first page; a continuation token
+    // already points past it and takes over.
     let marker = continuation_token
         .clone()
-        .or_else(|| query.get("start-after").cloned())
+        .or_else(|| start_after.clone())
         .unwrap_or_default();
     let delimiter = query.get("delimiter").cloned().unwrap_or_default();
     let max_keys = parse_max_keys(&query);
+    let fetch_owner = query
+        .get("fetch-owner")
+        .is_some_and(|value| value == "true");
 
     let ListObjectsResult {
         objects,
@@ -565,7 +1235,14 @@ pub async fn list_objects_v2(
         is_truncated,
         next_marker,
     } = store
-        .list_objects(&bucket, &prefix, &marker, &delimiter, max_keys)
+        .list_objects(
+            &bucket,
+            &prefix,
+            &marker,
+            &delimiter,
+            max_keys,
+            ListOrder::KeyAscending,
+        )
         .await?;
 
     let key_count = objects.len() as i32;
@@ -575,7 +1252,8 @@ pub async fn list_objects_v2(
         key_count,
         max_keys,
         is_truncated,
-        contents: map_objects(objects),
+        contents: map_objects(objects, fetch_owner),
+        start_after,
         continuation_token,
         next_continuation_token: next_marker,
         common_prefixes: map_prefixes(prefixes),
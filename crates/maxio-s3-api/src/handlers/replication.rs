@@ -8,14 +8,12 @@ use axum::{
 };
 use maxio_common::error::MaxioError;
 use maxio_distributed::ReplicationConfig;
-use maxio_storage::traits::ObjectLayer;
+use maxio_storage::{naming::CONFIG_BUCKET, traits::ObjectLayer};
 
 use crate::error::S3Error;
 
 type S3Result = Result<Response, S3Error>;
 
-const INTERNAL_CONFIG_BUCKET: &str = ".minio.sys";
-
 fn replication_config_key(bucket: &str) -> String {
     format!("buckets/{bucket}/replication/config.xml")
 }
@@ -46,12 +44,12 @@ fn validate_replication_config(config: &ReplicationConfig) -> Result<(), MaxioEr
             )));
         }
 
-        if let Some(priority) = rule.priority {
-            if !priorities.insert(priority) {
-                return Err(MaxioError::InvalidArgument(format!(
-                    "duplicate replication rule priority: {priority}"
-                )));
-            }
+        if let Some(priority) = rule.priority
+            && !priorities.insert(priority)
+        {
+            return Err(MaxioError::InvalidArgument(format!(
+                "duplicate replication rule priority: {priority}"
+            )));
         }
     }
 
@@ -59,7 +57,7 @@ fn validate_replication_config(config: &ReplicationConfig) -> Result<(), MaxioEr
 }
 
 async fn ensure_internal_bucket(store: &Arc<dyn ObjectLayer>) -> Result<(), MaxioError> {
-    match store.make_bucket(INTERNAL_CONFIG_BUCKET).await {
+    match store.make_bucket(CONFIG_BUCKET).await {
         Ok(()) | Err(MaxioError::BucketAlreadyExists(_)) => Ok(()),
         Err(err) => Err(err),
     }
@@ -82,7 +80,7 @@ pub async fn put_bucket_replication(
     ensure_internal_bucket(&store).await?;
     store
         .put_object(
-            INTERNAL_CONFIG_BUCKET,
+            CONFIG_BUCKET,
             &key,
             Bytes::from(xml),
             Some("application/xml"),
@@ -99,23 +97,36 @@ pub async fn get_bucket_replication(
 ) -> S3Result {
     store.get_bucket_info(&bucket).await?;
 
-    let key = replication_config_key(&bucket);
-    let (_, body) = store
-        .get_object(INTERNAL_CONFIG_BUCKET, &key, None)
-        .await
-        .map_err(|err| match err {
-            MaxioError::ObjectNotFound { .. } => MaxioError::InvalidArgument(
+    let config = load_replication_config(&store, &bucket)
+        .await?
+        .ok_or_else(|| {
+            MaxioError::InvalidArgument(
                 "replication configuration not found for bucket".to_string(),
-            ),
-            other => other,
+            )
         })?;
+    let xml = config.to_xml()?;
+    xml_response(StatusCode::OK, xml)
+}
 
+/// The bucket's replication configuration, or `None` when it has none.
+pub(crate) async fn load_replication_config(
+    store: &Arc<dyn ObjectLayer>,
+    bucket: &str,
+) -> Result<Option<ReplicationConfig>, MaxioError> {
+    let body = match store
+        .get_object(CONFIG_BUCKET, &replication_config_key(bucket), None)
+        .await
+    {
+        Ok((_, body)) => body,
+        Err(MaxioError::ObjectNotFound { .. } | MaxioError::BucketNotFound(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
     let config_body = std::str::from_utf8(&body).map_err(|err| {
-        MaxioError::InternalError(format!("stored replication config is not valid UTF-8: {err}"))
+        MaxioError::InternalError(format!(
+            "stored replication config is not valid UTF-8: {err}"
+        ))
     })?;
-    let config = ReplicationConfig::from_xml(config_body)?;
-    let xml = config.to_xml()?;
-    xml_response(StatusCode::OK, xml)
+    ReplicationConfig::from_xml(config_body).map(Some)
 }
 
 pub async fn delete_bucket_replication(
@@ -125,8 +136,8 @@ pub async fn delete_bucket_replication(
     store.get_bucket_info(&bucket).await?;
 
     let key = replication_config_key(&bucket);
-    match store.delete_object(INTERNAL_CONFIG_BUCKET, &key).await {
-        Ok(()) | Err(MaxioError::ObjectNotFound { .. }) => {
+    match store.delete_object(CONFIG_BUCKET, &key).await {
+        Ok(_) | Err(MaxioError::ObjectNotFound { .. }) => {
             Ok(StatusCode::NO_CONTENT.into_response())
         }
         Err(err) => Err(S3Error::from(err)),
@@ -29,6 +29,19 @@ struct ListVersionsResultXml {
     name: String,
     #[serde(rename = "Prefix")]
     prefix: String,
+    #[serde(rename = "KeyMarker")]
+    key_marker: String,
+    #[serde(rename = "VersionIdMarker")]
+    version_id_marker: String,
+    #[serde(rename = "NextKeyMarker", skip_serializing_if = "Option::is_none")]
+    next_key_marker: Option<String>,
+    #[serde(
+        rename = "NextVersionIdMarker",
+        skip_serializing_if = "Option::is_none"
+    )]
+    next_version_id_marker: Option<String>,
+    #[serde(rename = "Delimiter", skip_serializing_if = "String::is_empty")]
+    delimiter: String,
     #[serde(rename = "MaxKeys")]
     max_keys: i32,
     #[serde(rename = "IsTruncated")]
@@ -37,6 +50,14 @@ struct ListVersionsResultXml {
     versions: Vec<VersionXml>,
     #[serde(rename = "DeleteMarker", default)]
     delete_markers: Vec<DeleteMarkerXml>,
+    #[serde(rename = "CommonPrefixes", default)]
+    common_prefixes: Vec<CommonPrefixXml>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommonPrefixXml {
+    #[serde(rename = "Prefix")]
+    prefix: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -168,18 +189,45 @@ pub async fn list_object_versions(
     Query(query): Query<HashMap<String, String>>,
 ) -> S3Result {
     let prefix = query.get("prefix").cloned().unwrap_or_default();
+    let key_marker = query.get("key-marker").cloned().unwrap_or_default();
+    let version_id_marker = query.get("version-id-marker").cloned().unwrap_or_default();
+    let delimiter = query.get("delimiter").cloned().unwrap_or_default();
     let max_keys = parse_max_keys(&query);
-    let items = store
-        .list_object_versions(&bucket, &prefix, max_keys)
+    if !version_id_marker.is_empty() && key_marker.is_empty() {
+        return Err(MaxioError::InvalidArgument(
+            "a version-id-marker cannot be specified without a key-marker".to_string(),
+        )
+        .into());
+    }
+
+    let listed = store
+        .list_object_versions(
+            &bucket,
+            &prefix,
+            &key_marker,
+            &version_id_marker,
+            &delimiter,
+            max_keys,
+        )
         .await?;
-    let (versions, delete_markers) = split_versions(items);
+    let (versions, delete_markers) = split_versions(listed.versions);
     let payload = ListVersionsResultXml {
         name: bucket,
         prefix,
+        key_marker,
+        version_id_marker,
+        next_key_marker: listed.next_key_marker,
+        next_version_id_marker: listed.next_version_id_marker,
+        delimiter,
         max_keys,
-        is_truncated: false,
+        is_truncated: listed.is_truncated,
         versions,
         delete_markers,
+        common_prefixes: listed
+            .prefixes
+            .into_iter()
+            .map(|prefix| CommonPrefixXml { prefix })
+            .collect(),
     };
     xml_response(StatusCode::OK, &payload)
 }
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Extension,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    response::{IntoResponse, Response},
+};
+use maxio_common::error::MaxioError;
+use maxio_storage::traits::ObjectLayer;
+use quick_xml::{de::from_str as xml_from_str, se::to_string as xml_to_string};
+use serde::Serialize;
+
+use crate::{
+    error::S3Error,
+    handlers::object::get_object,
+    website::{WebsiteConfiguration, WebsiteStore},
+};
+
+type S3Result = Result<Response, S3Error>;
+
+fn xml_response<T: Serialize>(status: StatusCode, payload: &T) -> S3Result {
+    let xml = xml_to_string(payload).map_err(|err| {
+        S3Error::from(MaxioError::InternalError(format!(
+            "failed to serialize xml response: {err}"
+        )))
+    })?;
+    let body = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{xml}");
+    Ok((status, [("Content-Type", "application/xml")], body).into_response())
+}
+
+pub async fn put_bucket_website(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    Path(bucket): Path<String>,
+    body: Bytes,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    let body_str = std::str::from_utf8(&body)
+        .map_err(|err| MaxioError::InvalidArgument(format!("invalid xml body encoding: {err}")))?;
+    let config: WebsiteConfiguration = xml_from_str(body_str)
+        .map_err(|err| MaxioError::InvalidArgument(format!("invalid website xml body: {err}")))?;
+    config.validate()?;
+    website.set_config(&bucket, &config).await?;
+    Ok(StatusCode::OK.into_response())
+}
+
+pub async fn get_bucket_website(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    Path(bucket): Path<String>,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    let Some(config) = website.get_config(&bucket).await? else {
+        return Err(S3Error::from(MaxioError::InvalidArgument(
+            "website configuration not found for bucket".to_string(),
+        )));
+    };
+    xml_response(StatusCode::OK, &config)
+}
+
+pub async fn delete_bucket_website(
+    State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    Path(bucket): Path<String>,
+) -> S3Result {
+    store.get_bucket_info(&bucket).await?;
+    website.delete_config(&bucket).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// The website configuration a GET is served under, if any. Website
+/// behaviour only applies to anonymous requests without a subresource;
+/// signed requests keep the S3 API semantics.
+pub(crate) async fn website_for_request(
+    website: &WebsiteStore,
+    bucket: &str,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<Option<WebsiteConfiguration>, MaxioError> {
+    if !query.is_empty() || headers.contains_key(AUTHORIZATION) {
+        return Ok(None);
+    }
+    match website.get_config(bucket).await {
+        Err(MaxioError::BucketNotFound(_)) => Ok(None),
+        result => result,
+    }
+}
+
+/// Serves `key` the way a website endpoint would: directory keys map to the
+/// index document, and a missing object is answered with the error
+/// document under a `404` when one is configured.
+pub(crate) async fn serve_website_object(
+    store: Arc<dyn ObjectLayer>,
+    bucket: String,
+    key: &str,
+    config: &WebsiteConfiguration,
+    headers: HeaderMap,
+) -> S3Result {
+    let result = get_object(
+        State(Arc::clone(&store)),
+        None,
+        Path((bucket.clone(), config.resolve(key))),
+        Query(HashMap::new()),
+        headers,
+    )
+    .await;
+
+    let Err(S3Error(MaxioError::ObjectNotFound { .. })) = &result else {
+        return result;
+    };
+    let Some(error_document) = config.error_document.as_ref() else {
+        return result;
+    };
+    match get_object(
+        State(store),
+        None,
+        Path((bucket, error_document.key.clone())),
+        Query(HashMap::new()),
+        HeaderMap::new(),
+    )
+    .await
+    {
+        Ok(mut response) => {
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            Ok(response)
+        }
+        Err(_) => result,
+    }
+}
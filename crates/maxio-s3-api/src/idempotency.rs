@@ -0,0 +1,148 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use maxio_common::{error::MaxioError, hash::md5_digest, types::ObjectInfo};
+use maxio_storage::traits::ObjectLayer;
+
+/// How long a versioned PUT is remembered when
+/// `MAXIO_API_PUT_DEDUPE_WINDOW_SECS` is unset.
+pub const DEFAULT_PUT_DEDUPE_WINDOW: Duration = Duration::from_secs(10);
+const MAX_RECENT_PUTS: usize = 4096;
+
+/// Everything that makes two PUTs identical: target, content and the
+/// metadata stored with it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PutFingerprint {
+    bucket: String,
+    key: String,
+    content_md5: [u8; 16],
+    content_type: Option<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl PutFingerprint {
+    pub fn new(
+        bucket: &str,
+        key: &str,
+        body: &[u8],
+        content_type: Option<&str>,
+        metadata: &HashMap<String, String>,
+    ) -> Self {
+        Self {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            content_md5: md5_digest(body),
+            content_type: content_type.map(str::to_string),
+            metadata: metadata
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Versions created by recent PUTs, so a client retrying a PUT whose
+/// response it never saw gets the version it already created instead of a
+/// duplicate one.
+#[derive(Debug, Clone)]
+pub struct RecentPuts {
+    window: Duration,
+    entries: Arc<Mutex<HashMap<PutFingerprint, (Instant, ObjectInfo)>>>,
+}
+
+impl RecentPuts {
+    /// A zero `window` turns deduplication off.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Reads `MAXIO_API_PUT_DEDUPE_WINDOW_SECS`, defaulting to
+    /// [`DEFAULT_PUT_DEDUPE_WINDOW`].
+    pub fn from_env() -> Self {
+        let window = std::env::var("MAXIO_API_PUT_DEDUPE_WINDOW_SECS")
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map_or(DEFAULT_PUT_DEDUPE_WINDOW, Duration::from_secs);
+        Self::new(window)
+    }
+
+    /// The version an identical PUT created within the window, provided it is
+    /// still the latest version of the key.
+    pub async fn replay(
+        &self,
+        store: &dyn ObjectLayer,
+        fingerprint: &PutFingerprint,
+    ) -> Result<Option<ObjectInfo>, MaxioError> {
+        let Some(previous) = self.lookup(fingerprint) else {
+            return Ok(None);
+        };
+        let latest = match store
+            .get_object_info(&fingerprint.bucket, &fingerprint.key, None)
+            .await
+        {
+            Ok(latest) => latest,
+            Err(MaxioError::ObjectNotFound { .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(
+            (latest.version_id == previous.version_id && latest.etag == previous.etag)
+                .then_some(previous),
+        )
+    }
+
+    /// Remembers a PUT that created a new version. Unversioned PUTs are not
+    /// recorded: repeating them replaces the object without adding anything.
+    pub fn record(&self, fingerprint: PutFingerprint, info: &ObjectInfo) {
+        if self.window.is_zero()
+            || info
+                .version_id
+                .as_deref()
+                .is_none_or(|version_id| version_id == "null")
+        {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if entries.len() >= MAX_RECENT_PUTS {
+            entries.retain(|_, (at, _)| now.duration_since(*at) < self.window);
+        }
+        if entries.len() >= MAX_RECENT_PUTS {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (at, _))| *at)
+                .map(|(fingerprint, _)| fingerprint.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(fingerprint, (now, info.clone()));
+    }
+
+    fn lookup(&self, fingerprint: &PutFingerprint) -> Option<ObjectInfo> {
+        if self.window.is_zero() {
+            return None;
+        }
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match entries.get(fingerprint) {
+            Some((at, info)) if at.elapsed() < self.window => Some(info.clone()),
+            Some(_) => {
+                entries.remove(fingerprint);
+                None
+            }
+            None => None,
+        }
+    }
+}
@@ -1,3 +1,11 @@
+pub mod concurrency;
+pub mod content_type;
 pub mod error;
 pub mod handlers;
+pub mod idempotency;
+pub mod limits;
+pub mod region;
+pub mod request_id;
 pub mod router;
+pub mod timeouts;
+pub mod website;
@@ -0,0 +1,116 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use axum::body::Body;
+use futures::StreamExt;
+use maxio_common::error::MaxioError;
+
+/// Largest object S3 accepts in a single PUT.
+pub const DEFAULT_MAX_PUT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Largest object S3 stores at all, however it was uploaded.
+pub const DEFAULT_MAX_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024 * 1024;
+
+/// Upload size caps. Objects above `max_put_size` have to be uploaded with
+/// multipart; nothing may grow past `max_object_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectSizeLimits {
+    pub max_put_size: u64,
+    pub max_object_size: u64,
+}
+
+impl Default for ObjectSizeLimits {
+    fn default() -> Self {
+        Self {
+            max_put_size: DEFAULT_MAX_PUT_SIZE,
+            max_object_size: DEFAULT_MAX_OBJECT_SIZE,
+        }
+    }
+}
+
+impl ObjectSizeLimits {
+    /// Reads `MAXIO_API_MAX_PUT_SIZE` and `MAXIO_API_MAX_OBJECT_SIZE`, both in
+    /// bytes; unset or unparsable values keep the S3 defaults.
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_put_size: read("MAXIO_API_MAX_PUT_SIZE", DEFAULT_MAX_PUT_SIZE),
+            max_object_size: read("MAXIO_API_MAX_OBJECT_SIZE", DEFAULT_MAX_OBJECT_SIZE),
+        }
+    }
+
+    /// Largest object a single PUT may store.
+    pub fn max_put(&self) -> u64 {
+        self.max_put_size.min(self.max_object_size)
+    }
+
+    /// Rejects a single PUT of `size` bytes.
+    pub fn check_put(&self, size: u64) -> Result<(), MaxioError> {
+        check(size, self.max_put())
+    }
+
+    /// Rejects an object, such as a completed multipart upload, of `size`
+    /// bytes.
+    pub fn check_object(&self, size: u64) -> Result<(), MaxioError> {
+        check(size, self.max_object_size)
+    }
+}
+
+fn check(size: u64, max_size: u64) -> Result<(), MaxioError> {
+    if size > max_size {
+        return Err(MaxioError::EntityTooLarge { size, max_size });
+    }
+    Ok(())
+}
+
+/// Ends `body` with an error once more than `max_size` bytes arrived,
+/// storing the count reached in `received` so the caller can answer
+/// `EntityTooLarge`. Bodies sent without a `Content-Length`, such as chunked
+/// uploads, are only sized this way.
+pub(crate) fn with_size_cap(body: Body, max_size: u64, received: Arc<AtomicU64>) -> Body {
+    let mut total = 0_u64;
+    let chunks = body.into_data_stream().map(move |chunk| {
+        let chunk = chunk?;
+        total = total.saturating_add(chunk.len() as u64);
+        if total > max_size {
+            received.store(total, Ordering::Relaxed);
+            return Err(axum::Error::new("request body is over the size limit"));
+        }
+        Ok(chunk)
+    });
+    Body::from_stream(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_puts_are_capped_by_both_limits() {
+        let limits = ObjectSizeLimits {
+            max_put_size: 100,
+            max_object_size: 50,
+        };
+        assert!(limits.check_put(50).is_ok());
+        assert!(matches!(
+            limits.check_put(51),
+            Err(MaxioError::EntityTooLarge {
+                size: 51,
+                max_size: 50
+            })
+        ));
+        assert!(limits.check_object(50).is_ok());
+        assert!(limits.check_object(51).is_err());
+
+        let defaults = ObjectSizeLimits::default();
+        assert!(defaults.check_put(DEFAULT_MAX_PUT_SIZE).is_ok());
+        assert!(defaults.check_put(DEFAULT_MAX_PUT_SIZE + 1).is_err());
+        assert!(defaults.check_object(DEFAULT_MAX_PUT_SIZE + 1).is_ok());
+    }
+}
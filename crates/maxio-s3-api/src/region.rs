@@ -0,0 +1,78 @@
+use maxio_common::error::MaxioError;
+
+/// Region served when `MAXIO_REGION` is unset. S3 also reads an empty
+/// location constraint as this region.
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+/// The region this server stands for: buckets are created in it and report
+/// it as their location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerRegion {
+    name: String,
+}
+
+impl Default for ServerRegion {
+    fn default() -> Self {
+        Self::new(DEFAULT_REGION)
+    }
+}
+
+impl ServerRegion {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+
+    /// Reads `MAXIO_REGION`; unset or blank values keep [`DEFAULT_REGION`].
+    pub fn from_env() -> Self {
+        std::env::var("MAXIO_REGION")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map_or_else(Self::default, Self::new)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `LocationConstraint` S3 reports for buckets in this region, which
+    /// is empty for `us-east-1`.
+    pub fn location_constraint(&self) -> &str {
+        if self.name == DEFAULT_REGION {
+            ""
+        } else {
+            &self.name
+        }
+    }
+
+    /// Rejects a bucket location constraint naming another region. An empty
+    /// constraint leaves the choice to the server and is always accepted.
+    pub fn check_location_constraint(&self, constraint: &str) -> Result<(), MaxioError> {
+        let constraint = constraint.trim();
+        if constraint.is_empty() || constraint == self.name {
+            return Ok(());
+        }
+        Err(MaxioError::InvalidLocationConstraint(format!(
+            "{constraint} does not match the server region {}",
+            self.name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_server_region_is_a_valid_constraint() {
+        let region = ServerRegion::new("eu-west-1");
+        assert!(region.check_location_constraint("eu-west-1").is_ok());
+        assert!(region.check_location_constraint("").is_ok());
+        assert!(matches!(
+            region.check_location_constraint("us-east-1"),
+            Err(MaxioError::InvalidLocationConstraint(_))
+        ));
+        assert_eq!(region.location_constraint(), "eu-west-1");
+        assert_eq!(ServerRegion::default().location_constraint(), "");
+    }
+}
@@ -1,34 +1,372 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
 
 use axum::{
     Extension, Router,
-    extract::{DefaultBodyLimit, Path, Query, State},
-    response::Response,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{
+        HeaderValue, StatusCode,
+        header::{CONTENT_LENGTH, EXPECT},
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
 };
-use maxio_auth::{credentials::CredentialProvider, middleware::AuthLayer};
+use maxio_auth::{
+    credentials::CredentialProvider,
+    middleware::{AuthLayer, Caller, SigningRegions},
+};
 use maxio_common::error::MaxioError;
-use maxio_distributed::DistributedSys;
+use maxio_distributed::{DistributedSys, ReplicationPool, ReplicationState};
 use maxio_iam::IAMSys;
 use maxio_lifecycle::LifecycleSys;
 use maxio_notification::NotificationSys;
-use maxio_storage::traits::ObjectLayer;
+use maxio_storage::{access_tracker::AccessTracker, traits::ObjectLayer};
+use percent_encoding::percent_decode_str;
+use tracing::Instrument;
 
-use crate::handlers;
+use crate::{
+    concurrency::RequestLimiter,
+    content_type::ContentTypeSniffing,
+    handlers,
+    idempotency::RecentPuts,
+    limits::{ObjectSizeLimits, with_size_cap},
+    region::ServerRegion,
+    request_id::RequestId,
+    timeouts::{RequestTimeouts, with_idle_timeout},
+    website::WebsiteStore,
+};
 
 use crate::error::S3Error;
 
 const MAX_BODY_SIZE: usize = 5 * 1024 * 1024 * 1024; // 5GB
+const MAX_CONFIG_BODY_SIZE: usize = 1024 * 1024; // 1MiB
+
+/// `100-continue` is the only defined expectation. hyper sends the interim
+/// `100 Continue` when a handler first reads the body, which only happens
+/// after `AuthLayer` accepted the request, so rejected uploads are answered
+/// without the client streaming the body.
+async fn check_expectation(request: Request, next: Next) -> Response {
+    let unsupported = request
+        .headers()
+        .get(EXPECT)
+        .is_some_and(|value| !value.as_bytes().eq_ignore_ascii_case(b"100-continue"));
+    if unsupported {
+        return StatusCode::EXPECTATION_FAILED.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Answers `408` when the client stalls while sending the body and `504`
+/// when the request as a whole misses its deadline, so neither a slow client
+/// nor a stuck handler holds the connection indefinitely.
+async fn enforce_timeouts(
+    Extension(timeouts): Extension<RequestTimeouts>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let stalled = Arc::new(AtomicBool::new(false));
+    let request = request.map(|body| with_idle_timeout(body, timeouts.idle, Arc::clone(&stalled)));
+
+    let response = if timeouts.request.is_zero() {
+        next.run(request).await
+    } else {
+        match tokio::time::timeout(timeouts.request, next.run(request)).await {
+            Ok(response) => response,
+            Err(_) => {
+                return S3Error::from(MaxioError::OperationTimedOut(timeouts.request))
+                    .into_response();
+            }
+        }
+    };
+    if stalled.load(Ordering::Relaxed) {
+        return S3Error::from(MaxioError::RequestTimeout(timeouts.idle)).into_response();
+    }
+    response
+}
+
+/// Answers `503 SlowDown` with a `Retry-After` once the server is serving
+/// as many requests as it allows. Health probes and admin calls are always
+/// served, so an overloaded node can still be observed and managed. The slot
+/// is held until the handler returns a response.
+async fn shed_load(
+    Extension(limiter): Extension<RequestLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.uri().path().starts_with("/minio/") {
+        return next.run(request).await;
+    }
+    let Some(_permit) = limiter.try_admit() else {
+        let max_requests = limiter.metrics().max_requests as usize;
+        return S3Error::from(MaxioError::ServerBusy(max_requests)).into_response();
+    };
+    next.run(request).await
+}
+
+/// Rejects an object PUT whose declared length is over the single-PUT limit
+/// before any of the body is read. A PUT without a `Content-Length`, such as
+/// a chunked upload, is read until it passes the limit instead. Part uploads
+/// are bounded by the body limit alone, since they exist to get past this
+/// one.
+async fn check_put_size(
+    Extension(limits): Extension<ObjectSizeLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_part = request
+        .uri()
+        .query()
+        .is_some_and(|query| query.split('&').any(|pair| pair.starts_with("uploadId=")));
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if is_part {
+        return next.run(request).await;
+    }
+    if let Some(size) = declared {
+        if let Err(err) = limits.check_put(size) {
+            return S3Error::from(err).into_response();
+        }
+        return next.run(request).await;
+    }
+
+    let received = Arc::new(AtomicU64::new(0));
+    let request = request.map(|body| with_size_cap(body, limits.max_put(), Arc::clone(&received)));
+    let response = next.run(request).await;
+    match limits.check_put(received.load(Ordering::Relaxed)) {
+        Ok(()) => response,
+        Err(err) => S3Error::from(err).into_response(),
+    }
+}
+
+/// Longest object key recorded on a request span; longer keys are cut so a
+/// single request cannot blow up every log line it causes.
+const MAX_SPAN_KEY_LEN: usize = 128;
+
+/// Operations named by a subresource, as `(method, subresource, operation)`.
+/// Bucket and object requests without one fall back to their plain
+/// operation.
+const BUCKET_SUBRESOURCE_OPERATIONS: &[(&str, &str, &str)] = &[
+    ("GET", "versions", "ListObjectVersions"),
+    ("GET", "uploads", "ListMultipartUploads"),
+    ("GET", "location", "GetBucketLocation"),
+    ("GET", "versioning", "GetBucketVersioning"),
+    ("PUT", "versioning", "PutBucketVersioning"),
+    ("GET", "lifecycle", "GetBucketLifecycleConfiguration"),
+    ("PUT", "lifecycle", "PutBucketLifecycleConfiguration"),
+    ("DELETE", "lifecycle", "DeleteBucketLifecycle"),
+    ("GET", "notification", "GetBucketNotificationConfiguration"),
+    ("PUT", "notification", "PutBucketNotificationConfiguration"),
+    ("GET", "replication", "GetBucketReplication"),
+    ("PUT", "replication", "PutBucketReplication"),
+    ("DELETE", "replication", "DeleteBucketReplication"),
+    ("GET", "website", "GetBucketWebsite"),
+    ("PUT", "website", "PutBucketWebsite"),
+    ("DELETE", "website", "DeleteBucketWebsite"),
+];
+const OBJECT_SUBRESOURCE_OPERATIONS: &[(&str, &str, &str)] = &[
+    ("GET", "uploadId", "ListParts"),
+    ("PUT", "uploadId", "UploadPart"),
+    ("POST", "uploads", "CreateMultipartUpload"),
+    ("POST", "uploadId", "CompleteMultipartUpload"),
+    ("DELETE", "uploadId", "AbortMultipartUpload"),
+    ("GET", "tagging", "GetObjectTagging"),
+    ("PUT", "tagging", "PutObjectTagging"),
+    ("DELETE", "tagging", "DeleteObjectTagging"),
+];
+
+/// Runs every request inside an `s3_request` span carrying its operation,
+/// bucket, key and request id, so anything logged while serving it, down to
+/// the storage layer, can be tied back to the request. The access key is
+/// added by [`record_caller`] once the request is authenticated, and the
+/// request id is returned in `x-amz-request-id`.
+async fn trace_request(mut request: Request, next: Next) -> Response {
+    let request_id = RequestId::new();
+    request.extensions_mut().insert(request_id.clone());
+    let path = request.uri().path();
+    let (bucket, key) = if path.starts_with("/minio/") {
+        (None, None)
+    } else {
+        let mut segments = path.trim_start_matches('/').splitn(2, '/');
+        (
+            segments.next().filter(|bucket| !bucket.is_empty()),
+            segments.next().filter(|key| !key.is_empty()),
+        )
+    };
+    let span = tracing::info_span!(
+        "s3_request",
+        operation = operation_name(&request, bucket, key),
+        bucket = tracing::field::Empty,
+        key = tracing::field::Empty,
+        access_key = tracing::field::Empty,
+        request_id = %request_id,
+    );
+    if let Some(bucket) = bucket {
+        span.record("bucket", bucket);
+    }
+    if let Some(key) = key {
+        let key = percent_decode_str(key).decode_utf8_lossy();
+        match key.char_indices().nth(MAX_SPAN_KEY_LEN) {
+            Some((end, _)) => span.record("key", format!("{}...", &key[..end])),
+            None => span.record("key", key.as_ref()),
+        };
+    }
+
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+        response.headers_mut().insert("x-amz-request-id", value);
+    }
+    response
+}
+
+/// Adds the authenticated access key to the request span opened by
+/// [`trace_request`]. Sits inside `AuthLayer`, which provides the [`Caller`].
+async fn record_caller(request: Request, next: Next) -> Response {
+    if let Some(caller) = request.extensions().get::<Caller>() {
+        tracing::Span::current().record("access_key", caller.access_key());
+    }
+    next.run(request).await
+}
+
+/// The S3 operation a request maps to, such as `GetObject` or
+/// `ListObjectsV2`.
+fn operation_name(request: &Request, bucket: Option<&str>, key: Option<&str>) -> &'static str {
+    let path = request.uri().path();
+    if path.starts_with("/minio/health/") {
+        return "HealthCheck";
+    }
+    if path.starts_with("/minio/") {
+        return "Admin";
+    }
+
+    let method = request.method().as_str();
+    let query = request.uri().query().unwrap_or_default();
+    let has = |name: &str| {
+        query
+            .split('&')
+            .any(|pair| pair.split('=').next() == Some(name))
+    };
+    let subresources = if key.is_some() {
+        OBJECT_SUBRESOURCE_OPERATIONS
+    } else {
+        BUCKET_SUBRESOURCE_OPERATIONS
+    };
+    if let Some((_, _, operation)) = subresources
+        .iter()
+        .find(|(op_method, subresource, _)| *op_method == method && has(subresource))
+    {
+        return operation;
+    }
+
+    match (bucket, key, method) {
+        (None, _, "GET") => "ListBuckets",
+        (Some(_), None, "GET") if query.split('&').any(|pair| pair == "list-type=2") => {
+            "ListObjectsV2"
+        }
+        (Some(_), None, "GET") => "ListObjects",
+        (Some(_), None, "HEAD") => "HeadBucket",
+        (Some(_), None, "PUT") => "CreateBucket",
+        (Some(_), None, "DELETE") => "DeleteBucket",
+        (Some(_), Some(_), "GET") => "GetObject",
+        (Some(_), Some(_), "HEAD") => "HeadObject",
+        (Some(_), Some(_), "PUT")
+            if request
+                .headers()
+                .contains_key(handlers::object::COPY_SOURCE_HEADER) =>
+        {
+            "CopyObject"
+        }
+        (Some(_), Some(_), "PUT") => "PutObject",
+        (Some(_), Some(_), "DELETE") => "DeleteObject",
+        _ => "Unknown",
+    }
+}
+
+/// Bucket subresources S3 defines but this server does not implement. A
+/// request naming one is answered with `501 NotImplemented` rather than
+/// falling through to listing, bucket creation or bucket deletion.
+/// `accelerate` and `requestPayment` are only answered for GET, with their
+/// default documents, because SDKs probe them while bootstrapping.
+const UNIMPLEMENTED_BUCKET_SUBRESOURCES: &[&str] = &[
+    "accelerate",
+    "acl",
+    "analytics",
+    "cors",
+    "encryption",
+    "intelligent-tiering",
+    "inventory",
+    "logging",
+    "metrics",
+    "object-lock",
+    "ownershipControls",
+    "policy",
+    "policyStatus",
+    "publicAccessBlock",
+    "requestPayment",
+    "tagging",
+];
+
+/// Object subresources S3 defines but this server does not implement.
+const UNIMPLEMENTED_OBJECT_SUBRESOURCES: &[&str] = &[
+    "acl",
+    "attributes",
+    "legal-hold",
+    "restore",
+    "retention",
+    "select",
+    "torrent",
+];
+
+fn reject_unimplemented(
+    method: &str,
+    query: &HashMap<String, String>,
+    subresources: &[&str],
+) -> Result<(), S3Error> {
+    match subresources
+        .iter()
+        .find(|subresource| query.contains_key(**subresource))
+    {
+        Some(subresource) => Err(S3Error::from(MaxioError::NotImplemented(format!(
+            "{method} ?{subresource} is not implemented"
+        )))),
+        None => Ok(()),
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 async fn get_bucket_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
     Extension(lifecycle): Extension<Arc<LifecycleSys>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    Extension(region): Extension<ServerRegion>,
     Path(bucket): Path<String>,
     Query(query): Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response, S3Error> {
+    if let Some(config) =
+        handlers::website::website_for_request(&website, &bucket, &headers, &query).await?
+    {
+        return handlers::website::serve_website_object(store, bucket, "", &config, headers).await;
+    }
+    if query.contains_key("accelerate") {
+        return handlers::bucket::get_bucket_accelerate_configuration(State(store), Path(bucket))
+            .await;
+    } else if query.contains_key("requestPayment") {
+        return handlers::bucket::get_bucket_request_payment(State(store), Path(bucket)).await;
+    }
+    reject_unimplemented("GET", &query, UNIMPLEMENTED_BUCKET_SUBRESOURCES)?;
     if query.contains_key("location") {
-        handlers::bucket::get_bucket_location(State(store), Path(bucket)).await
+        handlers::bucket::get_bucket_location(State(store), Extension(region), Path(bucket)).await
     } else if query.contains_key("versioning") {
         handlers::versioning::get_bucket_versioning(State(store), Path(bucket)).await
     } else if query.contains_key("versions") {
@@ -51,6 +389,8 @@ async fn get_bucket_dispatch(
         .await
     } else if query.contains_key("replication") {
         handlers::replication::get_bucket_replication(State(store), Path(bucket)).await
+    } else if query.contains_key("website") {
+        handlers::website::get_bucket_website(State(store), Extension(website), Path(bucket)).await
     } else if query.get("list-type").is_some_and(|v| v == "2") {
         handlers::object::list_objects_v2(State(store), Path(bucket), Query(query)).await
     } else {
@@ -58,14 +398,19 @@ async fn get_bucket_dispatch(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn put_bucket_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
     Extension(lifecycle): Extension<Arc<LifecycleSys>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    Extension(region): Extension<ServerRegion>,
     Path(bucket): Path<String>,
     Query(query): Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
     body: axum::body::Bytes,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("PUT", &query, UNIMPLEMENTED_BUCKET_SUBRESOURCES)?;
     if query.contains_key("versioning") {
         handlers::versioning::put_bucket_versioning(State(store), Path(bucket), body).await
     } else if query.contains_key("notification") {
@@ -76,27 +421,40 @@ async fn put_bucket_dispatch(
             body,
         )
         .await
+    } else if query.contains_key("lifecycle") && query.contains_key("validate") {
+        handlers::lifecycle::validate_bucket_lifecycle_configuration(
+            State(store),
+            Path(bucket),
+            body,
+        )
+        .await
     } else if query.contains_key("lifecycle") {
         handlers::lifecycle::put_bucket_lifecycle_configuration(
             State(store),
             Extension(lifecycle),
             Path(bucket),
+            headers,
             body,
         )
         .await
     } else if query.contains_key("replication") {
         handlers::replication::put_bucket_replication(State(store), Path(bucket), body).await
+    } else if query.contains_key("website") {
+        handlers::website::put_bucket_website(State(store), Extension(website), Path(bucket), body)
+            .await
     } else {
-        handlers::bucket::make_bucket(State(store), Path(bucket)).await
+        handlers::bucket::make_bucket(State(store), Extension(region), Path(bucket), body).await
     }
 }
 
 async fn delete_bucket_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(lifecycle): Extension<Arc<LifecycleSys>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
     Path(bucket): Path<String>,
     Query(query): Query<HashMap<String, String>>,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("DELETE", &query, UNIMPLEMENTED_BUCKET_SUBRESOURCES)?;
     if query.contains_key("lifecycle") {
         handlers::lifecycle::delete_bucket_lifecycle_configuration(
             State(store),
@@ -106,28 +464,53 @@ async fn delete_bucket_dispatch(
         .await
     } else if query.contains_key("replication") {
         handlers::replication::delete_bucket_replication(State(store), Path(bucket)).await
+    } else if query.contains_key("website") {
+        handlers::website::delete_bucket_website(State(store), Extension(website), Path(bucket))
+            .await
     } else {
         handlers::bucket::delete_bucket(State(store), Path(bucket)).await
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn put_object_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(sniffing): Extension<ContentTypeSniffing>,
+    Extension(recent_puts): Extension<RecentPuts>,
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    caller: Option<Extension<Caller>>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
     headers: axum::http::HeaderMap,
     body: axum::body::Bytes,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("PUT", &query, UNIMPLEMENTED_OBJECT_SUBRESOURCES)?;
     if query.contains_key("tagging") {
         handlers::tagging::put_object_tagging(State(store), Path((bucket, key)), body).await
     } else if query.contains_key("uploadId") && query.contains_key("partNumber") {
         handlers::multipart::upload_part(State(store), Path((bucket, key)), Query(query), body)
             .await
+    } else if headers.contains_key(handlers::object::COPY_SOURCE_HEADER) {
+        handlers::object::copy_object(
+            State(store),
+            Extension(notifications),
+            caller,
+            request_id,
+            Path((bucket, key)),
+            headers,
+        )
+        .await
     } else {
         handlers::object::put_object(
             State(store),
             Extension(notifications),
+            Extension(sniffing),
+            Extension(recent_puts),
+            Extension(distributed),
+            caller,
+            request_id,
             Path((bucket, key)),
             headers,
             body,
@@ -136,21 +519,33 @@ async fn put_object_dispatch(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn post_object_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(limits): Extension<ObjectSizeLimits>,
+    caller: Option<Extension<Caller>>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
     headers: axum::http::HeaderMap,
     body: axum::body::Bytes,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("POST", &query, UNIMPLEMENTED_OBJECT_SUBRESOURCES)?;
     if query.contains_key("uploads") {
-        handlers::multipart::create_multipart_upload(State(store), Path((bucket, key)), headers)
-            .await
+        handlers::multipart::create_multipart_upload(
+            State(store),
+            caller,
+            Path((bucket, key)),
+            headers,
+        )
+        .await
     } else if query.contains_key("uploadId") {
         handlers::multipart::complete_multipart_upload(
             State(store),
             Extension(notifications),
+            Extension(limits),
+            request_id,
             Path((bucket, key)),
             Query(query),
             headers,
@@ -164,27 +559,54 @@ async fn post_object_dispatch(
     }
 }
 
+/// Reads of plain objects are reported to the [`AccessTracker`], when the
+/// server runs one, for access-time lifecycle rules.
 async fn get_object_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
+    Extension(website): Extension<Arc<WebsiteStore>>,
+    access: Option<Extension<Arc<AccessTracker>>>,
+    replication: Option<Extension<Arc<ReplicationState>>>,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
     headers: axum::http::HeaderMap,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("GET", &query, UNIMPLEMENTED_OBJECT_SUBRESOURCES)?;
     if query.contains_key("tagging") {
         handlers::tagging::get_object_tagging(State(store), Path((bucket, key))).await
     } else if query.contains_key("uploadId") {
         handlers::multipart::list_parts(State(store), Path((bucket, key)), Query(query)).await
+    } else if let Some(config) =
+        handlers::website::website_for_request(&website, &bucket, &headers, &query).await?
+    {
+        handlers::website::serve_website_object(store, bucket, &key, &config, headers).await
     } else {
-        handlers::object::get_object(State(store), Path((bucket, key)), Query(query), headers).await
+        let response = handlers::object::get_object(
+            State(store),
+            replication,
+            Path((bucket.clone(), key.clone())),
+            Query(query),
+            headers,
+        )
+        .await?;
+        if let Some(Extension(tracker)) = access {
+            tracker.record(&bucket, &key);
+        }
+        Ok(response)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn delete_object_dispatch(
     State(store): State<Arc<dyn ObjectLayer>>,
     Extension(notifications): Extension<Arc<NotificationSys>>,
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    replication: Option<Extension<Arc<ReplicationPool>>>,
+    request_id: RequestId,
     Path((bucket, key)): Path<(String, String)>,
     Query(query): Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
 ) -> Result<Response, S3Error> {
+    reject_unimplemented("DELETE", &query, UNIMPLEMENTED_OBJECT_SUBRESOURCES)?;
     if query.contains_key("tagging") {
         handlers::tagging::delete_object_tagging(State(store), Path((bucket, key))).await
     } else if query.contains_key("uploadId") {
@@ -194,8 +616,12 @@ async fn delete_object_dispatch(
         handlers::object::delete_object(
             State(store),
             Extension(notifications),
+            Extension(distributed),
+            replication,
+            request_id,
             Path((bucket, key)),
             Query(query),
+            headers,
         )
         .await
     }
@@ -208,8 +634,11 @@ pub fn s3_router(
     notifications: Arc<NotificationSys>,
     lifecycle: Arc<LifecycleSys>,
     distributed: Arc<DistributedSys>,
+    website: Arc<WebsiteStore>,
 ) -> Router {
-    let app: Router<Arc<dyn ObjectLayer>> = Router::<Arc<dyn ObjectLayer>>::new()
+    // Admin and bucket routes only take small XML/JSON configuration bodies,
+    // so they get a tight limit; object uploads keep the global one.
+    let config_routes: Router<Arc<dyn ObjectLayer>> = Router::<Arc<dyn ObjectLayer>>::new()
         .route("/minio/admin/v3/add-user", post(handlers::admin::add_user))
         .route(
             "/minio/admin/v3/remove-user",
@@ -223,16 +652,39 @@ pub fn s3_router(
             "/minio/admin/v3/add-canned-policy",
             post(handlers::admin::add_canned_policy),
         )
+        .route(
+            "/minio/admin/v3/info-canned-policy",
+            get(handlers::admin::info_canned_policy),
+        )
         .route(
             "/minio/admin/v3/set-user-or-group-policy",
             put(handlers::admin::set_user_or_group_policy),
         )
-        .route("/minio/health/live", get(handlers::health::health_live))
         .route(
-            "/minio/health/cluster",
-            get(handlers::health::health_cluster),
+            "/minio/admin/v3/config-kv",
+            get(handlers::admin::get_config_kv).put(handlers::admin::set_config_kv),
+        )
+        .route(
+            "/minio/admin/v3/rename-prefix",
+            post(handlers::admin::rename_prefix),
+        )
+        .route(
+            "/minio/admin/v3/list-objects",
+            get(handlers::admin::list_objects),
+        )
+        .route("/minio/admin/v3/top/locks", get(handlers::admin::top_locks))
+        .route(
+            "/minio/admin/v3/metrics/requests",
+            get(handlers::admin::request_metrics),
+        )
+        .route(
+            "/minio/admin/v3/force-unlock",
+            post(handlers::admin::force_unlock),
+        )
+        .route(
+            "/minio/admin/v3/verify",
+            get(handlers::admin::verify_prefix),
         )
-        .route("/", get(handlers::bucket::list_buckets))
         .route(
             "/{bucket}",
             put(put_bucket_dispatch)
@@ -240,20 +692,3596 @@ pub fn s3_router(
                 .delete(delete_bucket_dispatch)
                 .get(get_bucket_dispatch),
         )
+        // A trailing slash addresses the bucket too; website requests for
+        // the bucket root arrive this way.
+        .route("/{bucket}/", get(get_bucket_dispatch))
+        .layer(DefaultBodyLimit::max(MAX_CONFIG_BODY_SIZE));
+
+    let app: Router<Arc<dyn ObjectLayer>> = Router::<Arc<dyn ObjectLayer>>::new()
+        .merge(config_routes)
+        .route("/minio/health/live", get(handlers::health::health_live))
+        .route("/minio/health/ready", get(handlers::health::health_ready))
+        .route(
+            "/minio/health/cluster",
+            get(handlers::health::health_cluster),
+        )
+        .route("/", get(handlers::bucket::list_buckets))
         .route(
             "/{bucket}/{*key}",
             put(put_object_dispatch)
+                // Layered before the other methods so it only wraps PUT.
+                .layer(middleware::from_fn(check_put_size))
                 .post(post_object_dispatch)
                 .get(get_object_dispatch)
                 .head(handlers::object::head_object)
                 .delete(delete_object_dispatch),
         );
 
+    let region = ServerRegion::from_env();
+    let signing_regions = SigningRegions::from_env(region.name());
     app.layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
-        .layer(AuthLayer::new(credential_provider))
+        .layer(middleware::from_fn(record_caller))
+        .layer(AuthLayer::new(credential_provider).with_signing_regions(signing_regions))
         .layer(Extension(iam))
         .layer(Extension(notifications))
         .layer(Extension(lifecycle))
         .layer(Extension(distributed))
+        .layer(Extension(website))
+        .layer(Extension(ContentTypeSniffing::from_env()))
+        .layer(Extension(ObjectSizeLimits::from_env()))
+        .layer(Extension(region))
+        .layer(Extension(RecentPuts::from_env()))
+        .layer(middleware::from_fn(enforce_timeouts))
+        .layer(Extension(RequestTimeouts::from_env()))
+        .layer(middleware::from_fn(shed_load))
+        .layer(Extension(RequestLimiter::from_env()))
+        .layer(middleware::from_fn(check_expectation))
+        .layer(middleware::from_fn(trace_request))
         .with_state(object_layer)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use axum::body::Body;
+    use futures::StreamExt;
+    use http::Request;
+    use maxio_auth::credentials::StaticCredentialProvider;
+    use maxio_common::{
+        error::Result,
+        types::{BucketInfo, ObjectInfo},
+    };
+    use maxio_distributed::{ClusterConfig, StatusType};
+    use maxio_lifecycle::LifecycleStore;
+    use maxio_notification::{
+        NotificationStore, NotificationTarget,
+        types::{NotificationConfiguration, QueueConfiguration, S3Event},
+    };
+    use maxio_storage::{
+        single::SingleDiskObjectLayer,
+        storage_info::StorageInfo,
+        traits::{
+            CompletePart, DeleteCondition, DeletedObject, GetEncryptionOptions,
+            ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult, ListOrder,
+            ObjectPartInfo, PartInfo, PutEncryptionOptions, VersioningState,
+        },
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn test_router(root: &Path) -> Router {
+        test_router_with_credentials(root, Arc::new(StaticCredentialProvider::disabled())).await
+    }
+
+    async fn test_router_with_credentials(
+        root: &Path,
+        credential_provider: Arc<dyn CredentialProvider>,
+    ) -> Router {
+        let object_layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        test_router_with_layer(root, object_layer, credential_provider).await
+    }
+
+    async fn test_router_with_layer(
+        root: &Path,
+        object_layer: Arc<dyn ObjectLayer>,
+        credential_provider: Arc<dyn CredentialProvider>,
+    ) -> Router {
+        let distributed = Arc::new(
+            DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9000".to_string())).await,
+        );
+        test_router_with_distributed(root, object_layer, credential_provider, distributed).await
+    }
+
+    async fn test_router_with_distributed(
+        root: &Path,
+        object_layer: Arc<dyn ObjectLayer>,
+        credential_provider: Arc<dyn CredentialProvider>,
+        distributed: Arc<DistributedSys>,
+    ) -> Router {
+        let iam = Arc::new(IAMSys::new(root.join("iam")).await.expect("iam"));
+        let notifications = Arc::new(NotificationSys::new(NotificationStore::new(
+            root.to_path_buf(),
+        )));
+        let lifecycle = Arc::new(LifecycleSys::new(
+            LifecycleStore::new(root.to_path_buf()),
+            root.to_path_buf(),
+        ));
+
+        s3_router(
+            object_layer,
+            credential_provider,
+            iam,
+            notifications,
+            lifecycle,
+            distributed,
+            Arc::new(WebsiteStore::new(root.join("data"))),
+        )
+    }
+
+    async fn send_with_headers(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        router
+            .clone()
+            .oneshot(request.body(Body::from(body)).expect("request"))
+            .await
+            .expect("response")
+    }
+
+    async fn send(router: &Router, method: &str, uri: &str, body: Vec<u8>) -> StatusCode {
+        send_with_headers(router, method, uri, &[], body)
+            .await
+            .status()
+    }
+
+    fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+        for entry in std::fs::read_dir(dir).ok()?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if let Some(found) = find_file(&path, name) {
+                    return Some(found);
+                }
+            } else if path.file_name().is_some_and(|file| file == name) {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    #[tokio::test]
+    async fn config_bodies_are_limited_but_object_uploads_are_not() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let oversized = vec![b' '; MAX_CONFIG_BODY_SIZE + 1];
+        assert_eq!(
+            send(&router, "PUT", "/bucket?lifecycle", oversized).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let object = vec![7u8; 2 * MAX_CONFIG_BODY_SIZE];
+        assert_eq!(
+            send(&router, "PUT", "/bucket/large.bin", object).await,
+            StatusCode::OK
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn metadata_only_self_copy_keeps_object_data() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/bucket/photo.jpg", b"image-bytes".to_vec()).await,
+            StatusCode::OK
+        );
+
+        let data_file = find_file(&root.join("data").join("bucket"), "part.1").expect("data file");
+        let modified = std::fs::metadata(&data_file).unwrap().modified().unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let response = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/photo.jpg",
+            &[
+                ("x-amz-copy-source", "/bucket/photo.jpg"),
+                ("x-amz-metadata-directive", "REPLACE"),
+                ("content-type", "image/jpeg"),
+                ("x-amz-meta-camera", "x100"),
+            ],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let head = send_with_headers(&router, "HEAD", "/bucket/photo.jpg", &[], Vec::new()).await;
+        assert_eq!(head.headers()["content-type"], "image/jpeg");
+        assert_eq!(head.headers()["x-amz-meta-camera"], "x100");
+
+        assert_eq!(std::fs::read(&data_file).unwrap(), b"image-bytes");
+        assert_eq!(
+            std::fs::metadata(&data_file).unwrap().modified().unwrap(),
+            modified
+        );
+
+        let rejected = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/photo.jpg",
+            &[("x-amz-copy-source", "bucket/photo.jpg")],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    fn tag_values(body: &str, tag: &str) -> Vec<String> {
+        let open = format!("<{tag}>");
+        let close = format!("</{tag}>");
+        body.split(&open)
+            .skip(1)
+            .filter_map(|rest| rest.split_once(&close).map(|(value, _)| value.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn multipart_upload_listing_paginates() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for key in ["logs/1", "logs/2", "photo", "video", "video"] {
+            assert_eq!(
+                send(
+                    &router,
+                    "POST",
+                    &format!("/bucket/{key}?uploads"),
+                    Vec::new()
+                )
+                .await,
+                StatusCode::OK
+            );
+        }
+
+        let mut listed = Vec::new();
+        let mut markers = (String::new(), String::new());
+        for page in 0.. {
+            assert!(page < 5, "listing did not terminate");
+            let uri = format!(
+                "/bucket?uploads&max-uploads=2&key-marker={}&upload-id-marker={}",
+                markers.0, markers.1
+            );
+            let response = send_with_headers(&router, "GET", &uri, &[], Vec::new()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8_lossy(&body).to_string();
+            let keys = tag_values(&body, "Key");
+            assert!(keys.len() <= 2);
+            listed.extend(keys.into_iter().zip(tag_values(&body, "UploadId")));
+            if tag_values(&body, "IsTruncated") != ["true"] {
+                assert!(tag_values(&body, "NextKeyMarker").is_empty());
+                break;
+            }
+            markers = (
+                tag_values(&body, "NextKeyMarker").remove(0),
+                tag_values(&body, "NextUploadIdMarker").remove(0),
+            );
+        }
+        let keys = listed
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["logs/1", "logs/2", "photo", "video", "video"]);
+        assert_ne!(listed[3].1, listed[4].1);
+
+        let response = send_with_headers(
+            &router,
+            "GET",
+            "/bucket?uploads&delimiter=/&max-uploads=2",
+            &[],
+            Vec::new(),
+        )
+        .await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body).to_string();
+        assert!(body.contains("<CommonPrefixes><Prefix>logs/</Prefix></CommonPrefixes>"));
+        assert_eq!(tag_values(&body, "Key"), ["photo"]);
+        assert_eq!(tag_values(&body, "NextKeyMarker"), ["photo"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn storage_class_header_is_recorded() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let put = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/cached",
+            &[("x-amz-storage-class", "REDUCED_REDUNDANCY")],
+            b"scratch".to_vec(),
+        )
+        .await;
+        assert_eq!(put.status(), StatusCode::OK);
+        let rejected = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/cold",
+            &[("x-amz-storage-class", "DEEP_ARCHIVE")],
+            b"scratch".to_vec(),
+        )
+        .await;
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+        for (method, path) in [
+            ("PUT", "/bucket/spoofed"),
+            ("POST", "/bucket/spoofed?uploads"),
+        ] {
+            let spoofed = send_with_headers(
+                &router,
+                method,
+                path,
+                &[("x-amz-meta-x-amz-storage-class", "REDUCED_REDUNDANCY")],
+                Vec::new(),
+            )
+            .await;
+            assert_eq!(spoofed.status(), StatusCode::BAD_REQUEST, "{method} {path}");
+        }
+
+        let head = send_with_headers(&router, "HEAD", "/bucket/cached", &[], Vec::new()).await;
+        assert_eq!(head.headers()["x-amz-storage-class"], "REDUCED_REDUNDANCY");
+        assert!(
+            head.headers()
+                .keys()
+                .all(|name| !name.as_str().starts_with("x-amz-meta-"))
+        );
+
+        let list = send_with_headers(&router, "GET", "/bucket", &[], Vec::new()).await;
+        let body = axum::body::to_bytes(list.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(
+            String::from_utf8_lossy(&body)
+                .contains("<StorageClass>REDUCED_REDUNDANCY</StorageClass>")
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn list_v2_reports_owners_only_with_fetch_owner() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/bucket/owned", b"data".to_vec()).await,
+            StatusCode::OK
+        );
+
+        let list = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = send_with_headers(&router, "GET", uri, &[], Vec::new()).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+        let owner = "<Owner><ID>maxio</ID><DisplayName>maxio</DisplayName></Owner>";
+        assert!(!list("/bucket?list-type=2").await.contains("<Owner>"));
+        assert!(
+            !list("/bucket?list-type=2&fetch-owner=false")
+                .await
+                .contains("<Owner>")
+        );
+        assert!(
+            list("/bucket?list-type=2&fetch-owner=true")
+                .await
+                .contains(owner)
+        );
+        assert!(list("/bucket").await.contains(owner));
+
+        let head = send_with_headers(&router, "HEAD", "/bucket/owned", &[], Vec::new()).await;
+        assert!(
+            head.headers()
+                .keys()
+                .all(|name| !name.as_str().starts_with("x-amz-meta-"))
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn copy_source_conditions_gate_the_copy() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let put = send_with_headers(&router, "PUT", "/bucket/src", &[], b"source".to_vec()).await;
+        let etag = put.headers()["etag"].to_str().unwrap().to_string();
+        let past = "Wed, 21 Oct 2015 07:28:00 GMT";
+        let future = "Fri, 01 Jan 2100 00:00:00 GMT";
+
+        let cases: [(&[(&str, &str)], StatusCode); 10] = [
+            (&[("x-amz-copy-source-if-match", &etag)], StatusCode::OK),
+            (
+                &[("x-amz-copy-source-if-match", "\"0123\"")],
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (
+                &[("x-amz-copy-source-if-none-match", &etag)],
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (
+                &[("x-amz-copy-source-if-none-match", "\"0123\"")],
+                StatusCode::OK,
+            ),
+            (
+                &[("x-amz-copy-source-if-modified-since", past)],
+                StatusCode::OK,
+            ),
+            (
+                &[("x-amz-copy-source-if-modified-since", future)],
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (
+                &[("x-amz-copy-source-if-unmodified-since", future)],
+                StatusCode::OK,
+            ),
+            (
+                &[("x-amz-copy-source-if-unmodified-since", past)],
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (
+                &[
+                    ("x-amz-copy-source-if-match", &etag),
+                    ("x-amz-copy-source-if-unmodified-since", past),
+                ],
+                StatusCode::OK,
+            ),
+            (
+                &[
+                    ("x-amz-copy-source-if-none-match", &etag),
+                    ("x-amz-copy-source-if-modified-since", past),
+                ],
+                StatusCode::PRECONDITION_FAILED,
+            ),
+        ];
+        for (index, (conditions, expected)) in cases.into_iter().enumerate() {
+            let dest = format!("/bucket/dest-{index}");
+            let mut headers = vec![("x-amz-copy-source", "/bucket/src")];
+            headers.extend_from_slice(conditions);
+            let response = send_with_headers(&router, "PUT", &dest, &headers, Vec::new()).await;
+            assert_eq!(response.status(), expected, "case {index}: {conditions:?}");
+            let stored = send(&router, "HEAD", &dest, Vec::new()).await;
+            if expected == StatusCode::OK {
+                assert_eq!(stored, StatusCode::OK);
+            } else {
+                assert_eq!(stored, StatusCode::NOT_FOUND, "case {index} wrote the copy");
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                assert!(String::from_utf8_lossy(&body).contains("<Code>PreconditionFailed</Code>"));
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn copy_into_versioned_bucket_creates_new_version() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        for bucket in ["/source", "/target"] {
+            assert_eq!(
+                send(&router, "PUT", bucket, Vec::new()).await,
+                StatusCode::OK
+            );
+            assert_eq!(
+                send(
+                    &router,
+                    "PUT",
+                    &format!("{bucket}?versioning"),
+                    b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+                        .to_vec(),
+                )
+                .await,
+                StatusCode::OK
+            );
+        }
+
+        let put = send_with_headers(&router, "PUT", "/source/doc.txt", &[], b"v1".to_vec()).await;
+        assert_eq!(put.status(), StatusCode::OK);
+        let source_version = put.headers()["x-amz-version-id"].clone();
+
+        let copy = send_with_headers(
+            &router,
+            "PUT",
+            "/target/doc.txt",
+            &[("x-amz-copy-source", "/source/doc.txt")],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(copy.status(), StatusCode::OK);
+        assert_eq!(
+            copy.headers()["x-amz-copy-source-version-id"],
+            source_version
+        );
+        let copy_version = copy.headers()["x-amz-version-id"].clone();
+        assert_ne!(copy_version, source_version);
+
+        // Copying a specific source version into the same key adds a version.
+        let self_copy = send_with_headers(
+            &router,
+            "PUT",
+            "/source/doc.txt",
+            &[(
+                "x-amz-copy-source",
+                &format!(
+                    "/source/doc.txt?versionId={}",
+                    source_version.to_str().unwrap()
+                ),
+            )],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(self_copy.status(), StatusCode::OK);
+        assert_eq!(
+            self_copy.headers()["x-amz-copy-source-version-id"],
+            source_version
+        );
+        assert_ne!(self_copy.headers()["x-amz-version-id"], source_version);
+        assert_ne!(self_copy.headers()["x-amz-version-id"], copy_version);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn versioned_delete_reports_the_delete_marker_version() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(
+                &router,
+                "PUT",
+                "/bucket?versioning",
+                b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+                    .to_vec(),
+            )
+            .await,
+            StatusCode::OK
+        );
+
+        let put = send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], b"v1".to_vec()).await;
+        let object_version = put.headers()["x-amz-version-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let delete = send_with_headers(&router, "DELETE", "/bucket/doc.txt", &[], Vec::new()).await;
+        assert_eq!(delete.status(), StatusCode::NO_CONTENT);
+        assert_eq!(delete.headers()["x-amz-delete-marker"], "true");
+        let marker_version = delete.headers()["x-amz-version-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_ne!(marker_version, object_version);
+
+        // The marker hides the object, but its version can still be stat'ed.
+        let head = send_with_headers(
+            &router,
+            "HEAD",
+            &format!("/bucket/doc.txt?versionId={object_version}"),
+            &[],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(head.status(), StatusCode::OK);
+        assert_eq!(head.headers()["content-length"], "2");
+        assert_eq!(head.headers()["etag"], put.headers()["etag"]);
+
+        // Removing the marker by id restores the object and says it was a marker.
+        let remove_marker = send_with_headers(
+            &router,
+            "DELETE",
+            &format!("/bucket/doc.txt?versionId={marker_version}"),
+            &[],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(remove_marker.status(), StatusCode::NO_CONTENT);
+        assert_eq!(remove_marker.headers()["x-amz-delete-marker"], "true");
+        assert_eq!(
+            remove_marker.headers()["x-amz-version-id"],
+            marker_version.as_str()
+        );
+        assert_eq!(
+            send(&router, "GET", "/bucket/doc.txt", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let remove_object = send_with_headers(
+            &router,
+            "DELETE",
+            &format!("/bucket/doc.txt?versionId={object_version}"),
+            &[],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(remove_object.status(), StatusCode::NO_CONTENT);
+        assert!(remove_object.headers().get("x-amz-delete-marker").is_none());
+        assert_eq!(
+            remove_object.headers()["x-amz-version-id"],
+            object_version.as_str()
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn versions_report_their_own_replication_status() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let replication = Arc::new(ReplicationState::new());
+        let router = test_router(&root)
+            .await
+            .layer(Extension(Arc::clone(&replication)));
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(
+                &router,
+                "PUT",
+                "/bucket?versioning",
+                b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+                    .to_vec(),
+            )
+            .await,
+            StatusCode::OK
+        );
+
+        let mut versions = Vec::new();
+        for body in [b"v1", b"v2"] {
+            let put =
+                send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], body.to_vec()).await;
+            versions.push(
+                put.headers()["x-amz-version-id"]
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        for (version_id, status) in versions
+            .iter()
+            .zip([StatusType::Completed, StatusType::Failed])
+        {
+            replication
+                .set_target_status("bucket", "doc.txt", Some(version_id), "arn", status)
+                .await;
+        }
+
+        let status = |method: &'static str, uri: String| {
+            let router = router.clone();
+            async move {
+                let response = send_with_headers(&router, method, &uri, &[], Vec::new()).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                response
+                    .headers()
+                    .get("x-amz-replication-status")
+                    .map(|value| value.to_str().unwrap().to_string())
+            }
+        };
+        let first = format!("/bucket/doc.txt?versionId={}", versions[0]);
+        let second = format!("/bucket/doc.txt?versionId={}", versions[1]);
+        assert_eq!(
+            status("HEAD", first.clone()).await.as_deref(),
+            Some("COMPLETED")
+        );
+        assert_eq!(status("GET", first).await.as_deref(), Some("COMPLETED"));
+        assert_eq!(status("HEAD", second).await.as_deref(), Some("FAILED"));
+        assert_eq!(
+            status("GET", "/bucket/doc.txt".to_string())
+                .await
+                .as_deref(),
+            Some("FAILED")
+        );
+
+        send_with_headers(&router, "PUT", "/bucket/other.txt", &[], b"data".to_vec()).await;
+        assert_eq!(status("HEAD", "/bucket/other.txt".to_string()).await, None);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn bucket_replication_config_round_trips() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let config = b"<ReplicationConfiguration><Role>arn:role</Role><Rule><ID>all</ID>\
+            <Status>Enabled</Status><Destination><Bucket>arn:aws:s3:::dest</Bucket>\
+            </Destination></Rule></ReplicationConfiguration>";
+        assert_eq!(
+            send(&router, "PUT", "/bucket?replication", config.to_vec()).await,
+            StatusCode::OK
+        );
+
+        let response =
+            send_with_headers(&router, "GET", "/bucket?replication", &[], Vec::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert_eq!(tag_values(&body, "Bucket"), ["arn:aws:s3:::dest"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn delete_markers_replicate_with_their_own_status() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A destination that answers every request with `204` and reports
+        // each request line it receives.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (requests_tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let requests_tx = requests_tx.clone();
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    loop {
+                        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+                            match stream.read(&mut buf).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(n) => head.extend_from_slice(&buf[..n]),
+                            }
+                        }
+                        let end = head.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                        let line = String::from_utf8_lossy(&head[..end])
+                            .lines()
+                            .next()
+                            .unwrap_or_default()
+                            .to_string();
+                        head.drain(..end);
+                        let _ = requests_tx.send(line);
+                        let response = b"HTTP/1.1 204 No Content\r\n\r\n";
+                        if stream.write_all(response).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let pool = Arc::new(
+            ReplicationPool::new(maxio_distributed::ReplicationPoolConfig {
+                normal_workers: 1,
+                large_workers: 1,
+                mrf_workers: 0,
+                mrf_persistence_dir: root.join("mrf"),
+                state_persistence_dir: root.join("replication"),
+                targets: vec![maxio_distributed::ReplicationTarget {
+                    arn: "arn:maxio:replication::dest".to_string(),
+                    endpoint: format!("http://{addr}"),
+                    bucket: "dest".to_string(),
+                    region: String::new(),
+                    access_key: "access".to_string(),
+                    secret_key: "secret".to_string(),
+                    session_token: None,
+                }],
+                ..maxio_distributed::ReplicationPoolConfig::default()
+            })
+            .await
+            .unwrap(),
+        );
+        let router = test_router(&root)
+            .await
+            .layer(Extension(pool.state()))
+            .layer(Extension(Arc::clone(&pool)));
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for (query, body) in [
+            (
+                "versioning",
+                "<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>",
+            ),
+            (
+                "replication",
+                "<ReplicationConfiguration><Role>arn:role</Role><Rule><ID>docs</ID>\
+                 <Status>Enabled</Status><Filter><Prefix>docs/</Prefix></Filter>\
+                 <Destination><Bucket>arn:aws:s3:::dest</Bucket></Destination></Rule>\
+                 </ReplicationConfiguration>",
+            ),
+        ] {
+            assert_eq!(
+                send(
+                    &router,
+                    "PUT",
+                    &format!("/bucket?{query}"),
+                    body.as_bytes().to_vec()
+                )
+                .await,
+                StatusCode::OK
+            );
+        }
+
+        let mut markers = Vec::new();
+        for key in ["docs/a.txt", "other.txt"] {
+            send(&router, "PUT", &format!("/bucket/{key}"), b"data".to_vec()).await;
+            let deleted = send_with_headers(
+                &router,
+                "DELETE",
+                &format!("/bucket/{key}"),
+                &[],
+                Vec::new(),
+            )
+            .await;
+            assert_eq!(deleted.headers()["x-amz-delete-marker"], "true");
+            markers.push(
+                deleted.headers()["x-amz-version-id"]
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+
+        assert_eq!(
+            tokio::time::timeout(Duration::from_secs(10), requests.recv())
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("DELETE /dest/docs/a.txt HTTP/1.1")
+        );
+        let state = pool.state();
+        let status = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match state
+                    .get_overall_status("bucket", "docs/a.txt", Some(&markers[0]))
+                    .await
+                {
+                    Some(maxio_distributed::ReplicationStatus::Pending) | None => {
+                        tokio::time::sleep(Duration::from_millis(10)).await
+                    }
+                    status => return status,
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            status,
+            Some(maxio_distributed::ReplicationStatus::Completed)
+        );
+        // The marker's status is its own, not the hidden version's.
+        assert_eq!(
+            state.get_overall_status("bucket", "docs/a.txt", None).await,
+            None
+        );
+        assert_eq!(
+            state
+                .get_overall_status("bucket", "other.txt", Some(&markers[1]))
+                .await,
+            None
+        );
+        assert!(requests.try_recv().is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn retried_identical_puts_create_a_single_version() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(
+                &router,
+                "PUT",
+                "/bucket?versioning",
+                b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+                    .to_vec(),
+            )
+            .await,
+            StatusCode::OK
+        );
+
+        let first = send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], b"v1".to_vec()).await;
+        let retry = send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], b"v1".to_vec()).await;
+        assert_eq!(retry.status(), StatusCode::OK);
+        assert_eq!(
+            retry.headers()["x-amz-version-id"],
+            first.headers()["x-amz-version-id"]
+        );
+        assert_eq!(retry.headers()["etag"], first.headers()["etag"]);
+
+        let changed =
+            send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], b"v2".to_vec()).await;
+        assert_ne!(
+            changed.headers()["x-amz-version-id"],
+            first.headers()["x-amz-version-id"]
+        );
+
+        let versions = send_with_headers(&router, "GET", "/bucket?versions", &[], Vec::new()).await;
+        let body = axum::body::to_bytes(versions.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&body).matches("<Version>").count(),
+            2
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn version_listing_groups_prefixes_and_paginates() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(
+                &router,
+                "PUT",
+                "/bucket?versioning",
+                b"<VersioningConfiguration><Status>Enabled</Status></VersioningConfiguration>"
+                    .to_vec(),
+            )
+            .await,
+            StatusCode::OK
+        );
+        let mut readme_versions = Vec::new();
+        for (key, body) in [
+            ("logs/2024/a.log", "a"),
+            ("logs/2024/b.log", "b"),
+            ("logs/readme", "v1"),
+            ("logs/readme", "v2"),
+            ("photos/cat.png", "cat"),
+            ("top.txt", "top"),
+        ] {
+            let response = send_with_headers(
+                &router,
+                "PUT",
+                &format!("/bucket/{key}"),
+                &[],
+                body.as_bytes().to_vec(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            if key == "logs/readme" {
+                let version_id = response.headers()["x-amz-version-id"].to_str().unwrap();
+                readme_versions.insert(0, version_id.to_string());
+            }
+        }
+
+        async fn list(router: &Router, uri: &str) -> String {
+            let response = send_with_headers(router, "GET", uri, &[], Vec::new()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&body).into_owned()
+        }
+
+        let body = list(&router, "/bucket?versions&delimiter=/").await;
+        assert_eq!(tag_values(&body, "Key"), ["top.txt"]);
+        assert_eq!(
+            tag_values(&body, "CommonPrefixes"),
+            ["<Prefix>logs/</Prefix>", "<Prefix>photos/</Prefix>"]
+        );
+        assert_eq!(tag_values(&body, "IsTruncated"), ["false"]);
+
+        // The nested prefix and the newest readme version fill the first
+        // page; the older readme version is left for the next one.
+        let body = list(
+            &router,
+            "/bucket?versions&prefix=logs/&delimiter=/&max-keys=2",
+        )
+        .await;
+        assert_eq!(
+            tag_values(&body, "CommonPrefixes"),
+            ["<Prefix>logs/2024/</Prefix>"]
+        );
+        assert_eq!(tag_values(&body, "Key"), ["logs/readme"]);
+        assert_eq!(tag_values(&body, "VersionId"), [readme_versions[0].clone()]);
+        assert_eq!(tag_values(&body, "IsTruncated"), ["true"]);
+        assert_eq!(tag_values(&body, "NextKeyMarker"), ["logs/readme"]);
+        assert_eq!(
+            tag_values(&body, "NextVersionIdMarker"),
+            [readme_versions[0].clone()]
+        );
+
+        let next = format!(
+            "/bucket?versions&prefix=logs/&delimiter=/&max-keys=2&key-marker=logs/readme&version-id-marker={}",
+            readme_versions[0]
+        );
+        let body = list(&router, &next).await;
+        assert!(tag_values(&body, "CommonPrefixes").is_empty());
+        assert_eq!(tag_values(&body, "Key"), ["logs/readme"]);
+        assert_eq!(tag_values(&body, "VersionId"), [readme_versions[1].clone()]);
+        assert_eq!(tag_values(&body, "IsLatest"), ["false"]);
+        assert_eq!(tag_values(&body, "IsTruncated"), ["false"]);
+        assert!(tag_values(&body, "NextKeyMarker").is_empty());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn website_buckets_serve_index_and_error_documents() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/site", Vec::new()).await,
+            StatusCode::OK
+        );
+        for (key, body) in [
+            ("index.html", "home"),
+            ("docs/index.html", "docs home"),
+            ("error.html", "not here"),
+        ] {
+            assert_eq!(
+                send(&router, "PUT", &format!("/site/{key}"), body.into()).await,
+                StatusCode::OK
+            );
+        }
+        assert_eq!(
+            send(&router, "GET", "/site/missing.html", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let config = b"<WebsiteConfiguration>\
+            <IndexDocument><Suffix>index.html</Suffix></IndexDocument>\
+            <ErrorDocument><Key>error.html</Key></ErrorDocument>\
+            </WebsiteConfiguration>";
+        assert_eq!(
+            send(&router, "PUT", "/site?website", config.to_vec()).await,
+            StatusCode::OK
+        );
+        let stored = send_with_headers(&router, "GET", "/site?website", &[], Vec::new()).await;
+        assert_eq!(stored.status(), StatusCode::OK);
+        let stored = axum::body::to_bytes(stored.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(
+            String::from_utf8_lossy(&stored).contains("<Suffix>index.html</Suffix>"),
+            "{stored:?}"
+        );
+
+        for (uri, status, expected) in [
+            ("/site/", StatusCode::OK, "home"),
+            ("/site/docs/", StatusCode::OK, "docs home"),
+            ("/site/docs/index.html", StatusCode::OK, "docs home"),
+            ("/site/missing.html", StatusCode::NOT_FOUND, "not here"),
+        ] {
+            let response = send_with_headers(&router, "GET", uri, &[], Vec::new()).await;
+            assert_eq!(response.status(), status, "{uri}");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected.as_bytes(), "{uri}");
+        }
+
+        // API requests with a subresource still list the bucket.
+        assert_eq!(
+            send(&router, "GET", "/site?list-type=2", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        assert_eq!(
+            send(&router, "DELETE", "/site?website", Vec::new()).await,
+            StatusCode::NO_CONTENT
+        );
+        let missing =
+            send_with_headers(&router, "GET", "/site/missing.html", &[], Vec::new()).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(missing.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("NoSuchKey"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn content_type_sniffing_follows_admin_config() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let content_type = |key: &'static str| {
+            let router = router.clone();
+            async move {
+                let head = send_with_headers(&router, "HEAD", key, &[], Vec::new()).await;
+                head.headers()["content-type"].to_str().unwrap().to_string()
+            }
+        };
+
+        let page = b"<html><body>hi</body></html>".to_vec();
+        assert_eq!(
+            send(&router, "PUT", "/bucket/off.html", page.clone()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            content_type("/bucket/off.html").await,
+            "application/octet-stream"
+        );
+
+        let mut headers = signed_headers("PUT", "/minio/admin/v3/config-kv", "access", "secret");
+        headers.push(("content-type".to_string(), "application/json".to_string()));
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let enable = send_with_headers(
+            &router,
+            "PUT",
+            "/minio/admin/v3/config-kv",
+            &headers,
+            br#"{"key":"api:content_type_sniffing","value":"on"}"#.to_vec(),
+        )
+        .await;
+        assert_eq!(enable.status(), StatusCode::OK);
+
+        assert_eq!(
+            send(&router, "PUT", "/bucket/on.html", page.clone()).await,
+            StatusCode::OK
+        );
+        assert_eq!(content_type("/bucket/on.html").await, "text/html");
+
+        let explicit = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/explicit.html",
+            &[("content-type", "text/plain")],
+            page,
+        )
+        .await;
+        assert_eq!(explicit.status(), StatusCode::OK);
+        assert_eq!(content_type("/bucket/explicit.html").await, "text/plain");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn create_if_absent_succeeds_once_across_nodes() {
+        use maxio_distributed::NetLocker;
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let object_layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        let a =
+            DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9000".to_string())).await;
+        let b =
+            DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9001".to_string())).await;
+        let a_locker: Arc<dyn NetLocker> = a.locker();
+        let b_locker: Arc<dyn NetLocker> = b.locker();
+        let mut routers = Vec::new();
+        for (name, node) in [
+            ("a", a.with_peer_lockers(vec![b_locker])),
+            ("b", b.with_peer_lockers(vec![a_locker])),
+        ] {
+            routers.push(
+                test_router_with_distributed(
+                    &root.join(name),
+                    Arc::clone(&object_layer),
+                    Arc::new(StaticCredentialProvider::disabled()),
+                    Arc::new(node),
+                )
+                .await,
+            );
+        }
+        assert_eq!(
+            send(&routers[0], "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let attempts = (0..8_u64)
+            .map(|attempt| {
+                let router = routers[attempt as usize % 2].clone();
+                tokio::spawn(async move {
+                    let body = format!("attempt {attempt}").into_bytes();
+                    loop {
+                        let status = send_with_headers(
+                            &router,
+                            "PUT",
+                            "/bucket/key",
+                            &[("if-none-match", "*")],
+                            body.clone(),
+                        )
+                        .await
+                        .status();
+                        // Losing the lock race answers SlowDown; retry like
+                        // an S3 client would.
+                        if status != StatusCode::SERVICE_UNAVAILABLE {
+                            return status;
+                        }
+                        tokio::time::sleep(Duration::from_millis(1 + attempt)).await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut statuses = Vec::new();
+        for attempt in attempts {
+            statuses.push(attempt.await.unwrap());
+        }
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| **status == StatusCode::OK)
+                .count(),
+            1,
+            "{statuses:?}"
+        );
+        assert!(
+            statuses
+                .iter()
+                .all(|status| *status == StatusCode::OK
+                    || *status == StatusCode::PRECONDITION_FAILED)
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Delegates to a single-disk layer; while `hang_puts` is set, PUTs
+    /// signal `put_started` and then never finish.
+    struct HangingPutLayer {
+        inner: SingleDiskObjectLayer,
+        hang_puts: AtomicBool,
+        put_started: tokio::sync::Notify,
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectLayer for HangingPutLayer {
+        async fn make_bucket(&self, bucket: &str) -> Result<()> {
+            self.inner.make_bucket(bucket).await
+        }
+
+        async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+            self.inner.get_bucket_info(bucket).await
+        }
+
+        async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+            self.inner.list_buckets().await
+        }
+
+        async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+            self.inner.delete_bucket(bucket).await
+        }
+
+        async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+            self.inner.get_bucket_versioning(bucket).await
+        }
+
+        async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+            self.inner.set_bucket_versioning(bucket, state).await
+        }
+
+        async fn put_object(
+            &self,
+            bucket: &str,
+            key: &str,
+            data: bytes::Bytes,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+            encryption: Option<PutEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            if self.hang_puts.load(Ordering::SeqCst) {
+                self.put_started.notify_one();
+                std::future::pending::<()>().await;
+            }
+            self.inner
+                .put_object(bucket, key, data, content_type, metadata, encryption)
+                .await
+        }
+
+        async fn get_object(
+            &self,
+            bucket: &str,
+            key: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<(ObjectInfo, bytes::Bytes)> {
+            self.inner.get_object(bucket, key, encryption).await
+        }
+
+        async fn get_object_version(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<(ObjectInfo, bytes::Bytes)> {
+            self.inner
+                .get_object_version(bucket, key, version_id, encryption)
+                .await
+        }
+
+        async fn get_object_info(
+            &self,
+            bucket: &str,
+            key: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            self.inner.get_object_info(bucket, key, encryption).await
+        }
+
+        async fn get_object_version_info(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+            encryption: Option<GetEncryptionOptions>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .get_object_version_info(bucket, key, version_id, encryption)
+                .await
+        }
+
+        async fn update_object_metadata(
+            &self,
+            bucket: &str,
+            key: &str,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .update_object_metadata(bucket, key, content_type, metadata)
+                .await
+        }
+
+        async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+            self.inner.delete_object(bucket, key).await
+        }
+
+        async fn delete_object_if(
+            &self,
+            bucket: &str,
+            key: &str,
+            condition: &DeleteCondition,
+        ) -> Result<DeletedObject> {
+            self.inner.delete_object_if(bucket, key, condition).await
+        }
+
+        async fn delete_object_version(
+            &self,
+            bucket: &str,
+            key: &str,
+            version_id: &str,
+        ) -> Result<DeletedObject> {
+            self.inner
+                .delete_object_version(bucket, key, version_id)
+                .await
+        }
+
+        async fn list_objects(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            marker: &str,
+            delimiter: &str,
+            max_keys: i32,
+            order: ListOrder,
+        ) -> Result<ListObjectsResult> {
+            self.inner
+                .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
+                .await
+        }
+
+        async fn list_object_versions(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            key_marker: &str,
+            version_id_marker: &str,
+            delimiter: &str,
+            max_keys: i32,
+        ) -> Result<ListObjectVersionsResult> {
+            self.inner
+                .list_object_versions(
+                    bucket,
+                    prefix,
+                    key_marker,
+                    version_id_marker,
+                    delimiter,
+                    max_keys,
+                )
+                .await
+        }
+
+        async fn create_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            content_type: Option<&str>,
+            metadata: HashMap<String, String>,
+        ) -> Result<String> {
+            self.inner
+                .create_multipart_upload(bucket, key, content_type, metadata)
+                .await
+        }
+
+        async fn upload_part(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+            part_number: i32,
+            data: bytes::Bytes,
+        ) -> Result<String> {
+            self.inner
+                .upload_part(bucket, key, upload_id, part_number, data)
+                .await
+        }
+
+        async fn complete_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+            parts: Vec<CompletePart>,
+        ) -> Result<ObjectInfo> {
+            self.inner
+                .complete_multipart_upload(bucket, key, upload_id, parts)
+                .await
+        }
+
+        async fn abort_multipart_upload(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+        ) -> Result<()> {
+            self.inner
+                .abort_multipart_upload(bucket, key, upload_id)
+                .await
+        }
+
+        async fn list_parts(
+            &self,
+            bucket: &str,
+            key: &str,
+            upload_id: &str,
+        ) -> Result<Vec<PartInfo>> {
+            self.inner.list_parts(bucket, key, upload_id).await
+        }
+
+        async fn list_multipart_uploads(
+            &self,
+            bucket: &str,
+            prefix: &str,
+            key_marker: &str,
+            upload_id_marker: &str,
+            delimiter: &str,
+            max_uploads: i32,
+        ) -> Result<ListMultipartUploadsResult> {
+            self.inner
+                .list_multipart_uploads(
+                    bucket,
+                    prefix,
+                    key_marker,
+                    upload_id_marker,
+                    delimiter,
+                    max_uploads,
+                )
+                .await
+        }
+
+        async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+            self.inner.stat_object_parts(bucket, key).await
+        }
+
+        async fn storage_info(&self) -> StorageInfo {
+            self.inner.storage_info().await
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_conditional_put_releases_the_object_lock() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let layer = Arc::new(HangingPutLayer {
+            inner: SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+            hang_puts: AtomicBool::new(false),
+            put_started: tokio::sync::Notify::new(),
+        });
+        let distributed = Arc::new(
+            DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9000".to_string())).await,
+        );
+        let router = test_router_with_distributed(
+            &root,
+            Arc::clone(&layer) as Arc<dyn ObjectLayer>,
+            Arc::new(StaticCredentialProvider::disabled()),
+            Arc::clone(&distributed),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        layer.hang_puts.store(true, Ordering::SeqCst);
+        let put_started = layer.put_started.notified();
+        let request = tokio::spawn({
+            let router = router.clone();
+            async move {
+                send_with_headers(
+                    &router,
+                    "PUT",
+                    "/bucket/key",
+                    &[("if-none-match", "*")],
+                    b"abandoned".to_vec(),
+                )
+                .await
+            }
+        });
+        put_started.await;
+        assert_eq!(distributed.held_locks().len(), 1);
+
+        // Dropping the handler future is what a client disconnect or a
+        // request timeout does.
+        request.abort();
+        assert!(request.await.unwrap_err().is_cancelled());
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !distributed.held_locks().is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("object lock released");
+
+        layer.hang_puts.store(false, Ordering::SeqCst);
+        let response = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/key",
+            &[("if-none-match", "*")],
+            b"written".to_vec(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn admin_listing_can_run_from_the_end_of_the_key_space() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for key in ["/bucket/logs/a", "/bucket/logs/b", "/bucket/logs/c"] {
+            assert_eq!(
+                send(&router, "PUT", key, b"data".to_vec()).await,
+                StatusCode::OK
+            );
+        }
+        let list = |uri: String| {
+            let router = router.clone();
+            async move {
+                let headers = signed_headers("GET", &uri, "access", "secret");
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                let response = send_with_headers(&router, "GET", &uri, &headers, Vec::new()).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let keys = page["objects"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|obj| obj["key"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>();
+                (
+                    keys,
+                    page["nextMarker"].as_str().unwrap_or_default().to_string(),
+                )
+            }
+        };
+
+        let (keys, _) =
+            list("/minio/admin/v3/list-objects?bucket=bucket&prefix=logs/".to_string()).await;
+        assert_eq!(keys, ["logs/a", "logs/b", "logs/c"]);
+
+        let descending =
+            "/minio/admin/v3/list-objects?bucket=bucket&max-keys=2&order=key-descending";
+        let (keys, next) = list(descending.to_string()).await;
+        assert_eq!(keys, ["logs/c", "logs/b"]);
+        let (keys, _) = list(format!("{descending}&marker={next}")).await;
+        assert_eq!(keys, ["logs/a"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn admin_rename_prefix_moves_objects() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for key in ["/bucket/logs/a.txt", "/bucket/logs/2024/b.txt"] {
+            assert_eq!(
+                send(&router, "PUT", key, b"data".to_vec()).await,
+                StatusCode::OK
+            );
+        }
+
+        let rename = |body: &'static [u8]| {
+            let router = router.clone();
+            async move {
+                let mut headers =
+                    signed_headers("POST", "/minio/admin/v3/rename-prefix", "access", "secret");
+                headers.push(("content-type".to_string(), "application/json".to_string()));
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                send_with_headers(
+                    &router,
+                    "POST",
+                    "/minio/admin/v3/rename-prefix",
+                    &headers,
+                    body.to_vec(),
+                )
+                .await
+                .status()
+            }
+        };
+        assert_eq!(
+            rename(br#"{"bucket":"bucket","from":"logs/","to":"archive/"}"#).await,
+            StatusCode::OK
+        );
+        for (old, new) in [
+            ("/bucket/logs/a.txt", "/bucket/archive/a.txt"),
+            ("/bucket/logs/2024/b.txt", "/bucket/archive/2024/b.txt"),
+        ] {
+            assert_eq!(send(&router, "GET", new, Vec::new()).await, StatusCode::OK);
+            assert_eq!(
+                send(&router, "GET", old, Vec::new()).await,
+                StatusCode::NOT_FOUND
+            );
+        }
+        assert_eq!(
+            rename(br#"{"bucket":"bucket","from":"archive/","to":"archive/old/"}"#).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn admin_force_unlock_releases_a_stuck_lock() {
+        use maxio_distributed::DRWMutex;
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let object_layer: Arc<dyn ObjectLayer> = Arc::new(
+            SingleDiskObjectLayer::new(root.join("data"))
+                .await
+                .expect("object layer"),
+        );
+        let distributed = Arc::new(
+            DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9000".to_string())).await,
+        );
+        let router = test_router_with_distributed(
+            &root,
+            object_layer,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+            Arc::clone(&distributed),
+        )
+        .await;
+        let admin = |method: &'static str, uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let headers = signed_headers(method, uri, "access", "secret");
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                send_with_headers(&router, method, uri, &headers, Vec::new()).await
+            }
+        };
+
+        let stuck = DRWMutex::new(
+            Arc::clone(distributed.dsync()),
+            vec!["bucket/key".to_string()],
+            "crashed-client",
+            "test",
+        );
+        assert!(stuck.lock().await.unwrap());
+
+        let response = admin("GET", "/minio/admin/v3/top/locks").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let locks: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(locks[0]["resource"], "bucket/key");
+        assert_eq!(locks[0]["owner"], "crashed-client");
+        assert!(locks[0]["uid"].as_str().is_some_and(|uid| !uid.is_empty()));
+        assert!(locks[0]["ageSeconds"].is_i64());
+
+        let next = DRWMutex::new(
+            Arc::clone(distributed.dsync()),
+            vec!["bucket/key".to_string()],
+            "next-client",
+            "test",
+        );
+        assert!(!next.lock().await.unwrap());
+        assert_eq!(
+            admin("POST", "/minio/admin/v3/force-unlock?paths=bucket/key")
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        assert!(distributed.held_locks().is_empty());
+        assert!(next.lock().await.unwrap());
+        next.unlock().await.unwrap();
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// SigV4 headers for an unsigned-payload request without a query string.
+    fn signed_headers(
+        method: &str,
+        path: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Vec<(String, String)> {
+        use maxio_auth::signature_v4::{
+            canonical_query_string, get_canonical_request, get_signature, get_signing_key,
+            get_string_to_sign,
+        };
+
+        let date_time = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let date = &date_time[..8];
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let canonical_request = get_canonical_request(
+            method,
+            path,
+            &canonical_query_string(query),
+            &format!("host:localhost\nx-amz-date:{date_time}\n"),
+            "host;x-amz-date",
+            "UNSIGNED-PAYLOAD",
+        );
+        let scope = format!("{date}/us-east-1/s3/aws4_request");
+        let signature = get_signature(
+            &get_signing_key(secret_key, date, "us-east-1"),
+            &get_string_to_sign(&canonical_request, &date_time, &scope),
+        );
+        vec![
+            ("host".to_string(), "localhost".to_string()),
+            ("x-amz-date".to_string(), date_time),
+            (
+                "authorization".to_string(),
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, \
+                     SignedHeaders=host;x-amz-date, Signature={signature}"
+                ),
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn bucket_listing_only_shows_buckets_the_caller_may_list() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let iam = Arc::new(IAMSys::new(root.join("users")).await.unwrap());
+        iam.create_user("reader", "reader-secret").await.unwrap();
+        iam.create_policy(
+            serde_json::from_str(
+                r#"{"name": "photos-readonly", "Statement": [
+                    {"Effect": "Allow", "Action": "s3:ListAllMyBuckets",
+                     "Resource": "arn:aws:s3:::*"},
+                    {"Effect": "Allow", "Action": ["s3:Get*", "s3:ListBucket"],
+                     "Resource": ["arn:aws:s3:::photos", "arn:aws:s3:::photos/*"]}
+                ]}"#,
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+        iam.attach_policy("reader", "photos-readonly")
+            .await
+            .unwrap();
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::with_iam("access", "secret", iam)),
+        )
+        .await;
+
+        let list = |access_key: &'static str, secret_key: &'static str| {
+            let router = router.clone();
+            async move {
+                let headers = signed_headers("GET", "/", access_key, secret_key);
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                let response = send_with_headers(&router, "GET", "/", &headers, Vec::new()).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body = String::from_utf8(body.to_vec()).unwrap();
+                ["documents", "photos"]
+                    .into_iter()
+                    .filter(|bucket| body.contains(&format!("<Name>{bucket}</Name>")))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        for bucket in ["/documents", "/photos"] {
+            let headers = signed_headers("PUT", bucket, "access", "secret");
+            let headers = headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<Vec<_>>();
+            let response = send_with_headers(&router, "PUT", bucket, &headers, Vec::new()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        assert_eq!(list("access", "secret").await, vec!["documents", "photos"]);
+        assert_eq!(list("reader", "reader-secret").await, vec!["photos"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn copy_object_requires_read_access_to_the_source() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let iam = Arc::new(IAMSys::new(root.join("users")).await.unwrap());
+        iam.create_user("writer", "writer-secret").await.unwrap();
+        iam.create_policy(
+            serde_json::from_str(
+                r#"{"name": "uploads-write", "Statement": [
+                    {"Effect": "Allow", "Action": "s3:PutObject",
+                     "Resource": "arn:aws:s3:::uploads/*"}
+                ]}"#,
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+        iam.attach_policy("writer", "uploads-write").await.unwrap();
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::with_iam("access", "secret", iam)),
+        )
+        .await;
+        let send_signed = |method: &'static str,
+                           uri: &'static str,
+                           access_key: &'static str,
+                           secret_key: &'static str,
+                           extra: &'static [(&'static str, &'static str)],
+                           body: Vec<u8>| {
+            let router = router.clone();
+            async move {
+                let headers = signed_headers(method, uri, access_key, secret_key);
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .chain(extra.iter().copied())
+                    .collect::<Vec<_>>();
+                send_with_headers(&router, method, uri, &headers, body).await
+            }
+        };
+
+        for bucket in ["/secrets", "/uploads"] {
+            let response = send_signed("PUT", bucket, "access", "secret", &[], Vec::new()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = send_signed(
+            "PUT",
+            "/secrets/key",
+            "access",
+            "secret",
+            &[],
+            b"classified".to_vec(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let copy = |access_key, secret_key, source| {
+            send_signed(
+                "PUT",
+                "/uploads/copy",
+                access_key,
+                secret_key,
+                source,
+                Vec::new(),
+            )
+        };
+        for source in [
+            &[("x-amz-copy-source", "/secrets/key")][..],
+            &[("x-amz-copy-source", "/secrets/key?versionId=null")][..],
+        ] {
+            let response = copy("writer", "writer-secret", source).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(String::from_utf8_lossy(&body).contains("<Code>AccessDenied</Code>"));
+        }
+        let response =
+            send_signed("GET", "/uploads/copy", "access", "secret", &[], Vec::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = copy("access", "secret", &[("x-amz-copy-source", "/secrets/key")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Records the fields of every `s3_request` span, keyed by span id.
+    #[derive(Clone, Default)]
+    struct RequestSpans(Arc<std::sync::Mutex<HashMap<u64, HashMap<String, String>>>>);
+
+    struct SpanFields<'a>(&'a mut HashMap<String, String>);
+
+    impl tracing::field::Visit for SpanFields<'_> {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for RequestSpans {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() != "s3_request" {
+                return;
+            }
+            let mut spans = self.0.lock().unwrap();
+            attrs.record(&mut SpanFields(spans.entry(id.into_u64()).or_default()));
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if let Some(fields) = self.0.lock().unwrap().get_mut(&id.into_u64()) {
+                values.record(&mut SpanFields(fields));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_run_in_spans_naming_operation_and_caller() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let spans = RequestSpans::default();
+        let _subscriber =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        let long_key = "k".repeat(MAX_SPAN_KEY_LEN + 10);
+        let send_signed = |method: &'static str, uri: String| {
+            let router = router.clone();
+            async move {
+                let headers = signed_headers(method, &uri, "access", "secret");
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                send_with_headers(&router, method, &uri, &headers, Vec::new()).await
+            }
+        };
+        for (method, uri) in [
+            ("PUT", "/bucket".to_string()),
+            ("PUT", "/bucket/photo.jpg".to_string()),
+            ("PUT", format!("/bucket/{long_key}")),
+        ] {
+            assert_eq!(send_signed(method, uri).await.status(), StatusCode::OK);
+        }
+        let get = send_signed("GET", "/bucket/photo.jpg".to_string()).await;
+        assert_eq!(get.status(), StatusCode::OK);
+        let request_id = get.headers()["x-amz-request-id"].to_str().unwrap();
+
+        let spans = spans.0.lock().unwrap();
+        let span_for = |operation: &str| {
+            spans
+                .values()
+                .find(|fields| fields["operation"] == operation)
+                .unwrap_or_else(|| panic!("no span for {operation}"))
+        };
+        let get_span = span_for("GetObject");
+        assert_eq!(get_span["bucket"], "bucket");
+        assert_eq!(get_span["key"], "photo.jpg");
+        assert_eq!(get_span["access_key"], "access");
+        assert_eq!(get_span["request_id"], request_id);
+        assert!(span_for("CreateBucket").get("key").is_none());
+        assert!(spans.values().any(|fields| {
+            fields["operation"] == "PutObject"
+                && fields["key"] == format!("{}...", &long_key[..MAX_SPAN_KEY_LEN])
+        }));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Hands every event it receives to the test.
+    struct CapturingTarget(tokio::sync::mpsc::UnboundedSender<S3Event>);
+
+    #[async_trait::async_trait]
+    impl NotificationTarget for CapturingTarget {
+        async fn send(&self, event: &S3Event) -> Result<()> {
+            let _ = self.0.send(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn events_carry_the_request_id_returned_to_the_client() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let mut notifications = NotificationSys::new(NotificationStore::new(root.join("data")));
+        notifications.register_target("arn:test".to_string(), Box::new(CapturingTarget(events_tx)));
+        let notifications = Arc::new(notifications);
+        let object_layer: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.join("data")).await.unwrap());
+        let router = s3_router(
+            object_layer,
+            Arc::new(StaticCredentialProvider::disabled()),
+            Arc::new(IAMSys::new(root.join("iam")).await.unwrap()),
+            Arc::clone(&notifications),
+            Arc::new(LifecycleSys::new(
+                LifecycleStore::new(root.clone()),
+                root.clone(),
+            )),
+            Arc::new(
+                DistributedSys::new(ClusterConfig::single("http://127.0.0.1:9000".to_string()))
+                    .await,
+            ),
+            Arc::new(WebsiteStore::new(root.join("data"))),
+        );
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        notifications
+            .set_config(
+                "bucket",
+                NotificationConfiguration {
+                    queue_configurations: vec![QueueConfiguration {
+                        id: "all".to_string(),
+                        queue_arn: "arn:test".to_string(),
+                        events: vec![
+                            "s3:ObjectCreated:*".to_string(),
+                            "s3:ObjectRemoved:*".to_string(),
+                        ],
+                        filter: None,
+                    }],
+                    ..NotificationConfiguration::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        for method in ["PUT", "DELETE"] {
+            let response =
+                send_with_headers(&router, method, "/bucket/doc.txt", &[], b"data".to_vec()).await;
+            assert!(response.status().is_success());
+            let request_id = response.headers()["x-amz-request-id"].to_str().unwrap();
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(event.request_id, request_id, "{method}");
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    async fn read_head(stream: &mut tokio::net::TcpStream) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let mut head = Vec::new();
+        let mut byte = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            let read =
+                tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut byte))
+                    .await
+                    .expect("response in time")
+                    .expect("read response");
+            if read == 0 {
+                break;
+            }
+            head.push(byte[0]);
+        }
+        String::from_utf8(head).expect("utf8 response head")
+    }
+
+    #[tokio::test]
+    async fn expect_continue_is_sent_only_after_auth() {
+        use tokio::io::AsyncWriteExt;
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /bucket/upload.bin HTTP/1.1\r\nHost: localhost\r\n\
+                  Content-Length: 5\r\nExpect: 100-continue\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let interim = read_head(&mut stream).await;
+        assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{interim}");
+        stream.write_all(b"hello").await.unwrap();
+        let head = read_head(&mut stream).await;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /bucket/denied.bin HTTP/1.1\r\nHost: localhost\r\n\
+                  Authorization: AWS4-HMAC-SHA256 invalid\r\n\
+                  Content-Length: 5\r\nExpect: 100-continue\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let head = read_head(&mut stream).await;
+        assert!(head.starts_with("HTTP/1.1 403"), "{head}");
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"PUT /bucket/odd.bin HTTP/1.1\r\nHost: localhost\r\n\
+                  Content-Length: 5\r\nExpect: 200-ok\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let head = read_head(&mut stream).await;
+        assert!(head.starts_with("HTTP/1.1 417"), "{head}");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn writes_below_quorum_ask_clients_to_retry() {
+        use maxio_storage::erasure::{ErasureConfig, sets::ErasureObjectLayer};
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let config = ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            ..ErasureConfig::default()
+        };
+        let disks = (0..config.total_shards())
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect::<Vec<_>>();
+        let object_layer: Arc<dyn ObjectLayer> = Arc::new(
+            ErasureObjectLayer::new(disks.clone(), config)
+                .await
+                .expect("erasure layer"),
+        );
+        let router = test_router_with_layer(
+            &root,
+            object_layer,
+            Arc::new(StaticCredentialProvider::disabled()),
+        )
+        .await;
+
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for disk in &disks[..3] {
+            std::fs::write(disk.join("bucket").join("blocked"), b"file").unwrap();
+        }
+
+        let response =
+            send_with_headers(&router, "PUT", "/bucket/blocked", &[], b"data".to_vec()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Code>SlowDown</Code>"), "{body}");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_disks_going_offline_and_recovering() {
+        use maxio_storage::erasure::{ErasureConfig, sets::ErasureObjectLayer};
+
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let config = ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            ..ErasureConfig::default()
+        };
+        let disks = (0..config.total_shards())
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect::<Vec<_>>();
+        let erasure = ErasureObjectLayer::new(disks.clone(), config)
+            .await
+            .expect("erasure layer");
+        let recovery = erasure.start_disk_recovery(std::time::Duration::from_millis(20));
+        let router = test_router_with_layer(
+            &root,
+            Arc::new(erasure),
+            Arc::new(StaticCredentialProvider::disabled()),
+        )
+        .await;
+
+        assert_eq!(
+            send(&router, "GET", "/minio/health/live", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "GET", "/minio/health/ready", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "GET", "/minio/health/cluster", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        // Three of the four disks are swapped for files, as if unmounted, so
+        // every request fails on them until they are taken offline.
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for disk in &disks[..3] {
+            std::fs::rename(disk, disk.with_extension("detached")).unwrap();
+            std::fs::write(disk, b"unmounted").unwrap();
+        }
+        for _ in 0..maxio_storage::erasure::health::DEFAULT_OFFLINE_THRESHOLD {
+            send(&router, "PUT", "/bucket/object", b"data".to_vec()).await;
+        }
+
+        assert_eq!(
+            send(&router, "GET", "/minio/health/live", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "GET", "/minio/health/ready", Vec::new()).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            send(&router, "GET", "/minio/health/cluster", Vec::new()).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        // Once the disks are back, the recovery probe brings them online and
+        // readiness follows without a restart.
+        for disk in &disks[..3] {
+            std::fs::remove_file(disk).unwrap();
+            std::fs::rename(disk.with_extension("detached"), disk).unwrap();
+        }
+        let mut ready = StatusCode::SERVICE_UNAVAILABLE;
+        for _ in 0..100 {
+            ready = send(&router, "GET", "/minio/health/ready", Vec::new()).await;
+            if ready == StatusCode::OK {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(ready, StatusCode::OK);
+        assert_eq!(
+            send(&router, "PUT", "/bucket/object", b"data".to_vec()).await,
+            StatusCode::OK
+        );
+
+        recovery.abort();
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn auto_encryption_applies_sse_s3_to_plain_uploads() {
+        for enabled in [false, true] {
+            let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+            let object_layer: Arc<dyn ObjectLayer> = Arc::new(
+                SingleDiskObjectLayer::new(root.join("data"))
+                    .await
+                    .expect("object layer")
+                    .with_auto_encryption(enabled),
+            );
+            let router = test_router_with_layer(
+                &root,
+                object_layer,
+                Arc::new(StaticCredentialProvider::disabled()),
+            )
+            .await;
+            assert_eq!(
+                send(&router, "PUT", "/bucket", Vec::new()).await,
+                StatusCode::OK
+            );
+
+            let put =
+                send_with_headers(&router, "PUT", "/bucket/plain.txt", &[], b"secret".to_vec())
+                    .await;
+            assert_eq!(put.status(), StatusCode::OK);
+            assert_eq!(
+                put.headers()
+                    .get("x-amz-server-side-encryption")
+                    .map(|value| value.to_str().unwrap()),
+                enabled.then_some("AES256")
+            );
+
+            let data_file =
+                find_file(&root.join("data").join("bucket"), "part.1").expect("data file");
+            assert_eq!(std::fs::read(&data_file).unwrap() != b"secret", enabled);
+
+            let get = send_with_headers(&router, "GET", "/bucket/plain.txt", &[], Vec::new()).await;
+            assert_eq!(get.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(get.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body.as_ref(), b"secret");
+
+            // Multipart uploads are encrypted once their parts are assembled.
+            let created = send_with_headers(
+                &router,
+                "POST",
+                "/bucket/multi.bin?uploads",
+                &[],
+                Vec::new(),
+            )
+            .await;
+            assert_eq!(created.status(), StatusCode::OK);
+            let created = axum::body::to_bytes(created.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let upload_id = tag_values(&String::from_utf8_lossy(&created), "UploadId").remove(0);
+            let part = send_with_headers(
+                &router,
+                "PUT",
+                &format!("/bucket/multi.bin?partNumber=1&uploadId={upload_id}"),
+                &[],
+                b"multipart secret".to_vec(),
+            )
+            .await;
+            assert_eq!(part.status(), StatusCode::OK);
+            let etag = part.headers()["etag"].to_str().unwrap().to_string();
+            let complete = format!(
+                "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber>\
+                 <ETag>{etag}</ETag></Part></CompleteMultipartUpload>"
+            );
+            let completed = send_with_headers(
+                &router,
+                "POST",
+                &format!("/bucket/multi.bin?uploadId={upload_id}"),
+                &[],
+                complete.into_bytes(),
+            )
+            .await;
+            assert_eq!(completed.status(), StatusCode::OK);
+
+            let data_file = find_file(
+                &root.join("data").join("bucket").join("multi.bin"),
+                "part.1",
+            )
+            .expect("multipart data file");
+            assert_eq!(
+                std::fs::read(&data_file).unwrap() != b"multipart secret",
+                enabled
+            );
+            let get = send_with_headers(&router, "GET", "/bucket/multi.bin", &[], Vec::new()).await;
+            assert_eq!(get.status(), StatusCode::OK);
+            assert_eq!(
+                get.headers()
+                    .get("x-amz-server-side-encryption")
+                    .map(|value| value.to_str().unwrap()),
+                enabled.then_some("AES256")
+            );
+            let body = axum::body::to_bytes(get.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body.as_ref(), b"multipart secret");
+
+            let _ = std::fs::remove_dir_all(root);
+        }
+    }
+
+    #[tokio::test]
+    async fn over_long_keys_are_rejected_with_key_too_long() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        // Short segments, so only the 1024-byte key limit applies.
+        let too_long = format!("{}c", "k/".repeat(512));
+        assert_eq!(too_long.len(), 1025);
+        let single_segment = format!("dir/{}", "s".repeat(300));
+
+        for key in [too_long, single_segment] {
+            let response = send_with_headers(
+                &router,
+                "PUT",
+                &format!("/bucket/{key}"),
+                &[],
+                b"x".to_vec(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8_lossy(&body);
+            assert!(body.contains("<Code>KeyTooLongError</Code>"), "{body}");
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn unimplemented_subresources_return_not_implemented() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let response = send_with_headers(&router, "GET", "/bucket?cors", &[], Vec::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Code>NotImplemented</Code>"), "{body}");
+        assert!(body.contains("GET ?cors"), "{body}");
+
+        // Must not fall through to bucket creation or object reads either.
+        assert_eq!(
+            send(&router, "PUT", "/other?accelerate", Vec::new()).await,
+            StatusCode::NOT_IMPLEMENTED
+        );
+        assert_eq!(
+            send(&router, "PUT", "/other?logging", Vec::new()).await,
+            StatusCode::NOT_IMPLEMENTED
+        );
+        assert_eq!(
+            send(&router, "HEAD", "/other", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            send(&router, "GET", "/bucket/key?retention", Vec::new()).await,
+            StatusCode::NOT_IMPLEMENTED
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn start_after_only_positions_the_first_page() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        for key in ["a1", "b/x", "b/y", "c1", "d/z"] {
+            assert_eq!(
+                send(&router, "PUT", &format!("/bucket/{key}"), b"x".to_vec()).await,
+                StatusCode::OK
+            );
+        }
+
+        let list = |query: &'static str| {
+            let router = router.clone();
+            async move {
+                let uri = format!("/bucket?list-type=2&{query}");
+                let response = send_with_headers(&router, "GET", &uri, &[], Vec::new()).await;
+                assert_eq!(response.status(), StatusCode::OK, "{query}");
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body = String::from_utf8(body.to_vec()).unwrap();
+                let values = |tag: &str| {
+                    body.split(&format!("<{tag}>"))
+                        .skip(1)
+                        .map(|rest| rest.split('<').next().unwrap().to_string())
+                        .collect::<Vec<_>>()
+                };
+                (values("Key"), values("Prefix"), values("StartAfter"))
+            }
+        };
+
+        let (keys, _, start_after) = list("start-after=a1").await;
+        assert_eq!(keys, ["b/x", "b/y", "c1", "d/z"]);
+        assert_eq!(start_after, ["a1"]);
+
+        // The token wins; start-after is not applied on top of it.
+        let (keys, _, _) = list("start-after=c1&continuation-token=a1").await;
+        assert_eq!(keys, ["b/x", "b/y", "c1", "d/z"]);
+        let (keys, _, _) = list("start-after=a1&continuation-token=c1").await;
+        assert_eq!(keys, ["d/z"]);
+
+        let (keys, prefixes, _) = list("delimiter=/&start-after=a1").await;
+        assert_eq!(keys, ["c1"]);
+        assert_eq!(prefixes, ["b/", "d/"]);
+        let (keys, prefixes, _) = list("delimiter=/&start-after=b/").await;
+        assert_eq!(keys, ["c1"]);
+        assert_eq!(prefixes, ["d/"]);
+        let (keys, prefixes, _) = list("delimiter=/&continuation-token=b/").await;
+        assert_eq!(keys, ["c1"]);
+        assert_eq!(prefixes, ["d/"]);
+        let (keys, prefixes, _) = list("delimiter=/&start-after=b/x").await;
+        assert_eq!(keys, ["c1"]);
+        assert_eq!(prefixes, ["b/", "d/"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn bucket_creation_checks_the_location_constraint() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        let configuration = |region: &str| {
+            format!(
+                "<CreateBucketConfiguration \
+                 xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+                 <LocationConstraint>{region}</LocationConstraint>\
+                 </CreateBucketConfiguration>"
+            )
+            .into_bytes()
+        };
+
+        assert_eq!(
+            send(&router, "PUT", "/plain", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/matching", configuration("us-east-1")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/unnamed", configuration("")).await,
+            StatusCode::OK
+        );
+
+        let response = send_with_headers(
+            &router,
+            "PUT",
+            "/elsewhere",
+            &[],
+            configuration("eu-west-1"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>InvalidLocationConstraint</Code>"));
+        assert_eq!(
+            send(&router, "HEAD", "/elsewhere", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn list_bucket_result_declares_s3_namespace() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let list = send_with_headers(&router, "GET", "/bucket", &[], Vec::new()).await;
+        assert_eq!(list.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(list.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">"
+        ));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn validate_mode_checks_configs_without_storing_them() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        std::fs::create_dir_all(root.join("bucket")).unwrap();
+
+        let body_of = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let no_action = b"<LifecycleConfiguration><Rule><ID>keep</ID><Status>Enabled</Status>\
+                          </Rule></LifecycleConfiguration>"
+            .to_vec();
+        let response =
+            send_with_headers(&router, "PUT", "/bucket?lifecycle&validate", &[], no_action).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_of(response).await;
+        assert!(body.contains("<Valid>false</Valid>"), "{body}");
+        assert!(
+            body.contains("<Error>invalid argument: lifecycle rule keep must include expiration"),
+            "{body}"
+        );
+
+        let disabled = b"<LifecycleConfiguration><Rule><ID>old</ID><Status>Disabled</Status>\
+                         <Expiration><Days>30</Days></Expiration></Rule></LifecycleConfiguration>"
+            .to_vec();
+        let response =
+            send_with_headers(&router, "PUT", "/bucket?lifecycle&validate", &[], disabled).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_of(response).await;
+        assert!(body.contains("<Valid>true</Valid>"), "{body}");
+        assert!(
+            body.contains("<Warning>lifecycle rule old is disabled"),
+            "{body}"
+        );
+
+        let stored = send_with_headers(&router, "GET", "/bucket?lifecycle", &[], Vec::new()).await;
+        assert!(!stored.headers().contains_key("etag"));
+        assert!(!body_of(stored).await.contains("<Rule>"));
+
+        let validate = "/minio/admin/v3/add-canned-policy?validate";
+        let mut headers = signed_headers("POST", validate, "access", "secret");
+        headers.push(("content-type".to_string(), "application/json".to_string()));
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let response = send_with_headers(
+            &router,
+            "POST",
+            validate,
+            &headers,
+            br#"{"name":"team","policy":{"name":"team","Statement":[{"Effect":"Allow","Action":[],"Resource":"*"}]}}"#
+                .to_vec(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let result: serde_json::Value = serde_json::from_str(&body_of(response).await).unwrap();
+        assert_eq!(result["valid"], false);
+        assert_eq!(result["errors"].as_array().unwrap().len(), 1);
+
+        let info = signed_headers(
+            "GET",
+            "/minio/admin/v3/info-canned-policy?name=team",
+            "access",
+            "secret",
+        );
+        let info = info
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let response = send_with_headers(
+            &router,
+            "GET",
+            "/minio/admin/v3/info-canned-policy?name=team",
+            &info,
+            Vec::new(),
+        )
+        .await;
+        assert!(!response.status().is_success());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn stale_if_match_config_updates_are_rejected() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router_with_credentials(
+            &root,
+            Arc::new(StaticCredentialProvider::new("access", "secret")),
+        )
+        .await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        std::fs::create_dir_all(root.join("bucket")).unwrap();
+
+        let rule = |days: u32| {
+            format!(
+                "<LifecycleConfiguration><Rule><ID>expire</ID><Status>Enabled</Status>\
+                 <Expiration><Days>{days}</Days></Expiration></Rule></LifecycleConfiguration>"
+            )
+            .into_bytes()
+        };
+        let first = send_with_headers(&router, "PUT", "/bucket?lifecycle", &[], rule(1)).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let stale = first.headers()["etag"].to_str().unwrap().to_string();
+        let get = send_with_headers(&router, "GET", "/bucket?lifecycle", &[], Vec::new()).await;
+        assert_eq!(get.headers()["etag"].to_str().unwrap(), stale);
+
+        // Another writer updates the configuration in between.
+        let second = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket?lifecycle",
+            &[("if-match", &stale)],
+            rule(2),
+        )
+        .await;
+        assert_eq!(second.status(), StatusCode::OK);
+        let fresh = second.headers()["etag"].to_str().unwrap().to_string();
+        assert_ne!(fresh, stale);
+
+        let rejected = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket?lifecycle",
+            &[("if-match", &stale)],
+            rule(3),
+        )
+        .await;
+        assert_eq!(rejected.status(), StatusCode::PRECONDITION_FAILED);
+        let accepted = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket?lifecycle",
+            &[("if-match", &fresh)],
+            rule(3),
+        )
+        .await;
+        assert_eq!(accepted.status(), StatusCode::OK);
+
+        let policy = br#"{"name":"team","policy":{"name":"team","Version":"2012-10-17","Statement":[{"Effect":"Allow","Action":["s3:GetObject"],"Resource":["arn:aws:s3:::bucket/*"]}]}}"#;
+        let admin = |method: &'static str, uri: &'static str, if_match: Option<String>| {
+            let router = router.clone();
+            async move {
+                let mut headers = signed_headers(method, uri, "access", "secret");
+                headers.push(("content-type".to_string(), "application/json".to_string()));
+                headers.extend(if_match.map(|etag| ("if-match".to_string(), etag)));
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                let body = if method == "POST" {
+                    policy.to_vec()
+                } else {
+                    Vec::new()
+                };
+                send_with_headers(&router, method, uri, &headers, body).await
+            }
+        };
+        let add = "/minio/admin/v3/add-canned-policy";
+        let created = admin("POST", add, None).await;
+        assert_eq!(created.status(), StatusCode::OK);
+        let stale = created.headers()["etag"].to_str().unwrap().to_string();
+        let info = admin("GET", "/minio/admin/v3/info-canned-policy?name=team", None).await;
+        assert_eq!(info.headers()["etag"].to_str().unwrap(), stale);
+        let body = axum::body::to_bytes(info.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(!body.contains("revision"), "{body}");
+        assert_eq!(
+            admin("POST", add, Some(stale.clone())).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            admin("POST", add, Some(stale)).await.status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn accelerate_and_request_payment_return_defaults() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        for (uri, expected) in [
+            (
+                "/bucket?accelerate",
+                "<AccelerateConfiguration xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"/>",
+            ),
+            (
+                "/bucket?requestPayment",
+                "<RequestPaymentConfiguration xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+                 <Payer>BucketOwner</Payer></RequestPaymentConfiguration>",
+            ),
+        ] {
+            let response = send_with_headers(&router, "GET", uri, &[], Vec::new()).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(
+                String::from_utf8_lossy(&body).ends_with(expected),
+                "{uri}: {}",
+                String::from_utf8_lossy(&body)
+            );
+        }
+
+        assert_eq!(
+            send(&router, "GET", "/missing?accelerate", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn single_puts_over_five_gigabytes_are_rejected() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        // Rejected from the declared length alone, before the body is read.
+        let too_large = (5_u64 * 1024 * 1024 * 1024 + 1).to_string();
+        let response = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/huge.bin",
+            &[("content-length", &too_large)],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Code>EntityTooLarge</Code>"), "{body}");
+        assert_eq!(
+            send(&router, "HEAD", "/bucket/huge.bin", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        assert_eq!(
+            send(&router, "PUT", "/bucket/small.bin", b"small".to_vec()).await,
+            StatusCode::OK
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn chunked_uploads_without_content_length_are_sized_from_the_body() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let chunked = |parts: &[&'static [u8]]| {
+            let chunks = parts
+                .iter()
+                .map(|part| Ok::<_, std::io::Error>(axum::body::Bytes::from_static(part)))
+                .collect::<Vec<_>>();
+            Body::from_stream(futures::stream::iter(chunks))
+        };
+        let upload = |uri: &'static str, body: Body| {
+            let router = router.clone();
+            async move {
+                router
+                    .oneshot(
+                        Request::builder()
+                            .method("PUT")
+                            .uri(uri)
+                            .header("transfer-encoding", "chunked")
+                            .body(body)
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = upload(
+            "/bucket/streamed",
+            chunked(&[b"first chunk, ", b"second chunk, ", b"last chunk"]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = maxio_common::hash::md5_hex(b"first chunk, second chunk, last chunk");
+        assert_eq!(response.headers()["etag"], format!("\"{etag}\"").as_str());
+        let head = send_with_headers(&router, "HEAD", "/bucket/streamed", &[], Vec::new()).await;
+        assert_eq!(head.headers()["content-length"], "37");
+        assert_eq!(head.headers()["etag"], format!("\"{etag}\"").as_str());
+
+        // Without a declared length the single-PUT limit applies to the bytes
+        // actually received.
+        let capped = Router::new()
+            .route(
+                "/upload",
+                put(|body: axum::body::Bytes| async move { body.len().to_string() }),
+            )
+            .layer(middleware::from_fn(check_put_size))
+            .layer(Extension(ObjectSizeLimits {
+                max_put_size: 8,
+                max_object_size: 8,
+            }));
+        let send_capped = |body: Body| {
+            capped.clone().oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/upload")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let response = send_capped(chunked(&[b"1234", b"5678"])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send_capped(chunked(&[b"1234", b"5678", b"9"]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>EntityTooLarge</Code>"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn user_metadata_is_limited_to_two_kilobytes() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        // "note" plus "k" count towards the limit along with the values.
+        let value = |len: usize| "v".repeat(len);
+        let at_limit = value(2048 - "note".len() - "k".len() - 1);
+        let put = |key: &'static str, headers: Vec<(&'static str, String)>| {
+            let router = router.clone();
+            async move {
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| (*name, value.as_str()))
+                    .collect::<Vec<_>>();
+                send_with_headers(&router, "PUT", key, &headers, b"x".to_vec()).await
+            }
+        };
+
+        let accepted = put(
+            "/bucket/at-limit",
+            vec![
+                ("x-amz-meta-note", at_limit.clone()),
+                ("x-amz-meta-k", "v".to_string()),
+            ],
+        )
+        .await;
+        assert_eq!(accepted.status(), StatusCode::OK);
+        let head = send_with_headers(&router, "HEAD", "/bucket/at-limit", &[], Vec::new()).await;
+        assert_eq!(head.headers()["x-amz-meta-note"], at_limit.as_str());
+
+        let rejected = put(
+            "/bucket/over-limit",
+            vec![
+                ("x-amz-meta-note", at_limit.clone()),
+                ("x-amz-meta-k", "vv".to_string()),
+            ],
+        )
+        .await;
+        assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(rejected.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>MetadataTooLarge</Code>"));
+        assert_eq!(
+            send(&router, "HEAD", "/bucket/over-limit", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let non_ascii = put(
+            "/bucket/non-ascii",
+            vec![("x-amz-meta-city", "Z\u{fc}rich".to_string())],
+        )
+        .await;
+        assert_eq!(non_ascii.status(), StatusCode::BAD_REQUEST);
+        let encoded = put(
+            "/bucket/encoded",
+            vec![("x-amz-meta-city", "=?UTF-8?B?WsO8cmljaA==?=".to_string())],
+        )
+        .await;
+        assert_eq!(encoded.status(), StatusCode::OK);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn metadata_names_match_case_insensitively() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let put = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/photo",
+            &[("X-Amz-Meta-MyKey", "Value")],
+            b"x".to_vec(),
+        )
+        .await;
+        assert_eq!(put.status(), StatusCode::OK);
+
+        let get = send_with_headers(&router, "GET", "/bucket/photo", &[], Vec::new()).await;
+        assert_eq!(get.status(), StatusCode::OK);
+        assert_eq!(get.headers()["X-Amz-Meta-MyKey"], "Value");
+        assert_eq!(get.headers()["x-amz-meta-mykey"], "Value");
+        let names = get
+            .headers()
+            .keys()
+            .filter(|name| name.as_str().starts_with("x-amz-meta-"))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["x-amz-meta-mykey"]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn get_and_head_advertise_byte_ranges() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/bucket/file", b"0123456789".to_vec()).await,
+            StatusCode::OK
+        );
+
+        for (method, headers) in [
+            ("GET", &[][..]),
+            ("HEAD", &[][..]),
+            ("GET", &[("range", "bytes=0-3")][..]),
+        ] {
+            let response =
+                send_with_headers(&router, method, "/bucket/file", headers, Vec::new()).await;
+            assert!(response.status().is_success(), "{method} {headers:?}");
+            assert_eq!(
+                response.headers()["accept-ranges"],
+                "bytes",
+                "{method} {headers:?}"
+            );
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn if_range_serves_partial_content_only_while_the_validator_matches() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let put =
+            send_with_headers(&router, "PUT", "/bucket/file", &[], b"0123456789".to_vec()).await;
+        let etag = put.headers()["etag"].to_str().unwrap().to_string();
+        let head = send_with_headers(&router, "HEAD", "/bucket/file", &[], Vec::new()).await;
+        let last_modified = head.headers()["last-modified"]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let ranged = |if_range: String| {
+            let router = router.clone();
+            async move {
+                let response = send_with_headers(
+                    &router,
+                    "GET",
+                    "/bucket/file",
+                    &[("range", "bytes=2-4"), ("if-range", if_range.as_str())],
+                    Vec::new(),
+                )
+                .await;
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, body.to_vec())
+            }
+        };
+
+        assert_eq!(
+            ranged(etag.clone()).await,
+            (StatusCode::PARTIAL_CONTENT, b"234".to_vec())
+        );
+        assert_eq!(
+            ranged(last_modified).await,
+            (StatusCode::PARTIAL_CONTENT, b"234".to_vec())
+        );
+        assert_eq!(
+            ranged("\"0123456789abcdef0123456789abcdef\"".to_string()).await,
+            (StatusCode::OK, b"0123456789".to_vec())
+        );
+        assert_eq!(
+            ranged("Thu, 01 Jan 2015 00:00:00 GMT".to_string()).await,
+            (StatusCode::OK, b"0123456789".to_vec())
+        );
+        assert_eq!(
+            ranged(format!("W/{etag}")).await,
+            (StatusCode::OK, b"0123456789".to_vec())
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn ranged_head_reports_the_range_without_a_body() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&router, "PUT", "/bucket/file", b"0123456789".to_vec()).await,
+            StatusCode::OK
+        );
+        let head = |range: &'static str| {
+            let router = router.clone();
+            async move {
+                send_with_headers(
+                    &router,
+                    "HEAD",
+                    "/bucket/file",
+                    &[("range", range)],
+                    Vec::new(),
+                )
+                .await
+            }
+        };
+
+        for (range, content_range, content_length) in [
+            ("bytes=2-4", "bytes 2-4/10", "3"),
+            ("bytes=7-", "bytes 7-9/10", "3"),
+            ("bytes=-4", "bytes 6-9/10", "4"),
+            ("bytes=8-100", "bytes 8-9/10", "2"),
+        ] {
+            let response = head(range).await;
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{range}");
+            assert_eq!(response.headers()["content-range"], content_range);
+            assert_eq!(response.headers()["content-length"], content_length);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.is_empty());
+        }
+
+        for range in ["bytes=10-", "bytes=20-30", "bytes=-0"] {
+            let response = head(range).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "{range}"
+            );
+            assert_eq!(response.headers()["content-range"], "bytes */10");
+        }
+        let get = send_with_headers(
+            &router,
+            "GET",
+            "/bucket/file",
+            &[("range", "bytes=10-")],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(get.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        // Malformed ranges are ignored rather than rejected.
+        let response = head("bytes=4-2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-length"], "10");
+        assert!(!response.headers().contains_key("content-range"));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn requests_over_the_concurrency_limit_are_shed() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let limiter = RequestLimiter::new(1);
+        let router = Router::new()
+            .route(
+                "/bucket/key",
+                get({
+                    let release = Arc::clone(&release);
+                    move || async move {
+                        release.notified().await;
+                        StatusCode::OK
+                    }
+                }),
+            )
+            .layer(middleware::from_fn(shed_load))
+            .layer(Extension(limiter.clone()));
+
+        let held = tokio::spawn({
+            let router = router.clone();
+            async move { send_with_headers(&router, "GET", "/bucket/key", &[], Vec::new()).await }
+        });
+        while limiter.metrics().in_flight == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let shed = send_with_headers(&router, "GET", "/bucket/key", &[], Vec::new()).await;
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(shed.headers().contains_key("retry-after"));
+        let body = axum::body::to_bytes(shed.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>SlowDown</Code>"));
+        assert_eq!(limiter.metrics().rejected, 1);
+
+        release.notify_one();
+        assert_eq!(held.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(limiter.metrics().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn slow_handlers_and_stalled_bodies_time_out() {
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    StatusCode::OK
+                }),
+            )
+            .route(
+                "/upload",
+                put(|_body: axum::body::Bytes| async { StatusCode::OK }),
+            )
+            .layer(middleware::from_fn(enforce_timeouts))
+            .layer(Extension(RequestTimeouts {
+                header_read: Duration::ZERO,
+                idle: Duration::from_millis(100),
+                request: Duration::from_millis(300),
+            }));
+
+        let started = std::time::Instant::now();
+        let response = send_with_headers(&router, "GET", "/slow", &[], Vec::new()).await;
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("<Code>OperationTimedOut</Code>"));
+
+        // One chunk, then the client goes quiet without finishing the body.
+        let stalled = futures::stream::once(async {
+            Ok::<_, std::io::Error>(axum::body::Bytes::from_static(b"partial"))
+        })
+        .chain(futures::stream::pending());
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/upload")
+                    .body(Body::from_stream(stalled))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        assert_eq!(
+            send(&router, "PUT", "/upload", b"complete".to_vec()).await,
+            StatusCode::OK
+        );
+    }
+
+    /// SSE-C headers for a key of `byte` repeated, under `prefix`
+    /// (`x-amz-` or `x-amz-copy-source-`).
+    fn sse_c_headers(prefix: &str, byte: u8) -> Vec<(String, String)> {
+        use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64_STANDARD};
+
+        let key = [byte; 32];
+        [
+            ("algorithm", "AES256".to_string()),
+            ("key", BASE64_STANDARD.encode(key)),
+            (
+                "key-md5",
+                BASE64_STANDARD.encode(maxio_common::hash::md5_digest(&key)),
+            ),
+        ]
+        .into_iter()
+        .map(|(suffix, value)| {
+            (
+                format!("{prefix}server-side-encryption-customer-{suffix}"),
+                value,
+            )
+        })
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn copy_object_re_encrypts_sse_c_objects_under_a_new_key() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let old_key = sse_c_headers("x-amz-", 1);
+        let new_key = sse_c_headers("x-amz-", 2);
+        let mut copy = sse_c_headers("x-amz-copy-source-", 1);
+        copy.extend(new_key.iter().cloned());
+        copy.push((
+            "x-amz-copy-source".to_string(),
+            "/bucket/secret.txt".to_string(),
+        ));
+        let [old_key, new_key, copy] = [&old_key, &new_key, &copy].map(|headers| {
+            headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<Vec<_>>()
+        });
+
+        let put = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/secret.txt",
+            &old_key,
+            b"rotate me".to_vec(),
+        )
+        .await;
+        assert_eq!(put.status(), StatusCode::OK);
+
+        let copied =
+            send_with_headers(&router, "PUT", "/bucket/rotated.txt", &copy, Vec::new()).await;
+        assert_eq!(copied.status(), StatusCode::OK);
+        assert_eq!(
+            copied.headers()["x-amz-server-side-encryption-customer-key-md5"],
+            new_key[2].1
+        );
+
+        let get =
+            send_with_headers(&router, "GET", "/bucket/rotated.txt", &new_key, Vec::new()).await;
+        assert_eq!(get.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(get.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"rotate me");
+
+        // The copy is no longer readable with the source key, and the source
+        // cannot be copied without it.
+        let stale =
+            send_with_headers(&router, "GET", "/bucket/rotated.txt", &old_key, Vec::new()).await;
+        assert_ne!(stale.status(), StatusCode::OK);
+        let keyless = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/rotated-again.txt",
+            &[("x-amz-copy-source", "/bucket/secret.txt")],
+            Vec::new(),
+        )
+        .await;
+        assert_ne!(keyless.status(), StatusCode::OK);
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn sse_c_objects_read_without_the_key_are_invalid_requests() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let key = sse_c_headers("x-amz-", 7);
+        let key = key
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let put = send_with_headers(
+            &router,
+            "PUT",
+            "/bucket/secret.txt",
+            &key,
+            b"customer data".to_vec(),
+        )
+        .await;
+        assert_eq!(put.status(), StatusCode::OK);
+
+        let get = send_with_headers(&router, "GET", "/bucket/secret.txt", &[], Vec::new()).await;
+        assert_eq!(get.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get.headers()["content-type"], "application/xml");
+        let body = axum::body::to_bytes(get.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("<Code>InvalidRequest</Code>"), "{body}");
+        assert!(
+            body.contains("must provide the appropriate secret key"),
+            "{body}"
+        );
+        assert!(!body.contains("customer data"));
+
+        let head = send_with_headers(&router, "HEAD", "/bucket/secret.txt", &[], Vec::new()).await;
+        assert_eq!(head.status(), StatusCode::BAD_REQUEST);
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn delete_with_if_match_requires_the_current_etag() {
+        let root = std::env::temp_dir().join(format!("maxio-router-{}", uuid::Uuid::new_v4()));
+        let router = test_router(&root).await;
+        assert_eq!(
+            send(&router, "PUT", "/bucket", Vec::new()).await,
+            StatusCode::OK
+        );
+        let put = send_with_headers(&router, "PUT", "/bucket/doc.txt", &[], b"v1".to_vec()).await;
+        let etag = put.headers()["etag"].to_str().unwrap().to_string();
+
+        let stale = send_with_headers(
+            &router,
+            "DELETE",
+            "/bucket/doc.txt",
+            &[("if-match", "\"0123456789abcdef0123456789abcdef\"")],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(stale.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            send(&router, "GET", "/bucket/doc.txt", Vec::new()).await,
+            StatusCode::OK
+        );
+
+        let matching = send_with_headers(
+            &router,
+            "DELETE",
+            "/bucket/doc.txt",
+            &[("x-amz-if-match", &etag)],
+            Vec::new(),
+        )
+        .await;
+        assert_eq!(matching.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            send(&router, "GET", "/bucket/doc.txt", Vec::new()).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::body::Body;
+use futures::{StreamExt, stream};
+
+/// How long a client may take to send its request headers.
+pub const DEFAULT_HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a request body may stall between two chunks.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a request may take from its headers to its response, body
+/// upload included.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Deadlines that keep slow or stuck requests from holding a connection
+/// forever. A zero duration disables that timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    /// Enforced by the HTTP server while it reads the request head.
+    pub header_read: Duration,
+    pub idle: Duration,
+    pub request: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            header_read: DEFAULT_HEADER_READ_TIMEOUT,
+            idle: DEFAULT_IDLE_TIMEOUT,
+            request: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Reads `MAXIO_API_HEADER_READ_TIMEOUT_SECS`,
+    /// `MAXIO_API_IDLE_TIMEOUT_SECS` and `MAXIO_API_REQUEST_TIMEOUT_SECS`;
+    /// unset or unparsable values keep the defaults.
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: Duration| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map_or(default, Duration::from_secs)
+        };
+        Self {
+            header_read: read(
+                "MAXIO_API_HEADER_READ_TIMEOUT_SECS",
+                DEFAULT_HEADER_READ_TIMEOUT,
+            ),
+            idle: read("MAXIO_API_IDLE_TIMEOUT_SECS", DEFAULT_IDLE_TIMEOUT),
+            request: read("MAXIO_API_REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+}
+
+/// Ends `body` with an error once no chunk arrived for `idle`, raising
+/// `stalled` so the caller can tell a slow client from a malformed body.
+pub(crate) fn with_idle_timeout(body: Body, idle: Duration, stalled: Arc<AtomicBool>) -> Body {
+    if idle.is_zero() {
+        return body;
+    }
+    let chunks = stream::unfold(Some(body.into_data_stream()), move |chunks| {
+        let stalled = Arc::clone(&stalled);
+        async move {
+            let mut chunks = chunks?;
+            match tokio::time::timeout(idle, chunks.next()).await {
+                Ok(chunk) => chunk.map(|chunk| (chunk, Some(chunks))),
+                Err(_) => {
+                    stalled.store(true, Ordering::Relaxed);
+                    Some((Err(axum::Error::new("request body stalled")), None))
+                }
+            }
+        }
+    });
+    Body::from_stream(chunks)
+}
@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use maxio_common::error::{MaxioError, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+const WEBSITE_FILE_NAME: &str = ".website.json";
+
+/// Static website hosting settings of a bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "WebsiteConfiguration")]
+pub struct WebsiteConfiguration {
+    #[serde(rename = "IndexDocument", default)]
+    pub index_document: Option<IndexDocument>,
+    #[serde(
+        rename = "ErrorDocument",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error_document: Option<ErrorDocument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexDocument {
+    #[serde(rename = "Suffix")]
+    pub suffix: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDocument {
+    #[serde(rename = "Key")]
+    pub key: String,
+}
+
+impl WebsiteConfiguration {
+    pub fn validate(&self) -> Result<()> {
+        let Some(index) = self.index_document.as_ref() else {
+            return Err(MaxioError::InvalidArgument(
+                "website configuration must include an IndexDocument".to_string(),
+            ));
+        };
+        if index.suffix.is_empty() || index.suffix.contains('/') {
+            return Err(MaxioError::InvalidArgument(
+                "IndexDocument Suffix must be non-empty and must not contain a slash".to_string(),
+            ));
+        }
+        if self
+            .error_document
+            .as_ref()
+            .is_some_and(|error| error.key.is_empty())
+        {
+            return Err(MaxioError::InvalidArgument(
+                "ErrorDocument Key must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The object a website request for `key` is answered with: keys naming
+    /// a directory (the bucket root or ending in `/`) get the index document.
+    pub fn resolve(&self, key: &str) -> String {
+        match self.index_document.as_ref() {
+            Some(index) if key.is_empty() || key.ends_with('/') => {
+                format!("{key}{}", index.suffix)
+            }
+            _ => key.to_string(),
+        }
+    }
+}
+
+/// Persists website configurations next to the other per-bucket
+/// configuration files under the data root.
+#[derive(Debug, Clone)]
+pub struct WebsiteStore {
+    root: PathBuf,
+}
+
+impl WebsiteStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub async fn get_config(&self, bucket: &str) -> Result<Option<WebsiteConfiguration>> {
+        self.ensure_bucket_dir(bucket).await?;
+        let path = self.config_path(bucket);
+        match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|err| {
+                MaxioError::InternalError(format!(
+                    "failed to parse bucket website config {}: {err}",
+                    path.display()
+                ))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(MaxioError::Io(err)),
+        }
+    }
+
+    pub async fn set_config(&self, bucket: &str, config: &WebsiteConfiguration) -> Result<()> {
+        self.ensure_bucket_dir(bucket).await?;
+        let path = self.config_path(bucket);
+        let bytes = serde_json::to_vec_pretty(config).map_err(|err| {
+            MaxioError::InternalError(format!(
+                "failed to serialize bucket website config {}: {err}",
+                path.display()
+            ))
+        })?;
+        fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    pub async fn delete_config(&self, bucket: &str) -> Result<()> {
+        self.ensure_bucket_dir(bucket).await?;
+        match fs::remove_file(self.config_path(bucket)).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(MaxioError::Io(err)),
+        }
+    }
+
+    fn config_path(&self, bucket: &str) -> PathBuf {
+        self.root.join(bucket).join(WEBSITE_FILE_NAME)
+    }
+
+    async fn ensure_bucket_dir(&self, bucket: &str) -> Result<()> {
+        match fs::metadata(self.root.join(bucket)).await {
+            Ok(metadata) if metadata.is_dir() => Ok(()),
+            Ok(_) => Err(MaxioError::BucketNotFound(bucket.to_string())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(MaxioError::BucketNotFound(bucket.to_string()))
+            }
+            Err(err) => Err(MaxioError::Io(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::de::from_str as xml_from_str;
+
+    use super::*;
+
+    #[test]
+    fn directory_keys_resolve_to_the_index_document() {
+        let config: WebsiteConfiguration = xml_from_str(
+            "<WebsiteConfiguration><IndexDocument><Suffix>index.html</Suffix></IndexDocument>\
+             </WebsiteConfiguration>",
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.resolve(""), "index.html");
+        assert_eq!(config.resolve("docs/"), "docs/index.html");
+        assert_eq!(config.resolve("docs/page.html"), "docs/page.html");
+    }
+
+    #[test]
+    fn index_suffix_is_required_and_flat() {
+        for xml in [
+            "<WebsiteConfiguration></WebsiteConfiguration>",
+            "<WebsiteConfiguration><IndexDocument><Suffix>a/b.html</Suffix></IndexDocument>\
+             </WebsiteConfiguration>",
+        ] {
+            let config: WebsiteConfiguration = xml_from_str(xml).unwrap();
+            assert!(config.validate().is_err(), "{xml}");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::Request,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use hyper_util::rt::TokioIo;
+use maxio_distributed::{DistributedSys, GRID_PATH, grid_accept_key};
+use tracing::debug;
+
+/// Token every peer must present on its grid connection.
+#[derive(Clone)]
+struct GridToken(Arc<str>);
+
+/// Route peers open their grid connections on. It sits outside the S3
+/// router's signature checks; peers authenticate with the token derived from
+/// the shared root credentials instead.
+pub fn grid_router(distributed: Arc<DistributedSys>, auth_token: String) -> Router {
+    Router::new()
+        .route(GRID_PATH, get(accept_peer))
+        .layer(Extension(distributed))
+        .layer(Extension(GridToken(auth_token.into())))
+}
+
+async fn accept_peer(
+    Extension(distributed): Extension<Arc<DistributedSys>>,
+    Extension(token): Extension<GridToken>,
+    request: Request,
+) -> Response {
+    let headers = request.headers();
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !presented.is_some_and(|presented| tokens_match(presented, &token.0)) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let is_websocket = headers
+        .get(header::UPGRADE)
+        .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"websocket"));
+    let Some(key) = headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .filter(|_| is_websocket)
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let accept = grid_accept_key(key.as_bytes());
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(request).await {
+            Ok(upgraded) => {
+                distributed
+                    .serve_grid_peer("grid peer".to_string(), TokioIo::new(upgraded))
+                    .await;
+            }
+            Err(err) => debug!(error = %err, "grid upgrade failed"),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::UPGRADE, "websocket")
+        .header(header::CONNECTION, "Upgrade")
+        .header(header::SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// Compares without stopping at the first differing byte.
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use maxio_distributed::{
+        ClusterConfig, Connection, DRWMutex, DsyncClient, GridLocker, HandlerRegistry, LocalLocker,
+        NetLocker, grid_auth_token,
+    };
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn peers_lock_through_the_grid_route() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = grid_auth_token("access", "secret");
+        let node =
+            Arc::new(DistributedSys::new(ClusterConfig::single(format!("http://{addr}"))).await);
+        let app = grid_router(Arc::clone(&node), token.clone());
+        tokio::spawn(crate::http::serve_http(listener, app, Duration::ZERO));
+
+        let status = reqwest::Client::new()
+            .get(format!("http://{addr}{GRID_PATH}"))
+            .header("authorization", "Bearer wrong")
+            .header("upgrade", "websocket")
+            .header("connection", "Upgrade")
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .header("sec-websocket-version", "13")
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+
+        let peer = Arc::new(
+            Connection::new(format!("ws://{addr}{GRID_PATH}"), HandlerRegistry::new())
+                .with_auth_token(token),
+        );
+        peer.start().await.unwrap();
+        let client = Arc::new(DsyncClient::new(vec![
+            Arc::new(LocalLocker::new()) as Arc<dyn NetLocker>,
+            Arc::new(GridLocker::new(peer)),
+        ]));
+        let mutex = DRWMutex::new(client, vec!["bucket/key".to_string()], "peer", "test");
+
+        let mut locked = false;
+        for _ in 0..100 {
+            if mutex.lock().await.unwrap() {
+                locked = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(locked, "lock through the grid route never succeeded");
+        assert_eq!(node.held_locks().len(), 1);
+        assert_eq!(node.held_locks()[0].owner, "peer");
+
+        mutex.unlock().await.unwrap();
+        assert!(node.held_locks().is_empty());
+    }
+}
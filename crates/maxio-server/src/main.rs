@@ -1,13 +1,24 @@
-use std::{path::PathBuf, sync::Arc};
+mod grid;
+mod http;
+mod tls;
 
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use axum::Extension;
 use clap::Parser;
 use maxio_auth::credentials::{CredentialProvider, StaticCredentialProvider};
-use maxio_distributed::{ClusterConfig, DistributedSys};
+use maxio_distributed::{ClusterConfig, DistributedSys, HealEngine, grid_auth_token};
 use maxio_iam::IAMSys;
 use maxio_lifecycle::{LifecycleStore, LifecycleSys};
-use maxio_notification::{NotificationStore, NotificationSys, WebhookTarget};
+use maxio_notification::{NotificationStore, NotificationSys, TargetConfig, target_arn};
+use maxio_s3_api::{content_type::parse_switch, timeouts::RequestTimeouts, website::WebsiteStore};
 use maxio_storage::{
-    erasure::{ErasureConfig, objects::ErasureObjectLayer},
+    access_tracker::AccessTracker,
+    erasure::{ErasureConfig, sets::ErasureObjectLayer},
+    listing_cache::{CachedObjectLayer, ListingCacheConfig},
+    multipart_sweep::MultipartSweeper,
+    naming::{KeyNamePolicy, set_key_name_policy},
+    read_cache::{ReadCacheConfig, ReadCachedObjectLayer},
     single::SingleDiskObjectLayer,
     traits::ObjectLayer,
 };
@@ -31,6 +42,96 @@ struct Cli {
 
     #[arg(long)]
     disks: Option<String>,
+
+    /// PEM certificate chain; serves HTTPS together with `--tls-key`.
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for `--tls-cert`.
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
+
+    /// Directory of `<hostname>/public.crt` + `private.key` pairs served by SNI.
+    #[arg(long)]
+    tls_certs_dir: Option<PathBuf>,
+
+    /// Plain HTTP port that redirects every request to HTTPS.
+    #[arg(long)]
+    http_redirect_port: Option<u16>,
+
+    /// Encrypt every object uploaded without encryption headers with SSE-S3.
+    /// Also enabled by `MAXIO_KMS_AUTO_ENCRYPTION=on`.
+    #[arg(long, default_value_t = false)]
+    auto_encrypt: bool,
+
+    /// Serve repeated identical object listings from memory for this many
+    /// milliseconds. Writes through this server invalidate affected entries.
+    /// Disabled when 0.
+    #[arg(long, default_value_t = 0)]
+    list_cache_ttl_ms: u64,
+
+    /// Maximum number of cached object listings.
+    #[arg(long, default_value_t = 1024)]
+    list_cache_entries: usize,
+
+    /// Keep up to this many MiB of recently read objects in memory, checked
+    /// against the object's ETag on every hit. Disabled when 0.
+    #[arg(long, default_value_t = 0)]
+    read_cache_mb: u64,
+
+    /// Largest object, in KiB, the read cache holds.
+    #[arg(long, default_value_t = 1024)]
+    read_cache_max_object_kb: u64,
+
+    /// Remove multipart uploads left unfinished for this many hours, whether
+    /// or not a lifecycle rule covers them. Disabled when 0.
+    #[arg(long, default_value_t = 24)]
+    stale_uploads_expiry_hours: u64,
+
+    /// Record when objects are read, for lifecycle rules that expire idle
+    /// objects, stamping each object at most once per this many minutes.
+    /// Disabled when 0.
+    #[arg(long, default_value_t = 60)]
+    access_time_resolution_minutes: u64,
+}
+
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+const STALE_UPLOADS_SWEEP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const DISK_RECOVERY_INTERVAL: Duration = Duration::from_secs(30);
+const ACCESS_TIME_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn tls_options(cli: &Cli) -> std::io::Result<Option<tls::TlsOptions>> {
+    let cert = cli
+        .tls_cert
+        .clone()
+        .or_else(|| env_path("MAXIO_TLS_CERT_FILE"));
+    let key = cli
+        .tls_key
+        .clone()
+        .or_else(|| env_path("MAXIO_TLS_KEY_FILE"));
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(Some(tls::TlsOptions {
+            cert,
+            key,
+            certs_dir: cli
+                .tls_certs_dir
+                .clone()
+                .or_else(|| env_path("MAXIO_TLS_CERTS_DIR")),
+        })),
+        (None, None) => Ok(None),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--tls-cert and --tls-key must be set together",
+        )),
+    }
 }
 
 #[tokio::main]
@@ -40,7 +141,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let cli = Cli::parse();
     let addr = format!("{}:{}", cli.host, cli.port);
-    let (object_layer, notification_root): (Arc<dyn ObjectLayer>, PathBuf) = if cli.erasure {
+    set_key_name_policy(KeyNamePolicy::from_env());
+    let tls_config = tls_options(&cli)?;
+    let auto_encrypt = cli.auto_encrypt
+        || std::env::var("MAXIO_KMS_AUTO_ENCRYPTION")
+            .ok()
+            .as_deref()
+            .and_then(parse_switch)
+            .unwrap_or(false);
+    let (object_layer, notification_root, heal_engines): (
+        Arc<dyn ObjectLayer>,
+        PathBuf,
+        Vec<HealEngine>,
+    ) = if cli.erasure {
         let disks = cli.disks.as_deref().ok_or_else(|| {
             std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -62,22 +175,69 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .into());
         }
 
+        if auto_encrypt {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "automatic SSE-S3 encryption is not supported with --erasure",
+            )
+            .into());
+        }
+
         let notification_root = disk_paths[0].clone();
-        (
-            Arc::new(ErasureObjectLayer::new(disk_paths, ErasureConfig::default()).await?),
-            notification_root,
-        )
+        let config = ErasureConfig::default();
+        let set_size = config.total_shards();
+        let object_layer = ErasureObjectLayer::new(disk_paths.clone(), config.clone()).await?;
+        object_layer.start_disk_recovery(DISK_RECOVERY_INTERVAL);
+        let heal_engines = disk_paths
+            .chunks(set_size)
+            .map(|set_disks| HealEngine::new(set_disks.to_vec(), config.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        (Arc::new(object_layer), notification_root, heal_engines)
     } else {
         let data_dir = PathBuf::from(&cli.data_dir);
         tokio::fs::create_dir_all(&data_dir).await?;
         (
-            Arc::new(SingleDiskObjectLayer::new(data_dir.clone()).await?),
+            Arc::new(
+                SingleDiskObjectLayer::new(data_dir.clone())
+                    .await?
+                    .with_auto_encryption(auto_encrypt),
+            ),
             data_dir,
+            Vec::new(),
         )
     };
+    let read_cache = ReadCacheConfig {
+        max_bytes: cli.read_cache_mb * 1024 * 1024,
+        max_object_size: cli.read_cache_max_object_kb * 1024,
+    };
+    let object_layer: Arc<dyn ObjectLayer> = if read_cache.enabled() {
+        info!(
+            size_mb = cli.read_cache_mb,
+            max_object_kb = cli.read_cache_max_object_kb,
+            "object read cache enabled"
+        );
+        Arc::new(ReadCachedObjectLayer::new(object_layer, read_cache))
+    } else {
+        object_layer
+    };
+    let listing_cache = ListingCacheConfig {
+        ttl: Duration::from_millis(cli.list_cache_ttl_ms),
+        max_entries: cli.list_cache_entries,
+    };
+    let object_layer: Arc<dyn ObjectLayer> = if listing_cache.enabled() {
+        info!(
+            ttl_ms = cli.list_cache_ttl_ms,
+            entries = cli.list_cache_entries,
+            "object listing cache enabled"
+        );
+        Arc::new(CachedObjectLayer::new(object_layer, listing_cache))
+    } else {
+        object_layer
+    };
     let access_key = std::env::var("MAXIO_ROOT_USER").unwrap_or_else(|_| "minioadmin".to_string());
     let secret_key =
         std::env::var("MAXIO_ROOT_PASSWORD").unwrap_or_else(|_| "minioadmin".to_string());
+    let grid_token = grid_auth_token(&access_key, &secret_key);
     let iam_data_dir = PathBuf::from(&cli.data_dir);
     tokio::fs::create_dir_all(&iam_data_dir).await?;
     let iam = Arc::new(IAMSys::new(&iam_data_dir).await?);
@@ -85,18 +245,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         StaticCredentialProvider::with_iam(access_key, secret_key, Arc::clone(&iam)),
     );
 
-    let mut notification_sys = NotificationSys::new(NotificationStore::new(notification_root.clone()));
+    let mut notification_sys =
+        NotificationSys::load(NotificationStore::new(notification_root.clone())).await?;
+    // The environment's webhook lives only as long as the variable does.
+    // Earlier releases persisted it, so a stored copy is dropped first.
+    let webhook_arn = target_arn("", "1", "webhook");
+    notification_sys.remove_target(&webhook_arn).await?;
     if let Ok(endpoint) = std::env::var("MAXIO_NOTIFY_WEBHOOK_ENDPOINT") {
         let endpoint = endpoint.trim();
         if !endpoint.is_empty() {
-            notification_sys.register_target(
-                "webhook".to_string(),
-                Box::new(WebhookTarget::new(endpoint.to_string())),
+            notification_sys.register_config(
+                webhook_arn.clone(),
+                &TargetConfig::Webhook {
+                    endpoint: endpoint.to_string(),
+                    batch_window_ms: std::env::var("MAXIO_NOTIFY_WEBHOOK_BATCH_WINDOW_MS")
+                        .ok()
+                        .and_then(|value| value.trim().parse().ok()),
+                },
             );
-            info!("webhook notification target enabled");
+            info!(arn = %webhook_arn, "webhook notification target enabled");
         }
     }
     let notification_sys = Arc::new(notification_sys);
+    let website_store = Arc::new(WebsiteStore::new(notification_root.clone()));
     let lifecycle_store_root = notification_root.clone();
     let lifecycle_sys = Arc::new(LifecycleSys::new(
         LifecycleStore::new(lifecycle_store_root),
@@ -119,10 +290,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
     info!("lifecycle background scanner enabled");
 
+    if cli.stale_uploads_expiry_hours > 0 {
+        let sweeper = Arc::new(MultipartSweeper::new(
+            Arc::clone(&object_layer),
+            Duration::from_secs(cli.stale_uploads_expiry_hours * 60 * 60),
+        ));
+        sweeper.start(STALE_UPLOADS_SWEEP_INTERVAL);
+        info!(
+            expiry_hours = cli.stale_uploads_expiry_hours,
+            "stale multipart upload sweeper enabled"
+        );
+    }
+
+    let access_tracker = (cli.access_time_resolution_minutes > 0).then(|| {
+        let tracker = Arc::new(AccessTracker::new(
+            Arc::clone(&object_layer),
+            Duration::from_secs(cli.access_time_resolution_minutes * 60),
+        ));
+        Arc::clone(&tracker).start(ACCESS_TIME_FLUSH_INTERVAL);
+        info!(
+            resolution_minutes = cli.access_time_resolution_minutes,
+            "object access time tracking enabled"
+        );
+        tracker
+    });
+
     let default_node_endpoint = format!("http://127.0.0.1:{}", cli.port);
     let cluster_config = ClusterConfig::from_env()
         .unwrap_or_else(|| ClusterConfig::single(default_node_endpoint));
-    let distributed_sys = Arc::new(DistributedSys::new(cluster_config).await);
+    let grid_tls = tls_config
+        .as_ref()
+        .map(tls::grid_client_config)
+        .transpose()?;
+    let distributed_sys = Arc::new(
+        DistributedSys::new(cluster_config)
+            .await
+            .with_heal_engines(heal_engines)
+            .with_grid_peers(&grid_token, grid_tls)
+            .await,
+    );
+    let grid = grid::grid_router(Arc::clone(&distributed_sys), grid_token);
 
     let app = maxio_s3_api::router::s3_router(
         object_layer,
@@ -131,11 +338,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         notification_sys,
         lifecycle_sys,
         distributed_sys,
+        website_store,
     );
+    let app = match access_tracker {
+        Some(tracker) => app.layer(Extension(tracker)),
+        None => app,
+    };
+    let app = app.merge(grid);
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    info!("maxio server listening on {addr}");
-    axum::serve(listener, app).await?;
+    if auto_encrypt {
+        info!("automatic SSE-S3 encryption enabled for new objects");
+    }
+    let header_read_timeout = RequestTimeouts::from_env().header_read;
+    let Some(tls_config) = tls_config else {
+        info!("maxio server listening on {addr}");
+        http::serve_http(listener, app, header_read_timeout).await?;
+        return Ok(());
+    };
+
+    let resolver = Arc::new(tls::CertResolver::load(tls_config)?);
+    resolver.spawn_reloader(TLS_RELOAD_INTERVAL);
+    if let Some(redirect_port) = cli.http_redirect_port {
+        let redirect_addr = format!("{}:{redirect_port}", cli.host);
+        let redirect_listener = tokio::net::TcpListener::bind(&redirect_addr).await?;
+        info!("redirecting http on {redirect_addr} to https");
+        let https_port = cli.port;
+        tokio::spawn(async move {
+            if let Err(err) = tls::serve_redirect(redirect_listener, https_port).await {
+                warn!(error = %err, "http redirect listener stopped");
+            }
+        });
+    }
+    info!("maxio server listening on {addr} (https)");
+    tls::serve_tls(
+        listener,
+        app,
+        tls::server_config(resolver)?,
+        header_read_timeout,
+    )
+    .await?;
 
     Ok(())
 }
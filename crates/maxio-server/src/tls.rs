@@ -0,0 +1,392 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use hyper_util::{rt::TokioIo, service::TowerToHyperService};
+use rustls::{
+    ClientConfig, RootCertStore, ServerConfig,
+    crypto::ring::{default_provider, sign::any_supported_type},
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+
+use crate::http::connection_builder;
+
+/// Certificate file name inside each per-host directory of the certs dir.
+pub const PUBLIC_CERT_FILE: &str = "public.crt";
+/// Private key file name inside each per-host directory of the certs dir.
+pub const PRIVATE_KEY_FILE: &str = "private.key";
+
+/// Where the listener's certificates live. `cert`/`key` are served by default;
+/// every `<certs_dir>/<hostname>/{public.crt,private.key}` pair is served to
+/// clients asking for that hostname via SNI.
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub certs_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct LoadedCerts {
+    default: Arc<CertifiedKey>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// Picks the certificate for each handshake and reloads it in place when the
+/// files on disk change.
+#[derive(Debug)]
+pub struct CertResolver {
+    options: TlsOptions,
+    certs: RwLock<LoadedCerts>,
+}
+
+impl CertResolver {
+    pub fn load(options: TlsOptions) -> io::Result<Self> {
+        let certs = load_certs(&options)?;
+        Ok(Self {
+            options,
+            certs: RwLock::new(certs),
+        })
+    }
+
+    /// Reloads every certificate when any watched file changed, appeared or
+    /// disappeared. Returns whether a reload happened; on error the previous
+    /// certificates stay in use.
+    pub fn reload_if_changed(&self) -> io::Result<bool> {
+        let current = watched_files(&self.options)?;
+        let unchanged = self
+            .certs
+            .read()
+            .map(|certs| certs.modified == current)
+            .unwrap_or(false);
+        if unchanged {
+            return Ok(false);
+        }
+
+        let certs = load_certs(&self.options)?;
+        if let Ok(mut guard) = self.certs.write() {
+            *guard = certs;
+        }
+        Ok(true)
+    }
+
+    /// Polls the certificate files every `interval` and reloads them on change.
+    pub fn spawn_reloader(self: &Arc<Self>, interval: Duration) {
+        let resolver = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match resolver.reload_if_changed() {
+                    Ok(true) => info!("reloaded tls certificates"),
+                    Ok(false) => {}
+                    Err(err) => warn!(error = %err, "failed to reload tls certificates"),
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.read().ok()?;
+        let by_name = client_hello
+            .server_name()
+            .and_then(|name| certs.by_name.get(&name.to_ascii_lowercase()));
+        Some(Arc::clone(by_name.unwrap_or(&certs.default)))
+    }
+}
+
+/// rustls server config offering HTTP/2 and HTTP/1.1 with certificates from
+/// `resolver`.
+pub fn server_config(resolver: Arc<CertResolver>) -> io::Result<Arc<ServerConfig>> {
+    let mut config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// rustls client config for dialing the grid endpoints of peers over
+/// `wss://`. Nodes of a cluster share a certificate or the CA that signed
+/// theirs, so the certificates in `options.cert` are what is trusted.
+pub fn grid_client_config(options: &TlsOptions) -> io::Result<Arc<ClientConfig>> {
+    let certs = CertificateDer::pem_file_iter(&options.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| invalid_data(&options.cert, err))?;
+    let mut roots = RootCertStore::empty();
+    let (added, _) = roots.add_parsable_certificates(certs);
+    if added == 0 {
+        return Err(invalid_data(&options.cert, "no certificates found"));
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Serves `app` over TLS on `listener`. Failed handshakes only drop that
+/// connection, as do handshakes not finished within `header_read_timeout`;
+/// zero disables that limit.
+pub async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    config: Arc<ServerConfig>,
+    header_read_timeout: Duration,
+) -> io::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                warn!(error = %err, "failed to accept connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let handshake = acceptor.accept(stream);
+            let handshake = if header_read_timeout.is_zero() {
+                handshake.await
+            } else {
+                match tokio::time::timeout(header_read_timeout, handshake).await {
+                    Ok(handshake) => handshake,
+                    Err(_) => {
+                        debug!(%peer, "tls handshake timed out");
+                        return;
+                    }
+                }
+            };
+            let stream = match handshake {
+                Ok(stream) => stream,
+                Err(err) => {
+                    debug!(%peer, error = %err, "tls handshake failed");
+                    return;
+                }
+            };
+            if let Err(err) = connection_builder(header_read_timeout)
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!(%peer, error = %err, "https connection closed with error");
+            }
+        });
+    }
+}
+
+/// Answers every plain HTTP request on `listener` with a permanent redirect to
+/// the same path on the HTTPS port.
+pub async fn serve_redirect(listener: TcpListener, https_port: u16) -> io::Result<()> {
+    let app = Router::new()
+        .fallback(redirect_to_https)
+        .with_state(https_port);
+    axum::serve(listener, app).await
+}
+
+async fn redirect_to_https(State(https_port): State<u16>, request: Request) -> Response {
+    let Some(host) = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+
+    (
+        StatusCode::PERMANENT_REDIRECT,
+        [(header::LOCATION, https_location(host, https_port, path))],
+    )
+        .into_response()
+}
+
+fn https_location(host: &str, https_port: u16, path: &str) -> String {
+    // Drop any port from the Host header; bracketed IPv6 hosts keep brackets.
+    let hostname = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') && port.parse::<u16>().is_ok() => name,
+        _ => host,
+    };
+    if https_port == 443 {
+        format!("https://{hostname}{path}")
+    } else {
+        format!("https://{hostname}:{https_port}{path}")
+    }
+}
+
+fn load_certs(options: &TlsOptions) -> io::Result<LoadedCerts> {
+    // Snapshot before reading so a write racing the load triggers another one.
+    let modified = watched_files(options)?;
+    let default = load_certified_key(&options.cert, &options.key)?;
+
+    let mut by_name = HashMap::new();
+    for (name, cert, key) in sni_pairs(options)? {
+        by_name.insert(name, load_certified_key(&cert, &key)?);
+    }
+
+    Ok(LoadedCerts {
+        default,
+        by_name,
+        modified,
+    })
+}
+
+fn load_certified_key(cert: &Path, key: &Path) -> io::Result<Arc<CertifiedKey>> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| invalid_data(cert, err))?;
+    if chain.is_empty() {
+        return Err(invalid_data(cert, "no certificates found"));
+    }
+    let key_der = PrivateKeyDer::from_pem_file(key).map_err(|err| invalid_data(key, err))?;
+    let signing_key = any_supported_type(&key_der).map_err(|err| invalid_data(key, err))?;
+    Ok(Arc::new(CertifiedKey::new(chain, signing_key)))
+}
+
+/// `(hostname, cert, key)` for every complete pair under the certs dir.
+fn sni_pairs(options: &TlsOptions) -> io::Result<Vec<(String, PathBuf, PathBuf)>> {
+    let Some(dir) = options.certs_dir.as_deref() else {
+        return Ok(Vec::new());
+    };
+
+    let mut pairs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let cert = path.join(PUBLIC_CERT_FILE);
+        let key = path.join(PRIVATE_KEY_FILE);
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if cert.is_file() && key.is_file() {
+            pairs.push((name.to_ascii_lowercase(), cert, key));
+        }
+    }
+    pairs.sort();
+    Ok(pairs)
+}
+
+fn watched_files(options: &TlsOptions) -> io::Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let mut files = vec![options.cert.clone(), options.key.clone()];
+    for (_, cert, key) in sni_pairs(options)? {
+        files.push(cert);
+        files.push(key);
+    }
+    Ok(files
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            (path, modified)
+        })
+        .collect())
+}
+
+fn invalid_data(path: &Path, err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid tls file {}: {err}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+
+    use super::*;
+
+    const TEST_CERT: &str = include_str!("../testdata/tls/public.crt");
+
+    fn test_options() -> TlsOptions {
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/tls");
+        TlsOptions {
+            cert: testdata.join(PUBLIC_CERT_FILE),
+            key: testdata.join(PRIVATE_KEY_FILE),
+            certs_dir: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_tls_connections_with_the_configured_cert() {
+        let resolver = Arc::new(CertResolver::load(test_options()).expect("load certs"));
+        assert!(!resolver.reload_if_changed().expect("check certs"));
+        let config = server_config(resolver).expect("server config");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/minio/health/live", get(|| async { "ok" }));
+        tokio::spawn(serve_tls(listener, app, config, Duration::ZERO));
+
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(TEST_CERT.as_bytes()).unwrap())
+            .resolve("localhost", addr)
+            .build()
+            .unwrap();
+        let response = client
+            .get(format!(
+                "https://localhost:{}/minio/health/live",
+                addr.port()
+            ))
+            .send()
+            .await
+            .expect("https request");
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn stalled_handshakes_are_dropped() {
+        use tokio::io::AsyncReadExt;
+
+        let resolver = Arc::new(CertResolver::load(test_options()).expect("load certs"));
+        let config = server_config(resolver).expect("server config");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/minio/health/live", get(|| async { "ok" }));
+        tokio::spawn(serve_tls(listener, app, config, Duration::from_millis(200)));
+
+        // Connects but never sends a ClientHello.
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("server closes the stalled connection");
+        assert!(matches!(read, Ok(0) | Err(_)));
+    }
+
+    #[test]
+    fn redirect_targets_the_https_port() {
+        assert_eq!(
+            https_location("example.com:9080", 9000, "/bucket/key?versionId=1"),
+            "https://example.com:9000/bucket/key?versionId=1"
+        );
+        assert_eq!(https_location("[::1]:80", 443, "/"), "https://[::1]/");
+        assert_eq!(https_location("[::1]", 9000, "/"), "https://[::1]:9000/");
+    }
+}
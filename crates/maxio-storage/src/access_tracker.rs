@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use maxio_common::error::MaxioError;
+use tracing::{debug, warn};
+
+use crate::traits::ObjectLayer;
+
+/// Default for how stale a recorded access time may get; an object read
+/// again within this window is not stamped again.
+pub const DEFAULT_ACCESS_RESOLUTION: Duration = Duration::from_secs(60 * 60);
+
+type ObjectKey = (String, String);
+
+/// Collects object reads and writes their access times to the object layer
+/// in the background, so reads never wait on a metadata write. An object is
+/// stamped at most once per resolution window, however often it is read.
+pub struct AccessTracker {
+    object_layer: Arc<dyn ObjectLayer>,
+    resolution: chrono::Duration,
+    state: Mutex<AccessState>,
+}
+
+#[derive(Default)]
+struct AccessState {
+    /// Reads not yet written, with the time of the first one.
+    pending: HashMap<ObjectKey, DateTime<Utc>>,
+    /// Access times written within the last resolution window.
+    recorded: HashMap<ObjectKey, DateTime<Utc>>,
+}
+
+impl AccessTracker {
+    pub fn new(object_layer: Arc<dyn ObjectLayer>, resolution: Duration) -> Self {
+        Self {
+            object_layer,
+            resolution: chrono::Duration::from_std(resolution).unwrap_or(chrono::Duration::MAX),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Notes a read of `key` now. Returns at once; the access time is
+    /// written by the next [`flush`](Self::flush).
+    pub fn record(&self, bucket: &str, key: &str) {
+        let now = Utc::now();
+        let object = (bucket.to_string(), key.to_string());
+        let mut state = self.lock();
+        if state
+            .recorded
+            .get(&object)
+            .is_some_and(|recorded| now - *recorded < self.resolution)
+        {
+            return;
+        }
+        state.pending.entry(object).or_insert(now);
+    }
+
+    /// Writes every pending access time and returns how many were written.
+    /// Objects deleted since they were read are skipped.
+    pub async fn flush(&self) -> u64 {
+        let pending = std::mem::take(&mut self.lock().pending);
+        let mut written = Vec::with_capacity(pending.len());
+        for ((bucket, key), accessed) in pending {
+            match self
+                .object_layer
+                .record_access(&bucket, &key, accessed)
+                .await
+            {
+                Ok(()) => written.push(((bucket, key), accessed)),
+                Err(MaxioError::ObjectNotFound { .. } | MaxioError::BucketNotFound(_)) => {}
+                Err(err) => {
+                    warn!(bucket = %bucket, key = %key, error = %err, "failed to record object access time");
+                }
+            }
+        }
+
+        let count = written.len() as u64;
+        let now = Utc::now();
+        let mut state = self.lock();
+        state
+            .recorded
+            .retain(|_, recorded| now - *recorded < self.resolution);
+        state.recorded.extend(written);
+        count
+    }
+
+    /// Flushes once every `interval`.
+    pub fn start(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let written = self.flush().await;
+                if written > 0 {
+                    debug!(written, "recorded object access times");
+                }
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AccessState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::single::SingleDiskObjectLayer;
+    use crate::traits::ObjectVersion;
+
+    async fn latest_version(layer: &dyn ObjectLayer, key: &str) -> ObjectVersion {
+        layer
+            .list_object_versions("bucket", key, "", "", "", 1000)
+            .await
+            .unwrap()
+            .versions
+            .into_iter()
+            .find(|version| version.key == key && version.is_latest)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_are_stamped_once_per_resolution_window() {
+        let root = std::env::temp_dir().join(format!("maxio-access-{}", Uuid::new_v4()));
+        let layer: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        layer.make_bucket("bucket").await.unwrap();
+        let written = layer
+            .put_object(
+                "bucket",
+                "key",
+                Bytes::from_static(b"data"),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            latest_version(layer.as_ref(), "key").await.last_access,
+            None
+        );
+
+        let tracker = AccessTracker::new(Arc::clone(&layer), DEFAULT_ACCESS_RESOLUTION);
+        tracker.record("bucket", "key");
+        tracker.record("bucket", "missing");
+        assert_eq!(tracker.flush().await, 1);
+        let stamped = latest_version(layer.as_ref(), "key").await;
+        assert!(stamped.last_access.is_some());
+        assert_eq!(stamped.last_modified, written.last_modified);
+        let info = layer.get_object_info("bucket", "key", None).await.unwrap();
+        assert_eq!(info.etag, written.etag);
+
+        tracker.record("bucket", "key");
+        assert_eq!(tracker.flush().await, 0);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
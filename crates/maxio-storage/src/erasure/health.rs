@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+pub const DEFAULT_OFFLINE_THRESHOLD: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DiskState {
+    Online,
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskHealth {
+    pub index: usize,
+    pub path: PathBuf,
+    pub state: DiskState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub last_error: Option<String>,
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct TrackerState {
+    disks: Vec<DiskHealth>,
+    offline_threshold: u32,
+    events: Option<mpsc::Sender<DiskHealth>>,
+}
+
+/// Tracks I/O failures per disk of an erasure set. A disk that fails
+/// `offline_threshold` operations in a row is marked offline and skipped by
+/// reads, writes and quorum accounting until a recovery probe brings it
+/// back.
+#[derive(Debug, Clone)]
+pub struct DiskHealthTracker {
+    inner: Arc<RwLock<TrackerState>>,
+}
+
+impl DiskHealthTracker {
+    pub fn new(disk_paths: &[PathBuf], offline_threshold: u32) -> Self {
+        let disks = disk_paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| DiskHealth {
+                index,
+                path: path.clone(),
+                state: DiskState::Online,
+                consecutive_failures: 0,
+                total_failures: 0,
+                last_error: None,
+                last_failure: None,
+            })
+            .collect();
+
+        Self {
+            inner: Arc::new(RwLock::new(TrackerState {
+                disks,
+                offline_threshold: offline_threshold.max(1),
+                events: None,
+            })),
+        }
+    }
+
+    /// State transitions are sent here so heal and discovery can react.
+    pub fn set_event_sender(&self, sender: mpsc::Sender<DiskHealth>) {
+        self.write_state().events = Some(sender);
+    }
+
+    pub fn is_online(&self, index: usize) -> bool {
+        self.read_state()
+            .disks
+            .get(index)
+            .is_some_and(|disk| disk.state == DiskState::Online)
+    }
+
+    pub fn online_count(&self) -> usize {
+        self.read_state()
+            .disks
+            .iter()
+            .filter(|disk| disk.state == DiskState::Online)
+            .count()
+    }
+
+    pub fn snapshot(&self) -> Vec<DiskHealth> {
+        self.read_state().disks.clone()
+    }
+
+    pub fn record_success(&self, index: usize) {
+        let mut state = self.write_state();
+        if let Some(disk) = state.disks.get_mut(index) {
+            disk.consecutive_failures = 0;
+        }
+    }
+
+    /// Returns true when this failure took the disk offline.
+    pub fn record_failure(&self, index: usize, error: impl ToString) -> bool {
+        let mut state = self.write_state();
+        let threshold = state.offline_threshold;
+        let Some(disk) = state.disks.get_mut(index) else {
+            return false;
+        };
+
+        disk.consecutive_failures = disk.consecutive_failures.saturating_add(1);
+        disk.total_failures = disk.total_failures.saturating_add(1);
+        disk.last_error = Some(error.to_string());
+        disk.last_failure = Some(Utc::now());
+
+        if disk.state == DiskState::Offline || disk.consecutive_failures < threshold {
+            return false;
+        }
+
+        disk.state = DiskState::Offline;
+        warn!(
+            disk = %disk.path.display(),
+            failures = disk.consecutive_failures,
+            error = disk.last_error.as_deref().unwrap_or_default(),
+            "marking disk offline after repeated failures"
+        );
+        let snapshot = disk.clone();
+        notify(&state.events, snapshot);
+        true
+    }
+
+    /// Brings a disk back once it passes a probe again.
+    pub fn mark_online(&self, index: usize) {
+        let mut state = self.write_state();
+        let Some(disk) = state.disks.get_mut(index) else {
+            return;
+        };
+        if disk.state == DiskState::Online {
+            return;
+        }
+
+        disk.state = DiskState::Online;
+        disk.consecutive_failures = 0;
+        info!(disk = %disk.path.display(), "disk marked online");
+        let snapshot = disk.clone();
+        notify(&state.events, snapshot);
+    }
+
+    fn read_state(&self) -> std::sync::RwLockReadGuard<'_, TrackerState> {
+        self.inner
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_state(&self) -> std::sync::RwLockWriteGuard<'_, TrackerState> {
+        self.inner
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn notify(events: &Option<mpsc::Sender<DiskHealth>>, disk: DiskHealth) {
+    if let Some(sender) = events {
+        let _ = sender.try_send(disk);
+    }
+}
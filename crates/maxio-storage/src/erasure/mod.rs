@@ -1,19 +1,32 @@
 use maxio_common::error::{MaxioError, Result};
+use maxio_common::types::REDUCED_REDUNDANCY_STORAGE_CLASS;
 use reed_solomon_simd::{ReedSolomonDecoder, ReedSolomonEncoder};
 use serde::{Deserialize, Serialize};
 
+pub mod health;
 pub mod objects;
+pub mod sets;
 pub mod storage;
 
 pub const DEFAULT_DATA_SHARDS: usize = 4;
 pub const DEFAULT_PARITY_SHARDS: usize = 2;
 pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+/// Parity shards used for `REDUCED_REDUNDANCY` objects.
+pub const REDUCED_REDUNDANCY_PARITY_SHARDS: usize = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErasureConfig {
     pub data_shards: usize,
     pub parity_shards: usize,
     pub block_size: usize,
+    /// Disks that must agree on metadata for an object to be readable.
+    /// Defaults to `data_shards`.
+    #[serde(default)]
+    pub read_quorum: Option<usize>,
+    /// Disks that must acknowledge a write or delete. Defaults to
+    /// `data_shards`.
+    #[serde(default)]
+    pub write_quorum: Option<usize>,
 }
 
 impl Default for ErasureConfig {
@@ -22,6 +35,8 @@ impl Default for ErasureConfig {
             data_shards: DEFAULT_DATA_SHARDS,
             parity_shards: DEFAULT_PARITY_SHARDS,
             block_size: DEFAULT_BLOCK_SIZE,
+            read_quorum: None,
+            write_quorum: None,
         }
     }
 }
@@ -31,10 +46,41 @@ impl ErasureConfig {
         self.data_shards + self.parity_shards
     }
 
+    pub fn read_quorum(&self) -> usize {
+        self.read_quorum.unwrap_or(self.data_shards)
+    }
+
+    pub fn write_quorum(&self) -> usize {
+        self.write_quorum.unwrap_or(self.data_shards)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        validate_config(self)
+    }
+
+    /// Shard layout for objects of `storage_class`. Reduced redundancy keeps
+    /// the same disks but turns all but
+    /// [`REDUCED_REDUNDANCY_PARITY_SHARDS`] parity shards into data shards.
+    pub fn for_storage_class(&self, storage_class: Option<&str>) -> Self {
+        if storage_class != Some(REDUCED_REDUNDANCY_STORAGE_CLASS)
+            || self.parity_shards <= REDUCED_REDUNDANCY_PARITY_SHARDS
+        {
+            return self.clone();
+        }
+        let data_shards = self.total_shards() - REDUCED_REDUNDANCY_PARITY_SHARDS;
+        Self {
+            data_shards,
+            parity_shards: REDUCED_REDUNDANCY_PARITY_SHARDS,
+            block_size: self.block_size,
+            read_quorum: self.read_quorum.map(|quorum| quorum.max(data_shards)),
+            write_quorum: self.write_quorum.map(|quorum| quorum.max(data_shards)),
+        }
+    }
+
     pub fn shard_size(&self) -> Result<usize> {
         validate_config(self)?;
         let mut shard_size = self.block_size.div_ceil(self.data_shards);
-        if shard_size % 2 != 0 {
+        if !shard_size.is_multiple_of(2) {
             shard_size += 1;
         }
         Ok(shard_size)
@@ -50,6 +96,15 @@ pub struct ErasureInfo {
     pub block_checksums: Vec<String>,
 }
 
+/// An object whose metadata exists on some disks but falls short of read
+/// quorum, so it cannot be served and needs to be healed or purged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialObject {
+    pub bucket: String,
+    pub key: String,
+    pub disks_with_meta: Vec<usize>,
+}
+
 pub fn encode_block(data: &[u8], config: &ErasureConfig) -> Result<Vec<Vec<u8>>> {
     validate_config(config)?;
 
@@ -177,6 +232,18 @@ fn validate_config(config: &ErasureConfig) -> Result<()> {
             "block_size must be greater than zero".to_string(),
         ));
     }
+    for (name, quorum) in [
+        ("read_quorum", config.read_quorum()),
+        ("write_quorum", config.write_quorum()),
+    ] {
+        if quorum < config.data_shards || quorum > config.total_shards() {
+            return Err(MaxioError::InvalidArgument(format!(
+                "{name} must be between {} and {}, got {quorum}",
+                config.data_shards,
+                config.total_shards()
+            )));
+        }
+    }
     Ok(())
 }
 
@@ -1,32 +1,106 @@
-use std::collections::HashMap;
-use std::path::{Component, Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use maxio_common::error::{MaxioError, Result};
-use maxio_common::types::{BucketInfo, ObjectInfo};
-use md5::Md5;
+use maxio_common::hash::md5_hex;
+use maxio_common::types::{BucketInfo, ObjectInfo, REDUCED_REDUNDANCY_STORAGE_CLASS};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
 
 use md5::Digest as _;
 
+use crate::erasure::health::DiskHealth;
 use crate::erasure::storage::ErasureStorage;
-use crate::erasure::{ErasureConfig, ErasureInfo, decode_block, encode_block};
+use crate::erasure::{ErasureConfig, ErasureInfo, PartialObject, decode_block, encode_block};
+use crate::key_lock::KeyLocks;
+use crate::naming::{
+    key_path_error, validate_bucket_name, validate_new_bucket_name, validate_object_key,
+    validate_prefix_rename,
+};
+use crate::storage_info::{DiskInfo, StorageInfo};
 use crate::traits::{
-    CompletePart, GetEncryptionOptions, ListObjectsResult, MultipartUploadInfo, ObjectLayer,
-    ObjectVersion, PartInfo, PutEncryptionOptions, VersioningState,
+    CompletePart, CopyMetadataFn, CopySource, DeleteCondition, DeletedObject, GetEncryptionOptions,
+    ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult, ListOrder,
+    ObjectLayer, ObjectPartInfo, ObjectVersion, PartInfo, PutEncryptionOptions,
+    STORAGE_CLASS_META_KEY, VersioningState, copy_by_reading,
 };
+use crate::xl::storage::{NULL_VERSION_ID, XlStorage, paginate_objects, paginate_versions};
 
 const META_FILE_NAME: &str = "xl.meta";
 const DATA_PART_FILE_NAME: &str = "part.1";
 const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+const SYS_DIR_NAME: &str = ".maxio.sys";
+const TRASH_DIR_NAME: &str = "trash";
+const MULTIPART_DIR_NAME: &str = ".multipart";
 
+/// A group of disks that erasure-codes objects across all of its members.
 #[derive(Debug, Clone)]
-pub struct ErasureObjectLayer {
+pub struct ErasureSet {
     storage: ErasureStorage,
+    heal_sender: Option<mpsc::Sender<PartialObject>>,
+    key_locks: KeyLocks,
+}
+
+/// Data of an object being written, read one erasure block at a time.
+enum ObjectSource<'a> {
+    Bytes(&'a [u8]),
+    /// Staged multipart part files, opened in turn so the assembled object
+    /// is never held in memory.
+    Parts {
+        paths: &'a [PathBuf],
+        current: Option<fs::File>,
+    },
+}
+
+impl ObjectSource<'_> {
+    /// Fills `block` as far as the source allows; a short count means the
+    /// source is exhausted.
+    async fn fill(&mut self, block: &mut [u8]) -> Result<usize> {
+        match self {
+            Self::Bytes(data) => {
+                let filled = block.len().min(data.len());
+                block[..filled].copy_from_slice(&data[..filled]);
+                *data = &data[filled..];
+                Ok(filled)
+            }
+            Self::Parts { paths, current } => {
+                let mut filled = 0;
+                while filled < block.len() {
+                    let file = match current {
+                        Some(file) => file,
+                        None => {
+                            let Some((path, rest)) = paths.split_first() else {
+                                break;
+                            };
+                            *paths = rest;
+                            current.insert(fs::File::open(path).await?)
+                        }
+                    };
+                    match file.read(&mut block[filled..]).await? {
+                        0 => *current = None,
+                        read => filled += read,
+                    }
+                }
+                Ok(filled)
+            }
+        }
+    }
+}
+
+/// What happened to one disk's copy of a path being moved.
+#[derive(Debug)]
+enum MoveOutcome {
+    Moved(PathBuf),
+    Missing,
+    Failed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,12 +112,36 @@ struct ErasureMeta {
     mod_time: DateTime<Utc>,
     metadata: HashMap<String, String>,
     erasure: ErasureInfo,
+    #[serde(default)]
+    parts: Vec<ObjectPartInfo>,
+    #[serde(default)]
+    storage_class: Option<String>,
+    /// Bumped on every metadata write so readers can tell the newest copy
+    /// from one a disk kept after missing later writes. Metadata written
+    /// before generations existed reads as 0.
+    #[serde(default)]
+    generation: u64,
+    /// Last read of the object, stamped without touching `mod_time`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_access: Option<DateTime<Utc>>,
 }
 
-impl ErasureObjectLayer {
+impl ErasureSet {
     pub async fn new(disk_paths: Vec<PathBuf>, config: ErasureConfig) -> Result<Self> {
+        config.validate()?;
         let storage = ErasureStorage::new(disk_paths, config).await?;
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            heal_sender: None,
+            key_locks: KeyLocks::new(),
+        })
+    }
+
+    /// Reports objects found below read quorum during listing so they can be
+    /// queued for heal.
+    pub fn with_heal_sender(mut self, sender: mpsc::Sender<PartialObject>) -> Self {
+        self.heal_sender = Some(sender);
+        self
     }
 
     fn object_path(&self, shard_idx: usize, bucket: &str, key: &str) -> Result<PathBuf> {
@@ -66,246 +164,154 @@ impl ErasureObjectLayer {
             .join(DATA_PART_FILE_NAME))
     }
 
-    async fn ensure_bucket_exists_for_quorum(&self, bucket: &str) -> Result<()> {
-        let mut available = 0_usize;
-        for shard in self.storage.shards() {
-            match fs::metadata(shard.path.join(bucket)).await {
-                Ok(metadata) if metadata.is_dir() => available += 1,
-                Ok(_) => {}
-                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
-                Err(_) => {}
-            }
-        }
-
-        if available < self.storage.config().data_shards {
-            return Err(MaxioError::BucketNotFound(bucket.to_string()));
-        }
-
-        Ok(())
-    }
-
-    async fn write_meta_to_quorum(
-        &self,
-        bucket: &str,
-        key: &str,
-        meta: &ErasureMeta,
-    ) -> Result<()> {
-        let meta_bytes = serde_json::to_vec(meta).map_err(|err| {
-            MaxioError::InternalError(format!("failed to serialize xl.meta: {err}"))
-        })?;
-        let mut success = 0_usize;
-
-        for shard_idx in 0..self.storage.shard_count() {
-            let object_path = self.object_path(shard_idx, bucket, key)?;
-            if fs::create_dir_all(&object_path).await.is_err() {
-                continue;
-            }
-
-            if fs::write(object_path.join(META_FILE_NAME), &meta_bytes)
-                .await
-                .is_ok()
-            {
-                success += 1;
-            }
-        }
-
-        if success < self.storage.config().data_shards {
-            return Err(MaxioError::InternalError(format!(
-                "failed to write metadata quorum: wrote {}, need {}",
-                success,
-                self.storage.config().data_shards
-            )));
-        }
-
-        Ok(())
-    }
-
-    async fn read_meta_from_any(&self, bucket: &str, key: &str) -> Result<ErasureMeta> {
-        let mut last_error: Option<MaxioError> = None;
-
-        for shard_idx in 0..self.storage.shard_count() {
-            let meta_path = self
-                .object_path(shard_idx, bucket, key)?
-                .join(META_FILE_NAME);
-            match fs::read(meta_path).await {
-                Ok(bytes) => {
-                    let meta: ErasureMeta = serde_json::from_slice(&bytes).map_err(|err| {
-                        MaxioError::InternalError(format!("failed to parse xl.meta: {err}"))
-                    })?;
-                    return Ok(meta);
-                }
-                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-                    last_error = Some(MaxioError::ObjectNotFound {
-                        bucket: bucket.to_string(),
-                        key: key.to_string(),
-                    });
-                }
+    /// Whether each disk holds `bucket`; `None` where the disk could not
+    /// tell, which counts against the disk if it is at fault.
+    async fn bucket_presence(&self, bucket: &str) -> Vec<Option<bool>> {
+        let mut presence = Vec::with_capacity(self.storage.shard_count());
+        for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+            presence.push(match fs::metadata(shard.path.join(bucket)).await {
+                Ok(metadata) => Some(metadata.is_dir()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Some(false),
                 Err(err) => {
-                    last_error = Some(MaxioError::Io(err));
+                    self.storage.record_failure(shard_idx, err).await;
+                    None
                 }
-            }
-        }
-
-        Err(last_error.unwrap_or(MaxioError::ObjectNotFound {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-        }))
-    }
-
-    fn meta_to_object_info(bucket: &str, key: &str, meta: &ErasureMeta) -> ObjectInfo {
-        ObjectInfo {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-            size: meta.size,
-            etag: meta.etag.clone(),
-            content_type: meta.content_type.clone(),
-            last_modified: meta.mod_time,
-            metadata: meta.metadata.clone(),
-            version_id: None,
-            encryption: None,
+            });
         }
+        presence
     }
-}
-
-#[async_trait]
-impl ObjectLayer for ErasureObjectLayer {
-    async fn make_bucket(&self, bucket: &str) -> Result<()> {
-        validate_bucket_name(bucket)?;
 
-        let mut created = 0_usize;
-        let mut already_exists = 0_usize;
-
-        for shard in self.storage.shards() {
-            match shard.storage.make_bucket(bucket).await {
-                Ok(()) => created += 1,
-                Err(MaxioError::BucketAlreadyExists(_)) => already_exists += 1,
-                Err(err) => return Err(err),
-            }
-        }
-
-        if already_exists == self.storage.shard_count() {
-            return Err(MaxioError::BucketAlreadyExists(bucket.to_string()));
+    /// A bucket on read quorum exists; the online disks missing it are
+    /// brought in line on the way.
+    async fn ensure_bucket_exists_for_quorum(&self, bucket: &str) -> Result<()> {
+        let presence = self.bucket_presence(bucket).await;
+        let available = presence
+            .iter()
+            .filter(|present| **present == Some(true))
+            .count();
+        if available < self.storage.config().read_quorum() {
+            return Err(MaxioError::BucketNotFound(bucket.to_string()));
         }
 
-        if created + already_exists < self.storage.config().data_shards {
-            return Err(MaxioError::InternalError(format!(
-                "insufficient buckets created: have {}, need {}",
-                created + already_exists,
-                self.storage.config().data_shards
-            )));
+        if available < presence.len() {
+            self.create_missing_buckets(bucket, &presence).await;
         }
-
         Ok(())
     }
 
-    async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
-        validate_bucket_name(bucket)?;
-
-        let mut last_not_found: Option<MaxioError> = None;
-        for shard in self.storage.shards() {
-            match shard.storage.get_bucket_info(bucket).await {
-                Ok(info) => return Ok(info),
-                Err(MaxioError::BucketNotFound(_)) => {
-                    last_not_found = Some(MaxioError::BucketNotFound(bucket.to_string()));
+    /// Creates `bucket` on the online disks `presence` reports it missing
+    /// from, carrying over the versioning state of a disk that has it.
+    async fn create_missing_buckets(&self, bucket: &str, presence: &[Option<bool>]) {
+        let health = self.storage.health();
+        let mut versioning = None;
+        for (shard, present) in self.storage.shards().iter().zip(presence) {
+            if *present == Some(true) {
+                versioning = shard.storage.get_bucket_versioning(bucket).await.ok();
+                if versioning.is_some() {
+                    break;
                 }
-                Err(err) => return Err(err),
             }
         }
 
-        Err(last_not_found.unwrap_or(MaxioError::BucketNotFound(bucket.to_string())))
-    }
-
-    async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
-        let mut last_error: Option<MaxioError> = None;
-        for shard in self.storage.shards() {
-            match shard.storage.list_buckets().await {
-                Ok(buckets) => return Ok(buckets),
-                Err(err) => last_error = Some(err),
+        for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+            if presence[shard_idx] != Some(false) || !health.is_online(shard_idx) {
+                continue;
+            }
+            if let Err(err) = make_bucket_copy(&shard.storage, bucket, versioning).await {
+                warn!(
+                    shard = shard_idx,
+                    bucket,
+                    error = %err,
+                    "failed to recreate bucket missing from disk"
+                );
             }
         }
-
-        Err(last_error.unwrap_or_else(|| {
-            MaxioError::InternalError("no readable disks available for list_buckets".to_string())
-        }))
     }
 
-    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+    /// Settles a bucket that only some disks agree on: one on read quorum is
+    /// created on the online disks missing it, and one below read quorum is
+    /// the leftover of a failed create or delete and is removed from the
+    /// disks still holding an empty copy. Leftovers are only removed while
+    /// every disk can be read, since an unreadable disk may hold the copies
+    /// that make up the quorum. Returns whether the bucket exists afterwards.
+    pub async fn reconcile_bucket(&self, bucket: &str) -> Result<bool> {
         validate_bucket_name(bucket)?;
-
-        let mut deleted = 0_usize;
-        let mut missing = 0_usize;
-
-        for shard in self.storage.shards() {
-            match shard.storage.delete_bucket(bucket).await {
-                Ok(()) => deleted += 1,
-                Err(MaxioError::BucketNotFound(_)) => missing += 1,
-                Err(err) => return Err(err),
+        let presence = self.bucket_presence(bucket).await;
+        let available = presence
+            .iter()
+            .filter(|present| **present == Some(true))
+            .count();
+        if available >= self.storage.config().read_quorum() {
+            if available < presence.len() {
+                self.create_missing_buckets(bucket, &presence).await;
             }
+            return Ok(true);
         }
 
-        if deleted == 0 && missing == self.storage.shard_count() {
-            return Err(MaxioError::BucketNotFound(bucket.to_string()));
+        let all_readable = presence.iter().all(Option::is_some)
+            && self.storage.health().online_count() == self.storage.shard_count();
+        if available > 0 && all_readable {
+            for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+                if presence[shard_idx] != Some(true) {
+                    continue;
+                }
+                if let Err(err) = shard.storage.delete_bucket(bucket).await {
+                    warn!(
+                        shard = shard_idx,
+                        bucket,
+                        error = %err,
+                        "failed to remove bucket copy below read quorum"
+                    );
+                }
+            }
         }
-
-        Ok(())
-    }
-
-    async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
-        validate_bucket_name(bucket)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
-
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
-        })?;
-        staging.get_bucket_versioning(bucket).await
+        Ok(false)
     }
 
-    async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
-        validate_bucket_name(bucket)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
-
-        let mut changed = 0_usize;
-        for shard in self.storage.shards() {
-            if shard
-                .storage
-                .set_bucket_versioning(bucket, state)
-                .await
-                .is_ok()
-            {
-                changed += 1;
+    /// Puts back the bucket copies an aborted delete already removed.
+    async fn restore_buckets(
+        &self,
+        bucket: &str,
+        shard_indices: &[usize],
+        versioning: Option<VersioningState>,
+    ) {
+        for &shard_idx in shard_indices {
+            let Some(storage) = self.storage.shard_storage(shard_idx) else {
+                continue;
+            };
+            if let Err(err) = make_bucket_copy(storage, bucket, versioning).await {
+                warn!(
+                    shard = shard_idx,
+                    bucket,
+                    error = %err,
+                    "failed to restore bucket after aborted delete"
+                );
             }
         }
-
-        if changed < self.storage.config().data_shards {
-            return Err(MaxioError::InternalError(format!(
-                "failed to set bucket versioning quorum: wrote {}, need {}",
-                changed,
-                self.storage.config().data_shards
-            )));
-        }
-
-        Ok(())
     }
 
-    async fn put_object(
+    /// Erasure-codes the object read from `source` onto the online disks
+    /// one block at a time and commits its metadata. Callers hold the key
+    /// lock.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_object(
         &self,
         bucket: &str,
         key: &str,
-        data: Bytes,
+        mut source: ObjectSource<'_>,
+        etag: String,
         content_type: Option<&str>,
-        metadata: HashMap<String, String>,
-        encryption: Option<PutEncryptionOptions>,
+        mut metadata: HashMap<String, String>,
+        parts: Vec<ObjectPartInfo>,
     ) -> Result<ObjectInfo> {
-        if encryption.is_some() {
-            return Err(MaxioError::NotImplemented(
-                "SSE is not implemented for erasure mode".to_string(),
-            ));
-        }
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
+        self.ensure_write_quorum_online()?;
 
+        let health = self.storage.health();
         for shard_idx in 0..self.storage.shard_count() {
+            if !health.is_online(shard_idx) {
+                continue;
+            }
+
             let object_path = self.object_path(shard_idx, bucket, key)?;
             match fs::remove_dir_all(&object_path).await {
                 Ok(()) => {}
@@ -314,57 +320,82 @@ impl ObjectLayer for ErasureObjectLayer {
             }
         }
 
-        let total_size = i64::try_from(data.len()).map_err(|_| {
-            MaxioError::InvalidArgument(format!("object is too large to store: {bucket}/{key}"))
-        })?;
-        let etag = format!("{:x}", Md5::digest(&data));
         let mod_time = Utc::now();
         let content_type = content_type.unwrap_or(DEFAULT_CONTENT_TYPE).to_string();
+        let storage_class = metadata.remove(STORAGE_CLASS_META_KEY);
+        let durable = storage_class.as_deref() != Some(REDUCED_REDUNDANCY_STORAGE_CLASS);
+
+        let config = &self
+            .storage
+            .config()
+            .for_storage_class(storage_class.as_deref());
+        let mut block = vec![0_u8; config.block_size];
+        let mut block_checksums = Vec::new();
+        let mut written = 0_u64;
+
+        for block_idx in 0.. {
+            let filled = source.fill(&mut block).await?;
+            // An empty object still gets one empty block.
+            if filled == 0 && block_idx > 0 {
+                break;
+            }
+            let data = &block[..filled];
+            written += filled as u64;
 
-        let config = self.storage.config();
-        let block_count = if data.is_empty() {
-            1
-        } else {
-            data.len().div_ceil(config.block_size)
-        };
-        let mut block_checksums = Vec::with_capacity(block_count);
-
-        for block_idx in 0..block_count {
-            let block = if data.is_empty() {
-                &[][..]
-            } else {
-                let start = block_idx * config.block_size;
-                let end = std::cmp::min(start + config.block_size, data.len());
-                &data[start..end]
-            };
-
-            let checksum = format!("{:x}", Sha256::digest(block));
+            let checksum = format!("{:x}", Sha256::digest(data));
             block_checksums.push(checksum);
 
-            let shards = encode_block(block, config)?;
+            let shards = encode_block(data, config)?;
             let mut successful_writes = 0_usize;
 
             for (shard_idx, shard) in shards.iter().enumerate() {
+                if !health.is_online(shard_idx) {
+                    continue;
+                }
+
                 let part_path = self.block_part_path(shard_idx, bucket, key, block_idx)?;
-                if let Some(parent) = part_path.parent() {
-                    if fs::create_dir_all(parent).await.is_err() {
-                        continue;
+                if let Some(parent) = part_path.parent()
+                    && let Err(err) = fs::create_dir_all(parent).await
+                {
+                    // Every disk refuses a key segment that is too long.
+                    if err.kind() == std::io::ErrorKind::InvalidFilename {
+                        return Err(key_path_error(err.into()));
                     }
+                    self.storage.record_failure(shard_idx, err).await;
+                    continue;
                 }
 
-                if fs::write(part_path, shard).await.is_ok() {
-                    successful_writes += 1;
+                match write_shard(&part_path, shard, durable).await {
+                    Ok(()) => {
+                        health.record_success(shard_idx);
+                        successful_writes += 1;
+                    }
+                    Err(err) => {
+                        self.storage.record_failure(shard_idx, err).await;
+                    }
                 }
             }
 
-            if successful_writes < config.data_shards {
-                return Err(MaxioError::InternalError(format!(
-                    "failed to write shard quorum for block {}: wrote {}, need {}",
-                    block_idx, successful_writes, config.data_shards
-                )));
+            if successful_writes < config.write_quorum() {
+                warn!(
+                    bucket,
+                    key,
+                    block = block_idx,
+                    "erasure block write missed quorum"
+                );
+                return Err(MaxioError::QuorumUnavailable {
+                    needed: config.write_quorum(),
+                    have: successful_writes,
+                });
+            }
+            if filled < config.block_size {
+                break;
             }
         }
 
+        let total_size = i64::try_from(written).map_err(|_| {
+            MaxioError::InvalidArgument(format!("object is too large to store: {bucket}/{key}"))
+        })?;
         let erasure_info = ErasureInfo {
             data_shards: config.data_shards,
             parity_shards: config.parity_shards,
@@ -381,6 +412,10 @@ impl ObjectLayer for ErasureObjectLayer {
             mod_time,
             metadata: metadata.clone(),
             erasure: erasure_info,
+            parts,
+            storage_class: storage_class.clone(),
+            generation: 0,
+            last_access: None,
         };
         self.write_meta_to_quorum(bucket, key, &meta).await?;
 
@@ -394,367 +429,2019 @@ impl ObjectLayer for ErasureObjectLayer {
             metadata,
             version_id: None,
             encryption: None,
+            storage_class,
         })
     }
 
-    async fn get_object(
+    /// Writes `meta` to every online disk as the next generation of the
+    /// object's metadata.
+    async fn write_meta_to_quorum(
         &self,
         bucket: &str,
         key: &str,
-        encryption: Option<GetEncryptionOptions>,
-    ) -> Result<(ObjectInfo, Bytes)> {
-        if encryption.is_some() {
-            return Err(MaxioError::NotImplemented(
-                "SSE is not implemented for erasure mode".to_string(),
-            ));
-        }
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-
-        let meta = self.read_meta_from_any(bucket, key).await?;
-        let object_info = Self::meta_to_object_info(bucket, key, &meta);
-
-        if meta.erasure.total_size == 0 {
-            return Ok((object_info, Bytes::new()));
-        }
+        meta: &ErasureMeta,
+    ) -> Result<()> {
+        let shards = (0..self.storage.shard_count()).collect::<Vec<_>>();
+        self.write_meta_to_shards(bucket, key, meta, &shards).await
+    }
 
-        let total_size = usize::try_from(meta.erasure.total_size).map_err(|_| {
-            MaxioError::InternalError("invalid total_size in erasure metadata".to_string())
-        })?;
-        let block_count = if meta.erasure.block_checksums.is_empty() {
-            total_size.div_ceil(meta.erasure.block_size)
-        } else {
-            meta.erasure.block_checksums.len()
+    /// Writes `meta` to the online disks among `shards` as the next
+    /// generation of the object's metadata, failing below write quorum.
+    async fn write_meta_to_shards(
+        &self,
+        bucket: &str,
+        key: &str,
+        meta: &ErasureMeta,
+        shards: &[usize],
+    ) -> Result<()> {
+        let current = match self.read_meta_from_any(bucket, key).await {
+            Ok(current) => current.generation,
+            Err(_) => 0,
         };
-
-        let block_config = ErasureConfig {
-            data_shards: meta.erasure.data_shards,
-            parity_shards: meta.erasure.parity_shards,
-            block_size: meta.erasure.block_size,
+        let meta = ErasureMeta {
+            generation: current.max(meta.generation) + 1,
+            ..meta.clone()
         };
+        let meta_bytes = serde_json::to_vec(&meta).map_err(|err| {
+            MaxioError::InternalError(format!("failed to serialize xl.meta: {err}"))
+        })?;
+        let health = self.storage.health();
+        let mut success = 0_usize;
 
-        let mut output = Vec::with_capacity(total_size);
-        for block_idx in 0..block_count {
-            let mut shards = Vec::with_capacity(block_config.total_shards());
-            let mut available = 0_usize;
+        for &shard_idx in shards {
+            if !health.is_online(shard_idx) {
+                continue;
+            }
 
-            for shard_idx in 0..block_config.total_shards() {
-                let part_path = self.block_part_path(shard_idx, bucket, key, block_idx)?;
-                match fs::read(part_path).await {
-                    Ok(bytes) => {
-                        available += 1;
-                        shards.push(Some(bytes));
-                    }
-                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-                        shards.push(None);
-                    }
-                    Err(_) => {
-                        shards.push(None);
-                    }
-                }
+            let object_path = self.object_path(shard_idx, bucket, key)?;
+            if let Err(err) = fs::create_dir_all(&object_path).await {
+                self.storage.record_failure(shard_idx, err).await;
+                continue;
             }
 
-            if available < block_config.data_shards {
-                return Err(MaxioError::InternalError(format!(
-                    "insufficient shards for block {}: got {}, need {}",
-                    block_idx, available, block_config.data_shards
-                )));
+            match fs::write(object_path.join(META_FILE_NAME), &meta_bytes).await {
+                Ok(()) => {
+                    health.record_success(shard_idx);
+                    success += 1;
+                }
+                Err(err) => {
+                    self.storage.record_failure(shard_idx, err).await;
+                }
             }
+        }
 
-            let decoded = decode_block(shards, &block_config)?;
-            let written = block_idx * block_config.block_size;
-            let expected_block_size =
-                std::cmp::min(block_config.block_size, total_size.saturating_sub(written));
+        if success < self.storage.config().write_quorum() {
+            return Err(MaxioError::QuorumUnavailable {
+                needed: self.storage.config().write_quorum(),
+                have: success,
+            });
+        }
 
-            if decoded.len() < expected_block_size {
-                return Err(MaxioError::InternalError(format!(
-                    "decoded block {} too short: got {}, expected at least {}",
-                    block_idx,
-                    decoded.len(),
-                    expected_block_size
-                )));
+        Ok(())
+    }
+
+    /// Reads the metadata of every online disk and returns the newest
+    /// generation, so a disk that missed the latest write is never preferred
+    /// over one that has it.
+    async fn read_meta_from_any(&self, bucket: &str, key: &str) -> Result<ErasureMeta> {
+        let mut newest: Option<ErasureMeta> = None;
+        let mut last_error: Option<MaxioError> = None;
+
+        for shard_idx in 0..self.storage.shard_count() {
+            if !self.storage.health().is_online(shard_idx) {
+                continue;
             }
 
-            let block_data = &decoded[..expected_block_size];
-            let checksum = format!("{:x}", Sha256::digest(block_data));
-            if let Some(expected_checksum) = meta.erasure.block_checksums.get(block_idx) {
-                if &checksum != expected_checksum {
-                    return Err(MaxioError::InternalError(format!(
-                        "bitrot detected in block {}",
-                        block_idx
-                    )));
+            let meta_path = self
+                .object_path(shard_idx, bucket, key)?
+                .join(META_FILE_NAME);
+            match fs::read(meta_path).await {
+                Ok(bytes) => match serde_json::from_slice::<ErasureMeta>(&bytes) {
+                    Ok(meta) => {
+                        if newest
+                            .as_ref()
+                            .is_none_or(|newest| meta.generation > newest.generation)
+                        {
+                            newest = Some(meta);
+                        }
+                    }
+                    Err(err) => {
+                        last_error = Some(MaxioError::InternalError(format!(
+                            "failed to parse xl.meta: {err}"
+                        )));
+                    }
+                },
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    last_error = Some(MaxioError::ObjectNotFound {
+                        bucket: bucket.to_string(),
+                        key: key.to_string(),
+                    });
+                }
+                Err(err) => {
+                    last_error = Some(MaxioError::Io(err));
                 }
             }
-
-            output.extend_from_slice(block_data);
-        }
-
-        if output.len() > total_size {
-            output.truncate(total_size);
         }
 
-        Ok((object_info, Bytes::from(output)))
+        newest.ok_or_else(|| {
+            last_error.unwrap_or(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            })
+        })
     }
 
-    async fn get_object_version(
+    async fn collect_object_metas(
         &self,
         bucket: &str,
-        key: &str,
-        version_id: &str,
-        encryption: Option<GetEncryptionOptions>,
-    ) -> Result<(ObjectInfo, Bytes)> {
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
+        prefix: &str,
+    ) -> BTreeMap<String, Vec<Option<ErasureMeta>>> {
+        let shard_count = self.storage.shard_count();
+        let mut metas: BTreeMap<String, Vec<Option<ErasureMeta>>> = BTreeMap::new();
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
-        })?;
-        staging
-            .get_object_version(bucket, key, version_id, encryption)
-            .await
-    }
+        for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+            if !self.storage.health().is_online(shard_idx) {
+                continue;
+            }
 
-    async fn get_object_info(
-        &self,
-        bucket: &str,
-        key: &str,
-        encryption: Option<GetEncryptionOptions>,
-    ) -> Result<ObjectInfo> {
-        if encryption.is_some() {
-            return Err(MaxioError::NotImplemented(
-                "SSE is not implemented for erasure mode".to_string(),
-            ));
+            let bucket_path = shard.path.join(bucket);
+            let keys = match collect_meta_keys(&bucket_path).await {
+                Ok(keys) => keys,
+                Err(err) => {
+                    warn!(shard = shard_idx, bucket, error = %err, "failed to walk erasure shard");
+                    continue;
+                }
+            };
+
+            for key in keys.into_iter().filter(|key| key.starts_with(prefix)) {
+                let meta = match fs::read(bucket_path.join(&key).join(META_FILE_NAME)).await {
+                    Ok(bytes) => serde_json::from_slice::<ErasureMeta>(&bytes).ok(),
+                    Err(_) => None,
+                };
+                metas.entry(key).or_insert_with(|| vec![None; shard_count])[shard_idx] = meta;
+            }
         }
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
 
-        let meta = self.read_meta_from_any(bucket, key).await?;
-        Ok(Self::meta_to_object_info(bucket, key, &meta))
+        metas
     }
 
-    async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+    /// Objects under `prefix` whose metadata reaches read quorum. Anything
+    /// below quorum is reported to the heal sender and left out.
+    pub(crate) async fn quorum_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Vec<ObjectInfo>> {
         validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-
-        let mut removed = 0_usize;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
 
-        for shard_idx in 0..self.storage.shard_count() {
-            let object_path = self.object_path(shard_idx, bucket, key)?;
-            match fs::remove_dir_all(object_path).await {
-                Ok(()) => removed += 1,
-                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
-                Err(_) => {}
+        let read_quorum = self.storage.config().read_quorum();
+        let mut objects = Vec::new();
+        for (key, metas) in self.collect_object_metas(bucket, prefix).await {
+            match select_quorum_meta(&metas, read_quorum) {
+                Some(meta) => objects.push(Self::meta_to_object_info(bucket, &key, meta)),
+                None => self.report_partial_object(bucket, &key, &metas),
             }
         }
 
-        if removed == 0 {
-            return Err(MaxioError::ObjectNotFound {
+        Ok(objects)
+    }
+
+    fn report_partial_object(&self, bucket: &str, key: &str, metas: &[Option<ErasureMeta>]) {
+        let disks_with_meta = metas
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, meta)| meta.as_ref().map(|_| idx))
+            .collect::<Vec<_>>();
+        warn!(
+            bucket,
+            key,
+            disks = ?disks_with_meta,
+            "object metadata below read quorum; skipping from listing"
+        );
+
+        if let Some(sender) = &self.heal_sender {
+            let _ = sender.try_send(PartialObject {
                 bucket: bucket.to_string(),
                 key: key.to_string(),
+                disks_with_meta,
             });
         }
-
-        Ok(())
     }
 
-    async fn delete_object_version(&self, bucket: &str, key: &str, version_id: &str) -> Result<()> {
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
+    async fn move_to_trash(&self, shard_idx: usize, object_path: &Path) -> MoveOutcome {
+        let health = self.storage.health();
+        if !health.is_online(shard_idx) {
+            return MoveOutcome::Failed;
+        }
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
-        })?;
-        staging.delete_object_version(bucket, key, version_id).await
+        match fs::metadata(object_path).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return MoveOutcome::Missing;
+            }
+            Err(err) => {
+                self.storage.record_failure(shard_idx, err).await;
+                return MoveOutcome::Failed;
+            }
+        }
+
+        let Some(shard_root) = self.storage.shard_path(shard_idx) else {
+            return MoveOutcome::Failed;
+        };
+        let trash_root = shard_root.join(SYS_DIR_NAME).join(TRASH_DIR_NAME);
+        if let Err(err) = fs::create_dir_all(&trash_root).await {
+            self.storage.record_failure(shard_idx, err).await;
+            return MoveOutcome::Failed;
+        }
+
+        let trash_path = trash_root.join(Uuid::new_v4().to_string());
+        match fs::rename(object_path, &trash_path).await {
+            Ok(()) => {
+                health.record_success(shard_idx);
+                MoveOutcome::Moved(trash_path)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => MoveOutcome::Missing,
+            Err(err) => {
+                self.storage.record_failure(shard_idx, err).await;
+                MoveOutcome::Failed
+            }
+        }
     }
 
-    async fn list_objects(
+    /// Renames `from_path` to `to_path` on one disk, clearing directories
+    /// left empty under `to_path` by earlier deletes first.
+    async fn rename_on_shard(
         &self,
-        bucket: &str,
-        prefix: &str,
-        marker: &str,
-        delimiter: &str,
-        max_keys: i32,
-    ) -> Result<ListObjectsResult> {
-        validate_bucket_name(bucket)?;
+        shard_idx: usize,
+        from_path: &Path,
+        to_path: &Path,
+    ) -> MoveOutcome {
+        let health = self.storage.health();
+        if !health.is_online(shard_idx) {
+            return MoveOutcome::Failed;
+        }
 
-        let mut last_error: Option<MaxioError> = None;
-        for shard in self.storage.shards() {
-            match shard
-                .storage
-                .list_objects(bucket, prefix, marker, delimiter, max_keys)
-                .await
-            {
-                Ok(result) => return Ok(result),
-                Err(err) => last_error = Some(err),
+        match fs::metadata(from_path).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return MoveOutcome::Missing;
+            }
+            Err(err) => {
+                self.storage.record_failure(shard_idx, err).await;
+                return MoveOutcome::Failed;
             }
         }
 
-        Err(last_error.unwrap_or_else(|| {
-            MaxioError::InternalError("no readable disks available for list_objects".to_string())
-        }))
+        remove_empty_dirs(to_path).await;
+        if let Some(parent) = to_path.parent()
+            && let Err(err) = fs::create_dir_all(parent).await
+        {
+            self.storage.record_failure(shard_idx, err).await;
+            return MoveOutcome::Failed;
+        }
+        match fs::rename(from_path, to_path).await {
+            Ok(()) => {
+                health.record_success(shard_idx);
+                MoveOutcome::Moved(to_path.to_path_buf())
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => MoveOutcome::Missing,
+            Err(err) => {
+                self.storage.record_failure(shard_idx, err).await;
+                MoveOutcome::Failed
+            }
+        }
     }
 
-    async fn list_object_versions(
+    /// Hard-links the blocks of `source` into `bucket/key` on one disk. Only
+    /// a disk whose copy of the source metadata matches `source_meta` holds
+    /// blocks that belong together, so any other disk is left out.
+    async fn link_blocks_on_shard(
         &self,
+        shard_idx: usize,
+        source: &CopySource,
+        source_meta: &ErasureMeta,
         bucket: &str,
-        prefix: &str,
-        max_keys: i32,
-    ) -> Result<Vec<ObjectVersion>> {
-        validate_bucket_name(bucket)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
+        key: &str,
+    ) -> Result<bool> {
+        let meta_path = self
+            .object_path(shard_idx, &source.bucket, &source.key)?
+            .join(META_FILE_NAME);
+        let Some(shard_meta) = fs::read(meta_path)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<ErasureMeta>(&bytes).ok())
+        else {
+            return Ok(false);
+        };
+        if shard_meta.generation != source_meta.generation || shard_meta.etag != source_meta.etag {
+            return Ok(false);
+        }
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
-        })?;
-        staging.list_object_versions(bucket, prefix, max_keys).await
+        let block_count = if source_meta.erasure.block_checksums.is_empty() {
+            usize::try_from(source_meta.erasure.total_size)
+                .unwrap_or_default()
+                .div_ceil(source_meta.erasure.block_size)
+        } else {
+            source_meta.erasure.block_checksums.len()
+        };
+        let health = self.storage.health();
+        for block_idx in 0..block_count {
+            let from = self.block_part_path(shard_idx, &source.bucket, &source.key, block_idx)?;
+            let to = self.block_part_path(shard_idx, bucket, key, block_idx)?;
+            let linked = match to.parent() {
+                Some(parent) => fs::create_dir_all(parent).await,
+                None => Ok(()),
+            };
+            let linked = match linked {
+                Ok(()) if fs::hard_link(&from, &to).await.is_ok() => Ok(()),
+                Ok(()) => fs::copy(&from, &to).await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            match linked {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => {
+                    self.storage.record_failure(shard_idx, err).await;
+                    return Ok(false);
+                }
+            }
+        }
+        health.record_success(shard_idx);
+        Ok(true)
     }
 
-    async fn create_multipart_upload(
-        &self,
-        bucket: &str,
-        key: &str,
-        content_type: Option<&str>,
-        metadata: HashMap<String, String>,
-    ) -> Result<String> {
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
+    /// Fails fast when too many disks are offline for a write to ever reach
+    /// quorum.
+    fn ensure_write_quorum_online(&self) -> Result<()> {
+        let online = self.storage.health().online_count();
+        let quorum = self.storage.config().write_quorum();
+        if online < quorum {
+            return Err(MaxioError::QuorumUnavailable {
+                needed: quorum,
+                have: online,
+            });
+        }
+        Ok(())
+    }
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
-        })?;
-        staging
-            .create_multipart_upload(bucket, key, content_type, metadata)
-            .await
+    /// Probes the set's offline disks and brings back those that pass.
+    /// Returns how many came back.
+    pub async fn recover_offline_disks(&self) -> usize {
+        self.storage.recover_offline_disks().await
     }
 
-    async fn upload_part(
-        &self,
-        bucket: &str,
-        key: &str,
-        upload_id: &str,
-        part_number: i32,
-        data: Bytes,
-    ) -> Result<String> {
-        validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
+    pub fn disk_health(&self) -> Vec<DiskHealth> {
+        self.storage.health().snapshot()
+    }
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
-        })?;
-        staging
-            .upload_part(bucket, key, upload_id, part_number, data)
-            .await
+    /// Forwards disks going offline or coming back to heal/discovery.
+    pub fn with_disk_health_sender(self, sender: mpsc::Sender<DiskHealth>) -> Self {
+        self.storage.health().set_event_sender(sender);
+        self
     }
 
-    async fn complete_multipart_upload(
-        &self,
-        bucket: &str,
-        key: &str,
-        upload_id: &str,
-        parts: Vec<CompletePart>,
-    ) -> Result<ObjectInfo> {
+    fn meta_to_object_info(bucket: &str, key: &str, meta: &ErasureMeta) -> ObjectInfo {
+        ObjectInfo {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: meta.size,
+            etag: meta.etag.clone(),
+            content_type: meta.content_type.clone(),
+            last_modified: meta.mod_time,
+            metadata: meta.metadata.clone(),
+            version_id: None,
+            encryption: None,
+            storage_class: meta.storage_class.clone(),
+        }
+    }
+
+    /// Moves `key` out of every shard, restoring it if the delete misses
+    /// quorum. Callers hold the key's write lock.
+    async fn remove_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
-        self.ensure_bucket_exists_for_quorum(bucket).await?;
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
-        })?;
-        let staged_info = staging
-            .complete_multipart_upload(bucket, key, upload_id, parts)
-            .await?;
-        let (_, staged_data) = staging.get_object(bucket, key, None).await?;
+        self.ensure_write_quorum_online()?;
 
-        let content_type = staged_info.content_type.clone();
-        let metadata = staged_info.metadata.clone();
-        let mut finalized = self
-            .put_object(
-                bucket,
-                key,
-                staged_data,
-                Some(&content_type),
-                metadata,
-                None,
-            )
-            .await?;
+        // Move each shard's copy into the disk-local trash first so a delete
+        // that misses quorum can be rolled back instead of leaving the object
+        // half-removed.
+        let mut outcomes = Vec::with_capacity(self.storage.shard_count());
+        for shard_idx in 0..self.storage.shard_count() {
+            let object_path = self.object_path(shard_idx, bucket, key)?;
+            outcomes.push(self.move_to_trash(shard_idx, &object_path).await);
+        }
+
+        let missing = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, MoveOutcome::Missing))
+            .count();
+        if missing == outcomes.len() {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            });
+        }
+
+        let failed = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, MoveOutcome::Failed))
+            .count();
+        let deleted = outcomes.len() - failed;
+        let quorum = self.storage.config().write_quorum();
+
+        if deleted < quorum {
+            for (shard_idx, outcome) in outcomes.iter().enumerate() {
+                if let MoveOutcome::Moved(trash_path) = outcome {
+                    let object_path = self.object_path(shard_idx, bucket, key)?;
+                    if let Err(err) = fs::rename(trash_path, &object_path).await {
+                        warn!(
+                            shard = shard_idx,
+                            bucket,
+                            key,
+                            error = %err,
+                            "failed to restore object shard after delete missed quorum"
+                        );
+                    }
+                }
+            }
+
+            return Err(MaxioError::QuorumUnavailable {
+                needed: quorum,
+                have: deleted,
+            });
+        }
+
+        for outcome in &outcomes {
+            if let MoveOutcome::Moved(trash_path) = outcome {
+                let _ = fs::remove_dir_all(trash_path).await;
+            }
+        }
+
+        if failed > 0 {
+            warn!(
+                bucket,
+                key, failed, "object deleted with quorum; stale shards remain and need heal"
+            );
+        }
+
+        Ok(DeletedObject::default())
+    }
+}
+
+#[async_trait]
+impl ObjectLayer for ErasureSet {
+    async fn make_bucket(&self, bucket: &str) -> Result<()> {
+        validate_new_bucket_name(bucket)?;
+
+        if self.reconcile_bucket(bucket).await? {
+            return Err(MaxioError::BucketAlreadyExists(bucket.to_string()));
+        }
+
+        // Disks failing the create are left to be reconciled later, as long
+        // as enough of them took it to make up write quorum.
+        let mut created = Vec::new();
+        let mut already_exists = 0_usize;
+        let mut last_error = None;
+        for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+            match shard.storage.make_bucket(bucket).await {
+                Ok(()) => created.push(shard_idx),
+                Err(MaxioError::BucketAlreadyExists(_)) => already_exists += 1,
+                Err(err) => {
+                    warn!(
+                        shard = shard_idx,
+                        bucket,
+                        error = %err,
+                        "failed to create bucket on disk"
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        let have = created.len() + already_exists;
+        if have < self.storage.config().write_quorum() {
+            // Roll back so no disk keeps a bucket the set does not have.
+            for shard_idx in created {
+                if let Some(storage) = self.storage.shard_storage(shard_idx)
+                    && let Err(err) = storage.delete_bucket(bucket).await
+                {
+                    warn!(
+                        shard = shard_idx,
+                        bucket,
+                        error = %err,
+                        "failed to roll back bucket create"
+                    );
+                }
+            }
+            return Err(match last_error {
+                Some(err) if have == 0 => err,
+                _ => MaxioError::QuorumUnavailable {
+                    needed: self.storage.config().write_quorum(),
+                    have,
+                },
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+        validate_bucket_name(bucket)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let mut last_not_found: Option<MaxioError> = None;
+        for shard in self.storage.shards() {
+            match shard.storage.get_bucket_info(bucket).await {
+                Ok(info) => return Ok(info),
+                Err(MaxioError::BucketNotFound(_)) => {
+                    last_not_found = Some(MaxioError::BucketNotFound(bucket.to_string()));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_not_found.unwrap_or(MaxioError::BucketNotFound(bucket.to_string())))
+    }
+
+    async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+        let mut last_error: Option<MaxioError> = None;
+        for shard in self.storage.shards() {
+            match shard.storage.list_buckets().await {
+                Ok(buckets) => return Ok(buckets),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            MaxioError::InternalError("no readable disks available for list_buckets".to_string())
+        }))
+    }
+
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        validate_bucket_name(bucket)?;
+
+        if !self.reconcile_bucket(bucket).await? {
+            return Err(MaxioError::BucketNotFound(bucket.to_string()));
+        }
+        let versioning = self.get_bucket_versioning(bucket).await.ok();
+
+        let mut deleted = Vec::new();
+        let mut missing = 0_usize;
+        for (shard_idx, shard) in self.storage.shards().iter().enumerate() {
+            match shard.storage.delete_bucket(bucket).await {
+                Ok(()) => deleted.push(shard_idx),
+                Err(MaxioError::BucketNotFound(_)) => missing += 1,
+                Err(err @ MaxioError::InvalidArgument(_)) => {
+                    self.restore_buckets(bucket, &deleted, versioning).await;
+                    return Err(err);
+                }
+                Err(err) => {
+                    warn!(
+                        shard = shard_idx,
+                        bucket,
+                        error = %err,
+                        "failed to delete bucket on disk"
+                    );
+                }
+            }
+        }
+
+        // Disks that kept the bucket now hold it below read quorum, where
+        // reconcile_bucket removes it once they are reachable again.
+        let gone = deleted.len() + missing;
+        if gone < self.storage.config().write_quorum() {
+            self.restore_buckets(bucket, &deleted, versioning).await;
+            return Err(MaxioError::QuorumUnavailable {
+                needed: self.storage.config().write_quorum(),
+                have: gone,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+        validate_bucket_name(bucket)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
+        })?;
+        staging.get_bucket_versioning(bucket).await
+    }
+
+    async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+        validate_bucket_name(bucket)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let mut changed = 0_usize;
+        for shard in self.storage.shards() {
+            if shard
+                .storage
+                .set_bucket_versioning(bucket, state)
+                .await
+                .is_ok()
+            {
+                changed += 1;
+            }
+        }
+
+        if changed < self.storage.config().write_quorum() {
+            return Err(MaxioError::QuorumUnavailable {
+                needed: self.storage.config().write_quorum(),
+                have: changed,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        if encryption.is_some() {
+            return Err(MaxioError::NotImplemented(
+                "SSE is not implemented for erasure mode".to_string(),
+            ));
+        }
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+        let _guard = self.key_locks.lock(bucket, key).await;
+        let etag = md5_hex(&data);
+        self.write_object(
+            bucket,
+            key,
+            ObjectSource::Bytes(&data),
+            etag,
+            content_type,
+            metadata,
+            Vec::new(),
+        )
+        .await
+    }
+
+    async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        if encryption.is_some() {
+            return Err(MaxioError::NotImplemented(
+                "SSE is not implemented for erasure mode".to_string(),
+            ));
+        }
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+
+        let meta = self.read_meta_from_any(bucket, key).await?;
+        let object_info = Self::meta_to_object_info(bucket, key, &meta);
+
+        if meta.erasure.total_size == 0 {
+            return Ok((object_info, Bytes::new()));
+        }
+
+        let total_size = usize::try_from(meta.erasure.total_size).map_err(|_| {
+            MaxioError::InternalError("invalid total_size in erasure metadata".to_string())
+        })?;
+        let block_count = if meta.erasure.block_checksums.is_empty() {
+            total_size.div_ceil(meta.erasure.block_size)
+        } else {
+            meta.erasure.block_checksums.len()
+        };
+
+        let block_config = ErasureConfig {
+            data_shards: meta.erasure.data_shards,
+            parity_shards: meta.erasure.parity_shards,
+            block_size: meta.erasure.block_size,
+            ..ErasureConfig::default()
+        };
+
+        let health = self.storage.health();
+        let shard_size = block_config.shard_size()?;
+        let mut output = Vec::with_capacity(total_size);
+        for block_idx in 0..block_count {
+            let mut shards = Vec::with_capacity(block_config.total_shards());
+            let mut available = 0_usize;
+
+            for shard_idx in 0..block_config.total_shards() {
+                if !health.is_online(shard_idx) {
+                    shards.push(None);
+                    continue;
+                }
+
+                let part_path = self.block_part_path(shard_idx, bucket, key, block_idx)?;
+                match fs::read(part_path).await {
+                    // A truncated or padded shard would poison the decoder, so
+                    // treat it as missing and let parity cover for it.
+                    Ok(bytes) if bytes.len() != shard_size => {
+                        warn!(
+                            shard = shard_idx,
+                            block = block_idx,
+                            bucket,
+                            key,
+                            expected = shard_size,
+                            actual = bytes.len(),
+                            "ignoring erasure shard with unexpected size"
+                        );
+                        shards.push(None);
+                    }
+                    Ok(bytes) => {
+                        health.record_success(shard_idx);
+                        available += 1;
+                        shards.push(Some(bytes));
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                        shards.push(None);
+                    }
+                    Err(err) => {
+                        self.storage.record_failure(shard_idx, err).await;
+                        shards.push(None);
+                    }
+                }
+            }
+
+            if available < block_config.data_shards {
+                warn!(
+                    bucket,
+                    key,
+                    block = block_idx,
+                    "erasure block read missed quorum"
+                );
+                return Err(MaxioError::QuorumUnavailable {
+                    needed: block_config.data_shards,
+                    have: available,
+                });
+            }
+
+            let decoded = decode_block(shards, &block_config)?;
+            let written = block_idx * block_config.block_size;
+            let expected_block_size =
+                std::cmp::min(block_config.block_size, total_size.saturating_sub(written));
+
+            if decoded.len() < expected_block_size {
+                return Err(MaxioError::InternalError(format!(
+                    "decoded block {} too short: got {}, expected at least {}",
+                    block_idx,
+                    decoded.len(),
+                    expected_block_size
+                )));
+            }
+
+            let block_data = &decoded[..expected_block_size];
+            let checksum = format!("{:x}", Sha256::digest(block_data));
+            if let Some(expected_checksum) = meta.erasure.block_checksums.get(block_idx)
+                && &checksum != expected_checksum
+            {
+                return Err(MaxioError::InternalError(format!(
+                    "bitrot detected in block {}",
+                    block_idx
+                )));
+            }
+
+            output.extend_from_slice(block_data);
+        }
+
+        if output.len() > total_size {
+            output.truncate(total_size);
+        }
+
+        Ok((object_info, Bytes::from(output)))
+    }
+
+    async fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
+        })?;
+        staging
+            .get_object_version(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn get_object_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        if encryption.is_some() {
+            return Err(MaxioError::NotImplemented(
+                "SSE is not implemented for erasure mode".to_string(),
+            ));
+        }
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+
+        let meta = self.read_meta_from_any(bucket, key).await?;
+        Ok(Self::meta_to_object_info(bucket, key, &meta))
+    }
+
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
+        })?;
+        staging
+            .get_object_version_info(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+        self.ensure_write_quorum_online()?;
+        let _guard = self.key_locks.lock(bucket, key).await;
 
         let mut meta = self.read_meta_from_any(bucket, key).await?;
-        meta.etag = staged_info.etag.clone();
+        if let Some(content_type) = content_type {
+            meta.content_type = content_type.to_string();
+        }
+        meta.metadata = metadata;
+        meta.mod_time = Utc::now();
         self.write_meta_to_quorum(bucket, key, &meta).await?;
 
-        finalized.etag = staged_info.etag;
-        Ok(finalized)
+        Ok(Self::meta_to_object_info(bucket, key, &meta))
+    }
+
+    /// Copies the latest version of an object by hard-linking its shards on
+    /// every disk, so nothing is decoded or encoded again. Copies that change
+    /// the erasure layout or involve versions or encryption go through a
+    /// full read and write.
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        let same_object = source.bucket == bucket && source.key == key;
+        if source.version_id.is_some()
+            || source.encryption.is_some()
+            || encryption.is_some()
+            || same_object
+        {
+            return copy_by_reading(self, source, bucket, key, metadata, encryption).await;
+        }
+        validate_bucket_name(&source.bucket)?;
+        validate_object_key(&source.key)?;
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(&source.bucket).await?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let source_meta = self.read_meta_from_any(&source.bucket, &source.key).await?;
+        let source_info = Self::meta_to_object_info(&source.bucket, &source.key, &source_meta);
+        let (content_type, mut metadata) = metadata(&source_info)?;
+        let config = self
+            .storage
+            .config()
+            .for_storage_class(metadata.get(STORAGE_CLASS_META_KEY).map(String::as_str));
+        if config.data_shards != source_meta.erasure.data_shards
+            || config.parity_shards != source_meta.erasure.parity_shards
+            || config.block_size != source_meta.erasure.block_size
+        {
+            let (source_info, data) = self.get_object(&source.bucket, &source.key, None).await?;
+            let info = self
+                .put_object(bucket, key, data, content_type.as_deref(), metadata, None)
+                .await?;
+            return Ok((source_info, info));
+        }
+
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.ensure_write_quorum_online()?;
+
+        let health = self.storage.health();
+        let mut linked = Vec::new();
+        for shard_idx in 0..self.storage.shard_count() {
+            if !health.is_online(shard_idx) {
+                continue;
+            }
+            let object_path = self.object_path(shard_idx, bucket, key)?;
+            match fs::remove_dir_all(&object_path).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(_) => {}
+            }
+            if self
+                .link_blocks_on_shard(shard_idx, source, &source_meta, bucket, key)
+                .await?
+            {
+                linked.push(shard_idx);
+            } else {
+                let _ = fs::remove_dir_all(&object_path).await;
+            }
+        }
+
+        if linked.len() < config.write_quorum() {
+            for &shard_idx in &linked {
+                let _ = fs::remove_dir_all(self.object_path(shard_idx, bucket, key)?).await;
+            }
+            warn!(bucket, key, "erasure copy missed quorum");
+            return Err(MaxioError::QuorumUnavailable {
+                needed: config.write_quorum(),
+                have: linked.len(),
+            });
+        }
+
+        let storage_class = metadata.remove(STORAGE_CLASS_META_KEY);
+        let meta = ErasureMeta {
+            content_type: content_type.unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string()),
+            mod_time: Utc::now(),
+            metadata,
+            storage_class,
+            generation: 0,
+            last_access: None,
+            ..source_meta
+        };
+        self.write_meta_to_shards(bucket, key, &meta, &linked)
+            .await?;
+
+        Ok((source_info, Self::meta_to_object_info(bucket, key, &meta)))
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.remove_object(bucket, key).await
+    }
+
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        let _guard = self.key_locks.lock(bucket, key).await;
+        let meta = self.read_meta_from_any(bucket, key).await?;
+        condition.check(&Self::meta_to_object_info(bucket, key, &meta))?;
+        self.remove_object(bucket, key).await
+    }
+
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+        let _guard = self.key_locks.lock(bucket, key).await;
+
+        // Erasure-coded objects are unversioned and listed as the null
+        // version.
+        if version_id == NULL_VERSION_ID && self.read_meta_from_any(bucket, key).await.is_ok() {
+            return self.remove_object(bucket, key).await;
+        }
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for versioning operations".to_string())
+        })?;
+        staging.delete_object_version(bucket, key, version_id).await
+    }
+
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+        order: ListOrder,
+    ) -> Result<ListObjectsResult> {
+        let objects = self.quorum_objects(bucket, prefix).await?;
+        Ok(paginate_objects(
+            objects, prefix, marker, delimiter, max_keys, order,
+        ))
+    }
+
+    /// Erasure-coded objects are unversioned, so each one whose metadata
+    /// reaches read quorum is listed as its key's only, null version.
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+    ) -> Result<ListObjectVersionsResult> {
+        validate_bucket_name(bucket)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let read_quorum = self.storage.config().read_quorum();
+        let mut versions = Vec::new();
+        for (key, metas) in self.collect_object_metas(bucket, prefix).await {
+            match select_quorum_meta(&metas, read_quorum) {
+                Some(meta) => versions.push(ObjectVersion {
+                    key,
+                    version_id: NULL_VERSION_ID.to_string(),
+                    is_latest: true,
+                    is_delete_marker: false,
+                    last_modified: meta.mod_time,
+                    etag: Some(meta.etag.clone()),
+                    size: meta.size,
+                    last_access: meta.last_access,
+                }),
+                None => self.report_partial_object(bucket, &key, &metas),
+            }
+        }
+
+        Ok(paginate_versions(
+            versions,
+            prefix,
+            key_marker,
+            version_id_marker,
+            delimiter,
+            max_keys,
+        ))
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
+        })?;
+        staging
+            .create_multipart_upload(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+    ) -> Result<String> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
+        })?;
+        staging
+            .upload_part(bucket, key, upload_id, part_number, data)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletePart>,
+    ) -> Result<ObjectInfo> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
+        })?;
+        let upload = staging
+            .prepare_multipart_completion(bucket, key, upload_id, &parts)
+            .await?;
+
+        let _guard = self.key_locks.lock(bucket, key).await;
+        let info = self
+            .write_object(
+                bucket,
+                key,
+                ObjectSource::Parts {
+                    paths: &upload.paths,
+                    current: None,
+                },
+                upload.etag,
+                Some(&upload.content_type),
+                upload.metadata,
+                upload.manifest,
+            )
+            .await?;
+        staging
+            .abort_multipart_upload(bucket, key, upload_id)
+            .await?;
+        Ok(info)
     }
 
     async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
         validate_bucket_name(bucket)?;
-        validate_object_key(key)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
+        })?;
+        staging.abort_multipart_upload(bucket, key, upload_id).await
+    }
+
+    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        self.ensure_bucket_exists_for_quorum(bucket).await?;
+
+        let staging = self.storage.shard_storage(0).ok_or_else(|| {
+            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
+        })?;
+        staging.list_parts(bucket, key, upload_id).await
+    }
+
+    async fn list_multipart_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
+        validate_bucket_name(bucket)?;
         self.ensure_bucket_exists_for_quorum(bucket).await?;
 
         let staging = self.storage.shard_storage(0).ok_or_else(|| {
             MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
         })?;
-        staging.abort_multipart_upload(bucket, key, upload_id).await
+        staging
+            .list_multipart_uploads(
+                bucket,
+                prefix,
+                key_marker,
+                upload_id_marker,
+                delimiter,
+                max_uploads,
+            )
+            .await
     }
 
-    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>> {
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
+
+        let meta = self.read_meta_from_any(bucket, key).await?;
+        Ok(meta.parts)
+    }
+
+    async fn rename_prefix(&self, bucket: &str, from_prefix: &str, to_prefix: &str) -> Result<()> {
+        validate_bucket_name(bucket)?;
+        let (from, to) = validate_prefix_rename(from_prefix, to_prefix)?;
+        self.ensure_write_quorum_online()?;
         self.ensure_bucket_exists_for_quorum(bucket).await?;
 
-        let staging = self.storage.shard_storage(0).ok_or_else(|| {
-            MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
-        })?;
-        staging.list_parts(bucket, key, upload_id).await
+        let health = self.storage.health();
+        let mut found = false;
+        for shard_idx in 0..self.storage.shard_count() {
+            if !health.is_online(shard_idx) {
+                continue;
+            }
+            let from_path = self.object_path(shard_idx, bucket, from)?;
+            let to_path = self.object_path(shard_idx, bucket, to)?;
+            if has_meta(&from_path).await {
+                return Err(MaxioError::InvalidArgument(format!(
+                    "object {from} shares a directory with prefix {from_prefix}"
+                )));
+            }
+            if !collect_meta_keys(&to_path).await?.is_empty() {
+                return Err(MaxioError::PreconditionFailed(format!(
+                    "destination prefix {to_prefix} is not empty"
+                )));
+            }
+            for (end, _) in to.rmatch_indices('/') {
+                if has_meta(&self.object_path(shard_idx, bucket, &to[..end])?).await {
+                    return Err(MaxioError::InvalidArgument(format!(
+                        "destination prefix {to_prefix} lies inside object {}",
+                        &to[..end]
+                    )));
+                }
+            }
+            found |= !collect_meta_keys(&from_path).await?.is_empty();
+        }
+        if !found {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: from_prefix.to_string(),
+            });
+        }
+
+        let mut outcomes = Vec::with_capacity(self.storage.shard_count());
+        for shard_idx in 0..self.storage.shard_count() {
+            let from_path = self.object_path(shard_idx, bucket, from)?;
+            let to_path = self.object_path(shard_idx, bucket, to)?;
+            outcomes.push(self.rename_on_shard(shard_idx, &from_path, &to_path).await);
+        }
+
+        let failed = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, MoveOutcome::Failed))
+            .count();
+        let renamed = outcomes.len() - failed;
+        let quorum = self.storage.config().write_quorum();
+        if renamed < quorum {
+            for (shard_idx, outcome) in outcomes.iter().enumerate() {
+                if let MoveOutcome::Moved(to_path) = outcome {
+                    let from_path = self.object_path(shard_idx, bucket, from)?;
+                    if let Err(err) = fs::rename(to_path, &from_path).await {
+                        warn!(
+                            shard = shard_idx,
+                            bucket,
+                            prefix = from_prefix,
+                            error = %err,
+                            "failed to restore prefix after rename missed quorum"
+                        );
+                    }
+                }
+            }
+
+            return Err(MaxioError::QuorumUnavailable {
+                needed: quorum,
+                have: renamed,
+            });
+        }
+
+        if failed > 0 {
+            warn!(
+                bucket,
+                from_prefix,
+                to_prefix,
+                failed,
+                "prefix renamed with quorum; stale shards remain and need heal"
+            );
+        }
+        Ok(())
     }
 
-    async fn list_multipart_uploads(
-        &self,
-        bucket: &str,
-        prefix: &str,
-    ) -> Result<Vec<MultipartUploadInfo>> {
+    async fn storage_info(&self) -> StorageInfo {
+        let mut disks = Vec::with_capacity(self.storage.shard_count());
+        for (index, shard) in self.storage.shards().iter().enumerate() {
+            let tracked_online = self.storage.health().is_online(index);
+            disks.push(DiskInfo::probe(&shard.path, 0, index, tracked_online).await);
+        }
+        StorageInfo { disks }
+    }
+
+    async fn record_access(&self, bucket: &str, key: &str, accessed: DateTime<Utc>) -> Result<()> {
         validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
         self.ensure_bucket_exists_for_quorum(bucket).await?;
+        let _guard = self.key_locks.lock(bucket, key).await;
+
+        let mut meta = self.read_meta_from_any(bucket, key).await?;
+        meta.last_access = Some(accessed);
+        self.write_meta_to_quorum(bucket, key, &meta).await
+    }
 
+    async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
         let staging = self.storage.shard_storage(0).ok_or_else(|| {
             MaxioError::InternalError("missing shard 0 for multipart staging".to_string())
         })?;
-        staging.list_multipart_uploads(bucket, prefix).await
+        staging.remove_stale_multipart_uploads(cutoff).await
     }
-}
 
-fn validate_bucket_name(bucket: &str) -> Result<()> {
-    if bucket.is_empty() || bucket == ".maxio.sys" || bucket.contains('/') || bucket.contains('\\')
-    {
-        return Err(MaxioError::InvalidBucketName(bucket.to_string()));
+    fn has_write_quorum(&self) -> bool {
+        self.ensure_write_quorum_online().is_ok()
     }
-    Ok(())
 }
 
-fn validate_object_key(key: &str) -> Result<()> {
-    if key.is_empty() || key.contains('\\') {
-        return Err(MaxioError::InvalidObjectName(key.to_string()));
+/// The newest metadata generation that at least `quorum` disks agree on.
+fn select_quorum_meta(metas: &[Option<ErasureMeta>], quorum: usize) -> Option<&ErasureMeta> {
+    let mut counts: HashMap<(u64, &str, DateTime<Utc>), usize> = HashMap::new();
+    for meta in metas.iter().flatten() {
+        *counts
+            .entry((meta.generation, meta.etag.as_str(), meta.mod_time))
+            .or_default() += 1;
     }
 
-    let key_path = Path::new(key);
-    if key_path.is_absolute() {
-        return Err(MaxioError::InvalidObjectName(key.to_string()));
+    let (generation, etag, mod_time) = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= quorum)
+        .map(|(version, _)| version)
+        .max()?;
+
+    metas.iter().flatten().find(|meta| {
+        meta.generation == generation && meta.etag == etag && meta.mod_time == mod_time
+    })
+}
+
+async fn collect_meta_keys(bucket_path: &Path) -> Result<Vec<String>> {
+    let mut stack = vec![bucket_path.to_path_buf()];
+    let mut keys = Vec::new();
+
+    while let Some(dir) = stack.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(items) => items,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(MaxioError::Io(err)),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !entry.metadata().await?.is_dir() || entry.file_name() == MULTIPART_DIR_NAME {
+                continue;
+            }
+
+            if !has_meta(&path).await {
+                stack.push(path);
+                continue;
+            }
+
+            if let Ok(rel) = path.strip_prefix(bucket_path) {
+                keys.push(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
     }
 
-    for component in key_path.components() {
-        match component {
-            Component::Normal(_) => {}
-            Component::CurDir
-            | Component::ParentDir
-            | Component::RootDir
-            | Component::Prefix(_) => {
-                return Err(MaxioError::InvalidObjectName(key.to_string()));
+    Ok(keys)
+}
+
+async fn has_meta(object_path: &Path) -> bool {
+    fs::metadata(object_path.join(META_FILE_NAME))
+        .await
+        .is_ok_and(|meta| meta.is_file())
+}
+
+/// Removes `path` and the directories below it that hold no files, leaving
+/// any directory with content in place.
+async fn remove_empty_dirs(path: &Path) {
+    let mut dirs = Vec::new();
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_type().await.is_ok_and(|kind| kind.is_dir()) {
+                stack.push(entry.path());
             }
         }
+        dirs.push(dir);
+    }
+    // Children were found after their parents, so they are removed first.
+    for dir in dirs.iter().rev() {
+        let _ = fs::remove_dir(dir).await;
+    }
+}
+
+/// Creates one disk's copy of `bucket` under the set's versioning state.
+async fn make_bucket_copy(
+    storage: &XlStorage,
+    bucket: &str,
+    versioning: Option<VersioningState>,
+) -> Result<()> {
+    match storage.make_bucket(bucket).await {
+        Ok(()) | Err(MaxioError::BucketAlreadyExists(_)) => {}
+        Err(err) => return Err(err),
+    }
+    match versioning {
+        Some(state) if state != VersioningState::Unversioned => {
+            storage.set_bucket_versioning(bucket, state).await
+        }
+        _ => Ok(()),
     }
+}
 
+/// Writes one shard, syncing it to disk when `durable`.
+async fn write_shard(path: &Path, shard: &[u8], durable: bool) -> std::io::Result<()> {
+    let mut file = fs::File::create(path).await?;
+    file.write_all(shard).await?;
+    file.flush().await?;
+    if durable {
+        file.sync_all().await?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::erasure::health::{self, DiskState};
+
+    const TEST_PAYLOAD: &[u8] = b"erasure coded payload spanning a couple of blocks";
+
+    fn test_config() -> ErasureConfig {
+        ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            block_size: 64,
+            ..ErasureConfig::default()
+        }
+    }
+
+    async fn test_layer() -> (ErasureSet, Vec<PathBuf>) {
+        let root = std::env::temp_dir().join(format!("maxio-erasure-{}", Uuid::new_v4()));
+        let disks = (0..test_config().total_shards())
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect::<Vec<_>>();
+        let layer = ErasureSet::new(disks.clone(), test_config())
+            .await
+            .expect("create erasure layer");
+        layer.make_bucket("bucket").await.expect("make bucket");
+        layer
+            .put_object(
+                "bucket",
+                "object",
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put object");
+        (layer, disks)
+    }
+
+    /// Moves `disk` aside and leaves a file in its place. Returns where the
+    /// disk went.
+    async fn detach_disk(disk: &Path) -> PathBuf {
+        let detached = disk.with_extension("detached");
+        fs::rename(disk, &detached).await.expect("detach disk");
+        fs::write(disk, b"unmounted").await.expect("replace disk");
+        detached
+    }
+
+    async fn reattach_disk(disk: &Path, detached: &Path) {
+        fs::remove_file(disk).await.expect("remove placeholder");
+        fs::rename(detached, disk).await.expect("reattach disk");
+    }
+
+    async fn break_trash(disk: &Path) {
+        let sys_dir = disk.join(SYS_DIR_NAME);
+        fs::create_dir_all(&sys_dir).await.expect("create sys dir");
+        fs::write(sys_dir.join(TRASH_DIR_NAME), b"not a directory")
+            .await
+            .expect("block trash dir");
+    }
+
+    #[tokio::test]
+    async fn get_object_reconstructs_around_truncated_shard() {
+        let (layer, disks) = test_layer().await;
+        let part_path = disks[0]
+            .join("bucket/object/block_0")
+            .join(DATA_PART_FILE_NAME);
+        let original = fs::read(&part_path).await.expect("read shard");
+        fs::write(&part_path, &original[..original.len() / 2])
+            .await
+            .expect("truncate shard");
+
+        let (_, data) = layer
+            .get_object("bucket", "object", None)
+            .await
+            .expect("read via parity");
+        assert_eq!(data, Bytes::from_static(TEST_PAYLOAD));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn reduced_redundancy_put_uses_less_parity() {
+        let (layer, disks) = test_layer().await;
+        let metadata = HashMap::from([(
+            STORAGE_CLASS_META_KEY.to_string(),
+            REDUCED_REDUNDANCY_STORAGE_CLASS.to_string(),
+        )]);
+        layer
+            .put_object(
+                "bucket",
+                "cache",
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                metadata,
+                None,
+            )
+            .await
+            .expect("put reduced redundancy object");
+
+        let meta = layer
+            .read_meta_from_any("bucket", "cache")
+            .await
+            .expect("read meta");
+        assert_eq!(meta.erasure.parity_shards, 1);
+        assert_eq!(meta.erasure.data_shards, 3);
+        assert!(meta.metadata.is_empty());
+
+        let listing = layer
+            .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .expect("list objects");
+        let classes = listing
+            .objects
+            .iter()
+            .map(|object| (object.key.as_str(), object.storage_class.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                ("cache", Some(REDUCED_REDUNDANCY_STORAGE_CLASS)),
+                ("object", None)
+            ]
+        );
+
+        let (_, data) = layer
+            .get_object("bucket", "cache", None)
+            .await
+            .expect("read back");
+        assert_eq!(data, Bytes::from_static(TEST_PAYLOAD));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn multipart_object_keeps_its_parts_manifest() {
+        let (layer, disks) = test_layer().await;
+        let upload_id = layer
+            .create_multipart_upload("bucket", "multi", None, HashMap::new())
+            .await
+            .expect("create upload");
+        let mut parts = Vec::new();
+        for (part_number, data) in [(1, &b"erasure part one"[..]), (2, b"two")] {
+            let etag = layer
+                .upload_part(
+                    "bucket",
+                    "multi",
+                    &upload_id,
+                    part_number,
+                    Bytes::from(data),
+                )
+                .await
+                .expect("upload part");
+            parts.push(CompletePart { part_number, etag });
+        }
+        layer
+            .complete_multipart_upload("bucket", "multi", &upload_id, parts)
+            .await
+            .expect("complete upload");
+
+        let parts = layer
+            .stat_object_parts("bucket", "multi")
+            .await
+            .expect("stat parts");
+        let sizes = parts.iter().map(|part| part.size).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![16, 3]);
+        assert!(
+            layer
+                .stat_object_parts("bucket", "object")
+                .await
+                .expect("stat parts")
+                .is_empty()
+        );
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn list_objects_skips_and_reports_sub_quorum_objects() {
+        let (layer, disks) = test_layer().await;
+        let (sender, mut receiver) = mpsc::channel(8);
+        let layer = layer.with_heal_sender(sender);
+        layer
+            .put_object(
+                "bucket",
+                "partial",
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put partial object");
+        for disk in &disks[1..] {
+            fs::remove_file(disk.join("bucket/partial").join(META_FILE_NAME))
+                .await
+                .expect("drop metadata");
+        }
+
+        let listing = layer
+            .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .expect("list objects");
+        let keys = listing
+            .objects
+            .iter()
+            .map(|object| object.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["object"]);
+
+        let partial = receiver.try_recv().expect("partial object reported");
+        assert_eq!(partial.key, "partial");
+        assert_eq!(partial.disks_with_meta, vec![0]);
+        assert!(receiver.try_recv().is_err());
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn repeated_write_failures_take_disk_offline() {
+        let (layer, disks) = test_layer().await;
+        let (sender, mut receiver) = mpsc::channel(8);
+        let layer = layer.with_disk_health_sender(sender);
+
+        // Swap disk 0 for a file, as if it were unmounted, so every write to
+        // it fails and so does its probe.
+        detach_disk(&disks[0]).await;
+
+        for idx in 0..health::DEFAULT_OFFLINE_THRESHOLD {
+            layer
+                .put_object(
+                    "bucket",
+                    &format!("object-{idx}"),
+                    Bytes::from_static(TEST_PAYLOAD),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put with quorum");
+        }
+
+        let offline = receiver.try_recv().expect("offline event");
+        assert_eq!(offline.index, 0);
+        assert_eq!(offline.state, DiskState::Offline);
+        let states = layer
+            .disk_health()
+            .iter()
+            .map(|disk| disk.state)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![
+                DiskState::Offline,
+                DiskState::Online,
+                DiskState::Online,
+                DiskState::Online
+            ]
+        );
+
+        // Quorum is now counted over the three remaining disks: one more loss
+        // still leaves write quorum, a second does not.
+        for _ in 0..health::DEFAULT_OFFLINE_THRESHOLD {
+            layer.storage.health().record_failure(1, "simulated");
+        }
+        layer
+            .put_object(
+                "bucket",
+                "two-online",
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("two online disks still meet write quorum");
+
+        for _ in 0..health::DEFAULT_OFFLINE_THRESHOLD {
+            layer.storage.health().record_failure(2, "simulated");
+        }
+        let err = layer
+            .put_object(
+                "bucket",
+                "one-online",
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect_err("one online disk cannot meet write quorum");
+        assert!(matches!(
+            err,
+            MaxioError::QuorumUnavailable { needed: 2, have: 1 }
+        ));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn object_path_conflicts_spare_the_disk_and_detached_disks_recover() {
+        let (layer, disks) = test_layer().await;
+        let put = |key: &'static str| {
+            layer.put_object(
+                "bucket",
+                key,
+                Bytes::from_static(TEST_PAYLOAD),
+                None,
+                HashMap::new(),
+                None,
+            )
+        };
+
+        // A file where the object's directory belongs fails every write of
+        // that object, but the disk itself is fine.
+        fs::write(disks[0].join("bucket/blocked"), b"file")
+            .await
+            .expect("block object dir");
+        for _ in 0..health::DEFAULT_OFFLINE_THRESHOLD {
+            put("blocked").await.expect("put with quorum");
+        }
+        assert!(layer.storage.health().is_online(0));
+
+        let detached = detach_disk(&disks[0]).await;
+        for _ in 0..health::DEFAULT_OFFLINE_THRESHOLD {
+            put("other").await.expect("put with quorum");
+        }
+        assert!(!layer.storage.health().is_online(0));
+        assert_eq!(layer.recover_offline_disks().await, 0);
+
+        reattach_disk(&disks[0], &detached).await;
+        assert_eq!(layer.recover_offline_disks().await, 1);
+        assert!(layer.storage.health().is_online(0));
+        put("after").await.expect("put after recovery");
+        assert!(disks[0].join("bucket/after").join(META_FILE_NAME).exists());
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn delete_object_succeeds_when_failures_leave_quorum() {
+        let (layer, disks) = test_layer().await;
+        break_trash(&disks[0]).await;
+        break_trash(&disks[1]).await;
+
+        layer
+            .delete_object("bucket", "object")
+            .await
+            .expect("delete with quorum");
+
+        for disk in &disks[..2] {
+            assert!(disk.join("bucket/object").join(META_FILE_NAME).exists());
+        }
+        for disk in &disks[2..] {
+            assert!(!disk.join("bucket/object").exists());
+        }
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn conditional_delete_only_removes_the_expected_etag() {
+        let (layer, disks) = test_layer().await;
+        let stale = DeleteCondition::ETag("\"0123456789abcdef0123456789abcdef\"".to_string());
+        assert!(matches!(
+            layer.delete_object_if("bucket", "object", &stale).await,
+            Err(MaxioError::PreconditionFailed(_))
+        ));
+        assert!(
+            layer
+                .get_object_info("bucket", "object", None)
+                .await
+                .is_ok()
+        );
+
+        let current = DeleteCondition::ETag(md5_hex(TEST_PAYLOAD));
+        layer
+            .delete_object_if("bucket", "object", &current)
+            .await
+            .expect("delete matching etag");
+        assert!(matches!(
+            layer.get_object_info("bucket", "object", None).await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn delete_object_without_quorum_fails_and_restores_object() {
+        let (layer, disks) = test_layer().await;
+        for disk in &disks[..3] {
+            break_trash(disk).await;
+        }
+
+        let err = layer
+            .delete_object("bucket", "object")
+            .await
+            .expect_err("delete should miss quorum");
+        assert!(matches!(err, MaxioError::QuorumUnavailable { .. }));
+
+        for disk in &disks {
+            assert!(disk.join("bucket/object").join(META_FILE_NAME).exists());
+        }
+        let (_, data) = layer
+            .get_object("bucket", "object", None)
+            .await
+            .expect("object still readable");
+        assert_eq!(data, Bytes::from_static(TEST_PAYLOAD));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn readers_prefer_the_newest_metadata_generation() {
+        let (layer, disks) = test_layer().await;
+        let meta_path = |disk: &PathBuf| disk.join("bucket/object").join(META_FILE_NAME);
+        let first = fs::read(meta_path(&disks[0]))
+            .await
+            .expect("read first meta");
+
+        let metadata = HashMap::from([("stage".to_string(), "second".to_string())]);
+        layer
+            .update_object_metadata("bucket", "object", None, metadata.clone())
+            .await
+            .expect("update metadata");
+
+        // The first disk missed the update and still holds generation 1.
+        fs::write(meta_path(&disks[0]), &first)
+            .await
+            .expect("restore stale meta");
+
+        let meta = layer
+            .read_meta_from_any("bucket", "object")
+            .await
+            .expect("read meta");
+        assert_eq!(meta.generation, 2);
+        assert_eq!(meta.metadata, metadata);
+
+        let listing = layer
+            .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .expect("list objects");
+        assert_eq!(listing.objects[0].metadata, metadata);
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn buckets_on_part_of_the_disks_are_reconciled() {
+        let (layer, disks) = test_layer().await;
+
+        // Disk 3 fails the create; the other three make up write quorum.
+        fs::write(disks[3].join("photos"), b"not a directory")
+            .await
+            .expect("block bucket dir");
+        layer.make_bucket("photos").await.expect("make bucket");
+        assert!(!disks[3].join("photos").is_dir());
+
+        fs::remove_file(disks[3].join("photos"))
+            .await
+            .expect("unblock bucket dir");
+        layer
+            .get_bucket_info("photos")
+            .await
+            .expect("bucket on quorum exists");
+        assert!(disks.iter().all(|disk| disk.join("photos").is_dir()));
+        layer.delete_bucket("photos").await.expect("delete bucket");
+        assert!(disks.iter().all(|disk| !disk.join("photos").exists()));
+
+        // Three failing disks leave no copy behind.
+        for disk in &disks[1..] {
+            fs::write(disk.join("lost"), b"not a directory")
+                .await
+                .expect("block bucket dir");
+        }
+        assert!(matches!(
+            layer.make_bucket("lost").await,
+            Err(MaxioError::QuorumUnavailable { needed: 2, have: 1 })
+        ));
+        assert!(!disks[0].join("lost").exists());
+
+        // A copy below read quorum is a leftover and gets removed.
+        fs::create_dir(disks[0].join("stale"))
+            .await
+            .expect("create stale bucket dir");
+        assert!(matches!(
+            layer.get_bucket_info("stale").await,
+            Err(MaxioError::BucketNotFound(_))
+        ));
+        assert!(!layer.reconcile_bucket("stale").await.expect("reconcile"));
+        assert!(!disks[0].join("stale").exists());
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn renamed_prefix_moves_every_shard() {
+        let (layer, disks) = test_layer().await;
+        for key in ["logs/a", "logs/deep/b", "archive/removed"] {
+            layer
+                .put_object(
+                    "bucket",
+                    key,
+                    Bytes::from(key.as_bytes().to_vec()),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+        }
+        // Deleting leaves the empty `archive` directory behind on every disk.
+        layer
+            .delete_object("bucket", "archive/removed")
+            .await
+            .expect("delete object");
+
+        layer
+            .rename_prefix("bucket", "logs/", "archive/")
+            .await
+            .expect("rename prefix");
+
+        for (old, new) in [("logs/a", "archive/a"), ("logs/deep/b", "archive/deep/b")] {
+            let (_, data) = layer
+                .get_object("bucket", new, None)
+                .await
+                .expect("read renamed object");
+            assert_eq!(data.as_ref(), old.as_bytes());
+            assert!(matches!(
+                layer.get_object_info("bucket", old, None).await,
+                Err(MaxioError::ObjectNotFound { .. })
+            ));
+        }
+        for disk in &disks {
+            assert!(!disk.join("bucket").join("logs").exists());
+        }
+        assert!(matches!(
+            layer.rename_prefix("bucket", "logs/", "other/").await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copy_links_shards_without_decoding() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (layer, disks) = test_layer().await;
+        let source = CopySource {
+            bucket: "bucket".to_string(),
+            key: "object".to_string(),
+            version_id: None,
+            encryption: None,
+        };
+        let (_, info) = layer
+            .copy_object(
+                &source,
+                "bucket",
+                "copy",
+                &|source| Ok((None, source.metadata.clone())),
+                None,
+            )
+            .await
+            .expect("copy object");
+        assert_eq!(info.etag, md5_hex(TEST_PAYLOAD));
+        assert_eq!(info.content_type, DEFAULT_CONTENT_TYPE);
+
+        for disk in &disks {
+            let shard = fs::metadata(disk.join("bucket/copy/block_0").join(DATA_PART_FILE_NAME))
+                .await
+                .expect("stat copied shard");
+            assert_eq!(shard.nlink(), 2);
+        }
+
+        layer
+            .delete_object("bucket", "object")
+            .await
+            .expect("delete source");
+        let (_, data) = layer
+            .get_object("bucket", "copy", None)
+            .await
+            .expect("read copy");
+        assert_eq!(data, Bytes::from_static(TEST_PAYLOAD));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+}
@@ -0,0 +1,885 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use maxio_common::error::{MaxioError, Result};
+use maxio_common::types::{BucketInfo, ObjectInfo};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::erasure::health::DiskHealth;
+use crate::erasure::objects::ErasureSet;
+use crate::erasure::{ErasureConfig, PartialObject};
+use crate::naming::validate_prefix_rename;
+use crate::storage_info::StorageInfo;
+use crate::traits::{
+    CompletePart, CopyMetadataFn, CopySource, DeleteCondition, DeletedObject, GetEncryptionOptions,
+    ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult, ListOrder,
+    ObjectLayer, ObjectPartInfo, PartInfo, PutEncryptionOptions, STORAGE_CLASS_META_KEY,
+    VersioningState, copy_by_reading,
+};
+use crate::xl::storage::{paginate_objects, paginate_uploads, paginate_versions};
+
+/// Erasure-coded object layer made of one or more equally sized sets. Every
+/// object lives entirely inside the set chosen by hashing its bucket and key,
+/// so each set tolerates its own parity count of disk failures.
+#[derive(Debug, Clone)]
+pub struct ErasureObjectLayer {
+    sets: Vec<ErasureSet>,
+}
+
+impl ErasureObjectLayer {
+    /// Splits `disk_paths` into sets of `config.total_shards()` disks each.
+    pub async fn new(disk_paths: Vec<PathBuf>, config: ErasureConfig) -> Result<Self> {
+        config.validate()?;
+        let set_size = config.total_shards();
+        if disk_paths.is_empty() || !disk_paths.len().is_multiple_of(set_size) {
+            return Err(MaxioError::InvalidArgument(format!(
+                "disk count {} is not a multiple of erasure set size {set_size}",
+                disk_paths.len()
+            )));
+        }
+
+        let mut sets = Vec::with_capacity(disk_paths.len() / set_size);
+        for disks in disk_paths.chunks(set_size) {
+            sets.push(ErasureSet::new(disks.to_vec(), config.clone()).await?);
+        }
+
+        Ok(Self { sets })
+    }
+
+    /// Reports objects found below read quorum during listing so they can be
+    /// queued for heal.
+    pub fn with_heal_sender(mut self, sender: mpsc::Sender<PartialObject>) -> Self {
+        self.sets = self
+            .sets
+            .into_iter()
+            .map(|set| set.with_heal_sender(sender.clone()))
+            .collect();
+        self
+    }
+
+    pub fn with_disk_health_sender(mut self, sender: mpsc::Sender<DiskHealth>) -> Self {
+        self.sets = self
+            .sets
+            .into_iter()
+            .map(|set| set.with_disk_health_sender(sender.clone()))
+            .collect();
+        self
+    }
+
+    /// Probes every offline disk and brings back those that pass. Returns
+    /// how many came back.
+    pub async fn recover_offline_disks(&self) -> usize {
+        let mut recovered = 0;
+        for set in &self.sets {
+            recovered += set.recover_offline_disks().await;
+        }
+        recovered
+    }
+
+    /// Probes offline disks once every `interval`, so a disk that was
+    /// unplugged or full rejoins quorum once it works again.
+    pub fn start_disk_recovery(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let layer = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                layer.recover_offline_disks().await;
+            }
+        })
+    }
+
+    /// Health of every disk, grouped by set in construction order.
+    pub fn disk_health(&self) -> Vec<Vec<DiskHealth>> {
+        self.sets.iter().map(ErasureSet::disk_health).collect()
+    }
+
+    pub fn set_count(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Stable placement of an object onto a set. The hash must not change
+    /// between releases, otherwise existing objects would become unreachable.
+    pub fn set_index(&self, bucket: &str, key: &str) -> usize {
+        let digest = Sha256::digest(format!("{bucket}/{key}").as_bytes());
+        let mut prefix = [0_u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(prefix) % self.sets.len() as u64) as usize
+    }
+
+    fn set_for(&self, bucket: &str, key: &str) -> &ErasureSet {
+        &self.sets[self.set_index(bucket, key)]
+    }
+
+    fn first_set(&self) -> &ErasureSet {
+        &self.sets[0]
+    }
+}
+
+#[async_trait]
+impl ObjectLayer for ErasureObjectLayer {
+    async fn make_bucket(&self, bucket: &str) -> Result<()> {
+        // Every set must hold the bucket, so a set failing the create rolls
+        // back the sets that already took it.
+        let mut created = Vec::new();
+        let mut already_exists = 0_usize;
+        for (set_idx, set) in self.sets.iter().enumerate() {
+            match set.make_bucket(bucket).await {
+                Ok(()) => created.push(set_idx),
+                Err(MaxioError::BucketAlreadyExists(_)) => already_exists += 1,
+                Err(err) => {
+                    for set_idx in created {
+                        if let Err(rollback) = self.sets[set_idx].delete_bucket(bucket).await {
+                            warn!(
+                                set = set_idx,
+                                bucket,
+                                error = %rollback,
+                                "failed to roll back bucket creation"
+                            );
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        if already_exists == self.sets.len() {
+            return Err(MaxioError::BucketAlreadyExists(bucket.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Merged across sets, so a bucket only some sets hold is still found.
+    /// It reports the earliest creation time any set recorded.
+    async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+        let mut merged: Option<BucketInfo> = None;
+        for set in &self.sets {
+            match set.get_bucket_info(bucket).await {
+                Ok(info) => merge_bucket_info(&mut merged, info),
+                Err(MaxioError::BucketNotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        merged.ok_or_else(|| MaxioError::BucketNotFound(bucket.to_string()))
+    }
+
+    async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+        let mut merged = BTreeMap::<String, Option<BucketInfo>>::new();
+        for set in &self.sets {
+            for info in set.list_buckets().await? {
+                merge_bucket_info(merged.entry(info.name.clone()).or_default(), info);
+            }
+        }
+        Ok(merged.into_values().flatten().collect())
+    }
+
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        for set in &self.sets {
+            match set.quorum_objects(bucket, "").await {
+                Ok(objects) if !objects.is_empty() => {
+                    return Err(MaxioError::InvalidArgument(format!(
+                        "bucket is not empty: {bucket}"
+                    )));
+                }
+                Ok(_) | Err(MaxioError::BucketNotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut missing = 0_usize;
+        for set in &self.sets {
+            match set.delete_bucket(bucket).await {
+                Ok(()) => {}
+                Err(MaxioError::BucketNotFound(_)) => missing += 1,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if missing == self.sets.len() {
+            return Err(MaxioError::BucketNotFound(bucket.to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+        self.first_set().get_bucket_versioning(bucket).await
+    }
+
+    async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+        // Read every set's state first, so a set failing the change can be
+        // put back and the sets never disagree.
+        let mut previous = Vec::with_capacity(self.sets.len());
+        for set in &self.sets {
+            previous.push(set.get_bucket_versioning(bucket).await?);
+        }
+
+        for (set_idx, set) in self.sets.iter().enumerate() {
+            if let Err(err) = set.set_bucket_versioning(bucket, state).await {
+                for (changed_idx, changed) in self.sets[..set_idx].iter().enumerate() {
+                    if let Err(rollback) = changed
+                        .set_bucket_versioning(bucket, previous[changed_idx])
+                        .await
+                    {
+                        warn!(
+                            set = changed_idx,
+                            bucket,
+                            error = %rollback,
+                            "failed to roll back bucket versioning"
+                        );
+                    }
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.set_for(bucket, key)
+            .put_object(bucket, key, data, content_type, metadata, encryption)
+            .await
+    }
+
+    async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        self.set_for(bucket, key)
+            .get_object(bucket, key, encryption)
+            .await
+    }
+
+    async fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        self.set_for(bucket, key)
+            .get_object_version(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn get_object_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.set_for(bucket, key)
+            .get_object_info(bucket, key, encryption)
+            .await
+    }
+
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.set_for(bucket, key)
+            .get_object_version_info(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        self.set_for(bucket, key)
+            .update_object_metadata(bucket, key, content_type, metadata)
+            .await
+    }
+
+    /// Shards can only be shared within a set, so a copy whose key hashes
+    /// to another set than its source is read and written again.
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        let set_idx = self.set_index(bucket, key);
+        if set_idx != self.set_index(&source.bucket, &source.key) {
+            return copy_by_reading(self, source, bucket, key, metadata, encryption).await;
+        }
+        self.sets[set_idx]
+            .copy_object(source, bucket, key, metadata, encryption)
+            .await
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+        self.set_for(bucket, key).delete_object(bucket, key).await
+    }
+
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject> {
+        self.set_for(bucket, key)
+            .delete_object_if(bucket, key, condition)
+            .await
+    }
+
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject> {
+        self.set_for(bucket, key)
+            .delete_object_version(bucket, key, version_id)
+            .await
+    }
+
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+        order: ListOrder,
+    ) -> Result<ListObjectsResult> {
+        let mut objects = Vec::new();
+        for set in &self.sets {
+            objects.extend(set.quorum_objects(bucket, prefix).await?);
+        }
+
+        Ok(paginate_objects(
+            objects, prefix, marker, delimiter, max_keys, order,
+        ))
+    }
+
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+    ) -> Result<ListObjectVersionsResult> {
+        let mut versions = Vec::new();
+        for set in &self.sets {
+            let listed = set
+                .list_object_versions(bucket, prefix, "", "", "", 0)
+                .await?;
+            versions.extend(listed.versions);
+        }
+
+        Ok(paginate_versions(
+            versions,
+            prefix,
+            key_marker,
+            version_id_marker,
+            delimiter,
+            max_keys,
+        ))
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        self.set_for(bucket, key)
+            .create_multipart_upload(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+    ) -> Result<String> {
+        self.set_for(bucket, key)
+            .upload_part(bucket, key, upload_id, part_number, data)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletePart>,
+    ) -> Result<ObjectInfo> {
+        self.set_for(bucket, key)
+            .complete_multipart_upload(bucket, key, upload_id, parts)
+            .await
+    }
+
+    async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        self.set_for(bucket, key)
+            .abort_multipart_upload(bucket, key, upload_id)
+            .await
+    }
+
+    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>> {
+        self.set_for(bucket, key)
+            .list_parts(bucket, key, upload_id)
+            .await
+    }
+
+    async fn list_multipart_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
+        let mut uploads = Vec::new();
+        for set in &self.sets {
+            let listed = set
+                .list_multipart_uploads(bucket, prefix, "", "", "", 0)
+                .await?;
+            uploads.extend(listed.uploads);
+        }
+
+        Ok(paginate_uploads(
+            uploads,
+            prefix,
+            key_marker,
+            upload_id_marker,
+            delimiter,
+            max_uploads,
+        ))
+    }
+
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+        self.set_for(bucket, key)
+            .stat_object_parts(bucket, key)
+            .await
+    }
+
+    async fn rename_prefix(&self, bucket: &str, from_prefix: &str, to_prefix: &str) -> Result<()> {
+        if let [set] = self.sets.as_slice() {
+            return set.rename_prefix(bucket, from_prefix, to_prefix).await;
+        }
+
+        // The set of a key follows from its name, so renamed objects mostly
+        // belong to another set and are copied there rather than moved.
+        validate_prefix_rename(from_prefix, to_prefix)?;
+        let mut objects = Vec::new();
+        for set in &self.sets {
+            if !set.quorum_objects(bucket, to_prefix).await?.is_empty() {
+                return Err(MaxioError::PreconditionFailed(format!(
+                    "destination prefix {to_prefix} is not empty"
+                )));
+            }
+            objects.extend(set.quorum_objects(bucket, from_prefix).await?);
+        }
+        if objects.is_empty() {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: from_prefix.to_string(),
+            });
+        }
+
+        for object in objects {
+            let new_key = format!("{to_prefix}{}", &object.key[from_prefix.len()..]);
+            let (info, data) = self.get_object(bucket, &object.key, None).await?;
+            let mut metadata = info.metadata;
+            if let Some(storage_class) = info.storage_class {
+                metadata.insert(STORAGE_CLASS_META_KEY.to_string(), storage_class);
+            }
+            self.put_object(
+                bucket,
+                &new_key,
+                data,
+                Some(&info.content_type),
+                metadata,
+                None,
+            )
+            .await?;
+            self.delete_object(bucket, &object.key).await?;
+        }
+        Ok(())
+    }
+
+    async fn storage_info(&self) -> StorageInfo {
+        let mut disks = Vec::new();
+        for (set_index, set) in self.sets.iter().enumerate() {
+            disks.extend(set.storage_info().await.disks.into_iter().map(|mut disk| {
+                disk.set_index = set_index;
+                disk
+            }));
+        }
+        StorageInfo { disks }
+    }
+
+    async fn record_access(&self, bucket: &str, key: &str, accessed: DateTime<Utc>) -> Result<()> {
+        self.set_for(bucket, key)
+            .record_access(bucket, key, accessed)
+            .await
+    }
+
+    async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let mut removed = 0;
+        for set in &self.sets {
+            removed += set.remove_stale_multipart_uploads(cutoff).await?;
+        }
+        Ok(removed)
+    }
+
+    /// Every set must be writable, since any of them may own the next key.
+    fn has_write_quorum(&self) -> bool {
+        self.sets.iter().all(ErasureSet::has_write_quorum)
+    }
+}
+
+/// Keeps the earliest creation time when sets report the same bucket.
+fn merge_bucket_info(merged: &mut Option<BucketInfo>, info: BucketInfo) {
+    if merged
+        .as_ref()
+        .is_none_or(|existing| info.created < existing.created)
+    {
+        *merged = Some(info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tokio::fs;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::storage_info::DiskState;
+
+    fn test_config() -> ErasureConfig {
+        ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            block_size: 64,
+            ..ErasureConfig::default()
+        }
+    }
+
+    fn test_disks(count: usize) -> Vec<PathBuf> {
+        let root = std::env::temp_dir().join(format!("maxio-erasure-sets-{}", Uuid::new_v4()));
+        (0..count)
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect()
+    }
+
+    async fn object_count(disk: &Path, bucket: &str) -> usize {
+        let mut entries = match fs::read_dir(disk.join(bucket)).await {
+            Ok(entries) => entries,
+            Err(_) => return 0,
+        };
+        let mut count = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.path().join("xl.meta").is_file() {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn new_rejects_mismatched_set_layout() {
+        let disks = test_disks(6);
+        let err = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect_err("6 disks cannot form sets of 4");
+        assert!(matches!(err, MaxioError::InvalidArgument(_)));
+
+        let config = ErasureConfig {
+            read_quorum: Some(1),
+            ..test_config()
+        };
+        let err = ErasureObjectLayer::new(disks[..4].to_vec(), config)
+            .await
+            .expect_err("read quorum below data shards");
+        assert!(matches!(err, MaxioError::InvalidArgument(_)));
+    }
+
+    #[tokio::test]
+    async fn new_rejects_disks_formatted_with_another_layout() {
+        let disks = test_disks(4);
+        ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("format disks");
+        ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("restart with the same layout");
+
+        let config = ErasureConfig {
+            data_shards: 3,
+            parity_shards: 1,
+            ..test_config()
+        };
+        let err = ErasureObjectLayer::new(disks.clone(), config)
+            .await
+            .expect_err("data shard count differs from the disks");
+        assert!(
+            matches!(&err, MaxioError::InvalidArgument(message) if message.contains("2 data + 2 parity"))
+        );
+    }
+
+    #[tokio::test]
+    async fn objects_spread_across_sets_and_survive_parity_failures_per_set() {
+        let disks = test_disks(8);
+        let layer = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("create layer");
+        assert_eq!(layer.set_count(), 2);
+        layer.make_bucket("bucket").await.expect("make bucket");
+
+        let mut per_set = [0_usize; 2];
+        for idx in 0..16 {
+            let key = format!("object-{idx}");
+            per_set[layer.set_index("bucket", &key)] += 1;
+            layer
+                .put_object(
+                    "bucket",
+                    &key,
+                    Bytes::from(format!("payload for {key}")),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+        }
+        assert!(per_set.iter().all(|count| *count > 0));
+        assert_eq!(object_count(&disks[0], "bucket").await, per_set[0]);
+        assert_eq!(object_count(&disks[4], "bucket").await, per_set[1]);
+
+        // Lose parity_shards disks in every set at once.
+        for disk in [&disks[0], &disks[1], &disks[6], &disks[7]] {
+            fs::remove_dir_all(disk.join("bucket"))
+                .await
+                .expect("wipe disk");
+        }
+
+        for idx in 0..16 {
+            let key = format!("object-{idx}");
+            let (_, data) = layer
+                .get_object("bucket", &key, None)
+                .await
+                .expect("read through parity");
+            assert_eq!(data, Bytes::from(format!("payload for {key}")));
+        }
+
+        let listing = layer
+            .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .expect("list objects");
+        assert_eq!(listing.objects.len(), 16);
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn failed_bucket_creation_rolls_back_every_set() {
+        let disks = test_disks(8);
+        let layer = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("create layer");
+
+        // Set 1 loses write quorum: three of its disks turn into files.
+        for disk in &disks[5..] {
+            fs::remove_dir_all(disk).await.expect("remove disk");
+            fs::write(disk, b"not a disk").await.expect("block disk");
+        }
+        layer
+            .make_bucket("bucket")
+            .await
+            .expect_err("set 1 cannot take the bucket");
+
+        assert!(!disks[0].join("bucket").exists());
+        assert!(layer.list_buckets().await.expect("list buckets").is_empty());
+        assert!(matches!(
+            layer.get_bucket_info("bucket").await,
+            Err(MaxioError::BucketNotFound(_))
+        ));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn buckets_are_merged_across_sets() {
+        let disks = test_disks(8);
+        let layer = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("create layer");
+        layer.make_bucket("shared").await.expect("make bucket");
+        // Left behind on one set only, as an interrupted create would.
+        layer.sets[1]
+            .make_bucket("partial")
+            .await
+            .expect("make bucket on set 1");
+
+        let names = layer
+            .list_buckets()
+            .await
+            .expect("list buckets")
+            .into_iter()
+            .map(|info| info.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["partial", "shared"]);
+        assert_eq!(
+            layer
+                .get_bucket_info("partial")
+                .await
+                .expect("bucket info")
+                .name,
+            "partial"
+        );
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn storage_info_reports_every_disk_and_removed_ones_offline() {
+        let disks = test_disks(8);
+        let layer = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("create layer");
+
+        let info = layer.storage_info().await;
+        assert_eq!(info.disks.len(), 8);
+        assert_eq!(info.online_disks(), 8);
+        let placement = info
+            .disks
+            .iter()
+            .map(|disk| (disk.set_index, disk.disk_index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            placement,
+            vec![
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (0, 3),
+                (1, 0),
+                (1, 1),
+                (1, 2),
+                (1, 3)
+            ]
+        );
+        assert!(
+            info.disks.iter().all(|disk| disk.total_bytes > 0
+                && disk.used_bytes == disk.total_bytes - disk.free_bytes)
+        );
+
+        fs::remove_dir_all(&disks[5]).await.expect("remove disk");
+        let info = layer.storage_info().await;
+        assert_eq!(info.online_disks(), 7);
+        assert_eq!(info.offline_disks(), 1);
+        let offline = info
+            .disks
+            .iter()
+            .find(|disk| disk.state == DiskState::Offline)
+            .expect("offline disk");
+        assert_eq!(offline.path, disks[5]);
+        assert_eq!((offline.set_index, offline.disk_index), (1, 1));
+        assert_eq!(offline.free_bytes, 0);
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn access_times_are_recorded_and_listed_from_every_set() {
+        let disks = test_disks(8);
+        let layer = ErasureObjectLayer::new(disks.clone(), test_config())
+            .await
+            .expect("create layer");
+        layer.make_bucket("bucket").await.expect("make bucket");
+        let keys = (0..8)
+            .map(|idx| format!("object-{idx}"))
+            .collect::<Vec<_>>();
+        for key in &keys {
+            layer
+                .put_object(
+                    "bucket",
+                    key,
+                    Bytes::from_static(b"payload"),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+        }
+        let written = layer
+            .get_object_info("bucket", "object-3", None)
+            .await
+            .expect("stat object");
+
+        let accessed = Utc::now();
+        layer
+            .record_access("bucket", "object-3", accessed)
+            .await
+            .expect("record access");
+
+        let versions = layer
+            .list_object_versions("bucket", "", "", "", "", 1000)
+            .await
+            .expect("list versions")
+            .versions;
+        assert_eq!(versions.len(), keys.len());
+        let stamped = versions
+            .iter()
+            .find(|version| version.key == "object-3")
+            .expect("stamped object listed");
+        assert_eq!(stamped.last_access, Some(accessed));
+        assert_eq!(stamped.last_modified, written.last_modified);
+        assert_eq!(stamped.etag.as_deref(), Some(written.etag.as_str()));
+        assert!(
+            versions
+                .iter()
+                .filter(|version| version.key != "object-3")
+                .all(|version| version.last_access.is_none())
+        );
+
+        layer
+            .delete_object_version("bucket", "object-3", &stamped.version_id)
+            .await
+            .expect("delete listed version");
+        assert!(matches!(
+            layer.get_object_info("bucket", "object-3", None).await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+
+        let _ = fs::remove_dir_all(disks[0].parent().unwrap()).await;
+    }
+}
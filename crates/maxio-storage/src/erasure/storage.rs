@@ -1,10 +1,39 @@
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
 use maxio_common::error::{MaxioError, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use uuid::Uuid;
 
 use crate::erasure::ErasureConfig;
+use crate::erasure::health::{DEFAULT_OFFLINE_THRESHOLD, DiskHealthTracker};
 use crate::xl::storage::XlStorage;
 
+/// Where each disk records the erasure layout it was formatted with.
+const FORMAT_FILE_PATH: &str = ".maxio.sys/format.json";
+/// Where disk probes write their scratch files.
+const PROBE_DIR_PATH: &str = ".maxio.sys";
+
+/// The parts of an [`ErasureConfig`] that decide how data sits on disk.
+/// Shards written under one layout cannot be decoded under another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DiskFormat {
+    data_shards: usize,
+    parity_shards: usize,
+    block_size: usize,
+}
+
+impl From<&ErasureConfig> for DiskFormat {
+    fn from(config: &ErasureConfig) -> Self {
+        Self {
+            data_shards: config.data_shards,
+            parity_shards: config.parity_shards,
+            block_size: config.block_size,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DiskShard {
     pub(crate) path: PathBuf,
@@ -15,6 +44,7 @@ pub struct DiskShard {
 pub struct ErasureStorage {
     config: ErasureConfig,
     shards: Vec<DiskShard>,
+    health: DiskHealthTracker,
 }
 
 impl ErasureStorage {
@@ -27,13 +57,19 @@ impl ErasureStorage {
             )));
         }
 
+        let health = DiskHealthTracker::new(&disk_paths, DEFAULT_OFFLINE_THRESHOLD);
         let mut shards = Vec::with_capacity(disk_paths.len());
         for path in disk_paths {
             let storage = XlStorage::new(path.clone()).await?;
             shards.push(DiskShard { path, storage });
         }
+        check_disk_formats(&shards, &config).await?;
 
-        Ok(Self { config, shards })
+        Ok(Self {
+            config,
+            shards,
+            health,
+        })
     }
 
     pub fn config(&self) -> &ErasureConfig {
@@ -55,4 +91,102 @@ impl ErasureStorage {
     pub fn shards(&self) -> &[DiskShard] {
         &self.shards
     }
+
+    pub fn health(&self) -> &DiskHealthTracker {
+        &self.health
+    }
+
+    /// Counts a failed operation against disk `index` when the disk is at
+    /// fault. Errors about the paths an object occupies, such as a file
+    /// where a directory should be, only count if the disk also fails a
+    /// probe, so one damaged object cannot take a healthy disk offline.
+    pub async fn record_failure(&self, index: usize, err: std::io::Error) {
+        if is_namespace_error(&err) && self.probe_disk(index).await.is_ok() {
+            return;
+        }
+        self.health.record_failure(index, err);
+    }
+
+    /// Checks that disk `index` still carries its format and takes writes.
+    pub async fn probe_disk(&self, index: usize) -> std::io::Result<()> {
+        let path = self
+            .shard_path(index)
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "no such disk"))?;
+        fs::read(path.join(FORMAT_FILE_PATH)).await?;
+        let probe = path
+            .join(PROBE_DIR_PATH)
+            .join(format!("probe-{}", Uuid::new_v4()));
+        fs::write(&probe, b"probe").await?;
+        fs::remove_file(&probe).await
+    }
+
+    /// Probes every offline disk and brings back those that pass. Returns
+    /// how many came back.
+    pub async fn recover_offline_disks(&self) -> usize {
+        let mut recovered = 0;
+        for index in 0..self.shard_count() {
+            if !self.health.is_online(index) && self.probe_disk(index).await.is_ok() {
+                self.health.mark_online(index);
+                recovered += 1;
+            }
+        }
+        recovered
+    }
+}
+
+/// Errors caused by what a disk holds rather than by the disk itself.
+fn is_namespace_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::NotFound
+            | ErrorKind::AlreadyExists
+            | ErrorKind::NotADirectory
+            | ErrorKind::IsADirectory
+            | ErrorKind::DirectoryNotEmpty
+            | ErrorKind::InvalidInput
+            | ErrorKind::InvalidFilename
+    )
+}
+
+/// Refuses disks formatted with a different erasure layout than `config`,
+/// then stamps the layout onto disks that have none yet: fresh disks, and
+/// disks from before the layout was recorded.
+async fn check_disk_formats(shards: &[DiskShard], config: &ErasureConfig) -> Result<()> {
+    let expected = DiskFormat::from(config);
+    let mut unformatted = Vec::new();
+    for shard in shards {
+        let format_path = shard.path.join(FORMAT_FILE_PATH);
+        let bytes = match fs::read(&format_path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                unformatted.push(format_path);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let format = serde_json::from_slice::<DiskFormat>(&bytes).map_err(|err| {
+            MaxioError::InternalError(format!("unreadable {}: {err}", format_path.display()))
+        })?;
+        if format != expected {
+            return Err(MaxioError::InvalidArgument(format!(
+                "disk {} was formatted with {} data + {} parity shards and {}-byte blocks, \
+                 but the server is configured for {} data + {} parity shards and {}-byte blocks",
+                shard.path.display(),
+                format.data_shards,
+                format.parity_shards,
+                format.block_size,
+                expected.data_shards,
+                expected.parity_shards,
+                expected.block_size
+            )));
+        }
+    }
+
+    let bytes = serde_json::to_vec(&expected).map_err(|err| {
+        MaxioError::InternalError(format!("failed to serialize disk format: {err}"))
+    })?;
+    for format_path in unformatted {
+        fs::write(format_path, &bytes).await?;
+    }
+    Ok(())
 }
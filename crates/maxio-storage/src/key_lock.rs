@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+type LockTable = HashMap<(String, String), Weak<AsyncMutex<()>>>;
+
+/// Table size past which released entries are swept on the next lock.
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// In-process write locks per object key. Layers take the lock around every
+/// write to a key so that a read-check-write sequence, such as a conditional
+/// delete, cannot interleave with another write to the same key.
+#[derive(Debug, Clone, Default)]
+pub struct KeyLocks {
+    locks: Arc<Mutex<LockTable>>,
+}
+
+impl KeyLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits for exclusive access to `bucket/key`, held until the guard is
+    /// dropped.
+    pub async fn lock(&self, bucket: &str, key: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self
+                .locks
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if locks.len() >= SWEEP_THRESHOLD {
+                locks.retain(|_, lock| lock.strong_count() > 0);
+            }
+            let entry = locks
+                .entry((bucket.to_string(), key.to_string()))
+                .or_default();
+            match entry.upgrade() {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(AsyncMutex::new(()));
+                    *entry = Arc::downgrade(&lock);
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn same_key_is_exclusive_and_other_keys_are_not() {
+        let locks = KeyLocks::new();
+        let held = locks.lock("bucket", "a").await;
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), locks.lock("bucket", "b"))
+                .await
+                .is_ok()
+        );
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), locks.lock("bucket", "a"))
+                .await
+                .is_err()
+        );
+
+        drop(held);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), locks.lock("bucket", "a"))
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -1,6 +1,13 @@
+pub mod access_tracker;
 pub mod datatypes;
 pub mod erasure;
+pub mod key_lock;
+pub mod listing_cache;
+pub mod multipart_sweep;
+pub mod naming;
 pub mod pool;
+pub mod read_cache;
 pub mod single;
+pub mod storage_info;
 pub mod traits;
 pub mod xl;
@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use maxio_common::error::Result;
+use maxio_common::types::{BucketInfo, ObjectInfo};
+
+use crate::storage_info::StorageInfo;
+use crate::traits::{
+    ChecksumReport, CompletePart, CopyMetadataFn, CopySource, DeleteCondition, DeletedObject,
+    GetEncryptionOptions, ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult,
+    ListOrder, ObjectLayer, ObjectPartInfo, PartInfo, PutEncryptionOptions, VersioningState,
+};
+
+/// Settings for caching `list_objects` results. A zero `ttl` or
+/// `max_entries` disables the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingCacheConfig {
+    pub ttl: Duration,
+    pub max_entries: usize,
+}
+
+impl ListingCacheConfig {
+    pub fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+}
+
+impl Default for ListingCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::ZERO,
+            max_entries: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ListingKey {
+    bucket: String,
+    prefix: String,
+    delimiter: String,
+    marker: String,
+    max_keys: i32,
+    order: ListOrder,
+}
+
+#[derive(Debug)]
+struct CachedListing {
+    result: ListObjectsResult,
+    inserted_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<ListingKey, CachedListing>,
+    /// Bumped on every invalidation so that a listing which raced with a
+    /// write is not stored after the write dropped the entries it affects.
+    generation: u64,
+}
+
+/// Wraps an object layer and serves repeated identical `list_objects` calls
+/// from memory for a short TTL. Writes and deletes going through the wrapper
+/// drop every cached listing whose prefix covers the changed key.
+pub struct CachedObjectLayer {
+    inner: Arc<dyn ObjectLayer>,
+    config: ListingCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl CachedObjectLayer {
+    pub fn new(inner: Arc<dyn ObjectLayer>, config: ListingCacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn cached(&self, key: &ListingKey) -> std::result::Result<ListObjectsResult, u64> {
+        let mut state = self.lock();
+        match state.entries.get(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.config.ttl => {
+                Ok(entry.result.clone())
+            }
+            Some(_) => {
+                state.entries.remove(key);
+                Err(state.generation)
+            }
+            None => Err(state.generation),
+        }
+    }
+
+    fn store(&self, key: ListingKey, result: &ListObjectsResult, generation: u64) {
+        let mut state = self.lock();
+        if state.generation != generation {
+            return;
+        }
+        if state.entries.len() >= self.config.max_entries && !state.entries.contains_key(&key) {
+            let ttl = self.config.ttl;
+            state
+                .entries
+                .retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+            if state.entries.len() >= self.config.max_entries
+                && let Some(oldest) = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.inserted_at)
+                    .map(|(key, _)| key.clone())
+            {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(
+            key,
+            CachedListing {
+                result: result.clone(),
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    fn invalidate_key(&self, bucket: &str, object_key: &str) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state
+            .entries
+            .retain(|key, _| key.bucket != bucket || !object_key.starts_with(&key.prefix));
+    }
+
+    fn invalidate_bucket(&self, bucket: &str) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.entries.retain(|key, _| key.bucket != bucket);
+    }
+}
+
+#[async_trait]
+impl ObjectLayer for CachedObjectLayer {
+    async fn make_bucket(&self, bucket: &str) -> Result<()> {
+        let result = self.inner.make_bucket(bucket).await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+        self.inner.get_bucket_info(bucket).await
+    }
+
+    async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+        self.inner.list_buckets().await
+    }
+
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        let result = self.inner.delete_bucket(bucket).await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+        self.inner.get_bucket_versioning(bucket).await
+    }
+
+    async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+        let result = self.inner.set_bucket_versioning(bucket, state).await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        let result = self
+            .inner
+            .put_object(bucket, key, data, content_type, metadata, encryption)
+            .await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        self.inner.get_object(bucket, key, encryption).await
+    }
+
+    async fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        self.inner
+            .get_object_version(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn get_object_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.inner.get_object_info(bucket, key, encryption).await
+    }
+
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.inner
+            .get_object_version_info(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        let result = self
+            .inner
+            .update_object_metadata(bucket, key, content_type, metadata)
+            .await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        let result = self
+            .inner
+            .copy_object(source, bucket, key, metadata, encryption)
+            .await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+        let result = self.inner.delete_object(bucket, key).await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject> {
+        let result = self.inner.delete_object_if(bucket, key, condition).await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject> {
+        let result = self
+            .inner
+            .delete_object_version(bucket, key, version_id)
+            .await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+        order: ListOrder,
+    ) -> Result<ListObjectsResult> {
+        if !self.config.enabled() {
+            return self
+                .inner
+                .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
+                .await;
+        }
+
+        let key = ListingKey {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            delimiter: delimiter.to_string(),
+            marker: marker.to_string(),
+            max_keys,
+            order,
+        };
+        let generation = match self.cached(&key) {
+            Ok(result) => return Ok(result),
+            Err(generation) => generation,
+        };
+        let result = self
+            .inner
+            .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
+            .await?;
+        self.store(key, &result, generation);
+        Ok(result)
+    }
+
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+    ) -> Result<ListObjectVersionsResult> {
+        self.inner
+            .list_object_versions(
+                bucket,
+                prefix,
+                key_marker,
+                version_id_marker,
+                delimiter,
+                max_keys,
+            )
+            .await
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        self.inner
+            .create_multipart_upload(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+    ) -> Result<String> {
+        self.inner
+            .upload_part(bucket, key, upload_id, part_number, data)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletePart>,
+    ) -> Result<ObjectInfo> {
+        let result = self
+            .inner
+            .complete_multipart_upload(bucket, key, upload_id, parts)
+            .await;
+        self.invalidate_key(bucket, key);
+        result
+    }
+
+    async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        self.inner
+            .abort_multipart_upload(bucket, key, upload_id)
+            .await
+    }
+
+    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>> {
+        self.inner.list_parts(bucket, key, upload_id).await
+    }
+
+    async fn list_multipart_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
+        self.inner
+            .list_multipart_uploads(
+                bucket,
+                prefix,
+                key_marker,
+                upload_id_marker,
+                delimiter,
+                max_uploads,
+            )
+            .await
+    }
+
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+        self.inner.stat_object_parts(bucket, key).await
+    }
+
+    async fn rename_prefix(&self, bucket: &str, from_prefix: &str, to_prefix: &str) -> Result<()> {
+        let result = self
+            .inner
+            .rename_prefix(bucket, from_prefix, to_prefix)
+            .await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn storage_info(&self) -> StorageInfo {
+        self.inner.storage_info().await
+    }
+
+    async fn verify_objects(&self, bucket: &str, prefix: &str) -> Result<ChecksumReport> {
+        self.inner.verify_objects(bucket, prefix).await
+    }
+
+    async fn record_access(&self, bucket: &str, key: &str, accessed: DateTime<Utc>) -> Result<()> {
+        self.inner.record_access(bucket, key, accessed).await
+    }
+
+    async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        self.inner.remove_stale_multipart_uploads(cutoff).await
+    }
+
+    fn has_write_quorum(&self) -> bool {
+        self.inner.has_write_quorum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::single::SingleDiskObjectLayer;
+
+    fn keys(result: &ListObjectsResult) -> Vec<&str> {
+        result
+            .objects
+            .iter()
+            .map(|object| object.key.as_str())
+            .collect()
+    }
+
+    async fn put(layer: &dyn ObjectLayer, key: &str) {
+        layer
+            .put_object(
+                "bucket",
+                key,
+                Bytes::from_static(b"x"),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn listings_are_cached_until_a_write_to_their_prefix() {
+        let root = std::env::temp_dir().join(format!("maxio-listing-cache-{}", Uuid::new_v4()));
+        let inner: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        let cached = CachedObjectLayer::new(
+            Arc::clone(&inner),
+            ListingCacheConfig {
+                ttl: Duration::from_secs(60),
+                max_entries: 16,
+            },
+        );
+        cached.make_bucket("bucket").await.unwrap();
+        put(&cached, "logs/a").await;
+
+        let first = cached
+            .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&first), vec!["logs/a"]);
+
+        // Written behind the cache's back, so only a fresh walk would see it.
+        put(inner.as_ref(), "logs/b").await;
+        let second = cached
+            .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&second), vec!["logs/a"]);
+
+        // A write elsewhere in the bucket leaves the listing cached.
+        put(&cached, "other/c").await;
+        let third = cached
+            .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&third), vec!["logs/a"]);
+
+        put(&cached, "logs/d").await;
+        let fourth = cached
+            .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&fourth), vec!["logs/a", "logs/b", "logs/d"]);
+
+        cached.delete_object("bucket", "logs/a").await.unwrap();
+        let fifth = cached
+            .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&fifth), vec!["logs/b", "logs/d"]);
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn disabled_cache_always_lists_fresh() {
+        let root = std::env::temp_dir().join(format!("maxio-listing-cache-{}", Uuid::new_v4()));
+        let inner: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        let cached = CachedObjectLayer::new(Arc::clone(&inner), ListingCacheConfig::default());
+        cached.make_bucket("bucket").await.unwrap();
+
+        assert!(
+            cached
+                .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+                .await
+                .unwrap()
+                .objects
+                .is_empty()
+        );
+        put(inner.as_ref(), "a").await;
+        let listed = cached
+            .list_objects("bucket", "", "", "", 1000, ListOrder::KeyAscending)
+            .await
+            .unwrap();
+        assert_eq!(keys(&listed), vec!["a"]);
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
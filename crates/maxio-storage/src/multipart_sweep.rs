@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use maxio_common::error::{MaxioError, Result};
+use tracing::{info, warn};
+
+use crate::traits::ObjectLayer;
+
+/// Periodically removes multipart uploads that were never completed or
+/// aborted, independent of any lifecycle configuration.
+pub struct MultipartSweeper {
+    object_layer: Arc<dyn ObjectLayer>,
+    expiry: Duration,
+    reclaimed: AtomicU64,
+}
+
+impl MultipartSweeper {
+    /// Uploads initiated more than `expiry` ago are considered abandoned.
+    pub fn new(object_layer: Arc<dyn ObjectLayer>, expiry: Duration) -> Self {
+        Self {
+            object_layer,
+            expiry,
+            reclaimed: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs one sweep and returns how many uploads it removed.
+    pub async fn sweep(&self) -> Result<u64> {
+        let expiry = chrono::Duration::from_std(self.expiry)
+            .map_err(|err| MaxioError::InvalidArgument(format!("invalid upload expiry: {err}")))?;
+        let removed = self
+            .object_layer
+            .remove_stale_multipart_uploads(Utc::now() - expiry)
+            .await?;
+        self.reclaimed.fetch_add(removed, Ordering::Relaxed);
+        Ok(removed)
+    }
+
+    /// Stale uploads removed since the sweeper was created.
+    pub fn reclaimed_total(&self) -> u64 {
+        self.reclaimed.load(Ordering::Relaxed)
+    }
+
+    /// Sweeps right away, then once every `interval`.
+    pub fn start(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.sweep().await {
+                    Ok(0) => {}
+                    Ok(removed) => info!(removed, "removed stale multipart uploads"),
+                    Err(err) => warn!(error = %err, "stale multipart upload sweep failed"),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::single::SingleDiskObjectLayer;
+
+    #[tokio::test]
+    async fn backdated_uploads_are_swept_and_fresh_ones_kept() {
+        let root = std::env::temp_dir().join(format!("maxio-multipart-sweep-{}", Uuid::new_v4()));
+        let layer: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        layer.make_bucket("bucket").await.unwrap();
+
+        let stale = layer
+            .create_multipart_upload("bucket", "stale", None, HashMap::new())
+            .await
+            .unwrap();
+        layer
+            .upload_part("bucket", "stale", &stale, 1, Bytes::from_static(b"part"))
+            .await
+            .unwrap();
+        let fresh = layer
+            .create_multipart_upload("bucket", "fresh", None, HashMap::new())
+            .await
+            .unwrap();
+
+        let meta_path = root
+            .join("bucket/.multipart")
+            .join(&stale)
+            .join("upload.json");
+        let mut meta: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&meta_path).unwrap()).unwrap();
+        meta["initiated"] = serde_json::json!(Utc::now() - chrono::Duration::days(2));
+        std::fs::write(&meta_path, serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        // A crash between creating the upload directory and writing its
+        // metadata leaves a directory only its modification time can age.
+        let orphan = root.join("bucket/.multipart/orphan");
+        std::fs::create_dir_all(&orphan).unwrap();
+        std::fs::File::open(&orphan)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(3 * 24 * 3600))
+            .unwrap();
+
+        let sweeper = MultipartSweeper::new(Arc::clone(&layer), Duration::from_secs(24 * 3600));
+        assert_eq!(sweeper.sweep().await.unwrap(), 2);
+        assert_eq!(sweeper.reclaimed_total(), 2);
+
+        let remaining = layer
+            .list_multipart_uploads("bucket", "", "", "", "", 0)
+            .await
+            .unwrap();
+        let ids = remaining
+            .uploads
+            .iter()
+            .map(|upload| upload.upload_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![fresh.as_str()]);
+        assert!(!root.join("bucket/.multipart").join(&stale).exists());
+        assert!(!orphan.exists());
+
+        assert_eq!(sweeper.sweep().await.unwrap(), 0);
+        assert_eq!(sweeper.reclaimed_total(), 2);
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
@@ -0,0 +1,446 @@
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use maxio_common::error::{MAX_OBJECT_KEY_LEN, MaxioError, Result};
+use unicode_normalization::is_nfc;
+
+/// Directory names the object layers keep next to buckets on every disk.
+const RESERVED_BUCKET_NAMES: &[&str] = &[".maxio.sys", ".crypto"];
+/// Per-bucket directory holding in-progress multipart uploads.
+const RESERVED_KEY_PREFIXES: &[&str] = &[".multipart"];
+/// Bucket the S3 handlers keep bucket configurations in. It is created
+/// internally and predates the DNS naming rules, so it is exempt from them.
+pub const CONFIG_BUCKET: &str = ".minio.sys";
+
+static KEY_NAME_POLICY: AtomicU8 = AtomicU8::new(KeyNamePolicy::Permissive as u8);
+
+/// Extra rules object keys must meet on top of the ones every layer
+/// enforces. Strict policies keep confusable keys, which look alike but
+/// differ in their bytes, out of a deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum KeyNamePolicy {
+    /// Any UTF-8 key is accepted.
+    #[default]
+    Permissive = 0,
+    /// Rejects control characters and keys not in Unicode NFC form.
+    Nfc = 1,
+    /// Accepts printable ASCII only.
+    Ascii = 2,
+}
+
+impl KeyNamePolicy {
+    /// Parses `permissive`, `nfc` or `ascii`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "permissive" | "off" => Some(Self::Permissive),
+            "nfc" | "strict" => Some(Self::Nfc),
+            "ascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+
+    /// Reads `MAXIO_API_OBJECT_NAME_POLICY`; unset or unknown values keep
+    /// keys permissive.
+    pub fn from_env() -> Self {
+        std::env::var("MAXIO_API_OBJECT_NAME_POLICY")
+            .ok()
+            .as_deref()
+            .and_then(Self::parse)
+            .unwrap_or_default()
+    }
+
+    pub fn check(self, key: &str) -> Result<()> {
+        let valid = match self {
+            Self::Permissive => true,
+            Self::Nfc => !key.chars().any(char::is_control) && is_nfc(key),
+            Self::Ascii => key
+                .bytes()
+                .all(|byte| byte.is_ascii_graphic() || byte == b' '),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(MaxioError::InvalidObjectName(key.to_string()))
+        }
+    }
+}
+
+/// Sets the policy [`validate_object_key`] applies for the whole process.
+pub fn set_key_name_policy(policy: KeyNamePolicy) {
+    KEY_NAME_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+pub fn key_name_policy() -> KeyNamePolicy {
+    match KEY_NAME_POLICY.load(Ordering::Relaxed) {
+        1 => KeyNamePolicy::Nfc,
+        2 => KeyNamePolicy::Ascii,
+        _ => KeyNamePolicy::Permissive,
+    }
+}
+
+/// Bucket name rules every access path applies: the name must be a single
+/// path segment and not one of the directories the layers reserve for
+/// themselves. Buckets created before [`validate_new_bucket_name`] existed
+/// stay reachable under these looser rules.
+pub fn validate_bucket_name(bucket: &str) -> Result<()> {
+    if bucket.is_empty()
+        || bucket == "."
+        || bucket == ".."
+        || bucket.contains('/')
+        || bucket.contains('\\')
+        || RESERVED_BUCKET_NAMES.contains(&bucket)
+    {
+        return Err(MaxioError::InvalidBucketName(bucket.to_string()));
+    }
+    Ok(())
+}
+
+/// Rules a bucket name must meet to be created: [`validate_bucket_name`]
+/// plus the S3 DNS-compatible naming rules.
+pub fn validate_new_bucket_name(bucket: &str) -> Result<()> {
+    validate_bucket_name(bucket)?;
+    if bucket == CONFIG_BUCKET {
+        return Ok(());
+    }
+    let invalid = || MaxioError::InvalidBucketName(bucket.to_string());
+    if !(3..=63).contains(&bucket.len()) {
+        return Err(invalid());
+    }
+    if !bucket.bytes().all(|byte| {
+        byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'.' || byte == b'-'
+    }) {
+        return Err(invalid());
+    }
+    let alphanumeric = |byte: Option<u8>| byte.is_some_and(|byte| byte.is_ascii_alphanumeric());
+    if !alphanumeric(bucket.bytes().next()) || !alphanumeric(bucket.bytes().last()) {
+        return Err(invalid());
+    }
+    if bucket.contains("..") || bucket.contains(".-") || bucket.contains("-.") {
+        return Err(invalid());
+    }
+    if bucket.parse::<Ipv4Addr>().is_ok() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Object key rules shared by every object layer: keys must be relative
+/// paths without `.`/`..` segments, fit in [`MAX_OBJECT_KEY_LEN`] bytes,
+/// stay out of the layers' reserved per-bucket directories and meet the
+/// configured [`KeyNamePolicy`].
+pub fn validate_object_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.contains('\\') {
+        return Err(MaxioError::InvalidObjectName(key.to_string()));
+    }
+    if key.len() > MAX_OBJECT_KEY_LEN {
+        return Err(MaxioError::KeyTooLong(format!(
+            "{} bytes, limit is {MAX_OBJECT_KEY_LEN}",
+            key.len()
+        )));
+    }
+
+    let key_path = Path::new(key);
+    if key_path.is_absolute() {
+        return Err(MaxioError::InvalidObjectName(key.to_string()));
+    }
+
+    for (index, component) in key_path.components().enumerate() {
+        match component {
+            Component::Normal(name)
+                if index == 0
+                    && RESERVED_KEY_PREFIXES
+                        .iter()
+                        .any(|reserved| name == *reserved) =>
+            {
+                return Err(MaxioError::InvalidObjectName(key.to_string()));
+            }
+            Component::Normal(_) => {}
+            Component::CurDir
+            | Component::ParentDir
+            | Component::RootDir
+            | Component::Prefix(_) => {
+                return Err(MaxioError::InvalidObjectName(key.to_string()));
+            }
+        }
+    }
+
+    key_name_policy().check(key)
+}
+
+/// Maps the filesystem refusing a path built from an object key to the
+/// client's error: a key segment longer than a file name may be
+/// (ENAMETOOLONG) is reported as [`MaxioError::KeyTooLong`]. Anything else
+/// passes through.
+pub(crate) fn key_path_error(err: MaxioError) -> MaxioError {
+    match err {
+        MaxioError::Io(err) if err.kind() == ErrorKind::InvalidFilename => {
+            MaxioError::KeyTooLong(err.to_string())
+        }
+        err => err,
+    }
+}
+
+/// Checks the prefixes of a prefix rename: both name a directory (a valid
+/// key followed by `/`) and neither lies inside the other. Returns them
+/// without the trailing `/`.
+pub fn validate_prefix_rename<'a>(
+    from_prefix: &'a str,
+    to_prefix: &'a str,
+) -> Result<(&'a str, &'a str)> {
+    let directory = |prefix: &'a str| {
+        let name = prefix
+            .strip_suffix('/')
+            .filter(|name| !name.is_empty() && !name.ends_with('/'))
+            .ok_or_else(|| {
+                MaxioError::InvalidArgument(format!("prefix must end with a single '/': {prefix}"))
+            })?;
+        validate_object_key(name)?;
+        Ok::<_, MaxioError>(name)
+    };
+    let from = directory(from_prefix)?;
+    let to = directory(to_prefix)?;
+    if from_prefix.starts_with(to_prefix) || to_prefix.starts_with(from_prefix) {
+        return Err(MaxioError::InvalidArgument(format!(
+            "cannot rename {from_prefix} to the overlapping prefix {to_prefix}"
+        )));
+    }
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bytes::Bytes;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::erasure::ErasureConfig;
+    use crate::erasure::objects::ErasureSet;
+    use crate::traits::ObjectLayer;
+    use crate::xl::storage::XlStorage;
+
+    #[test]
+    fn new_bucket_names_follow_dns_rules() {
+        for valid in ["bucket", "my-bucket.v2", "123", "a.b-c"] {
+            assert!(validate_new_bucket_name(valid).is_ok(), "{valid}");
+        }
+        for invalid in [
+            "",
+            "ab",
+            &"a".repeat(64),
+            "Bucket",
+            "my_bucket",
+            "-bucket",
+            "bucket.",
+            "my..bucket",
+            "my.-bucket",
+            "192.168.1.10",
+            ".maxio.sys",
+            ".crypto",
+            "../etc",
+            "a/b",
+        ] {
+            assert!(validate_new_bucket_name(invalid).is_err(), "{invalid}");
+        }
+        assert!(validate_new_bucket_name(CONFIG_BUCKET).is_ok());
+    }
+
+    #[test]
+    fn existing_bucket_names_only_need_to_be_safe_paths() {
+        for legacy in ["Bucket", "my_bucket", "ab", "192.168.1.10", "a..b"] {
+            assert!(validate_bucket_name(legacy).is_ok(), "{legacy}");
+        }
+        for invalid in ["", ".", "..", "a/b", "a\\b", ".maxio.sys", ".crypto"] {
+            assert!(validate_bucket_name(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn object_keys_stay_inside_the_bucket() {
+        for valid in ["a", "dir/file.txt", "dir/.multipart", "a//b"] {
+            assert!(validate_object_key(valid).is_ok(), "{valid}");
+        }
+        for invalid in [
+            "",
+            "../escape",
+            "a/../../b",
+            "/abs",
+            "./a",
+            "a\\b",
+            ".multipart/x",
+        ] {
+            assert!(validate_object_key(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn strict_policies_reject_control_characters_and_unnormalized_keys() {
+        let composed = "caf\u{e9}.txt";
+        let decomposed = "cafe\u{301}.txt";
+        for key in ["a\u{0}b", "line\nbreak", "tab\there", "bell\u{7}"] {
+            assert!(KeyNamePolicy::Permissive.check(key).is_ok(), "{key:?}");
+            assert!(KeyNamePolicy::Nfc.check(key).is_err(), "{key:?}");
+            assert!(KeyNamePolicy::Ascii.check(key).is_err(), "{key:?}");
+        }
+
+        assert!(KeyNamePolicy::Permissive.check(decomposed).is_ok());
+        assert!(KeyNamePolicy::Nfc.check(composed).is_ok());
+        assert!(matches!(
+            KeyNamePolicy::Nfc.check(decomposed),
+            Err(MaxioError::InvalidObjectName(_))
+        ));
+        assert!(KeyNamePolicy::Ascii.check(composed).is_err());
+        assert!(KeyNamePolicy::Ascii.check("dir/file name-1.txt").is_ok());
+
+        assert_eq!(key_name_policy(), KeyNamePolicy::Permissive);
+        assert!(validate_object_key(decomposed).is_ok());
+        assert_eq!(KeyNamePolicy::parse(" NFC "), Some(KeyNamePolicy::Nfc));
+        assert_eq!(KeyNamePolicy::parse("loose"), None);
+    }
+
+    #[test]
+    fn renamed_prefixes_are_disjoint_directories() {
+        assert_eq!(
+            validate_prefix_rename("logs/2024/", "archive/").expect("valid rename"),
+            ("logs/2024", "archive")
+        );
+        for (from, to) in [
+            ("logs", "archive/"),
+            ("logs/", "archive"),
+            ("/", "archive/"),
+            ("logs//", "archive/"),
+            ("logs/", "logs/old/"),
+            ("logs/old/", "logs/"),
+            ("logs/", "logs/"),
+            ("../logs/", "archive/"),
+            (".multipart/", "archive/"),
+        ] {
+            assert!(validate_prefix_rename(from, to).is_err(), "{from} -> {to}");
+        }
+    }
+
+    #[tokio::test]
+    async fn object_layers_agree_on_names() {
+        let root = std::env::temp_dir().join(format!("maxio-naming-{}", Uuid::new_v4()));
+        let xl = XlStorage::new(root.join("xl"))
+            .await
+            .expect("create xl storage");
+        let config = ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            block_size: 64,
+            ..ErasureConfig::default()
+        };
+        let disks = (0..config.total_shards())
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect();
+        let erasure = ErasureSet::new(disks, config)
+            .await
+            .expect("create erasure set");
+
+        for bucket in [
+            "bucket",
+            ".maxio.sys",
+            ".crypto",
+            ".multipart",
+            "Bucket",
+            "ab",
+            "a..b",
+            "10.0.0.1",
+            "..",
+            "a/b",
+        ] {
+            let on_xl = xl.make_bucket(bucket).await.is_ok();
+            let on_erasure = erasure.make_bucket(bucket).await.is_ok();
+            assert_eq!(on_xl, on_erasure, "layers disagree on bucket {bucket:?}");
+            assert_eq!(on_xl, bucket == "bucket", "{bucket:?}");
+        }
+
+        for key in [
+            "file",
+            "dir/file",
+            ".multipart/upload",
+            "../escape",
+            "a/./b",
+            "/abs",
+        ] {
+            let on_xl = xl
+                .put_object("bucket", key, Bytes::new(), None, HashMap::new(), None)
+                .await
+                .is_ok();
+            let on_erasure = erasure
+                .put_object("bucket", key, Bytes::new(), None, HashMap::new(), None)
+                .await
+                .is_ok();
+            assert_eq!(on_xl, on_erasure, "layers disagree on key {key:?}");
+        }
+
+        // A bucket created before the DNS rules applied is still served.
+        let legacy = "Legacy_Bucket";
+        for disk in ["xl", "disk0", "disk1", "disk2", "disk3"] {
+            tokio::fs::create_dir_all(root.join(disk).join(legacy))
+                .await
+                .expect("create legacy bucket");
+        }
+        xl.put_object(legacy, "file", Bytes::new(), None, HashMap::new(), None)
+            .await
+            .expect("write to legacy bucket on xl");
+        erasure
+            .put_object(legacy, "file", Bytes::new(), None, HashMap::new(), None)
+            .await
+            .expect("write to legacy bucket on erasure");
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn over_long_key_segments_are_key_errors_only_for_keys() {
+        let root = std::env::temp_dir().join(format!("maxio-naming-{}", Uuid::new_v4()));
+        let xl = XlStorage::new(root.join("xl"))
+            .await
+            .expect("create xl storage");
+        let config = ErasureConfig {
+            data_shards: 2,
+            parity_shards: 2,
+            block_size: 64,
+            ..ErasureConfig::default()
+        };
+        let disks = (0..config.total_shards())
+            .map(|idx| root.join(format!("disk{idx}")))
+            .collect();
+        let erasure = ErasureSet::new(disks, config)
+            .await
+            .expect("create erasure set");
+        xl.make_bucket("bucket").await.expect("create xl bucket");
+        erasure
+            .make_bucket("bucket")
+            .await
+            .expect("create erasure bucket");
+
+        let key = format!("dir/{}", "s".repeat(300));
+        let on_xl = xl
+            .put_object("bucket", &key, Bytes::new(), None, HashMap::new(), None)
+            .await;
+        assert!(matches!(on_xl, Err(MaxioError::KeyTooLong(_))), "{on_xl:?}");
+        let on_erasure = erasure
+            .put_object("bucket", &key, Bytes::new(), None, HashMap::new(), None)
+            .await;
+        assert!(
+            matches!(on_erasure, Err(MaxioError::KeyTooLong(_))),
+            "{on_erasure:?}"
+        );
+
+        // The same filesystem error on a path that is not an object key
+        // stays a server-side error.
+        let err = tokio::fs::write(root.join("s".repeat(300)), b"")
+            .await
+            .expect_err("over-long file name");
+        assert!(matches!(MaxioError::from(err), MaxioError::Io(_)));
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
@@ -23,12 +23,12 @@ pub async fn start_decommission(manager: &PoolManager, pool_id: &str) -> Result<
         )));
     }
 
-    if let Some(status) = state.decommission_status.get(pool_id) {
-        if status.progress < 100 {
-            return Err(MaxioError::InvalidArgument(format!(
-                "decommission already in progress for pool: {pool_id}"
-            )));
-        }
+    if let Some(status) = state.decommission_status.get(pool_id)
+        && status.progress < 100
+    {
+        return Err(MaxioError::InvalidArgument(format!(
+            "decommission already in progress for pool: {pool_id}"
+        )));
     }
 
     let started_at = Utc::now();
@@ -92,9 +92,10 @@ pub async fn start_decommission(manager: &PoolManager, pool_id: &str) -> Result<
     let mut bytes_moved = 0_u64;
     let mut objects_moved = 0_u64;
     let mut remaining = total_bytes;
+    let mut drained = false;
 
     for target_id in target_pool_ids {
-        if remaining == 0 {
+        if drained || (remaining == 0 && manager.mover.is_none()) {
             break;
         }
 
@@ -109,12 +110,33 @@ pub async fn start_decommission(manager: &PoolManager, pool_id: &str) -> Result<
             continue;
         }
 
-        let moved = remaining.min(free);
+        let (moved, moved_objects) = match &manager.mover {
+            // The mover empties the source whatever its recorded usage; one
+            // that moves less than the target can take has nothing left.
+            Some(mover) => {
+                let target = target.clone();
+                let result = mover.move_objects(&source, &target, free).await?;
+                drained = result.bytes < free;
+                (result.bytes, result.objects)
+            }
+            None => {
+                let moved = remaining.min(free);
+                (moved, moved.div_ceil(MIGRATION_OBJECT_CHUNK_BYTES))
+            }
+        };
+
+        let Some(target) = state.pools.get_mut(&target_id) else {
+            return Err(MaxioError::InternalError(format!(
+                "target pool disappeared during migration: {target_id}"
+            )));
+        };
         target.used_space = target.used_space.saturating_add(moved);
-        remaining = remaining.saturating_sub(moved);
+        remaining = if drained {
+            0
+        } else {
+            remaining.saturating_sub(moved)
+        };
         bytes_moved = bytes_moved.saturating_add(moved);
-
-        let moved_objects = moved.div_ceil(MIGRATION_OBJECT_CHUNK_BYTES);
         objects_moved = objects_moved.saturating_add(moved_objects);
 
         let progress = progress_percent(bytes_moved, total_bytes);
@@ -130,6 +152,12 @@ pub async fn start_decommission(manager: &PoolManager, pool_id: &str) -> Result<
             .insert(pool_id.to_string(), status.clone());
     }
 
+    if manager.mover.is_some() && !drained {
+        return Err(MaxioError::InternalError(format!(
+            "decommission filled every target pool and left objects on pool {pool_id}"
+        )));
+    }
+
     if remaining != 0 {
         return Err(MaxioError::InternalError(format!(
             "decommission left unmigrated bytes for pool {pool_id}: {remaining}"
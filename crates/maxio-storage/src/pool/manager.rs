@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 
 use crate::pool::decommission;
 use crate::pool::expansion;
+use crate::pool::mover::PoolMover;
 use crate::pool::rebalance;
 use crate::pool::types::{DecommissionStatus, PoolInfo, PoolStatus, RebalanceStatus};
 
@@ -20,6 +21,7 @@ pub(crate) struct PoolState {
 #[derive(Debug, Clone, Default)]
 pub struct PoolManager {
     pub(crate) state: Arc<RwLock<PoolState>>,
+    pub(crate) mover: Option<Arc<dyn PoolMover>>,
 }
 
 impl PoolManager {
@@ -27,6 +29,12 @@ impl PoolManager {
         Self::default()
     }
 
+    /// Moves the objects themselves during decommission and rebalance.
+    pub fn with_mover(mut self, mover: Arc<dyn PoolMover>) -> Self {
+        self.mover = Some(mover);
+        self
+    }
+
     pub async fn list_pools(&self) -> Vec<PoolInfo> {
         let state = self.state.read().await;
         state.pools.values().cloned().collect()
@@ -1,8 +1,10 @@
 pub mod decommission;
 pub mod expansion;
 pub mod manager;
+pub mod mover;
 pub mod rebalance;
 pub mod types;
 
 pub use manager::PoolManager;
+pub use mover::{MovedObjects, PoolMover};
 pub use types::{DecommissionStatus, PoolInfo, PoolStatus, RebalanceStatus};
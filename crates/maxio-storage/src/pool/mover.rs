@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use maxio_common::error::Result;
+
+use crate::pool::types::PoolInfo;
+
+/// What a [`PoolMover`] actually moved between two pools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MovedObjects {
+    pub bytes: u64,
+    pub objects: u64,
+}
+
+/// Moves object data between pools for decommission and rebalance. Without
+/// one, the pool manager only updates the pools' accounting.
+#[async_trait]
+pub trait PoolMover: Send + Sync + std::fmt::Debug {
+    /// Moves whole objects from `source` to `target` until at least `bytes`
+    /// have moved. Moving fewer means `source` has no objects left.
+    async fn move_objects(
+        &self,
+        source: &PoolInfo,
+        target: &PoolInfo,
+        bytes: u64,
+    ) -> Result<MovedObjects>;
+}
@@ -106,7 +106,21 @@ pub async fn start_rebalance(manager: &PoolManager) -> Result<RebalanceStatus> {
                 continue;
             }
 
-            let moved = available.min(*needed);
+            let mut moved = available.min(*needed);
+            if let Some(mover) = &manager.mover {
+                let source = state.pools.get(&source_id).cloned().ok_or_else(|| {
+                    MaxioError::InternalError(format!("missing source pool during rebalance: {source_id}"))
+                })?;
+                let target = state.pools.get(target_id.as_str()).cloned().ok_or_else(|| {
+                    MaxioError::InternalError(format!("missing target pool during rebalance: {target_id}"))
+                })?;
+                let wanted = moved;
+                moved = mover.move_objects(&source, &target, wanted).await?.bytes;
+                // A mover that falls short has emptied the source pool.
+                if moved < wanted {
+                    available = moved;
+                }
+            }
 
             {
                 let source = state.pools.get_mut(&source_id).ok_or_else(|| {
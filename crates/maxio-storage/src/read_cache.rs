@@ -0,0 +1,599 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use maxio_common::error::Result;
+use maxio_common::types::{BucketInfo, ObjectInfo};
+
+use crate::storage_info::StorageInfo;
+use crate::traits::{
+    ChecksumReport, CompletePart, CopyMetadataFn, CopySource, DeleteCondition, DeletedObject,
+    GetEncryptionOptions, ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult,
+    ListOrder, ObjectLayer, ObjectPartInfo, PartInfo, PutEncryptionOptions, VersioningState,
+};
+
+/// Settings for caching object reads in memory. A zero `max_bytes` disables
+/// the cache; objects larger than `max_object_size` are never cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadCacheConfig {
+    pub max_bytes: u64,
+    pub max_object_size: u64,
+}
+
+impl ReadCacheConfig {
+    pub fn enabled(&self) -> bool {
+        self.max_bytes > 0 && self.max_object_size > 0
+    }
+}
+
+impl Default for ReadCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 0,
+            max_object_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Counters of a [`ReadCachedObjectLayer`] since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadCacheStats {
+    pub hits: u64,
+    /// Reads that went to the inner layer, stale entries included.
+    pub misses: u64,
+    /// Entries dropped to stay under `max_bytes`.
+    pub evictions: u64,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+type CacheKey = (String, String);
+
+#[derive(Debug)]
+struct CachedObject {
+    etag: String,
+    version_id: Option<String>,
+    data: Bytes,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CachedObject>,
+    /// Entries by last use, oldest first.
+    recency: BTreeMap<u64, CacheKey>,
+    bytes: u64,
+    clock: u64,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.last_used);
+            self.bytes -= entry.data.len() as u64;
+        }
+    }
+
+    fn touch(&mut self, key: &CacheKey) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = clock;
+            self.recency.insert(clock, key.clone());
+        }
+    }
+}
+
+/// Wraps an object layer and keeps the data of recently read objects in
+/// memory, evicting the least recently used once `max_bytes` is reached.
+/// Every cache hit is checked against the object's current ETag and version
+/// on the inner layer, so objects rewritten elsewhere, such as on another
+/// node, are read afresh; this only pays off where that metadata lookup is
+/// cheaper than reading the data. Encrypted objects are never cached.
+pub struct ReadCachedObjectLayer {
+    inner: Arc<dyn ObjectLayer>,
+    config: ReadCacheConfig,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl ReadCachedObjectLayer {
+    pub fn new(inner: Arc<dyn ObjectLayer>, config: ReadCacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> ReadCacheStats {
+        let state = self.lock();
+        ReadCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: state.entries.len(),
+            bytes: state.bytes,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The cached data for `key`, provided it was read from the version
+    /// `current` describes.
+    fn cached(&self, key: &CacheKey, current: &ObjectInfo) -> Option<Bytes> {
+        let mut state = self.lock();
+        let entry = state.entries.get(key)?;
+        if entry.etag != current.etag || entry.version_id != current.version_id {
+            state.remove(key);
+            return None;
+        }
+        let data = entry.data.clone();
+        state.touch(key);
+        Some(data)
+    }
+
+    fn store(&self, key: CacheKey, info: &ObjectInfo, data: &Bytes) {
+        let size = data.len() as u64;
+        if info.encryption.is_some() || size > self.config.max_object_size {
+            return;
+        }
+
+        let mut state = self.lock();
+        state.remove(&key);
+        while state.bytes + size > self.config.max_bytes {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.remove(&oldest);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        if state.bytes + size > self.config.max_bytes {
+            return;
+        }
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.recency.insert(last_used, key.clone());
+        state.bytes += size;
+        state.entries.insert(
+            key,
+            CachedObject {
+                etag: info.etag.clone(),
+                version_id: info.version_id.clone(),
+                data: data.clone(),
+                last_used,
+            },
+        );
+    }
+
+    fn invalidate(&self, bucket: &str, key: &str) {
+        self.lock().remove(&(bucket.to_string(), key.to_string()));
+    }
+
+    fn invalidate_bucket(&self, bucket: &str) {
+        let mut state = self.lock();
+        let keys = state
+            .entries
+            .keys()
+            .filter(|(entry_bucket, _)| entry_bucket == bucket)
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            state.remove(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectLayer for ReadCachedObjectLayer {
+    async fn make_bucket(&self, bucket: &str) -> Result<()> {
+        self.inner.make_bucket(bucket).await
+    }
+
+    async fn get_bucket_info(&self, bucket: &str) -> Result<BucketInfo> {
+        self.inner.get_bucket_info(bucket).await
+    }
+
+    async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
+        self.inner.list_buckets().await
+    }
+
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        let result = self.inner.delete_bucket(bucket).await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningState> {
+        self.inner.get_bucket_versioning(bucket).await
+    }
+
+    async fn set_bucket_versioning(&self, bucket: &str, state: VersioningState) -> Result<()> {
+        self.inner.set_bucket_versioning(bucket, state).await
+    }
+
+    async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        let result = self
+            .inner
+            .put_object(bucket, key, data, content_type, metadata, encryption)
+            .await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        if !self.config.enabled() || encryption.is_some() {
+            return self.inner.get_object(bucket, key, encryption).await;
+        }
+
+        let cache_key = (bucket.to_string(), key.to_string());
+        let has_entry = self.lock().entries.contains_key(&cache_key);
+        if has_entry {
+            // The current info also carries metadata updated in place,
+            // which leaves the ETag alone.
+            match self.inner.get_object_info(bucket, key, None).await {
+                Ok(info) => {
+                    if let Some(data) = self.cached(&cache_key, &info) {
+                        self.hits.fetch_add(1, Ordering::Relaxed);
+                        return Ok((info, data));
+                    }
+                }
+                Err(_) => self.invalidate(bucket, key),
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let (info, data) = self.inner.get_object(bucket, key, None).await?;
+        self.store(cache_key, &info, &data);
+        Ok((info, data))
+    }
+
+    async fn get_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<(ObjectInfo, Bytes)> {
+        self.inner
+            .get_object_version(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn get_object_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.inner.get_object_info(bucket, key, encryption).await
+    }
+
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.inner
+            .get_object_version_info(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        self.inner
+            .update_object_metadata(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        let result = self
+            .inner
+            .copy_object(source, bucket, key, metadata, encryption)
+            .await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+        let result = self.inner.delete_object(bucket, key).await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject> {
+        let result = self.inner.delete_object_if(bucket, key, condition).await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject> {
+        let result = self
+            .inner
+            .delete_object_version(bucket, key, version_id)
+            .await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+        order: ListOrder,
+    ) -> Result<ListObjectsResult> {
+        self.inner
+            .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
+            .await
+    }
+
+    async fn list_object_versions(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
+        max_keys: i32,
+    ) -> Result<ListObjectVersionsResult> {
+        self.inner
+            .list_object_versions(
+                bucket,
+                prefix,
+                key_marker,
+                version_id_marker,
+                delimiter,
+                max_keys,
+            )
+            .await
+    }
+
+    async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<String> {
+        self.inner
+            .create_multipart_upload(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+    ) -> Result<String> {
+        self.inner
+            .upload_part(bucket, key, upload_id, part_number, data)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: Vec<CompletePart>,
+    ) -> Result<ObjectInfo> {
+        let result = self
+            .inner
+            .complete_multipart_upload(bucket, key, upload_id, parts)
+            .await;
+        self.invalidate(bucket, key);
+        result
+    }
+
+    async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        self.inner
+            .abort_multipart_upload(bucket, key, upload_id)
+            .await
+    }
+
+    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>> {
+        self.inner.list_parts(bucket, key, upload_id).await
+    }
+
+    async fn list_multipart_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
+        self.inner
+            .list_multipart_uploads(
+                bucket,
+                prefix,
+                key_marker,
+                upload_id_marker,
+                delimiter,
+                max_uploads,
+            )
+            .await
+    }
+
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+        self.inner.stat_object_parts(bucket, key).await
+    }
+
+    async fn rename_prefix(&self, bucket: &str, from_prefix: &str, to_prefix: &str) -> Result<()> {
+        let result = self
+            .inner
+            .rename_prefix(bucket, from_prefix, to_prefix)
+            .await;
+        self.invalidate_bucket(bucket);
+        result
+    }
+
+    async fn storage_info(&self) -> StorageInfo {
+        self.inner.storage_info().await
+    }
+
+    async fn verify_objects(&self, bucket: &str, prefix: &str) -> Result<ChecksumReport> {
+        self.inner.verify_objects(bucket, prefix).await
+    }
+
+    async fn record_access(&self, bucket: &str, key: &str, accessed: DateTime<Utc>) -> Result<()> {
+        self.inner.record_access(bucket, key, accessed).await
+    }
+
+    async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        self.inner.remove_stale_multipart_uploads(cutoff).await
+    }
+
+    fn has_write_quorum(&self) -> bool {
+        self.inner.has_write_quorum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::single::SingleDiskObjectLayer;
+
+    async fn put(layer: &dyn ObjectLayer, key: &str, data: &'static [u8]) {
+        layer
+            .put_object(
+                "bucket",
+                key,
+                Bytes::from_static(data),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn read(layer: &dyn ObjectLayer, key: &str) -> Bytes {
+        layer.get_object("bucket", key, None).await.unwrap().1
+    }
+
+    #[tokio::test]
+    async fn repeated_reads_hit_until_the_etag_changes() {
+        let root = std::env::temp_dir().join(format!("maxio-read-cache-{}", Uuid::new_v4()));
+        let inner: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        let cached = ReadCachedObjectLayer::new(
+            Arc::clone(&inner),
+            ReadCacheConfig {
+                max_bytes: 1024,
+                max_object_size: 64,
+            },
+        );
+        cached.make_bucket("bucket").await.unwrap();
+        put(&cached, "hot", b"first").await;
+
+        assert_eq!(read(&cached, "hot").await, Bytes::from_static(b"first"));
+        assert_eq!(read(&cached, "hot").await, Bytes::from_static(b"first"));
+        let stats = cached.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        // Rewritten behind the cache's back, as another node would.
+        put(inner.as_ref(), "hot", b"second").await;
+        assert_eq!(read(&cached, "hot").await, Bytes::from_static(b"second"));
+        assert_eq!(read(&cached, "hot").await, Bytes::from_static(b"second"));
+        let stats = cached.stats();
+        assert_eq!((stats.hits, stats.misses, stats.bytes), (2, 2, 6));
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_objects_are_evicted_first() {
+        let root = std::env::temp_dir().join(format!("maxio-read-cache-{}", Uuid::new_v4()));
+        let inner: Arc<dyn ObjectLayer> =
+            Arc::new(SingleDiskObjectLayer::new(root.clone()).await.unwrap());
+        let cached = ReadCachedObjectLayer::new(
+            inner,
+            ReadCacheConfig {
+                max_bytes: 8,
+                max_object_size: 4,
+            },
+        );
+        cached.make_bucket("bucket").await.unwrap();
+        for key in ["a", "b", "c"] {
+            put(&cached, key, b"1234").await;
+        }
+        put(&cached, "large", b"12345").await;
+
+        read(&cached, "a").await;
+        read(&cached, "b").await;
+        read(&cached, "a").await;
+        read(&cached, "c").await;
+        read(&cached, "large").await;
+        let stats = cached.stats();
+        assert_eq!((stats.entries, stats.bytes, stats.evictions), (2, 8, 1));
+
+        // "b" was the least recently used when "c" came in.
+        read(&cached, "a").await;
+        read(&cached, "b").await;
+        let stats = cached.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 5));
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
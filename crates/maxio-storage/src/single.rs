@@ -3,24 +3,54 @@ use std::path::PathBuf;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use maxio_common::error::Result;
 use maxio_common::types::{BucketInfo, ObjectInfo};
 
+use crate::key_lock::KeyLocks;
+use crate::storage_info::{DiskInfo, StorageInfo};
 use crate::traits::{
-    CompletePart, GetEncryptionOptions, ListObjectsResult, MultipartUploadInfo, ObjectLayer,
-    ObjectVersion, PartInfo, PutEncryptionOptions, VersioningState,
+    ChecksumReport, CompletePart, CopyMetadataFn, CopySource, DeleteCondition, DeletedObject,
+    GetEncryptionOptions, ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult,
+    ListOrder, ObjectLayer, ObjectPartInfo, PartInfo, PutEncryptionOptions, VersioningState,
 };
 use crate::xl::storage::XlStorage;
 
 #[derive(Debug, Clone)]
 pub struct SingleDiskObjectLayer {
     storage: XlStorage,
+    auto_encrypt: bool,
+    key_locks: KeyLocks,
 }
 
 impl SingleDiskObjectLayer {
     pub async fn new(data_dir: PathBuf) -> Result<Self> {
         let storage = XlStorage::new(data_dir).await?;
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            auto_encrypt: false,
+            key_locks: KeyLocks::new(),
+        })
+    }
+
+    /// Encrypts every object written without explicit encryption options
+    /// with SSE-S3, regardless of bucket settings.
+    pub fn with_auto_encryption(mut self, enabled: bool) -> Self {
+        self.auto_encrypt = enabled;
+        self
+    }
+
+    fn put_encryption(
+        &self,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Option<PutEncryptionOptions> {
+        encryption.or_else(|| {
+            self.auto_encrypt.then_some(PutEncryptionOptions {
+                sse_s3: true,
+                sse_c_key: None,
+                sse_c_key_md5: None,
+            })
+        })
     }
 }
 
@@ -59,6 +89,8 @@ impl ObjectLayer for SingleDiskObjectLayer {
         metadata: HashMap<String, String>,
         encryption: Option<PutEncryptionOptions>,
     ) -> Result<ObjectInfo> {
+        let encryption = self.put_encryption(encryption);
+        let _guard = self.key_locks.lock(bucket, key).await;
         self.storage
             .put_object(bucket, key, data, content_type, metadata, encryption)
             .await
@@ -94,11 +126,69 @@ impl ObjectLayer for SingleDiskObjectLayer {
         self.storage.get_object_info(bucket, key, encryption).await
     }
 
-    async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        self.storage
+            .get_object_version_info(bucket, key, version_id, encryption)
+            .await
+    }
+
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.storage
+            .update_object_metadata(bucket, key, content_type, metadata)
+            .await
+    }
+
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        let encryption = self.put_encryption(encryption);
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.storage
+            .copy_object(source, bucket, key, metadata, encryption)
+            .await
+    }
+
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.storage.delete_object(bucket, key).await
+    }
+
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        condition.check(&self.storage.stat_latest_object(bucket, key).await?)?;
         self.storage.delete_object(bucket, key).await
     }
 
-    async fn delete_object_version(&self, bucket: &str, key: &str, version_id: &str) -> Result<()> {
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject> {
+        let _guard = self.key_locks.lock(bucket, key).await;
         self.storage
             .delete_object_version(bucket, key, version_id)
             .await
@@ -111,9 +201,10 @@ impl ObjectLayer for SingleDiskObjectLayer {
         marker: &str,
         delimiter: &str,
         max_keys: i32,
+        order: ListOrder,
     ) -> Result<ListObjectsResult> {
         self.storage
-            .list_objects(bucket, prefix, marker, delimiter, max_keys)
+            .list_objects(bucket, prefix, marker, delimiter, max_keys, order)
             .await
     }
 
@@ -121,10 +212,20 @@ impl ObjectLayer for SingleDiskObjectLayer {
         &self,
         bucket: &str,
         prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
         max_keys: i32,
-    ) -> Result<Vec<ObjectVersion>> {
+    ) -> Result<ListObjectVersionsResult> {
         self.storage
-            .list_object_versions(bucket, prefix, max_keys)
+            .list_object_versions(
+                bucket,
+                prefix,
+                key_marker,
+                version_id_marker,
+                delimiter,
+                max_keys,
+            )
             .await
     }
 
@@ -160,8 +261,10 @@ impl ObjectLayer for SingleDiskObjectLayer {
         upload_id: &str,
         parts: Vec<CompletePart>,
     ) -> Result<ObjectInfo> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        let encryption = self.put_encryption(None);
         self.storage
-            .complete_multipart_upload(bucket, key, upload_id, parts)
+            .complete_multipart_upload(bucket, key, upload_id, parts, encryption)
             .await
     }
 
@@ -179,7 +282,49 @@ impl ObjectLayer for SingleDiskObjectLayer {
         &self,
         bucket: &str,
         prefix: &str,
-    ) -> Result<Vec<MultipartUploadInfo>> {
-        self.storage.list_multipart_uploads(bucket, prefix).await
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
+        self.storage
+            .list_multipart_uploads(
+                bucket,
+                prefix,
+                key_marker,
+                upload_id_marker,
+                delimiter,
+                max_uploads,
+            )
+            .await
+    }
+
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+        self.storage.stat_object_parts(bucket, key).await
+    }
+
+    async fn rename_prefix(&self, bucket: &str, from_prefix: &str, to_prefix: &str) -> Result<()> {
+        self.storage
+            .rename_prefix(bucket, from_prefix, to_prefix)
+            .await
+    }
+
+    async fn storage_info(&self) -> StorageInfo {
+        StorageInfo {
+            disks: vec![DiskInfo::probe(self.storage.root_dir(), 0, 0, true).await],
+        }
+    }
+
+    async fn verify_objects(&self, bucket: &str, prefix: &str) -> Result<ChecksumReport> {
+        self.storage.verify_objects(bucket, prefix).await
+    }
+
+    async fn record_access(&self, bucket: &str, key: &str, accessed: DateTime<Utc>) -> Result<()> {
+        let _guard = self.key_locks.lock(bucket, key).await;
+        self.storage.record_access(bucket, key, accessed).await
+    }
+
+    async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        self.storage.remove_stale_multipart_uploads(cutoff).await
     }
 }
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+pub use crate::erasure::health::DiskState;
+
+/// Capacity and reachability of one disk backing an object layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskInfo {
+    pub path: PathBuf,
+    /// Erasure set the disk belongs to; always 0 for single-disk layers.
+    pub set_index: usize,
+    /// Position of the disk inside its set.
+    pub disk_index: usize,
+    pub state: DiskState,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub used_bytes: u64,
+}
+
+impl DiskInfo {
+    /// Measures the filesystem holding `path`. A disk whose path is gone or
+    /// cannot be measured is reported offline, as is one `tracked_online`
+    /// says has been failing.
+    pub(crate) async fn probe(
+        path: &Path,
+        set_index: usize,
+        disk_index: usize,
+        tracked_online: bool,
+    ) -> Self {
+        let usage = if tracked_online {
+            let probe_path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || filesystem_usage(&probe_path))
+                .await
+                .ok()
+                .and_then(Result::ok)
+        } else {
+            None
+        };
+        let (state, (total_bytes, free_bytes)) = match usage {
+            Some(usage) => (DiskState::Online, usage),
+            None => (DiskState::Offline, (0, 0)),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            set_index,
+            disk_index,
+            state,
+            total_bytes,
+            free_bytes,
+            used_bytes: total_bytes.saturating_sub(free_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageInfo {
+    pub disks: Vec<DiskInfo>,
+}
+
+impl StorageInfo {
+    pub fn online_disks(&self) -> usize {
+        self.disks
+            .iter()
+            .filter(|disk| disk.state == DiskState::Online)
+            .count()
+    }
+
+    pub fn offline_disks(&self) -> usize {
+        self.disks.len() - self.online_disks()
+    }
+
+    /// Free space across the online disks.
+    pub fn free_bytes(&self) -> u64 {
+        self.disks
+            .iter()
+            .filter(|disk| disk.state == DiskState::Online)
+            .map(|disk| disk.free_bytes)
+            .sum()
+    }
+}
+
+/// Total and available bytes of the filesystem holding `path`, which must be
+/// an existing directory.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)] // statvfs field widths differ per platform
+fn filesystem_usage(path: &Path) -> std::io::Result<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    if !path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("disk path {} is not a directory", path.display()),
+        ));
+    }
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` points to writable
+    // memory large enough for a `statvfs`.
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: statvfs returned success, so it filled in `stat`.
+    let stat = unsafe { stat.assume_init() };
+    let block_size = stat.f_frsize as u64;
+    Ok((
+        (stat.f_blocks as u64).saturating_mul(block_size),
+        (stat.f_bavail as u64).saturating_mul(block_size),
+    ))
+}
+
+#[cfg(not(unix))]
+fn filesystem_usage(path: &Path) -> std::io::Result<(u64, u64)> {
+    if !path.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("disk path {} is not a directory", path.display()),
+        ));
+    }
+    Ok((0, 0))
+}
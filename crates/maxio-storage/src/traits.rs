@@ -3,10 +3,13 @@ use std::collections::HashMap;
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use maxio_common::error::Result;
+use maxio_common::error::{MaxioError, Result};
+use maxio_common::hash::normalize_etag;
 use maxio_common::types::{BucketInfo, ObjectInfo};
 use serde::{Deserialize, Serialize};
 
+use crate::storage_info::StorageInfo;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListObjectsResult {
     pub objects: Vec<ObjectInfo>,
@@ -15,6 +18,21 @@ pub struct ListObjectsResult {
     pub next_marker: Option<String>,
 }
 
+/// Order objects are listed in. S3 always lists keys in ascending byte
+/// order; the others are extensions for callers outside the S3 API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListOrder {
+    #[default]
+    KeyAscending,
+    /// Keys in descending byte order; the listing resumes before `marker`.
+    KeyDescending,
+    /// Most recently modified first, ties broken by key. The listing is
+    /// flat, as common prefixes have no modification time, and its marker
+    /// is opaque: resume with the `next_marker` of the previous page.
+    NewestFirst,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletePart {
     pub part_number: i32,
@@ -29,6 +47,43 @@ pub struct PartInfo {
     pub last_modified: DateTime<Utc>,
 }
 
+/// One part of a completed multipart object, in upload order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectPartInfo {
+    pub part_number: i32,
+    pub size: i64,
+    pub etag: String,
+}
+
+/// One page of a version listing. Versions of a key are newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListObjectVersionsResult {
+    pub versions: Vec<ObjectVersion>,
+    pub prefixes: Vec<String>,
+    pub is_truncated: bool,
+    pub next_key_marker: Option<String>,
+    pub next_version_id_marker: Option<String>,
+}
+
+/// A stored object version whose data no longer matches what was written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumMismatch {
+    pub bucket: String,
+    pub key: String,
+    pub version_id: String,
+    pub reason: String,
+}
+
+/// Outcome of [`ObjectLayer::verify_objects`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumReport {
+    pub verified: u64,
+    /// Versions the server cannot read back on its own, such as SSE-C
+    /// objects whose key only the client holds.
+    pub skipped: u64,
+    pub mismatches: Vec<ChecksumMismatch>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MultipartUploadInfo {
     pub key: String,
@@ -36,6 +91,15 @@ pub struct MultipartUploadInfo {
     pub initiated: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListMultipartUploadsResult {
+    pub uploads: Vec<MultipartUploadInfo>,
+    pub prefixes: Vec<String>,
+    pub is_truncated: bool,
+    pub next_key_marker: Option<String>,
+    pub next_upload_id_marker: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum VersioningState {
     #[default]
@@ -53,8 +117,27 @@ pub struct ObjectVersion {
     pub last_modified: DateTime<Utc>,
     pub etag: Option<String>,
     pub size: i64,
+    /// When the version was last read, if the layer records access times
+    /// and it has been read since.
+    #[serde(default)]
+    pub last_access: Option<DateTime<Utc>>,
+}
+
+/// Outcome of a delete. On a versioned bucket `version_id` names the version
+/// that was created (a new delete marker) or removed, and `delete_marker`
+/// tells whether that version is a marker.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletedObject {
+    pub version_id: Option<String>,
+    pub delete_marker: bool,
 }
 
+/// Reserved metadata key carrying a non-standard storage class into
+/// `put_object` and `create_multipart_upload`. Layers move it out of the user
+/// metadata and into the object's own record, so callers must keep client
+/// supplied metadata from using it.
+pub const STORAGE_CLASS_META_KEY: &str = "x-amz-storage-class";
+
 #[derive(Debug, Clone)]
 pub struct PutEncryptionOptions {
     pub sse_s3: bool,
@@ -68,6 +151,56 @@ pub struct GetEncryptionOptions {
     pub sse_c_key_md5: Option<String>,
 }
 
+/// The object a `copy_object` reads.
+#[derive(Debug, Clone)]
+pub struct CopySource {
+    pub bucket: String,
+    pub key: String,
+    /// Copies this version instead of the latest one.
+    pub version_id: Option<String>,
+    /// Key of an SSE-C encrypted source.
+    pub encryption: Option<GetEncryptionOptions>,
+}
+
+/// Given the source of a copy, returns the content type and user metadata
+/// of the new object, or an error to refuse the copy before anything is
+/// written.
+pub type CopyMetadataFn<'a> =
+    dyn Fn(&ObjectInfo) -> Result<(Option<String>, HashMap<String, String>)> + Send + Sync + 'a;
+
+/// What the latest version of an object must still be for a conditional
+/// delete to go ahead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteCondition {
+    /// The latest version's ETag, or `*` for any existing object.
+    ETag(String),
+    /// The latest version's id; `null` names an unversioned object.
+    VersionId(String),
+}
+
+impl DeleteCondition {
+    /// Fails with `PreconditionFailed` unless `current`, the latest version,
+    /// satisfies the condition.
+    pub fn check(&self, current: &ObjectInfo) -> Result<()> {
+        let matches = match self {
+            Self::ETag(etag) => {
+                let etag = normalize_etag(etag);
+                etag == "*" || etag == current.etag
+            }
+            Self::VersionId(version_id) => {
+                current.version_id.as_deref().unwrap_or("null") == version_id
+            }
+        };
+        if matches {
+            return Ok(());
+        }
+        Err(MaxioError::PreconditionFailed(format!(
+            "{}/{} no longer matches the delete condition",
+            current.bucket, current.key
+        )))
+    }
+}
+
 #[async_trait]
 pub trait ObjectLayer: Send + Sync {
     async fn make_bucket(&self, bucket: &str) -> Result<()>;
@@ -104,8 +237,55 @@ pub trait ObjectLayer: Send + Sync {
         key: &str,
         encryption: Option<GetEncryptionOptions>,
     ) -> Result<ObjectInfo>;
-    async fn delete_object(&self, bucket: &str, key: &str) -> Result<()>;
-    async fn delete_object_version(&self, bucket: &str, key: &str, version_id: &str) -> Result<()>;
+    /// One version's info, without reading its data.
+    async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo>;
+    /// Replaces the content type and user metadata of the latest version
+    /// without touching its data.
+    async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo>;
+    /// Writes a copy of `source` to `bucket/key` and returns the source's
+    /// info along with the new object's. Layers that can share or copy the
+    /// stored data override this; the default reads the whole source and
+    /// puts it again.
+    async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        copy_by_reading(self, source, bucket, key, metadata, encryption).await
+    }
+    async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject>;
+    /// Deletes `key` like `delete_object`, but only while its latest version
+    /// satisfies `condition`. The check and the delete happen under the
+    /// key's write lock, so no other write can slip in between.
+    async fn delete_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        condition: &DeleteCondition,
+    ) -> Result<DeletedObject>;
+    async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<DeletedObject>;
+    /// Lists the latest visible version of each object under `prefix`,
+    /// with S3 marker, delimiter and max-keys semantics in the given `order`.
     async fn list_objects(
         &self,
         bucket: &str,
@@ -113,13 +293,21 @@ pub trait ObjectLayer: Send + Sync {
         marker: &str,
         delimiter: &str,
         max_keys: i32,
+        order: ListOrder,
     ) -> Result<ListObjectsResult>;
+    /// Lists versions ordered by key, newest first within a key. Listing
+    /// resumes after `version_id_marker` of `key_marker`, or after every
+    /// version of `key_marker` when no version marker is given. A
+    /// non-positive `max_keys` returns every match.
     async fn list_object_versions(
         &self,
         bucket: &str,
         prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
         max_keys: i32,
-    ) -> Result<Vec<ObjectVersion>>;
+    ) -> Result<ListObjectVersionsResult>;
     async fn create_multipart_upload(
         &self,
         bucket: &str,
@@ -144,9 +332,111 @@ pub trait ObjectLayer: Send + Sync {
     ) -> Result<ObjectInfo>;
     async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()>;
     async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<PartInfo>>;
+    /// Lists in-progress uploads ordered by key then upload id. A
+    /// non-positive `max_uploads` returns every match.
     async fn list_multipart_uploads(
         &self,
         bucket: &str,
         prefix: &str,
-    ) -> Result<Vec<MultipartUploadInfo>>;
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult>;
+    /// Parts the latest version of an object was assembled from; empty when
+    /// it was not created by a multipart upload.
+    async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>>;
+
+    /// Moves every object under `from_prefix` to the same key under
+    /// `to_prefix`, keeping its versions and metadata. Both prefixes end in
+    /// `/`, must not overlap, and no object may exist under `to_prefix` yet.
+    /// Writes racing the rename may land under either prefix.
+    async fn rename_prefix(
+        &self,
+        _bucket: &str,
+        from_prefix: &str,
+        _to_prefix: &str,
+    ) -> Result<()> {
+        Err(MaxioError::NotImplemented(format!(
+            "renaming prefix {from_prefix}"
+        )))
+    }
+
+    /// Re-reads every object version under `prefix` and checks its data
+    /// against the size and ETag recorded when it was written. Layers that
+    /// verify data as they read it leave this to their own healing.
+    async fn verify_objects(&self, _bucket: &str, prefix: &str) -> Result<ChecksumReport> {
+        Err(MaxioError::NotImplemented(format!(
+            "verifying checksums under {prefix}"
+        )))
+    }
+
+    /// Notes that the latest version of `key` was read at `accessed`,
+    /// leaving its data, ETag and modification time alone. Layers that do
+    /// not keep access times ignore it.
+    async fn record_access(
+        &self,
+        _bucket: &str,
+        _key: &str,
+        _accessed: DateTime<Utc>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Removes in-progress multipart uploads initiated before `cutoff`,
+    /// whatever the bucket's lifecycle rules say, and returns how many were
+    /// removed. Layers without on-disk upload staging have nothing to sweep.
+    async fn remove_stale_multipart_uploads(&self, _cutoff: DateTime<Utc>) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Reachability and capacity of every disk behind the layer.
+    async fn storage_info(&self) -> StorageInfo;
+
+    /// Whether enough disks are online for writes to reach quorum. Layers
+    /// without redundancy are always writable while the process is up.
+    fn has_write_quorum(&self) -> bool {
+        true
+    }
+}
+
+/// Copies `source` through memory: the whole object is read, then put at
+/// `bucket/key`. Layers fall back to it for copies they cannot do in place.
+pub(crate) async fn copy_by_reading<L: ObjectLayer + ?Sized>(
+    layer: &L,
+    source: &CopySource,
+    bucket: &str,
+    key: &str,
+    metadata: &CopyMetadataFn<'_>,
+    encryption: Option<PutEncryptionOptions>,
+) -> Result<(ObjectInfo, ObjectInfo)> {
+    let (source_info, data) = match source.version_id.as_deref() {
+        Some(version_id) => {
+            layer
+                .get_object_version(
+                    &source.bucket,
+                    &source.key,
+                    version_id,
+                    source.encryption.clone(),
+                )
+                .await?
+        }
+        None => {
+            layer
+                .get_object(&source.bucket, &source.key, source.encryption.clone())
+                .await?
+        }
+    };
+    let (content_type, metadata) = metadata(&source_info)?;
+    let info = layer
+        .put_object(
+            bucket,
+            key,
+            data,
+            content_type.as_deref(),
+            metadata,
+            encryption,
+        )
+        .await?;
+    Ok((source_info, info))
 }
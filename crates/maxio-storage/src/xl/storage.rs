@@ -1,19 +1,30 @@
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use maxio_common::error::{MaxioError, Result};
-use maxio_common::types::{BucketInfo, ObjectEncryption, ObjectInfo};
+use maxio_common::hash::{CompositeEtag, decode_md5_hex, md5_digest, md5_hex, normalize_etag};
+use maxio_common::types::{
+    BucketInfo, ObjectEncryption, ObjectInfo, REDUCED_REDUNDANCY_STORAGE_CLASS,
+};
 use maxio_crypto::{MasterKey, cipher};
-use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::naming::{
+    key_path_error, validate_bucket_name, validate_new_bucket_name, validate_object_key,
+    validate_prefix_rename,
+};
 use crate::traits::{
-    CompletePart, GetEncryptionOptions, ListObjectsResult, MultipartUploadInfo, ObjectVersion,
-    PartInfo, PutEncryptionOptions, VersioningState,
+    ChecksumMismatch, ChecksumReport, CompletePart, CopyMetadataFn, CopySource, DeletedObject,
+    GetEncryptionOptions, ListMultipartUploadsResult, ListObjectVersionsResult, ListObjectsResult,
+    ListOrder, MultipartUploadInfo, ObjectPartInfo, ObjectVersion, PartInfo, PutEncryptionOptions,
+    STORAGE_CLASS_META_KEY, VersioningState,
 };
 
 const SYS_DIR_NAME: &str = ".maxio.sys";
@@ -26,7 +37,13 @@ const MULTIPART_DIR_NAME: &str = ".multipart";
 const MULTIPART_META_FILE_NAME: &str = "upload.json";
 const VERSIONING_FILE_NAME: &str = ".versioning.json";
 const VERSIONS_INDEX_FILE_NAME: &str = ".versions.json";
-const NULL_VERSION_ID: &str = "null";
+/// Plaintext bytes per sealed chunk of an encrypted multipart object, which
+/// bounds the memory completing the upload takes.
+const ENCRYPTION_CHUNK_SIZE: usize = 1024 * 1024;
+pub(crate) const NULL_VERSION_ID: &str = "null";
+/// S3's answer to a read of an SSE-C object that did not send the key.
+const SSE_C_KEY_REQUIRED: &str = "Requests specifying Server Side Encryption with Customer \
+                                  provided keys must provide the appropriate secret key.";
 
 #[derive(Debug, Clone)]
 pub struct XlStorage {
@@ -46,6 +63,14 @@ struct XlMeta {
     version_id: Option<String>,
     is_delete_marker: bool,
     encryption: Option<EncryptionInfo>,
+    #[serde(default)]
+    parts: Vec<ObjectPartInfo>,
+    #[serde(default)]
+    storage_class: Option<String>,
+    /// Last read of an unversioned object; versions keep theirs in the
+    /// versions index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_access: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +78,21 @@ struct EncryptionInfo {
     algorithm: String,
     sse_type: String,
     key_md5: Option<String>,
+    /// Whether the data was sealed with the object's bucket, key and
+    /// version as additional authenticated data. Objects written before
+    /// that decrypt without it.
+    #[serde(default)]
+    object_aad: bool,
+    /// Plaintext bytes per sealed chunk for data sealed in chunks. Data
+    /// sealed in one piece has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_size: Option<u64>,
+}
+
+/// Key and additional authenticated data an object's data is sealed with.
+struct DataKey {
+    key: [u8; 32],
+    aad: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +102,8 @@ struct VersionIndexEntry {
     last_modified: DateTime<Utc>,
     etag: Option<String>,
     size: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_access: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,7 +114,98 @@ struct MultipartUploadMeta {
     initiated: DateTime<Utc>,
 }
 
+/// A staged multipart upload checked and ready to be assembled.
+pub(crate) struct CompletedUpload {
+    /// Part files in object order.
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) etag: String,
+    pub(crate) manifest: Vec<ObjectPartInfo>,
+    pub(crate) content_type: String,
+    pub(crate) metadata: HashMap<String, String>,
+}
+
+/// Body of an object being stored.
+enum ObjectData<'a> {
+    Bytes(Bytes),
+    /// Uploaded multipart parts, copied into the data file one at a time so
+    /// the assembled object is never held in memory.
+    Parts {
+        paths: &'a [PathBuf],
+        etag: String,
+        manifest: Vec<ObjectPartInfo>,
+    },
+    /// The unencrypted data file of another object, hard-linked into place
+    /// so a copy neither reads nor rewrites it.
+    Linked {
+        path: PathBuf,
+        etag: String,
+        manifest: Vec<ObjectPartInfo>,
+    },
+}
+
+/// Removes the directory a write is staging into unless the write commits.
+/// Runs on drop, so a put whose future is dropped mid-write (client gone)
+/// or that fails part way leaves no partial data behind.
+struct StagingGuard {
+    dir: Option<PathBuf>,
+    /// Removed as well when left empty.
+    parent: Option<PathBuf>,
+}
+
+impl StagingGuard {
+    fn new(dir: PathBuf, parent: Option<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir),
+            parent,
+        }
+    }
+
+    fn commit(mut self) {
+        self.dir = None;
+    }
+}
+
+impl Drop for StagingGuard {
+    fn drop(&mut self) {
+        let Some(dir) = self.dir.take() else {
+            return;
+        };
+        let parent = self.parent.take();
+        let cleanup = move || remove_staged_dir(&dir, parent.as_deref());
+        // Guards are dropped on runtime workers when a request is cancelled,
+        // and removing a large staged object blocks.
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(cleanup);
+            }
+            Err(_) => cleanup(),
+        }
+    }
+}
+
+fn remove_staged_dir(dir: &Path, parent: Option<&Path>) {
+    if let Err(err) = std::fs::remove_dir_all(dir)
+        && err.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!(path = %dir.display(), error = %err, "failed to remove staged object data");
+    }
+    if let Some(parent) = parent {
+        let _ = std::fs::remove_dir(parent);
+    }
+}
+
+enum UploadEntry {
+    Upload(MultipartUploadInfo),
+    Prefix(String),
+}
+
+enum VersionEntry {
+    Version(ObjectVersion),
+    Prefix(String),
+}
+
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 enum ListEntry {
     Object(ObjectInfo),
     Prefix(String),
@@ -99,7 +232,7 @@ impl XlStorage {
     }
 
     pub async fn make_bucket(&self, bucket: &str) -> Result<()> {
-        validate_bucket_name(bucket)?;
+        validate_new_bucket_name(bucket)?;
         let bucket_path = self.bucket_path(bucket);
 
         if is_existing_directory(&bucket_path).await? {
@@ -169,12 +302,20 @@ impl XlStorage {
             .await
             .map_err(|err| map_bucket_io_error(bucket, err))?;
 
-        if entries.next_entry().await?.is_some() {
-            return Err(MaxioError::InvalidArgument(format!(
-                "bucket is not empty: {bucket}"
-            )));
+        // The bucket's own versioning state does not count as content.
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name() != VERSIONING_FILE_NAME {
+                return Err(MaxioError::InvalidArgument(format!(
+                    "bucket is not empty: {bucket}"
+                )));
+            }
         }
 
+        match fs::remove_file(bucket_path.join(VERSIONING_FILE_NAME)).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(MaxioError::Io(err)),
+        }
         fs::remove_dir(bucket_path)
             .await
             .map_err(|err| map_bucket_io_error(bucket, err))?;
@@ -213,11 +354,39 @@ impl XlStorage {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
         ensure_bucket_exists(self, bucket).await?;
+        self.store_object(
+            bucket,
+            key,
+            ObjectData::Bytes(data),
+            content_type,
+            metadata,
+            encryption,
+        )
+        .await
+        .map_err(key_path_error)
+    }
+
+    /// Writes a new object (or version) from `data` and commits its metadata.
+    /// Callers validate the bucket and key first.
+    async fn store_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: ObjectData<'_>,
+        content_type: Option<&str>,
+        mut metadata: HashMap<String, String>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
         let state = self.read_bucket_versioning(bucket).await?;
-        let size = i64::try_from(data.len()).map_err(|_| {
-            MaxioError::InvalidArgument(format!("object is too large to store: {bucket}/{key}"))
-        })?;
-        let etag = format!("{:x}", Md5::digest(&data));
+        let storage_class = metadata.remove(STORAGE_CLASS_META_KEY);
+        let durable = storage_class.as_deref() != Some(REDUCED_REDUNDANCY_STORAGE_CLASS);
+        // The ETag is taken before encryption so clients can check it against
+        // the bytes they sent and will read back.
+        let (etag, parts) = match &data {
+            ObjectData::Bytes(bytes) => (md5_hex(bytes), Vec::new()),
+            ObjectData::Parts { etag, manifest, .. }
+            | ObjectData::Linked { etag, manifest, .. } => (etag.clone(), manifest.clone()),
+        };
         let mod_time = Utc::now();
         let content_type = content_type.unwrap_or(DEFAULT_CONTENT_TYPE).to_string();
 
@@ -230,15 +399,21 @@ impl XlStorage {
 
                 let data_dir = Uuid::new_v4().to_string();
                 let data_path = object_path.join(&data_dir);
+                let staging = StagingGuard::new(data_path.clone(), Some(object_path.clone()));
                 fs::create_dir_all(&data_path).await?;
-                let (object_key, encryption_info) =
+                let (data_key, mut encryption_info) =
                     self.resolve_put_encryption(bucket, key, None, encryption.as_ref())?;
-                let stored_data = match object_key {
-                    Some(object_key) => {
-                        cipher::encrypt(&object_key, &data).map_err(map_crypto_error)?
-                    }
-                    None => data.to_vec(),
-                };
+                let (size, chunk_size) = write_object_data(
+                    &data_path.join(DATA_PART_FILE_NAME),
+                    data,
+                    data_key.as_ref(),
+                    durable,
+                )
+                .await?;
+                let size = object_size(bucket, key, size)?;
+                if let Some(info) = encryption_info.as_mut() {
+                    info.chunk_size = chunk_size;
+                }
 
                 let xl_meta = XlMeta {
                     version: "1.0".to_string(),
@@ -251,11 +426,14 @@ impl XlStorage {
                     version_id: None,
                     is_delete_marker: false,
                     encryption: encryption_info,
+                    parts,
+                    storage_class: storage_class.clone(),
+                    last_access: None,
                 };
 
-                fs::write(data_path.join(DATA_PART_FILE_NAME), stored_data).await?;
                 self.write_xl_meta(&object_path.join(META_FILE_NAME), &xl_meta)
                     .await?;
+                staging.commit();
 
                 Ok(ObjectInfo {
                     bucket: bucket.to_string(),
@@ -267,6 +445,7 @@ impl XlStorage {
                     metadata,
                     version_id: None,
                     encryption: xl_meta.encryption.clone().map(meta_encryption_to_object),
+                    storage_class,
                 })
             }
             VersioningState::Enabled | VersioningState::Suspended => {
@@ -288,19 +467,25 @@ impl XlStorage {
                 let data_dir = Uuid::new_v4().to_string();
                 let version_path = object_path.join(&version_id);
                 let data_path = version_path.join(&data_dir);
+                let staging = StagingGuard::new(version_path.clone(), None);
                 fs::create_dir_all(&data_path).await?;
-                let (object_key, encryption_info) = self.resolve_put_encryption(
+                let (data_key, mut encryption_info) = self.resolve_put_encryption(
                     bucket,
                     key,
                     Some(version_id.as_str()),
                     encryption.as_ref(),
                 )?;
-                let stored_data = match object_key {
-                    Some(object_key) => {
-                        cipher::encrypt(&object_key, &data).map_err(map_crypto_error)?
-                    }
-                    None => data.to_vec(),
-                };
+                let (size, chunk_size) = write_object_data(
+                    &data_path.join(DATA_PART_FILE_NAME),
+                    data,
+                    data_key.as_ref(),
+                    durable,
+                )
+                .await?;
+                let size = object_size(bucket, key, size)?;
+                if let Some(info) = encryption_info.as_mut() {
+                    info.chunk_size = chunk_size;
+                }
 
                 let xl_meta = XlMeta {
                     version: "1.0".to_string(),
@@ -313,9 +498,11 @@ impl XlStorage {
                     version_id: Some(version_id.clone()),
                     is_delete_marker: false,
                     encryption: encryption_info,
+                    parts,
+                    storage_class: storage_class.clone(),
+                    last_access: None,
                 };
 
-                fs::write(data_path.join(DATA_PART_FILE_NAME), stored_data).await?;
                 self.write_xl_meta(&version_path.join(META_FILE_NAME), &xl_meta)
                     .await?;
 
@@ -327,9 +514,11 @@ impl XlStorage {
                         last_modified: mod_time,
                         etag: Some(etag.clone()),
                         size,
+                        last_access: None,
                     },
                 );
                 self.write_versions_index(&object_path, &versions).await?;
+                staging.commit();
 
                 Ok(ObjectInfo {
                     bucket: bucket.to_string(),
@@ -341,6 +530,7 @@ impl XlStorage {
                     metadata,
                     version_id: Some(version_id),
                     encryption: xl_meta.encryption.clone().map(meta_encryption_to_object),
+                    storage_class,
                 })
             }
         }
@@ -373,12 +563,9 @@ impl XlStorage {
             return Ok((object_info, Bytes::from(plain)));
         }
 
+        // A delete marker as the latest version hides the object.
         let versions = self.ensure_versions_index(bucket, key).await?;
-        for entry in versions {
-            if entry.is_delete_marker {
-                continue;
-            }
-
+        if let Some(entry) = versions.first().filter(|entry| !entry.is_delete_marker) {
             return self
                 .get_object_version(bucket, key, &entry.version_id, encryption)
                 .await;
@@ -431,23 +618,234 @@ impl XlStorage {
         Ok((object_info, Bytes::from(plain)))
     }
 
+    /// One version's info, read from its metadata alone. An SSE-C version
+    /// still needs the customer key it was written with.
+    pub async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        encryption: Option<GetEncryptionOptions>,
+    ) -> Result<ObjectInfo> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        ensure_bucket_exists(self, bucket).await?;
+
+        let (object_info, xl_meta, _) = self
+            .read_object_version_meta(bucket, key, version_id)
+            .await?;
+        if xl_meta.is_delete_marker {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            });
+        }
+        if let Some(encryption_info) = xl_meta
+            .encryption
+            .as_ref()
+            .filter(|info| info.sse_type == "SSE-C")
+        {
+            sse_c_customer_key(encryption_info, encryption.as_ref())?;
+        }
+
+        Ok(object_info)
+    }
+
+    /// The latest visible version's info, read from its metadata alone so
+    /// that encrypted objects can be inspected without their key.
+    pub async fn stat_latest_object(&self, bucket: &str, key: &str) -> Result<ObjectInfo> {
+        let (object_info, _, _) = self.latest_object_meta(bucket, key).await?;
+        Ok(object_info)
+    }
+
+    /// Copies `source` to `bucket/key`. An unencrypted source is hard-linked
+    /// into the new object, so a copy of any size costs a metadata write;
+    /// encrypted sources, encrypted copies and copies onto the source itself
+    /// are read and stored again.
+    pub async fn copy_object(
+        &self,
+        source: &CopySource,
+        bucket: &str,
+        key: &str,
+        metadata: &CopyMetadataFn<'_>,
+        encryption: Option<PutEncryptionOptions>,
+    ) -> Result<(ObjectInfo, ObjectInfo)> {
+        validate_bucket_name(&source.bucket)?;
+        validate_object_key(&source.key)?;
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        ensure_bucket_exists(self, &source.bucket).await?;
+        ensure_bucket_exists(self, bucket).await?;
+
+        let (source_info, source_meta, source_dir) = match source.version_id.as_deref() {
+            Some(version_id) => {
+                self.read_object_version_meta(&source.bucket, &source.key, version_id)
+                    .await?
+            }
+            None => self.latest_object_meta(&source.bucket, &source.key).await?,
+        };
+        if source_meta.is_delete_marker {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: source.bucket.clone(),
+                key: source.key.clone(),
+            });
+        }
+
+        // Storing onto the source's own key replaces the directory the link
+        // would point into.
+        let same_object = source.bucket == bucket && source.key == key;
+        if source_meta.encryption.is_some() || encryption.is_some() || same_object {
+            let (source_info, data) = match source.version_id.as_deref() {
+                Some(version_id) => {
+                    self.get_object_version(
+                        &source.bucket,
+                        &source.key,
+                        version_id,
+                        source.encryption.clone(),
+                    )
+                    .await?
+                }
+                None => {
+                    self.get_object(&source.bucket, &source.key, source.encryption.clone())
+                        .await?
+                }
+            };
+            let (content_type, metadata) = metadata(&source_info)?;
+            let info = self
+                .store_object(
+                    bucket,
+                    key,
+                    ObjectData::Bytes(data),
+                    content_type.as_deref(),
+                    metadata,
+                    encryption,
+                )
+                .await
+                .map_err(key_path_error)?;
+            return Ok((source_info, info));
+        }
+
+        let (content_type, metadata) = metadata(&source_info)?;
+        let data = ObjectData::Linked {
+            path: source_dir
+                .join(&source_meta.data_dir)
+                .join(DATA_PART_FILE_NAME),
+            etag: source_meta.etag,
+            manifest: source_meta.parts,
+        };
+        let info = self
+            .store_object(bucket, key, data, content_type.as_deref(), metadata, None)
+            .await
+            .map_err(key_path_error)?;
+        Ok((source_info, info))
+    }
+
+    /// The latest visible version along with its metadata and the directory
+    /// holding it.
+    async fn latest_object_meta(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(ObjectInfo, XlMeta, PathBuf)> {
+        let state = self.read_bucket_versioning(bucket).await?;
+        if state == VersioningState::Unversioned {
+            return self.read_object(bucket, key).await;
+        }
+
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        let versions = self.ensure_versions_index(bucket, key).await?;
+        if let Some(entry) = versions.first().filter(|entry| !entry.is_delete_marker) {
+            return self
+                .read_object_version_meta(bucket, key, &entry.version_id)
+                .await;
+        }
+
+        Err(MaxioError::ObjectNotFound {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    /// The latest visible version's info, read from its metadata alone like
+    /// [`Self::get_object_version_info`]. An SSE-C object still needs the
+    /// customer key it was written with.
     pub async fn get_object_info(
         &self,
         bucket: &str,
         key: &str,
         encryption: Option<GetEncryptionOptions>,
     ) -> Result<ObjectInfo> {
-        let (object_info, _) = self.get_object(bucket, key, encryption).await?;
+        let (object_info, xl_meta, _) = self.latest_object_meta(bucket, key).await?;
+        if let Some(encryption_info) = xl_meta
+            .encryption
+            .as_ref()
+            .filter(|info| info.sse_type == "SSE-C")
+        {
+            sse_c_customer_key(encryption_info, encryption.as_ref())?;
+        }
         Ok(object_info)
     }
 
-    pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
+    pub async fn update_object_metadata(
+        &self,
+        bucket: &str,
+        key: &str,
+        content_type: Option<&str>,
+        metadata: HashMap<String, String>,
+    ) -> Result<ObjectInfo> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        ensure_bucket_exists(self, bucket).await?;
+
+        let object_path = self.object_path(bucket, key);
+        let mut versions = self.read_versions_index(&object_path).await?;
+        let latest_version = match versions.first() {
+            Some(entry) if entry.is_delete_marker => {
+                return Err(MaxioError::ObjectNotFound {
+                    bucket: bucket.to_string(),
+                    key: key.to_string(),
+                });
+            }
+            Some(entry) => Some(entry.version_id.clone()),
+            None => None,
+        };
+
+        let (_, mut meta, meta_dir) = match latest_version.as_deref() {
+            Some(version_id) => {
+                self.read_object_version_meta(bucket, key, version_id)
+                    .await?
+            }
+            None => self.read_object(bucket, key).await?,
+        };
+
+        if let Some(content_type) = content_type {
+            meta.content_type = content_type.to_string();
+        }
+        meta.metadata = metadata;
+        meta.mod_time = Utc::now();
+        self.write_xl_meta(&meta_dir.join(META_FILE_NAME), &meta)
+            .await?;
+
+        if let Some(entry) = versions.first_mut() {
+            entry.last_modified = meta.mod_time;
+            self.write_versions_index(&object_path, &versions).await?;
+        }
+
+        let mut info = self.meta_to_object_info(bucket, key, &meta);
+        if info.version_id.is_none() {
+            info.version_id = latest_version;
+        }
+        Ok(info)
+    }
+
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<DeletedObject> {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
         ensure_bucket_exists(self, bucket).await?;
 
         let state = self.read_bucket_versioning(bucket).await?;
-        if state != VersioningState::Enabled {
+        if state == VersioningState::Unversioned {
             let object_path = self.object_path(bucket, key);
             if !is_existing_directory(&object_path).await? {
                 return Err(MaxioError::ObjectNotFound {
@@ -458,7 +856,7 @@ impl XlStorage {
 
             fs::remove_dir_all(&object_path).await?;
             self.cleanup_empty_parents(bucket, &object_path).await?;
-            return Ok(());
+            return Ok(DeletedObject::default());
         }
 
         let object_path = self.object_path(bucket, key);
@@ -470,7 +868,16 @@ impl XlStorage {
         }
 
         let mut versions = self.ensure_versions_index(bucket, key).await?;
-        let version_id = Uuid::new_v4().to_string();
+        // With versioning suspended the marker becomes the null version,
+        // replacing any existing one; versions with ids are kept.
+        let version_id = if state == VersioningState::Enabled {
+            Uuid::new_v4().to_string()
+        } else {
+            versions.retain(|entry| entry.version_id != NULL_VERSION_ID);
+            self.remove_version_dir_if_exists(&object_path, NULL_VERSION_ID)
+                .await?;
+            NULL_VERSION_ID.to_string()
+        };
         let mod_time = Utc::now();
         let marker_meta = XlMeta {
             version: "1.0".to_string(),
@@ -483,6 +890,9 @@ impl XlStorage {
             version_id: Some(version_id.clone()),
             is_delete_marker: true,
             encryption: None,
+            parts: Vec::new(),
+            storage_class: None,
+            last_access: None,
         };
         let marker_path = object_path.join(&version_id);
         fs::create_dir_all(&marker_path).await?;
@@ -492,16 +902,20 @@ impl XlStorage {
         versions.insert(
             0,
             VersionIndexEntry {
-                version_id,
+                version_id: version_id.clone(),
                 is_delete_marker: true,
                 last_modified: mod_time,
                 etag: None,
                 size: 0,
+                last_access: None,
             },
         );
         self.write_versions_index(&object_path, &versions).await?;
 
-        Ok(())
+        Ok(DeletedObject {
+            version_id: Some(version_id),
+            delete_marker: true,
+        })
     }
 
     pub async fn delete_object_version(
@@ -509,7 +923,7 @@ impl XlStorage {
         bucket: &str,
         key: &str,
         version_id: &str,
-    ) -> Result<()> {
+    ) -> Result<DeletedObject> {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
         ensure_bucket_exists(self, bucket).await?;
@@ -528,15 +942,16 @@ impl XlStorage {
         }
 
         let mut versions = self.ensure_versions_index(bucket, key).await?;
-        let original_len = versions.len();
-        versions.retain(|entry| entry.version_id != version_id);
-
-        if versions.len() == original_len {
+        let Some(position) = versions
+            .iter()
+            .position(|entry| entry.version_id == version_id)
+        else {
             return Err(MaxioError::ObjectNotFound {
                 bucket: bucket.to_string(),
                 key: format!("{key}?versionId={version_id}"),
             });
-        }
+        };
+        let removed = versions.remove(position);
 
         self.remove_version_dir_if_exists(&object_path, version_id)
             .await?;
@@ -547,7 +962,10 @@ impl XlStorage {
             self.write_versions_index(&object_path, &versions).await?;
         }
 
-        Ok(())
+        Ok(DeletedObject {
+            version_id: Some(removed.version_id),
+            delete_marker: removed.is_delete_marker,
+        })
     }
 
     pub async fn list_objects(
@@ -557,6 +975,7 @@ impl XlStorage {
         marker: &str,
         delimiter: &str,
         max_keys: i32,
+        order: ListOrder,
     ) -> Result<ListObjectsResult> {
         validate_bucket_name(bucket)?;
         ensure_bucket_exists(self, bucket).await?;
@@ -579,73 +998,20 @@ impl XlStorage {
             }
         }
 
-        objects.sort_by(|a, b| a.key.cmp(&b.key));
-        let mut filtered: Vec<ObjectInfo> = objects
-            .into_iter()
-            .filter(|obj| obj.key.starts_with(prefix))
-            .filter(|obj| marker.is_empty() || obj.key.as_str() > marker)
-            .collect();
-
-        let mut entries = Vec::new();
-        let mut prefixes = HashSet::new();
-
-        if delimiter.is_empty() {
-            for obj in filtered {
-                entries.push(ListEntry::Object(obj));
-            }
-        } else {
-            for obj in filtered.drain(..) {
-                let suffix = &obj.key[prefix.len()..];
-                if let Some(idx) = suffix.find(delimiter) {
-                    let prefix_value = format!("{}{}", prefix, &suffix[..idx + delimiter.len()]);
-                    prefixes.insert(prefix_value);
-                } else {
-                    entries.push(ListEntry::Object(obj));
-                }
-            }
-
-            for prefix_value in prefixes {
-                entries.push(ListEntry::Prefix(prefix_value));
-            }
-        }
-
-        entries.sort_by(|a, b| a.marker().cmp(b.marker()));
-
-        let limit = if max_keys > 0 {
-            usize::try_from(max_keys).unwrap_or(usize::MAX)
-        } else {
-            entries.len()
-        };
-        let is_truncated = entries.len() > limit;
-        let selected = if is_truncated {
-            &entries[..limit]
-        } else {
-            &entries[..]
-        };
-
-        let mut out_objects = Vec::new();
-        let mut out_prefixes = Vec::new();
-        for entry in selected {
-            match entry {
-                ListEntry::Object(obj) => out_objects.push(obj.clone()),
-                ListEntry::Prefix(prefix_value) => out_prefixes.push(prefix_value.clone()),
-            }
-        }
-
-        Ok(ListObjectsResult {
-            objects: out_objects,
-            prefixes: out_prefixes,
-            is_truncated,
-            next_marker: selected.last().map(|entry| entry.marker().to_string()),
-        })
+        Ok(paginate_objects(
+            objects, prefix, marker, delimiter, max_keys, order,
+        ))
     }
 
     pub async fn list_object_versions(
         &self,
         bucket: &str,
         prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        delimiter: &str,
         max_keys: i32,
-    ) -> Result<Vec<ObjectVersion>> {
+    ) -> Result<ListObjectVersionsResult> {
         validate_bucket_name(bucket)?;
         ensure_bucket_exists(self, bucket).await?;
 
@@ -675,6 +1041,7 @@ impl XlStorage {
                         last_modified: meta.mod_time,
                         etag: Some(meta.etag),
                         size: meta.size,
+                        last_access: meta.last_access,
                     });
                 }
                 continue;
@@ -689,6 +1056,7 @@ impl XlStorage {
                     last_modified: entry.last_modified,
                     etag: entry.etag,
                     size: entry.size,
+                    last_access: entry.last_access,
                 });
             }
         }
@@ -700,12 +1068,14 @@ impl XlStorage {
                 .then(a.version_id.cmp(&b.version_id))
         });
 
-        if max_keys > 0 {
-            let limit = usize::try_from(max_keys).unwrap_or(usize::MAX);
-            versions.truncate(limit);
-        }
-
-        Ok(versions)
+        Ok(paginate_versions(
+            versions,
+            prefix,
+            key_marker,
+            version_id_marker,
+            delimiter,
+            max_keys,
+        ))
     }
 
     pub async fn create_multipart_upload(
@@ -758,7 +1128,7 @@ impl XlStorage {
             )));
         }
 
-        let etag = format!("{:x}", Md5::digest(&data));
+        let etag = md5_hex(&data);
         let part_path = self.multipart_part_path(bucket, upload_id, part_number);
         if let Some(parent) = part_path.parent() {
             fs::create_dir_all(parent).await?;
@@ -768,13 +1138,51 @@ impl XlStorage {
         Ok(etag)
     }
 
+    /// Assembles the listed parts into the object. The parts are streamed
+    /// into place; with `encryption` they are sealed in chunks of
+    /// [`ENCRYPTION_CHUNK_SIZE`] on the way.
     pub async fn complete_multipart_upload(
         &self,
         bucket: &str,
         key: &str,
         upload_id: &str,
         parts: Vec<CompletePart>,
+        encryption: Option<PutEncryptionOptions>,
     ) -> Result<ObjectInfo> {
+        let upload = self
+            .prepare_multipart_completion(bucket, key, upload_id, &parts)
+            .await?;
+        let object_info = self
+            .store_object(
+                bucket,
+                key,
+                ObjectData::Parts {
+                    paths: &upload.paths,
+                    etag: upload.etag,
+                    manifest: upload.manifest,
+                },
+                Some(&upload.content_type),
+                upload.metadata,
+                encryption,
+            )
+            .await
+            .map_err(key_path_error)?;
+
+        self.abort_multipart_upload(bucket, key, upload_id).await?;
+
+        Ok(object_info)
+    }
+
+    /// Checks `parts` against the staged upload and returns what completing
+    /// it needs, without touching the part files. Callers that store the
+    /// object elsewhere abort the upload once it is written.
+    pub(crate) async fn prepare_multipart_completion(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletePart],
+    ) -> Result<CompletedUpload> {
         validate_bucket_name(bucket)?;
         validate_object_key(key)?;
         ensure_bucket_exists(self, bucket).await?;
@@ -800,10 +1208,11 @@ impl XlStorage {
             .collect();
 
         let mut previous_part = 0;
-        let mut output = Vec::new();
-        let mut final_etag_material = Vec::with_capacity(parts.len() * 16);
+        let mut part_paths = Vec::with_capacity(parts.len());
+        let mut manifest = Vec::with_capacity(parts.len());
+        let mut final_etag = CompositeEtag::new();
 
-        for part in &parts {
+        for part in parts {
             validate_part_number(part.part_number)?;
             if part.part_number <= previous_part {
                 return Err(MaxioError::InvalidArgument(
@@ -828,7 +1237,7 @@ impl XlStorage {
             }
 
             let part_path = self.multipart_part_path(bucket, upload_id, part.part_number);
-            let bytes = fs::read(part_path).await.map_err(|err| {
+            fs::metadata(&part_path).await.map_err(|err| {
                 if err.kind() == std::io::ErrorKind::NotFound {
                     MaxioError::InvalidArgument(format!(
                         "missing uploaded part {} for upload id {upload_id}",
@@ -838,34 +1247,144 @@ impl XlStorage {
                     MaxioError::Io(err)
                 }
             })?;
-            output.extend_from_slice(&bytes);
+            part_paths.push(part_path);
+            manifest.push(ObjectPartInfo {
+                part_number: part.part_number,
+                size: part_info.size,
+                etag: part_info.etag.clone(),
+            });
 
-            let part_md5 = decode_md5_hex(&part_info.etag)?;
-            final_etag_material.extend_from_slice(&part_md5);
+            final_etag.add_part(&part_info.etag)?;
         }
 
-        let final_etag = format!("{:x}-{}", Md5::digest(&final_etag_material), parts.len());
-        let content_type = upload_meta
-            .content_type
-            .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+        Ok(CompletedUpload {
+            paths: part_paths,
+            etag: final_etag.finish(),
+            manifest,
+            content_type: upload_meta
+                .content_type
+                .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string()),
+            metadata: upload_meta.metadata,
+        })
+    }
 
-        let mut object_info = self
-            .put_object(
-                bucket,
-                key,
-                Bytes::from(output),
-                Some(&content_type),
-                upload_meta.metadata.clone(),
-                None,
-            )
-            .await?;
-        self.update_object_etag(bucket, key, object_info.version_id.as_deref(), &final_etag)
-            .await?;
+    pub async fn stat_object_parts(&self, bucket: &str, key: &str) -> Result<Vec<ObjectPartInfo>> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        ensure_bucket_exists(self, bucket).await?;
 
-        self.abort_multipart_upload(bucket, key, upload_id).await?;
+        if self.read_bucket_versioning(bucket).await? == VersioningState::Unversioned {
+            let (_, xl_meta, _) = self.read_object(bucket, key).await?;
+            return Ok(xl_meta.parts);
+        }
 
-        object_info.etag = final_etag;
-        Ok(object_info)
+        let versions = self.ensure_versions_index(bucket, key).await?;
+        let Some(entry) = versions.iter().find(|entry| !entry.is_delete_marker) else {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            });
+        };
+        let (_, xl_meta, _) = self
+            .read_object_version_meta(bucket, key, &entry.version_id)
+            .await?;
+        Ok(xl_meta.parts)
+    }
+
+    /// Stamps the latest version of `key` as read at `accessed`. Only the
+    /// access time is rewritten, so ETags, modification times and version
+    /// order stay as they were.
+    pub async fn record_access(
+        &self,
+        bucket: &str,
+        key: &str,
+        accessed: DateTime<Utc>,
+    ) -> Result<()> {
+        validate_bucket_name(bucket)?;
+        validate_object_key(key)?;
+        ensure_bucket_exists(self, bucket).await?;
+
+        let not_found = || MaxioError::ObjectNotFound {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        };
+        let object_path = self.object_path(bucket, key);
+        let mut versions = self.read_versions_index(&object_path).await?;
+        if let Some(latest) = versions.first_mut() {
+            if latest.is_delete_marker {
+                return Err(not_found());
+            }
+            latest.last_access = Some(accessed);
+            return self.write_versions_index(&object_path, &versions).await;
+        }
+
+        let meta_path = object_path.join(META_FILE_NAME);
+        let mut meta = self
+            .read_xl_meta_if_exists(&meta_path)
+            .await?
+            .ok_or_else(not_found)?;
+        meta.last_access = Some(accessed);
+        self.write_xl_meta(&meta_path, &meta).await
+    }
+
+    /// Re-reads every version under `prefix` and checks its data against the
+    /// size and ETag recorded when it was written. Unlike an erasure set, a
+    /// single disk has no bitrot check on read, so this is how corruption is
+    /// found before a client reads it.
+    pub async fn verify_objects(&self, bucket: &str, prefix: &str) -> Result<ChecksumReport> {
+        let listing = self
+            .list_object_versions(bucket, prefix, "", "", "", i32::MAX)
+            .await?;
+        let mut report = ChecksumReport::default();
+        for version in listing.versions {
+            if version.is_delete_marker {
+                continue;
+            }
+            let problem = match self
+                .read_object_version_meta(bucket, &version.key, &version.version_id)
+                .await
+            {
+                Ok((_, meta, _))
+                    if meta
+                        .encryption
+                        .as_ref()
+                        .is_some_and(|info| info.sse_type == "SSE-C") =>
+                {
+                    report.skipped += 1;
+                    continue;
+                }
+                Ok((_, meta, path)) => {
+                    let data_path = path.join(&meta.data_dir).join(DATA_PART_FILE_NAME);
+                    match fs::read(data_path).await {
+                        Ok(stored) => match self.decrypt_object_data(
+                            bucket,
+                            &version.key,
+                            Some(&version.version_id),
+                            meta.encryption.as_ref(),
+                            &stored,
+                            None,
+                        ) {
+                            Ok(data) => data_mismatch(&meta, &data),
+                            Err(err) => Some(err.to_string()),
+                        },
+                        Err(err) => Some(format!("data file is unreadable: {err}")),
+                    }
+                }
+                // Removed since it was listed.
+                Err(MaxioError::ObjectNotFound { .. }) => continue,
+                Err(err) => Some(err.to_string()),
+            };
+            report.verified += 1;
+            if let Some(reason) = problem {
+                report.mismatches.push(ChecksumMismatch {
+                    bucket: bucket.to_string(),
+                    key: version.key,
+                    version_id: version.version_id,
+                    reason,
+                });
+            }
+        }
+        Ok(report)
     }
 
     pub async fn abort_multipart_upload(
@@ -932,7 +1451,7 @@ impl XlStorage {
             let entry_meta = entry.metadata().await?;
             let last_modified =
                 filetime_to_utc(entry_meta.modified().ok()).unwrap_or_else(Utc::now);
-            let etag = format!("{:x}", Md5::digest(&bytes));
+            let etag = md5_hex(&bytes);
 
             parts.push(PartInfo {
                 part_number,
@@ -950,13 +1469,24 @@ impl XlStorage {
         &self,
         bucket: &str,
         prefix: &str,
-    ) -> Result<Vec<MultipartUploadInfo>> {
+        key_marker: &str,
+        upload_id_marker: &str,
+        delimiter: &str,
+        max_uploads: i32,
+    ) -> Result<ListMultipartUploadsResult> {
         validate_bucket_name(bucket)?;
         ensure_bucket_exists(self, bucket).await?;
 
         let multipart_root = self.multipart_root_path(bucket);
         if !is_existing_directory(&multipart_root).await? {
-            return Ok(Vec::new());
+            return Ok(paginate_uploads(
+                Vec::new(),
+                prefix,
+                key_marker,
+                upload_id_marker,
+                delimiter,
+                max_uploads,
+            ));
         }
 
         let mut entries = fs::read_dir(multipart_root).await?;
@@ -980,8 +1510,148 @@ impl XlStorage {
             });
         }
 
-        uploads.sort_by(|a, b| a.key.cmp(&b.key).then(a.upload_id.cmp(&b.upload_id)));
-        Ok(uploads)
+        Ok(paginate_uploads(
+            uploads,
+            prefix,
+            key_marker,
+            upload_id_marker,
+            delimiter,
+            max_uploads,
+        ))
+    }
+
+    /// Removes multipart uploads initiated before `cutoff` in every bucket.
+    /// Upload directories without readable metadata, left behind by a crash
+    /// during creation, are aged by their modification time instead.
+    pub async fn remove_stale_multipart_uploads(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let mut removed = 0;
+        for bucket in self.list_buckets().await? {
+            let multipart_root = self.multipart_root_path(&bucket.name);
+            if !is_existing_directory(&multipart_root).await? {
+                continue;
+            }
+
+            let mut entries = fs::read_dir(&multipart_root).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let upload_id = entry.file_name().to_string_lossy().to_string();
+                let initiated = match self
+                    .read_multipart_upload_meta(&bucket.name, &upload_id)
+                    .await
+                {
+                    Ok(meta) => Some(meta.initiated),
+                    Err(_) => entry
+                        .metadata()
+                        .await
+                        .ok()
+                        .and_then(|metadata| filetime_to_utc(metadata.modified().ok())),
+                };
+                if initiated.is_none_or(|initiated| initiated >= cutoff) {
+                    continue;
+                }
+
+                match fs::remove_dir_all(entry.path()).await {
+                    Ok(()) => removed += 1,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        warn!(
+                            bucket = %bucket.name,
+                            upload_id = %upload_id,
+                            error = %err,
+                            "failed to remove stale multipart upload"
+                        );
+                    }
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Moves the directory holding every object under `from_prefix` to
+    /// `to_prefix` with a single rename, so versions and metadata move with
+    /// the objects and nothing is copied.
+    pub async fn rename_prefix(
+        &self,
+        bucket: &str,
+        from_prefix: &str,
+        to_prefix: &str,
+    ) -> Result<()> {
+        validate_bucket_name(bucket)?;
+        let (from, to) = validate_prefix_rename(from_prefix, to_prefix)?;
+        ensure_bucket_exists(self, bucket).await?;
+
+        let from_path = self.object_path(bucket, from);
+        let to_path = self.object_path(bucket, to);
+        if is_object_root(&from_path).await {
+            return Err(MaxioError::InvalidArgument(format!(
+                "object {from} shares a directory with prefix {from_prefix}"
+            )));
+        }
+        let roots = self.collect_object_roots(&from_path).await?;
+        if roots.is_empty() {
+            return Err(MaxioError::ObjectNotFound {
+                bucket: bucket.to_string(),
+                key: from_prefix.to_string(),
+            });
+        }
+        // Encrypted objects are bound to their key, by the derived SSE-S3
+        // key or the authenticated data, and would no longer decrypt under
+        // their new name.
+        if self.any_key_bound_object(&roots).await? {
+            return Err(MaxioError::NotImplemented(format!(
+                "renaming prefix {from_prefix} holding encrypted objects"
+            )));
+        }
+
+        if fs::symlink_metadata(&to_path).await.is_ok() {
+            return Err(MaxioError::PreconditionFailed(format!(
+                "destination prefix {to_prefix} is not empty"
+            )));
+        }
+        for (end, _) in to.rmatch_indices('/') {
+            if is_object_root(&self.object_path(bucket, &to[..end])).await {
+                return Err(MaxioError::InvalidArgument(format!(
+                    "destination prefix {to_prefix} lies inside object {}",
+                    &to[..end]
+                )));
+            }
+        }
+
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        if let Err(err) = fs::rename(&from_path, &to_path).await {
+            self.cleanup_empty_parents(bucket, &to_path).await?;
+            return Err(MaxioError::Io(err));
+        }
+        self.cleanup_empty_parents(bucket, &from_path).await
+    }
+
+    async fn any_key_bound_object(&self, roots: &[PathBuf]) -> Result<bool> {
+        for root in roots {
+            let mut meta_paths = vec![root.join(META_FILE_NAME)];
+            let mut entries = fs::read_dir(root).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    meta_paths.push(entry.path().join(META_FILE_NAME));
+                }
+            }
+            for meta_path in meta_paths {
+                let meta = self.read_xl_meta_if_exists(&meta_path).await?;
+                if meta
+                    .and_then(|meta| meta.encryption)
+                    .is_some_and(|encryption| {
+                        encryption.sse_type == "SSE-S3" || encryption.object_aad
+                    })
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
     }
 
     fn bucket_path(&self, bucket: &str) -> PathBuf {
@@ -1087,6 +1757,7 @@ impl XlStorage {
             metadata: xl_meta.metadata.clone(),
             version_id: xl_meta.version_id.clone(),
             encryption: xl_meta.encryption.clone().map(meta_encryption_to_object),
+            storage_class: xl_meta.storage_class.clone(),
         }
     }
 
@@ -1096,10 +1767,11 @@ impl XlStorage {
         key: &str,
         version_id: Option<&str>,
         encryption: Option<&PutEncryptionOptions>,
-    ) -> Result<(Option<[u8; 32]>, Option<EncryptionInfo>)> {
+    ) -> Result<(Option<DataKey>, Option<EncryptionInfo>)> {
         let Some(encryption) = encryption else {
             return Ok((None, None));
         };
+        let aad = cipher::object_aad(bucket, key, version_id);
 
         if let Some(customer_key) = encryption.sse_c_key {
             let key_md5 = encryption.sse_c_key_md5.clone().ok_or_else(|| {
@@ -1109,22 +1781,32 @@ impl XlStorage {
             })?;
 
             return Ok((
-                Some(customer_key),
+                Some(DataKey {
+                    key: customer_key,
+                    aad,
+                }),
                 Some(EncryptionInfo {
                     algorithm: "AES256".to_string(),
                     sse_type: "SSE-C".to_string(),
                     key_md5: Some(key_md5),
+                    object_aad: true,
+                    chunk_size: None,
                 }),
             ));
         }
 
         if encryption.sse_s3 {
             return Ok((
-                Some(self.master_key.derive_object_key(bucket, key, version_id)),
+                Some(DataKey {
+                    key: self.master_key.derive_object_key(bucket, key, version_id),
+                    aad,
+                }),
                 Some(EncryptionInfo {
                     algorithm: "AES256".to_string(),
                     sse_type: "SSE-S3".to_string(),
                     key_md5: None,
+                    object_aad: true,
+                    chunk_size: None,
                 }),
             ));
         }
@@ -1144,49 +1826,40 @@ impl XlStorage {
         let Some(encryption_info) = encryption_info else {
             return Ok(stored_data.to_vec());
         };
+        let aad = if encryption_info.object_aad {
+            cipher::object_aad(bucket, key, version_id)
+        } else {
+            Vec::new()
+        };
+
+        let chunk_size = encryption_info
+            .chunk_size
+            .map(|size| {
+                usize::try_from(size).map_err(|_| {
+                    MaxioError::InternalError(format!("invalid encryption chunk size: {size}"))
+                })
+            })
+            .transpose()?;
+        let decrypt = |key: &[u8; 32]| match chunk_size {
+            Some(chunk_size) => cipher::decrypt_chunks(key, stored_data, &aad, chunk_size),
+            None => cipher::decrypt(key, stored_data, &aad),
+        };
 
         match encryption_info.sse_type.as_str() {
             "SSE-S3" => {
                 let object_key = self.master_key.derive_object_key(bucket, key, version_id);
-                match cipher::decrypt(&object_key, stored_data) {
+                match decrypt(&object_key) {
                     Ok(data) => Ok(data),
                     Err(err) if version_id == Some(NULL_VERSION_ID) => {
                         let fallback_key = self.master_key.derive_object_key(bucket, key, None);
-                        cipher::decrypt(&fallback_key, stored_data)
-                            .map_err(|_| map_crypto_error(err))
+                        decrypt(&fallback_key).map_err(|_| map_crypto_error(err))
                     }
                     Err(err) => Err(map_crypto_error(err)),
                 }
             }
             "SSE-C" => {
-                let request_encryption = request_encryption.ok_or_else(|| {
-                    MaxioError::InvalidArgument(
-                        "missing SSE-C headers for encrypted object access".to_string(),
-                    )
-                })?;
-                let customer_key = request_encryption.sse_c_key.ok_or_else(|| {
-                    MaxioError::InvalidArgument(
-                        "missing SSE-C customer key for encrypted object access".to_string(),
-                    )
-                })?;
-                let request_md5 = request_encryption.sse_c_key_md5.clone().ok_or_else(|| {
-                    MaxioError::InvalidArgument(
-                        "missing SSE-C customer key MD5 for encrypted object access".to_string(),
-                    )
-                })?;
-                let expected_md5 = encryption_info.key_md5.clone().ok_or_else(|| {
-                    MaxioError::InternalError(
-                        "encrypted object metadata missing SSE-C key md5".to_string(),
-                    )
-                })?;
-
-                if request_md5 != expected_md5 {
-                    return Err(MaxioError::AccessDenied(
-                        "SSE-C customer key MD5 mismatch".to_string(),
-                    ));
-                }
-
-                cipher::decrypt(&customer_key, stored_data).map_err(map_crypto_error)
+                let customer_key = sse_c_customer_key(encryption_info, request_encryption)?;
+                decrypt(&customer_key).map_err(map_crypto_error)
             }
             other => Err(MaxioError::InternalError(format!(
                 "unsupported encryption type in metadata: {other}"
@@ -1325,6 +1998,7 @@ impl XlStorage {
             last_modified: migrated_meta.mod_time,
             etag: Some(migrated_meta.etag),
             size: migrated_meta.size,
+            last_access: migrated_meta.last_access,
         }];
         self.write_versions_index(&object_path, &out).await?;
         Ok(out)
@@ -1347,17 +2021,15 @@ impl XlStorage {
             return Ok(None);
         }
 
-        for entry in versions {
-            if entry.is_delete_marker {
-                continue;
+        match versions.first() {
+            Some(entry) if !entry.is_delete_marker => {
+                let (info, _, _) = self
+                    .read_object_version_meta(bucket, key, &entry.version_id)
+                    .await?;
+                Ok(Some(info))
             }
-            let (info, _, _) = self
-                .read_object_version_meta(bucket, key, &entry.version_id)
-                .await?;
-            return Ok(Some(info));
+            _ => Ok(None),
         }
-
-        Ok(None)
     }
 
     async fn collect_object_roots(&self, bucket_path: &Path) -> Result<Vec<PathBuf>> {
@@ -1382,16 +2054,7 @@ impl XlStorage {
                     continue;
                 }
 
-                let has_versions = fs::metadata(path.join(VERSIONS_INDEX_FILE_NAME))
-                    .await
-                    .map(|meta| meta.is_file())
-                    .unwrap_or(false);
-                let has_legacy_meta = fs::metadata(path.join(META_FILE_NAME))
-                    .await
-                    .map(|meta| meta.is_file())
-                    .unwrap_or(false);
-
-                if has_versions || has_legacy_meta {
+                if is_object_root(&path).await {
                     roots.push(path);
                 } else {
                     stack.push(path);
@@ -1424,76 +2087,187 @@ impl XlStorage {
 
         Ok(())
     }
+}
 
-    async fn update_object_etag(
-        &self,
-        bucket: &str,
-        key: &str,
-        version_id: Option<&str>,
-        etag: &str,
-    ) -> Result<()> {
-        let object_path = self.object_path(bucket, key);
+fn meta_encryption_to_object(value: EncryptionInfo) -> ObjectEncryption {
+    ObjectEncryption {
+        algorithm: value.algorithm,
+        sse_type: value.sse_type,
+        key_md5: value.key_md5,
+    }
+}
 
-        match version_id {
-            Some(version_id) => {
-                let meta_path = object_path.join(version_id).join(META_FILE_NAME);
-                let mut meta = self
-                    .read_xl_meta_if_exists(&meta_path)
-                    .await?
-                    .ok_or_else(|| MaxioError::ObjectNotFound {
-                        bucket: bucket.to_string(),
-                        key: format!("{key}?versionId={version_id}"),
-                    })?;
-                meta.etag = etag.to_string();
-                self.write_xl_meta(&meta_path, &meta).await?;
-
-                let mut versions = self.read_versions_index(&object_path).await?;
-                for entry in &mut versions {
-                    if entry.version_id == version_id {
-                        entry.etag = Some(etag.to_string());
-                        break;
-                    }
-                }
-                if !versions.is_empty() {
-                    self.write_versions_index(&object_path, &versions).await?;
-                }
-            }
-            None => {
-                let meta_path = object_path.join(META_FILE_NAME);
-                let mut meta = self
-                    .read_xl_meta_if_exists(&meta_path)
-                    .await?
-                    .ok_or_else(|| MaxioError::ObjectNotFound {
-                        bucket: bucket.to_string(),
-                        key: key.to_string(),
-                    })?;
-                meta.etag = etag.to_string();
-                self.write_xl_meta(&meta_path, &meta).await?;
+/// Writes `data` to `path`, encrypting it with `data_key` when given, and
+/// syncs it to disk when `durable`. Returns the plaintext size, and the
+/// chunk size when the data was sealed in chunks.
+async fn write_object_data(
+    path: &Path,
+    data: ObjectData<'_>,
+    data_key: Option<&DataKey>,
+    durable: bool,
+) -> Result<(u64, Option<u64>)> {
+    if let ObjectData::Linked { path: source, .. } = &data {
+        if data_key.is_some() {
+            return Err(MaxioError::NotImplemented(
+                "server-side encryption of linked copies".to_string(),
+            ));
+        }
+        // Filesystems without hard links get a plain copy instead.
+        if fs::hard_link(source, path).await.is_err() {
+            fs::copy(source, path).await?;
+        }
+        return Ok((fs::metadata(path).await?.len(), None));
+    }
+
+    let mut file = fs::File::create(path).await?;
+    let mut chunk_size = None;
+    let written = match (data, data_key) {
+        (ObjectData::Bytes(data), Some(data_key)) => {
+            let encrypted =
+                cipher::encrypt(&data_key.key, &data, &data_key.aad).map_err(map_crypto_error)?;
+            file.write_all(&encrypted).await?;
+            data.len() as u64
+        }
+        (ObjectData::Bytes(data), None) => {
+            file.write_all(&data).await?;
+            data.len() as u64
+        }
+        (ObjectData::Parts { paths, .. }, Some(data_key)) => {
+            chunk_size = Some(ENCRYPTION_CHUNK_SIZE as u64);
+            write_sealed_chunks(&mut file, paths, data_key).await?
+        }
+        (ObjectData::Parts { paths, .. }, None) => {
+            let mut written = 0;
+            for part_path in paths {
+                let mut part = fs::File::open(part_path).await?;
+                written += tokio::io::copy(&mut part, &mut file).await?;
             }
+            written
         }
+        (ObjectData::Linked { .. }, _) => unreachable!("linked data is never written"),
+    };
+    file.flush().await?;
+    if durable {
+        file.sync_all().await?;
+    }
+    Ok((written, chunk_size))
+}
 
-        Ok(())
+/// Seals the concatenated `paths` into `file` in chunks of
+/// [`ENCRYPTION_CHUNK_SIZE`], holding one chunk in memory at a time.
+/// Returns the plaintext size.
+async fn write_sealed_chunks(
+    file: &mut fs::File,
+    paths: &[PathBuf],
+    data_key: &DataKey,
+) -> Result<u64> {
+    let mut total = 0;
+    for part_path in paths {
+        total += fs::metadata(part_path).await?.len();
+    }
+
+    let mut chunk = Vec::with_capacity(ENCRYPTION_CHUNK_SIZE);
+    let mut index = 0;
+    let mut written = 0;
+    for part_path in paths {
+        let mut part = fs::File::open(part_path).await?;
+        loop {
+            let room = (ENCRYPTION_CHUNK_SIZE - chunk.len()) as u64;
+            if (&mut part).take(room).read_to_end(&mut chunk).await? == 0 {
+                break;
+            }
+            if chunk.len() == ENCRYPTION_CHUNK_SIZE {
+                written += chunk.len() as u64;
+                let sealed = cipher::encrypt_chunk(
+                    &data_key.key,
+                    &chunk,
+                    &data_key.aad,
+                    index,
+                    written == total,
+                )
+                .map_err(map_crypto_error)?;
+                file.write_all(&sealed).await?;
+                chunk.clear();
+                index += 1;
+            }
+        }
     }
+    // The tail, or the single empty chunk of an empty object.
+    if !chunk.is_empty() || index == 0 {
+        written += chunk.len() as u64;
+        let sealed = cipher::encrypt_chunk(&data_key.key, &chunk, &data_key.aad, index, true)
+            .map_err(map_crypto_error)?;
+        file.write_all(&sealed).await?;
+    }
+    Ok(written)
 }
 
-fn validate_bucket_name(bucket: &str) -> Result<()> {
-    if bucket.is_empty()
-        || bucket == SYS_DIR_NAME
-        || bucket == CRYPTO_DIR_NAME
-        || bucket.contains('/')
-        || bucket.contains('\\')
-    {
-        return Err(MaxioError::InvalidBucketName(bucket.to_string()));
+/// Why `data` is not the data `meta` was written with, if it is not. A
+/// multipart object is checked part by part against its manifest.
+fn data_mismatch(meta: &XlMeta, data: &[u8]) -> Option<String> {
+    if i64::try_from(data.len()).ok() != Some(meta.size) {
+        return Some(format!(
+            "data is {} bytes, expected {}",
+            data.len(),
+            meta.size
+        ));
     }
-    Ok(())
+    let etag = if meta.parts.is_empty() {
+        md5_hex(data)
+    } else {
+        let mut composite = CompositeEtag::new();
+        let mut remaining = data;
+        for part in &meta.parts {
+            let size = usize::try_from(part.size).unwrap_or(usize::MAX);
+            if size > remaining.len() {
+                return Some(format!("part {} is truncated", part.part_number));
+            }
+            let (bytes, rest) = remaining.split_at(size);
+            let digest = md5_digest(bytes);
+            if decode_md5_hex(&normalize_etag(&part.etag)).ok() != Some(digest) {
+                return Some(format!("part {} does not match its ETag", part.part_number));
+            }
+            composite.add_part_md5(&digest);
+            remaining = rest;
+        }
+        if !remaining.is_empty() {
+            return Some("data extends past the last part".to_string());
+        }
+        composite.finish()
+    };
+    let expected = normalize_etag(&meta.etag);
+    (etag != expected).then(|| format!("ETag is {etag}, expected {expected}"))
 }
 
-fn meta_encryption_to_object(value: EncryptionInfo) -> ObjectEncryption {
-    ObjectEncryption {
-        algorithm: value.algorithm,
-        sse_type: value.sse_type,
-        key_md5: value.key_md5,
+fn object_size(bucket: &str, key: &str, size: u64) -> Result<i64> {
+    i64::try_from(size).map_err(|_| {
+        MaxioError::InvalidArgument(format!("object is too large to store: {bucket}/{key}"))
+    })
+}
+
+/// The customer key a request supplied for an SSE-C object, once its MD5
+/// matches the one the object was written with.
+fn sse_c_customer_key(
+    encryption_info: &EncryptionInfo,
+    request_encryption: Option<&GetEncryptionOptions>,
+) -> Result<[u8; 32]> {
+    let key_required = || MaxioError::InvalidRequest(SSE_C_KEY_REQUIRED.to_string());
+    let request_encryption = request_encryption.ok_or_else(key_required)?;
+    let customer_key = request_encryption.sse_c_key.ok_or_else(key_required)?;
+    let request_md5 = request_encryption
+        .sse_c_key_md5
+        .as_deref()
+        .ok_or_else(key_required)?;
+    let expected_md5 = encryption_info.key_md5.as_deref().ok_or_else(|| {
+        MaxioError::InternalError("encrypted object metadata missing SSE-C key md5".to_string())
+    })?;
+
+    if request_md5 != expected_md5 {
+        return Err(MaxioError::AccessDenied(
+            "SSE-C customer key MD5 mismatch".to_string(),
+        ));
     }
+    Ok(customer_key)
 }
 
 fn map_crypto_error(err: maxio_crypto::CryptoError) -> MaxioError {
@@ -1517,31 +2291,6 @@ async fn load_or_create_master_key(root_dir: &Path) -> Result<MasterKey> {
     }
 }
 
-fn validate_object_key(key: &str) -> Result<()> {
-    if key.is_empty() || key.contains('\\') {
-        return Err(MaxioError::InvalidObjectName(key.to_string()));
-    }
-
-    let key_path = Path::new(key);
-    if key_path.is_absolute() {
-        return Err(MaxioError::InvalidObjectName(key.to_string()));
-    }
-
-    for component in key_path.components() {
-        match component {
-            Component::Normal(_) => {}
-            Component::CurDir
-            | Component::ParentDir
-            | Component::RootDir
-            | Component::Prefix(_) => {
-                return Err(MaxioError::InvalidObjectName(key.to_string()));
-            }
-        }
-    }
-
-    Ok(())
-}
-
 async fn ensure_bucket_exists(storage: &XlStorage, bucket: &str) -> Result<()> {
     let bucket_path = storage.bucket_path(bucket);
     if !is_existing_directory(&bucket_path).await? {
@@ -1550,6 +2299,20 @@ async fn ensure_bucket_exists(storage: &XlStorage, bucket: &str) -> Result<()> {
     Ok(())
 }
 
+/// Whether `path` is the directory of an object: it holds a versions index
+/// or the metadata of an unversioned object.
+async fn is_object_root(path: &Path) -> bool {
+    for name in [VERSIONS_INDEX_FILE_NAME, META_FILE_NAME] {
+        if fs::metadata(path.join(name))
+            .await
+            .is_ok_and(|meta| meta.is_file())
+        {
+            return true;
+        }
+    }
+    false
+}
+
 async fn is_existing_directory(path: &Path) -> Result<bool> {
     match fs::metadata(path).await {
         Ok(metadata) => Ok(metadata.is_dir()),
@@ -1597,31 +2360,1102 @@ fn validate_part_number(part_number: i32) -> Result<()> {
     }
 }
 
-fn normalize_etag(etag: &str) -> String {
-    let trimmed = etag.trim();
-    if trimmed.starts_with('"') && trimmed.ends_with('"') && trimmed.len() >= 2 {
-        trimmed[1..trimmed.len() - 1].to_string()
+/// Applies S3 prefix/marker/delimiter/max-keys semantics to a flat set of
+/// visible objects, listed in `order`.
+pub(crate) fn paginate_objects(
+    mut objects: Vec<ObjectInfo>,
+    prefix: &str,
+    marker: &str,
+    delimiter: &str,
+    max_keys: i32,
+    order: ListOrder,
+) -> ListObjectsResult {
+    if order == ListOrder::NewestFirst {
+        return paginate_newest_first(objects, prefix, marker, max_keys);
+    }
+    let descending = order == ListOrder::KeyDescending;
+    objects.sort_by(|a, b| a.key.cmp(&b.key));
+    let mut filtered: Vec<ObjectInfo> = objects
+        .into_iter()
+        .filter(|obj| obj.key.starts_with(prefix))
+        .filter(|obj| {
+            marker.is_empty()
+                || if descending {
+                    obj.key.as_str() < marker
+                } else {
+                    obj.key.as_str() > marker
+                }
+        })
+        .collect();
+
+    let mut entries = Vec::new();
+    let mut prefixes = HashSet::new();
+
+    if delimiter.is_empty() {
+        for obj in filtered {
+            entries.push(ListEntry::Object(obj));
+        }
+    } else {
+        for obj in filtered.drain(..) {
+            let suffix = &obj.key[prefix.len()..];
+            if let Some(idx) = suffix.find(delimiter) {
+                let prefix_value = format!("{}{}", prefix, &suffix[..idx + delimiter.len()]);
+                // Keys under a marker that is itself a common prefix sort
+                // after it, but that prefix was already returned.
+                if prefix_value != marker {
+                    prefixes.insert(prefix_value);
+                }
+            } else {
+                entries.push(ListEntry::Object(obj));
+            }
+        }
+
+        for prefix_value in prefixes {
+            entries.push(ListEntry::Prefix(prefix_value));
+        }
+    }
+
+    entries.sort_by(|a, b| a.marker().cmp(b.marker()));
+    if descending {
+        entries.reverse();
+    }
+
+    let limit = if max_keys > 0 {
+        usize::try_from(max_keys).unwrap_or(usize::MAX)
+    } else {
+        entries.len()
+    };
+    let is_truncated = entries.len() > limit;
+    let selected = if is_truncated {
+        &entries[..limit]
     } else {
-        trimmed.to_string()
+        &entries[..]
+    };
+
+    let mut out_objects = Vec::new();
+    let mut out_prefixes = Vec::new();
+    for entry in selected {
+        match entry {
+            ListEntry::Object(obj) => out_objects.push(obj.clone()),
+            ListEntry::Prefix(prefix_value) => out_prefixes.push(prefix_value.clone()),
+        }
+    }
+
+    ListObjectsResult {
+        objects: out_objects,
+        prefixes: out_prefixes,
+        is_truncated,
+        next_marker: selected.last().map(|entry| entry.marker().to_string()),
     }
 }
 
-fn decode_md5_hex(etag: &str) -> Result<[u8; 16]> {
-    if etag.len() != 32 {
-        return Err(MaxioError::InvalidArgument(format!(
-            "invalid part etag format: {etag}"
-        )));
+/// Pages `objects` under `prefix` most recently modified first. The marker
+/// carries the modification time and key of the last object returned, so a
+/// listing resumes in place even once that object is gone; an unreadable
+/// marker starts over.
+fn paginate_newest_first(
+    mut objects: Vec<ObjectInfo>,
+    prefix: &str,
+    marker: &str,
+    max_keys: i32,
+) -> ListObjectsResult {
+    let after = marker.split_once('/').and_then(|(nanos, key)| {
+        let nanos = nanos.parse::<i64>().ok()?;
+        Some((Reverse(DateTime::from_timestamp_nanos(nanos)), key))
+    });
+    objects.retain(|obj| {
+        obj.key.starts_with(prefix)
+            && after.is_none_or(|after| (Reverse(obj.last_modified), obj.key.as_str()) > after)
+    });
+    objects.sort_by(|a, b| {
+        b.last_modified
+            .cmp(&a.last_modified)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+
+    let limit = if max_keys > 0 {
+        usize::try_from(max_keys).unwrap_or(usize::MAX)
+    } else {
+        objects.len()
+    };
+    let is_truncated = objects.len() > limit;
+    objects.truncate(limit);
+    let next_marker = objects.last().map(|obj| {
+        let nanos = obj.last_modified.timestamp_nanos_opt().unwrap_or_default();
+        format!("{nanos}/{}", obj.key)
+    });
+    ListObjectsResult {
+        objects,
+        prefixes: Vec::new(),
+        is_truncated,
+        next_marker,
     }
+}
 
-    let mut out = [0_u8; 16];
-    for idx in 0..16 {
-        let start = idx * 2;
-        let end = start + 2;
-        let byte = u8::from_str_radix(&etag[start..end], 16).map_err(|_| {
-            MaxioError::InvalidArgument(format!("invalid part etag format: {etag}"))
-        })?;
-        out[idx] = byte;
+/// Applies S3 prefix/key-marker/upload-id-marker/delimiter/max-uploads
+/// semantics to a flat set of in-progress uploads.
+pub(crate) fn paginate_uploads(
+    mut uploads: Vec<MultipartUploadInfo>,
+    prefix: &str,
+    key_marker: &str,
+    upload_id_marker: &str,
+    delimiter: &str,
+    max_uploads: i32,
+) -> ListMultipartUploadsResult {
+    uploads.retain(|upload| upload.key.starts_with(prefix));
+    uploads.sort_by(|a, b| a.key.cmp(&b.key).then(a.upload_id.cmp(&b.upload_id)));
+
+    // Sorted by key, so each common prefix first shows up in its own place
+    // in the listing order.
+    let mut entries = Vec::new();
+    let mut seen_prefixes = HashSet::new();
+    for upload in uploads {
+        let common_prefix = (!delimiter.is_empty())
+            .then(|| upload.key[prefix.len()..].find(delimiter))
+            .flatten()
+            .map(|idx| upload.key[..prefix.len() + idx + delimiter.len()].to_string());
+        match common_prefix {
+            Some(common_prefix) => {
+                if seen_prefixes.insert(common_prefix.clone()) {
+                    entries.push(UploadEntry::Prefix(common_prefix));
+                }
+            }
+            None => entries.push(UploadEntry::Upload(upload)),
+        }
+    }
+    entries.retain(|entry| match entry {
+        UploadEntry::Prefix(common_prefix) => common_prefix.as_str() > key_marker,
+        UploadEntry::Upload(upload) => {
+            upload.key.as_str() > key_marker
+                || (!upload_id_marker.is_empty()
+                    && upload.key == key_marker
+                    && upload.upload_id.as_str() > upload_id_marker)
+        }
+    });
+
+    let limit = if max_uploads > 0 {
+        usize::try_from(max_uploads).unwrap_or(usize::MAX)
+    } else {
+        entries.len()
+    };
+    let is_truncated = entries.len() > limit;
+    entries.truncate(limit);
+
+    let (next_key_marker, next_upload_id_marker) = match entries.last() {
+        Some(UploadEntry::Upload(upload)) if is_truncated => {
+            (Some(upload.key.clone()), Some(upload.upload_id.clone()))
+        }
+        Some(UploadEntry::Prefix(common_prefix)) if is_truncated => {
+            (Some(common_prefix.clone()), None)
+        }
+        _ => (None, None),
+    };
+
+    let mut result = ListMultipartUploadsResult {
+        uploads: Vec::new(),
+        prefixes: Vec::new(),
+        is_truncated,
+        next_key_marker,
+        next_upload_id_marker,
+    };
+    for entry in entries {
+        match entry {
+            UploadEntry::Upload(upload) => result.uploads.push(upload),
+            UploadEntry::Prefix(common_prefix) => result.prefixes.push(common_prefix),
+        }
+    }
+    result
+}
+
+/// Applies S3 prefix/key-marker/version-id-marker/delimiter/max-keys
+/// semantics to a flat set of versions. Each key's versions keep their
+/// newest-first order.
+pub(crate) fn paginate_versions(
+    mut versions: Vec<ObjectVersion>,
+    prefix: &str,
+    key_marker: &str,
+    version_id_marker: &str,
+    delimiter: &str,
+    max_keys: i32,
+) -> ListObjectVersionsResult {
+    versions.retain(|version| version.key.starts_with(prefix));
+    versions.sort_by(|a, b| a.key.cmp(&b.key));
+
+    // Versions of the marker key up to the marker version were returned on
+    // an earlier page; the rest of that key is still to come.
+    let resume_at = versions
+        .iter()
+        .position(|version| {
+            !version_id_marker.is_empty()
+                && version.key == key_marker
+                && version.version_id == version_id_marker
+        })
+        .map(|idx| idx + 1);
+
+    let mut entries = Vec::new();
+    let mut seen_prefixes = HashSet::new();
+    for (idx, version) in versions.into_iter().enumerate() {
+        let common_prefix = (!delimiter.is_empty())
+            .then(|| version.key[prefix.len()..].find(delimiter))
+            .flatten()
+            .map(|pos| version.key[..prefix.len() + pos + delimiter.len()].to_string());
+        match common_prefix {
+            Some(common_prefix) => {
+                if common_prefix.as_str() > key_marker
+                    && seen_prefixes.insert(common_prefix.clone())
+                {
+                    entries.push(VersionEntry::Prefix(common_prefix));
+                }
+            }
+            None => {
+                let after_marker = version.key.as_str() > key_marker
+                    || resume_at.is_some_and(|start| version.key == key_marker && idx >= start);
+                if after_marker {
+                    entries.push(VersionEntry::Version(version));
+                }
+            }
+        }
+    }
+
+    let limit = if max_keys > 0 {
+        usize::try_from(max_keys).unwrap_or(usize::MAX)
+    } else {
+        entries.len()
+    };
+    let is_truncated = entries.len() > limit;
+    entries.truncate(limit);
+
+    let (next_key_marker, next_version_id_marker) = match entries.last() {
+        Some(VersionEntry::Version(version)) if is_truncated => {
+            (Some(version.key.clone()), Some(version.version_id.clone()))
+        }
+        Some(VersionEntry::Prefix(common_prefix)) if is_truncated => {
+            (Some(common_prefix.clone()), None)
+        }
+        _ => (None, None),
+    };
+
+    let mut result = ListObjectVersionsResult {
+        versions: Vec::new(),
+        prefixes: Vec::new(),
+        is_truncated,
+        next_key_marker,
+        next_version_id_marker,
+    };
+    for entry in entries {
+        match entry {
+            VersionEntry::Version(version) => result.versions.push(version),
+            VersionEntry::Prefix(common_prefix) => result.prefixes.push(common_prefix),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn test_storage() -> (XlStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("maxio-xl-{}", Uuid::new_v4()));
+        let storage = XlStorage::new(root.clone()).await.expect("create storage");
+        storage.make_bucket("bucket").await.expect("make bucket");
+        (storage, root)
+    }
+
+    async fn upload_in_parts(storage: &XlStorage, key: &str, parts: &[&[u8]]) -> ObjectInfo {
+        let upload_id = storage
+            .create_multipart_upload("bucket", key, None, HashMap::new())
+            .await
+            .expect("create upload");
+        let mut completed = Vec::new();
+        for (part_number, data) in (1..).zip(parts) {
+            let etag = storage
+                .upload_part(
+                    "bucket",
+                    key,
+                    &upload_id,
+                    part_number,
+                    Bytes::copy_from_slice(data),
+                )
+                .await
+                .expect("upload part");
+            completed.push(CompletePart { part_number, etag });
+        }
+        storage
+            .complete_multipart_upload("bucket", key, &upload_id, completed, None)
+            .await
+            .expect("complete upload")
+    }
+
+    #[tokio::test]
+    async fn multipart_objects_record_their_parts() {
+        let (storage, root) = test_storage().await;
+        upload_in_parts(&storage, "multi", &[b"first part", b"second", b"third!!"]).await;
+
+        let parts = storage
+            .stat_object_parts("bucket", "multi")
+            .await
+            .expect("stat parts");
+        let sizes = parts.iter().map(|part| part.size).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![10, 6, 7]);
+        assert_eq!(parts[1].part_number, 2);
+        assert_eq!(parts[1].etag, md5_hex(b"second"));
+
+        storage
+            .put_object(
+                "bucket",
+                "single",
+                Bytes::from_static(b"one shot"),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put object");
+        assert!(
+            storage
+                .stat_object_parts("bucket", "single")
+                .await
+                .expect("stat parts")
+                .is_empty()
+        );
+
+        storage
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .expect("enable versioning");
+        upload_in_parts(&storage, "multi", &[b"a", b"bc"]).await;
+        let parts = storage
+            .stat_object_parts("bucket", "multi")
+            .await
+            .expect("stat parts");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].size, 2);
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn dropped_multipart_completion_leaves_no_staged_data() {
+        let (storage, root) = test_storage().await;
+        let upload_id = storage
+            .create_multipart_upload("bucket", "big", None, HashMap::new())
+            .await
+            .expect("create upload");
+        let mut parts = Vec::new();
+        for part_number in 1..=32 {
+            let etag = storage
+                .upload_part(
+                    "bucket",
+                    "big",
+                    &upload_id,
+                    part_number,
+                    Bytes::from(vec![0xa5; 1024 * 1024]),
+                )
+                .await
+                .expect("upload part");
+            parts.push(CompletePart { part_number, etag });
+        }
+
+        // Drive the completion until it starts staging data, then drop it as
+        // a disconnected client's request would be.
+        let object_path = storage.object_path("bucket", "big");
+        let mut completion =
+            Box::pin(storage.complete_multipart_upload("bucket", "big", &upload_id, parts, None));
+        loop {
+            tokio::select! {
+                biased;
+                _ = &mut completion => panic!("completion finished before it was dropped"),
+                _ = tokio::time::sleep(Duration::from_millis(1)) => {}
+            }
+            if object_path.exists() {
+                break;
+            }
+        }
+        drop(completion);
+
+        // The staged data is removed in the background.
+        for _ in 0..500 {
+            if !object_path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!object_path.exists(), "staged object data was left behind");
+        assert!(matches!(
+            storage.get_object_info("bucket", "big", None).await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+        let listed = storage
+            .list_parts("bucket", "big", &upload_id)
+            .await
+            .expect("upload survives");
+        assert_eq!(listed.len(), 32);
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn encrypted_objects_report_the_plaintext_etag() {
+        let (storage, root) = test_storage().await;
+        let body = b"plaintext body";
+        let customer_key = [7u8; 32];
+        let customer_key_md5 = "customer-key-md5".to_string();
+
+        for (key, put_encryption, get_encryption) in [
+            (
+                "sse-s3",
+                PutEncryptionOptions {
+                    sse_s3: true,
+                    sse_c_key: None,
+                    sse_c_key_md5: None,
+                },
+                None,
+            ),
+            (
+                "sse-c",
+                PutEncryptionOptions {
+                    sse_s3: false,
+                    sse_c_key: Some(customer_key),
+                    sse_c_key_md5: Some(customer_key_md5.clone()),
+                },
+                Some(GetEncryptionOptions {
+                    sse_c_key: Some(customer_key),
+                    sse_c_key_md5: Some(customer_key_md5.clone()),
+                }),
+            ),
+        ] {
+            let put = storage
+                .put_object(
+                    "bucket",
+                    key,
+                    Bytes::from_static(body),
+                    None,
+                    HashMap::new(),
+                    Some(put_encryption),
+                )
+                .await
+                .unwrap();
+            assert_eq!(put.etag, md5_hex(body), "{key}");
+
+            let meta = storage
+                .read_xl_meta_if_exists(&storage.object_path("bucket", key).join(META_FILE_NAME))
+                .await
+                .unwrap()
+                .unwrap();
+            let stored = fs::read(
+                storage
+                    .object_path("bucket", key)
+                    .join(&meta.data_dir)
+                    .join(DATA_PART_FILE_NAME),
+            )
+            .await
+            .unwrap();
+            assert_ne!(md5_hex(&stored), put.etag, "{key} data is not encrypted");
+
+            let info = storage
+                .get_object_info("bucket", key, get_encryption.clone())
+                .await
+                .unwrap();
+            assert_eq!(info.etag, put.etag, "{key}");
+            let (info, data) = storage
+                .get_object("bucket", key, get_encryption)
+                .await
+                .unwrap();
+            assert_eq!(info.etag, put.etag, "{key}");
+            assert_eq!(data.as_ref(), body);
+        }
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn encrypted_multipart_objects_are_sealed_in_chunks() {
+        let (storage, root) = test_storage().await;
+        let first = (0..ENCRYPTION_CHUNK_SIZE * 3 / 2)
+            .map(|idx| idx as u8)
+            .collect::<Vec<_>>();
+        let second = vec![9_u8; ENCRYPTION_CHUNK_SIZE + 3];
+        let upload_id = storage
+            .create_multipart_upload("bucket", "sealed", None, HashMap::new())
+            .await
+            .unwrap();
+        let mut completed = Vec::new();
+        for (part_number, data) in (1..).zip([&first, &second]) {
+            let etag = storage
+                .upload_part(
+                    "bucket",
+                    "sealed",
+                    &upload_id,
+                    part_number,
+                    Bytes::copy_from_slice(data),
+                )
+                .await
+                .unwrap();
+            completed.push(CompletePart { part_number, etag });
+        }
+        let encryption = PutEncryptionOptions {
+            sse_s3: true,
+            sse_c_key: None,
+            sse_c_key_md5: None,
+        };
+        storage
+            .complete_multipart_upload("bucket", "sealed", &upload_id, completed, Some(encryption))
+            .await
+            .unwrap();
+
+        let meta = storage
+            .read_xl_meta_if_exists(&storage.object_path("bucket", "sealed").join(META_FILE_NAME))
+            .await
+            .unwrap()
+            .unwrap();
+        let encryption = meta.encryption.as_ref().unwrap();
+        assert_eq!(encryption.chunk_size, Some(ENCRYPTION_CHUNK_SIZE as u64));
+        let data_path = storage
+            .object_path("bucket", "sealed")
+            .join(&meta.data_dir)
+            .join(DATA_PART_FILE_NAME);
+        let stored = fs::read(&data_path).await.unwrap();
+        let plain_len = first.len() + second.len();
+        assert_eq!(stored.len(), plain_len + 3 * cipher::SEALED_OVERHEAD);
+
+        let (_, data) = storage.get_object("bucket", "sealed", None).await.unwrap();
+        assert_eq!(&data[..first.len()], first.as_slice());
+        assert_eq!(&data[first.len()..], second.as_slice());
+
+        // Cutting the object at a chunk boundary does not go unnoticed.
+        let truncated = stored.len() - (3 + cipher::SEALED_OVERHEAD);
+        fs::write(&data_path, &stored[..truncated]).await.unwrap();
+        assert!(storage.get_object("bucket", "sealed", None).await.is_err());
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn encrypted_data_does_not_decrypt_under_another_key() {
+        let (storage, root) = test_storage().await;
+        let customer_key = [9u8; 32];
+        let put_encryption = || PutEncryptionOptions {
+            sse_s3: false,
+            sse_c_key: Some(customer_key),
+            sse_c_key_md5: Some("customer-key-md5".to_string()),
+        };
+        let get_encryption = || GetEncryptionOptions {
+            sse_c_key: Some(customer_key),
+            sse_c_key_md5: Some("customer-key-md5".to_string()),
+        };
+        for (key, body) in [("payroll", "secret figures"), ("notes", "harmless")] {
+            storage
+                .put_object(
+                    "bucket",
+                    key,
+                    Bytes::from(body),
+                    None,
+                    HashMap::new(),
+                    Some(put_encryption()),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Both objects share the customer key, but moving one's ciphertext
+        // into the other must not make it readable there.
+        let data_file = |meta: &XlMeta, object_path: &Path| {
+            object_path.join(&meta.data_dir).join(DATA_PART_FILE_NAME)
+        };
+        let (_, source_meta, source_path) = storage.read_object("bucket", "payroll").await.unwrap();
+        let (_, target_meta, target_path) = storage.read_object("bucket", "notes").await.unwrap();
+        fs::copy(
+            data_file(&source_meta, &source_path),
+            data_file(&target_meta, &target_path),
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            storage
+                .get_object("bucket", "notes", Some(get_encryption()))
+                .await
+                .is_err()
+        );
+        let (_, data) = storage
+            .get_object("bucket", "payroll", Some(get_encryption()))
+            .await
+            .unwrap();
+        assert_eq!(data.as_ref(), b"secret figures");
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn delimiters_group_keys_after_the_prefix() {
+        let (storage, root) = test_storage().await;
+        for key in [
+            "photos_2024_jan",
+            "photos_2024_feb",
+            "photos_2023_dec",
+            "videos_a",
+            "readme",
+            "a--b--c",
+            "a--d",
+            "a-e",
+        ] {
+            storage
+                .put_object("bucket", key, Bytes::new(), None, HashMap::new(), None)
+                .await
+                .unwrap();
+        }
+        let list = |prefix: &'static str, marker: &'static str, delimiter: &'static str| {
+            let storage = &storage;
+            async move {
+                let result = storage
+                    .list_objects(
+                        "bucket",
+                        prefix,
+                        marker,
+                        delimiter,
+                        4,
+                        ListOrder::KeyAscending,
+                    )
+                    .await
+                    .unwrap();
+                let keys = result
+                    .objects
+                    .into_iter()
+                    .map(|object| object.key)
+                    .collect::<Vec<_>>();
+                (keys, result.prefixes, result.next_marker)
+            }
+        };
+
+        let (keys, prefixes, next_marker) = list("", "", "_").await;
+        assert_eq!(keys, ["a--b--c", "a--d", "a-e"]);
+        assert_eq!(prefixes, ["photos_"]);
+        assert_eq!(next_marker.as_deref(), Some("photos_"));
+        // The next page does not repeat the common prefix it ended on.
+        let (keys, prefixes, _) = list("", "photos_", "_").await;
+        assert_eq!(keys, ["readme"]);
+        assert_eq!(prefixes, ["videos_"]);
+
+        // Only the part of the key after the prefix is searched, so a
+        // delimiter inside the prefix does not end the group early.
+        let (keys, prefixes, _) = list("photos_", "", "_").await;
+        assert!(keys.is_empty());
+        assert_eq!(prefixes, ["photos_2023_", "photos_2024_"]);
+        let (keys, prefixes, _) = list("photos_2024_", "", "_").await;
+        assert_eq!(keys, ["photos_2024_feb", "photos_2024_jan"]);
+        assert!(prefixes.is_empty());
+
+        let (keys, prefixes, _) = list("", "", "--").await;
+        assert_eq!(keys, ["a-e", "photos_2023_dec", "photos_2024_feb"]);
+        assert_eq!(prefixes, ["a--"]);
+        // A delimiter straddling the end of the prefix does not count.
+        let (keys, prefixes, _) = list("a-", "", "--").await;
+        assert_eq!(keys, ["a--d", "a-e"]);
+        assert_eq!(prefixes, ["a--b--"]);
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn suspended_delete_replaces_only_the_null_version() {
+        let (storage, root) = test_storage().await;
+        let put = |body: &'static [u8]| {
+            storage.put_object(
+                "bucket",
+                "doc",
+                Bytes::from_static(body),
+                None,
+                HashMap::new(),
+                None,
+            )
+        };
+
+        storage
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .unwrap();
+        let first = put(b"first").await.unwrap().version_id.unwrap();
+        let second = put(b"second").await.unwrap().version_id.unwrap();
+
+        storage
+            .set_bucket_versioning("bucket", VersioningState::Suspended)
+            .await
+            .unwrap();
+        let null = put(b"null").await.unwrap();
+        assert_eq!(null.version_id.as_deref(), Some(NULL_VERSION_ID));
+
+        storage.delete_object("bucket", "doc").await.unwrap();
+        assert!(matches!(
+            storage.get_object("bucket", "doc", None).await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+        for (version_id, body) in [(&first, b"first".as_slice()), (&second, b"second")] {
+            let (_, data) = storage
+                .get_object_version("bucket", "doc", version_id, None)
+                .await
+                .unwrap();
+            assert_eq!(data.as_ref(), body);
+        }
+
+        let versions = storage
+            .list_object_versions("bucket", "doc", "", "", "", 1000)
+            .await
+            .unwrap()
+            .versions;
+        let listed = versions
+            .iter()
+            .map(|version| {
+                (
+                    version.version_id.as_str(),
+                    version.is_delete_marker,
+                    version.is_latest,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listed,
+            vec![
+                (NULL_VERSION_ID, true, true),
+                (second.as_str(), false, false),
+                (first.as_str(), false, false),
+            ]
+        );
+
+        // A second delete overwrites the null marker instead of stacking.
+        storage.delete_object("bucket", "doc").await.unwrap();
+        let versions = storage
+            .list_object_versions("bucket", "doc", "", "", "", 1000)
+            .await
+            .unwrap()
+            .versions;
+        assert_eq!(versions.len(), 3);
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn renamed_prefix_keeps_versions_and_metadata() {
+        let (storage, root) = test_storage().await;
+        let put = |key: &'static str, body: &'static [u8], metadata: HashMap<String, String>| {
+            storage.put_object(
+                "bucket",
+                key,
+                Bytes::from_static(body),
+                Some("text/plain"),
+                metadata,
+                None,
+            )
+        };
+
+        storage
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .unwrap();
+        let first = put("logs/a.txt", b"first", HashMap::new())
+            .await
+            .unwrap()
+            .version_id
+            .unwrap();
+        put("logs/a.txt", b"second", HashMap::new()).await.unwrap();
+        let metadata = HashMap::from([("team".to_string(), "ops".to_string())]);
+        put("logs/deep/nested/b.txt", b"nested", metadata.clone())
+            .await
+            .unwrap();
+        put("logs-other", b"other", HashMap::new()).await.unwrap();
+
+        storage
+            .rename_prefix("bucket", "logs/", "archive/2024/")
+            .await
+            .unwrap();
+
+        let (_, data) = storage
+            .get_object("bucket", "archive/2024/a.txt", None)
+            .await
+            .unwrap();
+        assert_eq!(data.as_ref(), b"second");
+        let (_, data) = storage
+            .get_object_version("bucket", "archive/2024/a.txt", &first, None)
+            .await
+            .unwrap();
+        assert_eq!(data.as_ref(), b"first");
+        let nested = storage
+            .get_object_info("bucket", "archive/2024/deep/nested/b.txt", None)
+            .await
+            .unwrap();
+        assert_eq!(nested.metadata, metadata);
+        assert_eq!(nested.content_type, "text/plain");
+
+        assert!(
+            storage
+                .list_objects("bucket", "logs/", "", "", 1000, ListOrder::KeyAscending)
+                .await
+                .unwrap()
+                .objects
+                .is_empty()
+        );
+        assert!(matches!(
+            storage.get_object("bucket", "logs/a.txt", None).await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+        assert!(!root.join("bucket").join("logs").exists());
+        storage
+            .get_object("bucket", "logs-other", None)
+            .await
+            .unwrap();
+
+        put("logs/new.txt", b"new", HashMap::new()).await.unwrap();
+        assert!(matches!(
+            storage
+                .rename_prefix("bucket", "logs/", "archive/2024/")
+                .await,
+            Err(MaxioError::PreconditionFailed(_))
+        ));
+        assert!(matches!(
+            storage
+                .rename_prefix("bucket", "missing/", "elsewhere/")
+                .await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+        assert!(matches!(
+            storage
+                .rename_prefix("bucket", "logs/", "logs-other/inner/")
+                .await,
+            Err(MaxioError::InvalidArgument(_))
+        ));
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn copies_share_the_source_data_file() {
+        use std::os::unix::fs::MetadataExt;
+
+        let (storage, root) = test_storage().await;
+        let data = Bytes::from(vec![7_u8; 4 << 20]);
+        let source_info = storage
+            .put_object(
+                "bucket",
+                "source",
+                data.clone(),
+                Some("text/plain"),
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("put source");
+        let source = CopySource {
+            bucket: "bucket".to_string(),
+            key: "source".to_string(),
+            version_id: None,
+            encryption: None,
+        };
+
+        let refused = storage
+            .copy_object(
+                &source,
+                "bucket",
+                "refused",
+                &|_| Err(MaxioError::PreconditionFailed("refused".to_string())),
+                None,
+            )
+            .await;
+        assert!(matches!(refused, Err(MaxioError::PreconditionFailed(_))));
+        assert!(!root.join("bucket").join("refused").exists());
+
+        let (copied_from, info) = storage
+            .copy_object(
+                &source,
+                "bucket",
+                "copy",
+                &|source| {
+                    Ok((
+                        Some(source.content_type.clone()),
+                        HashMap::from([("x-amz-meta-copy".to_string(), "yes".to_string())]),
+                    ))
+                },
+                None,
+            )
+            .await
+            .expect("copy object");
+        assert_eq!(copied_from.etag, source_info.etag);
+        assert_eq!(info.etag, source_info.etag);
+
+        // The copy's data file is the source's, reached through a second link.
+        let (_, meta, dir) = storage
+            .latest_object_meta("bucket", "copy")
+            .await
+            .expect("copy meta");
+        let data_file = fs::metadata(dir.join(&meta.data_dir).join(DATA_PART_FILE_NAME))
+            .await
+            .expect("stat copy data");
+        assert_eq!(data_file.nlink(), 2);
+
+        let (copy_info, copy) = storage
+            .get_object("bucket", "copy", None)
+            .await
+            .expect("read copy");
+        assert_eq!(copy, data);
+        assert_eq!(copy_info.content_type, "text/plain");
+        assert_eq!(copy_info.metadata["x-amz-meta-copy"], "yes");
+
+        // Replacing the source leaves the copy's data alone.
+        storage
+            .put_object(
+                "bucket",
+                "source",
+                Bytes::from_static(b"replaced"),
+                None,
+                HashMap::new(),
+                None,
+            )
+            .await
+            .expect("replace source");
+        let (_, copy) = storage
+            .get_object("bucket", "copy", None)
+            .await
+            .expect("read copy after overwrite");
+        assert_eq!(copy, data);
+
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[test]
+    fn listings_page_in_the_requested_order() {
+        let object = |key: &str, age_secs: i64| ObjectInfo {
+            bucket: "bucket".to_string(),
+            key: key.to_string(),
+            size: 0,
+            etag: String::new(),
+            content_type: String::new(),
+            last_modified: DateTime::from_timestamp(1_700_000_000 - age_secs, 0).unwrap(),
+            metadata: HashMap::new(),
+            version_id: None,
+            encryption: None,
+            storage_class: None,
+        };
+        let objects = vec![
+            object("logs/b", 30),
+            object("logs/a", 10),
+            object("logs/d/x", 0),
+            object("logs/c", 20),
+            object("other", 0),
+        ];
+        let page = |marker: &str, delimiter: &str, order: ListOrder| {
+            let result = paginate_objects(objects.clone(), "logs/", marker, delimiter, 2, order);
+            let keys = result
+                .objects
+                .iter()
+                .map(|obj| obj.key.as_str())
+                .chain(result.prefixes.iter().map(String::as_str))
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>();
+            (keys, result.next_marker.unwrap_or_default())
+        };
+
+        let (keys, next) = page("", "/", ListOrder::default());
+        assert_eq!(keys, ["logs/a", "logs/b"]);
+        let (keys, _) = page(&next, "/", ListOrder::default());
+        assert_eq!(keys, ["logs/c", "logs/d/"]);
+
+        let (keys, next) = page("", "/", ListOrder::KeyDescending);
+        assert_eq!(keys, ["logs/c", "logs/d/"]);
+        let (keys, next) = page(&next, "/", ListOrder::KeyDescending);
+        assert_eq!(keys, ["logs/b", "logs/a"]);
+        let (keys, _) = page(&next, "/", ListOrder::KeyDescending);
+        assert!(keys.is_empty());
+
+        let (keys, next) = page("", "/", ListOrder::NewestFirst);
+        assert_eq!(keys, ["logs/d/x", "logs/a"]);
+        let (keys, _) = page(&next, "/", ListOrder::NewestFirst);
+        assert_eq!(keys, ["logs/c", "logs/b"]);
     }
 
-    Ok(out)
+    #[tokio::test]
+    async fn version_info_is_read_from_metadata_alone() {
+        let (storage, root) = test_storage().await;
+        storage
+            .set_bucket_versioning("bucket", VersioningState::Enabled)
+            .await
+            .expect("enable versioning");
+        let mut versions = Vec::new();
+        for body in [&b"first version"[..], b"second"] {
+            let info = storage
+                .put_object(
+                    "bucket",
+                    "key",
+                    Bytes::copy_from_slice(body),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+            versions.push(info);
+        }
+        let first_id = versions[0].version_id.clone().expect("version id");
+
+        // With the data gone, only a metadata read can still answer.
+        let (_, meta, object_path) = storage
+            .read_object_version_meta("bucket", "key", &first_id)
+            .await
+            .expect("read version meta");
+        fs::remove_file(object_path.join(meta.data_dir).join(DATA_PART_FILE_NAME))
+            .await
+            .expect("remove data");
+        assert!(
+            storage
+                .get_object_version("bucket", "key", &first_id, None)
+                .await
+                .is_err()
+        );
+
+        let info = storage
+            .get_object_version_info("bucket", "key", &first_id, None)
+            .await
+            .expect("version info");
+        assert_eq!(info.size, 13);
+        assert_eq!(info.etag, versions[0].etag);
+        assert_eq!(info.version_id.as_deref(), Some(first_id.as_str()));
+        assert!(matches!(
+            storage
+                .get_object_version_info("bucket", "key", "missing", None)
+                .await,
+            Err(MaxioError::ObjectNotFound { .. })
+        ));
+
+        // The latest version's info is read the same way, also in an
+        // unversioned bucket.
+        storage.make_bucket("plain").await.expect("create bucket");
+        for bucket in ["bucket", "plain"] {
+            let put = storage
+                .put_object(
+                    bucket,
+                    "latest",
+                    Bytes::from_static(b"latest"),
+                    None,
+                    HashMap::new(),
+                    None,
+                )
+                .await
+                .expect("put object");
+            let (_, meta, object_path) = storage
+                .latest_object_meta(bucket, "latest")
+                .await
+                .expect("read latest meta");
+            fs::remove_file(object_path.join(meta.data_dir).join(DATA_PART_FILE_NAME))
+                .await
+                .expect("remove data");
+            let info = storage
+                .get_object_info(bucket, "latest", None)
+                .await
+                .expect("latest info");
+            assert_eq!(info.etag, put.etag, "{bucket}");
+        }
+
+        let _ = fs::remove_dir_all(root).await;
+    }
 }
@@ -0,0 +1,115 @@
+//! Lives in its own test binary because it installs a counting global
+//! allocator; other tests running alongside would skew the peak.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::Bytes;
+use maxio_storage::erasure::ErasureConfig;
+use maxio_storage::erasure::objects::ErasureSet;
+use maxio_storage::traits::{CompletePart, ObjectLayer};
+use md5::{Digest, Md5};
+
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const PART_SIZE: usize = 4 * 1024 * 1024;
+const PART_COUNT: usize = 12;
+
+#[tokio::test]
+async fn erasure_complete_multipart_streams_parts_into_the_encoder() {
+    let root =
+        std::env::temp_dir().join(format!("maxio-erasure-multipart-{}", uuid::Uuid::new_v4()));
+    let config = ErasureConfig::default();
+    let disks = (0..config.total_shards())
+        .map(|index| root.join(format!("disk{index}")))
+        .collect::<Vec<_>>();
+    let layer = ErasureSet::new(disks, config).await.unwrap();
+    layer.make_bucket("bucket").await.unwrap();
+    let upload_id = layer
+        .create_multipart_upload("bucket", "big", None, HashMap::new())
+        .await
+        .unwrap();
+
+    let mut parts = Vec::new();
+    let mut etag_material = Vec::new();
+    for number in 1..=PART_COUNT {
+        let data = vec![number as u8; PART_SIZE];
+        etag_material.extend_from_slice(&Md5::digest(&data));
+        let etag = layer
+            .upload_part(
+                "bucket",
+                "big",
+                &upload_id,
+                number as i32,
+                Bytes::from(data),
+            )
+            .await
+            .unwrap();
+        parts.push(CompletePart {
+            part_number: number as i32,
+            etag,
+        });
+    }
+
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let info = layer
+        .complete_multipart_upload("bucket", "big", &upload_id, parts)
+        .await
+        .unwrap();
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+    assert_eq!(
+        info.etag,
+        format!("{:x}-{PART_COUNT}", Md5::digest(&etag_material))
+    );
+    assert_eq!(info.size, (PART_SIZE * PART_COUNT) as i64);
+    // Encoding holds a block and its shards at a time, whatever the size of
+    // the object.
+    assert!(
+        peak < PART_SIZE * PART_COUNT / 2,
+        "completing used {peak} bytes for a {} byte object",
+        PART_SIZE * PART_COUNT
+    );
+
+    let (_, data) = layer.get_object("bucket", "big", None).await.unwrap();
+    assert_eq!(data.len(), PART_SIZE * PART_COUNT);
+    for (index, chunk) in data.chunks(PART_SIZE).enumerate() {
+        assert!(chunk.iter().all(|byte| *byte == index as u8 + 1));
+    }
+    let stored_parts = layer.stat_object_parts("bucket", "big").await.unwrap();
+    assert_eq!(stored_parts.len(), PART_COUNT);
+    assert!(
+        layer
+            .list_multipart_uploads("bucket", "", "", "", "", 0)
+            .await
+            .unwrap()
+            .uploads
+            .is_empty()
+    );
+
+    let _ = std::fs::remove_dir_all(root);
+}